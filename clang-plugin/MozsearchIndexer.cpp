@@ -30,6 +30,7 @@
 #include <iostream>
 #include <map>
 #include <memory>
+#include <set>
 #include <sstream>
 #include <stack>
 #include <string>
@@ -1415,9 +1416,14 @@ public:
         J.objectEnd();
         J.attributeEnd();
       } else {
-        // Try and get the field as a record itself so we can know its size, but
-        // we don't actually want to recurse into it.
-        if (auto FieldRec = Field.getType()->getAs<RecordType>()) {
+        // `[[no_unique_address]]` members of empty types take up no space even
+        // though the size of their type is 1, and they may share their offset
+        // with another field.
+        if (Field.isZeroSize(C)) {
+          J.attribute("sizeBytes", 0);
+        } else if (auto FieldRec = Field.getType()->getAs<RecordType>()) {
+          // Try and get the field as a record itself so we can know its size,
+          // but we don't actually want to recurse into it.
           auto const &FieldLayout = C.getASTRecordLayout(FieldRec->getDecl());
           J.attribute("sizeBytes", FieldLayout.getSize().getQuantity());
         } else {
@@ -1591,6 +1597,209 @@ public:
     }
 
     emitBindingAttributes(J, *decl);
+    emitStaticInitInfo(J, decl);
+  }
+
+  // Collect the symbols referenced by an initializer expression, ex: the
+  // constructors and functions it calls and the globals it reads.
+  void collectInitializerSymbols(const Stmt *S, std::set<std::string> &Syms) {
+    if (!S) {
+      return;
+    }
+    const NamedDecl *Referenced = nullptr;
+    if (const auto *DRE = dyn_cast<DeclRefExpr>(S)) {
+      Referenced = DRE->getDecl();
+    } else if (const auto *ME = dyn_cast<MemberExpr>(S)) {
+      Referenced = ME->getMemberDecl();
+    } else if (const auto *CE = dyn_cast<CXXConstructExpr>(S)) {
+      Referenced = CE->getConstructor();
+    }
+    if (Referenced) {
+      // Locals and parameters (ex: of a lambda) aren't interesting.
+      const auto *VD = dyn_cast<VarDecl>(Referenced);
+      if (!VD || VD->hasGlobalStorage()) {
+        Syms.insert(getMangledName(CurMangleContext, Referenced));
+      }
+    }
+    for (const Stmt *Child : S->children()) {
+      collectInitializerSymbols(Child, Syms);
+    }
+  }
+
+  // Emit what runs for a variable with static or thread storage duration at
+  // startup, first use, or shutdown, ex: a global constructor, so that
+  // crossref can index these for `static-inits`.  Variables with constant
+  // initialization and trivial destruction are skipped.
+  void emitStaticInitInfo(llvm::json::OStream &J, const VarDecl *decl) {
+    if (!decl->hasGlobalStorage() ||
+        decl->isThisDeclarationADefinition() != VarDecl::Definition ||
+        decl->getDeclContext()->isDependentContext()) {
+      return;
+    }
+    const Expr *Init = decl->getInit();
+    bool DynamicInit = Init && !Init->isValueDependent() &&
+                       !decl->hasConstantInitialization();
+    bool Destructor = decl->needsDestruction(*AstContext) != QualType::DK_none;
+    if (!DynamicInit && !Destructor) {
+      return;
+    }
+
+    std::set<std::string> Syms;
+    if (DynamicInit) {
+      collectInitializerSymbols(Init, Syms);
+    }
+    if (Destructor) {
+      const Type *T = decl->getType()->getBaseElementTypeUnsafe();
+      if (const CXXRecordDecl *RD = T->getAsCXXRecordDecl()) {
+        if (const CXXDestructorDecl *Dtor = RD->getDestructor()) {
+          Syms.insert(getMangledName(CurMangleContext, Dtor));
+        }
+      }
+    }
+
+    J.attributeBegin("staticInit");
+    J.objectBegin();
+    if (decl->getTLSKind() != VarDecl::TLS_None) {
+      J.attribute("kind", "threadLocal");
+    } else if (decl->isStaticLocal()) {
+      J.attribute("kind", "staticLocal");
+    } else {
+      J.attribute("kind", "global");
+    }
+    J.attribute("dynamicInit", DynamicInit);
+    J.attribute("destructor", Destructor);
+    J.attributeBegin("syms");
+    J.arrayBegin();
+    for (const auto &Sym : Syms) {
+      J.value(Sym);
+    }
+    J.arrayEnd();
+    J.attributeEnd();
+    J.objectEnd();
+    J.attributeEnd();
+  }
+
+  // Emit the attribute-like annotations of the decl, ex: MOZ_CAN_RUN_SCRIPT
+  // (an `annotate("moz_can_run_script")` attribute) or `[[deprecated]]`, so
+  // that crossref can index which symbols bear each attribute.  The binding
+  // annotations we add ourselves in BindingOperations.cpp are skipped.
+  void emitStructuredAttributes(llvm::json::OStream &J, const NamedDecl *decl) {
+    std::set<std::string> Attrs;
+    for (const auto *Attr : decl->specific_attrs<AnnotateAttr>()) {
+      StringRef Annotation = Attr->getAnnotation();
+      if (Annotation == "binding_to" || Annotation == "bound_as") {
+        continue;
+      }
+      Attrs.insert(Annotation.str());
+    }
+    if (decl->hasAttr<DeprecatedAttr>()) {
+      Attrs.insert("deprecated");
+    }
+    if (decl->hasAttr<WarnUnusedResultAttr>()) {
+      Attrs.insert("nodiscard");
+    }
+    // The thread safety attributes with arguments are emitted by
+    // emitLockAnnotations.
+    if (decl->hasAttr<CapabilityAttr>()) {
+      Attrs.insert("capability");
+    }
+    if (decl->hasAttr<ScopedLockableAttr>()) {
+      Attrs.insert("scoped_capability");
+    }
+    if (decl->hasAttr<NoThreadSafetyAnalysisAttr>()) {
+      Attrs.insert("no_thread_safety_analysis");
+    }
+    if (Attrs.empty()) {
+      return;
+    }
+
+    J.attributeBegin("attributes");
+    J.arrayBegin();
+    for (const auto &Name : Attrs) {
+      J.value(Name);
+    }
+    J.arrayEnd();
+    J.attributeEnd();
+  }
+
+  // Emit one thread safety annotation, identifying the capability by the
+  // symbol it refers to, ex: the `mMutex` field in `MOZ_GUARDED_BY(mMutex)`.
+  // A null Arg means the annotated object itself, as in a `MOZ_CAPABILITY`
+  // class's own Lock method.
+  void emitLockAnnotation(llvm::json::OStream &J, const char *Kind,
+                          const Expr *Arg) {
+    J.objectBegin();
+    J.attribute("kind", Kind);
+    const Expr *E = Arg ? Arg->IgnoreParenImpCasts() : nullptr;
+    const NamedDecl *Capability = nullptr;
+    if (const auto *ME = dyn_cast_or_null<MemberExpr>(E)) {
+      Capability = ME->getMemberDecl();
+    } else if (const auto *DRE = dyn_cast_or_null<DeclRefExpr>(E)) {
+      Capability = DRE->getDecl();
+    }
+    if (Capability) {
+      J.attribute("sym", getMangledName(CurMangleContext, Capability));
+      J.attribute("pretty", getQualifiedName(Capability));
+    } else if (E) {
+      std::string Text;
+      llvm::raw_string_ostream OS(Text);
+      E->printPretty(OS, nullptr, PrintingPolicy(CI.getLangOpts()));
+      J.attribute("pretty", OS.str());
+    } else {
+      J.attribute("pretty", "this");
+    }
+    J.objectEnd();
+  }
+
+  // Emit the thread safety annotations of the decl, ex: MOZ_GUARDED_BY on
+  // fields and MOZ_REQUIRES on methods, so that `lock-report` can check
+  // accessors of guarded fields against them.
+  void emitLockAnnotations(llvm::json::OStream &J, const NamedDecl *decl) {
+    std::vector<std::pair<const char *, const Expr *>> Locks;
+    for (const auto *A : decl->specific_attrs<GuardedByAttr>()) {
+      Locks.emplace_back("guarded_by", A->getArg());
+    }
+    for (const auto *A : decl->specific_attrs<PtGuardedByAttr>()) {
+      Locks.emplace_back("pt_guarded_by", A->getArg());
+    }
+    for (const auto *A : decl->specific_attrs<RequiresCapabilityAttr>()) {
+      const char *Kind = A->isShared() ? "requires_shared" : "requires";
+      for (const Expr *Arg : A->args()) {
+        Locks.emplace_back(Kind, Arg);
+      }
+    }
+    for (const auto *A : decl->specific_attrs<LocksExcludedAttr>()) {
+      for (const Expr *Arg : A->args()) {
+        Locks.emplace_back("excludes", Arg);
+      }
+    }
+    for (const auto *A : decl->specific_attrs<AcquireCapabilityAttr>()) {
+      if (A->args_size() == 0) {
+        Locks.emplace_back("acquires", nullptr);
+      }
+      for (const Expr *Arg : A->args()) {
+        Locks.emplace_back("acquires", Arg);
+      }
+    }
+    for (const auto *A : decl->specific_attrs<ReleaseCapabilityAttr>()) {
+      if (A->args_size() == 0) {
+        Locks.emplace_back("releases", nullptr);
+      }
+      for (const Expr *Arg : A->args()) {
+        Locks.emplace_back("releases", Arg);
+      }
+    }
+    if (Locks.empty()) {
+      return;
+    }
+
+    J.attributeBegin("locks");
+    J.arrayBegin();
+    for (const auto &Lock : Locks) {
+      emitLockAnnotation(J, Lock.first, Lock.second);
+    }
+    J.arrayEnd();
+    J.attributeEnd();
   }
 
   void emitStructuredInfo(SourceLocation Loc, const NamedDecl *decl) {
@@ -1622,6 +1831,9 @@ public:
       emitStructuredVarInfo(J, VD);
     }
 
+    emitStructuredAttributes(J, decl);
+    emitLockAnnotations(J, decl);
+
     // End the top-level object.
     J.objectEnd();
 
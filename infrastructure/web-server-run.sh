@@ -38,12 +38,13 @@ nohup $MOZSEARCH_PATH/tools/target/release/web-server $CONFIG_FILE $STATUS_FILE
 
 # Let's try and stop the pipeline-server from causing problems by setting a ulimit
 # on virtual memory usage.  We use du to figure out the total sizes of all of
-# the files we will mmap, specifically: identifiers, crossref/crossref-extra,
-# and jumpref/jumpref-extra.  We then add an allowance for other libraries and
-# fundamental mapping, plus an allowance for runtime memory usage.
+# the files we will mmap, specifically: identifiers/identifiers-suffixes,
+# crossref/crossref-extra, and jumpref/jumpref-extra.  We then add an allowance
+# for other libraries and fundamental mapping, plus an allowance for runtime
+# memory usage.
 #
 # Resulting units are KiB in all cases, which is also what ulimit takes.
-MAPPED_FILES_USAGE_K=$(du -c $WORKING/*/identifiers $WORKING/*/crossref* $WORKING/*/jumpref* | cut -f1 | tail -1)
+MAPPED_FILES_USAGE_K=$(du -c $WORKING/*/identifiers* $WORKING/*/crossref* $WORKING/*/jumpref* | cut -f1 | tail -1)
 # When first adding the ulimit, our VM size was 13.7G with resident usage of
 # 390M.  When writing this on the spare config1 I'm seeing 13.5G VM with 668M
 # resident with the MAPPED_FILES_USAGE_K above reporting ~12.7G which gives 800M
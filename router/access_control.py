@@ -0,0 +1,31 @@
+# Path-based access control for trees with `access_control` config.  This
+# mirrors `tools/src/abstract_server/access_control.rs`: paths under a
+# restricted prefix are filtered out of search results unless the request's
+# groups header names one of the prefix's groups.
+
+from __future__ import absolute_import
+
+DEFAULT_GROUPS_HEADER = 'X-Searchfox-Groups'
+
+# The prefixes the current request may not see.  Each request is handled in
+# its own forked process, so it's fine for this to be global.
+denied_prefixes = []
+
+def set_request_access(tree_config, headers):
+    global denied_prefixes
+
+    access_config = tree_config.get('access_control', {})
+    header = headers.get(access_config.get('groups_header', DEFAULT_GROUPS_HEADER), '')
+    groups = set(g.strip() for g in header.split(',') if g.strip())
+
+    # Prefixes are directories, so we normalize away any slashes and match
+    # whole path components.
+    denied_prefixes = [
+        restricted['prefix'].strip('/') for restricted in access_config.get('restricted', [])
+        if not groups.intersection(restricted.get('groups', []))
+    ]
+
+def is_path_allowed(path):
+    path = path.strip('/')
+    return not any(path == prefix or path.startswith(prefix + '/')
+                   for prefix in denied_prefixes)
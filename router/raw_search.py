@@ -1,5 +1,7 @@
 import re
 
+import access_control
+
 class RawSearchResults(object):
     '''
     Alternate version of SearchResults that attempts to leave information in its
@@ -61,7 +63,7 @@ class RawSearchResults(object):
             self.pathre = re.compile(re.escape(path), re.IGNORECASE)
 
     def add_paths(self, paths):
-        self.paths.extend(paths)
+        self.paths.extend(p for p in paths if access_control.is_path_allowed(p['path']))
 
     def add_symbol(self, raw_sym, data):
         '''
@@ -100,6 +102,8 @@ class RawSearchResults(object):
                 # skip this path if it's filtered out.
                 if self.pathre and not self.pathre.search(path):
                     continue
+                if not access_control.is_path_allowed(path):
+                    continue
 
                 pathkind = self.categorize_path(path)
                 hits_by_kind = hits_by_pathkind.get(pathkind, None)
@@ -0,0 +1,153 @@
+# Masking of likely secrets in search results for trees with `redaction`
+# config.  This mirrors `tools/src/redaction.rs`: matches are replaced with
+# `*`s of the same length in bytes and recorded in the audit log, but never the
+# secrets themselves.  Only the lines of search results need masking here since
+# the rendered files are masked when they're generated.  The rules are matched
+# against the whole file a result came from so that lines in the middle of
+# multi-line secrets like private keys are masked too.
+
+from __future__ import absolute_import
+import json
+import os
+import re
+import time
+
+from logger import log
+
+BUILTIN_RULES = [
+    ('aws-access-key', r'\b(?:AKIA|ASIA)[0-9A-Z]{16}\b'),
+    ('github-token', r'\bgh[pousr]_[A-Za-z0-9]{36,}\b'),
+    ('slack-token', r'\bxox[abprs]-[A-Za-z0-9-]{10,}'),
+    ('private-key', r'(?s)-----BEGIN [A-Z ]*PRIVATE KEY-----.*?-----END [A-Z ]*PRIVATE KEY-----'),
+    ('secret-assignment',
+     r'''(?i)(?:api[_-]?key|secret|password|passwd|auth[_-]?token)["']?\s*[:=]\s*["']([^"'\s]{8,})["']'''),
+]
+
+# The rules, audit log, and source directories for the current request's tree,
+# and the masked files we've read for it.  Each request is handled in its own
+# forked process, so it's fine for these to be global.
+rules = []
+audit_log = None
+files_path = None
+objdir_path = None
+file_masks = {}
+
+def set_request_redaction(tree_config):
+    global rules, audit_log, files_path, objdir_path
+
+    redaction_config = tree_config.get('redaction', {})
+    configured = [(rule['name'], rule['pattern']) for rule in redaction_config.get('rules', [])]
+    if redaction_config.get('builtin_rules', False):
+        configured = BUILTIN_RULES + configured
+    rules = [(name, re.compile(pattern)) for (name, pattern) in configured]
+    audit_log = redaction_config.get('audit_log')
+    files_path = tree_config.get('files_path')
+    objdir_path = tree_config.get('objdir_path')
+
+def mask(text, start, end):
+    masked = ''.join(c if c == '\n' else '*' * len(c.encode('utf-8')) for c in text[start:end])
+    return text[:start] + masked + text[end:]
+
+def redact_text(text):
+    '''
+    Returns the masked text and the (1-based line number, rule name) of each
+    secret, where the line number is that of the start of the secret.
+    '''
+    matched = []
+    for (name, rule_re) in rules:
+        # Mask from the end so the earlier match positions stay valid.
+        for m in reversed(list(rule_re.finditer(text))):
+            group = 1 if rule_re.groups else 0
+            if m.start(group) == m.end(group):
+                continue
+            matched.append((text.count('\n', 0, m.start(group)) + 1, name))
+            text = mask(text, m.start(group), m.end(group))
+    matched.sort()
+    return (text, matched)
+
+def split_lines(text):
+    '''Split the text into lines like Rust's `str::lines`.'''
+    lines = [l[:-1] if l.endswith('\r') else l for l in text.split('\n')]
+    if lines and lines[-1] == '':
+        lines.pop()
+    return lines
+
+def get_file_mask(path):
+    '''
+    Returns the lines of the file with the search result path, the masked
+    lines, and the secrets found, or None if we can't read the file.
+    '''
+    if path in file_masks:
+        return file_masks[path]
+
+    file_mask = None
+    if path.startswith('__GENERATED__'):
+        source_path = objdir_path and path.replace('__GENERATED__', objdir_path)
+    else:
+        source_path = files_path and os.path.join(files_path, path)
+    if source_path:
+        try:
+            with open(source_path, encoding='utf-8') as f:
+                contents = f.read()
+            (masked, matched) = redact_text(contents)
+            file_mask = (split_lines(contents), split_lines(masked), matched)
+        except (IOError, UnicodeDecodeError):
+            pass
+    file_masks[path] = file_mask
+    return file_mask
+
+def redact_search_line(file_mask, lineno, text):
+    '''Returns the masked line and the names of the rules that matched.'''
+    if file_mask and lineno is not None and 0 < lineno <= len(file_mask[0]):
+        (file_lines, masked_lines, matched) = file_mask
+        if file_lines[lineno - 1] == text:
+            masked = masked_lines[lineno - 1]
+            if masked == text:
+                return (text, [])
+            # Attribute the line to the last secret starting at or before it.
+            names = [name for (start, name) in matched if start <= lineno][-1:]
+            return (masked, names)
+    (masked, matched) = redact_text(text)
+    return (masked, [name for (_, name) in matched])
+
+def redact_result_line(path, line):
+    '''
+    Mask any secrets in a search result's line (and its context lines) in
+    place, recording the redactions in the audit log.
+    '''
+    if not rules:
+        return
+
+    file_mask = get_file_mask(path)
+    lno = line.get('lno')
+    matched = []
+    if 'line' in line:
+        (line['line'], line_matched) = redact_search_line(file_mask, lno, line['line'])
+        matched.extend(line_matched)
+    for (context_key, first_lno) in (
+            ('context_before', lno - len(line.get('context_before', [])) if lno else None),
+            ('context_after', lno + 1 if lno else None)):
+        if context_key in line:
+            masked = []
+            for (i, context_line) in enumerate(line[context_key]):
+                context_lno = first_lno + i if first_lno is not None else None
+                (context_line, line_matched) = redact_search_line(file_mask, context_lno,
+                                                                  context_line)
+                masked.append(context_line)
+                matched.extend(line_matched)
+            line[context_key] = masked
+
+    if matched and audit_log:
+        at = int(time.time())
+        entries = ''.join(json.dumps({
+            'at': at,
+            'context': 'search',
+            'path': path,
+            'lineno': line.get('lno'),
+            'rule': name,
+        }) + '\n' for name in matched)
+        try:
+            with open(audit_log, 'a') as f:
+                f.write(entries)
+        except IOError as e:
+            log('Unable to write to redaction audit log %s: %s', audit_log, e)
@@ -16,9 +16,11 @@ import errno
 import traceback
 import collections
 
+import access_control
 import crossrefs
 import identifiers
 import codesearch
+import redaction
 from logger import log
 from raw_search import RawSearchResults
 
@@ -317,6 +319,9 @@ class SearchResults(object):
         if self.pathre and not self.pathre.search(path):
             return
 
+        if not access_control.is_path_allowed(path):
+            return
+
         # compiled is a map {pathkind: {qkind: {path: [(lines, line_modifier)]}}}
         kind_results = self.compiled.setdefault(pathkind, collections.OrderedDict()).setdefault(qkind, {})
         path_results = kind_results.setdefault(path, ([], line_modifier))
@@ -361,6 +366,7 @@ class SearchResults(object):
                         line_hash[key] = True
                         if line_modifier:
                             line_modifier(line)
+                        redaction.redact_result_line(path, line)
                         lines_out.append(line)
                         count += 1
                         if count == self.max_count:
@@ -789,6 +795,10 @@ class Handler(http.server.SimpleHTTPRequestHandler):
         # Strip any extra slashes.
         path_elts = [ elt for elt in path_elts if elt != '' ]
 
+        if len(path_elts) >= 1 and path_elts[0] in config['trees']:
+            access_control.set_request_access(config['trees'][path_elts[0]], self.headers)
+            redaction.set_request_redaction(config['trees'][path_elts[0]])
+
         if len(path_elts) >= 2 and path_elts[1] == 'search':
             tree_name = path_elts[0]
             query = urllib.parse.parse_qs(url.query)
@@ -816,7 +826,14 @@ class Handler(http.server.SimpleHTTPRequestHandler):
             query = urllib.parse.parse_qs(url.query)
             symbol = query['q'][0]
             results = expand_keys(tree_name, crossrefs.lookup_merging(tree_name, symbol), False)
-            definition = results['Definitions'][0]
+            definitions = [d for d in results['Definitions']
+                           if access_control.is_path_allowed(d['path'])]
+            if not definitions:
+                # Restricted definitions are indistinguishable from missing
+                # ones.
+                self.send_error(404)
+                return
+            definition = definitions[0]
             filename = definition['path']
             lineno = definition['lines'][0]['lno']
             url = '/' + tree_name + '/source/' + filename + '#' + str(lineno)
@@ -870,6 +887,31 @@ identifiers.load(config)
 with open(status_fname, "a") as status_out:
     status_out.write("router.py loaded\n")
 
+def reload_config(signum, frame):
+    '''
+    Reload the config on SIGHUP so that trees can be added to a running
+    deployment without restarting.  Requests are handled in forked children,
+    so only our state needs updating.  The codesearch daemons of new trees are
+    started lazily by their first search.  If the new config is broken, we
+    keep serving the current trees.
+    '''
+    global config
+    try:
+        new_config = json.load(open(config_fname))
+        for tree_name, tree_config in new_config['trees'].items():
+            if not os.path.isdir(tree_config['index_path']):
+                raise Exception('Tree %s is missing index directory %s' % (tree_name, tree_config['index_path']))
+        crossrefs.load(new_config)
+        identifiers.load(new_config)
+        codesearch.load(new_config, stop=False, start=False)
+    except Exception:
+        log('config reload failed, keeping current trees\n%s', traceback.format_exc())
+        return
+    config = new_config
+    log('reloaded config, serving trees: %s', ', '.join(config['trees']))
+
+signal.signal(signal.SIGHUP, reload_config)
+
 class ForkingServer(ForkingMixIn, http.server.HTTPServer):
     pass
 
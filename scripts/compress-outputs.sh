@@ -65,5 +65,15 @@ function idempotently_gzip_dir_no_touch {
   popd
 }
 
-compress_dir_with_touch "${INDEX_ROOT}/dir/"
+CONFIG_FILE=$(realpath $1)
+TREE_NAME=$2
+
+# If the tree's `precompress_html` config is set, `batch-render dir` already
+# wrote the gzipped directory listings and their zero-length markers, and
+# compressing them again would produce ".gz.gz" files.
+PRECOMPRESS_HTML=$(jq -r ".trees[\"${TREE_NAME}\"].precompress_html // false" ${CONFIG_FILE})
+if [ "$PRECOMPRESS_HTML" != "true" ]; then
+  compress_dir_with_touch "${INDEX_ROOT}/dir/"
+fi
+
 compress_dir_with_touch "${INDEX_ROOT}/analysis/"
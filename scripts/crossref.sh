@@ -18,3 +18,7 @@ $MOZSEARCH_PATH/tools/target/release/crossref $CONFIG_FILE $TREE_NAME $ANALYSIS_
 ID_FILE=$INDEX_ROOT/identifiers
 LC_ALL=C sort -f $ID_FILE > ${TMPDIR:-/tmp}/ids
 mv ${TMPDIR:-/tmp}/ids $ID_FILE
+
+# Build the suffix array for identifier substring searches, which refers to
+# offsets in the re-sorted file.
+$MOZSEARCH_PATH/tools/target/release/build-identifier-suffixes $ID_FILE
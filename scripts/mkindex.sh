@@ -40,6 +40,9 @@ source $LIVEGREP_VENV/bin/activate
 # "--env RUST_BACKTRACE" unless using "env_parallel".
 export RUST_BACKTRACE=1
 
+# Recorded in the index manifest written by `write-index-manifest.py`.
+INDEX_STARTED_AT=$(date -u +%Y-%m-%dT%H:%M:%S+00:00)
+
 date
 
 $MOZSEARCH_PATH/scripts/find-repo-files.py $CONFIG_REPO $CONFIG_FILE $TREE_NAME
@@ -132,13 +135,19 @@ $MOZSEARCH_PATH/scripts/build-codesearch.py $CONFIG_FILE $TREE_NAME || handle_tr
 
 date
 
-# This depends on INDEX_ROOT already being available.  The script doesn't
-# actually care about CONFIG_FILE or TREE_NAME, but it's helpful to
-# `indexer-logs-analyze.sh`.
+# This depends on INDEX_ROOT already being available.  The script only uses
+# CONFIG_FILE and TREE_NAME to check the tree's `precompress_html`, but they're
+# also helpful to `indexer-logs-analyze.sh`.
 $MOZSEARCH_PATH/scripts/compress-outputs.sh $CONFIG_FILE $TREE_NAME || handle_tree_error "compress-outputs.sh"
 
 date
 
+# Record what this index was generated from for `show-index-info` and the
+# `INDEX_INFO` template variable.
+$MOZSEARCH_PATH/scripts/write-index-manifest.py $CONFIG_FILE $TREE_NAME $INDEX_STARTED_AT || handle_tree_error "write-index-manifest.py"
+
+date
+
 # Check the resulting index for correctness, but there's no webserver so the
 # 4th argument needs to be empty.  We now also need the livegrep server to be
 # available, so start that first.
@@ -146,6 +155,18 @@ $MOZSEARCH_PATH/router/codesearch.py $CONFIG_FILE start $TREE_NAME
 date
 $MOZSEARCH_PATH/scripts/check-index.sh $CONFIG_FILE $TREE_NAME "filesystem" ""
 
+date
+
+# Run any post-index hooks (audits, reports) the tree's config asks for while
+# the livegrep server is still up, since their pipelines may need it.
+$MOZSEARCH_PATH/scripts/post-index-hooks.sh $CONFIG_FILE $TREE_NAME || handle_tree_error "post-index-hooks.sh"
+
+date
+
+# Cache the outputs of expensive query presets for the tree's hot symbols so
+# the first production hit on them is fast.
+$MOZSEARCH_PATH/scripts/precompute.sh $CONFIG_FILE $TREE_NAME || handle_tree_error "precompute.sh"
+
 # And we want to stop it after.  It's possible if we errored above that it will
 # still be hanging around, but codesearch.py always stops an existing server
 # first, so we're not really concerned about this affecting a re-run of the
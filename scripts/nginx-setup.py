@@ -18,6 +18,7 @@ from __future__ import print_function
 import sys
 import json
 import os.path
+import re
 import subprocess
 
 # The config file at the root of the WORKING directory; all paths should be
@@ -58,6 +59,29 @@ binary_types = {
 
 binary_types_str = " ".join((mime + " " + exts + ";") for (exts, mime) in binary_types.items())
 
+def restricted_paths(tree_config):
+    '''
+    Return a list of (prefix, deny_directive) for the tree's `access_control`
+    restricted path prefixes (see `tools/src/abstract_server/access_control.rs`)
+    where the directive 404s requests whose groups header doesn't name one of
+    the prefix's groups, plus the directive to use as the proxy cache key so
+    that requesters with different groups don't share cached responses.
+    '''
+    access_config = tree_config.get('access_control', {})
+    header = access_config.get('groups_header', 'X-Searchfox-Groups')
+    groups_var = '$http_' + header.lower().replace('-', '_')
+    restricted = []
+    for entry in access_config.get('restricted', []):
+        prefix = entry['prefix'].strip('/')
+        groups_re = '|'.join(re.escape(group) for group in entry.get('groups', []))
+        if groups_re:
+            deny = f'if ({groups_var} !~ "(^|,)\\s*({groups_re})\\s*(,|$)") {{ return 404; }}'
+        else:
+            deny = 'return 404;'
+        restricted.append((prefix, deny))
+    cache_key = f'proxy_cache_key "$scheme$proxy_host$request_uri {groups_var}";'
+    return restricted, cache_key
+
 def location(route, directives):
     print(f'  location {route} {{')
 
@@ -122,6 +146,14 @@ map $status $expires {
   "301" 1m;
 }
 
+# The pages reference static assets via URLs fingerprinted with the asset's
+# content hash as "?v=" (see `render asset-manifest`), and those URLs change
+# whenever the asset does, so they can be cached (practically) forever.
+map $arg_v $static_expires {
+  default 1y;
+  "" $expires;
+}
+
 server {
   listen 80 default_server;
 
@@ -162,11 +194,12 @@ for repo in config['trees']:
             pass
 
     # we use alias because the we don't want the "/{repo}" portion.
-    location(f'/{repo}/static/', [f'alias {mozsearch_path}/static/;'])
+    location(f'/{repo}/static/', [f'alias {mozsearch_path}/static/;', 'expires $static_expires;'])
 
-    location(f'/{repo}/pages/', [f'alias {index_path}/pages/;'])
+    # The pages may have gzipped copies if the tree's `precompress_html` is set.
+    location(f'/{repo}/pages/', [f'alias {index_path}/pages/;', 'gzip_static on;'])
 
-    location(f'/{repo}/source', [
+    source_directives = [
         f'root {doc_root};',
         'try_files /file/$uri /dir/$uri/index.html =404;',
         f'types {{ {binary_types_str} }}',
@@ -174,9 +207,8 @@ for repo in config['trees']:
         'add_header Cache-Control "must-revalidate";',
         'gzip_static always;',
         'gunzip on;',
-    ])
-
-    location(f'/{repo}/raw-analysis', [
+    ]
+    raw_analysis_directives = [
         f'root {doc_root};',
         'try_files /raw-analysis/$uri =404;',
         'types { }',
@@ -188,9 +220,33 @@ for repo in config['trees']:
         'add_header Cache-Control "must-revalidate";',
         'gzip_static always;',
         'gunzip on;',
-    ])
+    ]
+
+    # Restricted path prefixes get their own locations which 404 unless the
+    # requester is in one of the prefix's groups.  We use both an exact match
+    # and a "^~" prefix match on the directory so that ex: "foo/bar" doesn't
+    # also restrict "foo/bar-baz.txt" and so that the prefix match takes
+    # precedence over the regexp `rev` location below.
+    restricted, cache_key = restricted_paths(tree_config)
+    for (prefix, deny) in restricted:
+        for service, directives in [('source', source_directives),
+                                    ('raw-analysis', raw_analysis_directives)]:
+            location(f'= /{repo}/{service}/{prefix}', [deny] + directives)
+            location(f'^~ /{repo}/{service}/{prefix}/', [deny] + directives)
+        # Regexp locations are checked in order, so this needs to come before
+        # the head revision optimization below.
+        location(f'~^/{repo}/rev/[^/]+/{re.escape(prefix)}(/|$)', [
+            deny,
+            cache_key,
+            'proxy_pass http://localhost:8001;',
+        ])
 
-    location(f'/{repo}/file-lists', [
+    location(f'/{repo}/source', source_directives)
+    location(f'/{repo}/raw-analysis', raw_analysis_directives)
+
+    # The file lists enumerate every file in the tree, so only requesters who
+    # can see all of the restricted prefixes may see them.
+    location(f'/{repo}/file-lists', [deny for (_, deny) in restricted] + [
         f'root {doc_root};',
         'try_files /file-lists/$uri =404;',
         'types { }',
@@ -212,21 +268,29 @@ for repo in config['trees']:
             'gunzip on;',
         ])
 
+    # Dynamic responses for trees with restricted paths depend on the
+    # requester's groups, so they must not share cached responses.
+    def proxy(port):
+        return ([cache_key] if restricted else []) + [f'proxy_pass http://localhost:{port};']
+
     # Handled by router/router.py
-    location(f'/{repo}/search', ['proxy_pass http://localhost:8000;'])
-    location(f'/{repo}/sorch', ['proxy_pass http://localhost:8000;'])
-    location(f'/{repo}/define', ['proxy_pass http://localhost:8000;'])
+    location(f'/{repo}/search', proxy(8000))
+    location(f'/{repo}/sorch', proxy(8000))
+    location(f'/{repo}/define', proxy(8000))
 
     # Handled by Rust `web-server.rs`.
-    location(f'/{repo}/diff', ['proxy_pass http://localhost:8001;'])
-    location(f'/{repo}/commit', ['proxy_pass http://localhost:8001;'])
-    location(f'/{repo}/rev', ['proxy_pass http://localhost:8001;'])
-    location(f'/{repo}/hgrev', ['proxy_pass http://localhost:8001;'])
-    location(f'/{repo}/complete', ['proxy_pass http://localhost:8001;'])
-    location(f'/{repo}/commit-info', ['proxy_pass http://localhost:8001;'])
+    location(f'/{repo}/diff', proxy(8001))
+    location(f'/{repo}/commit', proxy(8001))
+    location(f'/{repo}/rev', proxy(8001))
+    location(f'/{repo}/hgrev', proxy(8001))
+    location(f'/{repo}/complete', proxy(8001))
+    location(f'/{repo}/commit-info', proxy(8001))
 
     # Handled by Rust `pipeline-server.rs`
-    location(f'/{repo}/query', ['proxy_pass http://localhost:8002;'])
+    location(f'/{repo}/query', proxy(8002))
+    location(f'/{repo}/hooks', proxy(8002))
+    location(f'/{repo}/badge', proxy(8002))
+    location(f'/{repo}/shared', proxy(8002))
 
 
 location('= /', [
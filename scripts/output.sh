@@ -16,6 +16,13 @@ TREE_NAME=$3
 URL_MAP_PATH=$4
 DOC_TREES_PATH=$5
 
+# Hash the static assets first so that every page rendered below (including by
+# output-file) references them via the same fingerprinted URLs.
+TOOL_CMD="render asset-manifest"
+SEARCHFOX_SERVER=${CONFIG_FILE} \
+    SEARCHFOX_TREE=${TREE_NAME} \
+    $MOZSEARCH_PATH/tools/target/release/searchfox-tool "$TOOL_CMD"
+
 # let's put the "parallel" output in a new `diags` directory, as we're still
 # seeing really poor output-file performance in bug 1567724.
 DIAGS_DIR=$INDEX_ROOT/diags/output
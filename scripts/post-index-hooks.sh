@@ -0,0 +1,27 @@
+#!/usr/bin/env bash
+
+set -x # Show commands
+set -eu # Errors/undefined vars are fatal
+set -o pipefail # Check all commands in a pipeline
+
+if [ $# != 2 ]
+then
+    echo "usage: $0 <config-file> <tree-name>"
+    exit 1
+fi
+
+CONFIG_FILE=$(realpath $1)
+TREE_NAME=$2
+
+# Nothing to do if the tree doesn't have any `post_index_hooks` configured.
+HOOK_COUNT=$(jq -r ".trees[\"${TREE_NAME}\"].post_index_hooks // {} | length" ${CONFIG_FILE})
+if [[ $HOOK_COUNT == "0" ]]; then
+    exit 0
+fi
+
+# The hooks' outputs end up under $INDEX_ROOT/post-index-hooks; see
+# `tools/src/query/post_index_hooks.rs`.
+TOOL_CMD="run-post-index-hooks"
+SEARCHFOX_SERVER=${CONFIG_FILE} \
+    SEARCHFOX_TREE=${TREE_NAME} \
+    $MOZSEARCH_PATH/tools/target/release/searchfox-tool "$TOOL_CMD"
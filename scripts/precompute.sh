@@ -0,0 +1,28 @@
+#!/usr/bin/env bash
+
+set -x # Show commands
+set -eu # Errors/undefined vars are fatal
+set -o pipefail # Check all commands in a pipeline
+
+if [ $# != 2 ]
+then
+    echo "usage: $0 <config-file> <tree-name>"
+    exit 1
+fi
+
+CONFIG_FILE=$(realpath $1)
+TREE_NAME=$2
+
+# Nothing to do if the tree doesn't have any presets to `precompute`.
+PRESET_COUNT=$(jq -r ".trees[\"${TREE_NAME}\"].precompute.presets // [] | length" ${CONFIG_FILE})
+if [[ $PRESET_COUNT == "0" ]]; then
+    exit 0
+fi
+
+# The outputs end up under $INDEX_ROOT/pipeline-cache; see
+# `tools/src/query/pipeline_cache.rs`.  We clear out any outputs from a previous
+# run since they would be for a different revision anyways.
+TOOL_CMD="precompute --clear"
+SEARCHFOX_SERVER=${CONFIG_FILE} \
+    SEARCHFOX_TREE=${TREE_NAME} \
+    $MOZSEARCH_PATH/tools/target/release/searchfox-tool "$TOOL_CMD"
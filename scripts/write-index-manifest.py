@@ -0,0 +1,75 @@
+#!/usr/bin/env python3
+
+# Write `index-manifest.json` into the tree's index directory, recording what
+# the index was generated from: the source and tooling revisions, the config,
+# and the analysis inputs that were available.  This is exposed by
+# searchfox-tool's `show-index-info` command and the `INDEX_INFO` template
+# variable.
+#
+# Usage: write-index-manifest.py <config-file> <tree-name> <started-at>
+
+from __future__ import absolute_import
+import datetime
+import hashlib
+import json
+import os
+import subprocess
+import sys
+
+MANIFEST_FORMAT = 1
+
+def try_output(cmd, **extra):
+    '''Run the command, returning its stripped stdout or None if it failed.
+    Missing tools shouldn't break indexing, they just won't be recorded.'''
+    try:
+        p = subprocess.run(cmd, stdout=subprocess.PIPE, stderr=subprocess.DEVNULL,
+                           check=False, **extra)
+    except OSError:
+        return None
+    if p.returncode:
+        return None
+    return p.stdout.decode().strip()
+
+def git_head(path):
+    if not path or not os.path.isdir(path):
+        return None
+    return try_output(['git', 'rev-parse', 'HEAD'], cwd=path)
+
+config_fname = sys.argv[1]
+tree_name = sys.argv[2]
+started_at = sys.argv[3]
+
+with open(config_fname, 'rb') as f:
+    config_bytes = f.read()
+config = json.loads(config_bytes)
+tree = config['trees'][tree_name]
+index_path = tree['index_path']
+
+analysis_inputs = sorted(
+    name for name in os.listdir(index_path)
+    if name.startswith('analysis-') and os.path.isdir(os.path.join(index_path, name)))
+
+manifest = {
+    'format': MANIFEST_FORMAT,
+    'tree': tree_name,
+    'revision': git_head(tree.get('git_path')),
+    'mozsearch_revision': git_head(config.get('mozsearch_path')),
+    'config_revision': git_head(config.get('config_repo')),
+    'config_sha256': hashlib.sha256(config_bytes).hexdigest(),
+    'tools': {
+        'rustc': try_output(['rustc', '--version']),
+        'clang': try_output(['clang', '--version']),
+    },
+    'started_at': started_at,
+    'finished_at': datetime.datetime.now(datetime.timezone.utc).isoformat(timespec='seconds'),
+    'analysis_inputs': analysis_inputs,
+    'scip_subtrees': sorted(tree.get('scip_subtrees', {}).keys()),
+}
+
+# clang prints multiple lines; only the first has the version.
+if manifest['tools']['clang']:
+    manifest['tools']['clang'] = manifest['tools']['clang'].splitlines()[0]
+
+with open(os.path.join(index_path, 'index-manifest.json'), 'w') as f:
+    json.dump(manifest, f, indent=2, sort_keys=True)
+    f.write('\n')
@@ -260,7 +260,11 @@ var Dxr = new (class Dxr {
       name: true,
       type: false,
       line: true,
+      align: false,
     };
+    // Columns whose data is only included in the results when requested in
+    // the query, so toggling them on requires re-running the query.
+    this.queryCols = new Set(["align"]);
 
     this.cols = {};
     for (const [key, defaultValue] of Object.entries(defaultCols)) {
@@ -304,7 +308,11 @@ var Dxr = new (class Dxr {
   }
 
   onColChange() {
+    let needsRequery = false;
     for (const [key, obj] of Object.entries(this.cols)) {
+      if (obj.node.checked && !obj.currentValue && this.queryCols.has(key)) {
+        needsRequery = true;
+      }
       obj.currentValue = obj.node.checked;
 
       if (!obj.defaultValue) {
@@ -315,6 +323,9 @@ var Dxr = new (class Dxr {
     }
 
     this.updateColQuery();
+    if (needsRequery) {
+      window.location = this.constructURL().href;
+    }
   }
 
   getShowCols() {
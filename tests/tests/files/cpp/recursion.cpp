@@ -0,0 +1,34 @@
+namespace recursion {
+
+bool is_odd(unsigned n);
+
+bool is_even(unsigned n) {
+  if (n == 0) {
+    return true;
+  }
+  return is_odd(n - 1);
+}
+
+bool is_odd(unsigned n) {
+  if (n == 0) {
+    return false;
+  }
+  return is_even(n - 1);
+}
+
+unsigned factorial(unsigned n) {
+  if (n <= 1) {
+    return 1;
+  }
+  return n * factorial(n - 1);
+}
+
+unsigned parity_sum(unsigned n) {
+  unsigned sum = 0;
+  if (is_even(n)) {
+    sum += factorial(n);
+  }
+  return sum;
+}
+
+}  // namespace recursion
@@ -0,0 +1,19 @@
+#include <stdint.h>
+#include <utility>
+
+namespace field_layout {
+
+namespace external_base {
+
+struct Sub : public std::pair<uint32_t, uint32_t> {
+  uint32_t x;
+};
+
+Sub f() {
+  Sub s;
+  return s;
+}
+
+}  // namespace external_base
+
+}  // namespace field_layout
@@ -0,0 +1,25 @@
+#include <stdint.h>
+
+namespace field_layout {
+
+namespace no_unique_address {
+
+struct Empty {};
+
+struct Base {
+  uint32_t a;
+};
+
+struct Sub : public Base {
+  [[no_unique_address]] Empty e;
+  uint32_t x;
+};
+
+Sub f() {
+  Sub s;
+  return s;
+}
+
+}  // namespace no_unique_address
+
+}  // namespace field_layout
@@ -0,0 +1,845 @@
+//! Path-based access control for trees that are only partially public.  A
+//! tree's `access_control` config (see `AccessControlConfig`) lists path
+//! prefixes that only requesters in specific groups may see.  The
+//! pipeline-server wraps the tree's server in an `AccessControlledServer` for
+//! each request based on the requester's groups, which:
+//! - Refuses to fetch sources, analyses, and HTML for restricted paths.
+//! - Filters restricted paths out of file and text search results.
+//! - Filters path containers for restricted paths out of crossref and jumpref
+//!   data, which is what pipelines build their outputs from.
+//! - Filters symbols all of whose jumps are to restricted paths out of
+//!   identifier search results and symbol scans.
+//! - Filters entries for restricted paths out of directory listings.
+//!
+//! The pipeline-server additionally filters the final `PipelineValues` of
+//! queries via `PipelineValues::apply_access_policy` in case a command derived
+//! paths from something other than the wrapped server.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures_core::stream::BoxStream;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde_json::Value;
+use ustr::Ustr;
+
+use super::server_interface::{
+    AbstractServer, BlameLine, EndpointMetrics, ErrorDetails, ErrorKind, ErrorLayer, FileMatches,
+    HistoryEntry, HtmlFileRoot, IndexCapabilities, PopularHits, RawBytesChunk, ResourceCounters,
+    Result, SearchfoxIndexRoot, ServerError, TextMatches, TreeInfo,
+};
+use crate::file_format::{
+    config::AccessControlConfig, index_manifest::IndexManifest,
+    structured_columns::StructuredColumn,
+};
+
+/// The path prefixes a specific requester may not see.
+#[derive(Clone, Debug, Default)]
+pub struct AccessPolicy {
+    /// Directories without a trailing "/".
+    denied_prefixes: Vec<String>,
+}
+
+impl AccessPolicy {
+    pub fn for_groups(config: &AccessControlConfig, groups: &[&str]) -> AccessPolicy {
+        AccessPolicy {
+            denied_prefixes: config
+                .restricted
+                .iter()
+                .filter(|restricted| {
+                    !restricted
+                        .groups
+                        .iter()
+                        .any(|g| groups.contains(&g.as_str()))
+                })
+                .map(|restricted| restricted.prefix.trim_matches('/').to_string())
+                .collect(),
+        }
+    }
+
+    /// Parse the comma-delimited groups header value.
+    pub fn for_header(config: &AccessControlConfig, header: Option<&str>) -> AccessPolicy {
+        let groups: Vec<&str> = header
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|group| !group.is_empty())
+            .collect();
+        Self::for_groups(config, &groups)
+    }
+
+    pub fn is_unrestricted(&self) -> bool {
+        self.denied_prefixes.is_empty()
+    }
+
+    /// Prefixes are matched by path component, so a prefix of "security/embargoed"
+    /// covers that directory and everything under it but not
+    /// "security/embargoed-list.txt".
+    pub fn allows(&self, path: &str) -> bool {
+        let path = path.strip_prefix('/').unwrap_or(path);
+        let path = path.strip_suffix('/').unwrap_or(path);
+        !self.denied_prefixes.iter().any(|dir| {
+            path == dir
+                || path
+                    .strip_prefix(dir.as_str())
+                    .map_or(false, |rest| rest.starts_with('/'))
+        })
+    }
+
+    /// Check the path portion of a jump like "dom/base/Document.cpp#123".
+    pub fn allows_jump(&self, jump: &str) -> bool {
+        self.allows(jump.split('#').next().unwrap_or(jump))
+    }
+
+    fn check(&self, path: &str) -> Result<()> {
+        if self.allows(path) {
+            Ok(())
+        } else {
+            // This is deliberately indistinguishable from a missing file.
+            Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::NotFound,
+                    ErrorLayer::DataLayer,
+                    format!("No such file: {}", path),
+                )
+                .with_context("path", path),
+            ))
+        }
+    }
+
+    /// Remove any objects with a restricted "path" or "file" from arrays
+    /// anywhere in the value, which covers the path containers of crossref data
+    /// and the per-file groupings of serialized search results, as well as
+    /// restricted jumps from the "jumps" of jumpref data.
+    pub fn filter_json(&self, value: &mut Value) {
+        match value {
+            Value::Array(items) => {
+                items.retain(|item| {
+                    ["path", "file"].iter().all(|key| match item.get(key) {
+                        Some(Value::String(path)) => self.allows(path),
+                        _ => true,
+                    })
+                });
+                for item in items {
+                    self.filter_json(item);
+                }
+            }
+            Value::Object(obj) => {
+                if let Some(Value::Object(jumps)) = obj.get_mut("jumps") {
+                    jumps.retain(|_, jump| match jump {
+                        Value::String(jump) => self.allows_jump(jump),
+                        _ => true,
+                    });
+                }
+                for (_, item) in obj.iter_mut() {
+                    self.filter_json(item);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Remove the rows for restricted paths from a directory listing produced
+    /// from `file_table.liquid`.
+    pub fn filter_dir_listing(&self, tree: &str, html: &str) -> String {
+        lazy_static! {
+            static ref ROW_RE: Regex = Regex::new(r#"(?s)<tr>\s*.*?</tr>\s*"#).unwrap();
+            static ref HREF_RE: Regex = Regex::new(r#"href="([^"]*)""#).unwrap();
+        }
+        let source_prefix = format!("/{}/source/", tree);
+        ROW_RE
+            .replace_all(html, |caps: &regex::Captures| {
+                let row = &caps[0];
+                let restricted = HREF_RE.captures(row).map_or(false, |href| {
+                    href[1]
+                        .strip_prefix(source_prefix.as_str())
+                        .map_or(false, |path| !self.allows(path))
+                });
+                if restricted {
+                    String::new()
+                } else {
+                    row.to_string()
+                }
+            })
+            .into_owned()
+    }
+}
+
+/// Wraps another server, applying an `AccessPolicy` to everything it returns.
+pub struct AccessControlledServer {
+    inner: Box<dyn AbstractServer + Send + Sync>,
+    policy: Arc<AccessPolicy>,
+}
+
+impl AccessControlledServer {
+    /// A symbol is hidden if it has jumps and all of them are to restricted
+    /// paths.  Symbols that are also declared or defined somewhere the
+    /// requester can see remain visible.
+    async fn is_symbol_visible(&self, symbol: &str) -> Result<bool> {
+        let jumpref = self.inner.jumpref_lookup(symbol).await?;
+        let jumps: Vec<&str> = match jumpref.get("jumps") {
+            Some(Value::Object(jumps)) => jumps.values().filter_map(Value::as_str).collect(),
+            _ => vec![],
+        };
+        Ok(jumps.is_empty() || jumps.iter().any(|jump| self.policy.allows_jump(jump)))
+    }
+
+    async fn filter_symbols(&self, symbols: Vec<String>) -> Result<Vec<String>> {
+        let mut visible = vec![];
+        for symbol in symbols {
+            if self.is_symbol_visible(&symbol).await? {
+                visible.push(symbol);
+            }
+        }
+        Ok(visible)
+    }
+
+    async fn filter_identifier_results(
+        &self,
+        results: Vec<(Ustr, Ustr)>,
+    ) -> Result<Vec<(Ustr, Ustr)>> {
+        let mut visible = vec![];
+        for (symbol, id) in results {
+            if self.is_symbol_visible(&symbol).await? {
+                visible.push((symbol, id));
+            }
+        }
+        Ok(visible)
+    }
+}
+
+/// Wrap the server so that it applies the policy, or just return the server if
+/// the policy doesn't restrict anything.
+pub fn restrict_server(
+    inner: Box<dyn AbstractServer + Send + Sync>,
+    policy: AccessPolicy,
+) -> Box<dyn AbstractServer + Send + Sync> {
+    if policy.is_unrestricted() {
+        return inner;
+    }
+    Box::new(AccessControlledServer {
+        inner,
+        policy: Arc::new(policy),
+    })
+}
+
+#[async_trait]
+impl AbstractServer for AccessControlledServer {
+    fn clonify(&self) -> Box<dyn AbstractServer + Send + Sync> {
+        Box::new(AccessControlledServer {
+            inner: self.inner.clonify(),
+            policy: self.policy.clone(),
+        })
+    }
+
+    fn tree_info(&self) -> Result<TreeInfo> {
+        self.inner.tree_info()
+    }
+
+    fn translate_path(&self, root: SearchfoxIndexRoot, sf_path: &str) -> Result<String> {
+        match root {
+            // Roots whose contents are per source path.
+            SearchfoxIndexRoot::CompressedAnalysis
+            | SearchfoxIndexRoot::FileDescription
+            | SearchfoxIndexRoot::UncompressedDirectoryListing => self.policy.check(sf_path)?,
+            // Roots holding data derived from the whole tree without any
+            // restrictions, which callers must filter themselves via the
+            // unrestricted server.
+            SearchfoxIndexRoot::IndexRoot
+            | SearchfoxIndexRoot::DeploymentState
+            | SearchfoxIndexRoot::PipelineCache
+            | SearchfoxIndexRoot::PostIndexHooks => {
+                return Err(ServerError::StickyProblem(
+                    ErrorDetails::new(
+                        ErrorKind::IndexMissingCapability,
+                        ErrorLayer::ConfigLayer,
+                        "Unavailable to requesters with path restrictions".to_string(),
+                    )
+                    .with_context("path", sf_path),
+                ));
+            }
+            // Roots that have nothing to do with source paths.
+            SearchfoxIndexRoot::ConfigRepo
+            | SearchfoxIndexRoot::IndexTemplates
+            | SearchfoxIndexRoot::IndexPages
+            | SearchfoxIndexRoot::StaticAssets => {}
+        }
+        self.inner.translate_path(root, sf_path)
+    }
+
+    async fn fetch_raw_analysis<'a>(&self, sf_path: &str) -> Result<BoxStream<'a, Value>> {
+        self.policy.check(sf_path)?;
+        self.inner.fetch_raw_analysis(sf_path).await
+    }
+
+    async fn fetch_raw_source(&self, sf_path: &str) -> Result<String> {
+        self.policy.check(sf_path)?;
+        self.inner.fetch_raw_source(sf_path).await
+    }
+
+    async fn fetch_source_at_revision(&self, sf_path: &str, rev: &str) -> Result<String> {
+        self.policy.check(sf_path)?;
+        self.inner.fetch_source_at_revision(sf_path, rev).await
+    }
+
+    async fn fetch_raw_bytes(
+        &self,
+        sf_path: &str,
+        offset: u64,
+        length: usize,
+    ) -> Result<RawBytesChunk> {
+        self.policy.check(sf_path)?;
+        self.inner.fetch_raw_bytes(sf_path, offset, length).await
+    }
+
+    async fn fetch_formatted_lines(&self, sf_path: &str) -> Result<(Vec<String>, String)> {
+        self.policy.check(sf_path)?;
+        self.inner.fetch_formatted_lines(sf_path).await
+    }
+
+    async fn fetch_html(&self, root: HtmlFileRoot, sf_path: &str) -> Result<String> {
+        match root {
+            HtmlFileRoot::FormattedFile => {
+                self.policy.check(sf_path)?;
+                self.inner.fetch_html(root, sf_path).await
+            }
+            HtmlFileRoot::FormattedDir => {
+                self.policy.check(sf_path)?;
+                let html = self.inner.fetch_html(root, sf_path).await?;
+                let tree = self.inner.tree_info()?.name;
+                Ok(self.policy.filter_dir_listing(&tree, &html))
+            }
+            HtmlFileRoot::FormattedTemplate => self.inner.fetch_html(root, sf_path).await,
+        }
+    }
+
+    async fn crossref_lookup(&self, symbol: &str, extra_processing: bool) -> Result<Value> {
+        let mut info = self.inner.crossref_lookup(symbol, extra_processing).await?;
+        self.policy.filter_json(&mut info);
+        Ok(info)
+    }
+
+    async fn crossref_lookup_keys(&self, symbol: &str, keys: &[&str]) -> Result<Value> {
+        let mut info = self.inner.crossref_lookup_keys(symbol, keys).await?;
+        self.policy.filter_json(&mut info);
+        Ok(info)
+    }
+
+    async fn structured_column_lookup(
+        &self,
+        symbol: &str,
+        column: StructuredColumn,
+    ) -> Result<Value> {
+        let mut value = self.inner.structured_column_lookup(symbol, column).await?;
+        self.policy.filter_json(&mut value);
+        Ok(value)
+    }
+
+    async fn jumpref_lookup(&self, symbol: &str) -> Result<Value> {
+        let mut info = self.inner.jumpref_lookup(symbol).await?;
+        self.policy.filter_json(&mut info);
+        Ok(info)
+    }
+
+    async fn search_files(
+        &self,
+        pathre: &str,
+        include_dirs: bool,
+        limit: usize,
+    ) -> Result<FileMatches> {
+        // Filtering after the fact means we may return fewer than `limit`
+        // matches even if more were available, which is fine.
+        let mut matches = self.inner.search_files(pathre, include_dirs, limit).await?;
+        matches
+            .file_matches
+            .retain(|fm| self.policy.allows(&fm.path));
+        Ok(matches)
+    }
+
+    async fn search_identifiers(
+        &self,
+        needle: &str,
+        exact_match: bool,
+        ignore_case: bool,
+        match_limit: usize,
+    ) -> Result<Vec<(Ustr, Ustr)>> {
+        let results = self
+            .inner
+            .search_identifiers(needle, exact_match, ignore_case, match_limit)
+            .await?;
+        self.filter_identifier_results(results).await
+    }
+
+    async fn search_identifier_substrings(
+        &self,
+        needle: &str,
+        ignore_case: bool,
+        match_limit: usize,
+    ) -> Result<Vec<(Ustr, Ustr)>> {
+        let results = self
+            .inner
+            .search_identifier_substrings(needle, ignore_case, match_limit)
+            .await?;
+        self.filter_identifier_results(results).await
+    }
+
+    async fn search_text(
+        &self,
+        pattern: &str,
+        fold_case: bool,
+        path: &str,
+        limit: usize,
+    ) -> Result<TextMatches> {
+        let mut matches = self
+            .inner
+            .search_text(pattern, fold_case, path, limit)
+            .await?;
+        matches.by_file.retain(|tm| self.policy.allows(&tm.file));
+        Ok(matches)
+    }
+
+    async fn search_text_scan(
+        &self,
+        pattern: &str,
+        literal: Option<&str>,
+        fold_case: bool,
+        path: &str,
+        limit: usize,
+    ) -> Result<TextMatches> {
+        let mut matches = self
+            .inner
+            .search_text_scan(pattern, literal, fold_case, path, limit)
+            .await?;
+        matches.by_file.retain(|tm| self.policy.allows(&tm.file));
+        Ok(matches)
+    }
+
+    async fn scan_structured_column(
+        &self,
+        column: StructuredColumn,
+        pred: &(dyn Fn(&Value) -> bool + Send + Sync),
+        limit: usize,
+    ) -> Result<Vec<String>> {
+        let symbols = self
+            .inner
+            .scan_structured_column(column, pred, limit)
+            .await?;
+        self.filter_symbols(symbols).await
+    }
+
+    async fn list_crossref_symbols(&self, limit: usize) -> Result<Vec<String>> {
+        let symbols = self.inner.list_crossref_symbols(limit).await?;
+        self.filter_symbols(symbols).await
+    }
+
+    async fn fetch_blame(&self, sf_path: &str) -> Result<Vec<BlameLine>> {
+        self.policy.check(sf_path)?;
+        self.inner.fetch_blame(sf_path).await
+    }
+
+    async fn fetch_file_log(&self, sf_path: &str, limit: usize) -> Result<Vec<HistoryEntry>> {
+        self.policy.check(sf_path)?;
+        self.inner.fetch_file_log(sf_path, limit).await
+    }
+
+    async fn fetch_line_history(
+        &self,
+        sf_path: &str,
+        line: u32,
+        limit: usize,
+    ) -> Result<Vec<HistoryEntry>> {
+        self.policy.check(sf_path)?;
+        self.inner.fetch_line_history(sf_path, line, limit).await
+    }
+
+    fn capabilities(&self) -> IndexCapabilities {
+        self.inner.capabilities()
+    }
+
+    fn symbol_query_hits(&self, symbol: &str) -> u64 {
+        self.inner.symbol_query_hits(symbol)
+    }
+
+    fn path_query_hits(&self, path: &str) -> u64 {
+        if self.policy.allows(path) {
+            self.inner.path_query_hits(path)
+        } else {
+            0
+        }
+    }
+
+    fn request_metrics(&self) -> BTreeMap<String, EndpointMetrics> {
+        self.inner.request_metrics()
+    }
+
+    fn resource_counters(&self) -> Option<Arc<ResourceCounters>> {
+        self.inner.resource_counters()
+    }
+
+    fn popular_queries(&self, limit: usize) -> Result<PopularHits> {
+        let mut popular = self.inner.popular_queries(limit)?;
+        popular.paths.retain(|(path, _)| self.policy.allows(path));
+        Ok(popular)
+    }
+
+    async fn index_manifest(&self) -> Result<Option<IndexManifest>> {
+        self.inner.index_manifest().await
+    }
+
+    async fn perform_query(&self, q: &str) -> Result<Value> {
+        let mut results = self.inner.perform_query(q).await?;
+        self.policy.filter_json(&mut results);
+        Ok(results)
+    }
+}
+
+#[test]
+fn test_access_policy() {
+    use crate::file_format::config::RestrictedPathConfig;
+    use serde_json::json;
+
+    let config = AccessControlConfig {
+        groups_header: "X-Searchfox-Groups".to_string(),
+        restricted: vec![RestrictedPathConfig {
+            prefix: "security/embargoed/".to_string(),
+            groups: vec!["sec".to_string()],
+        }],
+    };
+
+    assert!(AccessPolicy::for_header(&config, Some("dev, sec")).is_unrestricted());
+
+    let policy = AccessPolicy::for_header(&config, Some("dev"));
+    assert!(!policy.allows("security/embargoed/bug.cpp"));
+    assert!(!policy.allows("/security/embargoed/bug.cpp"));
+    assert!(!policy.allows("security/embargoed"));
+    assert!(!policy.allows("security/embargoed/"));
+    assert!(policy.allows("security/embargoed-list.txt"));
+    assert!(policy.allows("security/public.cpp"));
+    assert!(policy.allows("security"));
+    assert!(!policy.allows_jump("security/embargoed/bug.cpp#12"));
+    assert!(policy.allows_jump("security/public.cpp#12"));
+
+    let mut crossref = json!({
+        "defs": [
+            { "path": "security/embargoed/bug.cpp", "lines": [] },
+            { "path": "dom/base/Document.cpp", "lines": [] },
+        ],
+        "meta": { "pretty": "Bug" },
+    });
+    policy.filter_json(&mut crossref);
+    assert_eq!(crossref["defs"].as_array().unwrap().len(), 1);
+    assert_eq!(crossref["defs"][0]["path"], "dom/base/Document.cpp");
+
+    let mut jumpref = json!({
+        "sym": "Bug",
+        "jumps": {
+            "decl": "security/bug.h#3",
+            "def": "security/embargoed/bug.cpp#12",
+        },
+    });
+    policy.filter_json(&mut jumpref);
+    assert_eq!(jumpref["jumps"], json!({ "decl": "security/bug.h#3" }));
+
+    let mut text_matches = json!({
+        "by_file": [
+            { "file": "security/embargoed/bug.cpp", "matches": [] },
+            { "file": "security/public.cpp", "matches": [] },
+        ],
+    });
+    policy.filter_json(&mut text_matches);
+    assert_eq!(text_matches["by_file"].as_array().unwrap().len(), 1);
+    assert_eq!(text_matches["by_file"][0]["file"], "security/public.cpp");
+
+    let listing = r#"<tbody>
+        <tr>
+          <td><a href="/tests/source/security/embargoed">embargoed</a></td>
+        </tr>
+        <tr>
+          <td><a href="/tests/source/security/public.cpp">public.cpp</a></td>
+        </tr>
+    </tbody>"#;
+    let filtered = policy.filter_dir_listing("tests", listing);
+    assert!(!filtered.contains("embargoed"));
+    assert!(filtered.contains("public.cpp"));
+}
+
+#[cfg(test)]
+fn test_access_config() -> AccessControlConfig {
+    use crate::file_format::config::RestrictedPathConfig;
+
+    AccessControlConfig {
+        groups_header: "X-Searchfox-Groups".to_string(),
+        restricted: vec![RestrictedPathConfig {
+            prefix: "security/embargoed".to_string(),
+            groups: vec!["sec".to_string()],
+        }],
+    }
+}
+
+/// A server that has the same answers for everyone so we can see what the
+/// `AccessControlledServer` removes.
+#[cfg(test)]
+struct FakeServer;
+
+#[cfg(test)]
+#[async_trait]
+impl AbstractServer for FakeServer {
+    fn clonify(&self) -> Box<dyn AbstractServer + Send + Sync> {
+        Box::new(FakeServer)
+    }
+
+    fn tree_info(&self) -> Result<TreeInfo> {
+        Err(ServerError::Unsupported)
+    }
+
+    fn translate_path(&self, _root: SearchfoxIndexRoot, sf_path: &str) -> Result<String> {
+        Ok(format!("/index/{}", sf_path))
+    }
+
+    async fn fetch_raw_analysis<'a>(&self, _sf_path: &str) -> Result<BoxStream<'a, Value>> {
+        Err(ServerError::Unsupported)
+    }
+
+    async fn fetch_raw_source(&self, sf_path: &str) -> Result<String> {
+        Ok(format!("// {}", sf_path))
+    }
+
+    async fn fetch_formatted_lines(&self, _sf_path: &str) -> Result<(Vec<String>, String)> {
+        Err(ServerError::Unsupported)
+    }
+
+    async fn fetch_html(&self, _root: HtmlFileRoot, _sf_path: &str) -> Result<String> {
+        Err(ServerError::Unsupported)
+    }
+
+    async fn crossref_lookup(&self, _symbol: &str, _extra_processing: bool) -> Result<Value> {
+        Ok(Value::Null)
+    }
+
+    async fn jumpref_lookup(&self, symbol: &str) -> Result<Value> {
+        Ok(match symbol {
+            "Embargoed" => serde_json::json!({
+                "jumps": { "def": "security/embargoed/bug.cpp#3" },
+            }),
+            "Declared" => serde_json::json!({
+                "jumps": {
+                    "decl": "security/bug.h#2",
+                    "def": "security/embargoed/bug.cpp#5",
+                },
+            }),
+            "Public" => serde_json::json!({
+                "jumps": { "def": "dom/base/Document.cpp#7" },
+            }),
+            _ => Value::Null,
+        })
+    }
+
+    async fn search_files(
+        &self,
+        _pathre: &str,
+        _include_dirs: bool,
+        _limit: usize,
+    ) -> Result<FileMatches> {
+        Err(ServerError::Unsupported)
+    }
+
+    async fn search_identifiers(
+        &self,
+        _needle: &str,
+        _exact_match: bool,
+        _ignore_case: bool,
+        _match_limit: usize,
+    ) -> Result<Vec<(Ustr, Ustr)>> {
+        Ok(["Embargoed", "Declared", "Public", "Unknown"]
+            .iter()
+            .map(|sym| (ustr::ustr(sym), ustr::ustr("Bug")))
+            .collect())
+    }
+
+    async fn search_text(
+        &self,
+        _pattern: &str,
+        _fold_case: bool,
+        _path: &str,
+        _limit: usize,
+    ) -> Result<TextMatches> {
+        Err(ServerError::Unsupported)
+    }
+
+    async fn scan_structured_column(
+        &self,
+        _column: StructuredColumn,
+        _pred: &(dyn Fn(&Value) -> bool + Send + Sync),
+        _limit: usize,
+    ) -> Result<Vec<String>> {
+        Ok(vec!["Embargoed".to_string(), "Public".to_string()])
+    }
+
+    async fn list_crossref_symbols(&self, _limit: usize) -> Result<Vec<String>> {
+        Ok(vec!["Declared".to_string(), "Embargoed".to_string()])
+    }
+
+    async fn perform_query(&self, _q: &str) -> Result<Value> {
+        Err(ServerError::Unsupported)
+    }
+}
+
+#[tokio::test]
+async fn test_access_controlled_server() {
+    let config = test_access_config();
+    let server = restrict_server(
+        Box::new(FakeServer),
+        AccessPolicy::for_header(&config, Some("dev")),
+    );
+
+    assert!(server
+        .fetch_raw_source("security/embargoed/bug.cpp")
+        .await
+        .is_err());
+    assert!(server
+        .fetch_raw_source("security/embargoed-list.txt")
+        .await
+        .is_ok());
+
+    let translates =
+        |root: SearchfoxIndexRoot, path: &str| server.translate_path(root, path).is_ok();
+    let embargoed = "security/embargoed/bug.cpp";
+    assert!(!translates(
+        SearchfoxIndexRoot::CompressedAnalysis,
+        embargoed
+    ));
+    assert!(!translates(SearchfoxIndexRoot::FileDescription, embargoed));
+    assert!(!translates(
+        SearchfoxIndexRoot::UncompressedDirectoryListing,
+        embargoed
+    ));
+    assert!(translates(
+        SearchfoxIndexRoot::FileDescription,
+        "dom/base/Document.cpp"
+    ));
+    assert!(!translates(SearchfoxIndexRoot::PipelineCache, "presets"));
+    assert!(!translates(SearchfoxIndexRoot::IndexRoot, "file"));
+    assert!(!translates(
+        SearchfoxIndexRoot::DeploymentState,
+        "symbol-annotations.json"
+    ));
+    assert!(translates(SearchfoxIndexRoot::IndexPages, "settings.html"));
+
+    // Symbols whose only jumps are restricted are hidden, but symbols with no
+    // jumps at all are left alone.
+    let identifiers = server
+        .search_identifiers("Bug", true, false, 10)
+        .await
+        .unwrap();
+    let symbols: Vec<&str> = identifiers.iter().map(|(sym, _)| sym.as_str()).collect();
+    assert_eq!(symbols, vec!["Declared", "Public", "Unknown"]);
+
+    let scanned = server
+        .scan_structured_column(StructuredColumn::Kind, &|_: &Value| true, 10)
+        .await
+        .unwrap();
+    assert_eq!(scanned, vec!["Public".to_string()]);
+
+    let listed = server.list_crossref_symbols(10).await.unwrap();
+    assert_eq!(listed, vec!["Declared".to_string()]);
+
+    // Requesters in the group see everything.
+    let server = restrict_server(
+        Box::new(FakeServer),
+        AccessPolicy::for_header(&config, Some("sec")),
+    );
+    assert!(server
+        .fetch_raw_source("security/embargoed/bug.cpp")
+        .await
+        .is_ok());
+    assert_eq!(server.list_crossref_symbols(10).await.unwrap().len(), 2);
+}
+
+#[test]
+fn test_access_policy_pipeline_values() {
+    use crate::abstract_server::{TextMatchInFile, TextMatchesByFile};
+    use crate::cmd_pipeline::{
+        data_table::{DataCell, DataColumnType, DataTable},
+        interface::{PipelineValues, TextFile},
+    };
+    use crate::file_format::analysis::FileClassification;
+
+    let policy = AccessPolicy::for_header(&test_access_config(), None);
+
+    let by_file = |file: &str| TextMatchesByFile {
+        file: ustr::ustr(file),
+        path_kind: ustr::ustr("normal"),
+        classification: FileClassification::default(),
+        matches: Vec::<TextMatchInFile>::new(),
+    };
+    let mut values = PipelineValues::TextMatches(TextMatches {
+        by_file: vec![
+            by_file("security/embargoed/bug.cpp"),
+            by_file("security/public.cpp"),
+        ],
+        page: None,
+    });
+    values.apply_access_policy(&policy);
+    let PipelineValues::TextMatches(tm) = &values else {
+        panic!("apply_access_policy changed the variant");
+    };
+    assert_eq!(tm.by_file.len(), 1);
+    assert_eq!(tm.by_file[0].file.as_str(), "security/public.cpp");
+
+    let mut table = DataTable::new(vec![
+        ("path", DataColumnType::Path),
+        ("size", DataColumnType::Int),
+    ]);
+    for path in ["security/embargoed/bug.cpp", "security/public.cpp"] {
+        table
+            .push_row(vec![DataCell::String(path.to_string()), DataCell::Int(1)])
+            .unwrap();
+    }
+    let mut values = PipelineValues::DataTable(table);
+    values.apply_access_policy(&policy);
+    let PipelineValues::DataTable(dt) = &values else {
+        panic!("apply_access_policy changed the variant");
+    };
+    assert_eq!(dt.rows.len(), 1);
+
+    // Path columns are filtered based on their type rather than their name,
+    // including jumps.
+    let mut table = DataTable::new(vec![
+        ("from", DataColumnType::Path),
+        ("to", DataColumnType::Path),
+        ("note", DataColumnType::String),
+    ]);
+    for (from, to) in [
+        ("security/public.cpp", "security/embargoed/bug.cpp#12"),
+        ("security/embargoed", "security/public.cpp"),
+        ("security/public.cpp", "security/public.h#3"),
+    ] {
+        table
+            .push_row(vec![
+                DataCell::String(from.to_string()),
+                DataCell::String(to.to_string()),
+                DataCell::String("security/embargoed/bug.cpp".to_string()),
+            ])
+            .unwrap();
+    }
+    let mut values = PipelineValues::DataTable(table);
+    values.apply_access_policy(&policy);
+    let PipelineValues::DataTable(dt) = &values else {
+        panic!("apply_access_policy changed the variant");
+    };
+    assert_eq!(dt.rows.len(), 1);
+    assert_eq!(
+        dt.rows[0][1],
+        DataCell::String("security/public.h#3".to_string())
+    );
+
+    // There's nothing we can do about opaque text.
+    let mut values = PipelineValues::TextFile(TextFile {
+        mime_type: "text/plain".to_string(),
+        contents: "security/embargoed/bug.cpp".to_string(),
+    });
+    values.apply_access_policy(&policy);
+    assert!(matches!(values, PipelineValues::TextFile(_)));
+}
@@ -1,29 +1,45 @@
 use async_trait::async_trait;
 use flate2::read::GzDecoder;
 use futures_core::stream::BoxStream;
+use regex::{Regex, RegexBuilder};
 use serde_json::{from_str, Value};
-use std::collections::BTreeMap;
-use std::io::Read;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Read, SeekFrom};
+use std::path::Path;
+use std::sync::Arc;
 use std::time::Instant;
 use tokio::fs::File;
-use tokio::io::AsyncReadExt;
-use tracing::trace;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tracing::{trace, warn};
 use ustr::{ustr, Ustr};
 
 use super::server_interface::{
-    AbstractServer, ErrorDetails, ErrorLayer, FileMatches, HtmlFileRoot, Result,
+    AbstractServer, BlameLine, ErrorDetails, ErrorKind, ErrorLayer, FileMatches, HistoryEntry,
+    HtmlFileRoot, IndexCapabilities, PopularHits, RawBytesChunk, ResourceCounters, Result,
     SearchfoxIndexRoot, ServerError, TextBounds, TextMatchInFile,
 };
 use super::{TextMatches, TextMatchesByFile, TreeInfo};
 
 use crate::abstract_server::lazy_crossref::perform_lazy_crossref;
-use crate::file_format::analysis::{read_analyses, read_source};
-use crate::file_format::config::{load, TreeConfig, TreeConfigPaths};
-use crate::file_format::crossref_lookup::CrossrefLookupMap;
+use crate::blame::LineData;
+use crate::file_format::analysis::{read_analyses, read_source, FileClassification};
+use crate::file_format::config::{load, try_load, TreeConfig, TreeConfigPaths};
+use crate::file_format::crossref_lookup::{migrate_crossref_meta, CrossrefLookupMap};
 use crate::file_format::identifiers::IdentMap;
+use crate::file_format::index_manifest::{
+    load_index_manifest, IndexManifest, INDEX_MANIFEST_FILENAME,
+};
 use crate::file_format::per_file_info::FileLookupMap;
-use crate::format::format_code;
+use crate::file_format::query_popularity::{QueryPopularity, QUERY_POPULARITY_FILENAME};
+use crate::file_format::structured_columns::{
+    column_from_meta, StructuredColumn, StructuredColumns,
+};
+use crate::file_format::trigram_bloom::TrigramBloomIndex;
+use crate::format::{format_code, read_path_at_commit};
+use crate::git_ops::read_blob_entry;
 use crate::languages::select_formatting;
+use crate::line_diff::side_by_side_diff;
+use crate::redaction::Redactor;
 
 pub mod livegrep {
     tonic::include_proto!("_");
@@ -35,10 +51,11 @@ use livegrep::Query;
 /// IO errors amount to a 404 for our purposes which means a sticky problem.
 impl From<std::io::Error> for ServerError {
     fn from(err: std::io::Error) -> ServerError {
-        ServerError::StickyProblem(ErrorDetails {
-            layer: ErrorLayer::ServerLayer,
-            message: err.to_string(),
-        })
+        ServerError::StickyProblem(ErrorDetails::new(
+            ErrorKind::Internal,
+            ErrorLayer::ServerLayer,
+            err.to_string(),
+        ))
     }
 }
 
@@ -47,10 +64,11 @@ impl From<tonic::Status> for ServerError {
         // There are gRPC codes accessible via code() but for now, especially
         // since we lack the ability to restart the server, it seems safe to
         // assume any problem will not magically fix itself.
-        ServerError::StickyProblem(ErrorDetails {
-            layer: ErrorLayer::ServerLayer,
-            message: status.to_string(),
-        })
+        ServerError::StickyProblem(ErrorDetails::new(
+            ErrorKind::Internal,
+            ErrorLayer::ServerLayer,
+            status.to_string(),
+        ))
     }
 }
 
@@ -59,15 +77,17 @@ impl From<tonic::transport::Error> for ServerError {
         // There are gRPC codes accessible via code() but for now, especially
         // since we lack the ability to restart the server, it seems safe to
         // assume any problem will not magically fix itself.
-        ServerError::StickyProblem(ErrorDetails {
-            layer: ErrorLayer::ServerLayer,
-            message: err.to_string(),
-        })
+        ServerError::StickyProblem(ErrorDetails::new(
+            ErrorKind::Internal,
+            ErrorLayer::ServerLayer,
+            err.to_string(),
+        ))
     }
 }
 
-/// Read newline-delimited JSON that's been gzip-compressed.
-async fn read_gzipped_ndjson_from_file(path: &str) -> Result<Vec<Value>> {
+/// Read newline-delimited JSON that's been gzip-compressed, returning the
+/// values and the number of (compressed) bytes read.
+async fn read_gzipped_ndjson_from_file(path: &str) -> Result<(Vec<Value>, u64)> {
     let mut f = File::open(path).await?;
     // We read the entirety to a buffer because
     // https://github.com/serde-rs/json/issues/160 suggests that the buffered
@@ -87,26 +107,145 @@ async fn read_gzipped_ndjson_from_file(path: &str) -> Result<Vec<Value>> {
     // let mut raw_str = String::new();
     // f.read_to_string(&mut raw_str).await?;
 
-    raw_str
+    let values = raw_str
         .lines()
         .map(|s| from_str(s).map_err(ServerError::from))
-        .collect()
+        .collect::<Result<Vec<Value>>>()?;
+    Ok((values, buffer.len() as u64))
 }
 
 /// Helper to ensure that our path-ish use of &str's does not ever try and do
 /// something that can escape a hackily constructed path.  We probably should
 /// move to using path types more directly.
+/// Read a rendered HTML file or its gzipped copy at `{full_path}.gz`.  Either
+/// may be missing depending on whether compress-outputs.sh has run and on the
+/// tree's `precompress_html`, so we fall back to the other.  (The uncompressed
+/// file may also be a zero-length marker for nginx, which is why we prefer the
+/// gzipped copy where one is expected.)
+async fn read_html_file(
+    full_path: &str,
+    prefer_gzipped: bool,
+    sf_path: &str,
+    counters: &ResourceCounters,
+) -> Result<String> {
+    let gzipped_path = format!("{}.gz", full_path);
+    let candidates = if prefer_gzipped {
+        [(gzipped_path.as_str(), true), (full_path, false)]
+    } else {
+        [(full_path, false), (gzipped_path.as_str(), true)]
+    };
+    for (path, is_gzipped) in candidates {
+        let mut f = match File::open(path).await {
+            Ok(f) => f,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+
+        if !is_gzipped {
+            let mut raw_str = String::new();
+            f.read_to_string(&mut raw_str).await?;
+            counters.add_bytes_read(raw_str.len() as u64);
+            return Ok(raw_str);
+        }
+
+        let mut buffer = Vec::new();
+        f.read_to_end(&mut buffer).await?;
+        counters.add_bytes_read(buffer.len() as u64);
+
+        // When we want to go async here,
+        // https://github.com/rust-lang/flate2-rs/pull/214 suggests that we
+        // want to use the `async-compression` crate.
+        let mut gz = GzDecoder::new(&buffer[..]);
+
+        let mut raw_str = String::new();
+        if let Err(e) = gz.read_to_string(&mut raw_str) {
+            return Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::Internal,
+                    ErrorLayer::DataLayer,
+                    format!("Corrupt gzipped HTML: {}", e),
+                )
+                .with_context("path", sf_path),
+            ));
+        }
+        return Ok(raw_str);
+    }
+
+    Err(ServerError::StickyProblem(
+        ErrorDetails::new(
+            ErrorKind::NotFound,
+            ErrorLayer::DataLayer,
+            "No rendered HTML",
+        )
+        .with_context("path", sf_path),
+    ))
+}
+
 fn validate_absoluteish_path(path: &str) -> Result<()> {
     if path.split("/").any(|x| x == "..") {
-        Err(ServerError::StickyProblem(ErrorDetails {
-            layer: ErrorLayer::BadInput,
-            message: "All paths must be absolute-ish".to_string(),
-        }))
+        Err(ServerError::StickyProblem(ErrorDetails::new(
+            ErrorKind::BadQuery,
+            ErrorLayer::BadInput,
+            "All paths must be absolute-ish".to_string(),
+        )))
     } else {
         Ok(())
     }
 }
 
+fn git_problem(what: &str, norm_path: &str, e: git2::Error) -> ServerError {
+    ServerError::StickyProblem(ErrorDetails::new(
+        ErrorKind::Internal,
+        ErrorLayer::ServerLayer,
+        format!("Unable to read {} for {}: {}", what, norm_path, e),
+    ))
+}
+
+/// The original revision a blame repo commit was built for, per the
+/// "git <rev> [hg <rev>]" message `build-blame` gives it.
+fn blame_commit_rev(blame_commit: &git2::Commit) -> Option<git2::Oid> {
+    let message = blame_commit.message()?;
+    git2::Oid::from_str(message.split_whitespace().nth(1)?).ok()
+}
+
+fn history_entry(
+    repo: &git2::Repository,
+    rev: git2::Oid,
+    path: &str,
+    line: Option<u32>,
+) -> HistoryEntry {
+    let commit = repo.find_commit(rev).ok();
+    HistoryEntry {
+        rev: rev.to_string(),
+        author: commit
+            .as_ref()
+            .and_then(|commit| commit.author().name().map(|name| name.to_string()))
+            .unwrap_or_default(),
+        time: commit.as_ref().map_or(0, |commit| commit.time().seconds()),
+        summary: commit
+            .as_ref()
+            .and_then(|commit| commit.summary().map(|summary| summary.to_string()))
+            .unwrap_or_default(),
+        path: path.to_string(),
+        line,
+    }
+}
+
+/// The path a file was renamed from by a commit, per git's rename detection
+/// against the commit's first parent.
+fn renamed_from(repo: &git2::Repository, commit: &git2::Commit, path: &str) -> Option<String> {
+    let parent_tree = commit.parent(0).ok()?.tree().ok()?;
+    let mut diff = repo
+        .diff_tree_to_tree(Some(&parent_tree), Some(&commit.tree().ok()?), None)
+        .ok()?;
+    diff.find_similar(Some(git2::DiffFindOptions::new().renames(true)))
+        .ok()?;
+    let delta = diff.deltas().find(|delta| {
+        delta.status() == git2::Delta::Renamed && delta.new_file().path() == Some(Path::new(path))
+    })?;
+    Some(delta.old_file().path()?.to_str()?.to_string())
+}
+
 #[allow(dead_code)]
 #[derive(Clone, Debug)]
 pub struct LocalIndex {
@@ -122,16 +261,370 @@ pub struct LocalIndex {
     // likely the model we should use.
     config_paths: TreeConfigPaths,
     config_repo_path: String,
+    mozsearch_path: String,
     tree_name: String,
     // Note: IdentMap internally handles the identifiers db not existing
     ident_map: Option<IdentMap>,
     // But for crossref, it's on us.
     crossref_lookup_map: Option<CrossrefLookupMap>,
     jumpref_lookup_map: Option<CrossrefLookupMap>,
+    // Older indexes won't have this, in which case we fall back to crossref.
+    structured_columns: Option<StructuredColumns>,
+    // Only used for `search_text_scan`.
+    trigram_bloom_index: Option<TrigramBloomIndex>,
     file_lookup_map: FileLookupMap,
+    // Only present if `ingest-query-logs` has been run.
+    query_popularity: Option<Arc<QueryPopularity>>,
+    // Only present if the tree has redaction rules.
+    redactor: Option<Arc<Redactor>>,
+    // Shared by all clones so `--timing` sees the work of every stage.
+    resource_counters: Arc<ResourceCounters>,
 }
 
 impl LocalIndex {
+    /// Look up the HEAD revision of the tree's git repo, opening the repo on
+    /// demand per the comment on `config_paths`.
+    fn head_revision(&self) -> Option<String> {
+        let git_path = self.config_paths.git_path.as_ref()?;
+        let repo = git2::Repository::open(git_path).ok()?;
+        let oid = repo.refname_to_id("HEAD").ok()?;
+        Some(oid.to_string())
+    }
+
+    /// Read the blame for a file from the blame repo, opening the repos on
+    /// demand per the comment on `config_paths`.  The blame repo's HEAD
+    /// corresponds to the indexed revision because `build-blame` brings it up
+    /// to date as part of indexing.
+    fn read_blame(&self, norm_path: &str) -> Result<Vec<BlameLine>> {
+        let git_problem = |e| git_problem("blame", norm_path, e);
+        let (repo, blame_repo) = self.open_git_repos("blame", norm_path)?;
+        let blame_tree = blame_repo
+            .head()
+            .and_then(|head| head.peel_to_tree())
+            .map_err(git_problem)?;
+        let Ok(entry) = blame_tree.get_path(Path::new(norm_path)) else {
+            return Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::NotFound,
+                    ErrorLayer::BadInput,
+                    "No blame for file",
+                )
+                .with_context("path", norm_path),
+            ));
+        };
+
+        // Most files have far fewer revisions than lines.
+        let mut commits: HashMap<String, (String, i64)> = HashMap::new();
+        let mut lines = vec![];
+        for line in read_blob_entry(&blame_repo, &entry).lines() {
+            let rev = LineData::deserialize(line).rev.to_string();
+            let (author, time) = commits
+                .entry(rev.clone())
+                .or_insert_with(|| {
+                    git2::Oid::from_str(&rev)
+                        .and_then(|oid| repo.find_commit(oid))
+                        .map(|commit| {
+                            (
+                                commit.author().name().unwrap_or_default().to_string(),
+                                commit.time().seconds(),
+                            )
+                        })
+                        .unwrap_or_default()
+                })
+                .clone();
+            lines.push(BlameLine { rev, author, time });
+        }
+        Ok(lines)
+    }
+
+    /// Open the tree's git and blame repos on demand per the comment on
+    /// `config_paths`, failing if the tree doesn't have both.
+    fn open_git_repos(
+        &self,
+        what: &str,
+        norm_path: &str,
+    ) -> Result<(git2::Repository, git2::Repository)> {
+        let (Some(git_path), Some(blame_path)) = (
+            self.config_paths.git_path.as_ref(),
+            self.config_paths.git_blame_path.as_ref(),
+        ) else {
+            return Err(ServerError::Unsupported);
+        };
+        let git_problem = |e| git_problem(what, norm_path, e);
+        let repo = git2::Repository::open(git_path).map_err(git_problem)?;
+        let blame_repo = git2::Repository::open(blame_path).map_err(git_problem)?;
+        Ok((repo, blame_repo))
+    }
+
+    /// Walk the blame repo's first-parent history for the commits where the
+    /// file's blame changed, which are exactly the revisions that changed the
+    /// file.
+    fn read_file_log(&self, norm_path: &str, limit: usize) -> Result<Vec<HistoryEntry>> {
+        let git_problem = |e| git_problem("history", norm_path, e);
+        let (repo, blame_repo) = self.open_git_repos("history", norm_path)?;
+        let mut blame_commit = blame_repo
+            .head()
+            .and_then(|head| head.peel_to_commit())
+            .map_err(git_problem)?;
+
+        let mut path = norm_path.to_string();
+        let mut entries = vec![];
+        while entries.len() < limit {
+            let Ok(entry) = blame_commit
+                .tree()
+                .and_then(|tree| tree.get_path(Path::new(&path)))
+            else {
+                break;
+            };
+            let parent = blame_commit.parent(0).ok();
+            let parent_id = parent
+                .as_ref()
+                .and_then(|parent| parent.tree().ok())
+                .and_then(|tree| tree.get_path(Path::new(&path)).ok())
+                .map(|entry| entry.id());
+            if parent_id != Some(entry.id()) {
+                let Some(rev) = blame_commit_rev(&blame_commit) else {
+                    break;
+                };
+                entries.push(history_entry(&repo, rev, &path, None));
+                // The file was either added or renamed by this revision.
+                if parent_id.is_none() {
+                    let renamed_from = repo
+                        .find_commit(rev)
+                        .ok()
+                        .and_then(|commit| renamed_from(&repo, &commit, &path));
+                    match renamed_from {
+                        Some(old_path) => path = old_path,
+                        None => break,
+                    }
+                }
+            }
+            match parent {
+                Some(parent) => blame_commit = parent,
+                None => break,
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Follow a line back through history: find the revision that last
+    /// changed the line per the blame, find the version of the line that
+    /// revision replaced by diffing the file against the revision's first
+    /// parent, and repeat with the blame as of that parent.
+    fn read_line_history(
+        &self,
+        norm_path: &str,
+        line: u32,
+        limit: usize,
+    ) -> Result<Vec<HistoryEntry>> {
+        let git_problem = |e| git_problem("history", norm_path, e);
+        let (repo, blame_repo) = self.open_git_repos("history", norm_path)?;
+        let mut blame_commit = blame_repo
+            .head()
+            .and_then(|head| head.peel_to_commit())
+            .map_err(git_problem)?;
+
+        // Finding the blame commit for a revision means walking the blame
+        // repo, but each revision we look for is older than the last, so we
+        // can resume the walk each time.
+        let mut walk = blame_repo.revwalk().map_err(git_problem)?;
+        walk.push_head().map_err(git_problem)?;
+        walk.set_sorting(git2::Sort::TIME).map_err(git_problem)?;
+        let mut blame_commits: HashMap<git2::Oid, git2::Oid> = HashMap::new();
+        let mut find_blame_commit = |rev: git2::Oid| -> Option<git2::Oid> {
+            if let Some(found) = blame_commits.get(&rev) {
+                return Some(*found);
+            }
+            for oid in walk.by_ref() {
+                let oid = oid.ok()?;
+                let Some(orig) = blame_repo
+                    .find_commit(oid)
+                    .ok()
+                    .and_then(|commit| blame_commit_rev(&commit))
+                else {
+                    continue;
+                };
+                blame_commits.insert(orig, oid);
+                if orig == rev {
+                    return Some(oid);
+                }
+            }
+            None
+        };
+
+        let mut path = norm_path.to_string();
+        let mut lineno = line;
+        let mut entries = vec![];
+        while entries.len() < limit {
+            let Some(blame_line) = blame_commit
+                .tree()
+                .and_then(|tree| tree.get_path(Path::new(&path)))
+                .ok()
+                .and_then(|entry| {
+                    read_blob_entry(&blame_repo, &entry)
+                        .lines()
+                        .nth(lineno as usize - 1)
+                        .map(|line| line.to_string())
+                })
+            else {
+                break;
+            };
+            let blame_line = LineData::deserialize(&blame_line);
+            let (Ok(rev), Ok(rev_lineno)) = (
+                git2::Oid::from_str(&blame_line.rev),
+                blame_line.lineno.parse::<u32>(),
+            ) else {
+                break;
+            };
+            let rev_path = if blame_line.is_path_unchanged() {
+                path.clone()
+            } else {
+                blame_line.path.to_string()
+            };
+            entries.push(history_entry(&repo, rev, &rev_path, Some(rev_lineno)));
+
+            // Find the line in the revision's parent.
+            let Ok(commit) = repo.find_commit(rev) else {
+                break;
+            };
+            let Ok(parent) = commit.parent(0) else {
+                break;
+            };
+            let Ok(new) = read_path_at_commit(&repo, &commit, &rev_path) else {
+                break;
+            };
+            let parent_path = match read_path_at_commit(&repo, &parent, &rev_path) {
+                Ok(_) => Some(rev_path.clone()),
+                Err(_) => renamed_from(&repo, &commit, &rev_path),
+            };
+            let Some(parent_path) = parent_path else {
+                break;
+            };
+            let Ok(old) = read_path_at_commit(&repo, &parent, &parent_path) else {
+                break;
+            };
+            let hunks = side_by_side_diff(&rev_path, &old, &new, 0).map_err(git_problem)?;
+            let mut delta: i64 = 0;
+            let mut changed = None;
+            for hunk in &hunks {
+                if let Some(row) = hunk
+                    .rows
+                    .iter()
+                    .find(|row| row.new_lineno == Some(rev_lineno))
+                {
+                    changed = Some(row);
+                    break;
+                }
+                // Hunks without new lines start after the line `new_start`.
+                if hunk.new_start + hunk.new_lines.max(1) <= rev_lineno {
+                    delta += hunk.old_lines as i64 - hunk.new_lines as i64;
+                }
+            }
+            let old_lineno = match changed {
+                // An added line has no earlier version.
+                Some(row) => row.old_lineno,
+                // The revision didn't change the line itself (ex: it was on
+                // the blame ignore list), so it just moved by the lines before
+                // it.
+                None => Some((rev_lineno as i64 + delta) as u32),
+            };
+            let (Some(old_lineno), Some(parent_blame)) =
+                (old_lineno, find_blame_commit(parent.id()))
+            else {
+                break;
+            };
+            let Ok(parent_blame) = blame_repo.find_commit(parent_blame) else {
+                break;
+            };
+            blame_commit = parent_blame;
+            path = parent_path;
+            lineno = old_lineno;
+        }
+        Ok(entries)
+    }
+
+    /// Read a file as of a revision of the tree's git repo, opening the repo
+    /// on demand per the comment on `config_paths`.
+    fn read_source_at_revision(&self, norm_path: &str, rev: &str) -> Result<String> {
+        let Some(git_path) = self.config_paths.git_path.as_ref() else {
+            return Err(ServerError::Unsupported);
+        };
+        let repo = git2::Repository::open(git_path).map_err(|e| {
+            ServerError::StickyProblem(ErrorDetails::new(
+                ErrorKind::Internal,
+                ErrorLayer::ServerLayer,
+                format!("Unable to open git repo: {}", e),
+            ))
+        })?;
+        let not_found = |message: &str| {
+            ServerError::StickyProblem(
+                ErrorDetails::new(ErrorKind::NotFound, ErrorLayer::BadInput, message)
+                    .with_context("path", norm_path)
+                    .with_context("rev", rev),
+            )
+        };
+        let commit = repo
+            .revparse_single(rev)
+            .and_then(|obj| obj.peel_to_commit())
+            .map_err(|_| not_found("Bad revision"))?;
+        let contents = read_path_at_commit(&repo, &commit, norm_path).map_err(not_found)?;
+        self.resource_counters.add_bytes_read(contents.len() as u64);
+        Ok(contents)
+    }
+
+    fn make_text_matches_by_file(&self, path: Ustr) -> TextMatchesByFile {
+        let (path_kind, classification) = self
+            .file_lookup_map
+            .lookup_file_from_ustr(&path)
+            .map_or_else(
+                || (ustr(""), FileClassification::Normal),
+                |fi| (fi.path_kind, fi.classification),
+            );
+        TextMatchesByFile {
+            file: path,
+            path_kind,
+            classification,
+            matches: vec![],
+        }
+    }
+
+    /// Mask any secrets in the lines of a file's text search results.  We match
+    /// the redaction rules against the whole file, which we load if the caller
+    /// doesn't have it, so that lines in the middle of multi-line secrets like
+    /// private keys get masked too.
+    async fn redact_search_matches(
+        &self,
+        file_matches: &mut TextMatchesByFile,
+        source: Option<&str>,
+    ) {
+        let Some(redactor) = &self.redactor else {
+            return;
+        };
+        let path = file_matches.file.as_str();
+        let loaded;
+        let source = match source {
+            Some(source) => Some(source),
+            None => {
+                loaded = self.fetch_raw_source(path).await.ok();
+                loaded.as_deref()
+            }
+        };
+        let lines = file_matches
+            .matches
+            .iter_mut()
+            .map(|m| (m.line_num as usize, &mut m.line_str));
+        match source {
+            Some(source) => redactor.redact_lines("search", path, source, lines),
+            None => {
+                for (lineno, line) in lines {
+                    if let Some(masked) = redactor.redact_line("search", path, lineno, line) {
+                        *line = masked;
+                    }
+                }
+            }
+        }
+    }
+
     fn normalize_and_validate_path<'a>(&self, sf_path: &'a str) -> Result<&'a str> {
         // We normalize off any leading "/" mainly to support our test cases
         // being able to use "/" to indicate they're interested in a root dir.
@@ -142,6 +635,14 @@ impl LocalIndex {
 
         Ok(norm_path)
     }
+
+    /// The path of a (normalized) source file, which may be generated.
+    fn source_file_path(&self, norm_path: &str) -> String {
+        match norm_path.strip_prefix("__GENERATED__/") {
+            Some(objdir_path) => format!("{}/{}", self.config_paths.objdir_path, objdir_path),
+            None => format!("{}/{}", self.config_paths.files_path, norm_path),
+        }
+    }
 }
 
 #[async_trait]
@@ -153,6 +654,13 @@ impl AbstractServer for LocalIndex {
     fn tree_info(&self) -> Result<TreeInfo> {
         Ok(TreeInfo {
             name: self.tree_name.clone(),
+            path_sets: self.config_paths.path_sets.clone(),
+            query_presets: self.config_paths.query_presets.clone(),
+            post_index_hooks: self.config_paths.post_index_hooks.clone(),
+            precompute: self.config_paths.precompute.clone(),
+            access_control: self.config_paths.access_control.clone(),
+            revision: self.head_revision(),
+            precompress_html: self.config_paths.precompress_html,
         })
     }
 
@@ -163,6 +671,24 @@ impl AbstractServer for LocalIndex {
                 self.config_paths.index_path, sf_path
             )),
             SearchfoxIndexRoot::ConfigRepo => Ok(format!("{}/{}", self.config_repo_path, sf_path)),
+            SearchfoxIndexRoot::DeploymentState => match &self.config_paths.state_path {
+                Some(state_path) => Ok(format!("{}/{}", state_path, sf_path)),
+                None => Err(ServerError::StickyProblem(
+                    ErrorDetails::new(
+                        ErrorKind::IndexMissingCapability,
+                        ErrorLayer::ConfigLayer,
+                        "The tree has no state_path configured".to_string(),
+                    )
+                    .with_context("path", sf_path),
+                )),
+            },
+            SearchfoxIndexRoot::FileDescription => Ok(format!(
+                "{}/description/{}",
+                self.config_paths.index_path, sf_path
+            )),
+            SearchfoxIndexRoot::IndexRoot => {
+                Ok(format!("{}/{}", self.config_paths.index_path, sf_path))
+            }
             SearchfoxIndexRoot::IndexTemplates => Ok(format!(
                 "{}/templates/{}",
                 self.config_paths.index_path, sf_path
@@ -171,6 +697,17 @@ impl AbstractServer for LocalIndex {
                 "{}/pages/{}",
                 self.config_paths.index_path, sf_path
             )),
+            SearchfoxIndexRoot::PipelineCache => Ok(format!(
+                "{}/pipeline-cache/{}",
+                self.config_paths.index_path, sf_path
+            )),
+            SearchfoxIndexRoot::PostIndexHooks => Ok(format!(
+                "{}/post-index-hooks/{}",
+                self.config_paths.index_path, sf_path
+            )),
+            SearchfoxIndexRoot::StaticAssets => {
+                Ok(format!("{}/static/{}", self.mozsearch_path, sf_path))
+            }
             SearchfoxIndexRoot::UncompressedDirectoryListing => Ok(format!(
                 "{}/dir/{}/index.html",
                 self.config_paths.index_path, sf_path
@@ -181,31 +718,58 @@ impl AbstractServer for LocalIndex {
     async fn fetch_raw_analysis<'a>(&self, sf_path: &str) -> Result<BoxStream<'a, Value>> {
         let norm_path = self.normalize_and_validate_path(sf_path)?;
         let full_path = self.translate_path(SearchfoxIndexRoot::CompressedAnalysis, norm_path)?;
-        let values = read_gzipped_ndjson_from_file(&full_path).await?;
+        let (values, bytes_read) = read_gzipped_ndjson_from_file(&full_path).await?;
+        self.resource_counters.add_bytes_read(bytes_read);
         Ok(Box::pin(tokio_stream::iter(values)))
     }
 
     async fn fetch_raw_source(&self, sf_path: &str) -> Result<String> {
         let norm_path = self.normalize_and_validate_path(sf_path)?;
-        let full_path = if norm_path.starts_with("__GENERATED__/") {
-            format!(
-                "{}/{}",
-                self.config_paths.objdir_path,
-                norm_path.strip_prefix("__GENERATED__/").unwrap()
-            )
-        } else {
-            format!("{}/{}", self.config_paths.files_path, norm_path)
-        };
+        let full_path = self.source_file_path(norm_path);
 
         let mut f = File::open(full_path).await?;
         let mut raw_str = String::new();
         f.read_to_string(&mut raw_str).await?;
+        self.resource_counters.add_bytes_read(raw_str.len() as u64);
         Ok(raw_str)
     }
 
+    async fn fetch_source_at_revision(&self, sf_path: &str, rev: &str) -> Result<String> {
+        let norm_path = self.normalize_and_validate_path(sf_path)?;
+        tokio::task::block_in_place(|| self.read_source_at_revision(norm_path, rev))
+    }
+
+    async fn fetch_raw_bytes(
+        &self,
+        sf_path: &str,
+        offset: u64,
+        length: usize,
+    ) -> Result<RawBytesChunk> {
+        let norm_path = self.normalize_and_validate_path(sf_path)?;
+        let full_path = self.source_file_path(norm_path);
+
+        let mut f = File::open(full_path).await?;
+        let file_size = f.metadata().await?.len();
+        let offset = offset.min(file_size);
+        f.seek(SeekFrom::Start(offset)).await?;
+        let mut data = vec![];
+        f.take(length as u64).read_to_end(&mut data).await?;
+        self.resource_counters.add_bytes_read(data.len() as u64);
+        Ok(RawBytesChunk {
+            offset,
+            data,
+            file_size,
+        })
+    }
+
     async fn fetch_formatted_lines(&self, sf_path: &str) -> Result<(Vec<String>, String)> {
         let norm_path = self.normalize_and_validate_path(sf_path)?;
-        let source = self.fetch_raw_source(sf_path).await?;
+        let mut source = self.fetch_raw_source(sf_path).await?;
+        if let Some(redactor) = &self.redactor {
+            if let Some(masked) = redactor.redact_file("render", norm_path, &source) {
+                source = masked;
+            }
+        }
         let analysis_path =
             self.translate_path(SearchfoxIndexRoot::CompressedAnalysis, norm_path)?;
         let analysis = read_analyses(&[analysis_path], &mut read_source);
@@ -231,9 +795,9 @@ impl AbstractServer for LocalIndex {
 
     async fn fetch_html(&self, root: HtmlFileRoot, sf_path: &str) -> Result<String> {
         let norm_path = self.normalize_and_validate_path(sf_path)?;
-        let (full_path, is_gzipped) = match root {
+        let (full_path, prefer_gzipped) = match root {
             HtmlFileRoot::FormattedFile => (
-                format!("{}/file/{}.gz", self.config_paths.index_path, norm_path),
+                format!("{}/file/{}", self.config_paths.index_path, norm_path),
                 true,
             ),
             HtmlFileRoot::FormattedDir => {
@@ -252,7 +816,7 @@ impl AbstractServer for LocalIndex {
                 };
                 (
                     format!(
-                        "{}/dir/{}index.html.gz",
+                        "{}/dir/{}index.html",
                         self.config_paths.index_path, norm_path
                     ),
                     true,
@@ -264,32 +828,14 @@ impl AbstractServer for LocalIndex {
             ),
         };
 
-        if !is_gzipped {
-            let mut f = File::open(full_path).await?;
-            let mut raw_str = String::new();
-            f.read_to_string(&mut raw_str).await?;
-            return Ok(raw_str);
-        }
-
-        let mut f = File::open(full_path).await?;
-        let mut buffer = Vec::new();
-        f.read_to_end(&mut buffer).await?;
-
-        // When we want to go async here,
-        // https://github.com/rust-lang/flate2-rs/pull/214 suggests that we want
-        // to use the `async-compression` crate.
-        let mut gz = GzDecoder::new(&buffer[..]);
-
-        let mut raw_str = String::new();
-        gz.read_to_string(&mut raw_str)?;
-
-        Ok(raw_str)
+        read_html_file(&full_path, prefer_gzipped, sf_path, &self.resource_counters).await
     }
 
     async fn crossref_lookup(&self, symbol: &str, extra_processing: bool) -> Result<Value> {
         let now = Instant::now();
+        self.resource_counters.add_symbols_fetched(1);
         let result = match &self.crossref_lookup_map {
-            Some(crossref) => crossref.lookup(symbol),
+            Some(crossref) => crossref.lookup(symbol).and_then(migrate_crossref_meta),
             None => Ok(Value::Null),
         };
         trace!(
@@ -304,8 +850,157 @@ impl AbstractServer for LocalIndex {
         }
     }
 
+    async fn crossref_lookup_keys(&self, symbol: &str, keys: &[&str]) -> Result<Value> {
+        let now = Instant::now();
+        self.resource_counters.add_symbols_fetched(1);
+        let result = match &self.crossref_lookup_map {
+            Some(crossref) => crossref
+                .lookup_keys(symbol, keys)
+                .and_then(migrate_crossref_meta),
+            None => Ok(Value::Null),
+        };
+        trace!(
+            duration_us = now.elapsed().as_micros() as u64,
+            "crossref_lookup_keys: {}",
+            symbol
+        );
+        result
+    }
+
+    async fn structured_column_lookup(
+        &self,
+        symbol: &str,
+        column: StructuredColumn,
+    ) -> Result<Value> {
+        let Some(columns) = &self.structured_columns else {
+            let info = self.crossref_lookup_keys(symbol, &["meta"]).await?;
+            return Ok(column_from_meta(&info["meta"], column));
+        };
+        self.resource_counters.add_symbols_fetched(1);
+        Ok(columns.lookup(symbol, column))
+    }
+
+    async fn scan_structured_column(
+        &self,
+        column: StructuredColumn,
+        pred: &(dyn Fn(&Value) -> bool + Send + Sync),
+        limit: usize,
+    ) -> Result<Vec<String>> {
+        let Some(columns) = &self.structured_columns else {
+            return Err(ServerError::Unsupported);
+        };
+        Ok(tokio::task::block_in_place(|| {
+            columns
+                .scan(column)
+                .filter(|(_, val)| pred(val))
+                .map(|(sym, _)| sym.to_string())
+                .take(limit)
+                .collect()
+        }))
+    }
+
+    async fn list_crossref_symbols(&self, limit: usize) -> Result<Vec<String>> {
+        let Some(crossref) = &self.crossref_lookup_map else {
+            return Err(ServerError::Unsupported);
+        };
+        Ok(tokio::task::block_in_place(|| {
+            crossref
+                .symbols()
+                .map(|sym| sym.to_string())
+                .take(limit)
+                .collect()
+        }))
+    }
+
+    async fn fetch_blame(&self, sf_path: &str) -> Result<Vec<BlameLine>> {
+        let norm_path = self.normalize_and_validate_path(sf_path)?;
+        tokio::task::block_in_place(|| self.read_blame(norm_path))
+    }
+
+    async fn fetch_file_log(&self, sf_path: &str, limit: usize) -> Result<Vec<HistoryEntry>> {
+        let norm_path = self.normalize_and_validate_path(sf_path)?;
+        tokio::task::block_in_place(|| self.read_file_log(norm_path, limit))
+    }
+
+    async fn fetch_line_history(
+        &self,
+        sf_path: &str,
+        line: u32,
+        limit: usize,
+    ) -> Result<Vec<HistoryEntry>> {
+        let norm_path = self.normalize_and_validate_path(sf_path)?;
+        tokio::task::block_in_place(|| self.read_line_history(norm_path, line, limit))
+    }
+
+    fn resource_counters(&self) -> Option<Arc<ResourceCounters>> {
+        Some(self.resource_counters.clone())
+    }
+
+    fn capabilities(&self) -> IndexCapabilities {
+        let index_has =
+            |name: &str| Path::new(&format!("{}/{}", self.config_paths.index_path, name)).exists();
+        IndexCapabilities {
+            crossref: self.crossref_lookup_map.is_some(),
+            jumpref: self.jumpref_lookup_map.is_some(),
+            identifiers: self.ident_map.is_some(),
+            identifier_substrings: self
+                .ident_map
+                .as_ref()
+                .map_or(false, |ident_map| ident_map.has_suffixes()),
+            structured: self.structured_columns.is_some(),
+            source: Path::new(&self.config_paths.files_path).is_dir(),
+            analysis: index_has("analysis"),
+            html: index_has("file"),
+            trigram_bloom: self.trigram_bloom_index.is_some(),
+            blame: self
+                .config_paths
+                .git_blame_path
+                .as_ref()
+                .map_or(false, |path| Path::new(path).is_dir()),
+            coverage: index_has("code-coverage-report.json"),
+            query_popularity: self.query_popularity.is_some(),
+        }
+    }
+
+    fn symbol_query_hits(&self, symbol: &str) -> u64 {
+        self.query_popularity
+            .as_ref()
+            .map_or(0, |popularity| popularity.symbol_hits(symbol))
+    }
+
+    fn path_query_hits(&self, path: &str) -> u64 {
+        self.query_popularity
+            .as_ref()
+            .map_or(0, |popularity| popularity.path_hits(path))
+    }
+
+    fn popular_queries(&self, limit: usize) -> Result<PopularHits> {
+        let Some(popularity) = &self.query_popularity else {
+            return Ok(PopularHits::default());
+        };
+        let to_owned = |entries: Vec<(&String, u64)>| {
+            entries
+                .into_iter()
+                .map(|(key, hits)| (key.clone(), hits))
+                .collect()
+        };
+        Ok(PopularHits {
+            symbols: to_owned(popularity.top_symbols(limit)),
+            paths: to_owned(popularity.top_paths(limit)),
+        })
+    }
+
+    async fn index_manifest(&self) -> Result<Option<IndexManifest>> {
+        let path = format!(
+            "{}/{}",
+            self.config_paths.index_path, INDEX_MANIFEST_FILENAME
+        );
+        Ok(tokio::task::block_in_place(|| load_index_manifest(&path))?)
+    }
+
     async fn jumpref_lookup(&self, symbol: &str) -> Result<Value> {
         let now = Instant::now();
+        self.resource_counters.add_symbols_fetched(1);
         let result = match &self.jumpref_lookup_map {
             Some(jumpref) => jumpref.lookup(symbol),
             None => Ok(Value::Null),
@@ -353,6 +1048,30 @@ impl AbstractServer for LocalIndex {
         }
     }
 
+    async fn search_identifier_substrings(
+        &self,
+        needle: &str,
+        ignore_case: bool,
+        match_limit: usize,
+    ) -> Result<Vec<(Ustr, Ustr)>> {
+        let Some(ident_map) = self.ident_map.as_ref().filter(|m| m.has_suffixes()) else {
+            return Err(ServerError::Unsupported);
+        };
+        let now = Instant::now();
+        let results: Vec<(Ustr, Ustr)> = ident_map
+            .lookup_substring(needle, ignore_case, match_limit)
+            .into_iter()
+            .map(|ir| (ir.symbol, ir.id))
+            .collect();
+        trace!(
+            duration_us = now.elapsed().as_micros() as u64,
+            result_count = results.len(),
+            "search_identifier_substrings: {}",
+            needle
+        );
+        Ok(results)
+    }
+
     async fn search_text(
         &self,
         pattern: &str,
@@ -408,18 +1127,7 @@ impl AbstractServer for LocalIndex {
             let right = result.bounds.as_ref().map_or(0, |b| b.right);
             by_file
                 .entry(result.path.to_string())
-                .or_insert_with(|| {
-                    let path = ustr(&result.path);
-                    let path_kind = self
-                        .file_lookup_map
-                        .lookup_file_from_ustr(&path)
-                        .map_or_else(|| ustr(""), |fi| fi.path_kind);
-                    TextMatchesByFile {
-                        file: path,
-                        path_kind,
-                        matches: vec![],
-                    }
-                })
+                .or_insert_with(|| self.make_text_matches_by_file(ustr(&result.path)))
                 .matches
                 .push(TextMatchInFile {
                     line_num: result.line_number as u32,
@@ -430,9 +1138,105 @@ impl AbstractServer for LocalIndex {
                     line_str: result.line,
                 });
         }
+        for file_matches in by_file.values_mut() {
+            self.redact_search_matches(file_matches, None).await;
+        }
 
         Ok(TextMatches {
             by_file: by_file.into_values().collect(),
+            page: None,
+        })
+    }
+
+    async fn search_text_scan(
+        &self,
+        pattern: &str,
+        literal: Option<&str>,
+        fold_case: bool,
+        path: &str,
+        limit: usize,
+    ) -> Result<TextMatches> {
+        let now = Instant::now();
+
+        let Some(blooms) = &self.trigram_bloom_index else {
+            return Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::IndexMissingCapability,
+                    ErrorLayer::ConfigLayer,
+                    "text scanning requires a trigram-bloom index",
+                )
+                .with_hint(
+                    "Re-index the tree to generate its `trigram-bloom` file, or use \
+                     `search-text` with a running codesearch server.",
+                ),
+            ));
+        };
+        let re = RegexBuilder::new(pattern)
+            .case_insensitive(fold_case)
+            .build()?;
+        let path_re = if path.is_empty() {
+            None
+        } else {
+            Some(Regex::new(path)?)
+        };
+
+        let mut by_file = vec![];
+        let mut match_count = 0;
+        let mut skipped_count = 0;
+        for (file_path, may_match) in blooms.candidates(literal) {
+            if limit > 0 && match_count >= limit {
+                break;
+            }
+            if let Some(path_re) = &path_re {
+                if !path_re.is_match(file_path) {
+                    continue;
+                }
+            }
+            if !may_match {
+                skipped_count += 1;
+                continue;
+            }
+            // Binary and missing files are just not searchable.
+            let Ok(source) = self.fetch_raw_source(file_path).await else {
+                continue;
+            };
+
+            let mut file_matches = self.make_text_matches_by_file(ustr(file_path));
+            for (i_line, line) in source.lines().enumerate() {
+                if limit > 0 && match_count >= limit {
+                    break;
+                }
+                // Like livegrep, we only report the first match on a line.
+                if let Some(m) = re.find(line) {
+                    file_matches.matches.push(TextMatchInFile {
+                        line_num: i_line as u32 + 1,
+                        bounds: TextBounds {
+                            start: m.start() as i32,
+                            end_exclusive: m.end() as i32,
+                        },
+                        line_str: line.to_string(),
+                    });
+                    match_count += 1;
+                }
+            }
+            if !file_matches.matches.is_empty() {
+                self.redact_search_matches(&mut file_matches, Some(&source))
+                    .await;
+                by_file.push(file_matches);
+            }
+        }
+
+        trace!(
+            duration_us = now.elapsed().as_micros() as u64,
+            skipped_count,
+            result_count = match_count,
+            "search_text_scan: scan completed: {}",
+            pattern
+        );
+
+        Ok(TextMatches {
+            by_file,
+            page: None,
         })
     }
 
@@ -449,6 +1253,7 @@ fn fab_server(
     tree_config: TreeConfig,
     tree_name: &str,
     config_repo_path: &str,
+    mozsearch_path: &str,
 ) -> Result<Box<dyn AbstractServer + Send + Sync>> {
     let ident_path = format!("{}/identifiers", tree_config.paths.index_path);
     let ident_map = IdentMap::new(&ident_path);
@@ -463,6 +1268,12 @@ fn fab_server(
 
     let jumpref_lookup_map = CrossrefLookupMap::new(&jumpref_path, &jumpref_extra_path);
 
+    let structured_columns_path = format!("{}/structured-columns", tree_config.paths.index_path);
+    let structured_columns = StructuredColumns::new(&structured_columns_path);
+
+    let trigram_bloom_path = format!("{}/trigram-bloom", tree_config.paths.index_path);
+    let trigram_bloom_index = TrigramBloomIndex::new(&trigram_bloom_path);
+
     let file_lookup_path = format!(
         "{}/concise-per-file-info.json",
         tree_config.paths.index_path
@@ -470,15 +1281,34 @@ fn fab_server(
 
     let file_lookup_map = FileLookupMap::new(&file_lookup_path);
 
+    let query_popularity_path = format!(
+        "{}/{}",
+        tree_config.paths.index_path, QUERY_POPULARITY_FILENAME
+    );
+    // Popularity only affects ranking, so a broken store shouldn't stop us.
+    let query_popularity = match QueryPopularity::load(&query_popularity_path) {
+        Ok(popularity) => popularity.map(Arc::new),
+        Err(e) => {
+            warn!("Unable to load {}: {}", query_popularity_path, e);
+            None
+        }
+    };
+
     Ok(Box::new(LocalIndex {
         // We don't need the blame_map and hg_map (yet)
         config_paths: tree_config.paths,
         config_repo_path: config_repo_path.to_string(),
+        mozsearch_path: mozsearch_path.to_string(),
         tree_name: tree_name.to_string(),
         ident_map,
         crossref_lookup_map,
         jumpref_lookup_map,
+        structured_columns,
+        trigram_bloom_index,
         file_lookup_map,
+        query_popularity,
+        redactor: tree_config.redactor.map(Arc::new),
+        resource_counters: Arc::new(ResourceCounters::default()),
     }))
 }
 
@@ -490,24 +1320,117 @@ pub fn make_local_server(
     let tree_config = match config.trees.remove(tree_name) {
         Some(t) => t,
         None => {
-            return Err(ServerError::StickyProblem(ErrorDetails {
-                layer: ErrorLayer::BadInput,
-                message: format!("bad tree name: {}", &tree_name),
-            }))
+            return Err(ServerError::StickyProblem(ErrorDetails::new(
+                ErrorKind::NotFound,
+                ErrorLayer::BadInput,
+                format!("bad tree name: {}", &tree_name),
+            )))
         }
     };
 
-    fab_server(tree_config, tree_name, &config.config_repo_path)
+    fab_server(
+        tree_config,
+        tree_name,
+        &config.config_repo_path,
+        &config.mozsearch_path,
+    )
 }
 
 pub fn make_all_local_servers(
     config_path: &str,
 ) -> Result<BTreeMap<String, Box<dyn AbstractServer + Send + Sync>>> {
-    let config = load(config_path, false, None, None, None);
+    let config = try_load(config_path, false, None, None, None).map_err(|e| {
+        ServerError::StickyProblem(ErrorDetails::new(
+            ErrorKind::Internal,
+            ErrorLayer::ConfigLayer,
+            e,
+        ))
+    })?;
     let mut servers = BTreeMap::new();
     for (tree_name, tree_config) in config.trees {
-        let server = fab_server(tree_config, &tree_name, &config.config_repo_path)?;
+        let server = fab_server(
+            tree_config,
+            &tree_name,
+            &config.config_repo_path,
+            &config.mozsearch_path,
+        )?;
         servers.insert(tree_name, server);
     }
     Ok(servers)
 }
+
+#[tokio::test]
+async fn test_read_html_file() {
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    let dir = std::env::temp_dir().join(format!("read-html-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = |name: &str| dir.join(name).to_str().unwrap().to_string();
+    let write_gz = |name: &str, contents: &str| {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(contents.as_bytes()).unwrap();
+        std::fs::write(path(name), encoder.finish().unwrap()).unwrap();
+    };
+    let counters = ResourceCounters::default();
+
+    // Only the gzipped copy, whichever we prefer.
+    write_gz("gz-only.html.gz", "gzipped");
+    for prefer_gzipped in [true, false] {
+        assert_eq!(
+            read_html_file(&path("gz-only.html"), prefer_gzipped, "gz", &counters)
+                .await
+                .unwrap(),
+            "gzipped"
+        );
+    }
+
+    // Only the plain file, whichever we prefer.
+    std::fs::write(path("plain-only.html"), "plain").unwrap();
+    for prefer_gzipped in [true, false] {
+        assert_eq!(
+            read_html_file(&path("plain-only.html"), prefer_gzipped, "p", &counters)
+                .await
+                .unwrap(),
+            "plain"
+        );
+    }
+
+    // With both, the preference picks, so a zero-length nginx marker next to
+    // the gzipped copy doesn't win.
+    std::fs::write(path("both.html"), "").unwrap();
+    write_gz("both.html.gz", "gzipped");
+    assert_eq!(
+        read_html_file(&path("both.html"), true, "both", &counters)
+            .await
+            .unwrap(),
+        "gzipped"
+    );
+    assert_eq!(
+        read_html_file(&path("both.html"), false, "both", &counters)
+            .await
+            .unwrap(),
+        ""
+    );
+
+    // A corrupt gzipped copy is an error rather than falling back.
+    std::fs::write(path("corrupt.html.gz"), "not gzip").unwrap();
+    match read_html_file(&path("corrupt.html"), true, "corrupt", &counters).await {
+        Err(ServerError::StickyProblem(details)) => {
+            assert_eq!(details.kind, ErrorKind::Internal);
+            assert_eq!(details.context["path"], "corrupt");
+        }
+        _ => panic!("expected a corrupt gzip error"),
+    }
+
+    match read_html_file(&path("missing.html"), true, "missing", &counters).await {
+        Err(ServerError::StickyProblem(details)) => {
+            assert_eq!(details.kind, ErrorKind::NotFound);
+            assert_eq!(details.context["path"], "missing");
+        }
+        _ => panic!("expected a not found error"),
+    }
+
+    assert!(counters.snapshot().bytes_read > 0);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -1,11 +1,15 @@
+mod access_control;
 mod lazy_crossref;
 mod local_index;
 mod remote_server;
 mod server_interface;
 
+pub use access_control::{restrict_server, AccessPolicy};
 pub use local_index::{make_all_local_servers, make_local_server};
-pub use remote_server::make_remote_server;
+pub use remote_server::{make_remote_server, RetryPolicy};
 pub use server_interface::{
-    AbstractServer, ErrorDetails, ErrorLayer, FileMatch, FileMatches, HtmlFileRoot, Result,
-    SearchfoxIndexRoot, ServerError, TextMatches, TextMatchesByFile, TreeInfo,
+    AbstractServer, BlameLine, EndpointMetrics, ErrorDetails, ErrorKind, ErrorLayer, FileMatch,
+    FileMatches, HistoryEntry, HtmlFileRoot, IndexCapabilities, PageInfo, PopularHits,
+    RawBytesChunk, RawSourceRange, ResourceCounters, ResourceUsage, Result, SearchfoxIndexRoot,
+    ServerError, TextBounds, TextMatchInFile, TextMatches, TextMatchesByFile, TreeInfo,
 };
@@ -1,93 +1,308 @@
 use async_trait::async_trait;
 use futures_core::stream::BoxStream;
 use serde_json::{from_str, Value};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+use tracing::{trace, warn};
 use url::{ParseError, Url};
 use ustr::Ustr;
 
 use super::{
     server_interface::{
-        AbstractServer, ErrorDetails, ErrorLayer, FileMatches, Result, SearchfoxIndexRoot,
-        ServerError,
+        AbstractServer, EndpointMetrics, ErrorDetails, ErrorKind, ErrorLayer, FileMatches,
+        IndexCapabilities, Result, SearchfoxIndexRoot, ServerError,
     },
     HtmlFileRoot, TextMatches, TreeInfo,
 };
+use crate::file_format::config::{AccessControlConfig, PrecomputeConfig};
 
-/// reqwest won't return an error for an unhappy status code itself; someone
-/// would need to call `Response::error_from_status`, so for now we'll generally
-/// assume everything is some kind of transient problem.
+/// Only timeouts and failures to connect are transient problems worth
+/// retrying.  Anything else, like a body we couldn't decode, would just fail
+/// the same way again.
 impl From<reqwest::Error> for ServerError {
     fn from(err: reqwest::Error) -> ServerError {
-        ServerError::TransientProblem(ErrorDetails {
-            layer: ErrorLayer::ServerLayer,
-            message: err.to_string(),
-        })
+        if let Some(status) = err.status() {
+            status_error(status)
+        } else if err.is_timeout() {
+            ServerError::TransientProblem(ErrorDetails::new(
+                ErrorKind::UpstreamTimeout,
+                ErrorLayer::ServerLayer,
+                format!("Timed out: {}", err),
+            ))
+        } else if err.is_connect() {
+            ServerError::TransientProblem(
+                ErrorDetails::new(
+                    ErrorKind::UpstreamTimeout,
+                    ErrorLayer::ServerLayer,
+                    format!("Unable to connect: {}", err),
+                )
+                .with_hint("Check the server URL and that the server is up."),
+            )
+        } else {
+            ServerError::StickyProblem(ErrorDetails::new(
+                ErrorKind::Internal,
+                ErrorLayer::ServerLayer,
+                err.to_string(),
+            ))
+        }
     }
 }
 
 impl From<ParseError> for ServerError {
     fn from(err: ParseError) -> ServerError {
-        ServerError::StickyProblem(ErrorDetails {
-            layer: ErrorLayer::BadInput,
-            message: err.to_string(),
-        })
+        ServerError::StickyProblem(ErrorDetails::new(
+            ErrorKind::BadQuery,
+            ErrorLayer::BadInput,
+            err.to_string(),
+        ))
     }
 }
 
-#[allow(dead_code)]
+/// How a remote server retries failed requests so that a flaky connection
+/// doesn't fail a whole CI check.  Transient failures (connection failures,
+/// timeouts, and 5xx statuses) are retried with exponential backoff until we've
+/// made `max_retries` retries or the request's `budget` runs out.  After
+/// `breaker_threshold` consecutive transient failures of an endpoint, that
+/// endpoint's circuit breaker opens and requests to it fail immediately for
+/// `breaker_cooldown` so that a server that's down doesn't make every request
+/// wait out its retries.  Once the cooldown passes, requests are let through
+/// again, and the breaker reopens if the next one fails too.
 #[derive(Clone, Debug)]
-struct RemoteServer {
-    tree_name: String,
-    server_base_url: Url,
-    tree_base_url: Url,
-    source_base_url: Url,
-    raw_analysis_base_url: Url,
-    search_url: Url,
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    /// The delay before the first retry, which doubles for each retry after.
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// The timeout for each individual request.
+    pub request_timeout: Duration,
+    /// The total time we're willing to spend on a request, including retries.
+    pub budget: Duration,
+    pub breaker_threshold: u32,
+    pub breaker_cooldown: Duration,
 }
 
-async fn get(url: Url) -> Result<reqwest::Response> {
-    //println!("Using URL {}", url);
-    let res = reqwest::get(url).await?;
-
-    if !res.status().is_success() {
-        if res.status().is_server_error() {
-            return Err(ServerError::TransientProblem(ErrorDetails {
-                layer: ErrorLayer::ServerLayer,
-                message: format!("Server status of {}", res.status()),
-            }));
-        } else {
-            return Err(ServerError::StickyProblem(ErrorDetails {
-                layer: ErrorLayer::DataLayer,
-                message: format!("Server status of {}", res.status()),
-            }));
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(4),
+            request_timeout: Duration::from_secs(30),
+            budget: Duration::from_secs(120),
+            breaker_threshold: 5,
+            breaker_cooldown: Duration::from_secs(30),
         }
     }
+}
 
-    Ok(res)
+impl RetryPolicy {
+    fn backoff(&self, retry: u32) -> Duration {
+        self.initial_backoff
+            .checked_mul(1 << retry.min(16))
+            .map_or(self.max_backoff, |backoff| backoff.min(self.max_backoff))
+    }
 }
 
-async fn get_json(url: Url) -> Result<reqwest::Response> {
-    let client = reqwest::Client::new();
-    let res = client
-        .get(url)
-        .header("Accept", "application/json")
-        .send()
-        .await?;
-
-    if !res.status().is_success() {
-        if res.status().is_server_error() {
-            return Err(ServerError::TransientProblem(ErrorDetails {
-                layer: ErrorLayer::ServerLayer,
-                message: format!("Server status of {}", res.status()),
-            }));
-        } else {
-            return Err(ServerError::StickyProblem(ErrorDetails {
-                layer: ErrorLayer::DataLayer,
-                message: format!("Server status of {}", res.status()),
-            }));
+#[derive(Debug, Default)]
+struct EndpointState {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+    metrics: EndpointMetrics,
+}
+
+/// The HTTP client shared by all clones of a `RemoteServer` so that they share
+/// circuit breakers and metrics.
+#[derive(Debug)]
+struct RemoteClient {
+    client: reqwest::Client,
+    policy: RetryPolicy,
+    endpoints: Mutex<BTreeMap<&'static str, EndpointState>>,
+}
+
+/// The error for an unsuccessful status.  Only 5xx statuses are transient; a
+/// 4xx means the server understood us and retrying won't change its answer.
+fn status_error(status: reqwest::StatusCode) -> ServerError {
+    let message = format!("Server status of {}", status);
+    if status.is_server_error() {
+        ServerError::TransientProblem(ErrorDetails::new(
+            ErrorKind::UpstreamTimeout,
+            ErrorLayer::ServerLayer,
+            message,
+        ))
+    } else if status == reqwest::StatusCode::NOT_FOUND {
+        ServerError::StickyProblem(ErrorDetails::new(
+            ErrorKind::NotFound,
+            ErrorLayer::DataLayer,
+            message,
+        ))
+    } else if status == reqwest::StatusCode::UNAUTHORIZED
+        || status == reqwest::StatusCode::FORBIDDEN
+    {
+        ServerError::StickyProblem(
+            ErrorDetails::new(ErrorKind::BadQuery, ErrorLayer::BadInput, message)
+                .with_hint("The remote server requires credentials we don't have."),
+        )
+    } else {
+        ServerError::StickyProblem(ErrorDetails::new(
+            ErrorKind::BadQuery,
+            ErrorLayer::BadInput,
+            message,
+        ))
+    }
+}
+
+fn check_status(res: reqwest::Response) -> Result<reqwest::Response> {
+    if res.status().is_success() {
+        Ok(res)
+    } else {
+        Err(status_error(res.status()))
+    }
+}
+
+impl RemoteClient {
+    fn new(policy: RetryPolicy) -> Self {
+        RemoteClient {
+            client: reqwest::Client::new(),
+            policy,
+            endpoints: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Fail fast if the endpoint's circuit breaker is open.
+    fn check_breaker(&self, endpoint: &'static str, now: Instant) -> Result<()> {
+        let mut endpoints = self.endpoints.lock().unwrap();
+        let state = endpoints.entry(endpoint).or_default();
+        match state.open_until {
+            Some(open_until) if now < open_until => {
+                state.metrics.short_circuited += 1;
+                Err(ServerError::TransientProblem(
+                    ErrorDetails::new(
+                        ErrorKind::UpstreamTimeout,
+                        ErrorLayer::ServerLayer,
+                        format!(
+                            "Not contacting the {} endpoint after {} consecutive failures",
+                            endpoint, state.consecutive_failures
+                        ),
+                    )
+                    .with_context("endpoint", endpoint)
+                    .with_hint("The remote server seems to be down; try again later."),
+                ))
+            }
+            _ => Ok(()),
         }
     }
 
-    Ok(res)
+    /// Update the endpoint's metrics and circuit breaker for a request that
+    /// either got a response from the server or failed transiently.
+    fn record_attempt(&self, endpoint: &'static str, now: Instant, retry: bool, failed: bool) {
+        let mut endpoints = self.endpoints.lock().unwrap();
+        let state = endpoints.entry(endpoint).or_default();
+        state.metrics.requests += 1;
+        if retry {
+            state.metrics.retries += 1;
+        }
+        if !failed {
+            state.consecutive_failures = 0;
+            state.open_until = None;
+            return;
+        }
+        state.metrics.failures += 1;
+        state.consecutive_failures += 1;
+        if state.consecutive_failures >= self.policy.breaker_threshold {
+            warn!(
+                endpoint,
+                failures = state.consecutive_failures,
+                "Opening remote circuit breaker"
+            );
+            state.open_until = Some(now + self.policy.breaker_cooldown);
+            state.metrics.breaker_trips += 1;
+        }
+    }
+
+    fn record_budget_exhausted(&self, endpoint: &'static str) {
+        let mut endpoints = self.endpoints.lock().unwrap();
+        endpoints
+            .entry(endpoint)
+            .or_default()
+            .metrics
+            .budget_exhausted += 1;
+    }
+
+    /// GET the URL from the given endpoint (ex: "search"), retrying timeouts,
+    /// connection failures, and 5xx statuses per our `RetryPolicy`.  Anything
+    /// else, like a 4xx status, is a sticky error that we don't retry and that
+    /// doesn't count against the circuit breaker, since the server was able to
+    /// answer.
+    async fn get(
+        &self,
+        endpoint: &'static str,
+        url: Url,
+        accept: Option<&str>,
+    ) -> Result<reqwest::Response> {
+        let started = Instant::now();
+        let mut retry = 0;
+        loop {
+            self.check_breaker(endpoint, Instant::now())?;
+
+            let remaining = self.policy.budget.saturating_sub(started.elapsed());
+            let mut req = self
+                .client
+                .get(url.clone())
+                .timeout(self.policy.request_timeout.min(remaining));
+            if let Some(accept) = accept {
+                req = req.header("Accept", accept);
+            }
+            let result = match req.send().await {
+                Ok(res) => check_status(res),
+                Err(err) => Err(err.into()),
+            };
+
+            let failed = matches!(result, Err(ServerError::TransientProblem(_)));
+            self.record_attempt(endpoint, Instant::now(), retry > 0, failed);
+            let details = match result {
+                Err(ServerError::TransientProblem(details)) => details,
+                result => return result,
+            };
+
+            let delay = self.policy.backoff(retry);
+            let out_of_budget = started.elapsed() + delay >= self.policy.budget;
+            if retry >= self.policy.max_retries || out_of_budget {
+                if out_of_budget {
+                    self.record_budget_exhausted(endpoint);
+                }
+                return Err(ServerError::TransientProblem(
+                    details
+                        .with_context("endpoint", endpoint)
+                        .with_context("attempts", (retry + 1).to_string()),
+                ));
+            }
+            trace!(endpoint, retry, ?delay, "Retrying remote request");
+            sleep(delay).await;
+            retry += 1;
+        }
+    }
+
+    fn metrics(&self) -> BTreeMap<String, EndpointMetrics> {
+        let endpoints = self.endpoints.lock().unwrap();
+        endpoints
+            .iter()
+            .map(|(endpoint, state)| (endpoint.to_string(), state.metrics.clone()))
+            .collect()
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Debug)]
+struct RemoteServer {
+    tree_name: String,
+    server_base_url: Url,
+    tree_base_url: Url,
+    source_base_url: Url,
+    raw_analysis_base_url: Url,
+    search_url: Url,
+    client: Arc<RemoteClient>,
 }
 
 #[async_trait]
@@ -99,6 +314,13 @@ impl AbstractServer for RemoteServer {
     fn tree_info(&self) -> Result<TreeInfo> {
         Ok(TreeInfo {
             name: self.tree_name.clone(),
+            path_sets: BTreeMap::new(),
+            query_presets: BTreeMap::new(),
+            post_index_hooks: BTreeMap::new(),
+            precompute: PrecomputeConfig::default(),
+            access_control: AccessControlConfig::default(),
+            revision: None,
+            precompress_html: false,
         })
     }
 
@@ -109,7 +331,12 @@ impl AbstractServer for RemoteServer {
 
     async fn fetch_raw_analysis<'a>(&self, sf_path: &str) -> Result<BoxStream<'a, Value>> {
         let url = self.raw_analysis_base_url.join(sf_path)?;
-        let raw_str = get(url).await?.text().await?;
+        let raw_str = self
+            .client
+            .get("raw-analysis", url, None)
+            .await?
+            .text()
+            .await?;
         let values: Result<Vec<Value>> = raw_str
             .lines()
             .map(|s| from_str(s).map_err(ServerError::from))
@@ -140,7 +367,7 @@ impl AbstractServer for RemoteServer {
         // have any security-ish things to worry about for a public web server.
 
         let url = self.source_base_url.join(norm_path)?;
-        let html = get(url).await?.text().await?;
+        let html = self.client.get("source", url, None).await?.text().await?;
         Ok(html)
     }
 
@@ -193,17 +420,35 @@ impl AbstractServer for RemoteServer {
         Err(ServerError::Unsupported)
     }
 
+    fn capabilities(&self) -> IndexCapabilities {
+        IndexCapabilities {
+            analysis: true,
+            html: true,
+            ..IndexCapabilities::default()
+        }
+    }
+
+    fn request_metrics(&self) -> BTreeMap<String, EndpointMetrics> {
+        self.client.metrics()
+    }
+
     async fn perform_query(&self, q: &str) -> Result<Value> {
         let mut url = self.search_url.clone();
         // If adding more parameters, considering using `query_pairs_mut()`.
         url.set_query(Some(&format!("q={}", q)));
-        let raw_str = get_json(url).await?.text().await?;
+        let raw_str = self
+            .client
+            .get("search", url, Some("application/json"))
+            .await?
+            .text()
+            .await?;
         match from_str(&raw_str) {
             Ok(json) => Ok(json),
-            Err(err) => Err(ServerError::StickyProblem(ErrorDetails {
-                layer: ErrorLayer::ServerLayer,
-                message: err.to_string(),
-            })),
+            Err(err) => Err(ServerError::StickyProblem(ErrorDetails::new(
+                ErrorKind::Internal,
+                ErrorLayer::ServerLayer,
+                err.to_string(),
+            ))),
         }
     }
 }
@@ -211,6 +456,7 @@ impl AbstractServer for RemoteServer {
 pub fn make_remote_server(
     server_base_url: Url,
     tree_name: &str,
+    retry_policy: RetryPolicy,
 ) -> Result<Box<dyn AbstractServer + Send + Sync>> {
     let tree_base_url = server_base_url.join(&format!("{}/", tree_name))?;
     let source_base_url = tree_base_url.join("source/")?;
@@ -224,5 +470,53 @@ pub fn make_remote_server(
         source_base_url,
         raw_analysis_base_url,
         search_url,
+        client: Arc::new(RemoteClient::new(retry_policy)),
     }))
 }
+
+#[test]
+fn test_retry_policy() {
+    let policy = RetryPolicy {
+        breaker_threshold: 2,
+        ..RetryPolicy::default()
+    };
+    assert_eq!(policy.backoff(0), Duration::from_millis(250));
+    assert_eq!(policy.backoff(2), Duration::from_secs(1));
+    assert_eq!(policy.backoff(10), policy.max_backoff);
+    assert_eq!(policy.backoff(100), policy.max_backoff);
+
+    let client = RemoteClient::new(policy);
+    let now = Instant::now();
+    client.record_attempt("search", now, false, true);
+    assert!(client.check_breaker("search", now).is_ok());
+    client.record_attempt("search", now, true, true);
+    assert!(client.check_breaker("search", now).is_err());
+    // Other endpoints have their own breakers.
+    assert!(client.check_breaker("source", now).is_ok());
+    // Once the cooldown passes we try again, and success closes the breaker.
+    let later = now + client.policy.breaker_cooldown;
+    assert!(client.check_breaker("search", later).is_ok());
+    client.record_attempt("search", later, false, false);
+    assert!(client.check_breaker("search", later).is_ok());
+
+    let metrics = &client.metrics()["search"];
+    assert_eq!(metrics.requests, 3);
+    assert_eq!(metrics.retries, 1);
+    assert_eq!(metrics.failures, 2);
+    assert_eq!(metrics.breaker_trips, 1);
+    assert_eq!(metrics.short_circuited, 1);
+}
+
+#[test]
+fn test_status_error() {
+    let kind = |status: u16| match status_error(reqwest::StatusCode::from_u16(status).unwrap()) {
+        ServerError::TransientProblem(details) => (true, details.kind),
+        ServerError::StickyProblem(details) => (false, details.kind),
+        _ => unreachable!(),
+    };
+    assert_eq!(kind(500), (true, ErrorKind::UpstreamTimeout));
+    assert_eq!(kind(503), (true, ErrorKind::UpstreamTimeout));
+    assert_eq!(kind(404), (false, ErrorKind::NotFound));
+    assert_eq!(kind(403), (false, ErrorKind::BadQuery));
+    assert_eq!(kind(400), (false, ErrorKind::BadQuery));
+}
@@ -1,22 +1,46 @@
 use async_trait::async_trait;
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
+use axum::Json;
 use futures_core::stream::BoxStream;
 use serde::Serialize;
-use serde_json::Value;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use ustr::{ustr, Ustr};
 
+use crate::file_format::analysis::{FileClassification, SchemaError};
+use crate::file_format::config::{
+    AccessControlConfig, PostIndexHookConfig, PrecomputeConfig, QueryPresetConfig,
+};
+use crate::file_format::index_manifest::IndexManifest;
 use crate::file_format::repo_data_ingestion::ConcisePerFileInfo;
+use crate::file_format::structured_columns::{column_from_meta, StructuredColumn};
+use crate::languages::select_formatting;
 
 pub type Result<T> = std::result::Result<T, ServerError>;
 
 // JSON parse errors are sticky data problems.
 impl From<serde_json::Error> for ServerError {
     fn from(err: serde_json::Error) -> ServerError {
-        ServerError::StickyProblem(ErrorDetails {
-            layer: ErrorLayer::DataLayer,
-            message: err.to_string(),
-        })
+        ServerError::StickyProblem(ErrorDetails::new(
+            ErrorKind::Internal,
+            ErrorLayer::DataLayer,
+            err.to_string(),
+        ))
+    }
+}
+
+// Schema problems mean the index was produced by an incompatible version of
+// the indexing tools, which is a sticky data problem.
+impl From<SchemaError> for ServerError {
+    fn from(err: SchemaError) -> ServerError {
+        ServerError::StickyProblem(
+            ErrorDetails::new(ErrorKind::Internal, ErrorLayer::DataLayer, err.to_string())
+                .with_hint("Re-index the tree with the same version of the tools."),
+        )
     }
 }
 
@@ -26,10 +50,14 @@ impl From<serde_json::Error> for ServerError {
 // relating to user input.
 impl From<regex::Error> for ServerError {
     fn from(err: regex::Error) -> ServerError {
-        ServerError::StickyProblem(ErrorDetails {
-            layer: ErrorLayer::BadInput,
-            message: format!("bad regexp: {}", err),
-        })
+        ServerError::StickyProblem(
+            ErrorDetails::new(
+                ErrorKind::BadQuery,
+                ErrorLayer::BadInput,
+                format!("bad regexp: {}", err),
+            )
+            .with_hint("Escape regexp metacharacters like `(` and `.` to match them literally."),
+        )
     }
 }
 
@@ -44,26 +72,98 @@ impl From<tokio::task::JoinError> for ServerError {
             std::panic::resume_unwind(reason);
         }
         */
-        ServerError::StickyProblem(ErrorDetails {
-            layer: ErrorLayer::RuntimeInvariantViolation,
-            message: format!("task panicked?: {}", err),
-        })
+        ServerError::StickyProblem(ErrorDetails::new(
+            ErrorKind::Internal,
+            ErrorLayer::RuntimeInvariantViolation,
+            format!("task panicked?: {}", err),
+        ))
     }
 }
 
 impl From<liquid::Error> for ServerError {
     fn from(err: liquid::Error) -> ServerError {
-        ServerError::StickyProblem(ErrorDetails {
-            layer: ErrorLayer::ConfigLayer,
-            message: format!("Liquid error: {}", err),
-        })
+        ServerError::StickyProblem(ErrorDetails::new(
+            ErrorKind::Internal,
+            ErrorLayer::ConfigLayer,
+            format!("Liquid error: {}", err),
+        ))
     }
 }
 
+/// Respond with the status code for the error's kind and a JSON body that
+/// clients can present, like:
+/// `{"kind": "NotFound", "layer": "BadInput", "message": "No such preset",
+///   "context": {"preset": "foo"}, "hint": null, "transient": false}`
 impl IntoResponse for ServerError {
     fn into_response(self) -> Response {
-        let body = format!("Error: {:#?}", self);
-        (StatusCode::INTERNAL_SERVER_ERROR, body).into_response()
+        let kind = self.kind();
+        let body = match &self {
+            ServerError::StickyProblem(details) | ServerError::TransientProblem(details) => {
+                let mut body = serde_json::to_value(details).unwrap_or_default();
+                body["transient"] = Value::Bool(matches!(self, ServerError::TransientProblem(_)));
+                body
+            }
+            ServerError::Unsupported => json!({
+                "kind": kind,
+                "message": "Unsupported operation",
+                "hint": UNSUPPORTED_HINT,
+                "transient": false,
+            }),
+        };
+        (kind.status_code(), Json(body)).into_response()
+    }
+}
+
+const UNSUPPORTED_HINT: &str =
+    "This server or index doesn't support the operation; try a local index built with current tools.";
+
+impl ServerError {
+    /// The kind of the error, treating `Unsupported` as a missing capability.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            ServerError::StickyProblem(details) | ServerError::TransientProblem(details) => {
+                details.kind
+            }
+            ServerError::Unsupported => ErrorKind::IndexMissingCapability,
+        }
+    }
+}
+
+/// A user-facing rendition of the error for CLI output, like:
+///
+/// ```text
+/// Not found: No such preset (preset: foo)
+/// Hint: ...
+/// ```
+impl fmt::Display for ServerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let details = match self {
+            ServerError::StickyProblem(details) | ServerError::TransientProblem(details) => details,
+            ServerError::Unsupported => {
+                return write!(
+                    f,
+                    "{}: Unsupported operation\nHint: {}",
+                    self.kind(),
+                    UNSUPPORTED_HINT
+                );
+            }
+        };
+        write!(f, "{}: {}", details.kind, details.message)?;
+        if !details.context.is_empty() {
+            let context: Vec<String> = details
+                .context
+                .iter()
+                .map(|(k, v)| format!("{}: {}", k, v))
+                .collect();
+            write!(f, " ({})", context.join(", "))?;
+        }
+        if let ServerError::TransientProblem(_) = self {
+            write!(f, "\nThis may go away if retried.")?;
+        }
+        if let Some(hint) = &details.hint {
+            write!(f, "\nHint: {}", hint)?;
+        }
+        Ok(())
     }
 }
 
@@ -80,8 +180,52 @@ pub enum HtmlFileRoot {
     FormattedTemplate,
 }
 
+/// What went wrong from the user's perspective, which determines the HTTP status
+/// code of the response and how clients should present the error.  This is
+/// orthogonal to the `ErrorLayer`, which is about where things went wrong.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+pub enum ErrorKind {
+    /// The symbol, path, preset, or other named thing doesn't exist.
+    NotFound,
+    /// The query or pipeline is malformed, like a command being given input
+    /// it can't consume or an invalid regexp.
+    BadQuery,
+    /// The index lacks the data needed to answer (see `IndexCapabilities`) or
+    /// the server doesn't support the operation at all.
+    IndexMissingCapability,
+    /// A server we depend on (ex: a remote searchfox) failed or timed out, so
+    /// retrying may help.
+    UpstreamTimeout,
+    /// Something is wrong with the server, its config, or the index data.
+    Internal,
+}
+
+impl ErrorKind {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            ErrorKind::NotFound => StatusCode::NOT_FOUND,
+            ErrorKind::BadQuery => StatusCode::BAD_REQUEST,
+            ErrorKind::IndexMissingCapability => StatusCode::NOT_IMPLEMENTED,
+            ErrorKind::UpstreamTimeout => StatusCode::GATEWAY_TIMEOUT,
+            ErrorKind::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ErrorKind::NotFound => "Not found",
+            ErrorKind::BadQuery => "Bad query",
+            ErrorKind::IndexMissingCapability => "Missing index capability",
+            ErrorKind::UpstreamTimeout => "Upstream failure",
+            ErrorKind::Internal => "Internal error",
+        })
+    }
+}
+
 /// Express whether the error seems to be happening in the server or the data.
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub enum ErrorLayer {
     /// The request itself has structural issues like a malformed URL.  This
     /// should not be used for cases where the user input results in a search
@@ -120,8 +264,10 @@ pub enum ErrorLayer {
 /// ServerError payload to provide details about what went wrong for
 /// investigation purposes.  In the future, this could wrap the
 /// underlying errors we've seen.
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct ErrorDetails {
+    /// What went wrong from the user's perspective.
+    pub kind: ErrorKind,
     /// Attempt to distinguish failures due to server bugs from failures due to
     /// indexing bugs.  For example a 500 response from a server would be a
     /// `ServerLayer` problem, but if a 404 was instead returned, that would be
@@ -129,6 +275,33 @@ pub struct ErrorDetails {
     pub layer: ErrorLayer,
     /// Stringified version of the lower level error.
     pub message: String,
+    /// Structured context like the symbol, path, or preset involved, so that
+    /// clients don't have to parse it out of the message.
+    pub context: BTreeMap<&'static str, String>,
+    /// What the user can do about the error, if we have a suggestion.
+    pub hint: Option<String>,
+}
+
+impl ErrorDetails {
+    pub fn new(kind: ErrorKind, layer: ErrorLayer, message: impl Into<String>) -> Self {
+        ErrorDetails {
+            kind,
+            layer,
+            message: message.into(),
+            context: BTreeMap::new(),
+            hint: None,
+        }
+    }
+
+    pub fn with_context(mut self, key: &'static str, value: impl Into<String>) -> Self {
+        self.context.insert(key, value.into());
+        self
+    }
+
+    pub fn with_hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
 }
 
 /// Does a retry makes sense or not?
@@ -149,6 +322,66 @@ pub enum ServerError {
     Unsupported,
 }
 
+/// The blame for one line of a file, as returned by `AbstractServer::fetch_blame`.
+#[derive(Clone, Debug, Serialize)]
+pub struct BlameLine {
+    /// The git revision that last changed the line.
+    pub rev: String,
+    /// The author of that revision.
+    pub author: String,
+    /// The commit time of that revision, in seconds since the epoch.
+    pub time: i64,
+}
+
+/// A revision in the history of a file or of a line, as returned by
+/// `AbstractServer::fetch_file_log` and `AbstractServer::fetch_line_history`.
+#[derive(Clone, Debug, Serialize)]
+pub struct HistoryEntry {
+    pub rev: String,
+    pub author: String,
+    /// The commit time, in seconds since the epoch.
+    pub time: i64,
+    /// The first line of the commit message.
+    pub summary: String,
+    /// The file's path as of the revision, which differs from the requested
+    /// path if the file has since been renamed.
+    pub path: String,
+    /// For line history, the 1-based number of the line as of the revision.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<u32>,
+}
+
+/// A chunk of a file's raw bytes, as returned by
+/// `AbstractServer::fetch_raw_bytes`.
+#[derive(Clone, Debug)]
+pub struct RawBytesChunk {
+    /// The offset of the first byte of `data` within the file.
+    pub offset: u64,
+    /// The bytes, which may be fewer than requested at the end of the file.
+    pub data: Vec<u8>,
+    /// The size of the whole file.
+    pub file_size: u64,
+}
+
+/// Raw source for a range of a file's lines, as returned by
+/// `AbstractServer::fetch_raw_source_range`.
+#[derive(Clone, Debug, Serialize)]
+pub struct RawSourceRange {
+    pub path: String,
+    /// The git revision the tree was indexed at, if known.
+    pub revision: Option<String>,
+    /// The language's markdown slug (ex: "cpp"), or "" if it has none.
+    pub syntax: String,
+    /// The 1-based first line of the range.
+    pub start_line: u32,
+    /// The 1-based last line of the range, inclusive.
+    pub end_line: u32,
+    /// The number of lines in the whole file.
+    pub total_lines: u32,
+    /// The exact bytes of the lines, including their line terminators.
+    pub contents: String,
+}
+
 /// Livegrep/codesearch bounds
 #[derive(Serialize)]
 pub struct TextBounds {
@@ -169,6 +402,8 @@ pub struct TextMatchInFile {
 pub struct TextMatchesByFile {
     pub file: Ustr,
     pub path_kind: Ustr,
+    #[serde(skip_serializing_if = "FileClassification::is_normal")]
+    pub classification: FileClassification,
     pub matches: Vec<TextMatchInFile>,
 }
 
@@ -176,6 +411,9 @@ pub struct TextMatchesByFile {
 #[derive(Serialize)]
 pub struct TextMatches {
     pub by_file: Vec<TextMatchesByFile>,
+    /// Present if the results were paginated or sampled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<PageInfo>,
 }
 
 #[derive(Serialize)]
@@ -197,6 +435,29 @@ impl FileMatch {
 #[derive(Serialize)]
 pub struct FileMatches {
     pub file_matches: Vec<FileMatch>,
+    /// Present if the results were paginated or sampled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<PageInfo>,
+}
+
+/// Where a page of results sits in the full (sorted) result set so that
+/// callers like the HTTP layer can fetch the next page deterministically.  See
+/// `cmd_pipeline/pagination.rs`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PageInfo {
+    /// The number of results before pagination.
+    pub total: usize,
+    /// The number of results skipped, including those before the cursor.
+    pub offset: usize,
+    /// The number of results in this page.
+    pub count: usize,
+    /// Pass this as `--cursor` to get the next page, if there is one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+    /// True if this page is an evenly spaced sample of the results rather than
+    /// a contiguous run of them.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub sampled: bool,
 }
 
 pub enum SearchfoxIndexRoot {
@@ -205,6 +466,15 @@ pub enum SearchfoxIndexRoot {
     CompressedAnalysis,
     /// The root of the config repo.
     ConfigRepo,
+    /// The tree's `state_path` dir, which lives outside of the index so that
+    /// its contents (ex: symbol annotations) survive reindexing.
+    DeploymentState,
+    /// The "description" dir under the index root, holding the JSON file
+    /// descriptions (and binary file metadata) written during ingestion.
+    FileDescription,
+    /// The index root itself, for maintenance commands like `gc-index` that
+    /// need to walk its output directories.
+    IndexRoot,
     /// The templates dir under the index root, home of the search template and
     /// the rendered help.html.  This differs from IndexPages because the pages
     /// directory is intended to be exposed directly to the web, whereas if we
@@ -214,12 +484,157 @@ pub enum SearchfoxIndexRoot {
     /// exposed to the web in its entirety, which differs from IndexTemplates,
     /// which is not.  This is the home of the settings page.
     IndexPages,
+    /// The "pipeline-cache" dir under the index root, where `precompute`
+    /// stores the outputs of query presets run for hot symbols.
+    PipelineCache,
+    /// The "post-index-hooks" dir under the index root, where the outputs of
+    /// each hook's runs are stored in a sub-directory named for the hook.
+    PostIndexHooks,
+    /// mozsearch's "static" dir, which nginx serves as `/{tree}/static/`.
+    /// This is shared by all trees.
+    StaticAssets,
     /// Directory listings.
     UncompressedDirectoryListing,
 }
 
 pub struct TreeInfo {
     pub name: String,
+    /// Named path sets from the tree's config for use by `PathFilterArgs`.
+    /// This will be empty for remote servers.
+    pub path_sets: BTreeMap<String, Vec<String>>,
+    /// Query presets from the tree's config, not including the built-in
+    /// presets.  This will be empty for remote servers.
+    pub query_presets: BTreeMap<String, QueryPresetConfig>,
+    /// Post-index hooks from the tree's config.  This will be empty for remote
+    /// servers.
+    pub post_index_hooks: BTreeMap<String, PostIndexHookConfig>,
+    /// The tree's `precompute` config.  This will be empty for remote servers.
+    pub precompute: PrecomputeConfig,
+    /// The tree's `access_control` config.  This will be empty for remote
+    /// servers.
+    pub access_control: AccessControlConfig,
+    /// The git revision the tree was indexed at, for building revision-pinned
+    /// permalinks.  This will be None for remote servers and trees without a
+    /// git repo.
+    pub revision: Option<String>,
+    /// Whether rendered HTML should also be written gzipped, per the tree's
+    /// `precompress_html` config.  This will be false for remote servers.
+    pub precompress_html: bool,
+}
+
+/// Which optional parts of an index are present, as probed by
+/// `AbstractServer::capabilities`.  Older and minimal indexes (ex: test trees or
+/// trees indexed without a blame repo) lack some of these, and commands should
+/// check them to degrade with informative placeholders instead of erroring out.
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct IndexCapabilities {
+    /// The crossref database, without which symbol lookups find nothing.
+    pub crossref: bool,
+    /// The jumpref database.
+    pub jumpref: bool,
+    /// The identifiers database used by identifier searches.
+    pub identifiers: bool,
+    /// The suffix array of the identifiers database used by identifier
+    /// substring searches.
+    pub identifier_substrings: bool,
+    /// The `structured-columns` file, which is generated when the analyses
+    /// have structured records (ex: class layouts).
+    pub structured: bool,
+    /// The raw source files, which are needed for formatted source lines.
+    pub source: bool,
+    /// The per-file analysis records.
+    pub analysis: bool,
+    /// The pre-rendered source and directory listing HTML.
+    pub html: bool,
+    /// The trigram bloom skip index used by `search_text_scan`.
+    pub trigram_bloom: bool,
+    /// A blame repository for the tree's git repository.
+    pub blame: bool,
+    /// Code coverage data.
+    pub coverage: bool,
+    /// Query popularity data from `ingest-query-logs`.
+    pub query_popularity: bool,
+}
+
+/// Request statistics for one endpoint of a server that talks to another
+/// server over the network, as reported by `AbstractServer::request_metrics`.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct EndpointMetrics {
+    /// Requests made, including retries.
+    pub requests: u64,
+    /// Requests that were retries of an earlier failed request.
+    pub retries: u64,
+    /// Requests that failed transiently, whether or not a retry succeeded.
+    pub failures: u64,
+    /// How many times the endpoint's circuit breaker opened.
+    pub breaker_trips: u64,
+    /// Requests failed without being made because the breaker was open.
+    pub short_circuited: u64,
+    /// Requests given up on because their timeout budget ran out.
+    pub budget_exhausted: u64,
+}
+
+/// Running totals of the work a server has done, as exposed by
+/// `AbstractServer::resource_counters`.  The counters are shared by all clones
+/// of the server, so callers measure their own usage by taking the difference
+/// of snapshots from before and after, which will include the work of anything
+/// running concurrently.
+#[derive(Debug, Default)]
+pub struct ResourceCounters {
+    symbols_fetched: AtomicU64,
+    bytes_read: AtomicU64,
+    cache_hits: AtomicU64,
+}
+
+impl ResourceCounters {
+    pub fn add_symbols_fetched(&self, count: u64) {
+        self.symbols_fetched.fetch_add(count, Ordering::Relaxed);
+    }
+
+    pub fn add_bytes_read(&self, bytes: u64) {
+        self.bytes_read.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn add_cache_hit(&self) {
+        self.cache_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> ResourceUsage {
+        ResourceUsage {
+            symbols_fetched: self.symbols_fetched.load(Ordering::Relaxed),
+            bytes_read: self.bytes_read.load(Ordering::Relaxed),
+            cache_hits: self.cache_hits.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// A snapshot of `ResourceCounters` or the difference between two snapshots.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct ResourceUsage {
+    /// Crossref, jumpref, and structured column lookups.
+    pub symbols_fetched: u64,
+    /// Bytes read from source, analysis, and HTML files.
+    pub bytes_read: u64,
+    /// Outputs served from the pipeline cache populated by `precompute`.
+    pub cache_hits: u64,
+}
+
+impl ResourceUsage {
+    pub fn since(&self, earlier: &ResourceUsage) -> ResourceUsage {
+        ResourceUsage {
+            symbols_fetched: self.symbols_fetched.saturating_sub(earlier.symbols_fetched),
+            bytes_read: self.bytes_read.saturating_sub(earlier.bytes_read),
+            cache_hits: self.cache_hits.saturating_sub(earlier.cache_hits),
+        }
+    }
+}
+
+/// The most popular symbols and paths per the ingested query logs, as pairs of
+/// the symbol or path and its number of hits.
+#[derive(Debug, Default, Serialize)]
+pub struct PopularHits {
+    pub symbols: Vec<(String, u64)>,
+    pub paths: Vec<(String, u64)>,
 }
 
 /// Unified exposure for interacting with a local Searchfox index on disk or
@@ -303,6 +718,88 @@ pub trait AbstractServer {
     /// we can actually check the source file out if needed.
     async fn fetch_raw_source(&self, sf_path: &str) -> Result<String>;
 
+    /// Fetch the contents of a source file as of a revision of the tree's git
+    /// history, ex: to diff it against another revision.  Only supported by
+    /// local indices with a git repo.
+    async fn fetch_source_at_revision(&self, _sf_path: &str, _rev: &str) -> Result<String> {
+        Err(ServerError::Unsupported)
+    }
+
+    /// Fetch up to `length` bytes of a raw source file starting at `offset`,
+    /// for looking at binary files a page at a time (ex: `hex-dump`) without
+    /// reading the whole file.  Only supported by local indices.
+    async fn fetch_raw_bytes(
+        &self,
+        _sf_path: &str,
+        _offset: u64,
+        _length: usize,
+    ) -> Result<RawBytesChunk> {
+        Err(ServerError::Unsupported)
+    }
+
+    /// Fetch the raw source for the 1-based inclusive range of `lines` of a
+    /// file (or the whole file), along with the indexed revision and syntax
+    /// metadata, for tooling that needs the exact bytes rather than HTML (ex:
+    /// LSP hover or export bundles).  If `rev` is given, it must be the
+    /// revision the tree was indexed at, since that's the only revision we
+    /// have the source for.
+    async fn fetch_raw_source_range(
+        &self,
+        sf_path: &str,
+        rev: Option<&str>,
+        lines: Option<(u32, u32)>,
+    ) -> Result<RawSourceRange> {
+        let revision = self.tree_info()?.revision;
+        if let Some(rev) = rev {
+            if !revision
+                .as_deref()
+                .map_or(false, |indexed| indexed.starts_with(rev))
+            {
+                return Err(ServerError::StickyProblem(
+                    ErrorDetails::new(
+                        ErrorKind::NotFound,
+                        ErrorLayer::DataLayer,
+                        format!("Revision {} is not the indexed revision", rev),
+                    )
+                    .with_context("path", sf_path)
+                    .with_hint("Only the source at the indexed revision is available."),
+                ));
+            }
+        }
+
+        let contents = self.fetch_raw_source(sf_path).await?;
+        let total_lines = contents.split_inclusive('\n').count() as u32;
+        let (start_line, end_line) = match lines {
+            None => (1, total_lines),
+            Some((start, end)) if start >= 1 && start <= end => (start, end.min(total_lines)),
+            Some((start, end)) => {
+                return Err(ServerError::StickyProblem(
+                    ErrorDetails::new(
+                        ErrorKind::BadQuery,
+                        ErrorLayer::BadInput,
+                        format!("Bad line range {}-{}", start, end),
+                    )
+                    .with_context("path", sf_path),
+                ));
+            }
+        };
+        let contents = contents
+            .split_inclusive('\n')
+            .skip(start_line as usize - 1)
+            .take(end_line.saturating_sub(start_line - 1) as usize)
+            .collect();
+
+        Ok(RawSourceRange {
+            path: sf_path.to_string(),
+            revision,
+            syntax: select_formatting(sf_path).markdown_slug().to_string(),
+            start_line,
+            end_line,
+            total_lines,
+            contents,
+        })
+    }
+
     /// Fetch the lines in the rendered HTML file.
     ///
     /// Returns a tuple of a list of lines, 0-th item for line 1,
@@ -322,6 +819,31 @@ pub trait AbstractServer {
     /// specific use-cases that know they need the new experimental data.
     async fn crossref_lookup(&self, symbol: &str, extra_processing: bool) -> Result<Value>;
 
+    /// Like `crossref_lookup` without extra processing, but only the given
+    /// top-level keys (ex: "meta") of the crossref entry are returned.  Local
+    /// indices are able to avoid parsing the rest of the entry, which can be
+    /// very large for popular symbols.
+    async fn crossref_lookup_keys(&self, symbol: &str, keys: &[&str]) -> Result<Value> {
+        let mut info = self.crossref_lookup(symbol, false).await?;
+        if let Value::Object(obj) = &mut info {
+            obj.retain(|key, _| keys.contains(&key.as_str()));
+        }
+        Ok(info)
+    }
+
+    /// Retrieve a single column of the structured info for the given symbol,
+    /// returning Null if the symbol has no structured info.  Local indices
+    /// read this from the columnar `structured-columns` file when available,
+    /// avoiding deserializing the entire crossref entry.
+    async fn structured_column_lookup(
+        &self,
+        symbol: &str,
+        column: StructuredColumn,
+    ) -> Result<Value> {
+        let info = self.crossref_lookup_keys(symbol, &["meta"]).await?;
+        Ok(column_from_meta(&info["meta"], column))
+    }
+
     /// Retrieve the JSON contents of the jumpref database for the given
     /// symbol.
     async fn jumpref_lookup(&self, symbol: &str) -> Result<Value>;
@@ -373,6 +895,19 @@ pub trait AbstractServer {
         match_limit: usize,
     ) -> Result<Vec<(Ustr, Ustr)>>;
 
+    /// Like `search_identifiers`, but returning the identifiers that contain
+    /// the needle anywhere, so "ScrollContainer" finds "nsScrollContainerFrame".
+    /// Local indices use the suffix array of the identifiers file that is built
+    /// at index time.
+    async fn search_identifier_substrings(
+        &self,
+        _needle: &str,
+        _ignore_case: bool,
+        _match_limit: usize,
+    ) -> Result<Vec<(Ustr, Ustr)>> {
+        Err(ServerError::Unsupported)
+    }
+
     /// Given an re2 search pattern and additional config info, run a
     /// livegrep codesearch against an already-running codesearch server.  In
     /// the future while our rust code may be responsible for starting the
@@ -386,5 +921,116 @@ pub trait AbstractServer {
         limit: usize,
     ) -> Result<TextMatches>;
 
+    /// Run a fulltext search by directly reading the source files rather than
+    /// using livegrep, which is useful for indexes that don't have a running
+    /// codesearch server.  If `literal` is provided, it must be a substring that
+    /// every match of `pattern` contains, which allows local indices to
+    /// consult their trigram bloom skip index to avoid reading files that
+    /// can't possibly match.
+    async fn search_text_scan(
+        &self,
+        _pattern: &str,
+        _literal: Option<&str>,
+        _fold_case: bool,
+        _path: &str,
+        _limit: usize,
+    ) -> Result<TextMatches> {
+        Err(ServerError::Unsupported)
+    }
+
+    /// Return every symbol with structured info whose value for `column`
+    /// satisfies `pred`, in symbol order, stopping after `limit` matches.
+    /// This requires scanning every structured record, so it's only supported
+    /// by local indices with a `structured-columns` file.
+    async fn scan_structured_column(
+        &self,
+        _column: StructuredColumn,
+        _pred: &(dyn Fn(&Value) -> bool + Send + Sync),
+        _limit: usize,
+    ) -> Result<Vec<String>> {
+        Err(ServerError::Unsupported)
+    }
+
+    /// Return the symbols in the crossref database in sorted order, stopping
+    /// after `limit`.  This requires scanning the entire crossref database, so
+    /// it's only supported by local indices and is intended for offline checks
+    /// like `verify-index`.
+    async fn list_crossref_symbols(&self, _limit: usize) -> Result<Vec<String>> {
+        Err(ServerError::Unsupported)
+    }
+
+    /// Fetch the blame for a file at the indexed revision, one entry per line
+    /// of the file.  This requires the tree's git and blame repositories (see
+    /// `IndexCapabilities::blame`), so it's only supported by local indices.
+    async fn fetch_blame(&self, _sf_path: &str) -> Result<Vec<BlameLine>> {
+        Err(ServerError::Unsupported)
+    }
+
+    /// Fetch up to `limit` of the revisions that changed a file, newest first,
+    /// following the file through renames.  This walks the first-parent
+    /// history of the blame repo, so changes made on merged branches are
+    /// attributed to their merge.  Only supported by local indices with blame.
+    async fn fetch_file_log(&self, _sf_path: &str, _limit: usize) -> Result<Vec<HistoryEntry>> {
+        Err(ServerError::Unsupported)
+    }
+
+    /// Fetch up to `limit` of the revisions that changed a (1-based) line of
+    /// a file, newest first, following the line back through earlier versions
+    /// of itself and the file through renames.  The history ends at the
+    /// revision that added the line.  Only supported by local indices with
+    /// blame.
+    async fn fetch_line_history(
+        &self,
+        _sf_path: &str,
+        _line: u32,
+        _limit: usize,
+    ) -> Result<Vec<HistoryEntry>> {
+        Err(ServerError::Unsupported)
+    }
+
+    /// Probe which optional parts of the index are present.  Remote servers
+    /// report only what they can fetch from the web server.
+    fn capabilities(&self) -> IndexCapabilities {
+        IndexCapabilities::default()
+    }
+
+    /// Return how often the symbol (or identifier) was asked about per the
+    /// query logs ingested by `ingest-query-logs`, or 0 if it wasn't or there
+    /// is no popularity data, as is always the case for remote servers.
+    fn symbol_query_hits(&self, _symbol: &str) -> u64 {
+        0
+    }
+
+    /// Return how often the file was viewed per the ingested query logs, or 0.
+    fn path_query_hits(&self, _path: &str) -> u64 {
+        0
+    }
+
+    /// Return request statistics keyed by endpoint.  Only remote servers make
+    /// requests, so local indices have nothing to report.
+    fn request_metrics(&self) -> BTreeMap<String, EndpointMetrics> {
+        BTreeMap::new()
+    }
+
+    /// Return the counters of the work this server has done on behalf of
+    /// pipeline commands, if it keeps them.  Only local indices do.
+    fn resource_counters(&self) -> Option<Arc<ResourceCounters>> {
+        None
+    }
+
+    /// Return up to `limit` of the most popular symbols and paths per the
+    /// ingested query logs, most popular first.  Only supported by local
+    /// indices.
+    fn popular_queries(&self, _limit: usize) -> Result<PopularHits> {
+        Err(ServerError::Unsupported)
+    }
+
+    /// Return the manifest describing what the index was generated from, or
+    /// None if the index has no manifest because it predates them.  Only
+    /// supported by local indices.
+    async fn index_manifest(&self) -> Result<Option<IndexManifest>> {
+        Err(ServerError::Unsupported)
+    }
+
     async fn perform_query(&self, q: &str) -> Result<Value>;
 }
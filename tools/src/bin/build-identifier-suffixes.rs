@@ -0,0 +1,26 @@
+//! This tool writes the suffix array used for identifier substring searches
+//! next to the given `identifiers` file.  It must be run after the file has
+//! been re-sorted case-insensitively (see `crossref.sh`) since the suffix array
+//! refers to byte offsets within it.
+
+use std::env;
+use std::fs;
+
+extern crate tools;
+use tools::file_format::identifiers::{write_identifier_suffixes, IDENTIFIER_SUFFIXES_EXTENSION};
+
+fn main() {
+    let args: Vec<_> = env::args().skip(1).collect();
+
+    if args.len() != 1 {
+        eprintln!("Usage: build-identifier-suffixes <identifiers-file>");
+        std::process::exit(1);
+    }
+
+    let identifiers = fs::read(&args[0]).unwrap();
+    write_identifier_suffixes(
+        &identifiers,
+        &format!("{}{}", args[0], IDENTIFIER_SUFFIXES_EXTENSION),
+    )
+    .unwrap();
+}
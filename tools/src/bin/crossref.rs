@@ -1,6 +1,7 @@
 use std::collections::BTreeMap;
 use std::collections::BTreeSet;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::fs;
 use std::fs::create_dir_all;
 use std::fs::File;
@@ -13,6 +14,7 @@ extern crate tracing;
 
 extern crate clap;
 use clap::Parser;
+use git2::Oid;
 use itertools::Itertools;
 use serde_json::{json, Map};
 extern crate tools;
@@ -27,17 +29,34 @@ use tools::file_format::analysis::{
 };
 use tools::file_format::analysis_manglings::make_file_sym_from_path;
 use tools::file_format::analysis_manglings::split_pretty;
+use tools::file_format::bug_references::{BugReferenceIndex, BUG_REFERENCES_FILENAME};
 use tools::file_format::config;
 use tools::file_format::crossref_converter::convert_crossref_value_to_sym_info_rep;
+use tools::file_format::doc_comments::extract_doc_comment;
+use tools::file_format::js_modules::{is_js_module_path, JsModuleIndex, JS_MODULE_INDEX_FILENAME};
+use tools::file_format::lock_annotations::{LockAnnotationIndex, LOCK_ANNOTATIONS_FILENAME};
 use tools::file_format::ontology_mapping::OntologyRunnableMode;
 use tools::file_format::ontology_mapping::{
     OntologyLabelOwningClass, OntologyMappingIngestion, OntologyPointerKind,
 };
+use tools::file_format::refcount_graph::{RefcountGraph, REFCOUNT_GRAPH_FILENAME};
 use tools::file_format::repo_data_ingestion::RepoIngestion;
+use tools::file_format::rust_crates::{
+    RustCrateIndex, CARGO_METADATA_FILENAME, RUST_CRATES_FILENAME,
+};
+use tools::file_format::static_inits::{StaticInitIndex, STATIC_INITS_FILENAME};
+use tools::file_format::structured_columns::write_structured_columns;
+use tools::file_format::symbol_attributes::{SymbolAttributes, SYMBOL_ATTRIBUTES_FILENAME};
+use tools::file_format::symbol_kind_stats::{SymbolKindStats, SYMBOL_KIND_STATS_FILENAME};
+use tools::file_format::todo_comments::{extract_todos, TodoIndex, TODO_INDEX_FILENAME};
+use tools::file_format::trigram_bloom::{write_trigram_blooms, TrigramBloom};
+use tools::git_ops::get_blame_lines;
 use tools::logging::init_logging;
 use tools::logging::LoggedSpan;
 use tools::templating::builder::build_and_parse_ontology_ingestion_explainer;
 use tools::templating::builder::build_and_parse_repo_ingestion_explainer;
+use tools::unicode_folding::nfkc;
+use tools::url_map_handler::get_file_paths_for_url;
 use ustr::ustr;
 use ustr::Ustr;
 use ustr::UstrMap;
@@ -162,8 +181,19 @@ fn process_analysis_target(
             let sub = &components[i..components.len()];
             let sub = sub.join(delim);
 
+            // Identifiers are normalized to NFKC so that identifier searches,
+            // which normalize their queries the same way, find them however
+            // they were typed.  NFKC can produce whitespace (ex: from a
+            // no-break space), in which case we keep the original.
+            let normalized = nfkc(&sub);
+            let sub = if normalized.contains(char::is_whitespace) {
+                sub.as_str()
+            } else {
+                normalized.as_ref()
+            };
+
             if !sub.is_empty() {
-                let t1 = id_table.entry(ustr(&sub)).or_default();
+                let t1 = id_table.entry(ustr(sub)).or_default();
                 t1.insert(piece.sym);
             }
         }
@@ -229,6 +259,11 @@ fn make_subsystem(
             impl_kind: ustr("impl"),
             size_bytes: None,
             own_vf_ptr_bytes: None,
+            repr: None,
+            crate_name: None,
+            module_path: None,
+            static_init: None,
+            locks: vec![],
             binding_slots: vec![],
             ontology_slots: vec![],
             supers: vec![],
@@ -237,10 +272,12 @@ fn make_subsystem(
             overrides: vec![],
             props: vec![],
             labels: BTreeSet::default(),
+            attributes: BTreeSet::default(),
 
             idl_sym: None,
             subclass_syms: vec![],
             overridden_by_syms: vec![],
+            doc: None,
             variants: vec![],
             extra: Map::default(),
         };
@@ -352,6 +389,7 @@ async fn main() {
             cfg.maybe_read_file_from_given_root(&cli.tree_name, root, file)
         })
         .unwrap();
+    ingestion.apply_classifications();
 
     // After this point we will only have the concise information populated.
     // We're doing this to minimize our peak memory usage here, but if we find
@@ -490,8 +528,14 @@ async fn main() {
         // do not actually correspond to a source file.  This is the case for
         // Java imports from the JDK/Kotlin/Android runtimes.
         let structured_analysis = read_analysis(&analysis_fname, &mut read_structured);
+        // Symbols defined in this file whose doc comments we want to extract
+        // once we've loaded the source below.
+        let mut doc_candidates: Vec<(Ustr, u32)> = vec![];
         for datum in structured_analysis {
             for piece in datum.data {
+                if datum.loc.lineno > 0 {
+                    doc_candidates.push((piece.sym, datum.loc.lineno));
+                }
                 // If we don't have a location for the structured record then this
                 // is the SCIP external structured record case mentioned above and
                 // we need to insert the pretty and id_table mappings since there
@@ -546,6 +590,17 @@ async fn main() {
             })
             .collect();
 
+        if !doc_candidates.is_empty() {
+            let line_strs: Vec<&str> = lines.iter().map(|(line, _)| line.as_str()).collect();
+            for (sym, lineno) in doc_candidates {
+                if let Some(meta) = meta_table.get_mut(&sym) {
+                    if meta.doc.is_none() {
+                        meta.doc = extract_doc_comment(&line_strs, lineno);
+                    }
+                }
+            }
+        }
+
         let analysis = read_analysis(&analysis_fname, &mut read_target);
 
         for datum in analysis {
@@ -1045,17 +1100,151 @@ async fn main() {
     // file and we'd end up reporting the missing info a lot.
     let mut reported_missing_concise = UstrSet::default();
 
+    // If the tree's build saved its Cargo metadata, build the graph of the
+    // in-tree Rust crates for the `rust-crate-graph` command, counting each
+    // crate's symbols and its uses of other crates' symbols as we go.
+    let mut rust_crates = fs::read_to_string(format!(
+        "{}/{}",
+        tree_config.paths.index_path, CARGO_METADATA_FILENAME
+    ))
+    .ok()
+    .map(|json| {
+        RustCrateIndex::from_cargo_metadata(
+            &serde_json::from_str(&json).unwrap(),
+            &tree_config.paths.files_path,
+        )
+    });
+
+    // Collect the variables whose static initialization or destruction runs
+    // code for the `static-inits` command, located at their definitions.
+    let mut static_inits = StaticInitIndex::default();
+
+    // Collect the fields with thread safety annotations and the functions
+    // using them for the `lock-report` command.  The annotations of all the
+    // symbols need to be known before we process their uses.
+    let mut lock_annotations = LockAnnotationIndex::default();
+    for (sym, meta) in &meta_table {
+        let no_analysis = meta.attributes.contains(&ustr("no_thread_safety_analysis"));
+        if !meta.locks.is_empty() || no_analysis {
+            lock_annotations.add_symbol(sym, &meta.pretty, &meta.locks, no_analysis);
+        }
+    }
+
+    // Build the ownership graph between the refcounted classes for the
+    // `refcount-cycles` command, now that the ontology has labeled the
+    // refcounted classes and the fields holding strong references.
+    let mut refcount_graph = RefcountGraph::default();
+    for (sym, meta) in &meta_table {
+        if meta.kind.as_str() == "class" || meta.kind.as_str() == "struct" {
+            refcount_graph.add_class(sym, &meta.pretty, &meta.labels, &meta.supers, &meta.fields);
+        }
+    }
+    refcount_graph.finish();
+    refcount_graph
+        .write(&format!(
+            "{}/{}",
+            tree_config.paths.index_path, REFCOUNT_GRAPH_FILENAME
+        ))
+        .unwrap();
+
+    // Count the symbols defined in each directory by kind so that `trend` can
+    // compare this generation of the index with others.
+    let mut symbol_kinds = SymbolKindStats {
+        revision: tree_config
+            .git
+            .as_ref()
+            .and_then(|git| git.repo.head().and_then(|head| head.peel_to_commit()).ok())
+            .map(|commit| commit.id().to_string()),
+        ..SymbolKindStats::default()
+    };
+
     for (id, id_data) in table {
+        let first_def = id_data
+            .get(&AnalysisKind::Def)
+            .and_then(|defs| defs.iter().next())
+            .map(|(path, results)| (path.as_str(), results.first().map_or(0, |r| r.lineno)));
+        let uses = id_data
+            .get(&AnalysisKind::Use)
+            .into_iter()
+            .flatten()
+            .flat_map(|(path, results)| {
+                results.iter().map(move |r| {
+                    (
+                        path.as_str(),
+                        r.lineno,
+                        r.contextsym.as_str(),
+                        r.context.as_str(),
+                    )
+                })
+            });
+        lock_annotations.add_uses(&id, first_def, uses);
+
+        if let Some((path, _)) = first_def {
+            match meta_table.get(&id) {
+                Some(meta) if !meta.kind.is_empty() => {
+                    symbol_kinds.add(path, &meta.kind);
+                    if meta.kind.as_str() == "method" && meta.props.contains(&ustr("virtual")) {
+                        symbol_kinds.add(path, "virtualMethod");
+                    }
+                }
+                Some(_) => {}
+                None if id.starts_with("M_") => symbol_kinds.add(path, "macro"),
+                None => {}
+            }
+        }
+
+        if let Some(meta) = meta_table.get(&id) {
+            let def = id_data
+                .get(&AnalysisKind::Def)
+                .and_then(|defs| defs.iter().next());
+            if let (Some(info), Some((path, results))) = (&meta.static_init, def) {
+                static_inits.add(
+                    &id,
+                    &meta.pretty,
+                    path,
+                    results.first().map_or(0, |result| result.lineno),
+                    info,
+                    |sym| {
+                        pretty_table
+                            .get(&ustr(sym))
+                            .map(|pretty| pretty.to_string())
+                    },
+                );
+            }
+        }
+
+        if let Some(rust_crates) = rust_crates.as_mut() {
+            let crate_name = meta_table
+                .get(&id)
+                .and_then(|meta| meta.crate_name)
+                .and_then(|name| rust_crates.resolve_crate_name(&name))
+                .map(|name| name.to_string());
+            if let Some(crate_name) = crate_name {
+                if id_data.contains_key(&AnalysisKind::Def) {
+                    rust_crates.add_symbol(&crate_name);
+                }
+                for (path, results) in id_data.get(&AnalysisKind::Use).into_iter().flatten() {
+                    rust_crates.add_references(path, &crate_name, results.len() as u64);
+                }
+            }
+        }
+
         let mut kindmap = Map::new();
         for (kind, kind_data) in &id_data {
             let mut result = Vec::new();
             for (path, results) in kind_data {
                 if let Some(concise_info) = ingestion.state.concise_per_file.get(path) {
-                    result.push(json!({
+                    let mut path_result = json!({
                         "path": path,
                         "path_kind": concise_info.path_kind,
                         "lines": results,
-                    }));
+                    });
+                    // Only non-normal files get a classification to avoid
+                    // bloating the crossref database.
+                    if !concise_info.classification.is_normal() {
+                        path_result["classification"] = json!(concise_info.classification);
+                    }
+                    result.push(path_result);
                 } else {
                     // NSS seems to have an issue with auto-generated files we
                     // don't know about, so this can't be a warning because it's
@@ -1185,6 +1374,26 @@ async fn main() {
         }
     }
 
+    // Also write the subset of structured info that analyses need in bulk to
+    // a columnar file so they can avoid parsing entire crossref entries.
+    write_structured_columns(
+        &format!("{}/structured-columns", tree_config.paths.index_path),
+        meta_table.iter().map(|(sym, meta)| (sym.as_str(), meta)),
+    )
+    .unwrap();
+
+    // Index which symbols bear each attribute for `search-attributes`.
+    SymbolAttributes::from_symbols(
+        meta_table
+            .iter()
+            .map(|(sym, meta)| (sym.as_str(), meta.attributes.iter().map(|a| a.as_str()))),
+    )
+    .write(&format!(
+        "{}/{}",
+        tree_config.paths.index_path, SYMBOL_ATTRIBUTES_FILENAME
+    ))
+    .unwrap();
+
     let mut idf = File::create(id_file).unwrap();
     for (id, syms) in id_table {
         for sym in syms {
@@ -1193,6 +1402,135 @@ async fn main() {
         }
     }
 
+    // Build the trigram bloom skip index used by local text search scans.
+    // Files we can't read (ex: files that were deleted or are symlinks to
+    // nowhere) are just left out, which means scans will also skip them.
+    write_trigram_blooms(
+        &format!("{}/trigram-bloom", tree_config.paths.index_path),
+        all_files_paths.iter().filter_map(|path| {
+            let contents = fs::read(tree_config.find_source_file(path)).ok()?;
+            Some((path.as_str(), TrigramBloom::from_contents(&contents)))
+        }),
+    )
+    .unwrap();
+
+    // Index the TODO comments for the `todos` command.  Files that aren't
+    // UTF-8 are skipped.
+    TodoIndex {
+        todos: all_files_paths
+            .iter()
+            .filter_map(|path| {
+                let contents = fs::read_to_string(tree_config.find_source_file(path)).ok()?;
+                Some(extract_todos(path, &contents))
+            })
+            .flatten()
+            .collect(),
+    }
+    .write(&format!(
+        "{}/{}",
+        tree_config.paths.index_path, TODO_INDEX_FILENAME
+    ))
+    .unwrap();
+
+    // Index the bugs referenced by comments and by the commit messages of the
+    // revisions that last changed each line, for the `bug-refs` command.
+    let mut bug_refs = BugReferenceIndex::default();
+    let git = tree_config.git.as_ref();
+    let blame_commit = git
+        .and_then(|git| git.blame_repo.as_ref())
+        .and_then(|blame_repo| {
+            blame_repo
+                .head()
+                .and_then(|head| head.peel_to_commit())
+                .ok()
+        });
+    // Most revisions touch many lines, so cache their commit messages.
+    let mut commit_messages: HashMap<String, Option<String>> = HashMap::new();
+    for path in &all_files_paths {
+        if let Ok(contents) = fs::read_to_string(tree_config.find_source_file(path)) {
+            bug_refs.add_comment_references(path, &contents);
+        }
+        let Some(blame_lines) = get_blame_lines(git, &blame_commit, path) else {
+            continue;
+        };
+        bug_refs.add_commit_references(path, &blame_lines, |rev| {
+            commit_messages
+                .entry(rev.to_string())
+                .or_insert_with(|| {
+                    let commit = git?.repo.find_commit(Oid::from_str(rev).ok()?).ok()?;
+                    commit.message().map(|message| message.to_string())
+                })
+                .clone()
+        });
+    }
+    bug_refs
+        .write(&format!(
+            "{}/{}",
+            tree_config.paths.index_path, BUG_REFERENCES_FILENAME
+        ))
+        .unwrap();
+
+    // Index the import edges between JS modules for the `js-module-graph`
+    // command.  URL specifiers are resolved via the tree's URL map.
+    let mut js_modules = JsModuleIndex::default();
+    let all_files_set: HashSet<&str> = all_files_paths.iter().map(|path| path.as_str()).collect();
+    for path in all_files_paths
+        .iter()
+        .filter(|path| is_js_module_path(path))
+    {
+        let Ok(contents) = fs::read_to_string(tree_config.find_source_file(path)) else {
+            continue;
+        };
+        js_modules.add_module(
+            path,
+            &contents,
+            |url| {
+                get_file_paths_for_url(Some(&cfg), url)?
+                    .into_iter()
+                    .find_map(|item| item.pretty.strip_prefix("file ").map(|p| p.to_string()))
+            },
+            |target| all_files_set.contains(target),
+        );
+    }
+    js_modules
+        .write(&format!(
+            "{}/{}",
+            tree_config.paths.index_path, JS_MODULE_INDEX_FILENAME
+        ))
+        .unwrap();
+
+    symbol_kinds
+        .write(&format!(
+            "{}/{}",
+            tree_config.paths.index_path, SYMBOL_KIND_STATS_FILENAME
+        ))
+        .unwrap();
+
+    lock_annotations.finish();
+    lock_annotations
+        .write(&format!(
+            "{}/{}",
+            tree_config.paths.index_path, LOCK_ANNOTATIONS_FILENAME
+        ))
+        .unwrap();
+
+    static_inits.finish();
+    static_inits
+        .write(&format!(
+            "{}/{}",
+            tree_config.paths.index_path, STATIC_INITS_FILENAME
+        ))
+        .unwrap();
+
+    if let Some(rust_crates) = rust_crates {
+        rust_crates
+            .write(&format!(
+                "{}/{}",
+                tree_config.paths.index_path, RUST_CRATES_FILENAME
+            ))
+            .unwrap();
+    }
+
     ingestion
         .state
         .write_out_concise_file_info(&tree_config.paths.index_path);
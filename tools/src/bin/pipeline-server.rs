@@ -1,135 +1,516 @@
 use std::{
     collections::{BTreeMap, HashMap},
     env,
-    sync::Arc,
+    path::Path as FsPath,
+    sync::{Arc, RwLock},
 };
 
 use axum::{
     extract::{Path, Query},
-    http::{HeaderMap, StatusCode},
+    http::{header, HeaderMap},
     response::{Html, IntoResponse, Response},
-    routing::get,
+    routing::{get, post},
     Extension, Json, Router,
 };
 use axum_macros::debug_handler;
+use clap::ValueEnum;
 use liquid::Template;
 use serde_json::Value;
+use tokio::signal::unix::{signal, SignalKind};
 use tools::{
-    abstract_server::{make_all_local_servers, AbstractServer, ServerError},
-    cmd_pipeline::{builder::build_pipeline_graph, PipelineValues},
+    abstract_server::{
+        make_all_local_servers, restrict_server, AbstractServer, AccessPolicy, ErrorDetails,
+        ErrorKind, ErrorLayer, SearchfoxIndexRoot, ServerError,
+    },
+    cmd_pipeline::builder::{build_pipeline_graph, CommandSafetyLevel},
+    file_format::asset_manifest::{AssetManifest, ASSET_MANIFEST_FILENAME},
     logging::{init_logging, LoggedSpan},
-    query::chew_query::chew_query,
+    query::{
+        badges::{load_badge_runs, Badge, BadgeAggregate, BadgeMetric},
+        chew_query::chew_query,
+        pipeline_cache::load_cached_preset,
+        post_index_hooks::load_hook_run,
+        presets::{all_presets, run_preset},
+        shared_analyses::{load_shared_analysis, run_shared_analysis},
+    },
     templating::builder::build_and_parse_query_results,
 };
-use tracing::Instrument;
+use tracing::{error, info, Instrument};
+
+type ServerMap = BTreeMap<String, Box<dyn AbstractServer + Send + Sync>>;
+
+struct SomeTemplates {
+    query_results: Template,
+}
+
+/// Everything built from the config that a request needs, swapped as a unit.
+struct Served {
+    servers: ServerMap,
+    templates: SomeTemplates,
+    /// Each tree's asset manifest, for the `ASSET_MANIFEST` template global.
+    asset_manifests: BTreeMap<String, AssetManifest>,
+}
+
+impl Served {
+    fn load(config_path: &str) -> Result<Served, ServerError> {
+        let servers = make_all_local_servers(config_path)?;
+        validate_servers(&servers)?;
+        let mut asset_manifests = BTreeMap::new();
+        for (tree_name, server) in &servers {
+            let path = server
+                .translate_path(SearchfoxIndexRoot::IndexTemplates, ASSET_MANIFEST_FILENAME)?;
+            asset_manifests.insert(tree_name.clone(), AssetManifest::load_or_default(&path)?);
+        }
+        Ok(Served {
+            servers,
+            templates: SomeTemplates {
+                query_results: build_and_parse_query_results(),
+            },
+            asset_manifests,
+        })
+    }
+}
+
+/// The trees being served.  Reloading the config builds and validates an
+/// entirely new set of servers and templates and then atomically swaps them
+/// in, so requests already in flight finish against the servers they started
+/// with and a bad config leaves the current trees in place.
+struct ServedTrees {
+    config_path: String,
+    served: RwLock<Arc<Served>>,
+}
+
+impl ServedTrees {
+    fn current(&self) -> Arc<Served> {
+        self.served.read().unwrap().clone()
+    }
+
+    async fn reload(&self) -> Result<Vec<String>, ServerError> {
+        let config_path = self.config_path.clone();
+        // Opening every tree's indexes does blocking I/O, so keep it off the
+        // async workers.
+        let served = tokio::task::spawn_blocking(move || Served::load(&config_path)).await??;
+
+        let tree_names: Vec<String> = served.servers.keys().cloned().collect();
+        *self.served.write().unwrap() = Arc::new(served);
+        Ok(tree_names)
+    }
+}
+
+/// Make sure every tree's index directory and templates actually exist so that
+/// we don't swap in a config that can't serve anything.
+fn validate_servers(servers: &ServerMap) -> Result<(), ServerError> {
+    for (tree_name, server) in servers {
+        for root in [
+            SearchfoxIndexRoot::IndexRoot,
+            SearchfoxIndexRoot::IndexTemplates,
+        ] {
+            let dir = server.translate_path(root, "")?;
+            if !FsPath::new(&dir).is_dir() {
+                return Err(ServerError::StickyProblem(ErrorDetails::new(
+                    ErrorKind::Internal,
+                    ErrorLayer::ConfigLayer,
+                    format!("Tree {} is missing directory {}", tree_name, dir),
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Reload the config, returning the names of the trees now being served.  This
+/// is served by its own listener on the loopback interface rather than next to
+/// the routes nginx proxies, since proxied requests also arrive from localhost.
+#[debug_handler]
+async fn handle_reload_config(
+    served: Extension<Arc<ServedTrees>>,
+) -> Result<Response, ServerError> {
+    let tree_names = served.reload().await?;
+    Ok(Json(tree_names).into_response())
+}
+
+/// Reload the config whenever we receive SIGHUP.
+async fn reload_on_sighup(served: Arc<ServedTrees>) {
+    let mut hangups = signal(SignalKind::hangup()).unwrap();
+    while hangups.recv().await.is_some() {
+        match served.reload().await {
+            Ok(tree_names) => info!("Reloaded config, serving trees: {:?}", tree_names),
+            Err(e) => error!("Config reload failed, keeping current trees: {:?}", e),
+        }
+    }
+}
+
+/// Determine which restricted paths the requester may not see based on the
+/// groups header that the front-end proxy sets.  See the `access_control` tree
+/// config.
+fn access_policy(
+    server: &(dyn AbstractServer + Send + Sync),
+    headers: &HeaderMap,
+) -> Result<AccessPolicy, ServerError> {
+    let config = server.tree_info()?.access_control;
+    let groups = headers
+        .get(config.groups_header.as_str())
+        .and_then(|x| x.to_str().ok());
+    Ok(AccessPolicy::for_header(&config, groups))
+}
+
+/// The error for a request naming a tree, preset or hook we don't have.
+fn not_found(key: &'static str, value: &str, message: &str) -> ServerError {
+    ServerError::StickyProblem(
+        ErrorDetails::new(ErrorKind::NotFound, ErrorLayer::BadInput, message)
+            .with_context(key, value),
+    )
+}
 
 #[debug_handler]
 async fn handle_query(
-    local_servers: Extension<Arc<BTreeMap<String, Box<dyn AbstractServer + Send + Sync>>>>,
-    templates: Extension<Arc<SomeTemplates>>,
+    served: Extension<Arc<ServedTrees>>,
     headers: HeaderMap,
     Path((tree, preset)): Path<(String, String)>,
     Query(params): Query<HashMap<String, String>>,
 ) -> Result<Response, ServerError> {
-    let server = match local_servers.get(&tree) {
-        Some(s) => s,
-        None => {
-            return Ok((StatusCode::NOT_FOUND, format!("No such tree: {}", tree)).into_response());
-        }
+    let current = served.current();
+    let Some(server) = current.servers.get(&tree) else {
+        return Err(not_found("tree", &tree, "No such tree"));
     };
 
-    if preset.as_str() != "default" {
-        return Ok((StatusCode::NOT_FOUND, format!("No such preset: {}", preset)).into_response());
+    if preset.as_str() != "default"
+        && !all_presets(&server.tree_info()?.query_presets).contains_key(&preset)
+    {
+        return Err(not_found("preset", &preset, "No such preset"));
     }
 
+    let policy = access_policy(server.as_ref(), &headers)?;
+
+    let accept = headers
+        .get("accept")
+        .map(|x| x.to_str().unwrap_or("text/html"));
+    let make_html = !matches!(accept, Some("application/json"));
+
     let maybe_log = params.contains_key("debug");
+
+    // Serve outputs that `precompute` cached at index time, unless logs were
+    // requested, since those require actually running the preset.
+    if preset.as_str() != "default" && !maybe_log {
+        let preset_params: BTreeMap<String, String> =
+            params.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+        if let Some(mut cached) = load_cached_preset(server.as_ref(), &preset, &preset_params)? {
+            // Cached outputs were computed without restrictions.
+            policy.filter_json(&mut cached.output);
+            policy.filter_json(&mut cached.sym_info);
+            if !make_html {
+                return Ok(Json(cached.output).into_response());
+            }
+            let index_info = server.index_manifest().await.ok().flatten();
+            let globals = liquid::object!({
+                "results": cached.output,
+                "query": preset_params
+                    .iter()
+                    .map(|(k, v)| format!("{}={}", k, v))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                "preset": preset.clone(),
+                "tree": tree.clone(),
+                "logs": Value::Null,
+                "SYM_INFO_STR": cached.sym_info.to_string(),
+                "INDEX_INFO": index_info,
+                "ASSET_MANIFEST": &current.asset_manifests[&tree].assets,
+            });
+            let output = current.templates.query_results.render(&globals)?;
+            return Ok(Html(output).into_response());
+        }
+    }
+
+    let server = restrict_server(server.clonify(), policy.clone());
+
     let logged_span: Option<LoggedSpan> = if maybe_log {
         Some(LoggedSpan::new_logged_span("query"))
     } else {
         None
     };
 
-    let query = match params.get("q") {
-        Some(q) => q,
-        None => {
-            return Ok((StatusCode::BAD_REQUEST, "No 'q' parameter, no results!").into_response());
-        }
-    };
+    let (query, mut result) = if preset.as_str() == "default" {
+        let query = match params.get("q") {
+            Some(q) => q.clone(),
+            None => {
+                return Err(ServerError::StickyProblem(
+                    ErrorDetails::new(
+                        ErrorKind::BadQuery,
+                        ErrorLayer::BadInput,
+                        "No 'q' parameter, no results!",
+                    )
+                    .with_hint("Pass the search as the `q` query parameter."),
+                ));
+            }
+        };
 
-    let graph = {
-        let _log_entered = logged_span
-            .as_ref()
-            .map(|lspan| lspan.span.clone().entered());
+        let graph = {
+            let _log_entered = logged_span
+                .as_ref()
+                .map(|lspan| lspan.span.clone().entered());
 
-        let pipeline_plan = chew_query(query)?;
+            let pipeline_plan = chew_query(&query)?;
 
-        build_pipeline_graph(server.clonify(), pipeline_plan)?
-    };
+            build_pipeline_graph(
+                server.clonify(),
+                pipeline_plan,
+                CommandSafetyLevel::WebSafety,
+            )?
+        };
 
-    let result = match &logged_span {
-        Some(lspan) => graph.run(true).instrument(lspan.span.clone()).await?,
-        _ => graph.run(true).await?,
+        let result = match &logged_span {
+            Some(lspan) => graph.run(true).instrument(lspan.span.clone()).await?,
+            _ => graph.run(true).await?,
+        };
+        (query, result)
+    } else {
+        // Named presets take their parameters directly from the query string.
+        let preset_params: BTreeMap<String, String> = params
+            .iter()
+            .filter(|(k, _)| k.as_str() != "debug")
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        let result = match &logged_span {
+            Some(lspan) => {
+                run_preset(server.as_ref(), &preset, &preset_params)
+                    .instrument(lspan.span.clone())
+                    .await?
+            }
+            _ => run_preset(server.as_ref(), &preset, &preset_params).await?,
+        };
+        let query = preset_params
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>()
+            .join(" ");
+        (query, result)
     };
 
-    let accept = headers
-        .get("accept")
-        .map(|x| x.to_str().unwrap_or("text/html"));
-    let make_html = !matches!(accept, Some("application/json"));
-
     let logs = match logged_span {
         Some(lspan) => lspan.retrieve_serde_json().await,
         _ => Value::Null,
     };
 
+    result.apply_access_policy(&policy);
+
     if make_html {
-        let sym_info_str = match &result {
-            PipelineValues::GraphResultsBundle(grb) => {
-                serde_json::to_string(&grb.symbols).unwrap_or_else(|_| "{}".to_string())
-            }
-            PipelineValues::SymbolTreeTableList(sttl) => {
-                serde_json::to_string(&sttl.unioned_node_sets_as_jumprefs())
-                    .unwrap_or_else(|_| "{}".to_string())
-            }
-            _ => "{}".to_string(),
-        };
+        let sym_info_str = result.sym_info().to_string();
+        // The manifest is informational, so don't fail the query over it.
+        let index_info = server.index_manifest().await.ok().flatten();
 
         let globals = liquid::object!({
             "results": result,
-            "query": query.clone(),
+            "query": query,
             "preset": preset.clone(),
             "tree": tree.clone(),
             "logs": logs,
             "SYM_INFO_STR": sym_info_str,
+            "INDEX_INFO": index_info,
+            "ASSET_MANIFEST": &current.asset_manifests[&tree].assets,
         });
 
-        let output = templates.query_results.render(&globals)?;
+        let output = current.templates.query_results.render(&globals)?;
         Ok(Html(output).into_response())
     } else {
         Ok(Json(result).into_response())
     }
 }
 
-struct SomeTemplates {
-    query_results: Template,
+/// Re-run an analysis stored by the `share` command and render it like we would
+/// render live query results.
+#[debug_handler]
+async fn handle_shared(
+    served: Extension<Arc<ServedTrees>>,
+    headers: HeaderMap,
+    Path((tree, token)): Path<(String, String)>,
+) -> Result<Response, ServerError> {
+    let current = served.current();
+    let Some(server) = current.servers.get(&tree) else {
+        return Err(not_found("tree", &tree, "No such tree"));
+    };
+
+    let Some(analysis) = load_shared_analysis(server.as_ref(), &token)? else {
+        return Err(not_found("token", &token, "No such shared analysis"));
+    };
+
+    let policy = access_policy(server.as_ref(), &headers)?;
+    let server = restrict_server(server.clonify(), policy.clone());
+    let mut result = run_shared_analysis(server.as_ref(), &analysis).await?;
+    result.apply_access_policy(&policy);
+
+    let accept = headers
+        .get("accept")
+        .map(|x| x.to_str().unwrap_or("text/html"));
+    if matches!(accept, Some("application/json")) {
+        return Ok(Json(result).into_response());
+    }
+
+    let sym_info_str = result.sym_info().to_string();
+    let index_info = server.index_manifest().await.ok().flatten();
+    let globals = liquid::object!({
+        "results": result,
+        "query": analysis.pipeline,
+        "preset": "shared",
+        "tree": tree.clone(),
+        "logs": Value::Null,
+        "SYM_INFO_STR": sym_info_str,
+        "INDEX_INFO": index_info,
+        "ASSET_MANIFEST": &current.asset_manifests[&tree].assets,
+    });
+    let output = current.templates.query_results.render(&globals)?;
+    Ok(Html(output).into_response())
+}
+
+/// Render the stored output of a post-index hook run like we would render live
+/// query results.  The `ran_at` query parameter selects an older run.
+#[debug_handler]
+async fn handle_hook_results(
+    served: Extension<Arc<ServedTrees>>,
+    headers: HeaderMap,
+    Path((tree, hook)): Path<(String, String)>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Response, ServerError> {
+    let current = served.current();
+    let Some(server) = current.servers.get(&tree) else {
+        return Err(not_found("tree", &tree, "No such tree"));
+    };
+
+    if !server.tree_info()?.post_index_hooks.contains_key(&hook) {
+        return Err(not_found("hook", &hook, "No such hook"));
+    }
+    let ran_at = params.get("ran_at").and_then(|r| r.parse().ok());
+    let mut run = match load_hook_run(server.as_ref(), &hook, ran_at)? {
+        Some(run) => run,
+        None => {
+            return Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::NotFound,
+                    ErrorLayer::BadInput,
+                    "No stored results for hook",
+                )
+                .with_context("hook", &hook)
+                .with_hint(
+                    "Hooks store their results when `run-post-index-hooks` runs after indexing.",
+                ),
+            ));
+        }
+    };
+
+    // Hook outputs were computed without restrictions.
+    let policy = access_policy(server.as_ref(), &headers)?;
+    policy.filter_json(&mut run.output);
+    policy.filter_json(&mut run.sym_info);
+
+    let accept = headers
+        .get("accept")
+        .map(|x| x.to_str().unwrap_or("text/html"));
+    if matches!(accept, Some("application/json")) {
+        return Ok(Json(run).into_response());
+    }
+
+    let index_info = server.index_manifest().await.ok().flatten();
+    let globals = liquid::object!({
+        "results": run.output,
+        "query": format!("{} (ran at {})", hook, run.ran_at),
+        "preset": "",
+        "tree": tree.clone(),
+        "logs": Value::Null,
+        "SYM_INFO_STR": run.sym_info.to_string(),
+        "INDEX_INFO": index_info,
+        "ASSET_MANIFEST": &current.asset_manifests[&tree].assets,
+    });
+
+    let output = current.templates.query_results.render(&globals)?;
+    Ok(Html(output).into_response())
+}
+
+/// Render an SVG badge for a metric of a post-index hook's most recent run
+/// for embedding in dashboards (see `query/badges.rs`).  The `pointer`,
+/// `aggregate`, and `field` query parameters locate the metric like the
+/// `badge` command's arguments of the same names, `label` defaults to the
+/// hook's name, and `higher_is_better` flips the trend colors.
+#[debug_handler]
+async fn handle_badge(
+    served: Extension<Arc<ServedTrees>>,
+    headers: HeaderMap,
+    Path((tree, hook)): Path<(String, String)>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Response, ServerError> {
+    let current = served.current();
+    let Some(server) = current.servers.get(&tree) else {
+        return Err(not_found("tree", &tree, "No such tree"));
+    };
+
+    if !server.tree_info()?.post_index_hooks.contains_key(&hook) {
+        return Err(not_found("hook", &hook, "No such hook"));
+    }
+    let aggregate = match params.get("aggregate") {
+        Some(aggregate) => BadgeAggregate::from_str(aggregate, true).map_err(|_| {
+            ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::BadQuery,
+                    ErrorLayer::BadInput,
+                    "Unknown badge aggregate",
+                )
+                .with_context("aggregate", aggregate)
+                .with_hint("Use one of `value`, `count`, or `sum`."),
+            )
+        })?,
+        None => BadgeAggregate::Value,
+    };
+    let metric = BadgeMetric {
+        pointer: params.get("pointer").cloned().unwrap_or_default(),
+        aggregate,
+        field: params.get("field").cloned(),
+    };
+
+    // Hook outputs were computed without restrictions.
+    let policy = access_policy(server.as_ref(), &headers)?;
+    let mut runs = load_badge_runs(server.as_ref(), &hook)?;
+    for run in &mut runs {
+        policy.filter_json(&mut run.output);
+    }
+    let badge = Badge::from_runs(
+        params.get("label").unwrap_or(&hook),
+        &runs,
+        &metric,
+        params.contains_key("higher_is_better"),
+    );
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "image/svg+xml"),
+            (header::CACHE_CONTROL, "no-cache"),
+        ],
+        badge.to_svg(),
+    )
+        .into_response())
 }
 
 #[tokio::main]
 async fn main() {
     init_logging();
 
-    let local_servers = Arc::new(make_all_local_servers(&env::args().nth(1).unwrap()).unwrap());
-    let templates = Arc::new(SomeTemplates {
-        query_results: build_and_parse_query_results(),
+    let config_path = env::args().nth(1).unwrap();
+    let served = Arc::new(ServedTrees {
+        served: RwLock::new(Arc::new(Served::load(&config_path).unwrap())),
+        config_path,
     });
+    tokio::spawn(reload_on_sighup(served.clone()));
 
-    // build our application with a single route
     let app = Router::new()
         .route("/:tree/query/:preset", get(handle_query))
-        .layer(Extension(local_servers))
-        .layer(Extension(templates));
+        .route("/:tree/shared/:token", get(handle_shared))
+        .route("/:tree/hooks/:hook", get(handle_hook_results))
+        .route("/:tree/badge/:hook", get(handle_badge))
+        .layer(Extension(served.clone()));
+    let admin = Router::new()
+        .route("/admin/reload-config", post(handle_reload_config))
+        .layer(Extension(served));
 
-    axum::Server::bind(&"0.0.0.0:8002".parse().unwrap())
-        .serve(app.into_make_service())
-        .await
-        .unwrap();
+    let public_server =
+        axum::Server::bind(&"0.0.0.0:8002".parse().unwrap()).serve(app.into_make_service());
+    let admin_server =
+        axum::Server::bind(&"127.0.0.1:8003".parse().unwrap()).serve(admin.into_make_service());
+    tokio::try_join!(public_server, admin_server).unwrap();
 }
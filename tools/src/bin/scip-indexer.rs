@@ -22,6 +22,9 @@ use tools::file_format::analysis::{
     StructuredSuperInfo, StructuredTag, TargetTag, WithLocation,
 };
 use tools::file_format::config;
+use tools::file_format::lsif::lsif_to_scip;
+use tools::file_format::symbol_attributes::rust_attribute_name;
+use tools::rust_repr::repr_attribute_args;
 use ustr::{ustr, Ustr, UstrMap, UstrSet};
 
 /// Normalize illegal symbol characters into underscores.
@@ -63,7 +66,8 @@ struct ScipIndexerCli {
     #[arg(long, value_parser)]
     platform: Option<String>,
 
-    /// rustc analysis directories or scip inputs
+    /// rustc analysis directories, scip inputs, or LSIF dumps (which must have
+    /// a ".lsif" extension)
     #[arg(value_parser)]
     inputs: Vec<PathBuf>,
 }
@@ -120,6 +124,71 @@ fn write_line(mut file: &mut File, data: &impl serde::Serialize) {
     file.write_all(b"\n").unwrap();
 }
 
+/// Find the Rust item whose name is at `loc`, ex: the `struct_item` for a
+/// struct's name.
+fn rust_item_at<'tree>(
+    root: tree_sitter::Node<'tree>,
+    loc: &Location,
+) -> Option<tree_sitter::Node<'tree>> {
+    let row = loc.lineno as usize - 1;
+    let point = tree_sitter::Point::new(row, loc.col_start as usize);
+    root.descendant_for_point_range(point, point)?.parent()
+}
+
+/// The text of the attributes preceding the item, in source order.  SCIP
+/// doesn't tell us about attributes, so we look at the parse tree.
+fn rust_item_attributes<'a>(item: tree_sitter::Node, source: &'a [u8]) -> Vec<&'a str> {
+    let mut attrs = vec![];
+    let mut sibling = item.prev_sibling();
+    while let Some(node) = sibling {
+        match node.kind() {
+            "attribute_item" => {
+                if let Ok(text) = node.utf8_text(source) {
+                    attrs.push(text);
+                }
+            }
+            "line_comment" | "block_comment" => {}
+            _ => break,
+        }
+        sibling = node.prev_sibling();
+    }
+    // We walked the attributes backwards.
+    attrs.reverse();
+    attrs
+}
+
+/// Find the `#[repr(...)]` of the Rust struct, enum, or union whose name is at
+/// `loc`, returning "Rust" if it has none, or None if `loc` isn't the name of
+/// such a type.
+fn rust_repr_at(root: tree_sitter::Node, source: &[u8], loc: &Location) -> Option<String> {
+    let item = rust_item_at(root, loc)?;
+    if !matches!(item.kind(), "struct_item" | "enum_item" | "union_item") {
+        return None;
+    }
+
+    let reprs: Vec<String> = rust_item_attributes(item, source)
+        .into_iter()
+        .filter_map(repr_attribute_args)
+        .collect();
+    if reprs.is_empty() {
+        return Some("Rust".to_string());
+    }
+    Some(reprs.join(", "))
+}
+
+/// The names of the attributes of the Rust item whose name is at `loc`, ex:
+/// "deprecated" for `#[deprecated(since = "1.0")]`.  See
+/// `symbol_attributes::rust_attribute_name` for the attributes we skip.
+fn rust_attribute_names_at(root: tree_sitter::Node, source: &[u8], loc: &Location) -> Vec<String> {
+    match rust_item_at(root, loc) {
+        Some(item) => rust_item_attributes(item, source)
+            .into_iter()
+            .filter_map(rust_attribute_name)
+            .collect(),
+        None => vec![],
+    }
+}
+
 fn scip_roles_to_searchfox_analysis_kind(roles: i32) -> AnalysisKind {
     macro_rules! map_to_searchfox {
         ($scip:ident, $sfox:ident) => {
@@ -458,6 +527,9 @@ struct SymbolAnalysis {
     norm_sym: Ustr,
     parent_sym: Option<Ustr>,
     contributes_to_parent: bool,
+    /// For Rust, the crate and module path of the symbol.
+    crate_name: Option<Ustr>,
+    module_path: Option<Ustr>,
 }
 
 fn symbol_name(lang_name: &str, subtree_name: Option<&str>, scip_symbol: &str) -> Ustr {
@@ -468,6 +540,30 @@ fn symbol_name(lang_name: &str, subtree_name: Option<&str>, scip_symbol: &str) -
     }
 }
 
+/// Derive the crate and module path of a rust-analyzer symbol from its
+/// package, whose name is the crate's, and its leading namespace descriptors.
+/// For "rust-analyzer cargo my-crate 0.1.0 foo/bar/Baz#" this is "my_crate"
+/// and "my_crate::foo::bar".
+fn rust_crate_and_module(symbol: &scip::types::Symbol) -> (Option<Ustr>, Option<Ustr>) {
+    if symbol.package.name.is_empty() {
+        return (None, None);
+    }
+    let crate_name = symbol.package.name.replace('-', "_");
+    let mut module_pieces = vec![crate_name.clone()];
+    // The last descriptor is the symbol itself, which may be a module.
+    let parents = &symbol.descriptors[..symbol.descriptors.len().saturating_sub(1)];
+    for descriptor in parents {
+        match descriptor.suffix.enum_value() {
+            Ok(Suffix::Package) | Ok(Suffix::Namespace) => {
+                module_pieces.push(descriptor.name.clone())
+            }
+            _ => break,
+        }
+    }
+    let module_path = module_pieces.join("::");
+    (Some(ustr(&crate_name)), Some(ustr(&module_path)))
+}
+
 fn analyse_symbol(
     symbol: &scip::types::Symbol,
     lang: &ScipLang,
@@ -669,13 +765,38 @@ fn analyse_symbol(
         None
     };
 
+    let (crate_name, module_path) = match lang {
+        ScipLang::Rust => rust_crate_and_module(symbol),
+        _ => (None, None),
+    };
+
     SymbolAnalysis {
         kind: last_kind,
         pretty,
         norm_sym,
         parent_sym,
         contributes_to_parent: last_contributes_to_parent,
+        crate_name,
+        module_path,
+    }
+}
+
+/// Load a SCIP index, or an LSIF dump (identified by its ".lsif" extension)
+/// converted into a SCIP index.
+fn load_index(scip_file: &Path) -> scip::types::Index {
+    use protobuf::Message;
+
+    let file = File::open(scip_file).expect("Can't open scip file");
+    if scip_file.extension().and_then(|ext| ext.to_str()) == Some("lsif") {
+        return lsif_to_scip(BufReader::new(file)).expect("Failed to convert lsif dump");
     }
+
+    let byte_count = file.metadata().expect("Failed to get file metadata").len();
+    let mut file = BufReader::new(file);
+    let mut file = protobuf::CodedInputStream::from_buf_read(&mut file);
+    let index = scip::types::Index::parse_from(&mut file).expect("Failed to read scip index");
+    assert_eq!(file.pos(), byte_count, "Should've processed the whole file");
+    index
 }
 
 fn analyze_using_scip(
@@ -685,14 +806,9 @@ fn analyze_using_scip(
     platform: &Option<String>,
     scip_file: PathBuf,
 ) {
-    use protobuf::Message;
     use scip::types::*;
 
-    let file = File::open(&scip_file).expect("Can't open scip file");
-    let byte_count = file.metadata().expect("Failed to get file metadata").len();
-    let mut file = BufReader::new(file);
-    let mut file = protobuf::CodedInputStream::from_buf_read(&mut file);
-    let index = Index::parse_from(&mut file).expect("Failed to read scip index");
+    let index = load_index(&scip_file);
 
     let mut scip_symbol_to_structured: UstrMap<AnalysisStructured> = UstrMap::default();
     let mut our_symbol_to_scip_sym: UstrMap<Ustr> = UstrMap::default();
@@ -929,6 +1045,11 @@ fn analyze_using_scip(
                         None
                     },
                     own_vf_ptr_bytes: None,
+                    repr: None,
+                    crate_name: symbol_info.crate_name,
+                    module_path: symbol_info.module_path,
+                    static_init: None,
+                    locks: vec![],
                     binding_slots: vec![],
                     ontology_slots: vec![],
                     supers,
@@ -937,10 +1058,12 @@ fn analyze_using_scip(
                     overrides,
                     props: vec![],
                     labels: BTreeSet::default(),
+                    attributes: BTreeSet::default(),
 
                     idl_sym: None,
                     subclass_syms: vec![],
                     overridden_by_syms: vec![],
+                    doc: None,
                     variants: vec![],
                     extra: Map::default(),
                 };
@@ -987,6 +1110,7 @@ fn analyze_using_scip(
                                             } else {
                                                 None
                                             },
+                                            align_bytes: None,
                                             labels: BTreeSet::default(),
                                             pointer_info: vec![],
                                         });
@@ -1131,6 +1255,28 @@ fn analyze_using_scip(
                 (false, ustr(&occurrence.symbol))
             };
 
+            // Record the repr of Rust types at their definition so that field
+            // layouts know whether the compiler may reorder the fields, and
+            // the attributes of all items so they can be searched for.
+            if matches!(lang, ScipLang::Rust)
+                && scip_roles_to_searchfox_analysis_kind(occurrence.symbol_roles)
+                    == AnalysisKind::Def
+            {
+                if let Some(structured) = scip_symbol_to_structured.get_mut(&norm_scip_sym) {
+                    let loc = scip_range_to_searchfox_location(&occurrence.range);
+                    if let Some(repr) =
+                        rust_repr_at(parse_tree.root_node(), &source_contents[..], &loc)
+                    {
+                        structured.repr = Some(ustr(&repr));
+                    }
+                    for name in
+                        rust_attribute_names_at(parse_tree.root_node(), &source_contents[..], &loc)
+                    {
+                        structured.attributes.insert(ustr(&name));
+                    }
+                }
+            }
+
             let sinfo = match scip_symbol_to_structured.get(&norm_scip_sym) {
                 Some(s) => s,
                 None => {
@@ -1171,6 +1317,11 @@ fn analyze_using_scip(
                         impl_kind: ustr("external"),
                         size_bytes: None,
                         own_vf_ptr_bytes: None,
+                        repr: None,
+                        crate_name: symbol_info.crate_name,
+                        module_path: symbol_info.module_path,
+                        static_init: None,
+                        locks: vec![],
                         binding_slots: vec![],
                         ontology_slots: vec![],
                         supers: vec![],
@@ -1179,10 +1330,12 @@ fn analyze_using_scip(
                         overrides: vec![],
                         props: vec![],
                         labels: BTreeSet::default(),
+                        attributes: BTreeSet::default(),
 
                         idl_sym: None,
                         subclass_syms: vec![],
                         overridden_by_syms: vec![],
+                        doc: None,
                         variants: vec![],
                         extra: Map::default(),
                     };
@@ -1374,6 +1527,11 @@ fn analyze_using_scip(
             impl_kind: ustr("external"),
             size_bytes: None,
             own_vf_ptr_bytes: None,
+            repr: None,
+            crate_name: symbol_info.crate_name,
+            module_path: symbol_info.module_path,
+            static_init: None,
+            locks: vec![],
             binding_slots: vec![],
             ontology_slots: vec![],
             supers: vec![],
@@ -1382,10 +1540,12 @@ fn analyze_using_scip(
             overrides: vec![],
             props: vec![],
             labels: BTreeSet::default(),
+            attributes: BTreeSet::default(),
 
             idl_sym: None,
             subclass_syms: vec![],
             overridden_by_syms: vec![],
+            doc: None,
             variants: vec![],
             extra: Map::default(),
         };
@@ -1432,8 +1592,6 @@ fn analyze_using_scip(
             }
         }
     }
-
-    assert_eq!(file.pos(), byte_count, "Should've processed the whole file");
 }
 
 fn main() {
@@ -1,8 +1,8 @@
 use std::env::args_os;
 
-use serde_json::{to_string_pretty, to_value, Value};
+use serde_json::{json, to_string_pretty, to_value, Value};
 use tools::{
-    abstract_server::{ErrorDetails, ErrorLayer, ServerError},
+    abstract_server::{ErrorDetails, ErrorKind, ServerError},
     cmd_pipeline::{builder::build_pipeline, parser::OutputFormat, PipelineValues},
 };
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
@@ -45,8 +45,9 @@ async fn main() {
     let (pipeline, output_format) = match build_pipeline(&os_args[0], &os_args[1]) {
         Ok(pipeline) => pipeline,
         Err(ServerError::StickyProblem(ErrorDetails {
-            layer: ErrorLayer::BadInput,
+            kind: ErrorKind::BadQuery,
             message,
+            ..
         })) => {
             println!("{}", message);
             std::process::exit(1);
@@ -56,7 +57,19 @@ async fn main() {
         }
     };
 
-    let results = pipeline.run(false).await;
+    let (results, timing) = pipeline.run_timed(false).await;
+
+    // Report any trouble talking to a remote server on stderr so that it shows
+    // up in CI logs without interfering with the output.
+    for (endpoint, metrics) in pipeline.server.request_metrics() {
+        if metrics.failures > 0 || metrics.short_circuited > 0 {
+            eprintln!(
+                "Remote {} endpoint requests: {}",
+                endpoint,
+                to_value(metrics).unwrap()
+            );
+        }
+    }
 
     let emit_json = |val: &Value| {
         if output_format == OutputFormat::Concise {
@@ -68,88 +81,122 @@ async fn main() {
         }
     };
 
-    std::process::exit(match results {
-        Ok(PipelineValues::Void) => {
-            println!("Void result.");
-            0
-        }
-        Ok(PipelineValues::IdentifierList(il)) => {
-            for identifier in il.identifiers {
-                println!("{}", identifier);
+    // With `--output-file` the final value is written to the file in the
+    // requested format instead of being printed.
+    let exit_code = if let Some(sink) = &pipeline.output_sink {
+        match results.and_then(|values| sink.write(&values)) {
+            Ok(()) => 0,
+            Err(err) => {
+                println!("Pipeline Error!");
+                println!("{}", err);
+                1
             }
-            0
-        }
-        Ok(PipelineValues::SymbolList(sl)) => {
-            emit_json(&to_value(sl).unwrap());
-            0
         }
-        Ok(PipelineValues::SymbolCrossrefInfoList(sl)) => {
-            for symbol_info in sl.symbol_crossref_infos {
-                emit_json(&symbol_info.crossref_info);
+    } else {
+        match results {
+            Ok(PipelineValues::Void) => {
+                println!("Void result.");
+                0
             }
-            0
-        }
-        Ok(PipelineValues::SymbolGraphCollection(sgc)) => {
-            emit_json(&sgc.to_json());
-            0
-        }
-        Ok(PipelineValues::FlattenedResultsBundle(frb)) => {
-            emit_json(&to_value(frb).unwrap());
-            0
-        }
-        Ok(PipelineValues::GraphResultsBundle(grb)) => {
-            emit_json(&to_value(grb).unwrap());
-            0
-        }
-        Ok(PipelineValues::HtmlExcerpts(he)) => {
-            for file_excerpts in he.by_file {
-                //println!("HTML excerpts from: {}", file_excerpts.file);
-                for str in file_excerpts.excerpts {
-                    println!("{}", str);
+            Ok(PipelineValues::IdentifierList(il)) => {
+                for identifier in il.identifiers {
+                    println!("{}", identifier);
                 }
+                0
             }
-            0
-        }
-        Ok(PipelineValues::TextFile(fb)) => {
-            println!("{}", fb.contents);
-            0
-        }
-        Ok(PipelineValues::JsonRecords(jr)) => {
-            for file_records in jr.by_file {
-                for value in file_records.records {
-                    emit_json(&value);
+            Ok(PipelineValues::SymbolList(sl)) => {
+                emit_json(&to_value(sl).unwrap());
+                0
+            }
+            Ok(PipelineValues::SymbolCrossrefInfoList(sl)) => {
+                for symbol_info in sl.symbol_crossref_infos {
+                    emit_json(&symbol_info.crossref_info);
                 }
+                0
+            }
+            Ok(PipelineValues::SymbolGraphCollection(sgc)) => {
+                emit_json(&sgc.to_json());
+                0
+            }
+            Ok(PipelineValues::FlattenedResultsBundle(frb)) => {
+                emit_json(&to_value(frb).unwrap());
+                0
+            }
+            Ok(PipelineValues::GraphResultsBundle(grb)) => {
+                emit_json(&to_value(grb).unwrap());
+                0
+            }
+            Ok(PipelineValues::HtmlExcerpts(he)) => {
+                for file_excerpts in he.by_file {
+                    //println!("HTML excerpts from: {}", file_excerpts.file);
+                    for str in file_excerpts.excerpts {
+                        println!("{}", str);
+                    }
+                }
+                0
+            }
+            Ok(PipelineValues::TextFile(fb)) => {
+                println!("{}", fb.contents);
+                0
+            }
+            Ok(PipelineValues::JsonRecords(jr)) => {
+                for file_records in jr.by_file {
+                    for value in file_records.records {
+                        emit_json(&value);
+                    }
+                }
+                0
+            }
+            Ok(PipelineValues::JsonValue(jv)) => {
+                emit_json(&jv.value);
+                0
+            }
+            Ok(PipelineValues::JsonValueList(jvl)) => {
+                emit_json(&to_value(jvl).unwrap());
+                0
+            }
+            Ok(PipelineValues::FileMatches(fm)) => {
+                emit_json(&to_value(fm).unwrap());
+                0
+            }
+            Ok(PipelineValues::TextMatches(tm)) => {
+                emit_json(&to_value(tm).unwrap());
+                0
+            }
+            Ok(PipelineValues::BatchGroups(bg)) => {
+                emit_json(&to_value(bg).unwrap());
+                0
+            }
+            Ok(PipelineValues::SymbolTreeTableList(sttl)) => {
+                emit_json(&to_value(sttl).unwrap());
+                0
+            }
+            Ok(PipelineValues::SymbolHitGroups(shg)) => {
+                emit_json(&to_value(shg).unwrap());
+                0
+            }
+            Ok(PipelineValues::SymbolExamplesList(sel)) => {
+                emit_json(&to_value(sel).unwrap());
+                0
+            }
+            Ok(PipelineValues::DataTable(dt)) => {
+                emit_json(&to_value(dt).unwrap());
+                0
+            }
+            Err(err) => {
+                println!("Pipeline Error!");
+                println!("{}", err);
+                1
             }
-            0
-        }
-        Ok(PipelineValues::JsonValue(jv)) => {
-            emit_json(&jv.value);
-            0
-        }
-        Ok(PipelineValues::JsonValueList(jvl)) => {
-            emit_json(&to_value(jvl).unwrap());
-            0
-        }
-        Ok(PipelineValues::FileMatches(fm)) => {
-            emit_json(&to_value(fm).unwrap());
-            0
-        }
-        Ok(PipelineValues::TextMatches(tm)) => {
-            emit_json(&to_value(tm).unwrap());
-            0
-        }
-        Ok(PipelineValues::BatchGroups(bg)) => {
-            emit_json(&to_value(bg).unwrap());
-            0
-        }
-        Ok(PipelineValues::SymbolTreeTableList(sttl)) => {
-            emit_json(&to_value(sttl).unwrap());
-            0
-        }
-        Err(err) => {
-            println!("Pipeline Error!");
-            println!("{:?}", err);
-            1
         }
-    });
+    };
+
+    // The timing goes after the results, as its own JSON value, with a human
+    // readable version on stderr.
+    if pipeline.timing {
+        emit_json(&json!({ "timing": timing }));
+        eprint!("{}", timing);
+    }
+
+    std::process::exit(exit_code);
 }
@@ -0,0 +1,434 @@
+//! Metadata extraction for the files `select_formatting` classifies as Binary
+//! so that their directory listing entries and `describe-file` output have
+//! something more useful to say than nothing.  We look at the magic numbers
+//! and headers of common image, font, and audio formats; anything else gets no
+//! metadata.  The results are stored alongside the text file descriptions in
+//! the index's `description/` sidecars during repo data ingestion.
+
+use std::convert::TryInto;
+
+use serde::Serialize;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BinaryKind {
+    Image,
+    Font,
+    Audio,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BinaryMetadata {
+    pub kind: BinaryKind,
+    /// The file format, ex: "PNG" or "WOFF".
+    pub format: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    /// The font family from the font's `name` table.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub family: Option<String>,
+    /// The font's table tags, ex: "cmap", "glyf".
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tables: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_secs: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sample_rate: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channels: Option<u16>,
+}
+
+impl BinaryMetadata {
+    fn new(kind: BinaryKind, format: &'static str) -> Self {
+        BinaryMetadata {
+            kind,
+            format,
+            width: None,
+            height: None,
+            family: None,
+            tables: vec![],
+            duration_secs: None,
+            sample_rate: None,
+            channels: None,
+        }
+    }
+
+    fn image(format: &'static str, width: u32, height: u32) -> Self {
+        BinaryMetadata {
+            width: Some(width),
+            height: Some(height),
+            ..BinaryMetadata::new(BinaryKind::Image, format)
+        }
+    }
+
+    /// A one-line summary for use as the file's description, ex:
+    /// "PNG image, 16x16".
+    pub fn summary(&self) -> String {
+        let mut parts = vec![match self.kind {
+            BinaryKind::Image => format!("{} image", self.format),
+            BinaryKind::Font => format!("{} font", self.format),
+            BinaryKind::Audio => format!("{} audio", self.format),
+        }];
+        if let (Some(width), Some(height)) = (self.width, self.height) {
+            parts.push(format!("{}x{}", width, height));
+        }
+        if let Some(family) = &self.family {
+            parts.push(family.clone());
+        }
+        if !self.tables.is_empty() {
+            parts.push(format!("{} tables", self.tables.len()));
+        }
+        if let Some(duration) = self.duration_secs {
+            parts.push(format!("{:.2}s", duration));
+        }
+        if let Some(sample_rate) = self.sample_rate {
+            parts.push(format!("{} Hz", sample_rate));
+        }
+        match self.channels {
+            Some(1) => parts.push("mono".to_string()),
+            Some(2) => parts.push("stereo".to_string()),
+            Some(channels) => parts.push(format!("{} channels", channels)),
+            None => {}
+        }
+        parts.join(", ")
+    }
+}
+
+fn bytes_at<const N: usize>(data: &[u8], offset: usize) -> Option<[u8; N]> {
+    data.get(offset..offset.checked_add(N)?)?.try_into().ok()
+}
+
+fn be_u16(data: &[u8], offset: usize) -> Option<u16> {
+    bytes_at(data, offset).map(u16::from_be_bytes)
+}
+
+fn be_u32(data: &[u8], offset: usize) -> Option<u32> {
+    bytes_at(data, offset).map(u32::from_be_bytes)
+}
+
+fn le_u16(data: &[u8], offset: usize) -> Option<u16> {
+    bytes_at(data, offset).map(u16::from_le_bytes)
+}
+
+fn le_u32(data: &[u8], offset: usize) -> Option<u32> {
+    bytes_at(data, offset).map(u32::from_le_bytes)
+}
+
+fn le_i64(data: &[u8], offset: usize) -> Option<i64> {
+    bytes_at(data, offset).map(i64::from_le_bytes)
+}
+
+/// Extract what metadata we can from the contents of a binary file.
+pub fn extract_binary_metadata(data: &[u8]) -> Option<BinaryMetadata> {
+    match data {
+        [0x89, b'P', b'N', b'G', ..] => png_metadata(data),
+        [b'G', b'I', b'F', b'8', ..] => Some(BinaryMetadata::image(
+            "GIF",
+            le_u16(data, 6)? as u32,
+            le_u16(data, 8)? as u32,
+        )),
+        [0xff, 0xd8, ..] => jpeg_metadata(data),
+        [b'B', b'M', ..] => Some(BinaryMetadata::image(
+            "BMP",
+            le_u32(data, 18)?,
+            (le_u32(data, 22)? as i32).unsigned_abs(),
+        )),
+        // The first entry of the icon directory; a size of 0 means 256.
+        [0, 0, 1, 0, ..] => Some(BinaryMetadata::image(
+            "ICO",
+            match *data.get(6)? {
+                0 => 256,
+                w => w as u32,
+            },
+            match *data.get(7)? {
+                0 => 256,
+                h => h as u32,
+            },
+        )),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => webp_metadata(data),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E', ..] => wav_metadata(data),
+        [b'f', b'L', b'a', b'C', ..] => flac_metadata(data),
+        [b'O', b'g', b'g', b'S', ..] => ogg_metadata(data),
+        [0, 1, 0, 0, ..] => sfnt_metadata(data, "TrueType"),
+        [b't', b'r', b'u', b'e', ..] => sfnt_metadata(data, "TrueType"),
+        [b'O', b'T', b'T', b'O', ..] => sfnt_metadata(data, "OpenType"),
+        [b't', b't', b'c', b'f', ..] => {
+            Some(BinaryMetadata::new(BinaryKind::Font, "TrueType collection"))
+        }
+        [b'w', b'O', b'F', b'F', ..] => woff_metadata(data),
+        [b'w', b'O', b'F', b'2', ..] => Some(BinaryMetadata::new(BinaryKind::Font, "WOFF2")),
+        _ => None,
+    }
+}
+
+fn png_metadata(data: &[u8]) -> Option<BinaryMetadata> {
+    // The IHDR chunk must come first, right after the 8 byte signature.
+    if data.get(12..16)? != b"IHDR" {
+        return None;
+    }
+    Some(BinaryMetadata::image(
+        "PNG",
+        be_u32(data, 16)?,
+        be_u32(data, 20)?,
+    ))
+}
+
+fn jpeg_metadata(data: &[u8]) -> Option<BinaryMetadata> {
+    let mut offset = 2;
+    loop {
+        if *data.get(offset)? != 0xff {
+            return None;
+        }
+        let marker = *data.get(offset + 1)?;
+        let length = be_u16(data, offset + 2)? as usize;
+        // Start-of-frame markers, excluding DHT, JPG, and DAC which share the
+        // range.
+        if (0xc0..=0xcf).contains(&marker) && ![0xc4, 0xc8, 0xcc].contains(&marker) {
+            return Some(BinaryMetadata::image(
+                "JPEG",
+                be_u16(data, offset + 7)? as u32,
+                be_u16(data, offset + 5)? as u32,
+            ));
+        }
+        offset += 2 + length;
+    }
+}
+
+fn webp_metadata(data: &[u8]) -> Option<BinaryMetadata> {
+    let (width, height) = match data.get(12..16)? {
+        b"VP8 " => (
+            (le_u16(data, 26)? & 0x3fff) as u32,
+            (le_u16(data, 28)? & 0x3fff) as u32,
+        ),
+        b"VP8L" => {
+            let bits = le_u32(data, 21)?;
+            ((bits & 0x3fff) + 1, ((bits >> 14) & 0x3fff) + 1)
+        }
+        b"VP8X" => {
+            let three_bytes = |offset| -> Option<u32> {
+                let [a, b, c] = bytes_at::<3>(data, offset)?;
+                Some(u32::from_le_bytes([a, b, c, 0]) + 1)
+            };
+            (three_bytes(24)?, three_bytes(27)?)
+        }
+        _ => return None,
+    };
+    Some(BinaryMetadata::image("WebP", width, height))
+}
+
+fn wav_metadata(data: &[u8]) -> Option<BinaryMetadata> {
+    let mut metadata = BinaryMetadata::new(BinaryKind::Audio, "WAV");
+    let mut byte_rate = None;
+    let mut offset = 12;
+    while let Some(id) = bytes_at::<4>(data, offset) {
+        let size = le_u32(data, offset + 4)? as usize;
+        match &id {
+            b"fmt " => {
+                metadata.channels = le_u16(data, offset + 10);
+                metadata.sample_rate = le_u32(data, offset + 12);
+                byte_rate = le_u32(data, offset + 16).filter(|rate| *rate > 0);
+            }
+            b"data" => {
+                metadata.duration_secs = byte_rate.map(|rate| size as f64 / rate as f64);
+                break;
+            }
+            _ => {}
+        }
+        // Chunks are padded to an even size.
+        offset += 8 + size + (size & 1);
+    }
+    Some(metadata)
+}
+
+fn flac_metadata(data: &[u8]) -> Option<BinaryMetadata> {
+    // The STREAMINFO block always comes first; after its 4 byte header are the
+    // block and frame sizes followed by 20 bits of sample rate, 3 bits of
+    // channels - 1, 5 bits of bits per sample - 1, and 36 bits of total samples.
+    let bits = u64::from_be_bytes(bytes_at(data, 18)?);
+    let sample_rate = (bits >> 44) as u32;
+    let total_samples = bits & 0xf_ffff_ffff;
+    let mut metadata = BinaryMetadata::new(BinaryKind::Audio, "FLAC");
+    metadata.sample_rate = Some(sample_rate);
+    metadata.channels = Some(((bits >> 41) & 0x7) as u16 + 1);
+    if sample_rate > 0 && total_samples > 0 {
+        metadata.duration_secs = Some(total_samples as f64 / sample_rate as f64);
+    }
+    Some(metadata)
+}
+
+fn ogg_metadata(data: &[u8]) -> Option<BinaryMetadata> {
+    // The first page holds the codec's identification header after the 27 byte
+    // page header and its segment table.
+    let segments = *data.get(26)? as usize;
+    let packet = data.get(27 + segments..)?;
+    let (mut metadata, granule_rate, pre_skip) = if packet.starts_with(b"\x01vorbis") {
+        let mut metadata = BinaryMetadata::new(BinaryKind::Audio, "Ogg Vorbis");
+        metadata.channels = packet.get(11).map(|c| *c as u16);
+        metadata.sample_rate = le_u32(packet, 12);
+        (metadata, le_u32(packet, 12)?, 0)
+    } else if packet.starts_with(b"OpusHead") {
+        let mut metadata = BinaryMetadata::new(BinaryKind::Audio, "Ogg Opus");
+        metadata.channels = packet.get(9).map(|c| *c as u16);
+        metadata.sample_rate = le_u32(packet, 12);
+        // Opus granule positions are always at 48kHz.
+        (metadata, 48000, le_u16(packet, 10)? as i64)
+    } else {
+        return Some(BinaryMetadata::new(BinaryKind::Audio, "Ogg"));
+    };
+
+    // The duration is the granule position of the last page.
+    let last_page = data.windows(4).rposition(|w| w == b"OggS")?;
+    let granule = le_i64(data, last_page + 6)?;
+    if granule_rate > 0 && granule > pre_skip {
+        metadata.duration_secs = Some((granule - pre_skip) as f64 / granule_rate as f64);
+    }
+    Some(metadata)
+}
+
+fn sfnt_metadata(data: &[u8], format: &'static str) -> Option<BinaryMetadata> {
+    let mut metadata = BinaryMetadata::new(BinaryKind::Font, format);
+    let num_tables = be_u16(data, 4)? as usize;
+    for i in 0..num_tables {
+        let record = 12 + i * 16;
+        let tag = bytes_at::<4>(data, record)?;
+        if &tag == b"name" {
+            let offset = be_u32(data, record + 8)? as usize;
+            let length = be_u32(data, record + 12)? as usize;
+            metadata.family = data
+                .get(offset..offset.checked_add(length)?)
+                .and_then(font_family_name);
+        }
+        metadata
+            .tables
+            .push(String::from_utf8_lossy(&tag).trim_end().to_string());
+    }
+    Some(metadata)
+}
+
+fn woff_metadata(data: &[u8]) -> Option<BinaryMetadata> {
+    let mut metadata = BinaryMetadata::new(BinaryKind::Font, "WOFF");
+    let num_tables = be_u16(data, 12)? as usize;
+    for i in 0..num_tables {
+        let entry = 44 + i * 20;
+        let tag = bytes_at::<4>(data, entry)?;
+        let offset = be_u32(data, entry + 4)? as usize;
+        let comp_length = be_u32(data, entry + 8)? as usize;
+        let orig_length = be_u32(data, entry + 12)? as usize;
+        // We only look at the name table if it's stored uncompressed.
+        if &tag == b"name" && comp_length == orig_length {
+            metadata.family = data
+                .get(offset..offset.checked_add(comp_length)?)
+                .and_then(font_family_name);
+        }
+        metadata
+            .tables
+            .push(String::from_utf8_lossy(&tag).trim_end().to_string());
+    }
+    Some(metadata)
+}
+
+/// Find the font family (name id 1) in a `name` table, preferring the Windows
+/// platform's UTF-16 strings over the Macintosh platform's 8-bit ones.
+fn font_family_name(table: &[u8]) -> Option<String> {
+    let count = be_u16(table, 2)? as usize;
+    let string_offset = be_u16(table, 4)? as usize;
+    let mut fallback = None;
+    for i in 0..count {
+        let record = 6 + i * 12;
+        let platform = be_u16(table, record)?;
+        let name_id = be_u16(table, record + 6)?;
+        if name_id != 1 {
+            continue;
+        }
+        let length = be_u16(table, record + 8)? as usize;
+        let offset = string_offset + be_u16(table, record + 10)? as usize;
+        let bytes = table.get(offset..offset + length)?;
+        match platform {
+            0 | 3 => {
+                let units: Vec<u16> = bytes
+                    .chunks_exact(2)
+                    .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                    .collect();
+                return Some(String::from_utf16_lossy(&units));
+            }
+            1 => fallback = Some(String::from_utf8_lossy(bytes).to_string()),
+            _ => {}
+        }
+    }
+    fallback
+}
+
+#[test]
+fn test_image_metadata() {
+    let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+    png.extend_from_slice(&16u32.to_be_bytes());
+    png.extend_from_slice(&32u32.to_be_bytes());
+    let metadata = extract_binary_metadata(&png).unwrap();
+    assert_eq!(metadata.summary(), "PNG image, 16x32");
+
+    let gif = b"GIF89a\x0a\x00\x14\x00";
+    assert_eq!(
+        extract_binary_metadata(gif).unwrap().summary(),
+        "GIF image, 10x20"
+    );
+
+    // SOI, an APP0 segment, then SOF0 with height 48 and width 64.
+    let jpeg = b"\xff\xd8\xff\xe0\x00\x04\x00\x00\xff\xc0\x00\x11\x08\x00\x30\x00\x40";
+    let metadata = extract_binary_metadata(jpeg).unwrap();
+    assert_eq!((metadata.width, metadata.height), (Some(64), Some(48)));
+
+    assert_eq!(
+        extract_binary_metadata(b"\x00\x00\x01\x00\x01\x00\x00\x00")
+            .unwrap()
+            .width,
+        Some(256)
+    );
+    assert_eq!(extract_binary_metadata(b"not an image"), None);
+}
+
+#[test]
+fn test_audio_metadata() {
+    let mut wav = b"RIFF\0\0\0\0WAVEfmt \x10\0\0\0\x01\0\x02\0".to_vec();
+    wav.extend_from_slice(&44100u32.to_le_bytes());
+    wav.extend_from_slice(&(44100u32 * 4).to_le_bytes());
+    wav.extend_from_slice(b"\x04\0\x10\0data");
+    wav.extend_from_slice(&(44100u32 * 6).to_le_bytes());
+    let metadata = extract_binary_metadata(&wav).unwrap();
+    assert_eq!(metadata.summary(), "WAV audio, 1.50s, 44100 Hz, stereo");
+
+    let mut flac = b"fLaC\0\0\0\x22".to_vec();
+    flac.extend_from_slice(&[0; 10]);
+    let bits: u64 = (48000 << 44) | (1 << 41) | (15 << 36) | 96000;
+    flac.extend_from_slice(&bits.to_be_bytes());
+    let metadata = extract_binary_metadata(&flac).unwrap();
+    assert_eq!(metadata.duration_secs, Some(2.0));
+    assert_eq!(metadata.channels, Some(2));
+}
+
+#[test]
+fn test_font_metadata() {
+    // A name table with a single Windows family name record.
+    let family: Vec<u8> = "Fira"
+        .encode_utf16()
+        .flat_map(|u| u.to_be_bytes())
+        .collect();
+    let mut name = vec![0, 0, 0, 1, 0, 18];
+    name.extend_from_slice(&[0, 3, 0, 1, 0x04, 0x09, 0, 1, 0, family.len() as u8, 0, 0]);
+    name.extend_from_slice(&family);
+
+    let mut ttf = b"\0\x01\0\0\0\x02\0\0\0\0\0\0".to_vec();
+    let name_offset = 12 + 2 * 16;
+    ttf.extend_from_slice(b"cmap\0\0\0\0\0\0\0\0\0\0\0\0");
+    ttf.extend_from_slice(b"name\0\0\0\0");
+    ttf.extend_from_slice(&(name_offset as u32).to_be_bytes());
+    ttf.extend_from_slice(&(name.len() as u32).to_be_bytes());
+    ttf.extend_from_slice(&name);
+
+    let metadata = extract_binary_metadata(&ttf).unwrap();
+    assert_eq!(metadata.kind, BinaryKind::Font);
+    assert_eq!(metadata.family.as_deref(), Some("Fira"));
+    assert_eq!(metadata.tables, vec!["cmap", "name"]);
+    assert_eq!(metadata.summary(), "TrueType font, Fira, 2 tables");
+}
@@ -8,14 +8,19 @@ use crate::{
     },
     query::chew_query::QueryPipelineGroupBuilder,
 };
+use std::time::Duration;
 use tracing::{trace, trace_span};
 use url::Url;
 
 use crate::{
     abstract_server::{
-        make_local_server, make_remote_server, ErrorDetails, ErrorLayer, Result, ServerError,
+        make_local_server, make_remote_server, ErrorDetails, ErrorKind, ErrorLayer, Result,
+        RetryPolicy, ServerError,
+    },
+    cmd_pipeline::{
+        output_sink::OutputSink,
+        parser::{Command, OutputFormat, ToolOpts},
     },
-    cmd_pipeline::parser::{Command, OutputFormat, ToolOpts},
 };
 
 use super::{
@@ -41,8 +46,78 @@ use super::{
 };
 use super::{cmd_show_html::ShowHtmlCommand, interface::ParallelPipelines};
 
+use super::cmd_annotate::AnnotateCommand;
+use super::cmd_annotations::AnnotationsCommand;
+use super::cmd_api_surface::ApiSurfaceCommand;
+use super::cmd_audit_external_links::AuditExternalLinksCommand;
+use super::cmd_badge::BadgeCommand;
+use super::cmd_bug_refs::BugRefsCommand;
+use super::cmd_cat_source::CatSourceCommand;
+use super::cmd_check_layering::CheckLayeringCommand;
+use super::cmd_check_layout_budget::CheckLayoutBudgetCommand;
+use super::cmd_completions::CompletionsCommand;
+use super::cmd_convert_lsif::ConvertLsifCommand;
+use super::cmd_describe_file::DescribeFileCommand;
+use super::cmd_diff_file::DiffFileCommand;
+use super::cmd_doc_symbol::DocSymbolCommand;
+use super::cmd_explain::ExplainCommand;
+use super::cmd_export_bundle::ExportBundleCommand;
+use super::cmd_export_scip::ExportScipCommand;
+use super::cmd_field_at::FieldAtCommand;
+use super::cmd_file_log::FileLogCommand;
+use super::cmd_find_duplicates::FindDuplicatesCommand;
+use super::cmd_find_examples::FindExamplesCommand;
+use super::cmd_find_field_type::FindFieldTypeCommand;
+use super::cmd_find_overrides::FindOverridesCommand;
+use super::cmd_find_paths::FindPathsCommand;
+use super::cmd_flaky_heatmap::FlakyHeatmapCommand;
+use super::cmd_format_table::FormatTableCommand;
+use super::cmd_gc_index::GcIndexCommand;
+use super::cmd_generate_tags::GenerateTagsCommand;
+use super::cmd_graph_cycles::GraphCyclesCommand;
+use super::cmd_graph_metrics::GraphMetricsCommand;
+use super::cmd_group_hits::GroupHitsCommand;
+use super::cmd_help_json::HelpJsonCommand;
+use super::cmd_hex_dump::HexDumpCommand;
+use super::cmd_hook_results::HookResultsCommand;
+use super::cmd_ingest_query_logs::IngestQueryLogsCommand;
+use super::cmd_ingest_test_stats::IngestTestStatsCommand;
+use super::cmd_js_module_graph::JsModuleGraphCommand;
+use super::cmd_l10n_xref::L10nXrefCommand;
+use super::cmd_language_stats::LanguageStatsCommand;
+use super::cmd_line_history::LineHistoryCommand;
+use super::cmd_load_graph::LoadGraphCommand;
+use super::cmd_lock_report::LockReportCommand;
+use super::cmd_map_script::MapScriptCommand;
+use super::cmd_notify::NotifyCommand;
+use super::cmd_offsetof::OffsetofCommand;
+use super::cmd_ownership_report::OwnershipReportCommand;
+use super::cmd_permalinks::PermalinksCommand;
+use super::cmd_popular_queries::PopularQueriesCommand;
+use super::cmd_precompute::PrecomputeCommand;
+use super::cmd_reachable_from::ReachableFromCommand;
+use super::cmd_refcount_cycles::RefcountCyclesCommand;
+use super::cmd_refine::RefineCommand;
+use super::cmd_run_post_index_hooks::RunPostIndexHooksCommand;
+use super::cmd_run_spec::RunSpecCommand;
+use super::cmd_rust_crate_graph::RustCrateGraphCommand;
+use super::cmd_save_graph::SaveGraphCommand;
+use super::cmd_search_attributes::SearchAttributesCommand;
+use super::cmd_share::ShareCommand;
+use super::cmd_show_index_info::ShowIndexInfoCommand;
+use super::cmd_sizeof::SizeofCommand;
+use super::cmd_static_inits::StaticInitsCommand;
+use super::cmd_structured_columns::StructuredColumnsCommand;
+use super::cmd_symbol_set::SymbolSetCommand;
+use super::cmd_tests_for_symbol::TestsForSymbolCommand;
+use super::cmd_todos::TodosCommand;
+use super::cmd_trend::TrendCommand;
+use super::cmd_tui::TuiCommand;
+use super::cmd_verify_index::VerifyIndexCommand;
 use super::interface::ServerPipeline;
+use super::plugins::fab_plugin_command;
 
+#[derive(Clone, Copy, Debug)]
 pub enum CommandSafetyLevel {
     DangerousToolUseAllowed,
     WebSafety,
@@ -53,54 +128,248 @@ pub fn fab_command_from_opts(
     safety: CommandSafetyLevel,
 ) -> Result<Box<dyn PipelineCommand + Send + Sync>> {
     match (opts.cmd, safety) {
+        (Command::Annotate(a), CommandSafetyLevel::DangerousToolUseAllowed) => {
+            Ok(Box::new(AnnotateCommand { args: a }))
+        }
+
+        (Command::Annotations(a), _) => Ok(Box::new(AnnotationsCommand { args: a })),
+
+        (Command::ApiSurface(api_surface), _) => {
+            Ok(Box::new(ApiSurfaceCommand { args: api_surface }))
+        }
+
+        (Command::AuditExternalLinks(ael), CommandSafetyLevel::DangerousToolUseAllowed) => {
+            Ok(Box::new(AuditExternalLinksCommand { args: ael }))
+        }
+
         (Command::AugmentResults(ar), _) => Ok(Box::new(AugmentResultsCommand { args: ar })),
 
+        (Command::Badge(b), _) => Ok(Box::new(BadgeCommand { args: b })),
+
         (Command::BatchRender(br), _) => Ok(Box::new(BatchRenderCommand { args: br })),
 
+        (Command::BugRefs(bug_refs), _) => Ok(Box::new(BugRefsCommand { args: bug_refs })),
+
         (Command::CatHtml(ch), _) => Ok(Box::new(CatHtmlCommand { args: ch })),
 
+        (Command::CatSource(cs), _) => Ok(Box::new(CatSourceCommand { args: cs })),
+
+        (Command::CheckLayering(cl), CommandSafetyLevel::DangerousToolUseAllowed) => {
+            Ok(Box::new(CheckLayeringCommand { args: cl }))
+        }
+
+        (Command::CheckLayoutBudget(clb), CommandSafetyLevel::DangerousToolUseAllowed) => {
+            Ok(Box::new(CheckLayoutBudgetCommand { args: clb }))
+        }
+
+        (Command::Completions(c), CommandSafetyLevel::DangerousToolUseAllowed) => {
+            Ok(Box::new(CompletionsCommand { args: c }))
+        }
+
+        (Command::ConvertLsif(cl), CommandSafetyLevel::DangerousToolUseAllowed) => {
+            Ok(Box::new(ConvertLsifCommand { args: cl }))
+        }
+
         (Command::CrossrefExpand(ce), _) => Ok(Box::new(CrossrefExpandCommand { args: ce })),
 
         (Command::CrossrefLookup(cl), _) => Ok(Box::new(CrossrefLookupCommand { args: cl })),
 
+        (Command::DescribeFile(describe_file), _) => Ok(Box::new(DescribeFileCommand {
+            args: describe_file,
+        })),
+
+        (Command::DiffFile(df), _) => Ok(Box::new(DiffFileCommand { args: df })),
+
+        (Command::DocSymbol(ds), _) => Ok(Box::new(DocSymbolCommand { args: ds })),
+
+        // Specs are read from arbitrary filesystem paths, and the errors for
+        // files that don't parse can quote their contents.
+        (Command::Explain(e), CommandSafetyLevel::DangerousToolUseAllowed) => {
+            Ok(Box::new(ExplainCommand { args: e }))
+        }
+        (Command::Explain(e), CommandSafetyLevel::WebSafety) if e.spec.is_none() => {
+            Ok(Box::new(ExplainCommand { args: e }))
+        }
+
+        (Command::ExportBundle(eb), _) => Ok(Box::new(ExportBundleCommand { args: eb })),
+
+        (Command::ExportScip(es), CommandSafetyLevel::DangerousToolUseAllowed) => {
+            Ok(Box::new(ExportScipCommand { args: es }))
+        }
+
+        (Command::FieldAt(field_at), _) => Ok(Box::new(FieldAtCommand { args: field_at })),
+
+        (Command::FileLog(fl), _) => Ok(Box::new(FileLogCommand { args: fl })),
+
         (Command::FilterAnalysis(fa), _) => Ok(Box::new(FilterAnalysisCommand { args: fa })),
 
+        (Command::FindDuplicates(fd), _) => Ok(Box::new(FindDuplicatesCommand { args: fd })),
+
+        (Command::FindExamples(fe), _) => Ok(Box::new(FindExamplesCommand { args: fe })),
+
+        (Command::FindFieldType(fft), _) => Ok(Box::new(FindFieldTypeCommand { args: fft })),
+
+        (Command::FindOverrides(fo), _) => Ok(Box::new(FindOverridesCommand { args: fo })),
+
+        (Command::FindPaths(fp), _) => Ok(Box::new(FindPathsCommand { args: fp })),
+
+        (Command::FlakyHeatmap(fh), _) => Ok(Box::new(FlakyHeatmapCommand { args: fh })),
+
         (Command::FormatSymbols(fs), _) => Ok(Box::new(FormatSymbolsCommand { args: fs })),
 
+        (Command::FormatTable(ft), _) => Ok(Box::new(FormatTableCommand { args: ft })),
+
+        (Command::GcIndex(gi), CommandSafetyLevel::DangerousToolUseAllowed) => {
+            Ok(Box::new(GcIndexCommand { args: gi }))
+        }
+
+        (Command::GenerateTags(gt), _) => Ok(Box::new(GenerateTagsCommand { args: gt })),
+
         (Command::Graph(g), _) => Ok(Box::new(GraphCommand { args: g })),
 
+        (Command::GraphCycles(gc), _) => Ok(Box::new(GraphCyclesCommand { args: gc })),
+
+        (Command::GraphMetrics(gm), _) => Ok(Box::new(GraphMetricsCommand { args: gm })),
+
+        (Command::GroupHits(gh), _) => Ok(Box::new(GroupHitsCommand { args: gh })),
+
+        (Command::HelpJson(hj), _) => Ok(Box::new(HelpJsonCommand { args: hj })),
+
+        (Command::HexDump(hex_dump), _) => Ok(Box::new(HexDumpCommand { args: hex_dump })),
+
+        (Command::HookResults(hr), _) => Ok(Box::new(HookResultsCommand { args: hr })),
+
+        (Command::IngestQueryLogs(iql), CommandSafetyLevel::DangerousToolUseAllowed) => {
+            Ok(Box::new(IngestQueryLogsCommand { args: iql }))
+        }
+
+        (Command::IngestTestStats(its), CommandSafetyLevel::DangerousToolUseAllowed) => {
+            Ok(Box::new(IngestTestStatsCommand { args: its }))
+        }
+
+        (Command::JsModuleGraph(jmg), _) => Ok(Box::new(JsModuleGraphCommand { args: jmg })),
+
         (Command::JumprefLookup(cl), _) => Ok(Box::new(JumprefLookupCommand { args: cl })),
 
+        (Command::L10nXref(lx), _) => Ok(Box::new(L10nXrefCommand { args: lx })),
+
+        (Command::LanguageStats(ls), _) => Ok(Box::new(LanguageStatsCommand { args: ls })),
+
+        (Command::LineHistory(lh), _) => Ok(Box::new(LineHistoryCommand { args: lh })),
+
+        (Command::LoadGraph(lg), CommandSafetyLevel::DangerousToolUseAllowed) => {
+            Ok(Box::new(LoadGraphCommand { args: lg }))
+        }
+
+        (Command::LockReport(lr), _) => Ok(Box::new(LockReportCommand { args: lr })),
+
+        (Command::MapScript(ms), CommandSafetyLevel::DangerousToolUseAllowed) => {
+            Ok(Box::new(MapScriptCommand { args: ms }))
+        }
+
         (Command::MergeAnalyses(ma), _) => Ok(Box::new(MergeAnalysesCommand { args: ma })),
 
+        (Command::Notify(n), CommandSafetyLevel::DangerousToolUseAllowed) => {
+            Ok(Box::new(NotifyCommand { args: n }))
+        }
+
+        (Command::Offsetof(offsetof), _) => Ok(Box::new(OffsetofCommand { args: offsetof })),
+
+        (Command::OwnershipReport(or), _) => Ok(Box::new(OwnershipReportCommand { args: or })),
+
+        (Command::Permalinks(p), _) => Ok(Box::new(PermalinksCommand { args: p })),
+
+        (Command::PopularQueries(pq), _) => Ok(Box::new(PopularQueriesCommand { args: pq })),
+
+        (Command::Precompute(pc), CommandSafetyLevel::DangerousToolUseAllowed) => {
+            Ok(Box::new(PrecomputeCommand { args: pc }))
+        }
+
         (Command::ProductionFilter(pf), _) => Ok(Box::new(ProductionFilterCommand { args: pf })),
 
         (Command::Query(q), _) => Ok(Box::new(QueryCommand { args: q })),
 
+        (Command::ReachableFrom(rf), _) => Ok(Box::new(ReachableFromCommand { args: rf })),
+
+        (Command::RefcountCycles(rc), _) => Ok(Box::new(RefcountCyclesCommand { args: rc })),
+
+        (Command::Refine(refine), _) => Ok(Box::new(RefineCommand { args: refine })),
+
         (Command::Render(r), _) => Ok(Box::new(RenderCommand { args: r })),
 
+        (Command::RunPostIndexHooks(rpih), CommandSafetyLevel::DangerousToolUseAllowed) => {
+            Ok(Box::new(RunPostIndexHooksCommand { args: rpih }))
+        }
+
+        (Command::RunSpec(rs), safety @ CommandSafetyLevel::DangerousToolUseAllowed) => {
+            Ok(Box::new(RunSpecCommand { args: rs, safety }))
+        }
+
+        (Command::RustCrateGraph(rcg), _) => Ok(Box::new(RustCrateGraphCommand { args: rcg })),
+
+        (Command::SaveGraph(sg), CommandSafetyLevel::DangerousToolUseAllowed) => {
+            Ok(Box::new(SaveGraphCommand { args: sg }))
+        }
+
         (Command::Search(q), _) => Ok(Box::new(SearchCommand { args: q })),
 
+        (Command::SearchAttributes(search_attributes), _) => {
+            Ok(Box::new(SearchAttributesCommand {
+                args: search_attributes,
+            }))
+        }
+
         (Command::SearchFiles(sf), _) => Ok(Box::new(SearchFilesCommand { args: sf })),
 
         (Command::SearchIdentifiers(si), _) => Ok(Box::new(SearchIdentifiersCommand { args: si })),
 
         (Command::SearchText(st), _) => Ok(Box::new(SearchTextCommand { args: st })),
 
+        (Command::Share(s), CommandSafetyLevel::DangerousToolUseAllowed) => {
+            Ok(Box::new(ShareCommand { args: s }))
+        }
+
         (Command::ShowHtml(sh), _) => Ok(Box::new(ShowHtmlCommand { args: sh })),
 
+        (Command::ShowIndexInfo(sii), _) => Ok(Box::new(ShowIndexInfoCommand { args: sii })),
+
+        (Command::Sizeof(so), _) => Ok(Box::new(SizeofCommand { args: so })),
+
+        (Command::StaticInits(si), _) => Ok(Box::new(StaticInitsCommand { args: si })),
+
+        (Command::StructuredColumns(sc), _) => Ok(Box::new(StructuredColumnsCommand { args: sc })),
+
+        (Command::TestsForSymbol(tfs), _) => Ok(Box::new(TestsForSymbolCommand { args: tfs })),
+
+        (Command::Todos(todos), _) => Ok(Box::new(TodosCommand { args: todos })),
+
         (Command::TokenizeSource(ts), _) => Ok(Box::new(TokenizeSourceCommand { args: ts })),
 
         (Command::Traverse(t), _) => Ok(Box::new(TraverseCommand { args: t })),
 
+        (Command::Trend(t), CommandSafetyLevel::DangerousToolUseAllowed) => {
+            Ok(Box::new(TrendCommand { args: t }))
+        }
+
+        (Command::Tui(t), CommandSafetyLevel::DangerousToolUseAllowed) => {
+            Ok(Box::new(TuiCommand { args: t }))
+        }
+
+        (Command::VerifyIndex(vi), CommandSafetyLevel::DangerousToolUseAllowed) => {
+            Ok(Box::new(VerifyIndexCommand { args: vi }))
+        }
+
         (Command::Webtest(t), CommandSafetyLevel::DangerousToolUseAllowed) => {
             Ok(Box::new(WebtestCommand { args: t }))
         }
 
-        _ => Err(ServerError::StickyProblem(ErrorDetails {
-            layer: ErrorLayer::BadInput,
-            message: "Command not allowed in this context".to_string(),
-        })),
+        (Command::External(args), safety) => fab_plugin_command(args, safety),
+
+        _ => Err(ServerError::StickyProblem(ErrorDetails::new(
+            ErrorKind::BadQuery,
+            ErrorLayer::BadInput,
+            "Command not allowed in this context".to_string(),
+        ))),
     }
 }
 
@@ -111,6 +380,8 @@ pub fn fab_junction_from_opts(
         JunctionCommand::CompileResults(cr) => Ok(Box::new(CompileResultsCommand { args: cr })),
 
         JunctionCommand::FuseCrossrefs(fc) => Ok(Box::new(FuseCrossrefsCommand { args: fc })),
+
+        JunctionCommand::SymbolSet(ss) => Ok(Box::new(SymbolSetCommand { args: ss })),
     }
 }
 
@@ -128,19 +399,23 @@ pub fn build_pipeline(bin_name: &str, arg_str: &str) -> Result<(ServerPipeline,
     let all_args = match shell_words::split(arg_str) {
         Ok(parsed) => parsed,
         Err(err) => {
-            return Err(ServerError::StickyProblem(ErrorDetails {
-                layer: ErrorLayer::BadInput,
-                message: err.to_string(),
-            }));
+            return Err(ServerError::StickyProblem(ErrorDetails::new(
+                ErrorKind::BadQuery,
+                ErrorLayer::BadInput,
+                err.to_string(),
+            )));
         }
     };
 
     let mut server_kind = "none";
     let mut server = None;
     let mut output_format = None;
+    let mut timing = false;
+    let mut output_sink = None;
     let mut first_time = true;
 
     let mut commands: Vec<Box<dyn PipelineCommand + Send + Sync>> = vec![];
+    let mut command_args = vec![];
 
     for arg_slices in all_args.split(|v| v == "|") {
         let mut fake_args = vec![bin_name.to_string()];
@@ -149,22 +424,39 @@ pub fn build_pipeline(bin_name: &str, arg_str: &str) -> Result<(ServerPipeline,
         let opts = match ToolOpts::try_parse_from(fake_args) {
             Ok(opts) => opts,
             Err(err) => {
-                return Err(ServerError::StickyProblem(ErrorDetails {
-                    layer: ErrorLayer::BadInput,
-                    message: err.to_string(),
-                }));
+                return Err(ServerError::StickyProblem(ErrorDetails::new(
+                    ErrorKind::BadQuery,
+                    ErrorLayer::BadInput,
+                    err.to_string(),
+                )));
             }
         };
         //println!("Pipeline segment: {:?}", opts);
 
         if first_time {
             (server_kind, server) = match Url::parse(&opts.server) {
-                Ok(url) => ("remote", Some(make_remote_server(url, &opts.tree)?)),
+                Ok(url) => {
+                    let retry_policy = RetryPolicy {
+                        max_retries: opts.remote_retries,
+                        request_timeout: Duration::from_secs(opts.remote_timeout),
+                        budget: Duration::from_secs(opts.remote_budget),
+                        breaker_threshold: opts.remote_breaker_threshold,
+                        ..RetryPolicy::default()
+                    };
+                    (
+                        "remote",
+                        Some(make_remote_server(url, &opts.tree, retry_policy)?),
+                    )
+                }
                 Err(_) => ("local", Some(make_local_server(&opts.server, &opts.tree)?)),
             };
             output_format = Some(opts.output_format.clone());
             first_time = false;
         }
+        timing |= opts.timing;
+        if let Some(path) = &opts.output_file {
+            output_sink = Some(OutputSink::new(path, opts.output_file_format)?);
+        }
 
         trace!(cmd = ?opts.cmd);
         // We only use this method (`build_pipeline`) for searchfox-tool and
@@ -175,6 +467,7 @@ pub fn build_pipeline(bin_name: &str, arg_str: &str) -> Result<(ServerPipeline,
             opts,
             CommandSafetyLevel::DangerousToolUseAllowed,
         )?);
+        command_args.push(shell_words::join(arg_slices));
     }
 
     Ok((
@@ -182,14 +475,20 @@ pub fn build_pipeline(bin_name: &str, arg_str: &str) -> Result<(ServerPipeline,
             server_kind: server_kind.to_string(),
             server: server.unwrap(),
             commands,
+            command_args,
+            timing,
+            output_sink,
         },
         output_format.unwrap(),
     ))
 }
 
+/// Build the graph for the query, building its commands with the given safety
+/// level.
 pub fn build_pipeline_graph(
     server: Box<dyn AbstractServer + Send + Sync>,
     query: QueryPipelineGroupBuilder,
+    safety: CommandSafetyLevel,
 ) -> Result<ServerPipelineGraph> {
     let mut pipelines = vec![];
     for phase in query.phases {
@@ -202,10 +501,11 @@ pub fn build_pipeline_graph(
             for group_name in groups_in_pipeline {
                 let group_info = query.groups.get(&group_name).ok_or_else(|| {
                     // This really shouldn't happen.
-                    ServerError::StickyProblem(ErrorDetails {
-                        layer: ErrorLayer::RuntimeInvariantViolation,
-                        message: format!("bad group name somehow: {}", group_name),
-                    })
+                    ServerError::StickyProblem(ErrorDetails::new(
+                        ErrorKind::Internal,
+                        ErrorLayer::RuntimeInvariantViolation,
+                        format!("bad group name somehow: {}", group_name),
+                    ))
                 })?;
                 if first {
                     first = false;
@@ -225,15 +525,16 @@ pub fn build_pipeline_graph(
                     let opts = match ToolOpts::try_parse_from(full_args) {
                         Ok(opts) => opts,
                         Err(err) => {
-                            return Err(ServerError::StickyProblem(ErrorDetails {
-                                layer: ErrorLayer::BadInput,
-                                message: err.to_string(),
-                            }));
+                            return Err(ServerError::StickyProblem(ErrorDetails::new(
+                                ErrorKind::BadQuery,
+                                ErrorLayer::BadInput,
+                                err.to_string(),
+                            )));
                         }
                     };
 
                     trace!(cmd = ?opts.cmd);
-                    commands.push(fab_command_from_opts(opts, CommandSafetyLevel::WebSafety)?);
+                    commands.push(fab_command_from_opts(opts, safety)?);
                 }
 
                 output_name = group_info
@@ -241,10 +542,11 @@ pub fn build_pipeline_graph(
                     .as_ref()
                     .ok_or_else(|| {
                         // This really shouldn't happen.
-                        ServerError::StickyProblem(ErrorDetails {
-                            layer: ErrorLayer::RuntimeInvariantViolation,
-                            message: format!("pipeline lacking output: {}", group_name),
-                        })
+                        ServerError::StickyProblem(ErrorDetails::new(
+                            ErrorKind::Internal,
+                            ErrorLayer::RuntimeInvariantViolation,
+                            format!("pipeline lacking output: {}", group_name),
+                        ))
                     })?
                     .clone();
             }
@@ -259,10 +561,11 @@ pub fn build_pipeline_graph(
         for junction_name in phase.junctions {
             let junction_info = query.junctions.get(&junction_name).ok_or_else(|| {
                 // This really shouldn't happen.
-                ServerError::StickyProblem(ErrorDetails {
-                    layer: ErrorLayer::RuntimeInvariantViolation,
-                    message: format!("bad junction name somehow: {}", junction_name),
-                })
+                ServerError::StickyProblem(ErrorDetails::new(
+                    ErrorKind::Internal,
+                    ErrorLayer::RuntimeInvariantViolation,
+                    format!("bad junction name somehow: {}", junction_name),
+                ))
             })?;
 
             let full_args: Vec<String> = [
@@ -277,10 +580,11 @@ pub fn build_pipeline_graph(
             let opts = match JunctionOpts::try_parse_from(full_args) {
                 Ok(opts) => opts,
                 Err(err) => {
-                    return Err(ServerError::StickyProblem(ErrorDetails {
-                        layer: ErrorLayer::BadInput,
-                        message: err.to_string(),
-                    }));
+                    return Err(ServerError::StickyProblem(ErrorDetails::new(
+                        ErrorKind::BadQuery,
+                        ErrorLayer::BadInput,
+                        err.to_string(),
+                    )));
                 }
             };
 
@@ -294,10 +598,11 @@ pub fn build_pipeline_graph(
                     .as_ref()
                     .ok_or_else(|| {
                         // This really shouldn't happen.
-                        ServerError::StickyProblem(ErrorDetails {
-                            layer: ErrorLayer::RuntimeInvariantViolation,
-                            message: format!("junction lacking output: {}", junction_name),
-                        })
+                        ServerError::StickyProblem(ErrorDetails::new(
+                            ErrorKind::Internal,
+                            ErrorLayer::RuntimeInvariantViolation,
+                            format!("junction lacking output: {}", junction_name),
+                        ))
                     })?
                     .clone(),
                 command,
@@ -313,3 +618,17 @@ pub fn build_pipeline_graph(
 
     Ok(ServerPipelineGraph { server, pipelines })
 }
+
+#[test]
+fn test_explain_spec_safety() {
+    let fab = |args: &[&str], safety| {
+        let opts = ToolOpts::try_parse_from(["searchfox-tool"].iter().chain(args)).unwrap();
+        fab_command_from_opts(opts, safety).is_ok()
+    };
+    let pipeline = ["explain", "search-identifiers Foo"];
+    let spec = ["explain", "--spec", "/etc/passwd"];
+    assert!(fab(&pipeline, CommandSafetyLevel::WebSafety));
+    assert!(fab(&pipeline, CommandSafetyLevel::DangerousToolUseAllowed));
+    assert!(!fab(&spec, CommandSafetyLevel::WebSafety));
+    assert!(fab(&spec, CommandSafetyLevel::DangerousToolUseAllowed));
+}
@@ -0,0 +1,112 @@
+use async_trait::async_trait;
+use clap::Args;
+use serde_json::{to_value, Map};
+
+use super::interface::{JsonValue, PipelineCommand, PipelineValues};
+use crate::{
+    abstract_server::{
+        AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, SearchfoxIndexRoot,
+        ServerError,
+    },
+    file_format::symbol_annotations::{SymbolAnnotations, SYMBOL_ANNOTATIONS_FILENAME},
+};
+
+/// Add or remove tags and notes on symbols in the tree's annotation store (see
+/// `file_format/symbol_annotations.rs`), ex: tagging classes as "memory-hot" so
+/// `annotations --tag=memory-hot` can later find them.  The symbols come from
+/// the arguments or from a SymbolList or SymbolCrossrefInfoList input.
+/// Produces a JsonValue of the resulting annotation of each symbol.
+#[derive(Debug, Args)]
+pub struct Annotate {
+    /// Explicit symbols to annotate.
+    #[clap(value_parser)]
+    symbols: Vec<String>,
+
+    /// Tag to add; may be repeated.
+    #[clap(long, value_parser)]
+    tag: Vec<String>,
+
+    /// Tag to remove; may be repeated.
+    #[clap(long, value_parser)]
+    untag: Vec<String>,
+
+    /// Replace the symbols' note.  An empty note removes it.
+    #[clap(long, value_parser)]
+    note: Option<String>,
+
+    /// Remove all of the symbols' tags and notes.
+    #[clap(long, value_parser, conflicts_with_all = ["tag", "untag", "note"])]
+    clear: bool,
+}
+
+#[derive(Debug)]
+pub struct AnnotateCommand {
+    pub args: Annotate,
+}
+
+#[async_trait]
+impl PipelineCommand for AnnotateCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let symbols: Vec<String> = match input {
+            PipelineValues::Void => self.args.symbols.clone(),
+            PipelineValues::SymbolList(sl) => {
+                sl.symbols.iter().map(|s| s.symbol.to_string()).collect()
+            }
+            PipelineValues::SymbolCrossrefInfoList(sil) => sil
+                .symbol_crossref_infos
+                .iter()
+                .map(|info| info.symbol.to_string())
+                .collect(),
+            _ => {
+                return Err(ServerError::StickyProblem(
+                    ErrorDetails::new(
+                        ErrorKind::BadQuery,
+                        ErrorLayer::ConfigLayer,
+                        "annotate needs a Void, SymbolList, or SymbolCrossrefInfoList".to_string(),
+                    )
+                    .with_hint(
+                        "Use `explain` to see what each pipeline stage consumes and produces.",
+                    ),
+                ));
+            }
+        };
+        if symbols.is_empty() {
+            return Err(ServerError::StickyProblem(ErrorDetails::new(
+                ErrorKind::BadQuery,
+                ErrorLayer::BadInput,
+                "annotate needs at least one symbol".to_string(),
+            )));
+        }
+
+        let store_path = server.translate_path(
+            SearchfoxIndexRoot::DeploymentState,
+            SYMBOL_ANNOTATIONS_FILENAME,
+        )?;
+        let mut annotations = SymbolAnnotations::load(&store_path)?.unwrap_or_default();
+
+        let mut results = Map::new();
+        for symbol in symbols {
+            let annotation = if self.args.clear {
+                annotations.remove(&symbol);
+                Default::default()
+            } else {
+                annotations.update(
+                    &symbol,
+                    &self.args.tag,
+                    &self.args.untag,
+                    self.args.note.as_deref(),
+                )
+            };
+            results.insert(symbol, to_value(annotation)?);
+        }
+        annotations.save(&store_path)?;
+
+        Ok(PipelineValues::JsonValue(JsonValue {
+            value: results.into(),
+        }))
+    }
+}
@@ -0,0 +1,107 @@
+use async_trait::async_trait;
+use clap::Args;
+use serde_json::{to_value, Map};
+use ustr::ustr;
+
+use super::interface::{
+    JsonValue, PipelineCommand, PipelineValues, SymbolList, SymbolQuality, SymbolWithContext,
+};
+use crate::{
+    abstract_server::{
+        AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, SearchfoxIndexRoot,
+        ServerError,
+    },
+    file_format::symbol_annotations::{SymbolAnnotations, SYMBOL_ANNOTATIONS_FILENAME},
+};
+
+/// Query the tree's annotation store (see `annotate`).  Without input, this
+/// produces a SymbolList of the annotated symbols that have all of the given
+/// tags, so they can be fed to `crossref-lookup` and on to, ex:
+/// `format-symbols`.  Given a SymbolList or SymbolCrossrefInfoList, it instead
+/// keeps only the symbols with all of the given tags (or with any annotation if
+/// no tags are given).  With `--details`, the annotations themselves are
+/// produced as a JsonValue.
+#[derive(Debug, Args)]
+pub struct Annotations {
+    /// Only include symbols with this tag; may be repeated.
+    #[clap(long, value_parser)]
+    tag: Vec<String>,
+
+    /// When filtering input, keep the symbols that don't match instead.
+    #[clap(long, value_parser)]
+    invert: bool,
+
+    /// Produce the matching symbols' tags and notes instead of a SymbolList.
+    #[clap(long, value_parser)]
+    details: bool,
+}
+
+#[derive(Debug)]
+pub struct AnnotationsCommand {
+    pub args: Annotations,
+}
+
+impl AnnotationsCommand {
+    fn matches(&self, annotations: &SymbolAnnotations, symbol: &str) -> bool {
+        let matched = annotations
+            .get(symbol)
+            .map_or(false, |annotation| annotation.has_tags(&self.args.tag));
+        matched != self.args.invert
+    }
+}
+
+#[async_trait]
+impl PipelineCommand for AnnotationsCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let store_path = server.translate_path(
+            SearchfoxIndexRoot::DeploymentState,
+            SYMBOL_ANNOTATIONS_FILENAME,
+        )?;
+        let annotations = SymbolAnnotations::load(&store_path)?.unwrap_or_default();
+
+        match input {
+            PipelineValues::Void if self.args.details => {
+                let mut results = Map::new();
+                for symbol in annotations.symbols_with_tags(&self.args.tag) {
+                    results.insert(symbol.to_string(), to_value(annotations.get(symbol))?);
+                }
+                Ok(PipelineValues::JsonValue(JsonValue {
+                    value: results.into(),
+                }))
+            }
+            PipelineValues::Void => Ok(PipelineValues::SymbolList(SymbolList {
+                symbols: annotations
+                    .symbols_with_tags(&self.args.tag)
+                    .map(|symbol| SymbolWithContext {
+                        symbol: ustr(symbol),
+                        quality: SymbolQuality::ExplicitSymbol,
+                        from_identifier: None,
+                    })
+                    .collect(),
+                page: None,
+            })),
+            PipelineValues::SymbolList(mut sl) => {
+                sl.symbols
+                    .retain(|s| self.matches(&annotations, s.symbol.as_str()));
+                Ok(PipelineValues::SymbolList(sl))
+            }
+            PipelineValues::SymbolCrossrefInfoList(mut sil) => {
+                sil.symbol_crossref_infos
+                    .retain(|info| self.matches(&annotations, info.symbol.as_str()));
+                Ok(PipelineValues::SymbolCrossrefInfoList(sil))
+            }
+            _ => Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::BadQuery,
+                    ErrorLayer::ConfigLayer,
+                    "annotations needs a Void, SymbolList, or SymbolCrossrefInfoList".to_string(),
+                )
+                .with_hint("Use `explain` to see what each pipeline stage consumes and produces."),
+            )),
+        }
+    }
+}
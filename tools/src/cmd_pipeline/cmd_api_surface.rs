@@ -0,0 +1,194 @@
+use std::collections::BTreeSet;
+
+use async_trait::async_trait;
+use clap::Args;
+use tokio_stream::StreamExt;
+use ustr::ustr;
+
+use super::{
+    data_table::{DataCell, DataColumnType, DataTable},
+    interface::{PipelineCommand, PipelineValues},
+};
+use crate::abstract_server::{
+    AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError,
+};
+
+/// Compute the "public API" of a directory: the symbols defined in it that are
+/// used from outside of it, ex: when considering componentizing the directory
+/// or extracting it into its own crate.  Produces a DataTable with a row per
+/// symbol, grouped by the kind of symbol from its structured record (ex:
+/// "class", "method", "field") and then by descending external use count,
+/// along with the first external use and its context.
+#[derive(Debug, Args)]
+pub struct ApiSurface {
+    /// The directory, ex: "dom/media".
+    #[clap(value_parser)]
+    dir: String,
+
+    /// Only include symbols with at least this many external uses.
+    #[clap(long, value_parser, default_value = "1")]
+    min_uses: usize,
+
+    /// The maximum number of files in the directory to consider.
+    #[clap(long, value_parser, default_value = "10000")]
+    limit: usize,
+}
+
+#[derive(Debug)]
+pub struct ApiSurfaceCommand {
+    pub args: ApiSurface,
+}
+
+struct ApiSymbol {
+    kind: String,
+    symbol: String,
+    pretty: String,
+    def_path: String,
+    uses: usize,
+    files: usize,
+    first_use: String,
+    first_context: Option<String>,
+}
+
+#[async_trait]
+impl PipelineCommand for ApiSurfaceCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        if !matches!(input, PipelineValues::Void) {
+            return Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::BadQuery,
+                    ErrorLayer::ConfigLayer,
+                    "api-surface needs no input".to_string(),
+                )
+                .with_hint("Use `explain` to see what each pipeline stage consumes and produces."),
+            ));
+        }
+
+        let dir = self.args.dir.trim_matches('/');
+        let prefix = format!("{}/", dir);
+        let is_internal = |path: &str| path.starts_with(&prefix);
+
+        // Gather the symbols defined in the directory.
+        let pathre = format!("^{}", regex::escape(&prefix));
+        let matches = server.search_files(&pathre, false, self.args.limit).await?;
+        let mut defined = BTreeSet::new();
+        for fm in matches.file_matches {
+            if fm.concise.is_dir {
+                continue;
+            }
+            // Not every file has analysis data, which is fine.
+            let Ok(mut records) = server.fetch_raw_analysis(&fm.path).await else {
+                continue;
+            };
+            while let Some(val) = records.next().await {
+                if val["kind"].as_str() != Some("def") {
+                    continue;
+                }
+                if let Some(syms) = val["sym"].as_str() {
+                    defined.extend(syms.split(',').map(|sym| sym.to_string()));
+                }
+            }
+        }
+
+        let mut api = vec![];
+        for symbol in defined {
+            let info = server.crossref_lookup(&symbol, false).await?;
+            let Some(def_path) = info.pointer("/defs/0/path").and_then(|path| path.as_str()) else {
+                continue;
+            };
+            // Symbols with multiple definitions may be defined primarily
+            // elsewhere, ex: a method whose inline definition lives in a
+            // header outside the directory.
+            if !is_internal(def_path) {
+                continue;
+            }
+
+            let mut uses = 0;
+            let mut files = 0;
+            let mut first = None;
+            for path_hits in info["uses"].as_array().into_iter().flatten() {
+                let Some(path) = path_hits["path"].as_str() else {
+                    continue;
+                };
+                if is_internal(path) {
+                    continue;
+                }
+                let hits = path_hits["lines"]
+                    .as_array()
+                    .map_or(&[][..], |v| v.as_slice());
+                if hits.is_empty() {
+                    continue;
+                }
+                uses += hits.len();
+                files += 1;
+                if first.is_none() {
+                    first = Some((
+                        format!("{}#{}", path, hits[0]["lno"]),
+                        hits[0]["context"]
+                            .as_str()
+                            .filter(|context| !context.is_empty())
+                            .map(|context| context.to_string()),
+                    ));
+                }
+            }
+            let Some((first_use, first_context)) = first else {
+                continue;
+            };
+            if uses < self.args.min_uses {
+                continue;
+            }
+
+            api.push(ApiSymbol {
+                kind: info["meta"]["kind"]
+                    .as_str()
+                    .unwrap_or("unknown")
+                    .to_string(),
+                pretty: info["meta"]["pretty"]
+                    .as_str()
+                    .unwrap_or(&symbol)
+                    .to_string(),
+                symbol,
+                def_path: def_path.to_string(),
+                uses,
+                files,
+                first_use,
+                first_context,
+            });
+        }
+        api.sort_by(|a, b| {
+            a.kind
+                .cmp(&b.kind)
+                .then(b.uses.cmp(&a.uses))
+                .then(a.pretty.cmp(&b.pretty))
+        });
+
+        let mut table = DataTable::new(vec![
+            ("kind", DataColumnType::String),
+            ("symbol", DataColumnType::SymbolRef),
+            ("pretty", DataColumnType::String),
+            ("def_path", DataColumnType::Path),
+            ("external_uses", DataColumnType::Int),
+            ("external_files", DataColumnType::Int),
+            ("first_external_use", DataColumnType::Path),
+            ("first_external_context", DataColumnType::String),
+        ]);
+        for sym in api {
+            table.push_row(vec![
+                DataCell::String(sym.kind),
+                DataCell::Symbol(ustr(&sym.symbol)),
+                DataCell::String(sym.pretty),
+                DataCell::String(sym.def_path),
+                DataCell::Int(sym.uses as i64),
+                DataCell::Int(sym.files as i64),
+                DataCell::String(sym.first_use),
+                sym.first_context.map_or(DataCell::Null, DataCell::String),
+            ])?;
+        }
+
+        Ok(PipelineValues::DataTable(table))
+    }
+}
@@ -0,0 +1,355 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use clap::Args;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tracing::{trace, warn};
+
+use super::{
+    data_table::{DataCell, DataColumnType, DataTable},
+    interface::{PipelineCommand, PipelineValues},
+    transforms::path_glob_transform,
+};
+use crate::abstract_server::{
+    AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, HtmlFileRoot, Result, SearchfoxIndexRoot,
+    ServerError,
+};
+
+/// Check the external http(s) links in the tree's rendered documentation
+/// (markdown, reStructuredText, and HTML files) and report the dead ones as a
+/// DataTable with the path and line of each link, its URL, and the HTTP status
+/// or error we got for it.  The links are extracted from the formatted pages
+/// for the files, so they're the links readers can actually follow.  Each
+/// distinct URL is only checked once, and results are cached in
+/// `external-links-cache.json` in the tree's index directory so that re-running
+/// the audit doesn't hammer the same servers.
+///
+/// Operates on the FileMatches from `search-files` if piped any, otherwise on
+/// all of the files in the tree (constrained by `path`, if given); either way
+/// only documentation files are considered.
+#[derive(Debug, Args)]
+pub struct AuditExternalLinks {
+    /// Path glob to constrain the files considered when not given FileMatches.
+    #[clap(value_parser)]
+    path: Option<String>,
+
+    /// The maximum number of URLs to check at once.
+    #[clap(long, value_parser, default_value = "8")]
+    concurrency: usize,
+
+    /// Seconds to wait for each URL before considering it dead.
+    #[clap(long, value_parser, default_value = "15")]
+    timeout: u64,
+
+    /// Reuse cached results younger than this many hours; 0 checks everything
+    /// again.
+    #[clap(long, value_parser, default_value = "24")]
+    cache_max_age: u64,
+
+    /// Don't check URLs matching this regex, ex: for intranet hosts.
+    #[clap(long, value_parser)]
+    ignore: Option<String>,
+
+    /// Report every link instead of just the dead ones.
+    #[clap(long, value_parser)]
+    all: bool,
+}
+
+#[derive(Debug)]
+pub struct AuditExternalLinksCommand {
+    pub args: AuditExternalLinks,
+}
+
+const DOC_EXTENSIONS: &[&str] = &["md", "markdown", "rst", "html", "htm"];
+
+const FILE_MATCH_LIMIT: usize = 2_000_000;
+
+const EXTERNAL_LINKS_CACHE_FILENAME: &str = "external-links-cache.json";
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LinkCheck {
+    status: Option<u16>,
+    error: Option<String>,
+    /// Seconds since the epoch.
+    checked_at: u64,
+}
+
+impl LinkCheck {
+    fn is_dead(&self) -> bool {
+        self.error.is_some() || self.status.map_or(true, |status| status >= 400)
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct LinkCheckCache {
+    #[serde(default)]
+    links: BTreeMap<String, LinkCheck>,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+fn is_doc_file(path: &str) -> bool {
+    match path.rsplit_once('.') {
+        Some((_, ext)) => DOC_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()),
+        None => false,
+    }
+}
+
+/// Undo the escaping `format.rs` applies to the source text.
+fn unescape_html(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&amp;", "&")
+}
+
+/// Find the http(s) links in a formatted source page, returning their 1-based
+/// line numbers and URLs.  Only the links in the source lines themselves count,
+/// not the ones in the page's navigation and panels.
+fn extract_rendered_links(html: &str) -> Vec<(usize, String)> {
+    lazy_static! {
+        static ref RE_ROW: Regex = Regex::new(r#"<div role="row" id="line-(\d+)""#).unwrap();
+        static ref RE_HREF: Regex = Regex::new(r#"<a href="(https?://[^"]+)""#).unwrap();
+    }
+    const SOURCE_LINE_START: &str = r#"class="source-line">"#;
+    const SOURCE_LINE_END: &str = "</code>";
+
+    let mut links = vec![];
+    for caps in RE_ROW.captures_iter(html) {
+        let Ok(lineno) = caps[1].parse::<usize>() else {
+            continue;
+        };
+        let rest = &html[caps.get(0).unwrap().end()..];
+        let Some(start) = rest.find(SOURCE_LINE_START) else {
+            continue;
+        };
+        let rest = &rest[start + SOURCE_LINE_START.len()..];
+        let Some(end) = rest.find(SOURCE_LINE_END) else {
+            continue;
+        };
+        for href in RE_HREF.captures_iter(&rest[..end]) {
+            links.push((lineno, unescape_html(&href[1])));
+        }
+    }
+    links
+}
+
+async fn check_url(client: &reqwest::Client, url: &str) -> LinkCheck {
+    let mut result = client.head(url).send().await;
+    // Some servers don't implement HEAD, so fall back to GET for them.
+    let head_unsupported = match &result {
+        Ok(res) => matches!(res.status().as_u16(), 405 | 501),
+        Err(_) => false,
+    };
+    if head_unsupported {
+        result = client.get(url).send().await;
+    }
+    let (status, error) = match result {
+        Ok(res) => (Some(res.status().as_u16()), None),
+        Err(e) => (None, Some(e.to_string())),
+    };
+    LinkCheck {
+        status,
+        error,
+        checked_at: now_secs(),
+    }
+}
+
+impl AuditExternalLinksCommand {
+    /// Check the URLs with at most `--concurrency` requests in flight.
+    async fn check_urls(&self, urls: Vec<String>) -> Result<Vec<(String, LinkCheck)>> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(self.args.timeout))
+            .user_agent("searchfox-audit-external-links")
+            .build()?;
+
+        let queue = Arc::new(Mutex::new(urls));
+        let mut workers = vec![];
+        for _ in 0..self.args.concurrency.max(1) {
+            let queue = queue.clone();
+            let client = client.clone();
+            workers.push(tokio::spawn(async move {
+                let mut checked = vec![];
+                loop {
+                    let next = queue.lock().unwrap().pop();
+                    let Some(url) = next else {
+                        break;
+                    };
+                    let check = check_url(&client, &url).await;
+                    trace!("audit-external-links: {} -> {:?}", url, check);
+                    checked.push((url, check));
+                }
+                checked
+            }));
+        }
+
+        let mut results = vec![];
+        for worker in workers {
+            results.extend(worker.await?);
+        }
+        Ok(results)
+    }
+}
+
+#[async_trait]
+impl PipelineCommand for AuditExternalLinksCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let matches = match input {
+            PipelineValues::FileMatches(fm) => fm,
+            PipelineValues::Void => {
+                let pathre = match &self.args.path {
+                    Some(path) => path_glob_transform(path),
+                    None => "".to_string(),
+                };
+                server
+                    .search_files(&pathre, false, FILE_MATCH_LIMIT)
+                    .await?
+            }
+            _ => {
+                return Err(ServerError::StickyProblem(
+                    ErrorDetails::new(
+                        ErrorKind::BadQuery,
+                        ErrorLayer::ConfigLayer,
+                        "audit-external-links needs FileMatches or no input".to_string(),
+                    )
+                    .with_hint(
+                        "Use `explain` to see what each pipeline stage consumes and produces.",
+                    ),
+                ));
+            }
+        };
+        let ignore = match &self.args.ignore {
+            Some(pattern) => Some(Regex::new(pattern)?),
+            None => None,
+        };
+
+        let mut links = vec![];
+        for fm in &matches.file_matches {
+            if fm.concise.is_dir || !is_doc_file(&fm.path) {
+                continue;
+            }
+            let html = match server
+                .fetch_html(HtmlFileRoot::FormattedFile, &fm.path)
+                .await
+            {
+                Ok(html) => html,
+                Err(e) => {
+                    trace!(
+                        "audit-external-links: no rendered page for {}: {:?}",
+                        fm.path,
+                        e
+                    );
+                    continue;
+                }
+            };
+            for (line, url) in extract_rendered_links(&html) {
+                if ignore.as_ref().map_or(false, |re| re.is_match(&url)) {
+                    continue;
+                }
+                links.push((fm.path.clone(), line, url));
+            }
+        }
+
+        // The cache is best-effort; servers without an index directory (ex:
+        // remote servers) just check everything.
+        let cache_path = server
+            .translate_path(SearchfoxIndexRoot::IndexRoot, EXTERNAL_LINKS_CACHE_FILENAME)
+            .ok();
+        let mut cache: LinkCheckCache = cache_path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+
+        let max_age_secs = self.args.cache_max_age * 60 * 60;
+        let now = now_secs();
+        let to_check: Vec<String> = links
+            .iter()
+            .map(|(_, _, url)| url)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .filter(|url| match cache.links.get(*url) {
+                Some(check) => now.saturating_sub(check.checked_at) >= max_age_secs,
+                None => true,
+            })
+            .cloned()
+            .collect();
+        cache.links.extend(self.check_urls(to_check).await?);
+        if let Some(path) = &cache_path {
+            if let Err(e) = fs::write(path, serde_json::to_string(&cache)?) {
+                warn!(
+                    "audit-external-links: unable to write cache {}: {}",
+                    path, e
+                );
+            }
+        }
+
+        links.sort();
+        let mut table = DataTable::new(vec![
+            ("path", DataColumnType::Path),
+            ("line", DataColumnType::Int),
+            ("url", DataColumnType::String),
+            ("status", DataColumnType::Int),
+            ("error", DataColumnType::String),
+        ]);
+        for (path, line, url) in links {
+            let Some(check) = cache.links.get(&url) else {
+                continue;
+            };
+            if !self.args.all && !check.is_dead() {
+                continue;
+            }
+            table.push_row(vec![
+                DataCell::String(path),
+                DataCell::Int(line as i64),
+                DataCell::String(url),
+                check
+                    .status
+                    .map_or(DataCell::Null, |status| DataCell::Int(status as i64)),
+                check.error.clone().map_or(DataCell::Null, DataCell::String),
+            ])?;
+        }
+
+        Ok(PipelineValues::DataTable(table))
+    }
+}
+
+#[test]
+fn test_extract_rendered_links() {
+    let html = concat!(
+        r#"<a href="https://example.com/nav">nav</a>"#,
+        r#"<div role="row" id="line-1" class="source-line-with-number">"#,
+        r#"<div role="cell"><div class="cov-strip"></div></div>"#,
+        r#"<code role="cell" class="source-line">See <a href="https://example.com/a?b=1&amp;c=2">https://example.com/a?b=1&amp;c=2</a>"#,
+        "\n</code></div>",
+        r#"<div role="row" id="line-2" class="source-line-with-number">"#,
+        r#"<code role="cell" class="source-line">No links here &lt;a href="http://x"&gt;"#,
+        "\n</code></div>",
+        r#"<div role="row" id="line-3" class="source-line-with-number">"#,
+        r#"<code role="cell" class="source-line"><a href="http://one.test/">x</a> <a href="https://two.test/">y</a>"#,
+        "\n</code></div>",
+        r#"<a href="https://example.com/footer">footer</a>"#,
+    );
+    assert_eq!(
+        extract_rendered_links(html),
+        vec![
+            (1, "https://example.com/a?b=1&c=2".to_string()),
+            (3, "http://one.test/".to_string()),
+            (3, "https://two.test/".to_string()),
+        ]
+    );
+}
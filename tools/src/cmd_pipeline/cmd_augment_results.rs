@@ -7,7 +7,7 @@ use ustr::UstrMap;
 
 use super::interface::{PipelineCommand, PipelineValues};
 use crate::abstract_server::{
-    AbstractServer, ErrorDetails, ErrorLayer, HtmlFileRoot, Result, ServerError,
+    AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, HtmlFileRoot, Result, ServerError,
 };
 
 /// Augment a FlattenedResultsBundle by scraping the rendered HTML output files
@@ -61,10 +61,16 @@ impl PipelineCommand for AugmentResultsCommand {
         let mut results = match input {
             PipelineValues::FlattenedResultsBundle(frb) => frb,
             _ => {
-                return Err(ServerError::StickyProblem(ErrorDetails {
-                    layer: ErrorLayer::ConfigLayer,
-                    message: "augment-resultst needs a FlattenedResultsBundle".to_string(),
-                }));
+                return Err(ServerError::StickyProblem(
+                    ErrorDetails::new(
+                        ErrorKind::BadQuery,
+                        ErrorLayer::ConfigLayer,
+                        "augment-resultst needs a FlattenedResultsBundle".to_string(),
+                    )
+                    .with_hint(
+                        "Use `explain` to see what each pipeline stage consumes and produces.",
+                    ),
+                ));
             }
         };
 
@@ -0,0 +1,103 @@
+use async_trait::async_trait;
+use clap::Args;
+
+use super::interface::{PipelineCommand, PipelineValues, TextFile};
+use crate::{
+    abstract_server::{AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError},
+    query::badges::{self, load_badge_runs, BadgeAggregate, BadgeMetric},
+};
+
+/// Render an SVG badge with the current value of a metric for embedding in a
+/// build dashboard (see `query/badges.rs`).  With `--hook`, the metric comes
+/// from the post-index hook's most recent stored run and the badge also shows
+/// the change since the previous run.  Otherwise the metric comes from the
+/// JsonValue piped in or the crossref info of the first symbol of a
+/// SymbolCrossrefInfoList (ex: `--pointer=/meta/sizeBytes`).  Produces a
+/// TextFile.
+#[derive(Debug, Args)]
+pub struct Badge {
+    /// The text on the left side of the badge.
+    #[clap(long, value_parser)]
+    label: String,
+
+    /// The post-index hook whose stored runs provide the metric.
+    #[clap(long, value_parser)]
+    hook: Option<String>,
+
+    /// JSON pointer to the metric's value, ex: `/DataTable/rows`.
+    #[clap(long, value_parser, default_value = "")]
+    pointer: String,
+
+    #[clap(long, value_parser, value_enum, default_value = "value")]
+    aggregate: BadgeAggregate,
+
+    /// JSON pointer within each item to the numbers to `--aggregate=sum`.
+    #[clap(long, value_parser)]
+    field: Option<String>,
+
+    /// Treat increases as good news rather than regressions.
+    #[clap(long, value_parser)]
+    higher_is_better: bool,
+}
+
+#[derive(Debug)]
+pub struct BadgeCommand {
+    pub args: Badge,
+}
+
+#[async_trait]
+impl PipelineCommand for BadgeCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let metric = BadgeMetric {
+            pointer: self.args.pointer.clone(),
+            aggregate: self.args.aggregate,
+            field: self.args.field.clone(),
+        };
+
+        let badge = match (&self.args.hook, input) {
+            (Some(hook), PipelineValues::Void) => badges::Badge::from_runs(
+                &self.args.label,
+                &load_badge_runs(server, hook)?,
+                &metric,
+                self.args.higher_is_better,
+            ),
+            (None, PipelineValues::JsonValue(jv)) => badges::Badge {
+                label: self.args.label.clone(),
+                value: metric.extract(&jv.value),
+                previous: None,
+                higher_is_better: self.args.higher_is_better,
+            },
+            (None, PipelineValues::SymbolCrossrefInfoList(sil)) => badges::Badge {
+                label: self.args.label.clone(),
+                value: sil
+                    .symbol_crossref_infos
+                    .first()
+                    .and_then(|info| metric.extract(&info.crossref_info)),
+                previous: None,
+                higher_is_better: self.args.higher_is_better,
+            },
+            _ => {
+                return Err(ServerError::StickyProblem(
+                    ErrorDetails::new(
+                        ErrorKind::BadQuery,
+                        ErrorLayer::ConfigLayer,
+                        "badge needs --hook and no input, or a JsonValue or SymbolCrossrefInfoList"
+                            .to_string(),
+                    )
+                    .with_hint(
+                        "Use `explain` to see what each pipeline stage consumes and produces.",
+                    ),
+                ));
+            }
+        };
+
+        Ok(PipelineValues::TextFile(TextFile {
+            mime_type: "image/svg+xml".to_string(),
+            contents: badge.to_svg(),
+        }))
+    }
+}
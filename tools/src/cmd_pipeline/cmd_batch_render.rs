@@ -1,12 +1,14 @@
 use async_trait::async_trait;
 use clap::Args;
 
+use super::cmd_render::load_asset_manifest;
 use super::interface::{PipelineCommand, PipelineValues};
 use crate::{
     abstract_server::{
-        AbstractServer, ErrorDetails, ErrorLayer, Result, SearchfoxIndexRoot, ServerError,
+        AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, SearchfoxIndexRoot,
+        ServerError,
     },
-    file_utils::write_file_ensuring_parent_dir,
+    file_utils::{write_file_ensuring_parent_dir, write_gzipped_file_ensuring_parent_dir},
     templating::builder::build_and_parse_dir_listing,
 };
 
@@ -38,10 +40,16 @@ impl PipelineCommand for BatchRenderCommand {
         let batch_groups = match input {
             PipelineValues::BatchGroups(bg) => bg,
             _ => {
-                return Err(ServerError::StickyProblem(ErrorDetails {
-                    layer: ErrorLayer::ConfigLayer,
-                    message: "batch-render needs BatchGroups".to_string(),
-                }));
+                return Err(ServerError::StickyProblem(
+                    ErrorDetails::new(
+                        ErrorKind::BadQuery,
+                        ErrorLayer::ConfigLayer,
+                        "batch-render needs BatchGroups".to_string(),
+                    )
+                    .with_hint(
+                        "Use `explain` to see what each pipeline stage consumes and produces.",
+                    ),
+                ));
             }
         };
 
@@ -49,6 +57,7 @@ impl PipelineCommand for BatchRenderCommand {
             "dir" => {
                 let template = build_and_parse_dir_listing();
                 let tree_info = server.tree_info()?;
+                let asset_manifest = load_asset_manifest(server)?;
                 for item in batch_groups.groups {
                     if let PipelineValues::FileMatches(fm) = item.value {
                         let liquid_globals = liquid::object!({
@@ -57,29 +66,38 @@ impl PipelineCommand for BatchRenderCommand {
                             "query": "",
                             "path": item.name,
                             "files": fm.file_matches,
+                            "ASSET_MANIFEST": &asset_manifest.assets,
                         });
                         let rendered = match template.render(&liquid_globals) {
                             Ok(r) => r,
                             Err(e) => {
-                                return Err(ServerError::StickyProblem(ErrorDetails {
-                                    layer: ErrorLayer::ConfigLayer,
-                                    message: format!("Template problems: {}", e),
-                                }));
+                                return Err(ServerError::StickyProblem(ErrorDetails::new(
+                                    ErrorKind::Internal,
+                                    ErrorLayer::ConfigLayer,
+                                    format!("Template problems: {}", e),
+                                )));
                             }
                         };
                         let output_path = server.translate_path(
                             SearchfoxIndexRoot::UncompressedDirectoryListing,
                             &item.name,
                         )?;
-                        write_file_ensuring_parent_dir(&output_path, &rendered)?;
+                        // compress-outputs.sh would otherwise gzip these
+                        // later, leaving the same zero-length placeholders.
+                        if tree_info.precompress_html {
+                            write_gzipped_file_ensuring_parent_dir(&output_path, &rendered, true)?;
+                        } else {
+                            write_file_ensuring_parent_dir(&output_path, &rendered)?;
+                        }
                     }
                 }
                 Ok(PipelineValues::Void)
             }
-            unknown => Err(ServerError::StickyProblem(ErrorDetails {
-                layer: ErrorLayer::ConfigLayer,
-                message: format!("Unknown task type: {}", unknown),
-            })),
+            unknown => Err(ServerError::StickyProblem(ErrorDetails::new(
+                ErrorKind::Internal,
+                ErrorLayer::ConfigLayer,
+                format!("Unknown task type: {}", unknown),
+            ))),
         }
     }
 }
@@ -0,0 +1,133 @@
+use async_trait::async_trait;
+use clap::Args;
+use regex::Regex;
+
+use super::{
+    data_table::{DataCell, DataColumnType, DataTable},
+    interface::{PipelineCommand, PipelineValues},
+    transforms::path_glob_transform,
+};
+use crate::{
+    abstract_server::{
+        AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, SearchfoxIndexRoot,
+        ServerError,
+    },
+    file_format::bug_references::{BugReferenceIndex, BugReferenceSource, BUG_REFERENCES_FILENAME},
+};
+
+/// List everything in the tree tied to the given bugs: the comments that
+/// mention them, ex: "// Remove once bug 1234567 is fixed", and the lines last
+/// changed by revisions whose commit messages mention them.  This uses the
+/// `bug-references.json` index that crossref builds, where commit references
+/// are only available if the tree has blame.  Produces a DataTable with a row
+/// per reference, where commit references cover all of the `lines` the
+/// revision last changed starting at `line`.
+#[derive(Debug, Args)]
+pub struct BugRefs {
+    /// The bug numbers, ex: 1234567.
+    #[clap(value_parser, required = true)]
+    bugs: Vec<u64>,
+
+    /// Only include references of this kind.
+    #[clap(long, value_parser, value_enum)]
+    source: Option<BugReferenceSource>,
+
+    /// Only include references in files matching this path glob.
+    #[clap(long, value_parser)]
+    path: Option<String>,
+
+    /// Only include references in files matching this regexp.
+    #[clap(long, value_parser, conflicts_with = "path")]
+    pathre: Option<String>,
+
+    #[clap(short, long, value_parser, default_value = "1000")]
+    limit: usize,
+}
+
+#[derive(Debug)]
+pub struct BugRefsCommand {
+    pub args: BugRefs,
+}
+
+#[async_trait]
+impl PipelineCommand for BugRefsCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        if !matches!(input, PipelineValues::Void) {
+            return Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::BadQuery,
+                    ErrorLayer::ConfigLayer,
+                    "bug-refs needs no input".to_string(),
+                )
+                .with_hint("Use `explain` to see what each pipeline stage consumes and produces."),
+            ));
+        }
+
+        let index_path =
+            server.translate_path(SearchfoxIndexRoot::IndexRoot, BUG_REFERENCES_FILENAME)?;
+        let Some(mut index) = BugReferenceIndex::load(&index_path)? else {
+            return Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::IndexMissingCapability,
+                    ErrorLayer::DataLayer,
+                    "This index has no bug references".to_string(),
+                )
+                .with_hint("Re-index the tree to build `bug-references.json`."),
+            ));
+        };
+
+        let path_re = match (&self.args.path, &self.args.pathre) {
+            (Some(path), _) => Some(Regex::new(&path_glob_transform(path))?),
+            (None, Some(pathre)) => Some(Regex::new(pathre)?),
+            (None, None) => None,
+        };
+
+        let mut table = DataTable::new(vec![
+            ("bug", DataColumnType::Int),
+            ("source", DataColumnType::String),
+            ("path", DataColumnType::Path),
+            ("line", DataColumnType::Int),
+            ("lines", DataColumnType::Int),
+            ("rev", DataColumnType::String),
+            ("text", DataColumnType::String),
+        ]);
+        let mut rows = 0;
+        for bug in &self.args.bugs {
+            for bug_ref in index.bugs.remove(bug).unwrap_or_default() {
+                if rows >= self.args.limit {
+                    break;
+                }
+                if let Some(source) = self.args.source {
+                    if bug_ref.source != source {
+                        continue;
+                    }
+                }
+                if let Some(path_re) = &path_re {
+                    if !path_re.is_match(&bug_ref.path) {
+                        continue;
+                    }
+                }
+                let source = match bug_ref.source {
+                    BugReferenceSource::Comment => "comment",
+                    BugReferenceSource::Commit => "commit",
+                };
+                table.push_row(vec![
+                    DataCell::Int(*bug as i64),
+                    DataCell::String(source.to_string()),
+                    DataCell::String(bug_ref.path),
+                    DataCell::Int(bug_ref.line as i64),
+                    DataCell::Int(bug_ref.lines as i64),
+                    bug_ref.rev.map_or(DataCell::Null, DataCell::String),
+                    DataCell::String(bug_ref.text),
+                ])?;
+                rows += 1;
+            }
+        }
+
+        Ok(PipelineValues::DataTable(table))
+    }
+}
@@ -0,0 +1,81 @@
+use async_trait::async_trait;
+use clap::Args;
+
+use super::interface::{JsonValue, PipelineCommand, PipelineValues, TextFile};
+use crate::abstract_server::{
+    AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError,
+};
+
+/// Dump the raw (not HTML rendered) source of a file at the indexed revision,
+/// or just a range of its lines, for tooling like LSP hover or export bundles
+/// that needs the exact bytes.  Produces a JsonValue with the path, the
+/// indexed `revision`, the `syntax` slug (ex: "cpp"), the line range, the
+/// file's total line count, and the `contents`, or just the contents as a
+/// TextFile with `--raw`.
+#[derive(Debug, Args)]
+pub struct CatSource {
+    /// Tree-relative source file path.
+    #[clap(value_parser)]
+    file: String,
+
+    /// The 1-based first line to include.
+    #[clap(long, value_parser)]
+    start_line: Option<u32>,
+
+    /// The 1-based last line to include, inclusive.
+    #[clap(long, value_parser)]
+    end_line: Option<u32>,
+
+    /// The revision the source must be from, ex: one recorded by an earlier
+    /// `cat-source`.  This fails if the tree has since been re-indexed at a
+    /// different revision.
+    #[clap(long, value_parser)]
+    rev: Option<String>,
+
+    /// Produce just the contents as a TextFile.
+    #[clap(long, action)]
+    raw: bool,
+}
+
+#[derive(Debug)]
+pub struct CatSourceCommand {
+    pub args: CatSource,
+}
+
+#[async_trait]
+impl PipelineCommand for CatSourceCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        if !matches!(input, PipelineValues::Void) {
+            return Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::BadQuery,
+                    ErrorLayer::ConfigLayer,
+                    "cat-source needs no input".to_string(),
+                )
+                .with_hint("Use `explain` to see what each pipeline stage consumes and produces."),
+            ));
+        }
+
+        let lines = match (self.args.start_line, self.args.end_line) {
+            (None, None) => None,
+            (start, end) => Some((start.unwrap_or(1), end.unwrap_or(u32::MAX))),
+        };
+        let source = server
+            .fetch_raw_source_range(&self.args.file, self.args.rev.as_deref(), lines)
+            .await?;
+
+        if self.args.raw {
+            return Ok(PipelineValues::TextFile(TextFile {
+                mime_type: "text/plain".to_string(),
+                contents: source.contents,
+            }));
+        }
+        Ok(PipelineValues::JsonValue(JsonValue {
+            value: serde_json::to_value(source)?,
+        }))
+    }
+}
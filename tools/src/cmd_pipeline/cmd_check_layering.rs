@@ -0,0 +1,278 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+
+use async_trait::async_trait;
+use clap::Args;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio_stream::StreamExt;
+
+use super::interface::{JsonValue, PipelineCommand, PipelineValues};
+use crate::{
+    abstract_server::{AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError},
+    file_format::analysis::parse_location,
+};
+
+/// Check the symbol references made by the tree against a layering
+/// specification so that CI can gate patches on not introducing dependencies
+/// like layout/ calling into browser/.  The specification is a JSON file with
+/// an ordered list of directory `layers`, from lowest to highest, where code
+/// may only use symbols defined in its own layer or lower ones, and/or an
+/// `allow` matrix listing the directories each directory may depend on, which
+/// takes precedence over the layers:
+///
+/// ```json
+/// { "layers": ["mfbt", "xpcom", "netwerk", "dom", "layout", "browser"],
+///   "allow": { "layout": ["mfbt", "xpcom", "dom", "gfx"] } }
+/// ```
+///
+/// Each file is assigned to the most specific directory containing it, and
+/// references to symbols are assigned to the directory containing the
+/// symbol's definition.  Files and symbols outside of all of the listed
+/// directories are not checked.
+///
+/// Produces an error listing the violations, with counts and example sites for
+/// each pair of directories, if there are any (unless `--warn-only`),
+/// otherwise a JsonValue report.
+#[derive(Debug, Args)]
+pub struct CheckLayering {
+    /// The layering specification file.
+    #[clap(value_parser)]
+    spec_file: String,
+
+    /// How many example sites to report for each violating pair of
+    /// directories.
+    #[clap(long, value_parser, default_value = "3")]
+    examples: usize,
+
+    /// The maximum number of files to check in each directory.
+    #[clap(long, value_parser, default_value = "100000")]
+    limit: usize,
+
+    /// Report violations in the JsonValue rather than failing, ex: while
+    /// burning down existing violations.
+    #[clap(long, value_parser)]
+    warn_only: bool,
+}
+
+#[derive(Debug)]
+pub struct CheckLayeringCommand {
+    pub args: CheckLayering,
+}
+
+#[derive(Debug, Deserialize)]
+struct LayeringSpec {
+    #[serde(default)]
+    layers: Vec<String>,
+    #[serde(default)]
+    allow: BTreeMap<String, Vec<String>>,
+}
+
+impl LayeringSpec {
+    fn normalize(mut self) -> Self {
+        let trim = |dir: &mut String| *dir = dir.trim_matches('/').to_string();
+        self.layers.iter_mut().for_each(trim);
+        self.allow = self
+            .allow
+            .into_iter()
+            .map(|(mut dir, mut allowed)| {
+                trim(&mut dir);
+                allowed.iter_mut().for_each(trim);
+                (dir, allowed)
+            })
+            .collect();
+        self
+    }
+
+    /// All of the directories the specification mentions.
+    fn dirs(&self) -> BTreeSet<&str> {
+        self.layers
+            .iter()
+            .chain(self.allow.keys())
+            .chain(self.allow.values().flatten())
+            .map(|dir| dir.as_str())
+            .collect()
+    }
+
+    /// The directories whose files have constraints to check.
+    fn constrained_dirs(&self) -> BTreeSet<&str> {
+        self.layers
+            .iter()
+            .chain(self.allow.keys())
+            .map(|dir| dir.as_str())
+            .collect()
+    }
+
+    fn is_allowed(&self, from: &str, to: &str) -> bool {
+        if from == to {
+            return true;
+        }
+        if let Some(allowed) = self.allow.get(from) {
+            return allowed.iter().any(|dir| dir == to);
+        }
+        let layer = |dir: &str| self.layers.iter().position(|l| l == dir);
+        match (layer(from), layer(to)) {
+            (Some(from_layer), Some(to_layer)) => to_layer <= from_layer,
+            _ => true,
+        }
+    }
+}
+
+/// The most specific of the directories containing the path.
+fn dir_for<'a>(dirs: &BTreeSet<&'a str>, path: &str) -> Option<&'a str> {
+    dirs.iter()
+        .filter(|dir| {
+            path.strip_prefix(**dir)
+                .map_or(false, |rest| rest.is_empty() || rest.starts_with('/'))
+        })
+        .max_by_key(|dir| dir.len())
+        .copied()
+}
+
+fn layering_problem(message: String) -> ServerError {
+    ServerError::StickyProblem(ErrorDetails::new(
+        ErrorKind::BadQuery,
+        ErrorLayer::BadInput,
+        message,
+    ))
+}
+
+/// References from one directory into another that it may not depend on.
+#[derive(Default)]
+struct Violation {
+    count: usize,
+    examples: Vec<Value>,
+}
+
+#[async_trait]
+impl PipelineCommand for CheckLayeringCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        _input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let contents = fs::read_to_string(&self.args.spec_file).map_err(|e| {
+            layering_problem(format!(
+                "Unable to read layering spec {}: {}",
+                self.args.spec_file, e
+            ))
+        })?;
+        let spec = serde_json::from_str::<LayeringSpec>(&contents)
+            .map_err(|e| {
+                layering_problem(format!("Bad layering spec {}: {}", self.args.spec_file, e))
+            })?
+            .normalize();
+        let dirs = spec.dirs();
+
+        // Where each referenced symbol is defined, if anywhere we know of.
+        let mut def_dirs: HashMap<String, Option<&str>> = HashMap::new();
+        let mut violations: BTreeMap<(&str, &str), Violation> = BTreeMap::new();
+        let mut files_checked = 0;
+        for from_dir in spec.constrained_dirs() {
+            let pathre = format!("^{}/", regex::escape(from_dir));
+            let matches = server.search_files(&pathre, false, self.args.limit).await?;
+            for fm in matches.file_matches {
+                // Files in more specific directories are checked for those.
+                if fm.concise.is_dir || dir_for(&dirs, &fm.path) != Some(from_dir) {
+                    continue;
+                }
+                // Not every file has analysis data, which is fine.
+                let Ok(mut records) = server.fetch_raw_analysis(&fm.path).await else {
+                    continue;
+                };
+                files_checked += 1;
+                while let Some(val) = records.next().await {
+                    if !val["target"].is_number() {
+                        continue;
+                    }
+                    let (Some(loc), Some(syms), Some(kind)) = (
+                        val["loc"].as_str(),
+                        val["sym"].as_str(),
+                        val["kind"].as_str(),
+                    ) else {
+                        continue;
+                    };
+                    if kind == "def" || kind == "decl" {
+                        continue;
+                    }
+                    for sym in syms.split(',') {
+                        let to_dir = match def_dirs.get(sym).copied() {
+                            Some(to_dir) => to_dir,
+                            None => {
+                                let info = server.crossref_lookup_keys(sym, &["defs"]).await?;
+                                let def_path =
+                                    info.pointer("/defs/0/path").and_then(|path| path.as_str());
+                                let to_dir = def_path.and_then(|path| dir_for(&dirs, path));
+                                def_dirs.insert(sym.to_string(), to_dir);
+                                to_dir
+                            }
+                        };
+                        let Some(to_dir) = to_dir else {
+                            continue;
+                        };
+                        if spec.is_allowed(from_dir, to_dir) {
+                            continue;
+                        }
+                        let violation = violations.entry((from_dir, to_dir)).or_default();
+                        violation.count += 1;
+                        if violation.examples.len() < self.args.examples {
+                            violation.examples.push(json!({
+                                "path": fm.path,
+                                "line": parse_location(loc).lineno,
+                                "symbol": sym,
+                            }));
+                        }
+                    }
+                }
+            }
+        }
+
+        let report: Vec<Value> = violations
+            .iter()
+            .map(|((from, to), violation)| {
+                json!({
+                    "from": from,
+                    "to": to,
+                    "count": violation.count,
+                    "examples": violation.examples,
+                })
+            })
+            .collect();
+
+        if !violations.is_empty() && !self.args.warn_only {
+            let mut message = format!(
+                "check-layering found {} disallowed references between {} pairs of directories",
+                violations.values().map(|v| v.count).sum::<usize>(),
+                violations.len()
+            );
+            for ((from, to), violation) in &violations {
+                message.push_str(&format!(
+                    "\n  {} -> {}: {} references",
+                    from, to, violation.count
+                ));
+                for example in &violation.examples {
+                    message.push_str(&format!(
+                        "\n    {}#{} uses {}",
+                        example["path"].as_str().unwrap_or_default(),
+                        example["line"],
+                        example["symbol"].as_str().unwrap_or_default()
+                    ));
+                }
+            }
+            return Err(ServerError::StickyProblem(
+                ErrorDetails::new(ErrorKind::Internal, ErrorLayer::DataLayer, message)
+                    .with_context("spec_file", &self.args.spec_file)
+                    .with_hint(
+                        "If the dependency is intended, allow it in the layering specification.",
+                    ),
+            ));
+        }
+
+        Ok(PipelineValues::JsonValue(JsonValue {
+            value: json!({
+                "filesChecked": files_checked,
+                "violations": report,
+            }),
+        }))
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use async_trait::async_trait;
+use clap::Args;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::interface::{JsonValue, PipelineCommand, PipelineValues};
+use crate::{
+    abstract_server::{AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError},
+    file_format::analysis::parse_structured_value,
+};
+
+/// Check the sizes of records against a budget file so that CI can gate
+/// patches on not growing key structs like nsIFrame beyond agreed sizes.  The
+/// budget file is JSON mapping each record's pretty name (or symbol) to either
+/// a maximum size in bytes for all platforms or an object of per-platform
+/// maximums, where `*` covers the platforms not otherwise listed:
+///
+/// ```json
+/// { "nsIFrame": { "*": 144, "win64": 152 }, "mozilla::dom::Element": 128 }
+/// ```
+///
+/// The sizes are the per-platform `sizeBytes` of the records' structured
+/// layout data.  Produces an error listing the violations (and any budgeted
+/// records that couldn't be found) if there are any, otherwise a JsonValue
+/// report of the checks with their remaining headroom.
+#[derive(Debug, Args)]
+pub struct CheckLayoutBudget {
+    /// The budget file.
+    #[clap(value_parser)]
+    budget_file: String,
+}
+
+#[derive(Debug)]
+pub struct CheckLayoutBudgetCommand {
+    pub args: CheckLayoutBudget,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Budget {
+    All(u32),
+    PerPlatform(BTreeMap<String, u32>),
+}
+
+impl Budget {
+    /// The budgets that apply to the given platform, labeled with the platform
+    /// they are for.  Records without per-platform data are checked against
+    /// all of the budgets.
+    fn for_platform(&self, platform: Option<&str>) -> Vec<(String, u32)> {
+        match (self, platform) {
+            (Budget::All(max), _) => vec![(platform.unwrap_or("*").to_string(), *max)],
+            (Budget::PerPlatform(maxes), Some(platform)) => maxes
+                .get(platform)
+                .or_else(|| maxes.get("*"))
+                .map(|max| vec![(platform.to_string(), *max)])
+                .unwrap_or_default(),
+            (Budget::PerPlatform(maxes), None) => maxes
+                .iter()
+                .map(|(platform, max)| (platform.clone(), *max))
+                .collect(),
+        }
+    }
+}
+
+fn budget_problem(message: String) -> ServerError {
+    ServerError::StickyProblem(ErrorDetails::new(
+        ErrorKind::BadQuery,
+        ErrorLayer::BadInput,
+        message,
+    ))
+}
+
+impl CheckLayoutBudgetCommand {
+    /// Find the structured records for a budget file key, which is either a
+    /// symbol or the pretty name of a record.
+    async fn lookup_records(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        key: &str,
+    ) -> Result<Vec<(String, Value)>> {
+        let info = server.crossref_lookup_keys(key, &["meta"]).await?;
+        if info["meta"].is_object() {
+            return Ok(vec![(key.to_string(), info["meta"].clone())]);
+        }
+
+        let mut records = vec![];
+        for (sym, pretty) in server.search_identifiers(key, true, false, 100).await? {
+            if pretty.as_str() != key {
+                continue;
+            }
+            let info = server.crossref_lookup_keys(&sym, &["meta"]).await?;
+            if info["meta"]["sizeBytes"].is_number() || info["meta"]["variants"].is_array() {
+                records.push((sym.to_string(), info["meta"].clone()));
+            }
+        }
+        Ok(records)
+    }
+}
+
+#[async_trait]
+impl PipelineCommand for CheckLayoutBudgetCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        _input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let contents = fs::read_to_string(&self.args.budget_file).map_err(|e| {
+            budget_problem(format!(
+                "Unable to read budget file {}: {}",
+                self.args.budget_file, e
+            ))
+        })?;
+        let budgets: BTreeMap<String, Budget> = serde_json::from_str(&contents).map_err(|e| {
+            budget_problem(format!("Bad budget file {}: {}", self.args.budget_file, e))
+        })?;
+
+        let mut checks = vec![];
+        let mut violations = vec![];
+        let mut missing = vec![];
+        for (key, budget) in &budgets {
+            let records = self.lookup_records(server, key).await?;
+            let mut checked = false;
+            for (sym, meta) in records {
+                let Ok(structured) = parse_structured_value(meta) else {
+                    continue;
+                };
+                for (platform, record) in structured.per_platform() {
+                    let Some(size) = record.size_bytes else {
+                        continue;
+                    };
+                    for (platform, max) in budget.for_platform(platform.as_deref()) {
+                        checked = true;
+                        if size > max {
+                            violations.push(format!(
+                                "{} ({}) on {}: {} bytes exceeds the {} byte budget",
+                                key, sym, platform, size, max
+                            ));
+                        }
+                        checks.push(json!({
+                            "record": key,
+                            "symbol": sym,
+                            "platform": platform,
+                            "size": size,
+                            "budget": max,
+                            "headroom": max as i64 - size as i64,
+                        }));
+                    }
+                }
+            }
+            if !checked {
+                missing.push(key.clone());
+            }
+        }
+
+        if !violations.is_empty() || !missing.is_empty() {
+            let mut message = format!(
+                "check-layout-budget found {} violations and {} missing records",
+                violations.len(),
+                missing.len()
+            );
+            for violation in &violations {
+                message.push_str(&format!("\n  {}", violation));
+            }
+            for key in &missing {
+                message.push_str(&format!("\n  {}: no layout data found", key));
+            }
+            return Err(ServerError::StickyProblem(
+                ErrorDetails::new(ErrorKind::Internal, ErrorLayer::DataLayer, message)
+                    .with_context("budget_file", &self.args.budget_file)
+                    .with_hint(
+                        "If the growth is intended, raise the record's budget in the budget file.",
+                    ),
+            ));
+        }
+
+        Ok(PipelineValues::JsonValue(JsonValue {
+            value: json!({
+                "checked": checks,
+            }),
+        }))
+    }
+}
@@ -14,7 +14,8 @@ use super::interface::{
 
 use crate::{
     abstract_server::{
-        AbstractServer, ErrorDetails, ErrorLayer, FileMatch, Result, ServerError, TextMatchesByFile,
+        AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, FileMatch, Result, ServerError,
+        TextMatchesByFile,
     },
     file_format::analysis::PathSearchResult,
 };
@@ -172,10 +173,11 @@ impl SearchResults {
         };
 
         let root_pretty = *self.sym_to_pretty.get(&root_sym).ok_or_else(|| {
-            ServerError::StickyProblem(ErrorDetails {
-                layer: ErrorLayer::RuntimeInvariantViolation,
-                message: format!("no pretty available for root_sym {}", root_sym),
-            })
+            ServerError::StickyProblem(ErrorDetails::new(
+                ErrorKind::Internal,
+                ErrorLayer::RuntimeInvariantViolation,
+                format!("no pretty available for root_sym {}", root_sym),
+            ))
         })?;
 
         if let Value::Object(obj) = info.crossref_info {
@@ -696,10 +698,16 @@ impl PipelineJunctionCommand for CompileResultsCommand {
                     results.ingest_fulltext_hits(tm.by_file);
                 }
                 _ => {
-                    return Err(ServerError::StickyProblem(ErrorDetails {
-                        layer: ErrorLayer::ConfigLayer,
-                        message: "compile-results got something weird".to_string(),
-                    }));
+                    return Err(ServerError::StickyProblem(
+                        ErrorDetails::new(
+                            ErrorKind::BadQuery,
+                            ErrorLayer::ConfigLayer,
+                            "compile-results got something weird".to_string(),
+                        )
+                        .with_hint(
+                            "Use `explain` to see what each pipeline stage consumes and produces.",
+                        ),
+                    ));
                 }
             }
         }
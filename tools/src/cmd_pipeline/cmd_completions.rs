@@ -0,0 +1,51 @@
+use async_trait::async_trait;
+use clap::Args;
+use clap_complete::{generate, Shell};
+
+use super::{
+    interface::{PipelineCommand, PipelineValues, TextFile},
+    plugins::tool_command_with_plugins,
+};
+use crate::abstract_server::{AbstractServer, Result};
+
+/// Generate a shell completion script covering all of the pipeline commands,
+/// their arguments, and the possible values of their enum arguments.  Install
+/// with something like:
+///
+/// `searchfox-tool 'completions --shell=bash' > ~/.local/share/bash-completion/completions/searchfox-tool`
+///
+/// Note that completions only help with the first command of a pipeline since
+/// searchfox-tool expects the whole pipeline as a single quoted argument.
+#[derive(Debug, Args)]
+pub struct Completions {
+    /// The shell to generate completions for.
+    #[clap(long, value_parser, value_enum)]
+    shell: Shell,
+}
+
+#[derive(Debug)]
+pub struct CompletionsCommand {
+    pub args: Completions,
+}
+
+#[async_trait]
+impl PipelineCommand for CompletionsCommand {
+    async fn execute(
+        &self,
+        _server: &(dyn AbstractServer + Send + Sync),
+        _input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let mut buf = vec![];
+        generate(
+            self.args.shell,
+            &mut tool_command_with_plugins(),
+            "searchfox-tool",
+            &mut buf,
+        );
+
+        Ok(PipelineValues::TextFile(TextFile {
+            mime_type: "text/plain".to_string(),
+            contents: String::from_utf8_lossy(&buf).to_string(),
+        }))
+    }
+}
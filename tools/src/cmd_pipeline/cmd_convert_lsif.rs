@@ -0,0 +1,82 @@
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io::BufReader;
+
+use async_trait::async_trait;
+use clap::Args;
+use protobuf::Message;
+use serde_json::json;
+
+use super::interface::{JsonValue, PipelineCommand, PipelineValues};
+use crate::{
+    abstract_server::{AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError},
+    file_format::lsif::lsif_to_scip,
+};
+
+/// Convert an LSIF dump into a SCIP index (see `file_format/lsif.rs`) using the
+/// per-language rules for mapping LSIF monikers onto SCIP symbols.
+/// `scip-indexer` can consume LSIF dumps directly, so this is mainly for
+/// checking how a dump maps onto SCIP, for example with `scip print`.
+/// Produces a JsonValue summarizing the converted index.
+#[derive(Debug, Args)]
+pub struct ConvertLsif {
+    /// The LSIF dump in its JSON lines form.
+    #[clap(value_parser)]
+    input: String,
+
+    /// Where to write the SCIP index.
+    #[clap(long, value_parser)]
+    output: String,
+}
+
+#[derive(Debug)]
+pub struct ConvertLsifCommand {
+    pub args: ConvertLsif,
+}
+
+fn lsif_problem(message: String) -> ServerError {
+    ServerError::StickyProblem(ErrorDetails::new(
+        ErrorKind::BadQuery,
+        ErrorLayer::BadInput,
+        message,
+    ))
+}
+
+#[async_trait]
+impl PipelineCommand for ConvertLsifCommand {
+    async fn execute(
+        &self,
+        _server: &(dyn AbstractServer + Send + Sync),
+        _input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let file = File::open(&self.args.input)
+            .map_err(|e| lsif_problem(format!("Unable to open {}: {}", self.args.input, e)))?;
+        let index = lsif_to_scip(BufReader::new(file))
+            .map_err(|e| lsif_problem(format!("Bad LSIF dump {}: {}", self.args.input, e)))?;
+        let bytes = index.write_to_bytes().map_err(|e| {
+            ServerError::StickyProblem(ErrorDetails::new(
+                ErrorKind::Internal,
+                ErrorLayer::ServerLayer,
+                format!("Unable to encode SCIP index: {}", e),
+            ))
+        })?;
+        fs::write(&self.args.output, bytes)?;
+
+        let languages: BTreeSet<&str> = index
+            .documents
+            .iter()
+            .map(|doc| doc.language.as_str())
+            .collect();
+        let occurrences: usize = index.documents.iter().map(|d| d.occurrences.len()).sum();
+        let symbols: usize = index.documents.iter().map(|d| d.symbols.len()).sum();
+        Ok(PipelineValues::JsonValue(JsonValue {
+            value: json!({
+                "tool": index.metadata.tool_info.name,
+                "documents": index.documents.len(),
+                "occurrences": occurrences,
+                "symbols": symbols,
+                "languages": languages,
+            }),
+        }))
+    }
+}
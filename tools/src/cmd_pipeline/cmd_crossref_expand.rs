@@ -6,12 +6,17 @@ use serde_json::Value;
 use tracing::trace;
 use ustr::ustr;
 
-use super::interface::{
-    OverloadInfo, OverloadKind, PipelineCommand, PipelineValues, SymbolCrossrefInfo,
-    SymbolCrossrefInfoList, SymbolMetaFlags, SymbolRelation,
+use super::{
+    interface::{
+        OverloadInfo, OverloadKind, PipelineCommand, PipelineValues, SymbolCrossrefInfo,
+        SymbolCrossrefInfoList, SymbolMetaFlags, SymbolRelation,
+    },
+    pagination::PaginationArgs,
 };
 
-use crate::abstract_server::{AbstractServer, ErrorDetails, ErrorLayer, Result, ServerError};
+use crate::abstract_server::{
+    AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError,
+};
 
 /// Given a set of symbol crossref data, expand the set via relevant semantic
 /// relationships like override set membership.  This is fundamentally entwined
@@ -28,6 +33,9 @@ pub struct CrossrefExpand {
     pub override_local_limit: u32,
     #[clap(long, value_parser, default_value = "400")]
     pub override_global_limit: u32,
+
+    #[clap(flatten)]
+    pub pagination: PaginationArgs,
 }
 
 /// Crosseref expansion exists to help us:
@@ -86,10 +94,16 @@ impl PipelineCommand for CrossrefExpandCommand {
         let source_crossrefs = match input {
             PipelineValues::SymbolCrossrefInfoList(scil) => scil,
             _ => {
-                return Err(ServerError::StickyProblem(ErrorDetails {
-                    layer: ErrorLayer::ConfigLayer,
-                    message: "crossref-expand needs a CrossrefInfoList".to_string(),
-                }));
+                return Err(ServerError::StickyProblem(
+                    ErrorDetails::new(
+                        ErrorKind::BadQuery,
+                        ErrorLayer::ConfigLayer,
+                        "crossref-expand needs a CrossrefInfoList".to_string(),
+                    )
+                    .with_hint(
+                        "Use `explain` to see what each pipeline stage consumes and produces.",
+                    ),
+                ));
             }
         };
 
@@ -147,6 +161,7 @@ impl PipelineCommand for CrossrefExpandCommand {
                         quality,
                         overloads_hit: vec![],
                         flags: SymbolMetaFlags::default(),
+                        score: None,
                     }
                 }
             };
@@ -317,11 +332,16 @@ impl PipelineCommand for CrossrefExpandCommand {
 
             expanded.push(info);
         }
+        let (expanded, page) = self
+            .args
+            .pagination
+            .paginate(expanded, |info| info.symbol.to_string())?;
 
         Ok(PipelineValues::SymbolCrossrefInfoList(
             SymbolCrossrefInfoList {
                 symbol_crossref_infos: expanded,
                 unknown_symbols: vec![],
+                page,
             },
         ))
     }
@@ -2,12 +2,18 @@ use async_trait::async_trait;
 use clap::Args;
 use ustr::{ustr, Ustr};
 
-use super::interface::{
-    PipelineCommand, PipelineValues, SymbolCrossrefInfo, SymbolCrossrefInfoList, SymbolMetaFlags,
-    SymbolQuality, SymbolRelation,
+use super::{
+    interface::{
+        PipelineCommand, PipelineValues, SymbolCrossrefInfo, SymbolCrossrefInfoList,
+        SymbolMetaFlags, SymbolQuality, SymbolRelation,
+    },
+    pagination::PaginationArgs,
+    ranking::{rank_symbol_crossref_infos, RankProfile},
 };
 
-use crate::abstract_server::{AbstractServer, ErrorDetails, ErrorLayer, Result, ServerError};
+use crate::abstract_server::{
+    AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError,
+};
 
 /// Return the crossref data for one or more symbols received via pipeline or as
 /// explicit arguments.
@@ -28,6 +34,16 @@ pub struct CrossrefLookup {
     /// search-identifiers which were not an absolute identifier match.
     #[clap(short, long, value_parser)]
     exact_match: bool,
+
+    /// Rank the resulting symbols using the given profile, exposing the score
+    /// on each symbol.  By default the input order is preserved.
+    #[clap(long, value_parser, value_enum, default_value = "none")]
+    rank_profile: RankProfile,
+
+    /// Paginating sorts the symbols by symbol name, so it should not be
+    /// combined with ranking.
+    #[clap(flatten)]
+    pagination: PaginationArgs,
 }
 
 #[derive(Debug)]
@@ -62,10 +78,16 @@ impl PipelineCommand for CrossrefLookupCommand {
                 .map(|sym| (sym.clone(), SymbolQuality::ExplicitSymbol, None))
                 .collect(),
             _ => {
-                return Err(ServerError::StickyProblem(ErrorDetails {
-                    layer: ErrorLayer::ConfigLayer,
-                    message: "crossref-lookup needs a Void or SymbolList".to_string(),
-                }));
+                return Err(ServerError::StickyProblem(
+                    ErrorDetails::new(
+                        ErrorKind::BadQuery,
+                        ErrorLayer::ConfigLayer,
+                        "crossref-lookup needs a Void or SymbolList".to_string(),
+                    )
+                    .with_hint(
+                        "Use `explain` to see what each pipeline stage consumes and produces.",
+                    ),
+                ));
             }
         };
 
@@ -90,6 +112,7 @@ impl PipelineCommand for CrossrefLookupCommand {
                 quality,
                 overloads_hit: vec![],
                 flags: SymbolMetaFlags::default(),
+                score: None,
             };
             if let (true, Some(pretty)) = (self.args.exact_match, from_ident) {
                 if pretty.to_lowercase() != crossref_info.get_pretty().to_lowercase() {
@@ -107,6 +130,7 @@ impl PipelineCommand for CrossrefLookupCommand {
                             quality: crossref_info.quality.clone(),
                             overloads_hit: vec![],
                             flags: SymbolMetaFlags::default(),
+                            score: None,
                         });
                     }
                     continue;
@@ -116,10 +140,19 @@ impl PipelineCommand for CrossrefLookupCommand {
             symbol_crossref_infos.push(crossref_info);
         }
 
+        rank_symbol_crossref_infos(&mut symbol_crossref_infos, self.args.rank_profile, |sym| {
+            server.symbol_query_hits(sym)
+        });
+        let (symbol_crossref_infos, page) = self
+            .args
+            .pagination
+            .paginate(symbol_crossref_infos, |info| info.symbol.to_string())?;
+
         Ok(PipelineValues::SymbolCrossrefInfoList(
             SymbolCrossrefInfoList {
                 symbol_crossref_infos,
                 unknown_symbols,
+                page,
             },
         ))
     }
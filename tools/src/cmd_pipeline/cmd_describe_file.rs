@@ -0,0 +1,108 @@
+use std::fs;
+
+use async_trait::async_trait;
+use clap::Args;
+use serde_json::{json, Value};
+
+use super::{
+    interface::{JsonValue, PipelineCommand, PipelineValues},
+    path_filter::PathFilterArgs,
+    transforms::path_glob_transform,
+};
+use crate::abstract_server::{
+    AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, SearchfoxIndexRoot, ServerError,
+};
+
+/// Show the descriptions recorded for files during indexing as a JsonValue
+/// array of `{ path, description, metadata }` objects.  For text files the
+/// description comes from the file's contents (ex: the title of an HTML file).
+/// For binary files it's a summary of the metadata found in the file's headers
+/// (see `binary_metadata.rs`), which is included as `metadata`: image
+/// dimensions and type, font family and tables, or audio duration, sample rate,
+/// and channels.  Files without a description have null values.
+///
+/// Operates on the FileMatches from `search-files` if piped any, otherwise on
+/// the files matching `path`.
+#[derive(Debug, Args)]
+pub struct DescribeFile {
+    /// Path glob of the files to describe when not given FileMatches.
+    #[clap(value_parser)]
+    path: Option<String>,
+
+    /// The maximum number of files to describe when not given FileMatches.
+    #[clap(long, value_parser, default_value = "1000")]
+    limit: usize,
+
+    #[clap(flatten)]
+    path_filter: PathFilterArgs,
+}
+
+#[derive(Debug)]
+pub struct DescribeFileCommand {
+    pub args: DescribeFile,
+}
+
+#[async_trait]
+impl PipelineCommand for DescribeFileCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let mut matches = match input {
+            PipelineValues::FileMatches(fm) => fm,
+            PipelineValues::Void => {
+                let pathre = match &self.args.path {
+                    Some(path) => path_glob_transform(path),
+                    None => "".to_string(),
+                };
+                server.search_files(&pathre, false, self.args.limit).await?
+            }
+            _ => {
+                return Err(ServerError::StickyProblem(
+                    ErrorDetails::new(
+                        ErrorKind::BadQuery,
+                        ErrorLayer::ConfigLayer,
+                        "describe-file needs FileMatches or no input".to_string(),
+                    )
+                    .with_hint(
+                        "Use `explain` to see what each pipeline stage consumes and produces.",
+                    ),
+                ));
+            }
+        };
+
+        let path_filter = self
+            .args
+            .path_filter
+            .compile(&server.tree_info()?.path_sets)?;
+        if !path_filter.is_empty() {
+            matches
+                .file_matches
+                .retain(|fm| path_filter.matches_classified(&fm.path, fm.concise.classification));
+        }
+
+        let mut described = vec![];
+        for fm in &matches.file_matches {
+            if fm.concise.is_dir {
+                continue;
+            }
+            let sidecar_path =
+                server.translate_path(SearchfoxIndexRoot::FileDescription, &fm.path)?;
+            // Only files that had something to say have a description file.
+            let sidecar: Value = match fs::read_to_string(sidecar_path) {
+                Ok(contents) => serde_json::from_str(&contents)?,
+                Err(_) => Value::Null,
+            };
+            described.push(json!({
+                "path": fm.path,
+                "description": sidecar["description"],
+                "metadata": sidecar["metadata"],
+            }));
+        }
+
+        Ok(PipelineValues::JsonValue(JsonValue {
+            value: Value::Array(described),
+        }))
+    }
+}
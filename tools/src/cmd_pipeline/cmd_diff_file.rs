@@ -0,0 +1,180 @@
+use std::ops::Range;
+
+use async_trait::async_trait;
+use clap::Args;
+use serde_json::{json, Value};
+
+use super::interface::{JsonValue, PipelineCommand, PipelineValues};
+use crate::{
+    abstract_server::{AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError},
+    format::format_code,
+    languages::{select_formatting, FormatAs},
+    line_diff::{intra_line_changes, side_by_side_diff, DiffRowKind},
+};
+
+/// Diff a file between two revisions of the tree's git history for
+/// review-style views, producing a JsonValue with the diff's hunks, each of
+/// which has side-by-side rows with the line numbers and syntax-highlighted
+/// HTML of the line on each side (null where a side has no line).  Removed
+/// lines are paired with the added lines that replaced them as "changed" rows,
+/// which are further diffed token by token, with `old_changes` and
+/// `new_changes` listing the [start, end) character offsets of the changed
+/// tokens within each side's `old_text` and `new_text` so that just the
+/// changed identifiers can be highlighted.  The source is read from the tree's
+/// git repo, so this is only supported for local indices, but there's no need
+/// for a checkout of either revision.
+#[derive(Debug, Args)]
+pub struct DiffFile {
+    /// Tree-relative source file path.
+    #[clap(value_parser)]
+    path: String,
+
+    /// The old revision.
+    #[clap(value_parser)]
+    from_rev: String,
+
+    /// The new revision.
+    #[clap(value_parser)]
+    to_rev: String,
+
+    /// The number of lines of context around each change.
+    #[clap(long, value_parser, default_value = "3")]
+    context: u32,
+}
+
+#[derive(Debug)]
+pub struct DiffFileCommand {
+    pub args: DiffFile,
+}
+
+/// Syntax highlight a file's lines without any analysis data.
+fn highlight_lines(path: &str, contents: &str) -> Vec<String> {
+    let (lines, _) = format_code(None, &None, select_formatting(path), path, contents, &[]);
+    lines.into_iter().map(|line| line.line).collect()
+}
+
+#[async_trait]
+impl PipelineCommand for DiffFileCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        if !matches!(input, PipelineValues::Void) {
+            return Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::BadQuery,
+                    ErrorLayer::ConfigLayer,
+                    "diff-file needs no input".to_string(),
+                )
+                .with_hint("Use `explain` to see what each pipeline stage consumes and produces."),
+            ));
+        }
+
+        let path = &self.args.path;
+        if let FormatAs::Binary = select_formatting(path) {
+            return Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::BadQuery,
+                    ErrorLayer::BadInput,
+                    "Cannot diff binary file",
+                )
+                .with_context("path", path),
+            ));
+        }
+
+        let old = server
+            .fetch_source_at_revision(path, &self.args.from_rev)
+            .await?;
+        let new = server
+            .fetch_source_at_revision(path, &self.args.to_rev)
+            .await?;
+        let hunks = side_by_side_diff(path, &old, &new, self.args.context).map_err(|e| {
+            ServerError::StickyProblem(ErrorDetails::new(
+                ErrorKind::Internal,
+                ErrorLayer::ServerLayer,
+                format!("Unable to diff {}: {}", path, e),
+            ))
+        })?;
+
+        let old_html = highlight_lines(path, &old);
+        let new_html = highlight_lines(path, &new);
+        let html = |lines: &[String], lineno: Option<u32>| -> Value {
+            lineno
+                .and_then(|lineno| lines.get(lineno as usize - 1))
+                .map_or(Value::Null, |line| json!(line))
+        };
+
+        let format = select_formatting(path);
+        let old_lines: Vec<&str> = old.lines().collect();
+        let new_lines: Vec<&str> = new.lines().collect();
+        let line_text = |lines: &[&str], lineno: Option<u32>| {
+            lineno.and_then(|lineno| lines.get(lineno as usize - 1).copied())
+        };
+        // Byte ranges become character offsets for the benefit of JS.
+        let char_ranges = |line: &str, ranges: Vec<Range<usize>>| -> Value {
+            ranges
+                .into_iter()
+                .map(|range| {
+                    json!([
+                        line[..range.start].chars().count(),
+                        line[..range.end].chars().count()
+                    ])
+                })
+                .collect()
+        };
+
+        let hunks: Vec<Value> = hunks
+            .into_iter()
+            .map(|hunk| {
+                let rows: Vec<Value> = hunk
+                    .rows
+                    .iter()
+                    .map(|row| {
+                        let old_text = line_text(&old_lines, row.old_lineno);
+                        let new_text = line_text(&new_lines, row.new_lineno);
+                        let (old_changes, new_changes) = match (row.kind, old_text, new_text) {
+                            (DiffRowKind::Changed, Some(old_text), Some(new_text)) => {
+                                let (old_changes, new_changes) =
+                                    intra_line_changes(&format, old_text, new_text);
+                                (
+                                    char_ranges(old_text, old_changes),
+                                    char_ranges(new_text, new_changes),
+                                )
+                            }
+                            _ => (Value::Null, Value::Null),
+                        };
+                        json!({
+                            "kind": row.kind,
+                            "old_line": row.old_lineno,
+                            "new_line": row.new_lineno,
+                            "old_text": old_text,
+                            "new_text": new_text,
+                            "old_html": html(&old_html, row.old_lineno),
+                            "new_html": html(&new_html, row.new_lineno),
+                            "old_changes": old_changes,
+                            "new_changes": new_changes,
+                        })
+                    })
+                    .collect();
+                json!({
+                    "old_start": hunk.old_start,
+                    "old_lines": hunk.old_lines,
+                    "new_start": hunk.new_start,
+                    "new_lines": hunk.new_lines,
+                    "rows": rows,
+                })
+            })
+            .collect();
+
+        Ok(PipelineValues::JsonValue(JsonValue {
+            value: json!({
+                "path": path,
+                "from_rev": self.args.from_rev,
+                "to_rev": self.args.to_rev,
+                "syntax": format.markdown_slug(),
+                "hunks": hunks,
+            }),
+        }))
+    }
+}
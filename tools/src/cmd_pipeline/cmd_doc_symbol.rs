@@ -0,0 +1,88 @@
+use async_trait::async_trait;
+use clap::Args;
+use serde_json::{json, Value};
+
+use super::interface::{JsonValue, JsonValueList, PipelineCommand, PipelineValues};
+
+use crate::abstract_server::{
+    AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError,
+};
+
+/// Return the documentation comment extracted at indexing time for one or more
+/// symbols received via pipeline as a `SymbolCrossrefInfoList` or as explicit
+/// arguments.  This is intended for hover panels and editor integrations.
+#[derive(Debug, Args)]
+pub struct DocSymbol {
+    /// Explicit symbols to lookup.
+    #[clap(value_parser)]
+    symbols: Vec<String>,
+
+    /// Omit symbols that don't have any documentation rather than returning
+    /// them with a null "doc".
+    #[clap(long, value_parser)]
+    documented_only: bool,
+}
+
+#[derive(Debug)]
+pub struct DocSymbolCommand {
+    pub args: DocSymbol,
+}
+
+fn doc_value_for(sym: &str, crossref_info: &Value) -> Value {
+    let meta = &crossref_info["meta"];
+    json!({
+        "sym": sym,
+        "pretty": meta["pretty"],
+        "kind": meta["kind"],
+        "doc": meta["doc"],
+        "path": crossref_info.pointer("/defs/0/path").cloned().unwrap_or(Value::Null),
+        "lno": crossref_info.pointer("/defs/0/lines/0/lno").cloned().unwrap_or(Value::Null),
+    })
+}
+
+#[async_trait]
+impl PipelineCommand for DocSymbolCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let mut docs = vec![];
+        match input {
+            PipelineValues::SymbolCrossrefInfoList(scil) => {
+                for info in scil.symbol_crossref_infos {
+                    docs.push(doc_value_for(&info.symbol, &info.crossref_info));
+                }
+            }
+            PipelineValues::Void => {
+                for sym in &self.args.symbols {
+                    let info = server.crossref_lookup_keys(sym, &["meta", "defs"]).await?;
+                    if info.is_null() {
+                        continue;
+                    }
+                    docs.push(doc_value_for(sym, &info));
+                }
+            }
+            _ => {
+                return Err(ServerError::StickyProblem(
+                    ErrorDetails::new(
+                        ErrorKind::BadQuery,
+                        ErrorLayer::ConfigLayer,
+                        "doc-symbol needs a Void or SymbolCrossrefInfoList".to_string(),
+                    )
+                    .with_hint(
+                        "Use `explain` to see what each pipeline stage consumes and produces.",
+                    ),
+                ));
+            }
+        };
+
+        Ok(PipelineValues::JsonValueList(JsonValueList {
+            values: docs
+                .into_iter()
+                .filter(|value| !self.args.documented_only || value["doc"].is_string())
+                .map(|value| JsonValue { value })
+                .collect(),
+        }))
+    }
+}
@@ -0,0 +1,207 @@
+use std::collections::{BTreeMap, HashMap};
+
+use async_trait::async_trait;
+use clap::{Args, CommandFactory, Parser};
+use serde_json::{json, Value};
+
+use super::{
+    cmd_query::parse_param,
+    interface::{JsonValue, PipelineCommand, PipelineValues},
+    parser::{JunctionOpts, ToolOpts},
+    signatures::{check_stage_compat, command_signature, junction_signature, CommandSignature},
+};
+use crate::{
+    abstract_server::{AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError},
+    query::{chew_query::PipelineSegment, pipeline_spec::load_pipeline_spec},
+};
+
+/// Describe each stage of a pipeline, including the `PipelineValues` variants
+/// it consumes and produces, and flag stages whose input doesn't line up with
+/// the previous stage's output, all without running anything.
+///
+/// The pipeline can be provided as a (quoted) searchfox-tool pipeline string
+/// or via `--spec` as a pipeline spec file.
+#[derive(Debug, Args)]
+pub struct Explain {
+    /// The pipeline to explain, like "search-identifiers Foo | crossref-lookup".
+    #[clap(value_parser)]
+    pipeline: Option<String>,
+
+    /// Path to a pipeline spec file to explain instead of a pipeline string.
+    /// Only available from the command line.
+    #[clap(long, value_parser)]
+    pub spec: Option<String>,
+
+    /// Spec variables of the form `name=value`.
+    #[clap(long, value_parser = parse_param)]
+    var: Vec<(String, String)>,
+}
+
+#[derive(Debug)]
+pub struct ExplainCommand {
+    pub args: Explain,
+}
+
+fn bad_input(message: String) -> ServerError {
+    ServerError::StickyProblem(ErrorDetails::new(
+        ErrorKind::BadQuery,
+        ErrorLayer::BadInput,
+        message,
+    ))
+}
+
+fn no_signature(name: &str) -> ServerError {
+    ServerError::StickyProblem(ErrorDetails::new(
+        ErrorKind::Internal,
+        ErrorLayer::RuntimeInvariantViolation,
+        format!("No signature for command: {}", name),
+    ))
+}
+
+fn stage_json(group: Option<&str>, command: &str, sig: &CommandSignature) -> Value {
+    json!({
+        "group": group,
+        "command": command,
+        "inputs": sig.inputs,
+        "outputs": sig.outputs,
+    })
+}
+
+fn segment_args(segment: &PipelineSegment) -> Vec<String> {
+    ["searchfox-tool".to_string(), segment.command.clone()]
+        .iter()
+        .chain(&segment.args.to_vec())
+        .cloned()
+        .collect()
+}
+
+impl ExplainCommand {
+    fn explain_pipeline(&self, pipeline: &str) -> Result<Value> {
+        let all_args = shell_words::split(pipeline).map_err(|e| bad_input(e.to_string()))?;
+
+        let mut stages = vec![];
+        let mut problems = vec![];
+        let mut prev: Option<(String, CommandSignature)> = None;
+        for arg_slices in all_args.split(|v| v == "|") {
+            let mut fake_args = vec!["searchfox-tool".to_string()];
+            fake_args.extend(arg_slices.iter().cloned());
+            let matches = ToolOpts::command()
+                .try_get_matches_from(fake_args)
+                .map_err(|e| bad_input(e.to_string()))?;
+            let name = matches.subcommand_name().unwrap_or_default().to_string();
+            let sig = command_signature(&name).ok_or_else(|| no_signature(&name))?;
+            if let Some(problem) =
+                check_stage_compat(prev.as_ref().map(|(n, s)| (n.as_str(), s)), &name, &sig)
+            {
+                problems.push(problem);
+            }
+            stages.push(stage_json(None, &name, &sig));
+            prev = Some((name, sig));
+        }
+
+        Ok(json!({ "stages": stages, "problems": problems }))
+    }
+
+    fn explain_spec(&self, spec_path: &str) -> Result<Value> {
+        let vars: BTreeMap<String, String> = self.args.var.iter().cloned().collect();
+        let builder = load_pipeline_spec(spec_path)?.to_group_builder(&vars)?;
+
+        // Map each output name to the name and signature of the last command
+        // of the group/junction producing it.
+        let mut producers: HashMap<String, (String, CommandSignature)> = HashMap::new();
+        let mut group_sigs = BTreeMap::new();
+        for (group_name, group) in &builder.groups {
+            let mut sigs = vec![];
+            for segment in &group.segments {
+                // Parse the args to validate them even though we only need the
+                // command name to look up the signature.
+                ToolOpts::try_parse_from(segment_args(segment))
+                    .map_err(|e| bad_input(e.to_string()))?;
+                let sig = command_signature(&segment.command)
+                    .ok_or_else(|| no_signature(&segment.command))?;
+                sigs.push((segment.command.clone(), sig));
+            }
+            if let (Some(output), Some(last)) = (&group.output, sigs.last()) {
+                producers.insert(output.clone(), last.clone());
+            }
+            group_sigs.insert(group_name.clone(), sigs);
+        }
+        let mut junction_sigs = BTreeMap::new();
+        for (junction_name, junction) in &builder.junctions {
+            JunctionOpts::try_parse_from(segment_args(&junction.command))
+                .map_err(|e| bad_input(e.to_string()))?;
+            let sig = junction_signature(&junction.command.command)
+                .ok_or_else(|| no_signature(&junction.command.command))?;
+            if let Some(output) = &junction.output {
+                producers.insert(
+                    output.clone(),
+                    (junction.command.command.clone(), sig.clone()),
+                );
+            }
+            junction_sigs.insert(junction_name.clone(), sig);
+        }
+
+        let mut stages = vec![];
+        let mut problems = vec![];
+        for phase in &builder.phases {
+            for group_name in phase.groups.iter().flatten() {
+                let group = &builder.groups[group_name];
+                let mut prev = group
+                    .input
+                    .as_ref()
+                    .and_then(|input| producers.get(input).cloned());
+                for (name, sig) in &group_sigs[group_name] {
+                    if let Some(problem) =
+                        check_stage_compat(prev.as_ref().map(|(n, s)| (n.as_str(), s)), name, sig)
+                    {
+                        problems.push(format!("{}: {}", group_name, problem));
+                    }
+                    stages.push(stage_json(Some(group_name), name, sig));
+                    prev = Some((name.clone(), sig.clone()));
+                }
+            }
+            for junction_name in &phase.junctions {
+                let junction = &builder.junctions[junction_name];
+                let sig = &junction_sigs[junction_name];
+                for input in &junction.inputs {
+                    let prev = producers.get(input);
+                    if let Some(problem) = check_stage_compat(
+                        prev.map(|(n, s)| (n.as_str(), s)),
+                        &junction.command.command,
+                        sig,
+                    ) {
+                        problems.push(format!("{}: {}", junction_name, problem));
+                    }
+                }
+                stages.push(stage_json(
+                    Some(junction_name),
+                    &junction.command.command,
+                    sig,
+                ));
+            }
+        }
+
+        Ok(json!({ "stages": stages, "problems": problems }))
+    }
+}
+
+#[async_trait]
+impl PipelineCommand for ExplainCommand {
+    async fn execute(
+        &self,
+        _server: &(dyn AbstractServer + Send + Sync),
+        _input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let value = match (&self.args.pipeline, &self.args.spec) {
+            (Some(pipeline), None) => self.explain_pipeline(pipeline)?,
+            (None, Some(spec)) => self.explain_spec(spec)?,
+            _ => {
+                return Err(bad_input(
+                    "explain needs exactly one of a pipeline string or --spec".to_string(),
+                ));
+            }
+        };
+
+        Ok(PipelineValues::JsonValue(JsonValue { value }))
+    }
+}
@@ -0,0 +1,504 @@
+use std::collections::{BTreeSet, HashMap};
+
+use async_trait::async_trait;
+use clap::{Args, ValueEnum};
+use serde_json::{json, Value};
+use tokio_stream::StreamExt;
+use ustr::ustr;
+
+use super::{
+    cmd_format_symbols::{
+        FormatSymbols, FormatSymbolsCommand, OffsetFormat, SizeFormat, SymbolFormatMode,
+    },
+    interface::{
+        JsonValue, PipelineCommand, PipelineValues, SymbolCrossrefInfo, SymbolCrossrefInfoList,
+        SymbolMetaFlags, SymbolQuality, SymbolRelation, SymbolTreeTable, SymbolTreeTableItem,
+        SymbolTreeTableList, TextFile,
+    },
+};
+use crate::abstract_server::{
+    AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum BundleFormat {
+    /// The bundle as a JsonValue.
+    Json,
+    /// A standalone HTML page as a TextFile.
+    Html,
+}
+
+/// Gather the complete context of a set of symbols received via pipeline as a
+/// `SymbolCrossrefInfoList`, or of the symbols defined in the given files, into
+/// a single self-contained bundle that can be attached to a bug or review for
+/// readers without searchfox access.  The bundle has each symbol's crossref
+/// records and structured data, source excerpts around its definitions,
+/// declarations, and first few uses, the `format-symbols` field layout tables
+/// of any classes, and the source of the given files.
+///
+/// Excerpts are best-effort; if the source isn't available (ex: a remote
+/// server), the crossref's line text is used instead.
+#[derive(Debug, Args)]
+pub struct ExportBundle {
+    /// File(s) whose source and defined symbols should be bundled, when not
+    /// given symbols via pipeline.
+    #[clap(long, value_parser)]
+    path: Vec<String>,
+
+    #[clap(long, value_parser, value_enum, default_value = "json")]
+    format: BundleFormat,
+
+    /// Lines of context to include before and after each excerpted line.
+    #[clap(long, value_parser, default_value = "3")]
+    context: u64,
+
+    /// How many uses of each symbol to excerpt, in addition to all of its
+    /// definitions and declarations.
+    #[clap(long, value_parser, default_value = "5")]
+    uses: usize,
+
+    /// The maximum number of symbols defined in the given files to bundle.
+    #[clap(long, value_parser, default_value = "50")]
+    max_symbols: usize,
+}
+
+#[derive(Debug)]
+pub struct ExportBundleCommand {
+    pub args: ExportBundle,
+}
+
+/// Files longer than this are truncated in the bundle.
+const MAX_FILE_LINES: usize = 5000;
+
+/// Crossref structured kinds that `format-symbols` can produce a layout for.
+const LAYOUT_KINDS: [&str; 5] = ["class", "struct", "union", "__interface", "enum"];
+
+type SourceCache = HashMap<String, Option<Vec<String>>>;
+
+async fn source_lines<'a>(
+    server: &(dyn AbstractServer + Send + Sync),
+    sources: &'a mut SourceCache,
+    path: &str,
+) -> Option<&'a Vec<String>> {
+    if !sources.contains_key(path) {
+        let lines = server
+            .fetch_raw_source(path)
+            .await
+            .ok()
+            .map(|source| source.lines().map(str::to_string).collect());
+        sources.insert(path.to_string(), lines);
+    }
+    sources[path].as_ref()
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+impl ExportBundleCommand {
+    /// Excerpt the lines around `lno`, falling back to the crossref's `line`
+    /// text if we can't get the source.
+    async fn excerpt(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        sources: &mut SourceCache,
+        kind: &str,
+        path: &str,
+        hit: &Value,
+    ) -> Option<Value> {
+        let lno = hit["lno"].as_u64()?;
+        let (first_lno, lines) = match source_lines(server, sources, path).await {
+            Some(lines) if lno as usize <= lines.len() => {
+                let first = lno.saturating_sub(self.args.context).max(1);
+                let last = (lno + self.args.context).min(lines.len() as u64);
+                (first, lines[first as usize - 1..last as usize].to_vec())
+            }
+            _ => (
+                lno,
+                vec![hit["line"].as_str().unwrap_or_default().to_string()],
+            ),
+        };
+        Some(json!({
+            "kind": kind,
+            "path": path,
+            "lno": lno,
+            "first_lno": first_lno,
+            "lines": lines,
+        }))
+    }
+
+    async fn bundle_symbol(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        sources: &mut SourceCache,
+        info: &SymbolCrossrefInfo,
+    ) -> Value {
+        let mut crossref = info.crossref_info.clone();
+        let structured = match &mut crossref {
+            Value::Object(obj) => obj.remove("meta").unwrap_or(Value::Null),
+            _ => Value::Null,
+        };
+
+        let mut excerpts = vec![];
+        for (kind, limit) in [
+            ("defs", usize::MAX),
+            ("decls", usize::MAX),
+            ("uses", self.args.uses),
+        ] {
+            let mut remaining = limit;
+            for path_hits in crossref[kind].as_array().into_iter().flatten() {
+                let Some(path) = path_hits["path"].as_str() else {
+                    continue;
+                };
+                for hit in path_hits["lines"].as_array().into_iter().flatten() {
+                    if remaining == 0 {
+                        break;
+                    }
+                    if let Some(excerpt) = self.excerpt(server, sources, kind, path, hit).await {
+                        excerpts.push(excerpt);
+                        remaining -= 1;
+                    }
+                }
+            }
+        }
+
+        json!({
+            "symbol": info.symbol,
+            "pretty": info.get_pretty(),
+            "structured": structured,
+            "crossref": crossref,
+            "excerpts": excerpts,
+        })
+    }
+
+    /// Find the symbols defined in the given files via their analysis target
+    /// records and look them up.
+    async fn lookup_file_symbols(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+    ) -> Result<Vec<SymbolCrossrefInfo>> {
+        let mut symbols = BTreeSet::new();
+        for path in &self.args.path {
+            let mut records = server.fetch_raw_analysis(path).await?;
+            while let Some(val) = records.next().await {
+                if !val["target"].is_number() || val["kind"].as_str() != Some("def") {
+                    continue;
+                }
+                if let Some(sym) = val["sym"].as_str() {
+                    symbols.insert(sym.to_string());
+                }
+            }
+        }
+
+        let mut infos = vec![];
+        for symbol in symbols.into_iter().take(self.args.max_symbols) {
+            let info = server.crossref_lookup(&symbol, false).await?;
+            if info.is_null() {
+                continue;
+            }
+            infos.push(SymbolCrossrefInfo {
+                symbol: ustr(&symbol),
+                crossref_info: info,
+                relation: SymbolRelation::Queried,
+                quality: SymbolQuality::ExplicitSymbol,
+                overloads_hit: vec![],
+                flags: SymbolMetaFlags::default(),
+                score: None,
+            });
+        }
+        Ok(infos)
+    }
+
+    async fn bundle_file(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        sources: &mut SourceCache,
+        path: &str,
+    ) -> Value {
+        match source_lines(server, sources, path).await {
+            Some(lines) => json!({
+                "path": path,
+                "lines": lines.iter().take(MAX_FILE_LINES).collect::<Vec<_>>(),
+                "truncated": lines.len() > MAX_FILE_LINES,
+            }),
+            None => json!({
+                "path": path,
+                "lines": Value::Null,
+                "truncated": false,
+            }),
+        }
+    }
+}
+
+fn render_excerpt_html(out: &mut String, excerpt: &Value) {
+    let first_lno = excerpt["first_lno"].as_u64().unwrap_or(1);
+    out.push_str(&format!(
+        "<h4>{} {}:{}</h4>\n<pre>",
+        escape_html(excerpt["kind"].as_str().unwrap_or_default()),
+        escape_html(excerpt["path"].as_str().unwrap_or_default()),
+        excerpt["lno"]
+    ));
+    for (i, line) in excerpt["lines"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .enumerate()
+    {
+        out.push_str(&format!(
+            "{:>6}  {}\n",
+            first_lno + i as u64,
+            escape_html(line.as_str().unwrap_or_default())
+        ));
+    }
+    out.push_str("</pre>\n");
+}
+
+fn render_json_details_html(out: &mut String, summary: &str, value: &Value) {
+    if value.is_null() {
+        return;
+    }
+    out.push_str(&format!(
+        "<details><summary>{}</summary><pre>{}</pre></details>\n",
+        summary,
+        escape_html(&serde_json::to_string_pretty(value).unwrap_or_default())
+    ));
+}
+
+fn render_cells_html(out: &mut String, cells: &[Option<String>]) {
+    for cell in cells {
+        out.push_str(&format!(
+            "<td>{}</td>",
+            escape_html(cell.as_deref().unwrap_or_default())
+        ));
+    }
+}
+
+fn render_table_html(out: &mut String, table: &SymbolTreeTable) {
+    out.push_str("<table>\n<tr><th>Name</th><th>Type</th>");
+    for platform in &table.platforms {
+        out.push_str(&format!("<th>{}</th>", escape_html(platform)));
+    }
+    out.push_str("</tr>\n");
+
+    for row in &table.rows {
+        out.push_str(&format!(
+            "<tr><th colspan=\"2\">{}</th>",
+            escape_html(&row.labeled_name())
+        ));
+        for size in &row.sizes {
+            let size = size.as_ref().map(|size| match &size.delta {
+                Some(delta) => format!("{} ({})", size.size, delta),
+                None => size.size.clone(),
+            });
+            render_cells_html(out, &[size]);
+        }
+        out.push_str("</tr>\n");
+
+        for item in &row.items {
+            out.push_str("<tr>");
+            match item {
+                SymbolTreeTableItem::Field(field) => {
+                    let types: Vec<&str> = field.types.iter().map(|t| t.name.as_str()).collect();
+                    render_cells_html(out, &[Some(field.name.clone()), Some(types.join(", "))]);
+                    let offsets: Vec<Option<String>> = field
+                        .offset_and_size
+                        .iter()
+                        .map(|os| os.as_ref().map(|os| format!("{} / {}", os.offset, os.size)))
+                        .collect();
+                    render_cells_html(out, &offsets);
+                }
+                SymbolTreeTableItem::Hole(cells) => {
+                    render_cells_html(out, &[Some("(hole)".to_string()), None]);
+                    render_cells_html(out, cells);
+                }
+                SymbolTreeTableItem::EndPadding(cells) => {
+                    render_cells_html(out, &[Some("(end padding)".to_string()), None]);
+                    render_cells_html(out, cells);
+                }
+                SymbolTreeTableItem::Method(method) => {
+                    render_cells_html(
+                        out,
+                        &[
+                            Some(format!("{}{}", method.name, method.signature)),
+                            Some(method.flags.join(" ")),
+                        ],
+                    );
+                }
+                SymbolTreeTableItem::Cells(cells) => {
+                    render_cells_html(out, &[None, None]);
+                    render_cells_html(out, cells);
+                }
+                SymbolTreeTableItem::Warning(text) => {
+                    out.push_str(&format!(
+                        "<td colspan=\"{}\"><em>{}</em></td>",
+                        2 + table.platforms.len(),
+                        escape_html(text)
+                    ));
+                }
+            }
+            out.push_str("</tr>\n");
+        }
+    }
+    out.push_str("</table>\n");
+}
+
+fn render_bundle_html(bundle: &Value, tables: Option<&SymbolTreeTableList>) -> String {
+    let tree = bundle["tree"].as_str().unwrap_or_default();
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{} bundle</title>\n\
+         <style>\nbody {{ font-family: sans-serif; }}\n\
+         pre {{ background: #f4f4f4; padding: 0.5em; overflow-x: auto; }}\n\
+         table {{ border-collapse: collapse; }}\n\
+         td, th {{ border: 1px solid #ccc; padding: 0.2em 0.5em; text-align: left; }}\n\
+         </style>\n</head>\n<body>\n<h1>{}</h1>\n",
+        escape_html(tree),
+        escape_html(tree)
+    );
+    if let Some(revision) = bundle["revision"].as_str() {
+        out.push_str(&format!("<p>Revision {}</p>\n", escape_html(revision)));
+    }
+
+    for symbol in bundle["symbols"].as_array().into_iter().flatten() {
+        out.push_str(&format!(
+            "<h2>{}</h2>\n<p><code>{}</code></p>\n",
+            escape_html(symbol["pretty"].as_str().unwrap_or_default()),
+            escape_html(symbol["symbol"].as_str().unwrap_or_default())
+        ));
+        for excerpt in symbol["excerpts"].as_array().into_iter().flatten() {
+            render_excerpt_html(&mut out, excerpt);
+        }
+        render_json_details_html(&mut out, "Structured data", &symbol["structured"]);
+        render_json_details_html(&mut out, "Crossref records", &symbol["crossref"]);
+    }
+
+    if let Some(tables) = tables {
+        out.push_str("<h2>Field layouts</h2>\n");
+        for table in &tables.tables {
+            render_table_html(&mut out, table);
+        }
+    }
+
+    for file in bundle["files"].as_array().into_iter().flatten() {
+        out.push_str(&format!(
+            "<h2>{}</h2>\n",
+            escape_html(file["path"].as_str().unwrap_or_default())
+        ));
+        if file["lines"].is_null() {
+            out.push_str("<p><em>Source unavailable.</em></p>\n");
+            continue;
+        }
+        render_excerpt_html(
+            &mut out,
+            &json!({
+                "kind": "source",
+                "path": file["path"],
+                "lno": 1,
+                "first_lno": 1,
+                "lines": file["lines"],
+            }),
+        );
+        if file["truncated"].as_bool() == Some(true) {
+            out.push_str(&format!(
+                "<p><em>Truncated after {} lines.</em></p>\n",
+                MAX_FILE_LINES
+            ));
+        }
+    }
+
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+#[async_trait]
+impl PipelineCommand for ExportBundleCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let infos = match input {
+            PipelineValues::SymbolCrossrefInfoList(scil) => scil.symbol_crossref_infos,
+            PipelineValues::Void if !self.args.path.is_empty() => {
+                self.lookup_file_symbols(server).await?
+            }
+            _ => {
+                return Err(ServerError::StickyProblem(
+                    ErrorDetails::new(
+                        ErrorKind::BadQuery,
+                        ErrorLayer::ConfigLayer,
+                        "export-bundle needs a SymbolCrossrefInfoList or --path".to_string(),
+                    )
+                    .with_hint(
+                        "Use `explain` to see what each pipeline stage consumes and produces.",
+                    ),
+                ));
+            }
+        };
+
+        let mut sources = SourceCache::new();
+        let mut symbols = vec![];
+        let mut layout_infos = vec![];
+        for info in infos {
+            symbols.push(self.bundle_symbol(server, &mut sources, &info).await);
+            let kind = info
+                .crossref_info
+                .pointer("/meta/kind")
+                .and_then(Value::as_str);
+            if kind.map_or(false, |kind| LAYOUT_KINDS.contains(&kind)) {
+                layout_infos.push(info);
+            }
+        }
+
+        let mut files = vec![];
+        for path in &self.args.path {
+            files.push(self.bundle_file(server, &mut sources, path).await);
+        }
+
+        let tables = if layout_infos.is_empty() {
+            None
+        } else {
+            let format_symbols = FormatSymbolsCommand {
+                args: FormatSymbols {
+                    mode: SymbolFormatMode::FieldLayout,
+                    show_cols: None,
+                    hide_cols: None,
+                    provenance: false,
+                    spans: false,
+                    include_methods: false,
+                    regroup_per_class: false,
+                    offsets: OffsetFormat::Hex,
+                    sizes: SizeFormat::Bytes,
+                    max_impls: 100,
+                },
+            };
+            let input = PipelineValues::SymbolCrossrefInfoList(SymbolCrossrefInfoList {
+                symbol_crossref_infos: layout_infos,
+                unknown_symbols: vec![],
+                page: None,
+            });
+            match format_symbols.execute(server, input).await? {
+                PipelineValues::SymbolTreeTableList(tables) => Some(tables),
+                _ => None,
+            }
+        };
+
+        let tree_info = server.tree_info()?;
+        let bundle = json!({
+            "tree": tree_info.name,
+            "revision": tree_info.revision,
+            "symbols": symbols,
+            "tables": tables.as_ref().map(serde_json::to_value).transpose()?,
+            "files": files,
+        });
+
+        Ok(match self.args.format {
+            BundleFormat::Json => PipelineValues::JsonValue(JsonValue { value: bundle }),
+            BundleFormat::Html => PipelineValues::TextFile(TextFile {
+                mime_type: "text/html".to_string(),
+                contents: render_bundle_html(&bundle, tables.as_ref()),
+            }),
+        })
+    }
+}
@@ -0,0 +1,247 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+
+use async_trait::async_trait;
+use clap::Args;
+use protobuf::{Message, MessageField};
+use scip::types::{
+    Document, Index, Metadata, Occurrence, Relationship, SymbolInformation, SymbolRole, ToolInfo,
+};
+use serde_json::{json, Value};
+use tokio_stream::StreamExt;
+
+use super::{
+    interface::{JsonValue, PipelineCommand, PipelineValues},
+    path_filter::PathFilterArgs,
+    transforms::path_glob_transform,
+};
+use crate::{
+    abstract_server::{AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError},
+    file_format::{analysis::parse_location, lsif::escape_descriptor_name},
+    languages::select_formatting,
+};
+
+/// Export the searchfox analysis for a subset of the tree as a SCIP index so
+/// that editors and third-party code intelligence tools can consume it.  Every
+/// target record becomes an occurrence whose SCIP symbol wraps the searchfox
+/// symbol (`searchfox mozsearch <tree> . <symbol>.`), so the symbols are
+/// stable across exports and can be mapped back to searchfox.  Symbols
+/// defined in the exported files get their pretty name and docs from the
+/// structured records, along with implementation relationships for their
+/// supers and overridden methods.
+///
+/// Operates on the FileMatches from `search-files` if piped any, otherwise on
+/// all of the files matching `path`.  Produces a JsonValue summarizing the
+/// export.
+#[derive(Debug, Args)]
+pub struct ExportScip {
+    /// Path glob of the files to export when not given FileMatches.
+    #[clap(value_parser)]
+    path: Option<String>,
+
+    /// Where to write the SCIP index.
+    #[clap(long, value_parser)]
+    output: String,
+
+    /// The maximum number of files to export.
+    #[clap(long, value_parser, default_value = "10000")]
+    limit: usize,
+
+    #[clap(flatten)]
+    path_filter: PathFilterArgs,
+}
+
+#[derive(Debug)]
+pub struct ExportScipCommand {
+    pub args: ExportScip,
+}
+
+/// Map a searchfox symbol onto a global SCIP symbol in the given tree.
+fn scip_symbol_for(tree: &str, symbol: &str) -> String {
+    format!(
+        "searchfox mozsearch {} . {}.",
+        tree,
+        escape_descriptor_name(symbol)
+    )
+}
+
+fn symbol_roles(kind: &str) -> i32 {
+    match kind {
+        "def" => SymbolRole::Definition as i32,
+        "assign" => SymbolRole::WriteAccess as i32,
+        _ => 0,
+    }
+}
+
+/// Build the SymbolInformation for a symbol defined in an exported file from
+/// its structured record.
+fn symbol_information(tree: &str, symbol: &str, meta: &Value) -> SymbolInformation {
+    let mut documentation = vec![];
+    if let Some(pretty) = meta["pretty"].as_str() {
+        documentation.push(format!("```\n{}\n```", pretty));
+    }
+    if let Some(doc) = meta["doc"].as_str() {
+        documentation.push(doc.to_string());
+    }
+
+    let mut relationships = vec![];
+    for key in ["supers", "overrides"] {
+        let Some(targets) = meta[key].as_array() else {
+            continue;
+        };
+        for target in targets {
+            if let Some(sym) = target["sym"].as_str() {
+                relationships.push(Relationship {
+                    symbol: scip_symbol_for(tree, sym),
+                    is_implementation: true,
+                    ..Default::default()
+                });
+            }
+        }
+    }
+
+    SymbolInformation {
+        symbol: scip_symbol_for(tree, symbol),
+        documentation,
+        relationships,
+        ..Default::default()
+    }
+}
+
+#[async_trait]
+impl PipelineCommand for ExportScipCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let mut matches = match input {
+            PipelineValues::FileMatches(fm) => fm,
+            PipelineValues::Void => {
+                let pathre = match &self.args.path {
+                    Some(path) => path_glob_transform(path),
+                    None => "".to_string(),
+                };
+                server.search_files(&pathre, false, self.args.limit).await?
+            }
+            _ => {
+                return Err(ServerError::StickyProblem(
+                    ErrorDetails::new(
+                        ErrorKind::BadQuery,
+                        ErrorLayer::ConfigLayer,
+                        "export-scip needs FileMatches or no input".to_string(),
+                    )
+                    .with_hint(
+                        "Use `explain` to see what each pipeline stage consumes and produces.",
+                    ),
+                ));
+            }
+        };
+
+        let tree_info = server.tree_info()?;
+        let path_filter = self.args.path_filter.compile(&tree_info.path_sets)?;
+        if !path_filter.is_empty() {
+            matches
+                .file_matches
+                .retain(|fm| path_filter.matches_classified(&fm.path, fm.concise.classification));
+        }
+
+        let mut documents = vec![];
+        let mut languages = BTreeSet::new();
+        let (mut occurrence_count, mut symbol_count) = (0, 0);
+        for fm in matches.file_matches.iter().take(self.args.limit) {
+            if fm.concise.is_dir {
+                continue;
+            }
+            // Not every file has analysis data, which is fine.
+            let Ok(mut records) = server.fetch_raw_analysis(&fm.path).await else {
+                continue;
+            };
+
+            let mut occurrences = vec![];
+            let mut defined = BTreeSet::new();
+            while let Some(val) = records.next().await {
+                if !val["target"].is_number() {
+                    continue;
+                }
+                let (Some(loc), Some(syms), Some(kind)) = (
+                    val["loc"].as_str(),
+                    val["sym"].as_str(),
+                    val["kind"].as_str(),
+                ) else {
+                    continue;
+                };
+                let loc = parse_location(loc);
+                // Target records for things like macros can have multiple
+                // comma-separated symbols.
+                for sym in syms.split(',') {
+                    if kind == "def" {
+                        defined.insert(sym.to_string());
+                    }
+                    occurrences.push(Occurrence {
+                        range: vec![
+                            loc.lineno as i32 - 1,
+                            loc.col_start as i32,
+                            loc.col_end as i32,
+                        ],
+                        symbol: scip_symbol_for(&tree_info.name, sym),
+                        symbol_roles: symbol_roles(kind),
+                        ..Default::default()
+                    });
+                }
+            }
+            if occurrences.is_empty() {
+                continue;
+            }
+
+            let mut symbols = BTreeMap::new();
+            for sym in defined {
+                let info = server.crossref_lookup_keys(&sym, &["meta"]).await?;
+                let information = symbol_information(&tree_info.name, &sym, &info["meta"]);
+                symbols.insert(sym, information);
+            }
+
+            let language = select_formatting(&fm.path).language_name();
+            languages.insert(language);
+            occurrence_count += occurrences.len();
+            symbol_count += symbols.len();
+            documents.push(Document {
+                language: language.to_string(),
+                relative_path: fm.path.clone(),
+                occurrences,
+                symbols: symbols.into_values().collect(),
+                ..Default::default()
+            });
+        }
+
+        let index = Index {
+            metadata: MessageField::some(Metadata {
+                tool_info: MessageField::some(ToolInfo {
+                    name: "searchfox".to_string(),
+                    ..Default::default()
+                }),
+                project_root: format!("searchfox://{}/", tree_info.name),
+                ..Default::default()
+            }),
+            documents,
+            ..Default::default()
+        };
+        let bytes = index.write_to_bytes().map_err(|e| {
+            ServerError::StickyProblem(ErrorDetails::new(
+                ErrorKind::Internal,
+                ErrorLayer::ServerLayer,
+                format!("Unable to encode SCIP index: {}", e),
+            ))
+        })?;
+        fs::write(&self.args.output, bytes)?;
+
+        Ok(PipelineValues::JsonValue(JsonValue {
+            value: json!({
+                "documents": index.documents.len(),
+                "occurrences": occurrence_count,
+                "symbols": symbol_count,
+                "languages": languages,
+            }),
+        }))
+    }
+}
@@ -0,0 +1,328 @@
+use async_trait::async_trait;
+use clap::Args;
+use ustr::ustr;
+
+use super::{
+    data_table::{DataCell, DataColumnType, DataTable},
+    interface::{PipelineCommand, PipelineValues},
+    record_layouts::{
+        field_name, layout_for_platform, load_record, lookup_record_syms, record_platforms,
+    },
+};
+use crate::abstract_server::{
+    AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError,
+};
+
+/// Map byte offsets within an instance of a class, ex: from a crash dump, to
+/// the fields containing them on each platform.  Inherited fields are laid out
+/// through the superclass offsets, and fields whose types are records embedded
+/// in the class are descended into so that an offset maps to, ex:
+/// `mRect.width` rather than just `mRect`.
+///
+/// Produces a DataTable with a row for each offset and group of platforms that
+/// agree about it, where `status` is one of:
+/// - "field": the offset is within the named field (or fields, for bitfields
+///   sharing a byte).
+/// - "hole": the offset is in padding between fields; `field` names the
+///   embedded field whose padding it is, if any.
+/// - "padding": the offset is in the tail padding after the last field.
+/// - "out-of-bounds": the offset is past the end of the class.
+#[derive(Debug, Args)]
+pub struct FieldAt {
+    /// The class, as a pretty name (ex: "mozilla::dom::Element") or symbol.
+    #[clap(value_parser)]
+    class: String,
+
+    /// Byte offsets within the class, in decimal or as 0x-prefixed hex.
+    #[clap(value_parser, required = true)]
+    offsets: Vec<String>,
+
+    /// How many levels of embedded records to descend into.
+    #[clap(long, value_parser, default_value = "4")]
+    depth: usize,
+
+    /// Report each platform separately instead of grouping platforms that
+    /// agree.
+    #[clap(long, value_parser)]
+    ungrouped: bool,
+}
+
+#[derive(Debug)]
+pub struct FieldAtCommand {
+    pub args: FieldAt,
+}
+
+/// A field laid out relative to the start of the outermost class.
+struct PlacedField {
+    /// Dotted path of field names from the outermost class.
+    path: String,
+    depth: usize,
+    start: u32,
+    /// Exclusive end byte; for bitfields, of the bytes the bits touch.
+    end: u32,
+    /// (bit offset, bit width) for bitfields.
+    bits: Option<(u32, u32)>,
+    /// Whether the field's own fields were laid out too.
+    expanded: bool,
+}
+
+/// What's at an offset.
+#[derive(Clone, Debug, PartialEq)]
+struct OffsetMapping {
+    status: &'static str,
+    field: Option<String>,
+    field_start: Option<u32>,
+    field_size: Option<u32>,
+    bits: Option<(u32, u32)>,
+}
+
+fn parse_offset(offset: &str) -> Result<u32> {
+    let parsed = match offset
+        .strip_prefix("0x")
+        .or_else(|| offset.strip_prefix("0X"))
+    {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => offset.parse(),
+    };
+    parsed.map_err(|_| {
+        ServerError::StickyProblem(ErrorDetails::new(
+            ErrorKind::BadQuery,
+            ErrorLayer::BadInput,
+            format!("Bad offset {}; use decimal or 0x-prefixed hex", offset),
+        ))
+    })
+}
+
+fn is_indirect_type(type_pretty: &str) -> bool {
+    let type_pretty = type_pretty.trim_end();
+    type_pretty.ends_with('*') || type_pretty.ends_with('&')
+}
+
+impl FieldAtCommand {
+    /// Lay out all of the fields of the class on the platform, returning the
+    /// class size too.
+    async fn place_fields(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        class_sym: &str,
+        platform: Option<&str>,
+    ) -> Result<(Option<u32>, Vec<PlacedField>)> {
+        let mut class_size = None;
+        let mut placed: Vec<PlacedField> = vec![];
+        // (symbol, base offset, path prefix, embedding field index, depth)
+        let mut pending: Vec<(String, u32, String, Option<usize>, usize)> =
+            vec![(class_sym.to_string(), 0, String::new(), None, 0)];
+        while let Some((sym, base, prefix, embedding, depth)) = pending.pop() {
+            let Some(record) = load_record(server, &sym).await? else {
+                continue;
+            };
+            let layout = layout_for_platform(&record, platform);
+            match embedding {
+                Some(index) => {
+                    // Only descend into records actually embedded in the field
+                    // rather than, ex: pointed to by a smart pointer.
+                    let field = &mut placed[index];
+                    if layout.size_bytes != Some(field.end - field.start) {
+                        continue;
+                    }
+                    field.expanded = true;
+                }
+                None if depth == 0 && class_size.is_none() => {
+                    class_size = layout.size_bytes;
+                }
+                None => {}
+            }
+
+            if let Some(vf_ptr_bytes) = layout.own_vf_ptr_bytes.filter(|bytes| *bytes > 0) {
+                placed.push(PlacedField {
+                    path: format!("{}(vtable pointer)", prefix),
+                    depth,
+                    start: base,
+                    end: base + vf_ptr_bytes,
+                    bits: None,
+                    expanded: false,
+                });
+            }
+            for (sup, offset) in layout.supers {
+                pending.push((sup, base + offset, prefix.clone(), None, depth));
+            }
+            for field in &layout.fields {
+                let start = base + field.offset_bytes;
+                let (end, bits) = match &field.bit_positions {
+                    Some(positions) => {
+                        let bit_offset = start * 8 + positions.begin;
+                        let bit_end = bit_offset + positions.width;
+                        ((bit_end + 7) / 8, Some((bit_offset, positions.width)))
+                    }
+                    None => (start + field.size_bytes.unwrap_or(0), None),
+                };
+                placed.push(PlacedField {
+                    path: format!("{}{}", prefix, field_name(field)),
+                    depth,
+                    start: bits.map_or(start, |(bit_offset, _)| bit_offset / 8),
+                    end,
+                    bits,
+                    expanded: false,
+                });
+                if depth < self.args.depth
+                    && bits.is_none()
+                    && !field.type_sym.is_empty()
+                    && !is_indirect_type(&field.type_pretty)
+                {
+                    pending.push((
+                        field.type_sym.to_string(),
+                        start,
+                        format!("{}.", placed[placed.len() - 1].path),
+                        Some(placed.len() - 1),
+                        depth + 1,
+                    ));
+                }
+            }
+        }
+        Ok((class_size, placed))
+    }
+}
+
+fn map_offset(offset: u32, class_size: Option<u32>, placed: &[PlacedField]) -> OffsetMapping {
+    let containing: Vec<&PlacedField> = placed
+        .iter()
+        .filter(|field| field.start <= offset && offset < field.end)
+        .collect();
+    let deepest = containing.iter().map(|field| field.depth).max();
+    let innermost: Vec<&PlacedField> = containing
+        .into_iter()
+        .filter(|field| Some(field.depth) == deepest)
+        .collect();
+
+    if let Some(first) = innermost.first() {
+        let status = if innermost.len() == 1 && first.expanded {
+            "hole"
+        } else {
+            "field"
+        };
+        return OffsetMapping {
+            status,
+            field: Some(
+                innermost
+                    .iter()
+                    .map(|field| field.path.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+            field_start: Some(first.start),
+            field_size: if first.bits.is_some() {
+                None
+            } else {
+                Some(first.end - first.start)
+            },
+            bits: if innermost.len() == 1 {
+                first.bits
+            } else {
+                None
+            },
+        };
+    }
+
+    let last_end = placed
+        .iter()
+        .filter(|field| field.depth == 0)
+        .map(|field| field.end)
+        .max()
+        .unwrap_or(0);
+    let status = match class_size {
+        Some(size) if offset >= size => "out-of-bounds",
+        _ if offset >= last_end => "padding",
+        _ => "hole",
+    };
+    OffsetMapping {
+        status,
+        field: None,
+        field_start: None,
+        field_size: None,
+        bits: None,
+    }
+}
+
+#[async_trait]
+impl PipelineCommand for FieldAtCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        _input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let offsets = self
+            .args
+            .offsets
+            .iter()
+            .map(|offset| parse_offset(offset))
+            .collect::<Result<Vec<u32>>>()?;
+
+        let class_syms = lookup_record_syms(server, &self.args.class).await?;
+        if class_syms.is_empty() {
+            return Err(ServerError::StickyProblem(ErrorDetails::new(
+                ErrorKind::NotFound,
+                ErrorLayer::DataLayer,
+                format!("No layout data found for class {}", self.args.class),
+            )));
+        }
+
+        let mut table = DataTable::new(vec![
+            ("symbol", DataColumnType::SymbolRef),
+            ("offset", DataColumnType::Int),
+            ("platforms", DataColumnType::String),
+            ("status", DataColumnType::String),
+            ("field", DataColumnType::String),
+            ("field_offset", DataColumnType::Int),
+            ("field_size", DataColumnType::Int),
+            ("offset_in_field", DataColumnType::Int),
+            ("bit_offset", DataColumnType::Int),
+            ("bit_width", DataColumnType::Int),
+        ]);
+        for sym in class_syms {
+            let Some(record) = load_record(server, &sym).await? else {
+                continue;
+            };
+            let mut layouts = vec![];
+            for platform in record_platforms(&record) {
+                let (class_size, placed) =
+                    self.place_fields(server, &sym, platform.as_deref()).await?;
+                layouts.push((
+                    platform.unwrap_or_else(|| "*".to_string()),
+                    class_size,
+                    placed,
+                ));
+            }
+
+            for offset in &offsets {
+                // Group the platforms by their mapping, keeping platform order.
+                let mut groups: Vec<(OffsetMapping, Vec<&str>)> = vec![];
+                for (platform, class_size, placed) in &layouts {
+                    let mapping = map_offset(*offset, *class_size, placed);
+                    match groups.iter_mut().find(|(m, _)| *m == mapping) {
+                        Some((_, platforms)) if !self.args.ungrouped => platforms.push(platform),
+                        _ => groups.push((mapping, vec![platform])),
+                    }
+                }
+                for (mapping, platforms) in groups {
+                    let int_cell = |value: Option<u32>| {
+                        value.map_or(DataCell::Null, |v| DataCell::Int(v as i64))
+                    };
+                    table.push_row(vec![
+                        DataCell::Symbol(ustr(&sym)),
+                        DataCell::Int(*offset as i64),
+                        DataCell::String(platforms.join(" ")),
+                        DataCell::String(mapping.status.to_string()),
+                        mapping.field.map_or(DataCell::Null, DataCell::String),
+                        int_cell(mapping.field_start),
+                        int_cell(mapping.field_size),
+                        int_cell(mapping.field_start.map(|start| offset - start)),
+                        int_cell(mapping.bits.map(|(bit_offset, _)| bit_offset)),
+                        int_cell(mapping.bits.map(|(_, bit_width)| bit_width)),
+                    ])?;
+                }
+            }
+        }
+
+        Ok(PipelineValues::DataTable(table))
+    }
+}
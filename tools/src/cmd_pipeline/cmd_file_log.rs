@@ -0,0 +1,84 @@
+use async_trait::async_trait;
+use clap::Args;
+
+use super::{
+    data_table::{DataCell, DataColumnType, DataTable},
+    interface::{PipelineCommand, PipelineValues},
+};
+use crate::abstract_server::{
+    AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError,
+};
+
+/// List the revisions that changed a file, newest first, following the file
+/// through renames, for history panels and bots.  Produces a DataTable with
+/// each revision's commit time (in seconds since the epoch), author, and
+/// commit message summary, along with the file's path as of the revision.
+/// This uses the blame repo, so it's only supported for local indices with
+/// blame.
+#[derive(Debug, Args)]
+pub struct FileLog {
+    /// Tree-relative source file path.
+    #[clap(value_parser)]
+    path: String,
+
+    #[clap(short, long, value_parser, default_value = "100")]
+    limit: usize,
+}
+
+#[derive(Debug)]
+pub struct FileLogCommand {
+    pub args: FileLog,
+}
+
+#[async_trait]
+impl PipelineCommand for FileLogCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        if !matches!(input, PipelineValues::Void) {
+            return Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::BadQuery,
+                    ErrorLayer::ConfigLayer,
+                    "file-log needs no input".to_string(),
+                )
+                .with_hint("Use `explain` to see what each pipeline stage consumes and produces."),
+            ));
+        }
+        if !server.capabilities().blame {
+            return Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::IndexMissingCapability,
+                    ErrorLayer::ConfigLayer,
+                    "file-log requires a local index with blame".to_string(),
+                )
+                .with_hint("Configure the tree's `git_blame_path` and re-index."),
+            ));
+        }
+
+        let entries = server
+            .fetch_file_log(&self.args.path, self.args.limit)
+            .await?;
+
+        let mut table = DataTable::new(vec![
+            ("rev", DataColumnType::String),
+            ("time", DataColumnType::Int),
+            ("author", DataColumnType::String),
+            ("summary", DataColumnType::String),
+            ("path", DataColumnType::Path),
+        ]);
+        for entry in entries {
+            table.push_row(vec![
+                DataCell::String(entry.rev),
+                DataCell::Int(entry.time),
+                DataCell::String(entry.author),
+                DataCell::String(entry.summary),
+                DataCell::String(entry.path),
+            ])?;
+        }
+
+        Ok(PipelineValues::DataTable(table))
+    }
+}
@@ -0,0 +1,256 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use async_trait::async_trait;
+use clap::Args;
+use serde_json::from_value;
+use tokio_stream::StreamExt;
+use ustr::ustr;
+
+use super::{
+    data_table::{DataCell, DataColumnType, DataTable},
+    interface::{PipelineCommand, PipelineValues},
+    transforms::path_glob_transform,
+};
+use crate::{
+    abstract_server::{AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError},
+    code_similarity::{normalized_tokens, similarity, winnow},
+    file_format::analysis::AnalysisSource,
+    languages::select_formatting,
+};
+
+/// Find clusters of near-identical functions, ex: copy-pasted helpers that
+/// should be shared.  Each function definition's source (per its nesting
+/// range) is tokenized like the formatter does, with identifiers and literals
+/// normalized away, and fingerprinted by winnowing k-grams of tokens.
+/// Functions whose fingerprint sets have a Jaccard similarity of at least
+/// `--threshold` are clustered together.
+///
+/// Operates on the FileMatches from `search-files` if piped any, otherwise on
+/// all of the files matching `path`.  Produces a DataTable with a row per
+/// function in each cluster, largest clusters first, where `similarity` is the
+/// function's highest similarity to another member of its cluster.
+#[derive(Debug, Args)]
+pub struct FindDuplicates {
+    /// Path glob of the files to check when not given FileMatches.
+    #[clap(value_parser)]
+    path: Option<String>,
+
+    /// The minimum similarity, from 0 to 1, for functions to be clustered.
+    #[clap(long, value_parser, default_value = "0.8")]
+    threshold: f64,
+
+    /// Ignore functions with fewer normalized tokens than this, since short
+    /// functions are often legitimately identical.
+    #[clap(long, value_parser, default_value = "50")]
+    min_tokens: usize,
+
+    /// The number of tokens in each fingerprinted k-gram.
+    #[clap(long, value_parser, default_value = "5")]
+    kgram: usize,
+
+    /// The winnowing window size.
+    #[clap(long, value_parser, default_value = "4")]
+    window: usize,
+
+    /// The maximum number of files to check.
+    #[clap(long, value_parser, default_value = "5000")]
+    limit: usize,
+}
+
+#[derive(Debug)]
+pub struct FindDuplicatesCommand {
+    pub args: FindDuplicates,
+}
+
+struct FunctionPrint {
+    symbol: String,
+    pretty: String,
+    path: String,
+    start_lineno: u32,
+    end_lineno: u32,
+    tokens: usize,
+    fingerprints: BTreeSet<u64>,
+}
+
+/// Fingerprints shared by more functions than this are boilerplate (ex: the
+/// k-grams of an empty loop) that would make candidate generation quadratic
+/// without helping find duplicates, so we don't use them to find candidates.
+const MAX_FINGERPRINT_FUNCTIONS: usize = 64;
+
+fn find_root(parents: &mut [usize], mut i: usize) -> usize {
+    while parents[i] != i {
+        parents[i] = parents[parents[i]];
+        i = parents[i];
+    }
+    i
+}
+
+#[async_trait]
+impl PipelineCommand for FindDuplicatesCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let matches = match input {
+            PipelineValues::FileMatches(fm) => fm,
+            PipelineValues::Void => {
+                let pathre = match &self.args.path {
+                    Some(path) => path_glob_transform(path),
+                    None => "".to_string(),
+                };
+                server.search_files(&pathre, false, self.args.limit).await?
+            }
+            _ => {
+                return Err(ServerError::StickyProblem(
+                    ErrorDetails::new(
+                        ErrorKind::BadQuery,
+                        ErrorLayer::ConfigLayer,
+                        "find-duplicates needs FileMatches or no input".to_string(),
+                    )
+                    .with_hint(
+                        "Use `explain` to see what each pipeline stage consumes and produces.",
+                    ),
+                ));
+            }
+        };
+
+        let mut functions = vec![];
+        for fm in matches.file_matches.iter().take(self.args.limit) {
+            if fm.concise.is_dir {
+                continue;
+            }
+            // Not every file has analysis data, which is fine.
+            let Ok(mut records) = server.fetch_raw_analysis(&fm.path).await else {
+                continue;
+            };
+            let mut ranges = BTreeMap::new();
+            while let Some(val) = records.next().await {
+                if !val["source"].is_number() {
+                    continue;
+                }
+                let Ok(source) = from_value::<AnalysisSource>(val) else {
+                    continue;
+                };
+                let range = &source.nesting_range;
+                if range.start_lineno == 0
+                    || !source.syntax.iter().any(|s| s.as_str() == "def")
+                    || !matches!(source.get_syntax_kind(), Some("function" | "method"))
+                {
+                    continue;
+                }
+                ranges.insert(
+                    (range.start_lineno, range.end_lineno),
+                    (source.get_joined_syms(), source.pretty.to_string()),
+                );
+            }
+            if ranges.is_empty() {
+                continue;
+            }
+
+            let Ok(contents) = server.fetch_raw_source(&fm.path).await else {
+                continue;
+            };
+            let lines: Vec<&str> = contents.lines().collect();
+            let format = select_formatting(&fm.path);
+            for ((start_lineno, end_lineno), (symbol, pretty)) in ranges {
+                let start = start_lineno as usize - 1;
+                let end = (end_lineno as usize).min(lines.len());
+                if start >= end {
+                    continue;
+                }
+                let tokens = normalized_tokens(&format, &lines[start..end].join("\n"));
+                if tokens.len() < self.args.min_tokens {
+                    continue;
+                }
+                functions.push(FunctionPrint {
+                    symbol,
+                    pretty: pretty
+                        .split_once(' ')
+                        .map_or(pretty.as_str(), |(_, name)| name)
+                        .to_string(),
+                    path: fm.path.to_string(),
+                    start_lineno,
+                    end_lineno,
+                    tokens: tokens.len(),
+                    fingerprints: winnow(&tokens, self.args.kgram, self.args.window),
+                });
+            }
+        }
+
+        // Find candidate pairs by their shared fingerprints, and cluster the
+        // ones that are similar enough.
+        let mut by_fingerprint: HashMap<u64, Vec<usize>> = HashMap::new();
+        for (i, function) in functions.iter().enumerate() {
+            for fingerprint in &function.fingerprints {
+                by_fingerprint.entry(*fingerprint).or_default().push(i);
+            }
+        }
+        let mut parents: Vec<usize> = (0..functions.len()).collect();
+        let mut best = vec![0.0; functions.len()];
+        for (i, function) in functions.iter().enumerate() {
+            let candidates: BTreeSet<usize> = function
+                .fingerprints
+                .iter()
+                .filter_map(|fingerprint| by_fingerprint.get(fingerprint))
+                .filter(|others| others.len() <= MAX_FINGERPRINT_FUNCTIONS)
+                .flatten()
+                .copied()
+                .filter(|j| *j > i)
+                .collect();
+            for j in candidates {
+                let score = similarity(&function.fingerprints, &functions[j].fingerprints);
+                if score < self.args.threshold {
+                    continue;
+                }
+                for k in [i, j] {
+                    if score > best[k] {
+                        best[k] = score;
+                    }
+                }
+                let (root_i, root_j) = (find_root(&mut parents, i), find_root(&mut parents, j));
+                parents[root_j] = root_i;
+            }
+        }
+
+        let mut clusters: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        for i in 0..functions.len() {
+            let root = find_root(&mut parents, i);
+            clusters.entry(root).or_default().push(i);
+        }
+        let mut clusters: Vec<Vec<usize>> = clusters
+            .into_values()
+            .filter(|members| members.len() > 1)
+            .collect();
+        // Largest clusters first, keeping the file order otherwise.
+        clusters.sort_by_key(|members| std::cmp::Reverse(members.len()));
+
+        let mut table = DataTable::new(vec![
+            ("cluster", DataColumnType::Int),
+            ("symbol", DataColumnType::SymbolRef),
+            ("pretty", DataColumnType::String),
+            ("path", DataColumnType::Path),
+            ("start_line", DataColumnType::Int),
+            ("end_line", DataColumnType::Int),
+            ("tokens", DataColumnType::Int),
+            ("similarity", DataColumnType::Float),
+        ]);
+        for (cluster, members) in clusters.into_iter().enumerate() {
+            for i in members {
+                let function = &functions[i];
+                table.push_row(vec![
+                    DataCell::Int(cluster as i64 + 1),
+                    DataCell::Symbol(ustr(&function.symbol)),
+                    DataCell::String(function.pretty.clone()),
+                    DataCell::String(function.path.clone()),
+                    DataCell::Int(function.start_lineno as i64),
+                    DataCell::Int(function.end_lineno as i64),
+                    DataCell::Int(function.tokens as i64),
+                    DataCell::Float(best[i]),
+                ])?;
+            }
+        }
+
+        Ok(PipelineValues::DataTable(table))
+    }
+}
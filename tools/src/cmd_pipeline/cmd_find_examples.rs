@@ -0,0 +1,223 @@
+use std::collections::BTreeSet;
+
+use async_trait::async_trait;
+use clap::Args;
+use serde_json::from_value;
+use ustr::{ustr, Ustr};
+
+use super::{
+    interface::{
+        ExampleLine, PipelineCommand, PipelineValues, SymbolCrossrefInfo, SymbolExample,
+        SymbolExamples, SymbolExamplesList,
+    },
+    path_filter::{PathFilter, PathFilterArgs},
+};
+
+use crate::{
+    abstract_server::{AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError},
+    file_format::analysis::{FileClassification, PathSearchResult},
+};
+
+/// Select a small number of representative call sites for each received
+/// symbol to power an "example usages" panel.
+///
+/// Uses are preferred from normal (non-test, non-generated, non-third-party)
+/// files, with short lines, and spread across as many different directories
+/// and then files as possible.  Each example is returned with surrounding
+/// source lines for context.
+#[derive(Debug, Args)]
+pub struct FindExamples {
+    /// Maximum number of examples to return per symbol.
+    #[clap(long, value_parser, default_value = "3")]
+    limit: usize,
+
+    /// Number of lines of context to include before and after each use.
+    #[clap(long, value_parser, default_value = "2")]
+    context: u32,
+
+    /// Uses in files that fail this filter are never picked as examples.
+    #[clap(flatten)]
+    path_filter: PathFilterArgs,
+}
+
+#[derive(Debug)]
+pub struct FindExamplesCommand {
+    pub args: FindExamples,
+}
+
+struct Candidate {
+    path: Ustr,
+    dir: Ustr,
+    classification: FileClassification,
+    lineno: u32,
+    context: Ustr,
+    line_len: usize,
+}
+
+impl Candidate {
+    /// Lower is better.  Test code is frequently not representative of how an
+    /// API is used in practice, and generated and third-party code even less so.
+    fn classification_rank(&self) -> u32 {
+        match self.classification {
+            FileClassification::Normal => 0,
+            FileClassification::Test => 1,
+            FileClassification::ThirdParty => 2,
+            FileClassification::Generated => 3,
+        }
+    }
+}
+
+fn containing_dir(path: &str) -> Ustr {
+    match path.rfind('/') {
+        Some(idx) => ustr(&path[..idx]),
+        None => ustr(""),
+    }
+}
+
+/// Pick up to `limit` candidates, first taking the best candidate from each
+/// not-yet-seen directory, then the best from each not-yet-seen file, then
+/// whatever is left.  `candidates` must already be sorted best-first.
+fn pick_diverse(candidates: &[Candidate], limit: usize) -> Vec<usize> {
+    let mut picked: Vec<usize> = vec![];
+    let mut seen_dirs = BTreeSet::new();
+    let mut seen_files = BTreeSet::new();
+
+    for pass in 0..3 {
+        for (idx, cand) in candidates.iter().enumerate() {
+            if picked.len() >= limit {
+                return picked;
+            }
+            if picked.contains(&idx) {
+                continue;
+            }
+            let eligible = match pass {
+                0 => !seen_dirs.contains(&cand.dir),
+                1 => !seen_files.contains(&cand.path),
+                _ => true,
+            };
+            if eligible {
+                seen_dirs.insert(cand.dir);
+                seen_files.insert(cand.path);
+                picked.push(idx);
+            }
+        }
+    }
+    picked
+}
+
+impl FindExamplesCommand {
+    async fn examples_for_symbol(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        info: SymbolCrossrefInfo,
+        path_filter: &PathFilter,
+    ) -> Result<SymbolExamples> {
+        let pretty = info.get_pretty();
+        let mut candidates = vec![];
+        if let Some(uses) = info.crossref_info.get("uses") {
+            let path_containers: Vec<PathSearchResult> = from_value(uses.clone())?;
+            for path_container in path_containers {
+                if !path_filter
+                    .matches_classified(&path_container.path, path_container.classification)
+                {
+                    continue;
+                }
+                let dir = containing_dir(&path_container.path);
+                for result in path_container.lines {
+                    candidates.push(Candidate {
+                        path: path_container.path,
+                        dir,
+                        classification: path_container.classification,
+                        lineno: result.lineno,
+                        context: result.context,
+                        line_len: result.line.len(),
+                    });
+                }
+            }
+        }
+
+        candidates.sort_by(|a, b| {
+            a.classification_rank()
+                .cmp(&b.classification_rank())
+                .then_with(|| a.line_len.cmp(&b.line_len))
+                .then_with(|| a.path.cmp(&b.path))
+                .then_with(|| a.lineno.cmp(&b.lineno))
+        });
+
+        let mut examples = vec![];
+        for idx in pick_diverse(&candidates, self.args.limit) {
+            let cand = &candidates[idx];
+            // If we can't get the source we still report the example, just
+            // without any lines.
+            let source = server
+                .fetch_raw_source(&cand.path)
+                .await
+                .unwrap_or_default();
+            let first = cand.lineno.saturating_sub(self.args.context).max(1);
+            let last = cand.lineno + self.args.context;
+            let lines = source
+                .lines()
+                .enumerate()
+                .map(|(i, line)| (i as u32 + 1, line))
+                .filter(|(lineno, _)| *lineno >= first && *lineno <= last)
+                .map(|(lineno, line)| ExampleLine {
+                    lineno,
+                    line: line.to_string(),
+                })
+                .collect();
+            examples.push(SymbolExample {
+                path: cand.path,
+                classification: cand.classification,
+                lineno: cand.lineno,
+                context: cand.context,
+                lines,
+            });
+        }
+
+        Ok(SymbolExamples {
+            symbol: info.symbol,
+            pretty,
+            candidate_count: candidates.len() as u32,
+            examples,
+        })
+    }
+}
+
+#[async_trait]
+impl PipelineCommand for FindExamplesCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let scil = match input {
+            PipelineValues::SymbolCrossrefInfoList(scil) => scil,
+            _ => {
+                return Err(ServerError::StickyProblem(
+                    ErrorDetails::new(
+                        ErrorKind::BadQuery,
+                        ErrorLayer::ConfigLayer,
+                        "find-examples needs a SymbolCrossrefInfoList".to_string(),
+                    )
+                    .with_hint(
+                        "Use `explain` to see what each pipeline stage consumes and produces.",
+                    ),
+                ));
+            }
+        };
+
+        let path_filter = self
+            .args
+            .path_filter
+            .compile(&server.tree_info()?.path_sets)?;
+
+        let mut symbols = vec![];
+        for info in scil.symbol_crossref_infos {
+            symbols.push(self.examples_for_symbol(server, info, &path_filter).await?);
+        }
+
+        Ok(PipelineValues::SymbolExamplesList(SymbolExamplesList {
+            symbols,
+        }))
+    }
+}
@@ -0,0 +1,118 @@
+use async_trait::async_trait;
+use clap::Args;
+use regex::Regex;
+use serde_json::Value;
+use ustr::ustr;
+
+use super::{
+    interface::{
+        PipelineCommand, PipelineValues, SymbolCrossrefInfo, SymbolCrossrefInfoList,
+        SymbolMetaFlags, SymbolQuality, SymbolRelation,
+    },
+    transforms::{normalize_type_whitespace, type_pattern_to_regex},
+};
+
+use crate::{
+    abstract_server::{AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError},
+    file_format::structured_columns::StructuredColumn,
+};
+
+/// Find the classes that have a field whose type matches a pattern, like
+/// "RefPtr<Document>" or "nsCOMPtr<*>", producing a SymbolCrossrefInfoList that
+/// can be piped into `format-symbols`.  This is the inverse of the
+/// field-layout view.
+///
+/// In the pattern, `*` matches anything and unqualified names also match
+/// qualified names.  This scans every structured record, so it's only available
+/// for local indices with a `structured-columns` file.
+#[derive(Debug, Args)]
+pub struct FindFieldType {
+    /// The type pattern to look for.
+    #[clap(value_parser)]
+    pattern: String,
+
+    /// Only include classes whose size in bytes is at least this large.
+    #[clap(long, value_parser)]
+    min_size: Option<u64>,
+
+    /// The maximum number of matching classes to consider, before applying
+    /// `--min-size`.
+    #[clap(long, value_parser, default_value = "1000")]
+    limit: usize,
+}
+
+#[derive(Debug)]
+pub struct FindFieldTypeCommand {
+    pub args: FindFieldType,
+}
+
+#[async_trait]
+impl PipelineCommand for FindFieldTypeCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        _input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let re = Regex::new(&type_pattern_to_regex(&self.args.pattern))?;
+        let has_matching_field = move |fields: &Value| match fields {
+            Value::Array(fields) => fields.iter().any(|field| {
+                field["type"]
+                    .as_str()
+                    .map_or(false, |ty| re.is_match(&normalize_type_whitespace(ty)))
+            }),
+            _ => false,
+        };
+
+        let class_syms = match server
+            .scan_structured_column(
+                StructuredColumn::Fields,
+                &has_matching_field,
+                self.args.limit,
+            )
+            .await
+        {
+            Err(ServerError::Unsupported) => {
+                return Err(ServerError::StickyProblem(ErrorDetails::new(
+                    ErrorKind::IndexMissingCapability,
+                    ErrorLayer::ConfigLayer,
+                    "find-field-type requires a local index with structured columns".to_string(),
+                )));
+            }
+            result => result?,
+        };
+
+        let mut symbol_crossref_infos = vec![];
+        for sym in class_syms {
+            if let Some(min_size) = self.args.min_size {
+                let size = server
+                    .structured_column_lookup(&sym, StructuredColumn::SizeBytes)
+                    .await?;
+                if size.as_u64().map_or(true, |size| size < min_size) {
+                    continue;
+                }
+            }
+
+            let crossref_info = server.crossref_lookup(&sym, false).await?;
+            if crossref_info.is_null() {
+                continue;
+            }
+            symbol_crossref_infos.push(SymbolCrossrefInfo {
+                symbol: ustr(&sym),
+                crossref_info,
+                relation: SymbolRelation::Queried,
+                quality: SymbolQuality::ExplicitSymbol,
+                overloads_hit: vec![],
+                flags: SymbolMetaFlags::default(),
+                score: None,
+            });
+        }
+
+        Ok(PipelineValues::SymbolCrossrefInfoList(
+            SymbolCrossrefInfoList {
+                symbol_crossref_infos,
+                unknown_symbols: vec![],
+                page: None,
+            },
+        ))
+    }
+}
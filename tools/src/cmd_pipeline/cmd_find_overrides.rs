@@ -0,0 +1,230 @@
+use std::collections::{HashSet, VecDeque};
+
+use async_trait::async_trait;
+use clap::{Args, ValueEnum};
+use serde_json::{from_value, json, Value};
+
+use super::interface::{JsonValue, JsonValueList, PipelineCommand, PipelineValues};
+
+use crate::{
+    abstract_server::{AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError},
+    file_format::analysis::{BindingOwnerLang, BindingSlotLang, StructuredBindingSlotInfo},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum OverrideDirection {
+    /// List every override/implementation of the method, transitively.
+    Implementations,
+    /// List the root declaration(s) the method ultimately overrides.
+    Roots,
+}
+
+/// Given virtual method symbols (or IDL method symbols, in which case we start
+/// from their C++ bindings), list all of their overrides/implementations across
+/// the tree or, with `--direction=roots`, the root declarations they override.
+/// This follows the crossref "overriddenBy" and "overrides" edges.
+///
+/// Produces a JsonValueList with one value per input symbol.
+#[derive(Debug, Args)]
+pub struct FindOverrides {
+    /// Explicit symbols to look up.
+    #[clap(value_parser)]
+    symbols: Vec<String>,
+
+    #[clap(long, value_parser, value_enum, default_value = "implementations")]
+    direction: OverrideDirection,
+
+    /// The maximum number of methods to report per input symbol.
+    #[clap(long, value_parser, default_value = "1000")]
+    limit: usize,
+}
+
+#[derive(Debug)]
+pub struct FindOverridesCommand {
+    pub args: FindOverrides,
+}
+
+fn bad_data(sym: &str, key: &str) -> ServerError {
+    ServerError::StickyProblem(ErrorDetails::new(
+        ErrorKind::Internal,
+        ErrorLayer::DataLayer,
+        format!("Bad edge info in sym {} on meta {}", sym, key),
+    ))
+}
+
+/// Describe a method for our output; the class is derived from the pretty
+/// name since that's what the user will want to see anyways.
+fn describe_method(sym: &str, crossref_info: &Value, depth: usize, via: Option<&str>) -> Value {
+    let pretty = crossref_info
+        .pointer("/meta/pretty")
+        .and_then(|p| p.as_str())
+        .unwrap_or(sym);
+    let class = pretty.rsplit_once("::").map(|(class, _)| class);
+    json!({
+        "sym": sym,
+        "pretty": pretty,
+        "class": class,
+        "classSym": crossref_info.pointer("/meta/parentsym"),
+        "path": crossref_info.pointer("/defs/0/path"),
+        "lineno": crossref_info.pointer("/defs/0/lines/0/lno"),
+        "depth": depth,
+        "via": via,
+    })
+}
+
+/// Extract the symbols from an "overrides" list of `{ sym, pretty }` objects
+/// or an "overriddenBy" list of bare symbols.
+fn edge_syms(
+    sym: &str,
+    crossref_info: &Value,
+    direction: OverrideDirection,
+) -> Result<Vec<String>> {
+    let key = match direction {
+        OverrideDirection::Implementations => "overriddenBy",
+        OverrideDirection::Roots => "overrides",
+    };
+    let Some(Value::Array(targets)) = crossref_info.get("meta").and_then(|m| m.get(key)) else {
+        return Ok(vec![]);
+    };
+    targets
+        .iter()
+        .map(|target| {
+            let target_sym = match direction {
+                OverrideDirection::Implementations => target.as_str(),
+                OverrideDirection::Roots => target["sym"].as_str(),
+            };
+            target_sym
+                .map(|s| s.to_string())
+                .ok_or_else(|| bad_data(sym, key))
+        })
+        .collect()
+}
+
+impl FindOverridesCommand {
+    /// IDL methods don't have override edges themselves, but their C++
+    /// binding slots do, so start from those.
+    fn start_syms(sym: &str, crossref_info: &Value) -> Vec<String> {
+        let Some(Value::Array(slots)) = crossref_info.pointer("/meta/bindingSlots") else {
+            return vec![sym.to_string()];
+        };
+        let cpp_slots: Vec<String> = slots
+            .iter()
+            .filter_map(|slot_val| from_value::<StructuredBindingSlotInfo>(slot_val.clone()).ok())
+            .filter(|slot| {
+                slot.props.owner_lang == BindingOwnerLang::Idl
+                    && slot.props.slot_lang == BindingSlotLang::Cpp
+            })
+            .map(|slot| slot.sym.to_string())
+            .collect();
+        if cpp_slots.is_empty() {
+            vec![sym.to_string()]
+        } else {
+            cpp_slots
+        }
+    }
+
+    async fn find_for_symbol(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        sym: &str,
+    ) -> Result<Value> {
+        let direction = self.args.direction;
+        let info = server.crossref_lookup(sym, false).await?;
+        if info.is_null() {
+            return Ok(json!({ "sym": sym, "unknown": true }));
+        }
+
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut pending: VecDeque<(String, usize, Option<String>)> = VecDeque::new();
+        for start in Self::start_syms(sym, &info) {
+            if seen.insert(start.clone()) {
+                pending.push_back((start, 0, None));
+            }
+        }
+
+        let mut results = vec![];
+        while let Some((cur_sym, depth, via)) = pending.pop_front() {
+            if results.len() >= self.args.limit {
+                break;
+            }
+            let cur_info = if cur_sym == sym {
+                info.clone()
+            } else {
+                server.crossref_lookup(&cur_sym, false).await?
+            };
+            let next_syms = edge_syms(&cur_sym, &cur_info, direction)?;
+
+            // For implementations we report everything below the starting
+            // method(s), but for roots we only want the methods at the top,
+            // which may be the starting method itself.
+            let report = match direction {
+                OverrideDirection::Implementations => depth > 0,
+                OverrideDirection::Roots => next_syms.is_empty(),
+            };
+            if report {
+                results.push(describe_method(&cur_sym, &cur_info, depth, via.as_deref()));
+            }
+
+            for next_sym in next_syms {
+                if seen.insert(next_sym.clone()) {
+                    pending.push_back((next_sym, depth + 1, Some(cur_sym.clone())));
+                }
+            }
+        }
+
+        Ok(json!({
+            "sym": sym,
+            "method": describe_method(sym, &info, 0, None),
+            "direction": match direction {
+                OverrideDirection::Implementations => "implementations",
+                OverrideDirection::Roots => "roots",
+            },
+            "results": results,
+        }))
+    }
+}
+
+#[async_trait]
+impl PipelineCommand for FindOverridesCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let symbol_list: Vec<String> = match input {
+            PipelineValues::SymbolList(sl) => sl
+                .symbols
+                .into_iter()
+                .map(|info| info.symbol.to_string())
+                .collect(),
+            PipelineValues::SymbolCrossrefInfoList(scil) => scil
+                .symbol_crossref_infos
+                .into_iter()
+                .map(|info| info.symbol.to_string())
+                .collect(),
+            PipelineValues::Void => self.args.symbols.to_vec(),
+            _ => {
+                return Err(ServerError::StickyProblem(
+                    ErrorDetails::new(
+                        ErrorKind::BadQuery,
+                        ErrorLayer::ConfigLayer,
+                        "find-overrides needs a Void, SymbolList, or SymbolCrossrefInfoList"
+                            .to_string(),
+                    )
+                    .with_hint(
+                        "Use `explain` to see what each pipeline stage consumes and produces.",
+                    ),
+                ));
+            }
+        };
+
+        let mut values = vec![];
+        for sym in symbol_list {
+            values.push(JsonValue {
+                value: self.find_for_symbol(server, &sym).await?,
+            });
+        }
+
+        Ok(PipelineValues::JsonValueList(JsonValueList { values }))
+    }
+}
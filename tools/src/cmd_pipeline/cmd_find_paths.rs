@@ -0,0 +1,209 @@
+use std::collections::HashSet;
+
+use async_trait::async_trait;
+use clap::{Args, ValueEnum};
+use serde_json::json;
+
+use super::{
+    interface::{JsonValue, JsonValueList, PipelineCommand, PipelineValues},
+    symbol_graph::{EdgeDetail, NamedSymbolGraph, SymbolGraphCollection, SymbolGraphNodeId},
+};
+use crate::abstract_server::{
+    AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum FindPathsFormat {
+    /// A JsonValueList with one value per path, annotating each node with its
+    /// role in the query and each hop with its source locations.
+    Json,
+    /// A SymbolGraphCollection containing only the paths, for `graph`.
+    Graph,
+}
+
+/// Find the paths through a SymbolGraphCollection (ex: from `traverse`) from
+/// any of the `--from` symbols to any of the `--to` symbols, subject to
+/// "must-avoid" and "must-pass-through" constraints.  This allows taint-style
+/// questions like "can the network input handlers reach this parser without
+/// going through the sandbox check?" via `--from=Handler --to=Parse
+/// --avoid=CheckSandbox`.
+///
+/// Symbols may be identified by their symbol or pretty identifier.
+#[derive(Debug, Args)]
+pub struct FindPaths {
+    /// Symbols paths may start from; may be specified multiple times.
+    #[clap(long, value_parser, required = true)]
+    from: Vec<String>,
+
+    /// Symbols paths may end at; may be specified multiple times.
+    #[clap(long, value_parser, required = true)]
+    to: Vec<String>,
+
+    /// Symbols no path may pass through; may be specified multiple times.
+    #[clap(long, value_parser)]
+    avoid: Vec<String>,
+
+    /// Symbols every path must pass through; may be specified multiple times.
+    #[clap(long, value_parser)]
+    through: Vec<String>,
+
+    /// Maximum path length in edges.
+    #[clap(long, value_parser = clap::value_parser!(u32).range(1..=16), default_value = "8")]
+    max_length: u32,
+
+    /// Maximum number of paths to report.
+    #[clap(long, value_parser = clap::value_parser!(u32).range(1..=1024), default_value = "64")]
+    max_paths: u32,
+
+    #[clap(long, value_parser, value_enum, default_value = "json")]
+    format: FindPathsFormat,
+}
+
+#[derive(Debug)]
+pub struct FindPathsCommand {
+    pub args: FindPaths,
+}
+
+impl FindPathsCommand {
+    fn resolve(
+        &self,
+        sgc: &SymbolGraphCollection,
+        names: &[String],
+        arg: &str,
+    ) -> Result<Vec<SymbolGraphNodeId>> {
+        let mut node_ids = vec![];
+        for name in names {
+            let matches = sgc.node_set.lookup_symbol_or_pretty(name);
+            if matches.is_empty() {
+                return Err(ServerError::StickyProblem(
+                    ErrorDetails::new(
+                        ErrorKind::NotFound,
+                        ErrorLayer::BadInput,
+                        format!("find-paths --{} {} is not in the graph", arg, name),
+                    )
+                    .with_context("symbol", name.to_string()),
+                ));
+            }
+            node_ids.extend(matches);
+        }
+        Ok(node_ids)
+    }
+}
+
+#[async_trait]
+impl PipelineCommand for FindPathsCommand {
+    async fn execute(
+        &self,
+        _server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let sgc = match input {
+            PipelineValues::SymbolGraphCollection(sgc) => sgc,
+            _ => {
+                return Err(ServerError::StickyProblem(
+                    ErrorDetails::new(
+                        ErrorKind::BadQuery,
+                        ErrorLayer::ConfigLayer,
+                        "find-paths needs a SymbolGraphCollection".to_string(),
+                    )
+                    .with_hint(
+                        "Use `explain` to see what each pipeline stage consumes and produces.",
+                    ),
+                ));
+            }
+        };
+
+        let sources = self.resolve(&sgc, &self.args.from, "from")?;
+        let targets = self.resolve(&sgc, &self.args.to, "to")?;
+        let avoid = self.resolve(&sgc, &self.args.avoid, "avoid")?;
+        let through = self.resolve(&sgc, &self.args.through, "through")?;
+
+        let max_paths = self.args.max_paths as usize;
+        let mut paths = vec![];
+        for graph in &sgc.graphs {
+            paths.extend(graph.constrained_simple_paths(
+                &sources,
+                &targets,
+                &avoid,
+                &through,
+                self.args.max_length as usize,
+                max_paths - paths.len(),
+            ));
+            if paths.len() >= max_paths {
+                break;
+            }
+        }
+
+        if self.args.format == FindPathsFormat::Graph {
+            let mut paths_graph = NamedSymbolGraph::new("paths".to_string());
+            for (source, target, edge_id) in paths.into_iter().flatten() {
+                paths_graph.ensure_edge(source, target, edge_id);
+            }
+            return Ok(PipelineValues::SymbolGraphCollection(
+                SymbolGraphCollection {
+                    node_set: sgc.node_set,
+                    edge_set: sgc.edge_set,
+                    graphs: vec![paths_graph],
+                    overloads_hit: sgc.overloads_hit,
+                    hierarchical_graphs: vec![],
+                },
+            ));
+        }
+
+        let source_set: HashSet<&SymbolGraphNodeId> = sources.iter().collect();
+        let target_set: HashSet<&SymbolGraphNodeId> = targets.iter().collect();
+        let through_set: HashSet<&SymbolGraphNodeId> = through.iter().collect();
+        let annotate_node = |node_id: &SymbolGraphNodeId, jumps: Vec<&String>| {
+            let info = sgc.node_set.get(node_id);
+            let role = if source_set.contains(node_id) {
+                Some("source")
+            } else if target_set.contains(node_id) {
+                Some("target")
+            } else if through_set.contains(node_id) {
+                Some("through")
+            } else {
+                None
+            };
+            json!({
+                "sym": info.symbol,
+                "pretty": info.get_pretty(),
+                "path": info.get_def_path(),
+                "lno": info.get_def_lno(),
+                "role": role,
+                "via": jumps,
+            })
+        };
+
+        let values = paths
+            .iter()
+            .map(|path| {
+                let mut nodes = vec![];
+                if let Some((first, _, _)) = path.first() {
+                    nodes.push(annotate_node(first, vec![]));
+                }
+                for (_, target, edge_id) in path {
+                    // The source locations responsible for the edge.
+                    let jumps = sgc
+                        .edge_set
+                        .get(edge_id)
+                        .data
+                        .iter()
+                        .filter_map(|detail| match detail {
+                            EdgeDetail::Jump(jump) => Some(jump),
+                            _ => None,
+                        })
+                        .collect();
+                    nodes.push(annotate_node(target, jumps));
+                }
+                JsonValue {
+                    value: json!({
+                        "length": path.len(),
+                        "nodes": nodes,
+                    }),
+                }
+            })
+            .collect();
+
+        Ok(PipelineValues::JsonValueList(JsonValueList { values }))
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use async_trait::async_trait;
+use clap::Args;
+use serde_json::from_value;
+
+use super::{
+    data_table::{DataCell, DataColumnType, DataTable},
+    interface::{PipelineCommand, PipelineValues},
+    test_mapping::map_symbols_to_tests,
+};
+use crate::{
+    abstract_server::{
+        AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, SearchfoxIndexRoot,
+        ServerError,
+    },
+    file_format::{
+        analysis::PathSearchResult,
+        test_stats::{TestStat, TestStats, TEST_STATS_FILENAME},
+    },
+};
+
+/// Attribute the test failures and retriggers ingested by `ingest-test-stats`
+/// to the source directories defining the received symbols, via the same
+/// symbol-to-test mapping as `tests-for-symbol`, ex:
+/// `search-identifiers mozilla::dom:: | crossref-lookup | flaky-heatmap --dir-depth=2`.
+///
+/// Produces a DataTable heatmap with a row per source directory, flakiest
+/// first, with the number of the received symbols it defines, the number of
+/// distinct tests that (transitively) use them, and the summed stats of those
+/// tests.  A test used by symbols in several directories counts towards each.
+#[derive(Debug, Args)]
+pub struct FlakyHeatmap {
+    /// How many levels of callers to follow from the received symbols to
+    /// find their tests.
+    #[clap(long, value_parser, default_value = "1")]
+    depth: u32,
+
+    /// Maximum number of callers to look up at each level per symbol.
+    #[clap(long, value_parser, default_value = "50")]
+    max_callers: usize,
+
+    /// Truncate the source directories to this many path components to
+    /// aggregate at a coarser granularity, ex: 2 for "dom/media".  0 means
+    /// use the full directory.
+    #[clap(long, value_parser, default_value = "0")]
+    dir_depth: usize,
+
+    #[clap(short, long, value_parser, default_value = "100")]
+    limit: usize,
+}
+
+#[derive(Debug)]
+pub struct FlakyHeatmapCommand {
+    pub args: FlakyHeatmap,
+}
+
+#[derive(Default)]
+struct DirHeat {
+    symbols: u32,
+    tests: BTreeSet<String>,
+}
+
+impl FlakyHeatmapCommand {
+    fn heatmap_dir(&self, path: &str) -> String {
+        let dir = path.rsplit_once('/').map_or("", |(dir, _)| dir);
+        if self.args.dir_depth == 0 {
+            return dir.to_string();
+        }
+        dir.split('/')
+            .take(self.args.dir_depth)
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+#[async_trait]
+impl PipelineCommand for FlakyHeatmapCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let scil = match input {
+            PipelineValues::SymbolCrossrefInfoList(scil) => scil,
+            _ => {
+                return Err(ServerError::StickyProblem(
+                    ErrorDetails::new(
+                        ErrorKind::BadQuery,
+                        ErrorLayer::ConfigLayer,
+                        "flaky-heatmap needs a SymbolCrossrefInfoList".to_string(),
+                    )
+                    .with_hint(
+                        "Use `explain` to see what each pipeline stage consumes and produces.",
+                    ),
+                ));
+            }
+        };
+
+        let stats_path =
+            server.translate_path(SearchfoxIndexRoot::IndexRoot, TEST_STATS_FILENAME)?;
+        let Some(stats) = TestStats::load(&stats_path)? else {
+            return Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::IndexMissingCapability,
+                    ErrorLayer::DataLayer,
+                    "This index has no test stats".to_string(),
+                )
+                .with_hint("Use `ingest-test-stats` to ingest a test failure feed."),
+            ));
+        };
+
+        let mut dirs: BTreeMap<String, DirHeat> = BTreeMap::new();
+        for info in &scil.symbol_crossref_infos {
+            let Some(defs) = info.crossref_info.get("defs") else {
+                continue;
+            };
+            let defs: Vec<PathSearchResult> = from_value(defs.clone())?;
+            let Some(def) = defs.first() else {
+                continue;
+            };
+            let heat = dirs.entry(self.heatmap_dir(&def.path)).or_default();
+            heat.symbols += 1;
+
+            let hits = map_symbols_to_tests(
+                server,
+                &[(info.symbol, &info.crossref_info)],
+                self.args.depth,
+                self.args.max_callers,
+            )
+            .await?;
+            heat.tests.extend(
+                hits.keys()
+                    .filter(|path| stats.tests.contains_key(path.as_str()))
+                    .map(|path| path.to_string()),
+            );
+        }
+
+        let mut rows: Vec<(String, u32, usize, TestStat)> = dirs
+            .into_iter()
+            .filter(|(_, heat)| !heat.tests.is_empty())
+            .map(|(dir, heat)| {
+                let mut total = TestStat::default();
+                for test in &heat.tests {
+                    let stat = &stats.tests[test];
+                    total.runs += stat.runs;
+                    total.failures += stat.failures;
+                    total.retriggers += stat.retriggers;
+                }
+                (dir, heat.symbols, heat.tests.len(), total)
+            })
+            .collect();
+        rows.sort_by(|a, b| {
+            (b.3.failures + b.3.retriggers)
+                .cmp(&(a.3.failures + a.3.retriggers))
+                .then_with(|| a.0.cmp(&b.0))
+        });
+
+        let mut table = DataTable::new(vec![
+            ("dir", DataColumnType::Path),
+            ("symbols", DataColumnType::Int),
+            ("tests", DataColumnType::Int),
+            ("runs", DataColumnType::Int),
+            ("failures", DataColumnType::Int),
+            ("retriggers", DataColumnType::Int),
+            ("failure_rate", DataColumnType::Float),
+        ]);
+        for (dir, symbols, tests, total) in rows.into_iter().take(self.args.limit) {
+            table.push_row(vec![
+                DataCell::String(dir),
+                DataCell::Int(symbols as i64),
+                DataCell::Int(tests as i64),
+                DataCell::Int(total.runs as i64),
+                DataCell::Int(total.failures as i64),
+                DataCell::Int(total.retriggers as i64),
+                if total.runs > 0 {
+                    DataCell::Float(total.failures as f64 / total.runs as f64)
+                } else {
+                    DataCell::Null
+                },
+            ])?;
+        }
+
+        Ok(PipelineValues::DataTable(table))
+    }
+}
@@ -1,6 +1,7 @@
 use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::Arc;
 
 use async_trait::async_trait;
 use clap::{Args, ValueEnum};
@@ -8,23 +9,36 @@ use itertools::Itertools;
 use serde_json::{from_str, Value};
 
 use super::{
+    idl_impls::IdlImplMatrix,
     interface::{
-        PipelineCommand, PipelineValues, SymbolCrossrefInfo, SymbolTreeTable, SymbolTreeTableField,
-        SymbolTreeTableFieldOffsetAndSize, SymbolTreeTableFieldType, SymbolTreeTableItem,
-        SymbolTreeTableList, SymbolTreeTableNode,
+        PipelineCommand, PipelineValues, SourceSpan, SymbolCrossrefInfo, SymbolTreeTable,
+        SymbolTreeTableClassSize, SymbolTreeTableField, SymbolTreeTableFieldOffsetAndSize,
+        SymbolTreeTableFieldType, SymbolTreeTableItem, SymbolTreeTableList, SymbolTreeTableMethod,
+        SymbolTreeTableNode, SymbolTreeTableWarning,
     },
     symbol_graph::{DerivedSymbolInfo, SymbolGraphNodeId},
 };
 
 use crate::file_format::analysis::{
-    AnalysisStructured, StructuredBitPositionInfo, StructuredFieldInfo,
+    AnalysisStructured, StructuredBitPositionInfo, StructuredFieldInfo, StructuredMethodInfo,
 };
 
-use crate::abstract_server::{AbstractServer, ErrorDetails, ErrorLayer, Result, ServerError};
+use crate::abstract_server::{
+    AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, IndexCapabilities, Result,
+    SearchfoxIndexRoot, ServerError,
+};
+use crate::file_format::symbol_annotations::{SymbolAnnotations, SYMBOL_ANNOTATIONS_FILENAME};
+use crate::number_format::{format_byte_size_human, format_count};
+use crate::rust_repr::RustRepr;
 
 #[derive(Clone, Debug, PartialEq, ValueEnum)]
 pub enum SymbolFormatMode {
     FieldLayout,
+    /// For XPIDL and WebIDL interfaces (or their bindings), a matrix of the
+    /// interface's methods and attributes against the concrete C++ classes
+    /// and JS files implementing it, marking each implementation as "impl",
+    /// "forwarded", "inherited", or "missing", and `[notxpcom]` methods.
+    IdlImpls,
     // - class-field-use-matrix: table for each class, look up all its methods and all its
     //   fields, then filter the method "calls" to the fields.
     // - caller-matrix: look up a class, get all its methods.  look up all of
@@ -40,6 +54,53 @@ pub enum SymbolFormatMode {
     //     the args.
 }
 
+/// How to display offsets in the field-layout table.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum OffsetFormat {
+    /// Decimal, like "@ 16".
+    Dec,
+    /// Hexadecimal, like "@ 0x10", matching debugger output.
+    Hex,
+}
+
+/// How to display sizes in the field-layout table.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum SizeFormat {
+    /// Always a number of bytes.
+    Bytes,
+    /// Binary units for sizes of 1 KiB or larger, like "1.5 KiB".
+    Human,
+}
+
+impl OffsetFormat {
+    fn format(&self, offset: u32) -> String {
+        match self {
+            OffsetFormat::Dec => format!("@ {}", offset),
+            OffsetFormat::Hex => format!("@ {:#x}", offset),
+        }
+    }
+}
+
+impl SizeFormat {
+    /// Format the size of a field, which is a bare number for `Bytes` since
+    /// the column makes the unit obvious.
+    fn format_field(&self, size: u32) -> String {
+        match self {
+            SizeFormat::Bytes => format!("{}", size),
+            SizeFormat::Human if size < 1024 => format!("{}", size),
+            SizeFormat::Human => format_byte_size_human(size as u64),
+        }
+    }
+
+    /// Format the size of a hole or padding, which always includes the unit.
+    fn format_gap(&self, size: u32) -> String {
+        match self {
+            SizeFormat::Bytes => format_count(size as u64, "byte", "bytes"),
+            SizeFormat::Human => format_byte_size_human(size as u64),
+        }
+    }
+}
+
 /// Given a list of symbol crossref infos, produce a SymbolTreeTable for display
 /// purposes.
 #[derive(Debug, Args)]
@@ -52,6 +113,41 @@ pub struct FormatSymbols {
 
     #[clap(long, value_parser)]
     pub hide_cols: Option<String>,
+
+    /// If specified, annotate each class row with the symbol and edge that
+    /// caused it to be included, and emit "provenance" for all symbols.
+    #[clap(long, value_parser)]
+    pub provenance: bool,
+
+    /// If specified, emit the source span (path, lines, and columns when
+    /// known) of each field's declaration so that front-ends can highlight
+    /// the declaration itself rather than whole lines.
+    #[clap(long, value_parser)]
+    pub spans: bool,
+
+    /// If specified, list each class's member functions beneath its fields.
+    #[clap(long, value_parser)]
+    pub include_methods: bool,
+
+    /// If specified, emit a separate table for each class whose columns only
+    /// distinguish the platform groups where that class's own layout differs,
+    /// rather than a single table with the platform groups of the whole
+    /// hierarchy.  Classes that are the same everywhere get a single column.
+    #[clap(long, value_parser)]
+    pub regroup_per_class: bool,
+
+    /// How to display field offsets.
+    #[clap(long, value_parser, value_enum, default_value = "hex")]
+    pub offsets: OffsetFormat,
+
+    /// How to display field, hole, and padding sizes.
+    #[clap(long, value_parser, value_enum, default_value = "bytes")]
+    pub sizes: SizeFormat,
+
+    /// For `--mode=idl-impls`, the maximum number of implementations of each
+    /// language to show per interface.
+    #[clap(long, value_parser, default_value = "100")]
+    pub max_impls: usize,
 }
 
 #[derive(Debug)]
@@ -80,6 +176,9 @@ type FieldId = SymbolGraphNodeId;
 #[derive(Clone, Copy, Eq, Hash, PartialEq)]
 struct TraversalId(u32);
 
+// A line number and optional 0-based column.
+type LinePos = (u64, Option<u32>);
+
 // A struct to represent single field and hole before the field,
 // for specific platform.
 #[derive(Clone, Eq, Hash, PartialEq)]
@@ -94,12 +193,22 @@ struct Field {
     def_path: String,
     start_lineno: u64,
     end_lineno: u64,
+    // 0-based columns of the start and (exclusive) end of the declaration, if
+    // known.
+    start_col: Option<u32>,
+    end_col: Option<u32>,
+    // The line and crossref "bounds" of the field's identifier.  The bounds
+    // are relative to the whitespace-trimmed line.
+    identifier_lineno: u64,
+    identifier_bounds: Option<(u32, u32)>,
     hole_bytes: Option<u32>,
     hole_after_base: bool,
     end_padding_bytes: Option<u32>,
     offset_bytes: u32,
     bit_positions: Option<StructuredBitPositionInfo>,
     size_bytes: Option<u32>,
+    // The alignment from the structured info, if the indexer provided it.
+    align_bytes: Option<u32>,
 }
 
 impl Field {
@@ -113,13 +222,15 @@ impl Field {
         field_type_syms: String,
         struct_def_path: &Option<String>,
         identifier_lineno: u64,
+        identifier_bounds: Option<(u32, u32)>,
         info: &StructuredFieldInfo,
     ) -> Self {
-        let (def_path, start_lineno, end_lineno) = Self::parse_path_and_line_range(
-            info.line_range.to_string(),
-            struct_def_path,
-            identifier_lineno,
-        );
+        let (def_path, (start_lineno, start_col), (end_lineno, end_col)) =
+            Self::parse_path_and_line_range(
+                info.line_range.to_string(),
+                struct_def_path,
+                identifier_lineno,
+            );
 
         Self {
             class_id,
@@ -132,20 +243,34 @@ impl Field {
             def_path,
             start_lineno,
             end_lineno,
+            start_col,
+            end_col,
+            identifier_lineno,
+            identifier_bounds,
             hole_bytes: None,
             hole_after_base: false,
             end_padding_bytes: None,
             offset_bytes: class_offset + info.offset_bytes,
             bit_positions: info.bit_positions.clone(),
             size_bytes: info.size_bytes,
+            align_bytes: info.align_bytes,
         }
     }
 
+    /// Parse a "lineRange" of the form "PATH#start-end" or "PATH#line" where
+    /// each line may optionally be followed by ":col" to give the exact span.
     fn parse_path_and_line_range(
         s: String,
         struct_def_path: &Option<String>,
         identifier_lineno: u64,
-    ) -> (String, u64, u64) {
+    ) -> (String, LinePos, LinePos) {
+        let parse_pos = |pos: &str| -> LinePos {
+            match pos.split_once(":") {
+                Some((line, col)) => (line.parse().unwrap_or(identifier_lineno), col.parse().ok()),
+                None => (pos.parse().unwrap_or(identifier_lineno), None),
+            }
+        };
+
         match s.split_once("#") {
             Some((path, range)) => {
                 let def_path = if path.is_empty() {
@@ -157,26 +282,70 @@ impl Field {
                 };
 
                 match range.split_once("-") {
-                    Some((start, end)) => (
-                        def_path,
-                        start.parse().unwrap_or(identifier_lineno),
-                        end.parse().unwrap_or(identifier_lineno),
-                    ),
-                    None => (
-                        def_path,
-                        range.parse().unwrap_or(identifier_lineno),
-                        range.parse().unwrap_or(identifier_lineno),
-                    ),
+                    Some((start, end)) => (def_path, parse_pos(start), parse_pos(end)),
+                    None => (def_path, parse_pos(range), parse_pos(range)),
                 }
             }
             None => (
                 struct_def_path.clone().unwrap_or("".to_string()),
-                identifier_lineno,
-                identifier_lineno,
+                (identifier_lineno, None),
+                (identifier_lineno, None),
             ),
         }
     }
 
+    // A placeholder for the entire subobject of an external base class.  The
+    // size is inferred from the derived class's layout and may include
+    // padding.
+    fn new_external_base(
+        class_id: ClassId,
+        class_traversal_id: TraversalId,
+        class_offset: u32,
+        size_bytes: Option<u32>,
+    ) -> Self {
+        Self {
+            class_id,
+            class_traversal_id,
+            class_end_offset: size_bytes.map(|size| class_offset + size),
+            field_id: None,
+            field_type_syms: None,
+            type_pretty: "".to_string(),
+            pretty: "(external base subobject)".to_string(),
+            def_path: "".to_string(),
+            start_lineno: 0,
+            end_lineno: 0,
+            start_col: None,
+            end_col: None,
+            identifier_lineno: 0,
+            identifier_bounds: None,
+            hole_bytes: None,
+            hole_after_base: false,
+            end_padding_bytes: None,
+            offset_bytes: class_offset,
+            bit_positions: None,
+            size_bytes,
+            align_bytes: None,
+        }
+    }
+
+    // Whether the field's cells in the table would be the same as the other's,
+    // ignoring bookkeeping that isn't displayed, like where the enclosing
+    // class ends or whether a field without a hole follows a base class.
+    fn displays_same_as(&self, other: &Field) -> bool {
+        self.field_id == other.field_id
+            && self.type_pretty == other.type_pretty
+            && self.def_path == other.def_path
+            && self.start_lineno == other.start_lineno
+            && self.end_lineno == other.end_lineno
+            && self.offset_bytes == other.offset_bytes
+            && self.bit_positions == other.bit_positions
+            && self.size_bytes == other.size_bytes
+            && self.align_bytes == other.align_bytes
+            && self.hole_bytes == other.hole_bytes
+            && (self.hole_bytes.is_none() || self.hole_after_base == other.hole_after_base)
+            && self.end_padding_bytes == other.end_padding_bytes
+    }
+
     fn new_vtable(
         class_id: ClassId,
         class_traversal_id: TraversalId,
@@ -195,16 +364,44 @@ impl Field {
             def_path: "".to_string(),
             start_lineno: 0,
             end_lineno: 0,
+            start_col: None,
+            end_col: None,
+            identifier_lineno: 0,
+            identifier_bounds: None,
             hole_bytes: None,
             hole_after_base: false,
             end_padding_bytes: None,
             offset_bytes: class_offset,
             bit_positions: None,
             size_bytes: Some(size_bytes),
+            align_bytes: None,
         }
     }
 }
 
+// The largest alignment we'll infer, matching the alignment of pointers and
+// 64-bit integers on the platforms we index.
+const MAX_INFERRED_ALIGN_BYTES: u32 = 8;
+
+// The natural alignment of a type of the given size, assuming it's made of
+// fundamental types.
+fn natural_align_bytes(size_bytes: u32) -> u32 {
+    if size_bytes == 0 {
+        return 1;
+    }
+    (1 << size_bytes.trailing_zeros()).min(MAX_INFERRED_ALIGN_BYTES)
+}
+
+// Infer the alignment of a field from its size, bounded by what its offset
+// allows.
+fn infer_align_bytes(offset_bytes: u32, size_bytes: u32) -> u32 {
+    let align = natural_align_bytes(size_bytes);
+    if offset_bytes == 0 {
+        return align;
+    }
+    align.min(1 << offset_bytes.trailing_zeros())
+}
+
 // A container for fields, with pre-calculated hash of fields.
 struct FieldsWithHash {
     fields: Vec<Field>,
@@ -240,6 +437,13 @@ impl FieldsWithHash {
         let len = self.fields.len();
 
         for index in 0..len {
+            // Zero-size entries, like `[[no_unique_address]]` members of empty
+            // types, share their offset with other fields, so they neither
+            // have holes before them nor end anything.
+            if self.fields[index].size_bytes == Some(0) {
+                continue;
+            }
+
             if self.fields[index].offset_bytes > last_end_offset {
                 if index != last_index
                     && self.fields[last_index].class_traversal_id
@@ -272,8 +476,14 @@ impl FieldsWithHash {
                 continue;
             }
 
+            // A field can start before the end of the previous one when it
+            // reuses space, ex: an empty `[[no_unique_address]]` member placed
+            // at the start of the class, so we never move the end backwards.
             if let Some(size) = &self.fields[index].size_bytes {
-                last_end_offset = self.fields[index].offset_bytes + size;
+                let end = self.fields[index].offset_bytes + size;
+                if end > last_end_offset {
+                    last_end_offset = end;
+                }
             }
         }
 
@@ -298,16 +508,64 @@ struct FieldListItem {
     average_lineno: u64,
     average_bit_offset: u64,
     group_bits: u64,
+    pretty: String,
+    field_id: Option<FieldId>,
     field_variants: Vec<Option<Field>>,
 }
 
+// Keep only the values of the given columns, which must be in ascending order.
+fn select_values<T>(values: Vec<T>, columns: &[usize]) -> Vec<T> {
+    values
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| columns.binary_search(i).is_ok())
+        .map(|(_, value)| value)
+        .collect()
+}
+
+// Reduce a class row to the given platform group columns.
+fn select_columns(mut node: SymbolTreeTableNode, columns: &[usize]) -> SymbolTreeTableNode {
+    node.sizes = select_values(node.sizes, columns);
+    node.items = node
+        .items
+        .into_iter()
+        .map(|item| match item {
+            SymbolTreeTableItem::Field(mut field) => {
+                field.offset_and_size = select_values(field.offset_and_size, columns);
+                SymbolTreeTableItem::Field(field)
+            }
+            SymbolTreeTableItem::Hole(holes) => {
+                SymbolTreeTableItem::Hole(select_values(holes, columns))
+            }
+            SymbolTreeTableItem::EndPadding(paddings) => {
+                SymbolTreeTableItem::EndPadding(select_values(paddings, columns))
+            }
+            SymbolTreeTableItem::Cells(cells) => {
+                SymbolTreeTableItem::Cells(select_values(cells, columns))
+            }
+            item => item,
+        })
+        .collect();
+    node
+}
+
 // A struct to represent single class, with
 // fields per each platform group.
 struct Class {
     id: ClassId,
     name: String,
+    // True if this is a base class we have no structured info for, in which
+    // case it's represented by a single placeholder field.
+    is_external: bool,
+    // The size of the class per platform.  For external base classes this is
+    // the inferred size of the subobject.
+    sizes: HashMap<PlatformId, u32>,
     fields: HashMap<Option<FieldId>, HashMap<PlatformGroupId, Field>>,
     merged_fields: Vec<Vec<Option<Field>>>,
+    // Member functions, if requested.
+    methods: Vec<SymbolTreeTableMethod>,
+    // For Rust types, the type's repr as recorded by the indexer.
+    repr: Option<String>,
 }
 
 impl Class {
@@ -315,11 +573,24 @@ impl Class {
         Self {
             id,
             name,
+            is_external: false,
+            sizes: HashMap::new(),
             fields: HashMap::new(),
             merged_fields: vec![],
+            methods: vec![],
+            repr: None,
         }
     }
 
+    // Whether holes between fields are meaningful, which isn't the case for
+    // Rust types with the default repr because the compiler may reorder the
+    // fields.
+    fn has_defined_layout(&self) -> bool {
+        self.repr
+            .as_deref()
+            .map_or(true, |repr| RustRepr::parse(repr).has_defined_layout())
+    }
+
     fn add_field(&mut self, group_id: PlatformGroupId, field: Field) {
         let field_id = field.field_id.clone();
 
@@ -338,15 +609,19 @@ impl Class {
         //   * Line number
         //   * Average bit offset of the field
         //   * Integer encoding of the groups where the field exists
+        //   * Pretty name and then symbol id, so that the order is stable
+        //     even when the above are all equal, as the fields map is a
+        //     HashMap whose iteration order varies between runs
 
         let mut field_list = vec![];
 
-        for field_variants_map in self.fields.values() {
+        for (field_id, field_variants_map) in &self.fields {
             let mut group_bits: u64 = 0;
             let mut total_lineno: u64 = 0;
             let mut total_bit_offset: u64 = 0;
             let mut field_count: u64 = 0;
             let mut field_def_paths = vec![];
+            let mut pretty = None;
 
             let mut field_variants = vec![];
             for (group_id, _) in groups {
@@ -361,6 +636,10 @@ impl Class {
 
                         field_count += 1;
 
+                        if pretty.is_none() {
+                            pretty = Some(field.pretty.clone());
+                        }
+
                         field_variants.push(Some(field.clone()));
 
                         if !field_def_paths.contains(&field.def_path) {
@@ -381,6 +660,8 @@ impl Class {
                 average_lineno,
                 average_bit_offset,
                 group_bits,
+                pretty: pretty.unwrap_or_default(),
+                field_id: field_id.clone(),
                 field_variants,
             })
         }
@@ -411,7 +692,12 @@ impl Class {
                 return result;
             }
 
-            Ordering::Equal
+            let result = a.pretty.cmp(&b.pretty);
+            if result != Ordering::Equal {
+                return result;
+            }
+
+            a.field_id.cmp(&b.field_id)
         });
 
         self.merged_fields = field_list
@@ -599,6 +885,10 @@ struct TraversalItem {
     // The offset for the class per platform.
     // Platforms not included in this map are not used for this traversal.
     offset_map: HashMap<PlatformId, u32>,
+
+    // The size of the class's subobject per platform as inferred from the
+    // derived class's layout, used if the class turns out to be external.
+    size_map: HashMap<PlatformId, u32>,
 }
 
 impl TraversalItem {
@@ -606,6 +896,7 @@ impl TraversalItem {
         Self {
             class_id,
             offset_map: HashMap::new(),
+            size_map: HashMap::new(),
         }
     }
 
@@ -613,6 +904,14 @@ impl TraversalItem {
         self.offset_map.insert(platform_id, offset);
     }
 
+    fn add_size(&mut self, platform_id: PlatformId, size: u32) {
+        self.size_map.insert(platform_id, size);
+    }
+
+    fn get_size(&self, platform_id: &PlatformId) -> Option<u32> {
+        self.size_map.get(platform_id).copied()
+    }
+
     fn get_offset(&self, platform_id: &PlatformId) -> u32 {
         match self.offset_map.get(platform_id) {
             Some(offset) => *offset,
@@ -635,7 +934,7 @@ impl TraversalItem {
 
 struct SupersMap {
     super_ids: Vec<ClassId>,
-    supers: HashMap<ClassId, HashMap<PlatformId, u32>>,
+    supers: HashMap<ClassId, HashMap<PlatformId, (u32, Option<u32>)>>,
 }
 
 impl SupersMap {
@@ -646,14 +945,14 @@ impl SupersMap {
         }
     }
 
-    fn add(&mut self, class_id: ClassId, platform_id: PlatformId, offset: u32) {
+    fn add(&mut self, class_id: ClassId, platform_id: PlatformId, offset: u32, size: Option<u32>) {
         if let Some(item) = self.supers.get_mut(&class_id) {
-            item.insert(platform_id, offset);
+            item.insert(platform_id, (offset, size));
             return;
         }
 
         let mut item = HashMap::new();
-        item.insert(platform_id, offset);
+        item.insert(platform_id, (offset, size));
         self.super_ids.push(class_id.clone());
         self.supers.insert(class_id, item);
     }
@@ -664,8 +963,11 @@ impl SupersMap {
         for class_id in self.super_ids {
             let offset_map = self.supers.get(&class_id).unwrap();
             let mut item = TraversalItem::new(class_id);
-            for (platform_id, offset) in offset_map {
+            for (platform_id, (offset, size)) in offset_map {
                 item.add_offset(*platform_id, *offset);
+                if let Some(size) = size {
+                    item.add_size(*platform_id, *size);
+                }
             }
             result.push(item);
         }
@@ -692,6 +994,27 @@ struct ClassMap {
 
     has_unsupported_multiple_inheritance: bool,
 
+    // Whether to emit source spans for each field.
+    emit_spans: bool,
+
+    // Whether to emit the alignment of each field.
+    emit_align: bool,
+
+    // Whether to list the member functions of each class.
+    include_methods: bool,
+
+    // Whether to emit a table per class with the class's own platform groups.
+    regroup_per_class: bool,
+
+    offset_format: OffsetFormat,
+    size_format: SizeFormat,
+
+    // What the index has, so we can explain missing information.
+    capabilities: IndexCapabilities,
+
+    // The tree's annotation store, for showing the tags of each class.
+    annotations: Arc<SymbolAnnotations>,
+
     root_class_id: Option<ClassId>,
     stt: SymbolTreeTable,
 }
@@ -705,6 +1028,14 @@ impl ClassMap {
             groups: vec![],
             file_lines: HashMap::new(),
             has_unsupported_multiple_inheritance: false,
+            emit_spans: false,
+            emit_align: false,
+            include_methods: false,
+            regroup_per_class: false,
+            offset_format: OffsetFormat::Hex,
+            size_format: SizeFormat::Bytes,
+            capabilities: IndexCapabilities::default(),
+            annotations: Arc::default(),
             root_class_id: None,
             stt: SymbolTreeTable::new(),
         }
@@ -739,12 +1070,78 @@ impl ClassMap {
 
             let sym_info = self.stt.node_set.get(&class_id);
             let depth = sym_info.depth;
+            let class_sym = sym_info.symbol;
+            let is_base = traversal_index > 0;
+            if is_base && sym_info.get_structured().is_none() {
+                // We don't have any info about this base class, likely because
+                // it's from the standard library or an external library that
+                // isn't indexed.  Rather than silently dropping the subobject,
+                // represent it with a placeholder covering it.
+                let mut cls = Class::new(class_id.clone(), sym_info.get_pretty().to_string());
+                cls.is_external = true;
+                for platform_id in item.platforms() {
+                    if let Some(size) = item.get_size(&platform_id) {
+                        cls.sizes.insert(platform_id, size);
+                    }
+                }
+
+                let traversal_id = TraversalId(traversal_index);
+                traversal_index += 1;
+                self.class_list.push(traversal_id);
+                self.class_map.insert(traversal_id, cls);
+
+                for platform_id in item.platforms() {
+                    let field = Field::new_external_base(
+                        class_id.clone(),
+                        traversal_id,
+                        item.get_offset(&platform_id),
+                        item.get_size(&platform_id),
+                    );
+                    fields_per_platform.add_field(&platform_id, field);
+                }
+                continue;
+            }
             let Some(structured) = Self::get_struct_structured(sym_info) else {
                 continue;
             };
             let struct_def_path = sym_info.get_def_path().cloned();
 
-            let cls = Class::new(class_id.clone(), structured.pretty.to_string());
+            let mut cls = Class::new(class_id.clone(), structured.pretty.to_string());
+            cls.repr = structured.repr.as_ref().map(|repr| repr.to_string());
+
+            for (maybe_platform, s) in structured.per_platform() {
+                let Some(size) = s.size_bytes else {
+                    continue;
+                };
+                if let Some(platform) = maybe_platform {
+                    let platform_id = self.platform_map.get(platform.clone());
+                    if item.is_enabled(&platform_id) {
+                        cls.sizes.insert(platform_id, size);
+                    }
+                } else {
+                    for platform_id in item.platforms() {
+                        cls.sizes.insert(platform_id, size);
+                    }
+                }
+            }
+
+            if self.include_methods {
+                for method in &structured.methods {
+                    let (_, method_info) = self
+                        .stt
+                        .node_set
+                        .ensure_symbol_via(&method.sym, server, depth + 1, class_sym, "method")
+                        .await?;
+                    let def_path = method_info.get_def_path().cloned();
+                    let def_lineno = def_path.as_ref().map(|_| method_info.get_def_lno());
+                    cls.methods.push(Self::method_item(
+                        &structured.pretty,
+                        method,
+                        def_path,
+                        def_lineno,
+                    ));
+                }
+            }
 
             let traversal_id = TraversalId(traversal_index);
 
@@ -758,7 +1155,7 @@ impl ClassMap {
             for (maybe_platform, s) in structured.per_platform() {
                 let mut maybe_platform_id: Option<PlatformId> = None;
 
-                if let Some(platform) = maybe_platform {
+                if let Some(platform) = &maybe_platform {
                     let platform_id = self.platform_map.get(platform.clone());
                     if !item.is_enabled(&platform_id) {
                         continue;
@@ -794,27 +1191,60 @@ impl ClassMap {
                     }
                 }
 
+                self.check_structured(&structured.pretty, &maybe_platform, s);
+
                 if s.supers.len() > 1 {
                     has_multiple_inheritance = true;
                 }
 
+                // Fields that take up space, as zero-size fields like
+                // `[[no_unique_address]]` members of empty types can share
+                // their offset with a base class subobject.
+                let sized_fields = || s.fields.iter().filter(|info| info.size_bytes != Some(0));
+
+                // The offsets where something in this class starts, which
+                // bound the size of each base class subobject.
+                let mut boundaries: Vec<u32> = s
+                    .supers
+                    .iter()
+                    .map(|info| info.offset_bytes)
+                    .chain(sized_fields().map(|info| info.offset_bytes))
+                    .chain(s.size_bytes)
+                    .collect();
+                boundaries.sort_unstable();
+
                 for super_info in &s.supers {
                     let (super_id, _) = self
                         .stt
                         .node_set
-                        .ensure_symbol(&super_info.sym, server, depth + 1)
+                        .ensure_symbol_via(&super_info.sym, server, depth + 1, class_sym, "super")
                         .await?;
 
                     if super_info.offset_bytes > 0 {
                         has_non_zero_super_offset = true;
                     }
 
+                    // If one of our fields starts where the base does, the
+                    // base must be empty and its subobject takes no space
+                    // thanks to the empty base optimization.
+                    let is_empty_base =
+                        sized_fields().any(|info| info.offset_bytes == super_info.offset_bytes);
+                    let size = if is_empty_base {
+                        Some(0)
+                    } else {
+                        boundaries
+                            .iter()
+                            .find(|b| **b > super_info.offset_bytes)
+                            .map(|b| b - super_info.offset_bytes)
+                    };
+
                     if let Some(platform_id) = &maybe_platform_id {
                         let offset = item.get_offset(platform_id);
                         supers.add(
                             super_id.clone(),
                             *platform_id,
                             offset + super_info.offset_bytes,
+                            size,
                         );
                     } else {
                         for platform_id in item.platforms() {
@@ -823,20 +1253,25 @@ impl ClassMap {
                                 super_id.clone(),
                                 platform_id,
                                 offset + super_info.offset_bytes,
+                                size,
                             );
                         }
                     }
                 }
 
                 for field in s.fields.clone() {
-                    let (field_id, field_lineno) = {
+                    let (field_id, field_lineno, field_bounds) = {
                         let (field_id, field_info) = self
                             .stt
                             .node_set
-                            .ensure_symbol(&field.sym, server, depth + 1)
+                            .ensure_symbol_via(&field.sym, server, depth + 1, class_sym, "field")
                             .await?;
 
-                        (field_id, field_info.get_def_lno())
+                        (
+                            field_id,
+                            field_info.get_def_lno(),
+                            field_info.get_def_bounds(),
+                        )
                     };
 
                     let mut field_type_syms_vec = vec![];
@@ -848,7 +1283,13 @@ impl ClassMap {
                         let _ = self
                             .stt
                             .node_set
-                            .ensure_symbol(&field.type_sym, server, depth + 1)
+                            .ensure_symbol_via(
+                                &field.type_sym,
+                                server,
+                                depth + 1,
+                                class_sym,
+                                "field-type",
+                            )
                             .await?;
 
                         field_type_syms_vec.push(field.type_sym.to_string());
@@ -862,7 +1303,7 @@ impl ClassMap {
                         let _ = self
                             .stt
                             .node_set
-                            .ensure_symbol(&info.sym, server, depth + 1)
+                            .ensure_symbol_via(&info.sym, server, depth + 1, class_sym, "pointer")
                             .await?;
 
                         field_type_syms_vec.push(info.sym.to_string());
@@ -882,6 +1323,7 @@ impl ClassMap {
                             field_type_syms,
                             &struct_def_path,
                             field_lineno,
+                            field_bounds,
                             &field,
                         );
                         self.populate_file_lines(&field.def_path, server).await?;
@@ -898,6 +1340,7 @@ impl ClassMap {
                                 field_type_syms.clone(),
                                 &struct_def_path,
                                 field_lineno,
+                                field_bounds,
                                 &field,
                             );
                             self.populate_file_lines(&field.def_path, server).await?;
@@ -935,6 +1378,93 @@ impl ClassMap {
         Ok(())
     }
 
+    /// Look for anomalies in a class's structured record for one platform,
+    /// recording them as warnings on the table.
+    fn check_structured(
+        &mut self,
+        class_pretty: &str,
+        platform: &Option<String>,
+        s: &AnalysisStructured,
+    ) {
+        let mut warn = |kind: &str, pretty: &str, message: String| {
+            self.stt.warnings.push(SymbolTreeTableWarning {
+                kind: kind.to_string(),
+                pretty: pretty.to_string(),
+                platform: platform.clone(),
+                message,
+            });
+        };
+
+        if s.size_bytes.is_none() {
+            warn(
+                "missing-size",
+                class_pretty,
+                "The analysis has no size for this class".to_string(),
+            );
+        }
+
+        // The bit ranges occupied by each field.
+        let mut extents = vec![];
+        for field in &s.fields {
+            let (start_bit, width_bits) = match (&field.bit_positions, field.size_bytes) {
+                (Some(pos), _) => (
+                    field.offset_bytes as u64 * 8 + pos.begin as u64,
+                    pos.width as u64,
+                ),
+                (None, Some(size)) => (field.offset_bytes as u64 * 8, size as u64 * 8),
+                (None, None) => {
+                    warn(
+                        "missing-size",
+                        &field.pretty,
+                        "The analysis has no size for this field".to_string(),
+                    );
+                    continue;
+                }
+            };
+            let end_bit = start_bit + width_bits;
+            if let Some(class_size) = s.size_bytes {
+                if end_bit > class_size as u64 * 8 {
+                    warn(
+                        "field-beyond-class-size",
+                        &field.pretty,
+                        format!(
+                            "The field ends at byte {} but the class is only {} bytes",
+                            (end_bit + 7) / 8,
+                            class_size
+                        ),
+                    );
+                }
+            }
+            // Zero-size fields can legitimately share their offset.
+            if width_bits > 0 {
+                extents.push((start_bit, end_bit, field.pretty.as_str()));
+            }
+        }
+
+        // The fields of unions and of Rust enum variants are expected to
+        // overlap.
+        if s.kind == "union" || s.kind == "enum" {
+            return;
+        }
+        extents.sort();
+        let mut furthest: Option<(u64, &str)> = None;
+        for (start_bit, end_bit, pretty) in extents {
+            if let Some((furthest_end, furthest_pretty)) = furthest {
+                if start_bit < furthest_end {
+                    warn(
+                        "overlapping-fields",
+                        pretty,
+                        format!("The field overlaps {}", furthest_pretty),
+                    );
+                }
+                if end_bit <= furthest_end {
+                    continue;
+                }
+            }
+            furthest = Some((end_bit, pretty));
+        }
+    }
+
     async fn populate_file_lines(
         &mut self,
         path: &String,
@@ -967,6 +1497,165 @@ impl ClassMap {
         Ok(())
     }
 
+    /// Build the source span for a field.  If the indexer didn't provide
+    /// columns for a single-line declaration, fall back to the columns of the
+    /// field's identifier, which crossref provides relative to the
+    /// whitespace-trimmed line.
+    fn field_span(&self, field: &Field) -> SourceSpan {
+        let mut span = SourceSpan {
+            path: field.def_path.clone(),
+            start_lineno: field.start_lineno,
+            start_col: field.start_col,
+            end_lineno: field.end_lineno,
+            end_col: field.end_col,
+        };
+        if span.start_col.is_none()
+            && span.start_lineno == span.end_lineno
+            && span.start_lineno == field.identifier_lineno
+        {
+            if let Some((start, end)) = field.identifier_bounds {
+                let indent = self
+                    .file_lines
+                    .get(&field.def_path)
+                    .and_then(|lines| lines.get((field.identifier_lineno as usize).checked_sub(1)?))
+                    .map_or(0, |line| {
+                        line.chars().take_while(|c| c.is_whitespace()).count()
+                    }) as u32;
+                span.start_col = Some(start + indent);
+                span.end_col = Some(end + indent);
+            }
+        }
+        span
+    }
+
+    /// Build the size cells for a class's header row, one per platform group,
+    /// along with how much of the size isn't accounted for by the class's own
+    /// fields.
+    fn class_sizes(&self, cls: &Class) -> Vec<Option<SymbolTreeTableClassSize>> {
+        self.groups
+            .iter()
+            .enumerate()
+            .map(|(group_index, (_, platforms))| {
+                let size = *cls.sizes.get(&platforms[0])?;
+
+                let field_bits: u64 = cls
+                    .merged_fields
+                    .iter()
+                    .filter_map(|field_variants| field_variants[group_index].as_ref())
+                    .map(|field| match &field.bit_positions {
+                        Some(pos) => pos.width as u64,
+                        None => field.size_bytes.unwrap_or(0) as u64 * 8,
+                    })
+                    .sum();
+                let delta = size as i64 - ((field_bits + 7) / 8) as i64;
+
+                Some(SymbolTreeTableClassSize::new(
+                    self.size_format.format_gap(size),
+                    if delta == 0 {
+                        None
+                    } else {
+                        Some(format!(
+                            "{}{}",
+                            if delta > 0 { "+" } else { "-" },
+                            self.size_format.format_gap(delta.unsigned_abs() as u32)
+                        ))
+                    },
+                ))
+            })
+            .collect()
+    }
+
+    /// Partition the indices of the platform group columns into sets where the
+    /// class's own size and fields are identical, in column order.
+    fn class_column_groups(&self, cls: &Class) -> Vec<Vec<usize>> {
+        let mut column_groups: Vec<Vec<usize>> = vec![];
+        'next_column: for (index, (_, platforms)) in self.groups.iter().enumerate() {
+            for column_group in &mut column_groups {
+                let rep = column_group[0];
+                let same_size =
+                    cls.sizes.get(&platforms[0]) == cls.sizes.get(&self.groups[rep].1[0]);
+                if same_size
+                    && cls.merged_fields.iter().all(|field_variants| {
+                        match (&field_variants[index], &field_variants[rep]) {
+                            (Some(a), Some(b)) => a.displays_same_as(b),
+                            (a, b) => a.is_none() && b.is_none(),
+                        }
+                    })
+                {
+                    column_group.push(index);
+                    continue 'next_column;
+                }
+            }
+            column_groups.push(vec![index]);
+        }
+        column_groups
+    }
+
+    fn method_item(
+        class_pretty: &str,
+        method: &StructuredMethodInfo,
+        def_path: Option<String>,
+        def_lineno: Option<u64>,
+    ) -> SymbolTreeTableMethod {
+        let name = method
+            .pretty
+            .strip_prefix(&format!("{}::", class_pretty))
+            .unwrap_or(method.pretty.as_str())
+            .to_string();
+        let signature = format!(
+            "({})",
+            method
+                .args
+                .iter()
+                .map(|arg| if arg.name.is_empty() {
+                    arg.type_pretty.to_string()
+                } else {
+                    format!("{} {}", arg.type_pretty, arg.name)
+                })
+                .join(", ")
+        );
+        // "instance" and "user" are the common case and just noise here.
+        let flags = method
+            .props
+            .iter()
+            .filter(|prop| prop.as_str() != "instance" && prop.as_str() != "user")
+            .map(|prop| prop.to_string())
+            .collect();
+
+        SymbolTreeTableMethod {
+            name,
+            symbols: method.sym.to_string(),
+            signature,
+            flags,
+            def_path,
+            def_lineno,
+        }
+    }
+
+    /// Describe a field's alignment, flagging fields whose alignment exceeds
+    /// their natural alignment and forced a hole before them.  Bitfields don't
+    /// have their own alignment.
+    fn field_align(&self, field: &Field) -> Option<String> {
+        if field.bit_positions.is_some() {
+            return None;
+        }
+        let size_bytes = field.size_bytes.unwrap_or(0);
+        match field.align_bytes {
+            Some(align) => {
+                let forced_hole = field.hole_bytes.map_or(false, |hole| hole > 0);
+                if forced_hole && align > natural_align_bytes(size_bytes) {
+                    Some(format!("{} (over-aligned)", align))
+                } else {
+                    Some(format!("{}", align))
+                }
+            }
+            None => Some(format!(
+                "~{}",
+                infer_align_bytes(field.offset_bytes, size_bytes)
+            )),
+        }
+    }
+
     fn get_struct_structured(sym_info: &DerivedSymbolInfo) -> Option<AnalysisStructured> {
         let structured = sym_info.get_structured()?;
 
@@ -1001,6 +1690,7 @@ impl ClassMap {
         while let Some(class_id) = pending_ids.pop_front() {
             let sym_info = self.stt.node_set.get(&class_id);
             let depth = sym_info.depth;
+            let class_sym = sym_info.symbol;
             let Some(structured) = Self::get_struct_structured(sym_info) else {
                 continue;
             };
@@ -1009,7 +1699,7 @@ impl ClassMap {
                 let (super_id, _) = self
                     .stt
                     .node_set
-                    .ensure_symbol(&super_info.sym, server, depth + 1)
+                    .ensure_symbol_via(&super_info.sym, server, depth + 1, class_sym, "super")
                     .await?;
                 pending_ids.push_back(super_id.clone());
             }
@@ -1026,6 +1716,31 @@ impl ClassMap {
         Ok(root_sym_id)
     }
 
+    /// When the root symbol has no layout information, emit a row for it that
+    /// says so and why, rather than an empty table.
+    fn push_no_layout_placeholder(&mut self) {
+        let Some(root_id) = &self.root_class_id else {
+            return;
+        };
+        let root_info = self.stt.node_set.get(root_id);
+        let mut class_node = SymbolTreeTableNode::new(
+            root_info.get_pretty().to_string(),
+            root_info.symbol.to_string(),
+        );
+        let reason = if !self.capabilities.crossref {
+            "this index has no crossref database"
+        } else if !self.capabilities.structured {
+            "this index has no structured analysis data, likely because it predates it"
+        } else {
+            "the indexer didn't record the layout of this type"
+        };
+        class_node.items.push(SymbolTreeTableItem::Warning(format!(
+            "(No layout information is available because {})",
+            reason
+        )));
+        self.stt.rows.push(class_node);
+    }
+
     fn generate_tables(mut self, tables: &mut Vec<SymbolTreeTable>) {
         for (_, platforms) in &self.groups {
             let label = platforms
@@ -1037,6 +1752,13 @@ impl ClassMap {
             self.stt.platforms.push(label);
         }
 
+        if self.class_list.is_empty() {
+            self.push_no_layout_placeholder();
+        }
+
+        // When regrouping, the rows along with the columns they keep.
+        let mut regrouped_rows = vec![];
+
         for traversal_id in &self.class_list {
             let cls = self.class_map.get(traversal_id).unwrap();
 
@@ -1046,10 +1768,29 @@ impl ClassMap {
                 format!(
                     "{}{}",
                     cls.name,
-                    if !is_root { " (base class)" } else { "" },
+                    if cls.is_external {
+                        " (external base class)"
+                    } else if !is_root {
+                        " (base class)"
+                    } else {
+                        ""
+                    },
                 ),
                 self.stt.node_set.get(&cls.id).symbol.to_string(),
             );
+            class_node.provenance = self.stt.node_set.get(&cls.id).provenance.clone();
+            class_node.sizes = self.class_sizes(cls);
+            class_node.layout = cls.repr.as_ref().map(|repr| format!("repr({})", repr));
+            if let Some(annotation) = self.annotations.get(&self.stt.node_set.get(&cls.id).symbol) {
+                class_node.tags = annotation.tags.iter().cloned().collect();
+            }
+
+            let has_defined_layout = cls.has_defined_layout();
+            if !has_defined_layout {
+                class_node.items.push(SymbolTreeTableItem::Warning(
+                    "(This type has the default Rust repr, so the compiler may reorder its fields and holes are not shown)".to_string(),
+                ));
+            }
 
             if self.has_unsupported_multiple_inheritance && is_root {
                 class_node.items.push(
@@ -1072,7 +1813,7 @@ impl ClassMap {
                     }
                 }
 
-                if has_hole {
+                if has_hole && has_defined_layout {
                     let mut holes = vec![];
 
                     for maybe_field in field_variants {
@@ -1085,9 +1826,8 @@ impl ClassMap {
                                 }
 
                                 holes.push(Some(format!(
-                                    "{} byte{} hole{}",
-                                    hole_bytes,
-                                    if hole_bytes > 1 { "s" } else { "" },
+                                    "{} hole{}",
+                                    self.size_format.format_gap(hole_bytes),
                                     if field.hole_after_base {
                                         " after base class"
                                     } else {
@@ -1143,6 +1883,10 @@ impl ClassMap {
                             if !path_and_range_set.contains(&key) {
                                 path_and_range_set.insert(key);
 
+                                if self.emit_spans && !field.def_path.is_empty() {
+                                    field_item.spans.push(self.field_span(field));
+                                }
+
                                 if let Some(lines) = self.file_lines.get(&field.def_path) {
                                     for lineno in field.start_lineno..=field.end_lineno {
                                         if lineno == 0 {
@@ -1153,6 +1897,10 @@ impl ClassMap {
                                             field_item.lines.push(line.clone());
                                         }
                                     }
+                                } else if !field.def_path.is_empty() && !self.capabilities.source {
+                                    field_item
+                                        .lines
+                                        .push("(source unavailable in this index)".to_string());
                                 }
                             }
 
@@ -1160,25 +1908,22 @@ impl ClassMap {
                                 field_item.offset_and_size.push(Some(
                                     SymbolTreeTableFieldOffsetAndSize::new(
                                         format!(
-                                            "@ {:#x} + {} bit{}",
-                                            field.offset_bytes,
-                                            pos.begin,
-                                            if pos.begin > 1 { "s" } else { "" }
-                                        ),
-                                        format!(
-                                            "{} bit{}",
-                                            pos.width,
-                                            if pos.width > 1 { "s" } else { "" }
+                                            "{} + {}",
+                                            self.offset_format.format(field.offset_bytes),
+                                            format_count(pos.begin as u64, "bit", "bits")
                                         ),
+                                        format_count(pos.width as u64, "bit", "bits"),
                                     ),
                                 ))
                             } else {
-                                field_item.offset_and_size.push(Some(
-                                    SymbolTreeTableFieldOffsetAndSize::new(
-                                        format!("@ {:#x}", field.offset_bytes,),
-                                        format!("{}", field.size_bytes.unwrap_or(0),),
-                                    ),
-                                ));
+                                let mut offset_and_size = SymbolTreeTableFieldOffsetAndSize::new(
+                                    self.offset_format.format(field.offset_bytes),
+                                    self.size_format.format_field(field.size_bytes.unwrap_or(0)),
+                                );
+                                if self.emit_align {
+                                    offset_and_size.align = self.field_align(field);
+                                }
+                                field_item.offset_and_size.push(Some(offset_and_size));
                             }
                         }
                         None => {
@@ -1201,7 +1946,7 @@ impl ClassMap {
                     }
                 }
 
-                if has_end_padding {
+                if has_end_padding && has_defined_layout {
                     let mut end_paddings = vec![];
 
                     for maybe_field in field_variants {
@@ -1214,9 +1959,8 @@ impl ClassMap {
                                 }
 
                                 end_paddings.push(Some(format!(
-                                    "{} byte{} padding",
-                                    end_padding_bytes,
-                                    if end_padding_bytes > 1 { "s" } else { "" }
+                                    "{} padding",
+                                    self.size_format.format_gap(end_padding_bytes)
                                 )));
                             }
                             None => {
@@ -1233,10 +1977,38 @@ impl ClassMap {
                 }
             }
 
-            self.stt.rows.push(class_node);
+            for method in &cls.methods {
+                class_node
+                    .items
+                    .push(SymbolTreeTableItem::Method(method.clone()));
+            }
+
+            if self.regroup_per_class {
+                regrouped_rows.push((self.class_column_groups(cls), class_node));
+            } else {
+                self.stt.rows.push(class_node);
+            }
+        }
+
+        if regrouped_rows.is_empty() {
+            tables.push(self.stt);
+            return;
         }
 
-        tables.push(self.stt);
+        // The first table keeps the node set and extra symbols, which is fine
+        // because consumers union them across all of the tables.
+        let group_labels = std::mem::take(&mut self.stt.platforms);
+        let mut first_table = Some(self.stt);
+        for (column_groups, class_node) in regrouped_rows {
+            let mut stt = first_table.take().unwrap_or_else(SymbolTreeTable::new);
+            stt.platforms = column_groups
+                .iter()
+                .map(|column_group| column_group.iter().map(|i| &group_labels[*i]).join(" "))
+                .collect();
+            let kept: Vec<usize> = column_groups.iter().map(|group| group[0]).collect();
+            stt.rows.push(select_columns(class_node, &kept));
+            tables.push(stt);
+        }
     }
 }
 
@@ -1250,10 +2022,16 @@ impl PipelineCommand for FormatSymbolsCommand {
         let cil = match input {
             PipelineValues::SymbolCrossrefInfoList(cil) => cil,
             _ => {
-                return Err(ServerError::StickyProblem(ErrorDetails {
-                    layer: ErrorLayer::ConfigLayer,
-                    message: "format-symbols needs a CrossrefInfoList".to_string(),
-                }));
+                return Err(ServerError::StickyProblem(
+                    ErrorDetails::new(
+                        ErrorKind::BadQuery,
+                        ErrorLayer::ConfigLayer,
+                        "format-symbols needs a CrossrefInfoList".to_string(),
+                    )
+                    .with_hint(
+                        "Use `explain` to see what each pipeline stage consumes and produces.",
+                    ),
+                ));
             }
         };
 
@@ -1261,8 +2039,33 @@ impl PipelineCommand for FormatSymbolsCommand {
             SymbolFormatMode::FieldLayout => {
                 let mut tables = vec![];
 
+                // The annotation store is optional and only available locally,
+                // so we just go without tags if we can't load it.
+                let annotations = server
+                    .translate_path(
+                        SearchfoxIndexRoot::DeploymentState,
+                        SYMBOL_ANNOTATIONS_FILENAME,
+                    )
+                    .ok()
+                    .and_then(|path| SymbolAnnotations::load(&path).ok().flatten())
+                    .unwrap_or_default();
+                let annotations = Arc::new(annotations);
+
                 for nom_sym_info in cil.symbol_crossref_infos {
                     let mut map = ClassMap::new();
+                    map.stt.node_set.record_provenance = self.args.provenance;
+                    map.emit_spans = self.args.spans;
+                    map.include_methods = self.args.include_methods;
+                    map.regroup_per_class = self.args.regroup_per_class;
+                    map.emit_align = self
+                        .args
+                        .show_cols
+                        .as_ref()
+                        .map_or(false, |cols| cols.split(",").any(|col| col == "align"));
+                    map.offset_format = self.args.offsets;
+                    map.size_format = self.args.sizes;
+                    map.capabilities = server.capabilities();
+                    map.annotations = annotations.clone();
                     map.populate(nom_sym_info, server).await?;
                     map.generate_tables(&mut tables);
                 }
@@ -1270,7 +2073,7 @@ impl PipelineCommand for FormatSymbolsCommand {
                 let mut class_names = vec![];
                 if let Some(cols) = &self.args.show_cols {
                     for col in cols.split(",") {
-                        if col == "type" {
+                        if col == "type" || col == "align" {
                             class_names.push(format!("show-{}", col));
                         }
                     }
@@ -1294,6 +2097,101 @@ impl PipelineCommand for FormatSymbolsCommand {
                     class_name,
                 }))
             }
+            SymbolFormatMode::IdlImpls => {
+                let matrix = IdlImplMatrix {
+                    max_impls: self.args.max_impls,
+                };
+                let mut tables = vec![];
+                for nom_sym_info in cil.symbol_crossref_infos {
+                    if let Some(stt) = matrix.build(server, &nom_sym_info.symbol).await? {
+                        tables.push(stt);
+                    }
+                }
+
+                Ok(PipelineValues::SymbolTreeTableList(SymbolTreeTableList {
+                    tables,
+                    class_name: None,
+                }))
+            }
+        }
+    }
+}
+
+#[test]
+fn test_class_field_order_is_stable() {
+    use super::symbol_graph::SymbolGraphNodeSet;
+    use serde_json::json;
+    use ustr::ustr;
+
+    let mut node_set = SymbolGraphNodeSet::new();
+    let mut add_node = |sym: &str| {
+        let (id, _) = node_set.add_symbol(DerivedSymbolInfo::new(ustr(sym), json!({}), 0));
+        id
+    };
+    let class_id = add_node("T_Class");
+    // Fields whose line, offset, and groups all match, as can happen for
+    // zero-sized fields or members of anonymous unions declared on one line.
+    let fields: Vec<(FieldId, &str)> = vec![
+        (add_node("F_<T_Class>_b"), "Class::b"),
+        (add_node("F_<T_Class>_a2"), "Class::a"),
+        (add_node("F_<T_Class>_a1"), "Class::a"),
+        (add_node("F_<T_Class>_c"), "Class::c"),
+    ];
+    let groups = vec![(PlatformGroupId(0), vec![PlatformId::all()])];
+
+    let merged_order = |insert_order: &[usize]| -> Vec<(Option<FieldId>, String)> {
+        let mut cls = Class::new(class_id.clone(), "Class".to_string());
+        for &i in insert_order {
+            let (field_id, pretty) = &fields[i];
+            let mut field = Field::new_external_base(class_id.clone(), TraversalId(0), 8, Some(0));
+            field.field_id = Some(field_id.clone());
+            field.pretty = pretty.to_string();
+            field.def_path = "class.h".to_string();
+            field.start_lineno = 10;
+            cls.add_field(PlatformGroupId(0), field);
         }
+        cls.finish_populating(&groups);
+        cls.merged_fields
+            .iter()
+            .map(|variants| {
+                let field = variants[0].as_ref().unwrap();
+                (field.field_id.clone(), field.pretty.clone())
+            })
+            .collect()
+    };
+
+    let expected = merged_order(&[0, 1, 2, 3]);
+    let pretties: Vec<&str> = expected.iter().map(|(_, p)| p.as_str()).collect();
+    assert_eq!(
+        pretties,
+        vec!["Class::a", "Class::a", "Class::b", "Class::c"]
+    );
+    // Same pretty name falls back to the symbol id, which is assigned in
+    // insertion order into the node set.
+    assert!(expected[0].0 == Some(fields[1].0.clone()));
+
+    // Each Class gets a freshly seeded HashMap, so repeat with different
+    // insertion orders to shake out any dependency on iteration order.
+    for _ in 0..16 {
+        assert!(merged_order(&[3, 2, 1, 0]) == expected);
+        assert!(merged_order(&[2, 0, 3, 1]) == expected);
     }
 }
+
+#[test]
+fn test_infer_align_bytes() {
+    assert_eq!(natural_align_bytes(0), 1);
+    assert_eq!(natural_align_bytes(2), 2);
+    assert_eq!(natural_align_bytes(12), 4);
+    assert_eq!(natural_align_bytes(64), 8);
+
+    // An int at offset 4.
+    assert_eq!(infer_align_bytes(4, 4), 4);
+    // A pointer at offset 0 or 16.
+    assert_eq!(infer_align_bytes(0, 8), 8);
+    assert_eq!(infer_align_bytes(16, 8), 8);
+    // A packed 8 byte field at offset 2 can't be more than 2-aligned.
+    assert_eq!(infer_align_bytes(2, 8), 2);
+    // A char array of odd length.
+    assert_eq!(infer_align_bytes(8, 3), 1);
+}
@@ -0,0 +1,68 @@
+use async_trait::async_trait;
+use clap::{Args, ValueEnum};
+
+use super::interface::{JsonValue, PipelineCommand, PipelineValues, TextFile};
+use crate::abstract_server::{
+    AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum TableFormat {
+    /// A SymbolTreeTableList for display in the web UI or `tui`.
+    Tree,
+    /// CSV with a header row, as a TextFile.
+    Csv,
+    /// A JSON array with one object per row.
+    Json,
+}
+
+/// Convert a DataTable produced by an analytic command into a presentation
+/// format.
+#[derive(Debug, Args)]
+pub struct FormatTable {
+    #[clap(long, value_parser, value_enum, default_value = "tree")]
+    format: TableFormat,
+}
+
+#[derive(Debug)]
+pub struct FormatTableCommand {
+    pub args: FormatTable,
+}
+
+#[async_trait]
+impl PipelineCommand for FormatTableCommand {
+    async fn execute(
+        &self,
+        _server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let table = match input {
+            PipelineValues::DataTable(dt) => dt,
+            _ => {
+                return Err(ServerError::StickyProblem(
+                    ErrorDetails::new(
+                        ErrorKind::BadQuery,
+                        ErrorLayer::ConfigLayer,
+                        "format-table needs a DataTable".to_string(),
+                    )
+                    .with_hint(
+                        "Use `explain` to see what each pipeline stage consumes and produces.",
+                    ),
+                ));
+            }
+        };
+
+        Ok(match self.args.format {
+            TableFormat::Tree => {
+                PipelineValues::SymbolTreeTableList(table.to_symbol_tree_table_list())
+            }
+            TableFormat::Csv => PipelineValues::TextFile(TextFile {
+                mime_type: "text/csv".to_string(),
+                contents: table.to_csv(),
+            }),
+            TableFormat::Json => PipelineValues::JsonValue(JsonValue {
+                value: table.to_json_records(),
+            }),
+        })
+    }
+}
@@ -5,7 +5,9 @@ use super::interface::{
     PipelineJunctionCommand, PipelineValues, SymbolCrossrefInfoList, SymbolMetaFlags,
 };
 
-use crate::abstract_server::{AbstractServer, ErrorDetails, ErrorLayer, Result, ServerError};
+use crate::abstract_server::{
+    AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError,
+};
 
 /// Experimental junction enabling multiple query chains that output
 /// crossref-lookup to be unified into a single SymbolCrossrefInfoList but with
@@ -53,10 +55,16 @@ impl PipelineJunctionCommand for FuseCrossrefsCommand {
                     fused_unknown.append(&mut scil.unknown_symbols);
                 }
                 _ => {
-                    return Err(ServerError::StickyProblem(ErrorDetails {
-                        layer: ErrorLayer::ConfigLayer,
-                        message: "fuse-crossrefs got something weird".to_string(),
-                    }));
+                    return Err(ServerError::StickyProblem(
+                        ErrorDetails::new(
+                            ErrorKind::BadQuery,
+                            ErrorLayer::ConfigLayer,
+                            "fuse-crossrefs got something weird".to_string(),
+                        )
+                        .with_hint(
+                            "Use `explain` to see what each pipeline stage consumes and produces.",
+                        ),
+                    ));
                 }
             }
         }
@@ -65,6 +73,7 @@ impl PipelineJunctionCommand for FuseCrossrefsCommand {
             SymbolCrossrefInfoList {
                 symbol_crossref_infos: fused_crossref,
                 unknown_symbols: fused_unknown,
+                page: None,
             },
         ))
     }
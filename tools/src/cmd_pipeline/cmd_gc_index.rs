@@ -0,0 +1,212 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+
+use async_trait::async_trait;
+use clap::{Args, ValueEnum};
+use serde_json::json;
+use tracing::warn;
+use walkdir::WalkDir;
+
+use super::interface::{JsonValue, PipelineCommand, PipelineValues};
+use crate::abstract_server::{
+    AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, SearchfoxIndexRoot, ServerError,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum GcRoot {
+    /// Formatted HTML for files under `file/`.
+    File,
+    /// Analysis records under `analysis/`.
+    Analysis,
+    /// File descriptions under `description/`.
+    Description,
+    /// Per-file info sidecars under `detailed-per-file-info/`.
+    DetailedPerFileInfo,
+    /// Directory listings under `dir/`.
+    Dir,
+}
+
+impl GcRoot {
+    fn dir_name(&self) -> &'static str {
+        match self {
+            GcRoot::File => "file",
+            GcRoot::Analysis => "analysis",
+            GcRoot::Description => "description",
+            GcRoot::DetailedPerFileInfo => "detailed-per-file-info",
+            GcRoot::Dir => "dir",
+        }
+    }
+
+    /// Determine whether the artifact at `rel_path` under this root is still
+    /// referenced by the file list.
+    fn is_referenced(
+        &self,
+        rel_path: &str,
+        files: &HashSet<String>,
+        dirs: &HashSet<String>,
+    ) -> bool {
+        match self {
+            // These are normally gzipped, but may not be yet if indexing is
+            // still in progress.
+            GcRoot::File | GcRoot::Analysis => {
+                files.contains(rel_path)
+                    || rel_path
+                        .strip_suffix(".gz")
+                        .map_or(false, |path| files.contains(path))
+            }
+            GcRoot::Description | GcRoot::DetailedPerFileInfo => files.contains(rel_path),
+            GcRoot::Dir => {
+                let listing = rel_path.strip_suffix(".gz").unwrap_or(rel_path);
+                match listing.strip_suffix("index.html") {
+                    Some(dir) => dirs.contains(dir.trim_end_matches('/')),
+                    // We don't know what this is, so leave it alone.
+                    None => true,
+                }
+            }
+        }
+    }
+}
+
+/// Scan the index output directories for artifacts that are no longer
+/// referenced by the current file list (ex: formatted HTML for files that have
+/// since been deleted from the tree), which otherwise accumulate in
+/// long-lived deployments that are updated in place.  By default this is a
+/// dry run that only lists what would be deleted; pass `--delete` to actually
+/// delete them.  Post-index hook runs are not considered because hooks prune
+/// their own runs per their `retention`.
+#[derive(Debug, Args)]
+pub struct GcIndex {
+    /// The output directories to scan; may be specified multiple times.
+    /// Defaults to all of them.
+    #[clap(long, value_parser, value_enum)]
+    root: Vec<GcRoot>,
+
+    /// Actually delete the orphaned artifacts.
+    #[clap(long, value_parser)]
+    delete: bool,
+
+    /// The maximum number of orphaned artifacts to list in the output.
+    #[clap(long, value_parser, default_value = "100")]
+    max_listed: usize,
+}
+
+#[derive(Debug)]
+pub struct GcIndexCommand {
+    pub args: GcIndex,
+}
+
+const FILE_MATCH_LIMIT: usize = 2_000_000;
+
+const ALL_ROOTS: [GcRoot; 5] = [
+    GcRoot::File,
+    GcRoot::Analysis,
+    GcRoot::Description,
+    GcRoot::DetailedPerFileInfo,
+    GcRoot::Dir,
+];
+
+#[async_trait]
+impl PipelineCommand for GcIndexCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        _input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let mut files = HashSet::new();
+        // The root directory is never in the file list.
+        let mut dirs = HashSet::from(["".to_string()]);
+        let file_matches = server
+            .search_files("", true, FILE_MATCH_LIMIT)
+            .await?
+            .file_matches;
+        // The file list is cut off at the limit, and anything past it would
+        // look like garbage.
+        if file_matches.len() >= FILE_MATCH_LIMIT {
+            return Err(ServerError::StickyProblem(ErrorDetails::new(
+                ErrorKind::Internal,
+                ErrorLayer::DataLayer,
+                format!(
+                    "gc-index refusing to run with a file list truncated at {} entries",
+                    FILE_MATCH_LIMIT
+                ),
+            )));
+        }
+        for fm in file_matches {
+            if fm.concise.is_dir {
+                dirs.insert(fm.path.to_string());
+            } else {
+                files.insert(fm.path.to_string());
+            }
+        }
+        // An empty file list almost certainly means something is wrong with
+        // the index rather than that every artifact is garbage.
+        if files.is_empty() {
+            return Err(ServerError::StickyProblem(ErrorDetails::new(
+                ErrorKind::Internal,
+                ErrorLayer::DataLayer,
+                "gc-index refusing to run with an empty file list".to_string(),
+            )));
+        }
+
+        let roots = if self.args.root.is_empty() {
+            &ALL_ROOTS[..]
+        } else {
+            &self.args.root[..]
+        };
+
+        let mut listed = vec![];
+        let mut by_root = BTreeMap::new();
+        let (mut total_orphans, mut total_bytes) = (0u64, 0u64);
+        for root in roots {
+            let root_path =
+                server.translate_path(SearchfoxIndexRoot::IndexRoot, root.dir_name())?;
+            let (mut orphans, mut bytes) = (0u64, 0u64);
+            for entry in WalkDir::new(&root_path).into_iter().filter_map(|e| e.ok()) {
+                if !entry.file_type().is_file() {
+                    continue;
+                }
+                let Some(rel_path) = entry
+                    .path()
+                    .strip_prefix(&root_path)
+                    .ok()
+                    .and_then(|p| p.to_str())
+                else {
+                    continue;
+                };
+                if root.is_referenced(rel_path, &files, &dirs) {
+                    continue;
+                }
+
+                orphans += 1;
+                bytes += entry.metadata().map(|m| m.len()).unwrap_or_default();
+                if listed.len() < self.args.max_listed {
+                    listed.push(format!("{}/{}", root.dir_name(), rel_path));
+                }
+                if self.args.delete {
+                    if let Err(e) = fs::remove_file(entry.path()) {
+                        warn!("gc-index failed to delete {:?}: {}", entry.path(), e);
+                    }
+                }
+            }
+            by_root.insert(
+                root.dir_name(),
+                json!({
+                    "orphans": orphans,
+                    "bytes": bytes,
+                }),
+            );
+            total_orphans += orphans;
+            total_bytes += bytes;
+        }
+
+        Ok(PipelineValues::JsonValue(JsonValue {
+            value: json!({
+                "deleted": self.args.delete,
+                "orphans": total_orphans,
+                "bytes": total_bytes,
+                "roots": by_root,
+                "listed": listed,
+            }),
+        }))
+    }
+}
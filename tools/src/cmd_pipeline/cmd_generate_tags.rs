@@ -0,0 +1,248 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use async_trait::async_trait;
+use clap::{Args, ValueEnum};
+use tokio_stream::StreamExt;
+
+use super::{
+    interface::{PipelineCommand, PipelineValues, TextFile},
+    path_filter::PathFilterArgs,
+    transforms::path_glob_transform,
+};
+use crate::{
+    abstract_server::{AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError},
+    file_format::analysis::parse_location,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum TagsFormat {
+    /// An exuberant-ctags compatible `tags` file for vim and friends.
+    Ctags,
+    /// An etags `TAGS` file for emacs.
+    Etags,
+}
+
+/// Generate a tags file for a subset of the tree from the definitions in the
+/// analysis data, giving vim and emacs users offline navigation that is as
+/// precise as searchfox's own rather than the guesswork of regex-based
+/// taggers.  The tag names are the unqualified pretty names of the defined
+/// symbols, and ctags entries carry the kind of the symbol from its structured
+/// record.
+///
+/// Operates on the FileMatches from `search-files` if piped any, otherwise on
+/// all of the files matching `path`.  Produces a TextFile.
+#[derive(Debug, Args)]
+pub struct GenerateTags {
+    /// Path glob of the files to generate tags for when not given FileMatches.
+    #[clap(value_parser)]
+    path: Option<String>,
+
+    #[clap(long, value_parser, value_enum, default_value = "ctags")]
+    format: TagsFormat,
+
+    /// The maximum number of files to generate tags for.
+    #[clap(long, value_parser, default_value = "10000")]
+    limit: usize,
+
+    #[clap(flatten)]
+    path_filter: PathFilterArgs,
+}
+
+#[derive(Debug)]
+pub struct GenerateTagsCommand {
+    pub args: GenerateTags,
+}
+
+struct Tag {
+    name: String,
+    lineno: u32,
+    col_end: u32,
+    kind: Option<char>,
+}
+
+/// The unqualified name of a symbol from its pretty name.
+fn tag_name(pretty: &str) -> &str {
+    match pretty.rsplit_once("::") {
+        Some((_, name)) => name,
+        None => pretty.rsplit('.').next().unwrap_or(pretty),
+    }
+}
+
+/// Map a structured record kind onto the single letter kinds that
+/// exuberant-ctags uses for C/C++.
+fn ctags_kind(kind: &str) -> Option<char> {
+    Some(match kind {
+        "class" => 'c',
+        "struct" => 's',
+        "union" => 'u',
+        "enum" => 'g',
+        "enumConstant" => 'e',
+        "function" | "method" => 'f',
+        "field" => 'm',
+        "variable" => 'v',
+        _ => return None,
+    })
+}
+
+fn render_ctags(tags: &BTreeMap<String, Vec<Tag>>) -> String {
+    let mut lines = BTreeSet::new();
+    for (path, file_tags) in tags {
+        for tag in file_tags {
+            let mut line = format!("{}\t{}\t{};\"", tag.name, path, tag.lineno);
+            if let Some(kind) = tag.kind {
+                line.push('\t');
+                line.push(kind);
+            }
+            line.push_str(&format!("\tline:{}", tag.lineno));
+            lines.insert(line);
+        }
+    }
+
+    let mut contents = String::from(
+        "!_TAG_FILE_FORMAT\t2\t/extended format/\n\
+         !_TAG_FILE_SORTED\t1\t/0=unsorted, 1=sorted, 2=foldcase/\n\
+         !_TAG_PROGRAM_NAME\tsearchfox-tool\t//\n",
+    );
+    for line in lines {
+        contents.push_str(&line);
+        contents.push('\n');
+    }
+    contents
+}
+
+/// Render an etags section for a file.  etags wants the text of the line up to
+/// the end of the tag along with the byte offset of the line, so this needs
+/// the file's source.
+fn render_etags_section(path: &str, source: &str, tags: &[Tag]) -> String {
+    let mut offsets = vec![];
+    let mut offset = 0;
+    for line in source.split_inclusive('\n') {
+        offsets.push((offset, line.trim_end_matches('\n')));
+        offset += line.len();
+    }
+
+    let mut entries = String::new();
+    for tag in tags {
+        let Some((offset, line)) = offsets.get(tag.lineno as usize - 1) else {
+            continue;
+        };
+        let prefix: String = line.chars().take(tag.col_end as usize).collect();
+        entries.push_str(&format!(
+            "{}\x7f{}\x01{},{}\n",
+            prefix, tag.name, tag.lineno, offset
+        ));
+    }
+    format!("\x0c\n{},{}\n{}", path, entries.len(), entries)
+}
+
+#[async_trait]
+impl PipelineCommand for GenerateTagsCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let mut matches = match input {
+            PipelineValues::FileMatches(fm) => fm,
+            PipelineValues::Void => {
+                let pathre = match &self.args.path {
+                    Some(path) => path_glob_transform(path),
+                    None => "".to_string(),
+                };
+                server.search_files(&pathre, false, self.args.limit).await?
+            }
+            _ => {
+                return Err(ServerError::StickyProblem(
+                    ErrorDetails::new(
+                        ErrorKind::BadQuery,
+                        ErrorLayer::ConfigLayer,
+                        "generate-tags needs FileMatches or no input".to_string(),
+                    )
+                    .with_hint(
+                        "Use `explain` to see what each pipeline stage consumes and produces.",
+                    ),
+                ));
+            }
+        };
+
+        let path_filter = self
+            .args
+            .path_filter
+            .compile(&server.tree_info()?.path_sets)?;
+        if !path_filter.is_empty() {
+            matches
+                .file_matches
+                .retain(|fm| path_filter.matches_classified(&fm.path, fm.concise.classification));
+        }
+
+        let mut tags = BTreeMap::new();
+        for fm in matches.file_matches.iter().take(self.args.limit) {
+            if fm.concise.is_dir {
+                continue;
+            }
+            // Not every file has analysis data, which is fine.
+            let Ok(mut records) = server.fetch_raw_analysis(&fm.path).await else {
+                continue;
+            };
+
+            // The structured records aren't necessarily before the target
+            // records in the analysis file, so gather the kinds first.
+            let mut defs = vec![];
+            let mut kinds = BTreeMap::new();
+            while let Some(val) = records.next().await {
+                if val["structured"].is_number() {
+                    if let (Some(sym), Some(kind)) = (val["sym"].as_str(), val["kind"].as_str()) {
+                        kinds.insert(sym.to_string(), kind.to_string());
+                    }
+                    continue;
+                }
+                if !val["target"].is_number() || val["kind"].as_str() != Some("def") {
+                    continue;
+                }
+                let (Some(loc), Some(sym), Some(pretty)) = (
+                    val["loc"].as_str(),
+                    val["sym"].as_str(),
+                    val["pretty"].as_str(),
+                ) else {
+                    continue;
+                };
+                defs.push((parse_location(loc), sym.to_string(), pretty.to_string()));
+            }
+
+            let mut file_tags = vec![];
+            let mut seen = BTreeSet::new();
+            for (loc, sym, pretty) in defs {
+                let name = tag_name(&pretty);
+                if name.is_empty() || !seen.insert((name.to_string(), loc.lineno)) {
+                    continue;
+                }
+                file_tags.push(Tag {
+                    name: name.to_string(),
+                    lineno: loc.lineno,
+                    col_end: loc.col_end,
+                    kind: kinds.get(&sym).and_then(|kind| ctags_kind(kind)),
+                });
+            }
+            if !file_tags.is_empty() {
+                tags.insert(fm.path.clone(), file_tags);
+            }
+        }
+
+        let contents = match self.args.format {
+            TagsFormat::Ctags => render_ctags(&tags),
+            TagsFormat::Etags => {
+                let mut contents = String::new();
+                for (path, file_tags) in &tags {
+                    let source = server.fetch_raw_source(path).await?;
+                    contents.push_str(&render_etags_section(path, &source, file_tags));
+                }
+                contents
+            }
+        };
+
+        Ok(PipelineValues::TextFile(TextFile {
+            mime_type: "text/plain".to_string(),
+            contents,
+        }))
+    }
+}
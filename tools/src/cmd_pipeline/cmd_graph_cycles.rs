@@ -0,0 +1,329 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use async_trait::async_trait;
+use clap::{Args, ValueEnum};
+use petgraph::{algo::tarjan_scc, graph::DiGraph};
+use ustr::ustr;
+
+use super::{
+    data_table::{DataCell, DataColumnType, DataTable},
+    interface::{PipelineCommand, PipelineValues, TextFile},
+    symbol_graph::{NamedSymbolGraph, SymbolGraphCollection, SymbolGraphNodeId},
+};
+use crate::abstract_server::{
+    AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum CycleLevel {
+    /// Report clusters of mutually recursive symbols.
+    Symbol,
+    /// Report dependency cycles between directories, where a directory depends
+    /// on another if one of its symbols has an edge to a symbol in the other.
+    Directory,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum CycleFormat {
+    /// A DataTable with one row per cycle member; see `format-table`.
+    Table,
+    /// A graphviz DOT TextFile with a cluster per cycle.
+    Dot,
+}
+
+/// Compute the strongly connected components of the graphs (ex: call graphs
+/// from `traverse`) in a SymbolGraphCollection and report the ones that
+/// involve a cycle, either as clusters of mutually recursive symbols or as
+/// dependency cycles between directories.
+#[derive(Debug, Args)]
+pub struct GraphCycles {
+    /// Only consider symbols defined under this path prefix.
+    #[clap(long, value_parser)]
+    path_prefix: Option<String>,
+
+    #[clap(long, value_parser, value_enum, default_value = "symbol")]
+    level: CycleLevel,
+
+    /// For `--level=directory`, how many leading path components identify a
+    /// directory, so that ex: `--dir-depth=2` treats everything under
+    /// "dom/media" as one module.  By default we use each symbol's immediate
+    /// parent directory.
+    #[clap(long, value_parser)]
+    dir_depth: Option<usize>,
+
+    #[clap(long, value_parser, value_enum, default_value = "table")]
+    format: CycleFormat,
+}
+
+#[derive(Debug)]
+pub struct GraphCyclesCommand {
+    pub args: GraphCycles,
+}
+
+/// A component with a cycle; members are symbols or directories depending on
+/// the level.
+struct Cycle {
+    members: Vec<CycleMember>,
+    edges: BTreeSet<(String, String)>,
+}
+
+struct CycleMember {
+    /// The symbol or directory.
+    id: String,
+    /// The pretty name of the symbol; the directory again for directories.
+    label: String,
+    path: Option<String>,
+}
+
+fn dot_quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+impl GraphCyclesCommand {
+    fn directory_of(&self, path: &str) -> String {
+        let components: Vec<&str> = path.split('/').collect();
+        let dir_len = match self.args.dir_depth {
+            Some(depth) => depth.min(components.len() - 1),
+            None => components.len() - 1,
+        };
+        components[..dir_len].join("/")
+    }
+
+    /// Find the symbol-level cycles in a graph restricted to the path prefix.
+    fn symbol_cycles(&self, sgc: &SymbolGraphCollection, graph: &NamedSymbolGraph) -> Vec<Cycle> {
+        let in_scope = |id: &SymbolGraphNodeId| match &self.args.path_prefix {
+            Some(prefix) => sgc
+                .node_set
+                .get(id)
+                .get_def_path()
+                .map_or(false, |path| path.starts_with(prefix.as_str())),
+            None => true,
+        };
+
+        let mut scoped = NamedSymbolGraph::new(graph.name.clone());
+        for (source, target, edge) in graph.list_edges() {
+            if in_scope(&source) && in_scope(&target) {
+                scoped.ensure_edge(source, target, edge);
+            }
+        }
+
+        scoped
+            .cyclic_components()
+            .into_iter()
+            .map(|comp| {
+                let comp_syms: BTreeSet<String> = comp
+                    .iter()
+                    .map(|id| sgc.node_set.get(id).symbol.to_string())
+                    .collect();
+                let edges = scoped
+                    .list_edges()
+                    .into_iter()
+                    .map(|(source, target, _)| {
+                        (
+                            sgc.node_set.get(&source).symbol.to_string(),
+                            sgc.node_set.get(&target).symbol.to_string(),
+                        )
+                    })
+                    .filter(|(source, target)| {
+                        comp_syms.contains(source) && comp_syms.contains(target)
+                    })
+                    .collect();
+                // Tarjan's algorithm gives us the members in traversal order,
+                // so sort them for stable output.
+                let mut members: Vec<CycleMember> = comp
+                    .iter()
+                    .map(|id| {
+                        let info = sgc.node_set.get(id);
+                        CycleMember {
+                            id: info.symbol.to_string(),
+                            label: info.get_pretty().to_string(),
+                            path: info.get_def_path().cloned(),
+                        }
+                    })
+                    .collect();
+                members.sort_by(|a, b| a.id.cmp(&b.id));
+                Cycle { members, edges }
+            })
+            .collect()
+    }
+
+    /// Find the cycles in the graph of dependencies between directories induced
+    /// by the graph's edges.
+    fn directory_cycles(
+        &self,
+        sgc: &SymbolGraphCollection,
+        graph: &NamedSymbolGraph,
+    ) -> Vec<Cycle> {
+        let mut dir_graph: DiGraph<String, ()> = DiGraph::new();
+        let mut dir_ixs = BTreeMap::new();
+        let mut dir_edges = BTreeSet::new();
+        for (source, target, _) in graph.list_edges() {
+            let (Some(source_path), Some(target_path)) = (
+                sgc.node_set.get(&source).get_def_path(),
+                sgc.node_set.get(&target).get_def_path(),
+            ) else {
+                continue;
+            };
+            if let Some(prefix) = &self.args.path_prefix {
+                if !source_path.starts_with(prefix.as_str())
+                    || !target_path.starts_with(prefix.as_str())
+                {
+                    continue;
+                }
+            }
+            let source_dir = self.directory_of(source_path);
+            let target_dir = self.directory_of(target_path);
+            if source_dir == target_dir {
+                continue;
+            }
+            let mut ix_for = |dir: &String| {
+                *dir_ixs
+                    .entry(dir.clone())
+                    .or_insert_with(|| dir_graph.add_node(dir.clone()))
+            };
+            let source_ix = ix_for(&source_dir);
+            let target_ix = ix_for(&target_dir);
+            dir_graph.update_edge(source_ix, target_ix, ());
+            dir_edges.insert((source_dir, target_dir));
+        }
+
+        tarjan_scc(&dir_graph)
+            .into_iter()
+            .filter(|comp| comp.len() > 1)
+            .map(|comp| {
+                let dirs: BTreeSet<String> = comp.iter().map(|ix| dir_graph[*ix].clone()).collect();
+                let edges = dir_edges
+                    .iter()
+                    .filter(|(source, target)| dirs.contains(source) && dirs.contains(target))
+                    .cloned()
+                    .collect();
+                let members = dirs
+                    .into_iter()
+                    .map(|dir| CycleMember {
+                        id: dir.clone(),
+                        label: dir.clone(),
+                        path: Some(dir),
+                    })
+                    .collect();
+                Cycle { members, edges }
+            })
+            .collect()
+    }
+
+    fn to_table(&self, cycles: &[Cycle]) -> Result<DataTable> {
+        let mut table = match self.args.level {
+            CycleLevel::Symbol => DataTable::new(vec![
+                ("cycle", DataColumnType::Int),
+                ("size", DataColumnType::Int),
+                ("symbol", DataColumnType::SymbolRef),
+                ("pretty", DataColumnType::String),
+                ("path", DataColumnType::Path),
+            ]),
+            CycleLevel::Directory => DataTable::new(vec![
+                ("cycle", DataColumnType::Int),
+                ("size", DataColumnType::Int),
+                ("directory", DataColumnType::Path),
+            ]),
+        };
+        for (i, cycle) in cycles.iter().enumerate() {
+            for member in &cycle.members {
+                let mut row = vec![
+                    DataCell::Int(i as i64),
+                    DataCell::Int(cycle.members.len() as i64),
+                ];
+                match self.args.level {
+                    CycleLevel::Symbol => {
+                        row.push(DataCell::Symbol(ustr(&member.id)));
+                        row.push(DataCell::String(member.label.clone()));
+                        row.push(match &member.path {
+                            Some(path) => DataCell::String(path.clone()),
+                            None => DataCell::Null,
+                        });
+                    }
+                    CycleLevel::Directory => {
+                        row.push(DataCell::String(member.id.clone()));
+                    }
+                }
+                table.push_row(row)?;
+            }
+        }
+        Ok(table)
+    }
+
+    fn to_dot(&self, cycles: &[Cycle]) -> String {
+        let mut dot =
+            "digraph g {\n  node [shape=box, fontname=\"Courier New\", fontsize=10];\n".to_string();
+        for (i, cycle) in cycles.iter().enumerate() {
+            dot.push_str(&format!(
+                "  subgraph cluster_{} {{\n    label={};\n",
+                i,
+                dot_quote(&format!("cycle {} ({} members)", i, cycle.members.len()))
+            ));
+            for member in &cycle.members {
+                dot.push_str(&format!(
+                    "    {} [label={}];\n",
+                    dot_quote(&member.id),
+                    dot_quote(&member.label)
+                ));
+            }
+            dot.push_str("  }\n");
+            for (source, target) in &cycle.edges {
+                dot.push_str(&format!(
+                    "  {} -> {};\n",
+                    dot_quote(source),
+                    dot_quote(target)
+                ));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[async_trait]
+impl PipelineCommand for GraphCyclesCommand {
+    async fn execute(
+        &self,
+        _server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let sgc = match input {
+            PipelineValues::SymbolGraphCollection(sgc) => sgc,
+            _ => {
+                return Err(ServerError::StickyProblem(
+                    ErrorDetails::new(
+                        ErrorKind::BadQuery,
+                        ErrorLayer::ConfigLayer,
+                        "graph-cycles needs a SymbolGraphCollection".to_string(),
+                    )
+                    .with_hint(
+                        "Use `explain` to see what each pipeline stage consumes and produces.",
+                    ),
+                ));
+            }
+        };
+
+        let mut cycles = vec![];
+        for graph in &sgc.graphs {
+            cycles.extend(match self.args.level {
+                CycleLevel::Symbol => self.symbol_cycles(&sgc, graph),
+                CycleLevel::Directory => self.directory_cycles(&sgc, graph),
+            });
+        }
+        // Biggest cycles first since they're the most interesting.
+        cycles.sort_by(|a, b| {
+            b.members
+                .len()
+                .cmp(&a.members.len())
+                .then_with(|| a.members[0].id.cmp(&b.members[0].id))
+        });
+
+        Ok(match self.args.format {
+            CycleFormat::Table => PipelineValues::DataTable(self.to_table(&cycles)?),
+            CycleFormat::Dot => PipelineValues::TextFile(TextFile {
+                mime_type: "text/x-dot".to_string(),
+                contents: self.to_dot(&cycles),
+            }),
+        })
+    }
+}
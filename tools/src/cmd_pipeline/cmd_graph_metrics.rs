@@ -0,0 +1,263 @@
+use std::collections::{hash_map::Entry, BTreeSet, HashMap, VecDeque};
+
+use async_trait::async_trait;
+use clap::{Args, ValueEnum};
+
+use super::{
+    data_table::{DataCell, DataColumnType, DataTable},
+    interface::{PipelineCommand, PipelineValues},
+    symbol_graph::{SymbolGraphCollection, SymbolGraphNodeId},
+};
+use crate::abstract_server::{
+    AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum GraphMetric {
+    FanIn,
+    FanOut,
+    Pagerank,
+    Betweenness,
+}
+
+/// Compute per-symbol metrics over the graphs (ex: from `traverse`) in a
+/// SymbolGraphCollection to find god-classes and chokepoints, producing a
+/// DataTable ranked by the `--sort-by` metric:
+/// - `fan_in` / `fan_out`: the number of distinct symbols with edges to / from
+///   the symbol.
+/// - `pagerank`: the symbol's PageRank, which favors symbols that are depended
+///   upon by other highly depended upon symbols.
+/// - `betweenness`: how many shortest paths between other symbols go through
+///   the symbol, normalized to [0, 1].  This is approximated by sampling
+///   source symbols unless `--betweenness-samples=0`.
+///
+/// All of the graphs in the collection are unioned together, and the metrics
+/// are only as meaningful as the graph is complete, so traverse deeply enough
+/// to capture the region of interest.
+#[derive(Debug, Args)]
+pub struct GraphMetrics {
+    #[clap(long, value_parser, value_enum, default_value = "pagerank")]
+    sort_by: GraphMetric,
+
+    /// Maximum number of rows to emit.  0 means no limit.
+    #[clap(long, value_parser, default_value = "50")]
+    limit: usize,
+
+    /// PageRank damping factor.
+    #[clap(long, value_parser, default_value = "0.85")]
+    damping: f64,
+
+    /// Number of source symbols, spread evenly over the graph, from which to
+    /// approximate betweenness.  0 computes it exactly from every symbol,
+    /// which is quadratic in the size of the graph.
+    #[clap(long, value_parser, default_value = "100")]
+    betweenness_samples: usize,
+}
+
+#[derive(Debug)]
+pub struct GraphMetricsCommand {
+    pub args: GraphMetrics,
+}
+
+/// The union of the collection's graphs as deduplicated adjacency lists over
+/// dense indices.
+struct MetricGraph {
+    nodes: Vec<SymbolGraphNodeId>,
+    outgoing: Vec<Vec<usize>>,
+    incoming: Vec<Vec<usize>>,
+}
+
+impl MetricGraph {
+    fn from_collection(sgc: &SymbolGraphCollection) -> Self {
+        // Keep the nodes in first-seen order so our output is stable.
+        let mut nodes = vec![];
+        let mut index_of: HashMap<SymbolGraphNodeId, usize> = HashMap::new();
+        let mut intern = |node_id: SymbolGraphNodeId| match index_of.entry(node_id.clone()) {
+            Entry::Occupied(e) => *e.get(),
+            Entry::Vacant(e) => {
+                nodes.push(node_id);
+                *e.insert(nodes.len() - 1)
+            }
+        };
+
+        let mut edges = BTreeSet::new();
+        for graph in &sgc.graphs {
+            for node_id in graph.list_nodes() {
+                intern(node_id);
+            }
+            for (source, target, _) in graph.list_edges() {
+                let from = intern(source);
+                let to = intern(target);
+                if from != to {
+                    edges.insert((from, to));
+                }
+            }
+        }
+
+        let mut outgoing = vec![vec![]; nodes.len()];
+        let mut incoming = vec![vec![]; nodes.len()];
+        for (from, to) in edges {
+            outgoing[from].push(to);
+            incoming[to].push(from);
+        }
+        MetricGraph {
+            nodes,
+            outgoing,
+            incoming,
+        }
+    }
+
+    /// Power iteration PageRank where symbols without outgoing edges spread
+    /// their rank over every symbol.
+    fn pagerank(&self, damping: f64) -> Vec<f64> {
+        let n = self.nodes.len();
+        if n == 0 {
+            return vec![];
+        }
+        let mut rank = vec![1.0 / n as f64; n];
+        for _ in 0..100 {
+            let dangling: f64 = (0..n)
+                .filter(|i| self.outgoing[*i].is_empty())
+                .map(|i| rank[i])
+                .sum();
+            let base = (1.0 - damping + damping * dangling) / n as f64;
+            let next: Vec<f64> = (0..n)
+                .map(|i| {
+                    base + damping
+                        * self.incoming[i]
+                            .iter()
+                            .map(|from| rank[*from] / self.outgoing[*from].len() as f64)
+                            .sum::<f64>()
+                })
+                .collect();
+            let delta: f64 = next.iter().zip(&rank).map(|(a, b)| (a - b).abs()).sum();
+            rank = next;
+            if delta < 1e-10 {
+                break;
+            }
+        }
+        rank
+    }
+
+    /// Brandes' betweenness centrality from the given sources, scaled up to
+    /// estimate the contribution of every source and normalized by the number
+    /// of ordered pairs of other symbols.
+    fn betweenness(&self, sources: &[usize]) -> Vec<f64> {
+        let n = self.nodes.len();
+        let mut centrality = vec![0.0; n];
+        for &source in sources {
+            let mut order = vec![];
+            let mut preds: Vec<Vec<usize>> = vec![vec![]; n];
+            let mut paths = vec![0.0; n];
+            let mut dist: Vec<Option<usize>> = vec![None; n];
+            paths[source] = 1.0;
+            dist[source] = Some(0);
+            let mut pending = VecDeque::from([source]);
+            while let Some(v) = pending.pop_front() {
+                order.push(v);
+                let next_dist = dist[v].unwrap() + 1;
+                for &w in &self.outgoing[v] {
+                    if dist[w].is_none() {
+                        dist[w] = Some(next_dist);
+                        pending.push_back(w);
+                    }
+                    if dist[w] == Some(next_dist) {
+                        paths[w] += paths[v];
+                        preds[w].push(v);
+                    }
+                }
+            }
+            let mut dependency = vec![0.0; n];
+            for &w in order.iter().rev() {
+                for &v in &preds[w] {
+                    dependency[v] += paths[v] / paths[w] * (1.0 + dependency[w]);
+                }
+                if w != source {
+                    centrality[w] += dependency[w];
+                }
+            }
+        }
+        if n > 2 && !sources.is_empty() {
+            let scale = n as f64 / sources.len() as f64 / ((n - 1) * (n - 2)) as f64;
+            for value in &mut centrality {
+                *value *= scale;
+            }
+        }
+        centrality
+    }
+}
+
+#[async_trait]
+impl PipelineCommand for GraphMetricsCommand {
+    async fn execute(
+        &self,
+        _server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let sgc = match input {
+            PipelineValues::SymbolGraphCollection(sgc) => sgc,
+            _ => {
+                return Err(ServerError::StickyProblem(
+                    ErrorDetails::new(
+                        ErrorKind::BadQuery,
+                        ErrorLayer::ConfigLayer,
+                        "graph-metrics needs a SymbolGraphCollection".to_string(),
+                    )
+                    .with_hint(
+                        "Use `explain` to see what each pipeline stage consumes and produces.",
+                    ),
+                ));
+            }
+        };
+
+        let graph = MetricGraph::from_collection(&sgc);
+        let n = graph.nodes.len();
+        let pagerank = graph.pagerank(self.args.damping);
+        let sources: Vec<usize> = match self.args.betweenness_samples {
+            0 => (0..n).collect(),
+            samples if samples >= n => (0..n).collect(),
+            samples => (0..samples).map(|i| i * n / samples).collect(),
+        };
+        let betweenness = graph.betweenness(&sources);
+
+        let metric = |i: usize| match self.args.sort_by {
+            GraphMetric::FanIn => graph.incoming[i].len() as f64,
+            GraphMetric::FanOut => graph.outgoing[i].len() as f64,
+            GraphMetric::Pagerank => pagerank[i],
+            GraphMetric::Betweenness => betweenness[i],
+        };
+        let mut ranked: Vec<usize> = (0..n).collect();
+        // Ties keep the graph order so our output is stable.
+        ranked.sort_by(|a, b| metric(*b).total_cmp(&metric(*a)));
+        if self.args.limit > 0 {
+            ranked.truncate(self.args.limit);
+        }
+
+        let mut table = DataTable::new(vec![
+            ("rank", DataColumnType::Int),
+            ("symbol", DataColumnType::SymbolRef),
+            ("pretty", DataColumnType::String),
+            ("path", DataColumnType::Path),
+            ("fan_in", DataColumnType::Int),
+            ("fan_out", DataColumnType::Int),
+            ("pagerank", DataColumnType::Float),
+            ("betweenness", DataColumnType::Float),
+        ]);
+        for (rank, i) in ranked.into_iter().enumerate() {
+            let info = sgc.node_set.get(&graph.nodes[i]);
+            table.push_row(vec![
+                DataCell::Int(rank as i64 + 1),
+                DataCell::Symbol(info.symbol),
+                DataCell::String(info.get_pretty().to_string()),
+                info.get_def_path()
+                    .map_or(DataCell::Null, |path| DataCell::String(path.clone())),
+                DataCell::Int(graph.incoming[i].len() as i64),
+                DataCell::Int(graph.outgoing[i].len() as i64),
+                DataCell::Float(pagerank[i]),
+                DataCell::Float(betweenness[i]),
+            ])?;
+        }
+
+        Ok(PipelineValues::DataTable(table))
+    }
+}
@@ -0,0 +1,414 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use async_trait::async_trait;
+use clap::Args;
+use serde_json::{from_value, Value};
+use tokio_stream::StreamExt;
+use ustr::{ustr, Ustr, UstrMap};
+
+use super::{
+    interface::{
+        PipelineCommand, PipelineValues, SymbolCrossrefInfoList, SymbolHit, SymbolHitGroup,
+        SymbolHitGroups,
+    },
+    path_filter::{PathFilter, PathFilterArgs},
+};
+
+use crate::{
+    abstract_server::{
+        AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError, TextMatches,
+    },
+    file_format::analysis::{parse_location, FileClassification, PathSearchResult},
+};
+
+/// Group raw fulltext hits or crossref hits by the symbol they resolve to,
+/// collapsing redundant hits so that consumers can present "N uses across M
+/// files" rather than a noisy flat list.
+///
+/// Fulltext hits are resolved to symbols by consulting the analysis records for
+/// the file and finding the source records that overlap the match bounds.
+/// Crossref hits are already associated with a symbol.  In both cases, hits on
+/// the same line of the same file for the same symbol are collapsed into a
+/// single hit that accumulates the kinds (ex: a declaration that's also a
+/// definition).
+#[derive(Debug, Args)]
+pub struct GroupHits {
+    /// Maximum number of example hits to retain per group.  The counts always
+    /// reflect all of the hits.  Pass 0 to retain all hits.
+    #[clap(long, value_parser, default_value = "0")]
+    examples: usize,
+
+    /// Count hits in generated files but don't retain them as example hits.
+    #[clap(long, value_parser)]
+    collapse_generated: bool,
+
+    /// Hits in files that fail this filter are dropped entirely and do not
+    /// contribute to the counts.
+    #[clap(flatten)]
+    path_filter: PathFilterArgs,
+}
+
+#[derive(Debug)]
+pub struct GroupHitsCommand {
+    pub args: GroupHits,
+}
+
+/// A source record from an analysis file, reduced to what we need to map a
+/// fulltext match to a symbol.
+struct SourceSpan {
+    col_start: u32,
+    col_end: u32,
+    sym: Ustr,
+    pretty: Ustr,
+    kind: &'static str,
+}
+
+/// Map the comma-delimited source record "syntax" to the crossref kind names so
+/// that fulltext-derived and crossref-derived hits use the same vocabulary.
+fn syntax_to_kind(syntax: &str) -> &'static str {
+    let mut kind = "uses";
+    for piece in syntax.split(',') {
+        match piece {
+            "def" => return "defs",
+            "decl" => kind = "decls",
+            "idl" => kind = "idl",
+            _ => {}
+        }
+    }
+    kind
+}
+
+/// The noun for `count` hits of the given crossref kind, or for hits of mixed
+/// kinds if there's no single kind.
+fn kind_noun(kind: Option<&str>, count: usize) -> &'static str {
+    let (singular, plural) = match kind {
+        Some("uses") => ("use", "uses"),
+        Some("defs") => ("definition", "definitions"),
+        Some("decls") => ("declaration", "declarations"),
+        Some("assignments") => ("assignment", "assignments"),
+        Some("idl") => ("IDL hit", "IDL hits"),
+        _ => ("hit", "hits"),
+    };
+    if count == 1 {
+        singular
+    } else {
+        plural
+    }
+}
+
+/// The kinds that source records prefix their pretty names with, like
+/// "function Foo::Bar".  Longer kinds sharing a first word come first.
+const PRETTY_KINDS: &[&str] = &[
+    "enum constant",
+    "class",
+    "constructor",
+    "destructor",
+    "enum",
+    "field",
+    "file",
+    "function",
+    "ipc",
+    "local",
+    "macro",
+    "method",
+    "namespace",
+    "property",
+    "type",
+    "variable",
+];
+
+/// Strip the kind prefix from a source record's pretty name, leaving any other
+/// spaces (ex: "operator ==" or "unsigned int") alone.
+fn strip_pretty_kind(pretty: &str) -> &str {
+    PRETTY_KINDS
+        .iter()
+        .find_map(|kind| pretty.strip_prefix(kind)?.strip_prefix(' '))
+        .unwrap_or(pretty)
+}
+
+#[derive(Default)]
+struct GroupAccumulator {
+    pretty: Ustr,
+    /// Keyed by (path, lineno) to collapse redundant hits.
+    hits: BTreeMap<(Ustr, u32), SymbolHit>,
+    duplicates_collapsed: u32,
+}
+
+impl GroupAccumulator {
+    fn add_hit(
+        &mut self,
+        path: Ustr,
+        path_kind: Ustr,
+        classification: FileClassification,
+        lineno: u32,
+        kind: &str,
+        line: &str,
+    ) {
+        if let Some(existing) = self.hits.get_mut(&(path, lineno)) {
+            self.duplicates_collapsed += 1;
+            if !existing.kinds.iter().any(|k| k == kind) {
+                existing.kinds.push(kind.to_string());
+                existing.kinds.sort();
+            }
+            return;
+        }
+        self.hits.insert(
+            (path, lineno),
+            SymbolHit {
+                path,
+                path_kind,
+                classification,
+                lineno,
+                kinds: vec![kind.to_string()],
+                line: line.trim().to_string(),
+            },
+        );
+    }
+}
+
+impl GroupHitsCommand {
+    fn finish(
+        &self,
+        accumulators: UstrMap<GroupAccumulator>,
+        unresolved: Vec<SymbolHit>,
+    ) -> SymbolHitGroups {
+        let mut groups: Vec<SymbolHitGroup> = accumulators
+            .into_iter()
+            .map(|(symbol, accum)| {
+                let files: BTreeSet<Ustr> = accum.hits.keys().map(|(path, _)| *path).collect();
+                let mut kind_counts = BTreeMap::new();
+                for hit in accum.hits.values() {
+                    for kind in &hit.kinds {
+                        *kind_counts.entry(kind.clone()).or_insert(0) += 1;
+                    }
+                }
+                let hit_count = accum.hits.len() as u32;
+                let mut hits: Vec<SymbolHit> = accum
+                    .hits
+                    .into_values()
+                    .filter(|hit| {
+                        !self.args.collapse_generated
+                            || hit.classification != FileClassification::Generated
+                    })
+                    .collect();
+                if self.args.examples > 0 {
+                    hits.truncate(self.args.examples);
+                }
+                // Describe the hits by their kind when they all share one.
+                let group_kind = if kind_counts.len() == 1 {
+                    kind_counts.keys().next().map(|kind| kind.as_str())
+                } else {
+                    None
+                };
+                let summary = format!(
+                    "{} {} across {} {}",
+                    hit_count,
+                    kind_noun(group_kind, hit_count as usize),
+                    files.len(),
+                    if files.len() == 1 { "file" } else { "files" },
+                );
+                SymbolHitGroup {
+                    symbol,
+                    pretty: accum.pretty,
+                    hit_count,
+                    file_count: files.len() as u32,
+                    duplicates_collapsed: accum.duplicates_collapsed,
+                    kind_counts,
+                    summary,
+                    hits,
+                }
+            })
+            .collect();
+        // Biggest groups first; the pretty/symbol tiebreakers keep our output
+        // stable for snapshots.
+        groups.sort_by(|a, b| {
+            b.hit_count
+                .cmp(&a.hit_count)
+                .then_with(|| a.pretty.cmp(&b.pretty))
+                .then_with(|| a.symbol.cmp(&b.symbol))
+        });
+
+        SymbolHitGroups { groups, unresolved }
+    }
+
+    fn group_crossref_hits(
+        &self,
+        scil: SymbolCrossrefInfoList,
+        path_filter: &PathFilter,
+    ) -> Result<SymbolHitGroups> {
+        let mut accumulators: UstrMap<GroupAccumulator> = UstrMap::default();
+        for info in scil.symbol_crossref_infos {
+            let accum = accumulators.entry(info.symbol).or_default();
+            accum.pretty = info.get_pretty();
+            if let Value::Object(obj) = info.crossref_info {
+                for (kind, val) in obj.into_iter() {
+                    match kind.as_str() {
+                        "idl" | "defs" | "decls" | "assignments" | "uses" => {}
+                        _ => continue,
+                    }
+                    let path_containers: Vec<PathSearchResult> = from_value(val)?;
+                    for path_container in path_containers {
+                        if !path_filter
+                            .matches_classified(&path_container.path, path_container.classification)
+                        {
+                            continue;
+                        }
+                        for search_result in path_container.lines {
+                            accum.add_hit(
+                                path_container.path,
+                                path_container.path_kind,
+                                path_container.classification,
+                                search_result.lineno,
+                                &kind,
+                                &search_result.line,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(self.finish(accumulators, vec![]))
+    }
+
+    async fn group_text_hits(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        tm: TextMatches,
+        path_filter: &PathFilter,
+    ) -> Result<SymbolHitGroups> {
+        let mut accumulators: UstrMap<GroupAccumulator> = UstrMap::default();
+        let mut unresolved = vec![];
+
+        for file_matches in tm.by_file {
+            if !path_filter.matches_classified(&file_matches.file, file_matches.classification) {
+                continue;
+            }
+            // Not every file has analysis data; all of its hits just end up
+            // unresolved in that case.
+            let mut spans_by_line: BTreeMap<u32, Vec<SourceSpan>> = BTreeMap::new();
+            if let Ok(mut records) = server.fetch_raw_analysis(&file_matches.file).await {
+                while let Some(val) = records.next().await {
+                    if !val["source"].is_number() {
+                        continue;
+                    }
+                    let (Some(loc), Some(syms)) = (val["loc"].as_str(), val["sym"].as_str()) else {
+                        continue;
+                    };
+                    let loc = parse_location(loc);
+                    // source records prefix the pretty with the kind, like
+                    // "function Foo::Bar", which we strip.
+                    let pretty = strip_pretty_kind(val["pretty"].as_str().unwrap_or(""));
+                    let kind = syntax_to_kind(val["syntax"].as_str().unwrap_or(""));
+                    // For multi-symbol records the first symbol is the most
+                    // specific one.
+                    if let Some(sym) = syms.split(',').next() {
+                        spans_by_line
+                            .entry(loc.lineno)
+                            .or_default()
+                            .push(SourceSpan {
+                                col_start: loc.col_start,
+                                col_end: loc.col_end,
+                                sym: ustr(sym),
+                                pretty: ustr(pretty),
+                                kind,
+                            });
+                    }
+                }
+            }
+
+            for text_match in file_matches.matches {
+                let start = text_match.bounds.start.max(0) as u32;
+                let end_exclusive = text_match.bounds.end_exclusive.max(0) as u32;
+                let mut resolved = false;
+                if let Some(spans) = spans_by_line.get(&text_match.line_num) {
+                    for span in spans {
+                        if span.col_start < end_exclusive && start <= span.col_end {
+                            let accum = accumulators.entry(span.sym).or_default();
+                            accum.pretty = span.pretty;
+                            accum.add_hit(
+                                file_matches.file,
+                                file_matches.path_kind,
+                                file_matches.classification,
+                                text_match.line_num,
+                                span.kind,
+                                &text_match.line_str,
+                            );
+                            resolved = true;
+                        }
+                    }
+                }
+                if !resolved {
+                    unresolved.push(SymbolHit {
+                        path: file_matches.file,
+                        path_kind: file_matches.path_kind,
+                        classification: file_matches.classification,
+                        lineno: text_match.line_num,
+                        kinds: vec![],
+                        line: text_match.line_str.trim().to_string(),
+                    });
+                }
+            }
+        }
+
+        Ok(self.finish(accumulators, unresolved))
+    }
+}
+
+#[async_trait]
+impl PipelineCommand for GroupHitsCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let path_filter = self
+            .args
+            .path_filter
+            .compile(&server.tree_info()?.path_sets)?;
+
+        let groups = match input {
+            PipelineValues::SymbolCrossrefInfoList(scil) => {
+                self.group_crossref_hits(scil, &path_filter)?
+            }
+            PipelineValues::TextMatches(tm) => {
+                self.group_text_hits(server, tm, &path_filter).await?
+            }
+            _ => {
+                return Err(ServerError::StickyProblem(
+                    ErrorDetails::new(
+                        ErrorKind::BadQuery,
+                        ErrorLayer::ConfigLayer,
+                        "group-hits needs a SymbolCrossrefInfoList or TextMatches".to_string(),
+                    )
+                    .with_hint(
+                        "Use `explain` to see what each pipeline stage consumes and produces.",
+                    ),
+                ));
+            }
+        };
+
+        Ok(PipelineValues::SymbolHitGroups(groups))
+    }
+}
+
+#[test]
+fn test_strip_pretty_kind() {
+    assert_eq!(strip_pretty_kind("function Foo::Bar"), "Foo::Bar");
+    assert_eq!(
+        strip_pretty_kind("function Foo::operator =="),
+        "Foo::operator =="
+    );
+    assert_eq!(strip_pretty_kind("type unsigned int"), "unsigned int");
+    assert_eq!(strip_pretty_kind("enum constant Foo::Bar"), "Foo::Bar");
+    assert_eq!(strip_pretty_kind("enum Foo"), "Foo");
+    assert_eq!(strip_pretty_kind("Foo::operator new"), "Foo::operator new");
+    assert_eq!(strip_pretty_kind("functional"), "functional");
+}
+
+#[test]
+fn test_kind_noun() {
+    assert_eq!(kind_noun(Some("uses"), 1), "use");
+    assert_eq!(kind_noun(Some("defs"), 2), "definitions");
+    assert_eq!(kind_noun(None, 4), "hits");
+}
@@ -0,0 +1,47 @@
+use async_trait::async_trait;
+use clap::Args;
+
+use super::{
+    help_json::pipeline_help_json,
+    interface::{JsonValue, PipelineCommand, PipelineValues},
+};
+use crate::abstract_server::{
+    AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError,
+};
+
+/// Dump every pipeline command's arguments (including the possible values of
+/// enum arguments like `format-symbols --mode`) and the `PipelineValues`
+/// variants it consumes and produces as JSON.
+#[derive(Debug, Args)]
+pub struct HelpJson {
+    /// Only describe the command with this name, like "format-symbols".
+    #[clap(value_parser)]
+    command: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct HelpJsonCommand {
+    pub args: HelpJson,
+}
+
+#[async_trait]
+impl PipelineCommand for HelpJsonCommand {
+    async fn execute(
+        &self,
+        _server: &(dyn AbstractServer + Send + Sync),
+        _input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let value = pipeline_help_json(self.args.command.as_deref()).ok_or_else(|| {
+            ServerError::StickyProblem(ErrorDetails::new(
+                ErrorKind::NotFound,
+                ErrorLayer::BadInput,
+                format!(
+                    "No such command: {}",
+                    self.args.command.as_deref().unwrap_or_default()
+                ),
+            ))
+        })?;
+
+        Ok(PipelineValues::JsonValue(JsonValue { value }))
+    }
+}
@@ -0,0 +1,96 @@
+use async_trait::async_trait;
+use clap::Args;
+use serde_json::json;
+
+use super::interface::{JsonValue, PipelineCommand, PipelineValues};
+use crate::{
+    abstract_server::{AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError},
+    hex_dump::hex_rows,
+};
+
+/// Produce one page of a hex dump of a (typically binary) source file as a
+/// JsonValue with the file's size, the page's offset and length, the offsets
+/// of the previous and next pages (null at the start and end of the file), and
+/// the page's rows, each of which has its offset, its bytes in hex, and its
+/// bytes as ASCII for a sidebar.  This lets the web UI page through large
+/// binary files without shipping the whole file to the client.
+#[derive(Debug, Args)]
+pub struct HexDump {
+    /// The file to dump.
+    #[clap(value_parser)]
+    path: String,
+
+    /// The offset to start the page at, which is rounded down to a multiple of
+    /// `--width` so that rows line up from page to page.
+    #[clap(long, value_parser, default_value = "0")]
+    offset: u64,
+
+    /// How many bytes to include in the page.
+    #[clap(long, value_parser, default_value = "4096")]
+    length: usize,
+
+    /// How many bytes to show per row.
+    #[clap(long, value_parser = clap::value_parser!(u16).range(1..=64), default_value = "16")]
+    width: u16,
+}
+
+#[derive(Debug)]
+pub struct HexDumpCommand {
+    pub args: HexDump,
+}
+
+/// Keep pages small enough to be reasonable to send to a client.
+const MAX_PAGE_LENGTH: usize = 64 * 1024;
+
+#[async_trait]
+impl PipelineCommand for HexDumpCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        _input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        if self.args.length > MAX_PAGE_LENGTH {
+            return Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::BadQuery,
+                    ErrorLayer::BadInput,
+                    format!(
+                        "hex-dump pages can be at most {} bytes, not {}",
+                        MAX_PAGE_LENGTH, self.args.length
+                    ),
+                )
+                .with_hint("Use `--offset` to page through larger files."),
+            ));
+        }
+
+        let width = self.args.width as u64;
+        let offset = self.args.offset - self.args.offset % width;
+        let chunk = server
+            .fetch_raw_bytes(&self.args.path, offset, self.args.length)
+            .await?;
+
+        let end = chunk.offset + chunk.data.len() as u64;
+        let prev_offset = if chunk.offset > 0 {
+            Some(chunk.offset.saturating_sub(self.args.length as u64))
+        } else {
+            None
+        };
+        let next_offset = if end < chunk.file_size {
+            Some(end)
+        } else {
+            None
+        };
+
+        Ok(PipelineValues::JsonValue(JsonValue {
+            value: json!({
+                "path": self.args.path,
+                "file_size": chunk.file_size,
+                "offset": chunk.offset,
+                "length": chunk.data.len(),
+                "prev_offset": prev_offset,
+                "next_offset": next_offset,
+                "rows": hex_rows(chunk.offset, &chunk.data, self.args.width as usize),
+            }),
+        }))
+    }
+}
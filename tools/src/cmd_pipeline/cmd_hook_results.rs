@@ -0,0 +1,68 @@
+use async_trait::async_trait;
+use clap::Args;
+use serde_json::{json, to_value};
+
+use super::interface::{JsonValue, PipelineCommand, PipelineValues};
+use crate::{
+    abstract_server::{AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError},
+    query::post_index_hooks::{list_hook_runs, load_hook_run},
+};
+
+/// Retrieve the stored results of the tree's post-index hooks (see
+/// `query/post_index_hooks.rs`).  Without a hook name, lists the configured
+/// hooks and their stored runs.  With a hook name, produces the stored run
+/// (the most recent one unless `--ran-at` is specified), including its output.
+#[derive(Debug, Args)]
+pub struct HookResults {
+    /// The hook whose results we want.
+    #[clap(value_parser)]
+    hook: Option<String>,
+
+    /// The `ranAt` time of the run to retrieve, as listed without a hook name.
+    #[clap(long, value_parser)]
+    ran_at: Option<u64>,
+}
+
+#[derive(Debug)]
+pub struct HookResultsCommand {
+    pub args: HookResults,
+}
+
+#[async_trait]
+impl PipelineCommand for HookResultsCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        _input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let Some(hook) = &self.args.hook else {
+            let mut hooks = vec![];
+            for (name, config) in server.tree_info()?.post_index_hooks {
+                hooks.push(json!({
+                    "hook": name,
+                    "description": config.preset.description,
+                    "retention": config.retention,
+                    "runs": list_hook_runs(server, &name)?,
+                }));
+            }
+            return Ok(PipelineValues::JsonValue(JsonValue {
+                value: json!(hooks),
+            }));
+        };
+
+        match load_hook_run(server, hook, self.args.ran_at)? {
+            Some(run) => Ok(PipelineValues::JsonValue(JsonValue {
+                value: to_value(run)?,
+            })),
+            None => Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::NotFound,
+                    ErrorLayer::BadInput,
+                    format!("No stored results for post-index hook {}", hook),
+                )
+                .with_context("hook", hook)
+                .with_hint("The hook runs at the end of indexing via `run-post-index-hooks`."),
+            )),
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+
+use async_trait::async_trait;
+use clap::Args;
+use flate2::read::GzDecoder;
+use serde_json::json;
+
+use super::interface::{JsonValue, PipelineCommand, PipelineValues};
+use crate::{
+    abstract_server::{
+        AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, SearchfoxIndexRoot,
+        ServerError,
+    },
+    file_format::query_popularity::{QueryPopularity, QUERY_POPULARITY_FILENAME},
+};
+
+/// Ingest web server query logs into the tree's compact popularity store (see
+/// `file_format/query_popularity.rs`), counting how often each symbol was
+/// searched for and each file was viewed.  The store boosts search ranking
+/// and can feed `precompute` via the `popular_symbols` precompute config.
+/// Produces a JsonValue summarizing what was ingested.
+#[derive(Debug, Args)]
+pub struct IngestQueryLogs {
+    /// Log file(s) to ingest, which may be gzipped.
+    #[clap(value_parser, required = true)]
+    log: Vec<String>,
+
+    /// Add to the existing counts instead of replacing them.
+    #[clap(long, value_parser)]
+    merge: bool,
+
+    /// The maximum number of symbols and of paths to keep, keeping the most
+    /// popular.
+    #[clap(long, value_parser, default_value = "100000")]
+    max_entries: usize,
+}
+
+#[derive(Debug)]
+pub struct IngestQueryLogsCommand {
+    pub args: IngestQueryLogs,
+}
+
+fn open_log(path: &str) -> Result<Box<dyn BufRead>> {
+    let file = File::open(path).map_err(|e| {
+        ServerError::StickyProblem(ErrorDetails::new(
+            ErrorKind::NotFound,
+            ErrorLayer::BadInput,
+            format!("Unable to open query log {}: {}", path, e),
+        ))
+    })?;
+    let reader: Box<dyn Read> = if path.ends_with(".gz") {
+        Box::new(GzDecoder::new(file))
+    } else {
+        Box::new(file)
+    };
+    Ok(Box::new(BufReader::new(reader)))
+}
+
+#[async_trait]
+impl PipelineCommand for IngestQueryLogsCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        _input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let tree_name = server.tree_info()?.name;
+        let store_path =
+            server.translate_path(SearchfoxIndexRoot::IndexRoot, QUERY_POPULARITY_FILENAME)?;
+
+        let mut popularity = if self.args.merge {
+            QueryPopularity::load(&store_path)?.unwrap_or_default()
+        } else {
+            QueryPopularity::default()
+        };
+
+        let (mut lines, mut recorded) = (0u64, 0u64);
+        for path in &self.args.log {
+            // Logs can be large and may contain invalid UTF-8 from hostile
+            // requests, so we stream them and skip lines we can't decode.
+            for line in open_log(path)?.split(b'\n') {
+                lines += 1;
+                let Ok(line) = String::from_utf8(line?) else {
+                    continue;
+                };
+                if popularity.record_log_line(&tree_name, &line) {
+                    recorded += 1;
+                }
+            }
+        }
+
+        popularity.prune(self.args.max_entries);
+        popularity.save(&store_path)?;
+
+        Ok(PipelineValues::JsonValue(JsonValue {
+            value: json!({
+                "lines": lines,
+                "recorded": recorded,
+                "symbols": popularity.symbols.len(),
+                "paths": popularity.paths.len(),
+            }),
+        }))
+    }
+}
@@ -0,0 +1,97 @@
+use std::fs::File;
+use std::io::Read;
+
+use async_trait::async_trait;
+use clap::Args;
+use flate2::read::GzDecoder;
+use serde_json::json;
+
+use super::interface::{JsonValue, PipelineCommand, PipelineValues};
+use crate::{
+    abstract_server::{
+        AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, SearchfoxIndexRoot,
+        ServerError,
+    },
+    file_format::test_stats::{TestStats, TEST_STATS_FILENAME},
+};
+
+/// Ingest test failure and retrigger statistics keyed by test path into the
+/// tree's test stats store (see `file_format/test_stats.rs`) for use by
+/// `flaky-heatmap`.  Feeds may be CSV files with a header row or JSON files,
+/// optionally gzipped; see `TestStats::record_csv` and
+/// `TestStats::record_json` for the accepted shapes.  Produces a JsonValue
+/// summarizing what was ingested.
+#[derive(Debug, Args)]
+pub struct IngestTestStats {
+    /// Feed file(s) to ingest.  Files whose name (ignoring any ".gz") ends in
+    /// ".json" are parsed as JSON and everything else as CSV.
+    #[clap(value_parser, required = true)]
+    feed: Vec<String>,
+
+    /// Add to the existing counts instead of replacing them.
+    #[clap(long, value_parser)]
+    merge: bool,
+}
+
+#[derive(Debug)]
+pub struct IngestTestStatsCommand {
+    pub args: IngestTestStats,
+}
+
+fn read_feed(path: &str) -> Result<String> {
+    let bad_feed = |e: String| {
+        ServerError::StickyProblem(ErrorDetails::new(
+            ErrorKind::NotFound,
+            ErrorLayer::BadInput,
+            format!("Unable to read test stats feed {}: {}", path, e),
+        ))
+    };
+    let file = File::open(path).map_err(|e| bad_feed(e.to_string()))?;
+    let mut reader: Box<dyn Read> = if path.ends_with(".gz") {
+        Box::new(GzDecoder::new(file))
+    } else {
+        Box::new(file)
+    };
+    let mut contents = String::new();
+    reader
+        .read_to_string(&mut contents)
+        .map_err(|e| bad_feed(e.to_string()))?;
+    Ok(contents)
+}
+
+#[async_trait]
+impl PipelineCommand for IngestTestStatsCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        _input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let store_path =
+            server.translate_path(SearchfoxIndexRoot::IndexRoot, TEST_STATS_FILENAME)?;
+
+        let mut stats = if self.args.merge {
+            TestStats::load(&store_path)?.unwrap_or_default()
+        } else {
+            TestStats::default()
+        };
+
+        let mut recorded = 0;
+        for path in &self.args.feed {
+            let contents = read_feed(path)?;
+            recorded += if path.trim_end_matches(".gz").ends_with(".json") {
+                stats.record_json(&serde_json::from_str(&contents)?)
+            } else {
+                stats.record_csv(&contents)
+            };
+        }
+
+        stats.save(&store_path)?;
+
+        Ok(PipelineValues::JsonValue(JsonValue {
+            value: json!({
+                "recorded": recorded,
+                "tests": stats.tests.len(),
+            }),
+        }))
+    }
+}
@@ -0,0 +1,274 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use async_trait::async_trait;
+use clap::{Args, ValueEnum};
+use petgraph::{algo::tarjan_scc, graph::DiGraph};
+
+use super::{
+    data_table::{DataCell, DataColumnType, DataTable},
+    interface::{PipelineCommand, PipelineValues, TextFile},
+};
+use crate::{
+    abstract_server::{
+        AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, SearchfoxIndexRoot,
+        ServerError,
+    },
+    file_format::js_modules::{JsImport, JsImportKind, JsModuleIndex, JS_MODULE_INDEX_FILENAME},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum JsModuleGraphFormat {
+    /// A DataTable listing the dependency tree depth-first, with a row per
+    /// import edge.
+    Tree,
+    /// A graphviz DOT TextFile of the modules and their import edges.
+    Dot,
+    /// A DataTable with a row per member of each import cycle.
+    Cycles,
+}
+
+/// Query the import edges between the tree's JS modules, covering ESM
+/// `import`/`export ... from`, dynamic `import()`, `ChromeUtils.importESModule`,
+/// and lazy module getters, as indexed by crossref into `js-modules.json`.
+/// Specifiers are resolved to files relative to the importing module or via
+/// the tree's URL map for "resource://" and "chrome://" URLs.
+///
+/// Starting from the given modules (or every module under `--path-prefix`),
+/// follow their imports (or with `--reverse`, their importers) and produce
+/// the dependency tree, a DOT graph, or the import cycles.
+#[derive(Debug, Args)]
+pub struct JsModuleGraph {
+    /// Tree-relative paths of the modules to start from.
+    #[clap(value_parser)]
+    modules: Vec<String>,
+
+    /// Follow the modules importing each module instead of its imports.
+    #[clap(long, value_parser)]
+    reverse: bool,
+
+    /// How many levels of imports to follow.  By default there's no limit.
+    #[clap(long, value_parser)]
+    depth: Option<u32>,
+
+    /// Only include modules under this path prefix.
+    #[clap(long, value_parser)]
+    path_prefix: Option<String>,
+
+    #[clap(long, value_parser, value_enum, default_value = "tree")]
+    format: JsModuleGraphFormat,
+}
+
+#[derive(Debug)]
+pub struct JsModuleGraphCommand {
+    pub args: JsModuleGraph,
+}
+
+/// A row of the dependency tree.
+struct TreeRow<'a> {
+    depth: u32,
+    from: &'a str,
+    to: &'a str,
+    import: &'a JsImport,
+    /// Whether `to` was already expanded earlier in the tree.
+    repeat: bool,
+}
+
+fn dot_quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+impl JsModuleGraphCommand {
+    fn in_scope(&self, path: &str) -> bool {
+        self.args
+            .path_prefix
+            .as_ref()
+            .map_or(true, |prefix| path.starts_with(prefix.as_str()))
+    }
+
+    /// Walk the graph depth-first from the roots, returning the tree rows.
+    /// Each module is only expanded the first time it's reached.
+    fn walk<'a>(
+        &self,
+        roots: &[&'a str],
+        adjacency: &BTreeMap<&'a str, Vec<(&'a str, &'a JsImport)>>,
+    ) -> Vec<TreeRow<'a>> {
+        let mut rows = vec![];
+        let mut expanded: BTreeSet<&str> = BTreeSet::new();
+        for root in roots {
+            if !expanded.insert(*root) {
+                continue;
+            }
+            // A stack of the (depth, module) pairs still to expand.
+            let mut stack = vec![(1, *root)];
+            while let Some((depth, module)) = stack.pop() {
+                if self.args.depth.map_or(false, |max| depth > max) {
+                    continue;
+                }
+                let mut children = vec![];
+                for &(next, import) in adjacency.get(module).into_iter().flatten() {
+                    let repeat = !expanded.insert(next);
+                    rows.push(TreeRow {
+                        depth,
+                        from: module,
+                        to: next,
+                        import,
+                        repeat,
+                    });
+                    if !repeat {
+                        children.push((depth + 1, next));
+                    }
+                }
+                // Each module's edges are listed together before descending
+                // into the modules they import, in order.
+                stack.extend(children.into_iter().rev());
+            }
+        }
+        rows
+    }
+
+    fn to_tree_table(&self, rows: &[TreeRow]) -> Result<DataTable> {
+        let mut table = DataTable::new(vec![
+            ("depth", DataColumnType::Int),
+            ("from", DataColumnType::Path),
+            ("to", DataColumnType::Path),
+            ("kind", DataColumnType::String),
+            ("line", DataColumnType::Int),
+            ("specifier", DataColumnType::String),
+            ("repeat", DataColumnType::Int),
+        ]);
+        for row in rows {
+            let kind = match row.import.kind {
+                JsImportKind::Import => "import",
+                JsImportKind::ReExport => "re_export",
+                JsImportKind::DynamicImport => "dynamic_import",
+                JsImportKind::ChromeUtils => "chrome_utils",
+                JsImportKind::LazyGetter => "lazy_getter",
+            };
+            table.push_row(vec![
+                DataCell::Int(row.depth as i64),
+                DataCell::String(row.from.to_string()),
+                DataCell::String(row.to.to_string()),
+                DataCell::String(kind.to_string()),
+                DataCell::Int(row.import.line as i64),
+                DataCell::String(row.import.specifier.clone()),
+                DataCell::Int(row.repeat as i64),
+            ])?;
+        }
+        Ok(table)
+    }
+
+    fn to_dot(&self, rows: &[TreeRow]) -> String {
+        let edges: BTreeSet<(&str, &str)> = rows.iter().map(|row| (row.from, row.to)).collect();
+        let mut dot =
+            "digraph g {\n  node [shape=box, fontname=\"Courier New\", fontsize=10];\n".to_string();
+        for (from, to) in edges {
+            dot.push_str(&format!("  {} -> {};\n", dot_quote(from), dot_quote(to)));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    fn to_cycles_table(&self, rows: &[TreeRow]) -> Result<DataTable> {
+        let mut graph: DiGraph<&str, ()> = DiGraph::new();
+        let mut ixs = BTreeMap::new();
+        for row in rows {
+            for module in [row.from, row.to] {
+                if !ixs.contains_key(module) {
+                    ixs.insert(module, graph.add_node(module));
+                }
+            }
+            graph.update_edge(ixs[row.from], ixs[row.to], ());
+        }
+
+        let mut cycles: Vec<BTreeSet<&str>> = tarjan_scc(&graph)
+            .into_iter()
+            .filter(|comp| comp.len() > 1)
+            .map(|comp| comp.iter().map(|ix| graph[*ix]).collect())
+            .collect();
+        // Biggest cycles first since they're the most interesting.
+        cycles.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+
+        let mut table = DataTable::new(vec![
+            ("cycle", DataColumnType::Int),
+            ("size", DataColumnType::Int),
+            ("path", DataColumnType::Path),
+        ]);
+        for (i, cycle) in cycles.iter().enumerate() {
+            for path in cycle {
+                table.push_row(vec![
+                    DataCell::Int(i as i64),
+                    DataCell::Int(cycle.len() as i64),
+                    DataCell::String(path.to_string()),
+                ])?;
+            }
+        }
+        Ok(table)
+    }
+}
+
+#[async_trait]
+impl PipelineCommand for JsModuleGraphCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        if !matches!(input, PipelineValues::Void) {
+            return Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::BadQuery,
+                    ErrorLayer::ConfigLayer,
+                    "js-module-graph needs no input".to_string(),
+                )
+                .with_hint("Use `explain` to see what each pipeline stage consumes and produces."),
+            ));
+        }
+
+        let index_path =
+            server.translate_path(SearchfoxIndexRoot::IndexRoot, JS_MODULE_INDEX_FILENAME)?;
+        let Some(index) = JsModuleIndex::load(&index_path)? else {
+            return Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::IndexMissingCapability,
+                    ErrorLayer::DataLayer,
+                    "This index has no JS module index".to_string(),
+                )
+                .with_hint("Re-index the tree to build `js-modules.json`."),
+            ));
+        };
+
+        let mut adjacency: BTreeMap<&str, Vec<(&str, &JsImport)>> = BTreeMap::new();
+        for (importer, import) in index.edges() {
+            let target = import.target.as_deref().unwrap_or_default();
+            if !self.in_scope(importer) || !self.in_scope(target) {
+                continue;
+            }
+            let (from, to) = if self.args.reverse {
+                (target, importer)
+            } else {
+                (importer, target)
+            };
+            adjacency.entry(from).or_default().push((to, import));
+        }
+
+        let roots: Vec<&str> = if self.args.modules.is_empty() {
+            adjacency.keys().copied().collect()
+        } else {
+            self.args
+                .modules
+                .iter()
+                .map(|module| module.as_str())
+                .collect()
+        };
+        let rows = self.walk(&roots, &adjacency);
+
+        Ok(match self.args.format {
+            JsModuleGraphFormat::Tree => PipelineValues::DataTable(self.to_tree_table(&rows)?),
+            JsModuleGraphFormat::Dot => PipelineValues::TextFile(TextFile {
+                mime_type: "text/x-dot".to_string(),
+                contents: self.to_dot(&rows),
+            }),
+            JsModuleGraphFormat::Cycles => PipelineValues::DataTable(self.to_cycles_table(&rows)?),
+        })
+    }
+}
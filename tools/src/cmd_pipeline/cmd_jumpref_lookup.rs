@@ -3,7 +3,9 @@ use clap::Args;
 
 use super::interface::{JsonValue, JsonValueList, PipelineCommand, PipelineValues};
 
-use crate::abstract_server::{AbstractServer, ErrorDetails, ErrorLayer, Result, ServerError};
+use crate::abstract_server::{
+    AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError,
+};
 
 /// Return the jumpref data for one or more symbols received via pipeline or as
 /// explicit arguments and provide it as JSON, specifically as a JsonValueList
@@ -44,10 +46,16 @@ impl PipelineCommand for JumprefLookupCommand {
             // arguments...
             PipelineValues::Void => self.args.symbols.to_vec(),
             _ => {
-                return Err(ServerError::StickyProblem(ErrorDetails {
-                    layer: ErrorLayer::ConfigLayer,
-                    message: "jumpref-lookup needs a Void or SymbolList".to_string(),
-                }));
+                return Err(ServerError::StickyProblem(
+                    ErrorDetails::new(
+                        ErrorKind::BadQuery,
+                        ErrorLayer::ConfigLayer,
+                        "jumpref-lookup needs a Void or SymbolList".to_string(),
+                    )
+                    .with_hint(
+                        "Use `explain` to see what each pipeline stage consumes and produces.",
+                    ),
+                ));
             }
         };
 
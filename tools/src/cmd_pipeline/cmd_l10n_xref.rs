@@ -0,0 +1,207 @@
+use std::collections::BTreeSet;
+
+use async_trait::async_trait;
+use clap::Args;
+use serde_json::{json, Value};
+
+use super::{
+    data_table::{DataCell, DataColumnType, DataTable},
+    interface::{JsonValue, PipelineCommand, PipelineValues},
+    transforms::path_glob_transform,
+};
+use crate::{
+    abstract_server::{
+        AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError, TextMatches,
+    },
+    file_format::l10n::{
+        extract_l10n_keys, l10n_definition_pattern, l10n_format, l10n_use_pattern, L10nFormat,
+        L10N_PATH_PATTERN,
+    },
+};
+
+/// Cross-reference localization keys from Fluent, DTD, and properties files
+/// with the code that uses them (see `file_format/l10n.rs`).  Given an id,
+/// produces a JsonValue with where the id is defined and where it's used from
+/// code.  With `--unreferenced`, produces a DataTable of the keys defined in
+/// the matching localization files that no code uses, which finds strings that
+/// can be removed.
+///
+/// Uses are found by text search since code refers to the keys with string
+/// literals, so this needs a running codesearch server unless `--scan` is
+/// given.
+#[derive(Debug, Args)]
+pub struct L10nXref {
+    /// The localization id to cross-reference.
+    #[clap(value_parser)]
+    id: Option<String>,
+
+    /// Report the unused keys defined in the localization files matching this
+    /// path glob (ex: `browser/**`) instead of cross-referencing an id.
+    #[clap(long, value_parser)]
+    unreferenced: Option<String>,
+
+    /// The maximum number of uses to report per id.
+    #[clap(long, value_parser, default_value = "100")]
+    limit: usize,
+
+    /// Search by reading the files directly instead of using livegrep, like
+    /// `search-text --scan`.
+    #[clap(long, value_parser)]
+    scan: bool,
+}
+
+#[derive(Debug)]
+pub struct L10nXrefCommand {
+    pub args: L10nXref,
+}
+
+const FILE_MATCH_LIMIT: usize = 100_000;
+
+/// How many matches to look at when checking whether a key is referenced at
+/// all, since some of the matches may be in other localization files.
+const REFERENCED_CHECK_LIMIT: usize = 10;
+
+fn text_hits(matches: TextMatches, l10n_files: bool) -> Vec<Value> {
+    let mut hits = vec![];
+    for by_file in matches.by_file {
+        if l10n_format(&by_file.file).is_some() != l10n_files {
+            continue;
+        }
+        for m in by_file.matches {
+            hits.push(json!({
+                "path": by_file.file,
+                "line": m.line_num,
+                "line_str": m.line_str.trim(),
+            }));
+        }
+    }
+    hits
+}
+
+impl L10nXrefCommand {
+    async fn search(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        pattern: &str,
+        literal: &str,
+        pathre: &str,
+        limit: usize,
+    ) -> Result<TextMatches> {
+        if self.args.scan {
+            server
+                .search_text_scan(pattern, Some(literal), false, pathre, limit)
+                .await
+        } else {
+            server.search_text(pattern, false, pathre, limit).await
+        }
+    }
+
+    async fn xref_id(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        id: &str,
+    ) -> Result<PipelineValues> {
+        let defs = self
+            .search(
+                server,
+                &l10n_definition_pattern(id),
+                id,
+                L10N_PATH_PATTERN,
+                0,
+            )
+            .await?;
+        let formats: BTreeSet<L10nFormat> = defs
+            .by_file
+            .iter()
+            .filter_map(|by_file| l10n_format(&by_file.file))
+            .collect();
+        let definitions = text_hits(defs, true);
+
+        // Without a definition we don't know how the id would be used, so we
+        // look for both string literals and entity references.
+        let formats = if formats.is_empty() {
+            BTreeSet::from([L10nFormat::Fluent, L10nFormat::Dtd])
+        } else {
+            formats
+        };
+        // Fluent and properties keys are used the same way.
+        let patterns: BTreeSet<String> = formats
+            .into_iter()
+            .map(|format| l10n_use_pattern(format, id))
+            .collect();
+        let mut uses = vec![];
+        for pattern in patterns {
+            let matches = self
+                .search(server, &pattern, id, "", self.args.limit)
+                .await?;
+            uses.extend(text_hits(matches, false));
+        }
+
+        Ok(PipelineValues::JsonValue(JsonValue {
+            value: json!({
+                "id": id,
+                "definitions": definitions,
+                "uses": uses,
+            }),
+        }))
+    }
+
+    async fn find_unreferenced(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        path: &str,
+    ) -> Result<PipelineValues> {
+        let files = server
+            .search_files(&path_glob_transform(path), false, FILE_MATCH_LIMIT)
+            .await?;
+
+        let mut table = DataTable::new(vec![
+            ("path", DataColumnType::Path),
+            ("line", DataColumnType::Int),
+            ("id", DataColumnType::String),
+            ("value", DataColumnType::String),
+        ]);
+        for fm in files.file_matches {
+            let Some(format) = l10n_format(&fm.path) else {
+                continue;
+            };
+            let source = server.fetch_raw_source(&fm.path).await?;
+            for key in extract_l10n_keys(format, &source) {
+                let pattern = l10n_use_pattern(format, &key.id);
+                let matches = self
+                    .search(server, &pattern, &key.id, "", REFERENCED_CHECK_LIMIT)
+                    .await?;
+                if !text_hits(matches, false).is_empty() {
+                    continue;
+                }
+                table.push_row(vec![
+                    DataCell::String(fm.path.to_string()),
+                    DataCell::Int(key.lineno as i64),
+                    DataCell::String(key.id),
+                    DataCell::String(key.value),
+                ])?;
+            }
+        }
+
+        Ok(PipelineValues::DataTable(table))
+    }
+}
+
+#[async_trait]
+impl PipelineCommand for L10nXrefCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        _input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        match (&self.args.id, &self.args.unreferenced) {
+            (Some(id), None) => self.xref_id(server, id).await,
+            (None, Some(path)) => self.find_unreferenced(server, path).await,
+            _ => Err(ServerError::StickyProblem(ErrorDetails::new(
+                ErrorKind::BadQuery,
+                ErrorLayer::BadInput,
+                "l10n-xref needs either an id or `--unreferenced`".to_string(),
+            ))),
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+use clap::Args;
+use tracing::trace;
+
+use super::{
+    data_table::{DataCell, DataColumnType, DataTable},
+    interface::{PipelineCommand, PipelineValues},
+    path_filter::PathFilterArgs,
+    transforms::path_glob_transform,
+};
+use crate::{
+    abstract_server::{AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError},
+    file_format::analysis::FileClassification,
+    languages::{select_formatting, FormatAs},
+};
+
+/// Report the composition of the tree as a DataTable with the number of files,
+/// lines, and bytes per language per top-level directory, further broken down
+/// by the file's classification (normal, test, generated, third_party).  This
+/// uses the indexer's own `select_formatting` logic to decide languages, so the
+/// numbers line up with what searchfox actually indexes rather than what an ad
+/// hoc `cloc` run thinks.
+///
+/// Operates on the FileMatches from `search-files` if piped any, otherwise on
+/// all of the files in the tree (constrained by `path`, if given).
+#[derive(Debug, Args)]
+pub struct LanguageStats {
+    /// Path glob to constrain the files considered when not given FileMatches.
+    #[clap(value_parser)]
+    path: Option<String>,
+
+    /// How many leading path components make up the directory we group by.
+    /// Use 0 to report totals for the whole tree.
+    #[clap(long, value_parser, default_value = "1")]
+    depth: usize,
+
+    #[clap(flatten)]
+    path_filter: PathFilterArgs,
+}
+
+#[derive(Debug)]
+pub struct LanguageStatsCommand {
+    pub args: LanguageStats,
+}
+
+#[derive(Default)]
+struct LanguageTotals {
+    files: i64,
+    lines: i64,
+    bytes: i64,
+}
+
+const FILE_MATCH_LIMIT: usize = 2_000_000;
+
+fn classification_name(classification: FileClassification) -> &'static str {
+    match classification {
+        FileClassification::Normal => "normal",
+        FileClassification::Generated => "generated",
+        FileClassification::ThirdParty => "third_party",
+        FileClassification::Test => "test",
+    }
+}
+
+impl LanguageStatsCommand {
+    fn directory_of(&self, path: &str) -> String {
+        let components: Vec<&str> = path.split('/').collect();
+        // Files at the root of the tree are reported as the empty directory.
+        let dir_len = self.args.depth.min(components.len() - 1);
+        components[..dir_len].join("/")
+    }
+}
+
+#[async_trait]
+impl PipelineCommand for LanguageStatsCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let mut matches = match input {
+            PipelineValues::FileMatches(fm) => fm,
+            PipelineValues::Void => {
+                let pathre = match &self.args.path {
+                    Some(path) => path_glob_transform(path),
+                    None => "".to_string(),
+                };
+                server
+                    .search_files(&pathre, false, FILE_MATCH_LIMIT)
+                    .await?
+            }
+            _ => {
+                return Err(ServerError::StickyProblem(
+                    ErrorDetails::new(
+                        ErrorKind::BadQuery,
+                        ErrorLayer::ConfigLayer,
+                        "language-stats needs FileMatches or no input".to_string(),
+                    )
+                    .with_hint(
+                        "Use `explain` to see what each pipeline stage consumes and produces.",
+                    ),
+                ));
+            }
+        };
+
+        let path_filter = self
+            .args
+            .path_filter
+            .compile(&server.tree_info()?.path_sets)?;
+        if !path_filter.is_empty() {
+            matches
+                .file_matches
+                .retain(|fm| path_filter.matches_classified(&fm.path, fm.concise.classification));
+        }
+
+        let mut totals: BTreeMap<(String, &'static str, &'static str), LanguageTotals> =
+            BTreeMap::new();
+        for fm in &matches.file_matches {
+            if fm.concise.is_dir {
+                continue;
+            }
+            let format = select_formatting(&fm.path);
+            let lines = match format {
+                FormatAs::Binary => 0,
+                _ => match server.fetch_raw_source(&fm.path).await {
+                    Ok(source) => source.lines().count() as i64,
+                    Err(e) => {
+                        // Generated files may not have their source available,
+                        // but we still want to count them.
+                        trace!("language-stats: no source for {}: {:?}", fm.path, e);
+                        0
+                    }
+                },
+            };
+            let entry = totals
+                .entry((
+                    self.directory_of(&fm.path),
+                    format.language_name(),
+                    classification_name(fm.concise.classification),
+                ))
+                .or_default();
+            entry.files += 1;
+            entry.lines += lines;
+            entry.bytes += fm.concise.file_size as i64;
+        }
+
+        let mut table = DataTable::new(vec![
+            ("directory", DataColumnType::Path),
+            ("language", DataColumnType::String),
+            ("classification", DataColumnType::String),
+            ("files", DataColumnType::Int),
+            ("lines", DataColumnType::Int),
+            ("bytes", DataColumnType::Int),
+        ]);
+        for ((directory, language, classification), counts) in totals {
+            table.push_row(vec![
+                DataCell::String(directory),
+                DataCell::String(language.to_string()),
+                DataCell::String(classification.to_string()),
+                DataCell::Int(counts.files),
+                DataCell::Int(counts.lines),
+                DataCell::Int(counts.bytes),
+            ])?;
+        }
+
+        Ok(PipelineValues::DataTable(table))
+    }
+}
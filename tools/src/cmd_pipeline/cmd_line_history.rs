@@ -0,0 +1,105 @@
+use async_trait::async_trait;
+use clap::Args;
+
+use super::{
+    data_table::{DataCell, DataColumnType, DataTable},
+    interface::{PipelineCommand, PipelineValues},
+};
+use crate::abstract_server::{
+    AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError,
+};
+
+/// List the revisions that changed a line, newest first, by following the
+/// line back through the earlier versions of itself that each revision
+/// replaced, and the file through renames, until the revision that added the
+/// line.  Produces a DataTable with each revision's commit time (in seconds
+/// since the epoch), author, and commit message summary, along with the path
+/// and line number of the line as of the revision.  This uses the blame repo,
+/// so it's only supported for local indices with blame.
+#[derive(Debug, Args)]
+pub struct LineHistory {
+    /// The line as `path:line`, ex: "dom/base/Element.cpp:123".
+    #[clap(value_parser)]
+    location: String,
+
+    #[clap(short, long, value_parser, default_value = "100")]
+    limit: usize,
+}
+
+#[derive(Debug)]
+pub struct LineHistoryCommand {
+    pub args: LineHistory,
+}
+
+#[async_trait]
+impl PipelineCommand for LineHistoryCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        if !matches!(input, PipelineValues::Void) {
+            return Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::BadQuery,
+                    ErrorLayer::ConfigLayer,
+                    "line-history needs no input".to_string(),
+                )
+                .with_hint("Use `explain` to see what each pipeline stage consumes and produces."),
+            ));
+        }
+        let Some((path, line)) = self
+            .args
+            .location
+            .rsplit_once(':')
+            .and_then(|(path, line)| Some((path, line.parse::<u32>().ok()?)))
+            .filter(|(_, line)| *line > 0)
+        else {
+            return Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::BadQuery,
+                    ErrorLayer::BadInput,
+                    format!("Bad location {}", self.args.location),
+                )
+                .with_hint("Specify the line as `path:line`, ex: `dom/base/Element.cpp:123`."),
+            ));
+        };
+        if !server.capabilities().blame {
+            return Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::IndexMissingCapability,
+                    ErrorLayer::ConfigLayer,
+                    "line-history requires a local index with blame".to_string(),
+                )
+                .with_hint("Configure the tree's `git_blame_path` and re-index."),
+            ));
+        }
+
+        let entries = server
+            .fetch_line_history(path, line, self.args.limit)
+            .await?;
+
+        let mut table = DataTable::new(vec![
+            ("rev", DataColumnType::String),
+            ("time", DataColumnType::Int),
+            ("author", DataColumnType::String),
+            ("summary", DataColumnType::String),
+            ("path", DataColumnType::Path),
+            ("line", DataColumnType::Int),
+        ]);
+        for entry in entries {
+            table.push_row(vec![
+                DataCell::String(entry.rev),
+                DataCell::Int(entry.time),
+                DataCell::String(entry.author),
+                DataCell::String(entry.summary),
+                DataCell::String(entry.path),
+                entry
+                    .line
+                    .map_or(DataCell::Null, |line| DataCell::Int(line as i64)),
+            ])?;
+        }
+
+        Ok(PipelineValues::DataTable(table))
+    }
+}
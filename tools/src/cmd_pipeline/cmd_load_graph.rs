@@ -0,0 +1,58 @@
+use std::fs::File;
+use std::io::BufReader;
+
+use async_trait::async_trait;
+use clap::Args;
+use serde_json::Value;
+
+use super::{
+    interface::{PipelineCommand, PipelineValues},
+    symbol_graph::SymbolGraphCollection,
+};
+use crate::abstract_server::{
+    AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError,
+};
+
+/// Load a graph snapshot written by `save-graph`, producing the
+/// SymbolGraphCollection as it was when saved, so that stages downstream of
+/// graph construction can be re-run without re-querying the index.
+///
+/// The snapshot includes the crossref data of its symbols as of when it was
+/// saved, so it may be stale relative to a newer index.
+#[derive(Debug, Args)]
+pub struct LoadGraph {
+    /// The graph snapshot to load.
+    #[clap(value_parser)]
+    input: String,
+}
+
+#[derive(Debug)]
+pub struct LoadGraphCommand {
+    pub args: LoadGraph,
+}
+
+#[async_trait]
+impl PipelineCommand for LoadGraphCommand {
+    async fn execute(
+        &self,
+        _server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        if !matches!(input, PipelineValues::Void) {
+            return Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::BadQuery,
+                    ErrorLayer::ConfigLayer,
+                    "load-graph needs no input".to_string(),
+                )
+                .with_hint("Use `explain` to see what each pipeline stage consumes and produces."),
+            ));
+        }
+
+        let file = File::open(&self.args.input)?;
+        let snapshot: Value = serde_json::from_reader(BufReader::new(file))?;
+        Ok(PipelineValues::SymbolGraphCollection(
+            SymbolGraphCollection::from_snapshot(snapshot)?,
+        ))
+    }
+}
@@ -0,0 +1,166 @@
+use async_trait::async_trait;
+use clap::Args;
+use ustr::ustr;
+
+use super::{
+    data_table::{DataCell, DataColumnType, DataTable},
+    interface::{PipelineCommand, PipelineValues},
+};
+use crate::{
+    abstract_server::{
+        AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, SearchfoxIndexRoot,
+        ServerError,
+    },
+    file_format::lock_annotations::{GuardedField, LockAnnotationIndex, LOCK_ANNOTATIONS_FILENAME},
+};
+
+/// Cross-check the tree's thread safety annotations for code health reviews.
+/// By default, list the fields annotated as guarded by a capability (a mutex,
+/// ex: `MOZ_GUARDED_BY(mMutex)`), grouped by capability, with how many of the
+/// functions using each field are missing an annotation.
+///
+/// With `--accessors`, instead list the functions using each field and
+/// whether they're annotated as requiring the capability (ex:
+/// `MOZ_REQUIRES(mMutex)`), reference the capability themselves (presumably
+/// to take the lock), are exempt from the analysis, or are missing an
+/// annotation.  With `--missing`, only list the ones missing an annotation.
+#[derive(Debug, Args)]
+pub struct LockReport {
+    /// Only include fields defined under this path prefix.
+    #[clap(long, value_parser)]
+    path_prefix: Option<String>,
+
+    /// Only include fields guarded by the capability with this pretty name,
+    /// ex: "mozilla::Foo::mMutex".
+    #[clap(long, value_parser)]
+    capability: Option<String>,
+
+    /// List the functions using each field instead of the fields.
+    #[clap(long, value_parser)]
+    accessors: bool,
+
+    /// List only the functions using each field that are missing an
+    /// annotation.  Implies `--accessors`.
+    #[clap(long, value_parser)]
+    missing: bool,
+}
+
+#[derive(Debug)]
+pub struct LockReportCommand {
+    pub args: LockReport,
+}
+
+impl LockReportCommand {
+    fn matches(&self, field: &GuardedField) -> bool {
+        if let Some(prefix) = &self.args.path_prefix {
+            if !field.path.starts_with(prefix.as_str()) {
+                return false;
+            }
+        }
+        self.args
+            .capability
+            .as_ref()
+            .map_or(true, |capability| &field.capability == capability)
+    }
+
+    fn fields_table(&self, fields: &[&GuardedField]) -> Result<DataTable> {
+        let mut table = DataTable::new(vec![
+            ("capability", DataColumnType::String),
+            ("field", DataColumnType::SymbolRef),
+            ("kind", DataColumnType::String),
+            ("path", DataColumnType::Path),
+            ("line", DataColumnType::Int),
+            ("accessors", DataColumnType::Int),
+            ("missing", DataColumnType::Int),
+        ]);
+        for field in fields {
+            let missing = field
+                .accessors
+                .iter()
+                .filter(|accessor| accessor.status == "missing")
+                .count();
+            table.push_row(vec![
+                DataCell::String(field.capability.clone()),
+                DataCell::Symbol(ustr(&field.sym)),
+                DataCell::String(field.kind.clone()),
+                DataCell::String(field.path.clone()),
+                DataCell::Int(field.line as i64),
+                DataCell::Int(field.accessors.len() as i64),
+                DataCell::Int(missing as i64),
+            ])?;
+        }
+        Ok(table)
+    }
+
+    fn accessors_table(&self, fields: &[&GuardedField]) -> Result<DataTable> {
+        let mut table = DataTable::new(vec![
+            ("capability", DataColumnType::String),
+            ("field", DataColumnType::SymbolRef),
+            ("function", DataColumnType::SymbolRef),
+            ("status", DataColumnType::String),
+            ("path", DataColumnType::Path),
+            ("line", DataColumnType::Int),
+        ]);
+        for field in fields {
+            for accessor in &field.accessors {
+                if self.args.missing && accessor.status != "missing" {
+                    continue;
+                }
+                table.push_row(vec![
+                    DataCell::String(field.capability.clone()),
+                    DataCell::Symbol(ustr(&field.sym)),
+                    DataCell::Symbol(ustr(&accessor.sym)),
+                    DataCell::String(accessor.status.clone()),
+                    DataCell::String(accessor.path.clone()),
+                    DataCell::Int(accessor.line as i64),
+                ])?;
+            }
+        }
+        Ok(table)
+    }
+}
+
+#[async_trait]
+impl PipelineCommand for LockReportCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        if !matches!(input, PipelineValues::Void) {
+            return Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::BadQuery,
+                    ErrorLayer::ConfigLayer,
+                    "lock-report needs no input".to_string(),
+                )
+                .with_hint("Use `explain` to see what each pipeline stage consumes and produces."),
+            ));
+        }
+
+        let index_path =
+            server.translate_path(SearchfoxIndexRoot::IndexRoot, LOCK_ANNOTATIONS_FILENAME)?;
+        let Some(index) = LockAnnotationIndex::load(&index_path)? else {
+            return Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::IndexMissingCapability,
+                    ErrorLayer::DataLayer,
+                    "This index has no lock annotation index".to_string(),
+                )
+                .with_hint("Re-index the tree to build `lock-annotations.json`."),
+            ));
+        };
+
+        let fields: Vec<&GuardedField> = index
+            .fields
+            .iter()
+            .filter(|field| self.matches(field))
+            .collect();
+        let table = if self.args.accessors || self.args.missing {
+            self.accessors_table(&fields)?
+        } else {
+            self.fields_table(&fields)?
+        };
+        Ok(PipelineValues::DataTable(table))
+    }
+}
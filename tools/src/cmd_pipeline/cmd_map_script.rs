@@ -0,0 +1,281 @@
+use async_trait::async_trait;
+use clap::{Args, ValueEnum};
+use rhai::{module_resolvers::DummyModuleResolver, Dynamic, Engine, Scope, AST};
+use serde::Serialize;
+use serde_json::Value;
+use tracing::trace;
+
+use super::{
+    data_table::{DataCell, DataColumnType, DataTable},
+    interface::{
+        JsonValue, JsonValueList, PipelineCommand, PipelineValues, SymbolCrossrefInfoList,
+        SymbolList,
+    },
+};
+use crate::abstract_server::{
+    AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum ScriptMode {
+    /// The script returns a bool and we keep the elements it returns true for,
+    /// producing the same type of value we were given.
+    Filter,
+    /// The script returns a new value for each element, producing a
+    /// JsonValueList (or, with `--column`, adding a column to a DataTable).
+    /// Returning `()` drops the element.
+    Map,
+}
+
+/// Run a user-provided rhai script over each element of a JsonValueList,
+/// SymbolList, SymbolCrossrefInfoList, or each row of a DataTable, for ad hoc
+/// filtering and mapping without recompiling the tools.  The element is
+/// available to the script as `item`, converted to rhai via its JSON
+/// representation; DataTable rows are maps keyed by column name.
+///
+/// Scripts run with no access to modules and under limits on the number of
+/// operations, call depth, and the sizes of strings, arrays, and maps.
+#[derive(Debug, Args)]
+pub struct MapScript {
+    /// The rhai script to run for each element.
+    #[clap(value_parser)]
+    script: String,
+
+    #[clap(long, value_parser, value_enum, default_value = "map")]
+    mode: ScriptMode,
+
+    /// In map mode with a DataTable input, add the script's results as a new
+    /// column with this name instead of producing a JsonValueList.
+    #[clap(long, value_parser)]
+    column: Option<String>,
+
+    /// The maximum number of operations the script may perform per element.
+    #[clap(long, value_parser, default_value = "100000")]
+    max_operations: u64,
+}
+
+#[derive(Debug)]
+pub struct MapScriptCommand {
+    pub args: MapScript,
+}
+
+fn script_problem(message: String) -> ServerError {
+    ServerError::StickyProblem(ErrorDetails::new(
+        ErrorKind::BadQuery,
+        ErrorLayer::BadInput,
+        message,
+    ))
+}
+
+fn unsupported_input() -> ServerError {
+    ServerError::StickyProblem(
+        ErrorDetails::new(
+            ErrorKind::BadQuery,
+            ErrorLayer::ConfigLayer,
+            "map-script needs a JsonValueList, SymbolList, SymbolCrossrefInfoList, or DataTable",
+        )
+        .with_hint("Use `explain` to see what each pipeline stage consumes and produces."),
+    )
+}
+
+impl MapScriptCommand {
+    fn make_engine(&self) -> Engine {
+        let mut engine = Engine::new();
+        engine
+            .set_module_resolver(DummyModuleResolver::new())
+            .set_max_operations(self.args.max_operations)
+            .set_max_call_levels(32)
+            .set_max_expr_depths(64, 32)
+            .set_max_string_size(1 << 20)
+            .set_max_array_size(100_000)
+            .set_max_map_size(10_000)
+            .on_print(|s| trace!("map-script print: {}", s))
+            .on_debug(|s, _, _| trace!("map-script debug: {}", s));
+        engine.disable_symbol("eval");
+        engine
+    }
+
+    fn run(&self, engine: &Engine, ast: &AST, item: impl Serialize) -> Result<Dynamic> {
+        let item = rhai::serde::to_dynamic(item).map_err(|e| script_problem(e.to_string()))?;
+        let mut scope = Scope::new();
+        scope.push("item", item);
+        engine
+            .eval_ast_with_scope::<Dynamic>(&mut scope, ast)
+            .map_err(|e| script_problem(format!("map-script error: {}", e)))
+    }
+
+    fn keep(&self, engine: &Engine, ast: &AST, item: impl Serialize) -> Result<bool> {
+        let result = self.run(engine, ast, item)?;
+        result.as_bool().map_err(|type_name| {
+            script_problem(format!(
+                "map-script filters must return a bool, not {}",
+                type_name
+            ))
+        })
+    }
+
+    fn map(&self, engine: &Engine, ast: &AST, item: impl Serialize) -> Result<Option<Value>> {
+        let result = self.run(engine, ast, item)?;
+        if result.is_unit() {
+            return Ok(None);
+        }
+        rhai::serde::from_dynamic::<Value>(&result)
+            .map(Some)
+            .map_err(|e| script_problem(e.to_string()))
+    }
+
+    fn row_value(table: &DataTable, row: &[DataCell]) -> Value {
+        Value::Object(
+            table
+                .columns
+                .iter()
+                .zip(row.iter())
+                .map(|(column, cell)| {
+                    (
+                        column.name.clone(),
+                        serde_json::to_value(cell).unwrap_or(Value::Null),
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    /// Add the script results as a new column, typed by the kinds of values
+    /// the script returned.
+    fn add_column(
+        &self,
+        mut table: DataTable,
+        name: &str,
+        results: Vec<Value>,
+    ) -> Result<DataTable> {
+        let non_null = results.iter().filter(|v| !v.is_null());
+        let column_type = if non_null.clone().all(|v| v.is_i64()) {
+            DataColumnType::Int
+        } else if non_null.clone().all(|v| v.is_number()) {
+            DataColumnType::Float
+        } else {
+            DataColumnType::String
+        };
+        let cells = results
+            .into_iter()
+            .map(|v| match (v, column_type) {
+                (Value::Null, _) => DataCell::Null,
+                (v, DataColumnType::Int) => DataCell::Int(v.as_i64().unwrap_or_default()),
+                (v, DataColumnType::Float) => DataCell::Float(v.as_f64().unwrap_or_default()),
+                (Value::String(s), _) => DataCell::String(s),
+                (v, _) => DataCell::String(v.to_string()),
+            })
+            .collect();
+        table.push_column(name, column_type, cells)?;
+        Ok(table)
+    }
+}
+
+#[async_trait]
+impl PipelineCommand for MapScriptCommand {
+    async fn execute(
+        &self,
+        _server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let engine = self.make_engine();
+        let ast = engine
+            .compile(&self.args.script)
+            .map_err(|e| script_problem(format!("map-script parse error: {}", e)))?;
+
+        if self.args.mode == ScriptMode::Filter {
+            return Ok(match input {
+                PipelineValues::JsonValueList(jvl) => {
+                    let mut values = vec![];
+                    for jv in jvl.values {
+                        if self.keep(&engine, &ast, &jv.value)? {
+                            values.push(jv);
+                        }
+                    }
+                    PipelineValues::JsonValueList(JsonValueList { values })
+                }
+                PipelineValues::SymbolList(sl) => {
+                    let mut symbols = vec![];
+                    for swc in sl.symbols {
+                        if self.keep(&engine, &ast, &swc)? {
+                            symbols.push(swc);
+                        }
+                    }
+                    PipelineValues::SymbolList(SymbolList {
+                        symbols,
+                        page: None,
+                    })
+                }
+                PipelineValues::SymbolCrossrefInfoList(scil) => {
+                    let mut symbol_crossref_infos = vec![];
+                    for sci in scil.symbol_crossref_infos {
+                        if self.keep(&engine, &ast, &sci)? {
+                            symbol_crossref_infos.push(sci);
+                        }
+                    }
+                    PipelineValues::SymbolCrossrefInfoList(SymbolCrossrefInfoList {
+                        symbol_crossref_infos,
+                        unknown_symbols: scil.unknown_symbols,
+                        page: None,
+                    })
+                }
+                PipelineValues::DataTable(mut dt) => {
+                    let mut rows = vec![];
+                    for row in std::mem::take(&mut dt.rows) {
+                        if self.keep(&engine, &ast, Self::row_value(&dt, &row))? {
+                            rows.push(row);
+                        }
+                    }
+                    dt.rows = rows;
+                    PipelineValues::DataTable(dt)
+                }
+                _ => {
+                    return Err(unsupported_input());
+                }
+            });
+        }
+
+        let items: Vec<Value> = match input {
+            PipelineValues::JsonValueList(jvl) => {
+                jvl.values.into_iter().map(|jv| jv.value).collect()
+            }
+            PipelineValues::SymbolList(sl) => sl
+                .symbols
+                .iter()
+                .map(serde_json::to_value)
+                .collect::<serde_json::Result<_>>()?,
+            PipelineValues::SymbolCrossrefInfoList(scil) => scil
+                .symbol_crossref_infos
+                .iter()
+                .map(serde_json::to_value)
+                .collect::<serde_json::Result<_>>()?,
+            PipelineValues::DataTable(dt) => {
+                if let Some(name) = &self.args.column {
+                    let mut results = vec![];
+                    for row in &dt.rows {
+                        let result = self.map(&engine, &ast, Self::row_value(&dt, row))?;
+                        results.push(result.unwrap_or(Value::Null));
+                    }
+                    return Ok(PipelineValues::DataTable(
+                        self.add_column(dt, name, results)?,
+                    ));
+                }
+                dt.rows
+                    .iter()
+                    .map(|row| Self::row_value(&dt, row))
+                    .collect()
+            }
+            _ => {
+                return Err(unsupported_input());
+            }
+        };
+
+        let mut values = vec![];
+        for item in items {
+            if let Some(value) = self.map(&engine, &ast, &item)? {
+                values.push(JsonValue { value });
+            }
+        }
+        Ok(PipelineValues::JsonValueList(JsonValueList { values }))
+    }
+}
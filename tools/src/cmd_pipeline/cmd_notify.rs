@@ -0,0 +1,219 @@
+use std::fs;
+
+use async_trait::async_trait;
+use clap::{Args, ValueEnum};
+use serde_json::{json, Value};
+
+use super::{
+    data_table::DataTable,
+    interface::{PipelineCommand, PipelineValues},
+};
+use crate::abstract_server::{
+    AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum NotifySink {
+    /// POST to a Slack incoming webhook.
+    Slack,
+    /// POST to a Matrix generic webhook (ex: hookshot), which takes both a
+    /// plain text and an HTML version of the message.
+    Matrix,
+    /// Write an RFC 5322 message to `--email-file`, suitable for piping into
+    /// `sendmail -t`.
+    Email,
+}
+
+/// Format a summary of the pipeline's results and send it somewhere owners
+/// will see it, so that scheduled index-time analyses (ex: "padding audit: top
+/// regressions since last week") can alert people automatically.  This is a
+/// terminal command and produces Void.
+#[derive(Debug, Args)]
+pub struct Notify {
+    /// The title of the notification, used as the email subject.
+    #[clap(long, value_parser)]
+    title: String,
+
+    #[clap(long, value_parser, value_enum)]
+    sink: NotifySink,
+
+    /// The webhook URL for the slack and matrix sinks.
+    #[clap(long, value_parser, env = "SEARCHFOX_NOTIFY_WEBHOOK")]
+    webhook_url: Option<String>,
+
+    /// The path to write the message to for the email sink.
+    #[clap(long, value_parser)]
+    email_file: Option<String>,
+
+    /// Recipient for the email sink; may be specified multiple times.
+    #[clap(long, value_parser)]
+    email_to: Vec<String>,
+
+    #[clap(long, value_parser, default_value = "searchfox@localhost")]
+    email_from: String,
+
+    /// The maximum number of items (rows, values, lines) to include in the
+    /// summary.
+    #[clap(long, value_parser, default_value = "20")]
+    max_items: usize,
+}
+
+#[derive(Debug)]
+pub struct NotifyCommand {
+    pub args: Notify,
+}
+
+fn notify_problem(message: String) -> ServerError {
+    ServerError::StickyProblem(ErrorDetails::new(
+        ErrorKind::Internal,
+        ErrorLayer::ConfigLayer,
+        message,
+    ))
+}
+
+/// Summarize a list of lines, noting how many we left out.
+fn summarize_lines(lines: Vec<String>, max_items: usize) -> String {
+    let total = lines.len();
+    let mut summary: Vec<String> = lines.into_iter().take(max_items).collect();
+    if total > max_items {
+        summary.push(format!("... and {} more", total - max_items));
+    }
+    summary.join("\n")
+}
+
+fn summarize_table(table: &DataTable, max_items: usize) -> String {
+    let header = table
+        .columns
+        .iter()
+        .map(|c| c.name.as_str())
+        .collect::<Vec<_>>()
+        .join(" | ");
+    let rows = table
+        .rows
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|cell| cell.to_text())
+                .collect::<Vec<_>>()
+                .join(" | ")
+        })
+        .collect();
+    format!("{}\n{}", header, summarize_lines(rows, max_items))
+}
+
+impl NotifyCommand {
+    fn summarize(&self, input: &PipelineValues) -> String {
+        let max_items = self.args.max_items;
+        match input {
+            PipelineValues::DataTable(dt) => summarize_table(dt, max_items),
+            PipelineValues::TextFile(tf) => summarize_lines(
+                tf.contents.lines().map(|l| l.to_string()).collect(),
+                max_items,
+            ),
+            PipelineValues::JsonValue(jv) => {
+                serde_json::to_string_pretty(&jv.value).unwrap_or_default()
+            }
+            PipelineValues::JsonValueList(jvl) => summarize_lines(
+                jvl.values.iter().map(|jv| jv.value.to_string()).collect(),
+                max_items,
+            ),
+            PipelineValues::SymbolList(sl) => summarize_lines(
+                sl.symbols.iter().map(|s| s.symbol.to_string()).collect(),
+                max_items,
+            ),
+            PipelineValues::SymbolCrossrefInfoList(scil) => summarize_lines(
+                scil.symbol_crossref_infos
+                    .iter()
+                    .map(|sci| sci.get_pretty().to_string())
+                    .collect(),
+                max_items,
+            ),
+            PipelineValues::Void => "(no results)".to_string(),
+            other => format!("(a {})", other.variant_name()),
+        }
+    }
+
+    async fn post_webhook(&self, body: Value) -> Result<()> {
+        let Some(url) = &self.args.webhook_url else {
+            return Err(notify_problem(
+                "notify needs --webhook-url (or SEARCHFOX_NOTIFY_WEBHOOK) for webhook sinks"
+                    .to_string(),
+            ));
+        };
+        let res = reqwest::Client::new()
+            .post(url)
+            .header("Content-Type", "application/json")
+            .body(body.to_string())
+            .send()
+            .await
+            .map_err(|e| notify_problem(format!("notify webhook failed: {}", e)))?;
+        if !res.status().is_success() {
+            return Err(notify_problem(format!(
+                "notify webhook returned {}",
+                res.status()
+            )));
+        }
+        Ok(())
+    }
+
+    fn write_email(&self, summary: &str) -> Result<()> {
+        let Some(path) = &self.args.email_file else {
+            return Err(notify_problem(
+                "notify needs --email-file for the email sink".to_string(),
+            ));
+        };
+        if self.args.email_to.is_empty() {
+            return Err(notify_problem(
+                "notify needs at least one --email-to for the email sink".to_string(),
+            ));
+        }
+        let message = format!(
+            "From: {}\r\nTo: {}\r\nSubject: {}\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n{}\r\n",
+            self.args.email_from,
+            self.args.email_to.join(", "),
+            self.args.title,
+            summary.replace('\n', "\r\n"),
+        );
+        fs::write(path, message)?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl PipelineCommand for NotifyCommand {
+    async fn execute(
+        &self,
+        _server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let summary = self.summarize(&input);
+        match self.args.sink {
+            NotifySink::Slack => {
+                self.post_webhook(json!({
+                    "text": format!("*{}*\n```\n{}\n```", self.args.title, summary),
+                }))
+                .await?
+            }
+            NotifySink::Matrix => {
+                self.post_webhook(json!({
+                    "text": format!("{}\n{}", self.args.title, summary),
+                    "html": format!(
+                        "<strong>{}</strong><pre>{}</pre>",
+                        escape_html(&self.args.title),
+                        escape_html(&summary)
+                    ),
+                }))
+                .await?
+            }
+            NotifySink::Email => self.write_email(&summary)?,
+        }
+
+        Ok(PipelineValues::Void)
+    }
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
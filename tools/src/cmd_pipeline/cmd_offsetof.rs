@@ -0,0 +1,198 @@
+use async_trait::async_trait;
+use clap::Args;
+use serde_json::{json, Value};
+
+use super::{
+    interface::{JsonValue, PipelineCommand, PipelineValues},
+    record_layouts::{
+        field_name, layout_for_platform, load_record, lookup_record_syms, record_platforms,
+    },
+};
+use crate::abstract_server::{
+    AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError,
+};
+
+/// Look up the offset of a field within a class for each platform, like C's
+/// `offsetof`, so that debugger scripts and memory-dump analyzers can ask the
+/// index instead of recomputing layouts from headers.  Fields inherited from
+/// superclasses are found through the superclass offsets, and a dotted field
+/// path like `mRect.width` descends into the types of the fields.
+///
+/// Produces a JsonValue with the byte offset and size of the field for each
+/// group of platforms where they're the same; bitfields instead have their bit
+/// offset (from the start of the class) and width.
+///
+/// ```json
+/// { "class": "nsIFrame", "field": "mRect.width", "offsets": [
+///   { "sym": "T_nsIFrame", "platforms": ["linux64", "macosx64"],
+///     "offsetBytes": 40, "sizeBytes": 4 } ] }
+/// ```
+#[derive(Debug, Args)]
+pub struct Offsetof {
+    /// The class, as a pretty name (ex: "mozilla::dom::Element") or symbol.
+    #[clap(value_parser)]
+    class: String,
+
+    /// The field name, or a dotted path of field names to descend into fields.
+    #[clap(value_parser)]
+    field: String,
+
+    /// Report each platform separately instead of grouping platforms with the
+    /// same offset.
+    #[clap(long, value_parser)]
+    ungrouped: bool,
+}
+
+#[derive(Debug)]
+pub struct OffsetofCommand {
+    pub args: Offsetof,
+}
+
+/// Where a field ended up, relative to the start of the outermost class.
+#[derive(Clone, Debug, PartialEq)]
+struct FieldOffset {
+    offset_bytes: u32,
+    size_bytes: Option<u32>,
+    /// (bit offset, bit width) for bitfields.
+    bits: Option<(u32, u32)>,
+}
+
+impl FieldOffset {
+    fn to_json(&self) -> Value {
+        match self.bits {
+            Some((bit_offset, bit_width)) => json!({
+                "offsetBytes": self.offset_bytes,
+                "bitOffset": bit_offset,
+                "bitWidth": bit_width,
+            }),
+            None => json!({
+                "offsetBytes": self.offset_bytes,
+                "sizeBytes": self.size_bytes,
+            }),
+        }
+    }
+}
+
+fn offsetof_problem(message: String) -> ServerError {
+    ServerError::StickyProblem(ErrorDetails::new(
+        ErrorKind::NotFound,
+        ErrorLayer::DataLayer,
+        message,
+    ))
+}
+
+impl OffsetofCommand {
+    /// Resolve the field path within the class on the given platform,
+    /// searching superclasses breadth-first for each path segment.
+    async fn resolve(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        class_sym: &str,
+        platform: Option<&str>,
+    ) -> Result<Option<FieldOffset>> {
+        let mut owner = class_sym.to_string();
+        let mut base = 0;
+        let mut found = None;
+        for segment in self.args.field.split('.') {
+            let mut pending = vec![(owner.clone(), base)];
+            let mut hit = None;
+            while !pending.is_empty() && hit.is_none() {
+                let (sym, sym_base) = pending.remove(0);
+                let Some(record) = load_record(server, &sym).await? else {
+                    continue;
+                };
+                let layout = layout_for_platform(&record, platform);
+                if let Some(field) = layout.fields.iter().find(|f| field_name(f) == segment) {
+                    hit = Some((sym_base, field.clone()));
+                } else {
+                    pending.extend(
+                        layout
+                            .supers
+                            .into_iter()
+                            .map(|(sup, offset)| (sup, sym_base + offset)),
+                    );
+                }
+            }
+            let Some((field_base, field)) = hit else {
+                return Ok(None);
+            };
+            let offset_bytes = field_base + field.offset_bytes;
+            found = Some(FieldOffset {
+                offset_bytes,
+                size_bytes: field.size_bytes,
+                bits: field
+                    .bit_positions
+                    .as_ref()
+                    .map(|bits| (offset_bytes * 8 + bits.begin, bits.width)),
+            });
+            owner = field.type_sym.to_string();
+            base = offset_bytes;
+        }
+        Ok(found)
+    }
+}
+
+#[async_trait]
+impl PipelineCommand for OffsetofCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        _input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let class_syms = lookup_record_syms(server, &self.args.class).await?;
+        if class_syms.is_empty() {
+            return Err(offsetof_problem(format!(
+                "No layout data found for class {}",
+                self.args.class
+            )));
+        }
+
+        let mut offsets = vec![];
+        for sym in class_syms {
+            let Some(record) = load_record(server, &sym).await? else {
+                continue;
+            };
+
+            // Group the platforms by their result, keeping the platform order.
+            let mut groups: Vec<(FieldOffset, Vec<String>)> = vec![];
+            for platform in record_platforms(&record) {
+                let Some(result) = self.resolve(server, &sym, platform.as_deref()).await? else {
+                    continue;
+                };
+                let label = platform.unwrap_or_else(|| "*".to_string());
+                match groups.iter_mut().find(|(r, _)| *r == result) {
+                    Some((_, labels)) if !self.args.ungrouped => labels.push(label),
+                    _ => groups.push((result, vec![label])),
+                }
+            }
+
+            for (result, platforms) in groups {
+                let mut entry = json!({
+                    "sym": sym,
+                    "platforms": platforms,
+                });
+                if let (Value::Object(entry), Value::Object(result)) =
+                    (&mut entry, result.to_json())
+                {
+                    entry.extend(result);
+                }
+                offsets.push(entry);
+            }
+        }
+
+        if offsets.is_empty() {
+            return Err(offsetof_problem(format!(
+                "No field {} found in {}",
+                self.args.field, self.args.class
+            )));
+        }
+
+        Ok(PipelineValues::JsonValue(JsonValue {
+            value: json!({
+                "class": self.args.class,
+                "field": self.args.field,
+                "offsets": offsets,
+            }),
+        }))
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use clap::Args;
+
+use super::{
+    data_table::{DataCell, DataColumnType, DataTable},
+    interface::{PipelineCommand, PipelineValues},
+    path_filter::PathFilterArgs,
+    transforms::path_glob_transform,
+};
+use crate::abstract_server::{
+    AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, FileMatch, Result, ServerError,
+};
+
+/// Report who owns each directory and how much it has been changing as a
+/// DataTable with, per top-level directory and owner, the number of files and
+/// lines along with the recent churn from blame: how many of the lines were
+/// last changed within `--since-days`, by how many distinct revisions, and by
+/// how many distinct authors.  Owners are the Bugzilla components from the
+/// repo's metadata (ex: `moz.build` files), falling back to the subsystem.
+/// This is useful for module owners planning review load.  Requires blame
+/// data, so it's only supported for local indices with a blame repo.
+///
+/// Operates on the FileMatches from `search-files` if piped any, otherwise on
+/// all of the files in the tree (constrained by `path`, if given).
+#[derive(Debug, Args)]
+pub struct OwnershipReport {
+    /// Path glob to constrain the files considered when not given FileMatches.
+    #[clap(value_parser)]
+    path: Option<String>,
+
+    /// How many leading path components make up the directory we group by.
+    /// Use 0 to report totals per owner for the whole tree.
+    #[clap(long, value_parser, default_value = "1")]
+    depth: usize,
+
+    /// How many days back a change counts as recent churn.
+    #[clap(long, value_parser, default_value = "90")]
+    since_days: u64,
+
+    #[clap(flatten)]
+    path_filter: PathFilterArgs,
+}
+
+#[derive(Debug)]
+pub struct OwnershipReportCommand {
+    pub args: OwnershipReport,
+}
+
+#[derive(Default)]
+struct OwnerTotals {
+    files: i64,
+    lines: i64,
+    changed_lines: i64,
+    revisions: BTreeSet<String>,
+    authors: BTreeSet<String>,
+}
+
+const FILE_MATCH_LIMIT: usize = 2_000_000;
+
+fn owner_of(fm: &FileMatch) -> String {
+    match (&fm.concise.bugzilla_component, &fm.concise.subsystem) {
+        (Some((product, component)), _) => format!("{} :: {}", product, component),
+        (None, Some(subsystem)) => subsystem.to_string(),
+        (None, None) => "(unowned)".to_string(),
+    }
+}
+
+impl OwnershipReportCommand {
+    fn directory_of(&self, path: &str) -> String {
+        let components: Vec<&str> = path.split('/').collect();
+        // Files at the root of the tree are reported as the empty directory.
+        let dir_len = self.args.depth.min(components.len() - 1);
+        components[..dir_len].join("/")
+    }
+}
+
+#[async_trait]
+impl PipelineCommand for OwnershipReportCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        if !server.capabilities().blame {
+            return Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::IndexMissingCapability,
+                    ErrorLayer::ConfigLayer,
+                    "ownership-report requires a local index with blame".to_string(),
+                )
+                .with_hint("Configure the tree's `git_blame_path` and re-index."),
+            ));
+        }
+
+        let mut matches = match input {
+            PipelineValues::FileMatches(fm) => fm,
+            PipelineValues::Void => {
+                let pathre = match &self.args.path {
+                    Some(path) => path_glob_transform(path),
+                    None => "".to_string(),
+                };
+                server
+                    .search_files(&pathre, false, FILE_MATCH_LIMIT)
+                    .await?
+            }
+            _ => {
+                return Err(ServerError::StickyProblem(
+                    ErrorDetails::new(
+                        ErrorKind::BadQuery,
+                        ErrorLayer::ConfigLayer,
+                        "ownership-report needs FileMatches or no input".to_string(),
+                    )
+                    .with_hint(
+                        "Use `explain` to see what each pipeline stage consumes and produces.",
+                    ),
+                ));
+            }
+        };
+
+        let path_filter = self
+            .args
+            .path_filter
+            .compile(&server.tree_info()?.path_sets)?;
+        if !path_filter.is_empty() {
+            matches
+                .file_matches
+                .retain(|fm| path_filter.matches_classified(&fm.path, fm.concise.classification));
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs() as i64);
+        let since = now - (self.args.since_days * 24 * 60 * 60) as i64;
+
+        let mut totals: BTreeMap<(String, String), OwnerTotals> = BTreeMap::new();
+        for fm in &matches.file_matches {
+            if fm.concise.is_dir {
+                continue;
+            }
+            // Generated files and files added since the blame was built have no
+            // blame, but they still count towards their owner.
+            let blame = server.fetch_blame(&fm.path).await.unwrap_or_default();
+            let entry = totals
+                .entry((self.directory_of(&fm.path), owner_of(fm)))
+                .or_default();
+            entry.files += 1;
+            entry.lines += blame.len() as i64;
+            for line in blame.into_iter().filter(|line| line.time >= since) {
+                entry.changed_lines += 1;
+                entry.revisions.insert(line.rev);
+                entry.authors.insert(line.author);
+            }
+        }
+
+        let mut table = DataTable::new(vec![
+            ("directory", DataColumnType::Path),
+            ("owner", DataColumnType::String),
+            ("files", DataColumnType::Int),
+            ("lines", DataColumnType::Int),
+            ("changed_lines", DataColumnType::Int),
+            ("revisions", DataColumnType::Int),
+            ("authors", DataColumnType::Int),
+        ]);
+        for ((directory, owner), counts) in totals {
+            table.push_row(vec![
+                DataCell::String(directory),
+                DataCell::String(owner),
+                DataCell::Int(counts.files),
+                DataCell::Int(counts.lines),
+                DataCell::Int(counts.changed_lines),
+                DataCell::Int(counts.revisions.len() as i64),
+                DataCell::Int(counts.authors.len() as i64),
+            ])?;
+        }
+
+        Ok(PipelineValues::DataTable(table))
+    }
+}
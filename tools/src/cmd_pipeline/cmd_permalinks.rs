@@ -0,0 +1,59 @@
+use async_trait::async_trait;
+use clap::Args;
+use serde_json::{to_value, Value};
+
+use super::{
+    interface::{JsonValue, PipelineCommand, PipelineValues},
+    permalink::PermalinkBuilder,
+};
+use crate::abstract_server::{AbstractServer, Result};
+
+/// Augment every file/line and symbol reference in the input with a canonical
+/// searchfox URL so the results can be pasted into bugs as-is.  File links are
+/// pinned to the revision the tree was indexed at when it's known.
+///
+/// The input is converted to JSON, so this should be the last stage of a
+/// pipeline.
+#[derive(Debug, Args)]
+pub struct Permalinks {
+    /// The searchfox origin to use for links, without a trailing `/`.
+    #[clap(long, value_parser, default_value = "https://searchfox.org")]
+    base_url: String,
+
+    /// Pin file links to this revision instead of the tree's indexed revision.
+    #[clap(long, value_parser)]
+    rev: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct PermalinksCommand {
+    pub args: Permalinks,
+}
+
+#[async_trait]
+impl PipelineCommand for PermalinksCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let tree_info = server.tree_info()?;
+        let builder = PermalinkBuilder {
+            base_url: self.args.base_url.trim_end_matches('/').to_string(),
+            tree_name: tree_info.name,
+            revision: self.args.rev.clone().or(tree_info.revision),
+        };
+
+        // PipelineValues serializes as `{ "Variant": payload }`; we only want
+        // the payload.
+        let mut value = match to_value(input)? {
+            Value::Object(mut obj) if obj.len() == 1 => {
+                obj.values_mut().next().map(Value::take).unwrap_or_default()
+            }
+            other => other,
+        };
+        builder.augment(&mut value);
+
+        Ok(PipelineValues::JsonValue(JsonValue { value }))
+    }
+}
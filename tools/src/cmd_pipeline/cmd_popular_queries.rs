@@ -0,0 +1,59 @@
+use async_trait::async_trait;
+use clap::Args;
+
+use super::{
+    data_table::{DataCell, DataColumnType, DataTable},
+    interface::{PipelineCommand, PipelineValues},
+};
+use crate::abstract_server::{AbstractServer, Result};
+
+/// Report the most popular symbols and files per the query logs ingested by
+/// `ingest-query-logs` as a DataTable of (kind, symbol, path, hits), most
+/// popular first within each kind.  Each row has either a symbol or a path.
+#[derive(Debug, Args)]
+pub struct PopularQueries {
+    /// How many symbols and how many paths to report.
+    #[clap(long, value_parser, default_value = "50")]
+    limit: usize,
+}
+
+#[derive(Debug)]
+pub struct PopularQueriesCommand {
+    pub args: PopularQueries,
+}
+
+#[async_trait]
+impl PipelineCommand for PopularQueriesCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        _input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let popular = server.popular_queries(self.args.limit)?;
+
+        let mut table = DataTable::new(vec![
+            ("kind", DataColumnType::String),
+            ("symbol", DataColumnType::String),
+            ("path", DataColumnType::Path),
+            ("hits", DataColumnType::Int),
+        ]);
+        for (name, hits) in popular.symbols {
+            table.push_row(vec![
+                DataCell::String("symbol".to_string()),
+                DataCell::String(name),
+                DataCell::Null,
+                DataCell::Int(hits as i64),
+            ])?;
+        }
+        for (name, hits) in popular.paths {
+            table.push_row(vec![
+                DataCell::String("path".to_string()),
+                DataCell::Null,
+                DataCell::String(name),
+                DataCell::Int(hits as i64),
+            ])?;
+        }
+
+        Ok(PipelineValues::DataTable(table))
+    }
+}
@@ -0,0 +1,110 @@
+use std::fs;
+
+use async_trait::async_trait;
+use clap::Args;
+use serde_json::json;
+
+use super::interface::{JsonValue, PipelineCommand, PipelineValues};
+use crate::{
+    abstract_server::{AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError},
+    query::pipeline_cache::{clear_pipeline_cache, precompute_presets},
+};
+
+/// Run expensive query presets (ex: `layout-audit`, `callers`) for a list of
+/// hot symbols at index time, storing their outputs in the pipeline cache (see
+/// `query/pipeline_cache.rs`) so that the pipeline-server's first hit on those
+/// pages is as fast as any other.  The presets and symbols default to the
+/// tree's `precompute` config, which can also include the most popular
+/// symbols from `ingest-query-logs`.  This is run by `precompute.sh` as part of
+/// indexing.  Produces a JsonValue summarizing the runs.
+#[derive(Debug, Args)]
+pub struct Precompute {
+    /// The preset(s) to run instead of those from the tree's config.  Each must
+    /// take exactly one parameter without a default.
+    #[clap(long, value_parser)]
+    preset: Vec<String>,
+
+    /// Hot symbol(s) to run the presets for in addition to those from the
+    /// tree's config.
+    #[clap(long, value_parser)]
+    symbol: Vec<String>,
+
+    /// A file listing additional hot symbols one per line.  Blank lines and
+    /// lines starting with `#` are ignored.
+    #[clap(long, value_parser)]
+    symbols_file: Option<String>,
+
+    /// Delete all previously cached outputs first.
+    #[clap(long, value_parser)]
+    clear: bool,
+}
+
+#[derive(Debug)]
+pub struct PrecomputeCommand {
+    pub args: Precompute,
+}
+
+fn read_symbols_file(path: &str, symbols: &mut Vec<String>) -> Result<()> {
+    let contents = fs::read_to_string(path).map_err(|e| {
+        ServerError::StickyProblem(ErrorDetails::new(
+            ErrorKind::Internal,
+            ErrorLayer::ConfigLayer,
+            format!("Unable to read precompute symbols file {}: {}", path, e),
+        ))
+    })?;
+    symbols.extend(
+        contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string),
+    );
+    Ok(())
+}
+
+#[async_trait]
+impl PipelineCommand for PrecomputeCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        _input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let config = server.tree_info()?.precompute;
+
+        let presets = if self.args.preset.is_empty() {
+            config.presets
+        } else {
+            self.args.preset.clone()
+        };
+
+        let mut symbols = config.symbols;
+        symbols.extend(self.args.symbol.iter().cloned());
+        for path in config.symbols_file.iter().chain(&self.args.symbols_file) {
+            read_symbols_file(path, &mut symbols)?;
+        }
+        if config.popular_symbols > 0 {
+            let popular = server.popular_queries(config.popular_symbols)?;
+            symbols.extend(popular.symbols.into_iter().map(|(symbol, _)| symbol));
+        }
+        symbols.sort();
+        symbols.dedup();
+
+        if self.args.clear {
+            clear_pipeline_cache(server)?;
+        }
+
+        let outcomes = precompute_presets(server, &presets, &symbols).await?;
+        let failed = outcomes
+            .iter()
+            .filter(|outcome| outcome.error.is_some())
+            .count();
+
+        Ok(PipelineValues::JsonValue(JsonValue {
+            value: json!({
+                "cached": outcomes.len() - failed,
+                "failed": failed,
+                "runs": outcomes,
+            }),
+        }))
+    }
+}
@@ -1,28 +1,64 @@
+use std::collections::BTreeMap;
+
 use async_trait::async_trait;
-use clap::Args;
-use serde_json::to_value;
+use clap::{Args, Subcommand};
+use serde_json::{json, to_value};
 
 use super::{
-    builder::build_pipeline_graph,
+    builder::{build_pipeline_graph, CommandSafetyLevel},
     interface::{JsonValue, PipelineCommand, PipelineValues},
 };
 use crate::{
-    abstract_server::{AbstractServer, Result},
-    query::chew_query::chew_query,
+    abstract_server::{AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError},
+    query::{
+        chew_query::chew_query,
+        presets::{all_presets, expand_preset, run_preset, ExpandedPreset},
+    },
 };
 
 /// Run a new-style `query-parser` `term:value` query parse against the local
 /// index.  Remote server is currently a no-op, but when supported the entire
 /// query will be run on the server (because we want to test the server).
+///
+/// Named presets (see `query_presets.toml` and the tree's `query_presets`
+/// config) can be run via `query run <name> --param key=value`.
 #[derive(Debug, Args)]
+#[clap(args_conflicts_with_subcommands = true)]
 pub struct Query {
     /// Query string
     #[clap(value_parser)]
-    query: String,
+    query: Option<String>,
 
     /// Output the constructed pipeline instead of running the pipeline.
-    #[clap(short, long, value_parser)]
+    #[clap(short, long, value_parser, global = true)]
     dump_pipeline: bool,
+
+    #[clap(subcommand)]
+    action: Option<QueryAction>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum QueryAction {
+    /// Run a named query preset.
+    Run(QueryRun),
+}
+
+#[derive(Debug, Args)]
+pub struct QueryRun {
+    /// The name of the preset to run.
+    #[clap(value_parser)]
+    name: String,
+
+    /// Preset parameters of the form `key=value`; may be repeated.
+    #[clap(long, value_parser = parse_param)]
+    param: Vec<(String, String)>,
+}
+
+pub fn parse_param(s: &str) -> std::result::Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((key, value)) => Ok((key.to_string(), value.to_string())),
+        None => Err(format!("expected key=value but got: {}", s)),
+    }
 }
 
 #[derive(Debug)]
@@ -37,7 +73,38 @@ impl PipelineCommand for QueryCommand {
         server: &(dyn AbstractServer + Send + Sync),
         _input: PipelineValues,
     ) -> Result<PipelineValues> {
-        let pipeline_plan = chew_query(&self.args.query)?;
+        if let Some(QueryAction::Run(run)) = &self.args.action {
+            let params: BTreeMap<String, String> = run.param.iter().cloned().collect();
+            if self.args.dump_pipeline {
+                let presets = all_presets(&server.tree_info()?.query_presets);
+                let preset = presets.get(&run.name).ok_or_else(|| {
+                    ServerError::StickyProblem(
+                        ErrorDetails::new(
+                            ErrorKind::NotFound,
+                            ErrorLayer::BadInput,
+                            format!("No such preset: {}", run.name),
+                        )
+                        .with_context("preset", &run.name),
+                    )
+                })?;
+                let value = match expand_preset(&run.name, preset, &params)? {
+                    ExpandedPreset::Query(q) => json!({ "query": q }),
+                    ExpandedPreset::Pipeline(p) => json!({ "pipeline": p }),
+                    ExpandedPreset::Spec { path, vars } => json!({ "spec": path, "vars": vars }),
+                };
+                return Ok(PipelineValues::JsonValue(JsonValue { value }));
+            }
+            return run_preset(server, &run.name, &params).await;
+        }
+
+        let Some(query) = &self.args.query else {
+            return Err(ServerError::StickyProblem(ErrorDetails::new(
+                ErrorKind::BadQuery,
+                ErrorLayer::BadInput,
+                "query needs a query string or `run <preset>`".to_string(),
+            )));
+        };
+        let pipeline_plan = chew_query(query)?;
 
         if self.args.dump_pipeline {
             return Ok(PipelineValues::JsonValue(JsonValue {
@@ -45,7 +112,11 @@ impl PipelineCommand for QueryCommand {
             }));
         }
 
-        let graph = build_pipeline_graph(server.clonify(), pipeline_plan)?;
+        let graph = build_pipeline_graph(
+            server.clonify(),
+            pipeline_plan,
+            CommandSafetyLevel::WebSafety,
+        )?;
 
         graph.run(true).await
     }
@@ -0,0 +1,237 @@
+use std::collections::{hash_map::Entry, HashMap, VecDeque};
+
+use async_trait::async_trait;
+use clap::{Args, ValueEnum};
+
+use super::{
+    data_table::{DataCell, DataColumnType, DataTable},
+    interface::{PipelineCommand, PipelineValues},
+    symbol_graph::{EdgeKind, SymbolGraphCollection, SymbolGraphNodeId},
+};
+use crate::abstract_server::{
+    AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum ReachableEdgeKind {
+    /// Calls and uses.
+    Default,
+    Inheritance,
+    Implementation,
+    Composition,
+    Aggregation,
+    Ipc,
+    CrossLanguage,
+}
+
+impl ReachableEdgeKind {
+    fn matches(&self, kind: &EdgeKind) -> bool {
+        matches!(
+            (self, kind),
+            (ReachableEdgeKind::Default, EdgeKind::Default)
+                | (ReachableEdgeKind::Inheritance, EdgeKind::Inheritance)
+                | (ReachableEdgeKind::Implementation, EdgeKind::Implementation)
+                | (ReachableEdgeKind::Composition, EdgeKind::Composition)
+                | (ReachableEdgeKind::Aggregation, EdgeKind::Aggregation)
+                | (ReachableEdgeKind::Ipc, EdgeKind::IPC)
+                | (ReachableEdgeKind::CrossLanguage, EdgeKind::CrossLanguage)
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum ReachableShow {
+    /// Both the reachable symbols and the unreachable candidates.
+    All,
+    Reachable,
+    /// Only the symbols that can't be reached from any root, ex: for dead code
+    /// triage.
+    Unreachable,
+}
+
+/// Given a SymbolGraphCollection (ex: from `traverse --edge=callees` over a
+/// set of candidate symbols), compute which of its symbols are reachable from
+/// the given root symbols (ex: `main`, XPCOM module constructors, IPC message
+/// handlers) and at what distance, as well as the complement of symbols which
+/// are not reachable from any root.  Produces a DataTable with a row per
+/// symbol, reachable symbols first in order of distance.
+///
+/// Note that the complement is only as complete as the graph, so the graph
+/// should be built from the full set of candidates being triaged.
+#[derive(Debug, Args)]
+pub struct ReachableFrom {
+    /// Root symbols, identified by their symbol or pretty identifier.  May be
+    /// specified multiple times.
+    #[clap(long, value_parser, required = true)]
+    root: Vec<String>,
+
+    /// Only follow edges of this kind; may be specified multiple times.  By
+    /// default all edges are followed.
+    #[clap(long, value_parser, value_enum)]
+    edge_kind: Vec<ReachableEdgeKind>,
+
+    /// Follow edges backwards, computing what can reach the roots instead of
+    /// what the roots can reach.  This is useful for attack surface review
+    /// where the roots are sensitive sinks.
+    #[clap(long, value_parser)]
+    reverse: bool,
+
+    /// Maximum distance from a root to consider reachable.  0 means no limit.
+    #[clap(long, value_parser, default_value = "0")]
+    max_distance: u32,
+
+    #[clap(long, value_parser, value_enum, default_value = "all")]
+    show: ReachableShow,
+}
+
+#[derive(Debug)]
+pub struct ReachableFromCommand {
+    pub args: ReachableFrom,
+}
+
+impl ReachableFromCommand {
+    /// Map the `--root` arguments to graph nodes, erroring if any of them are
+    /// not in the graph since that almost certainly means the graph was built
+    /// from the wrong inputs.
+    fn resolve_roots(&self, sgc: &SymbolGraphCollection) -> Result<Vec<SymbolGraphNodeId>> {
+        let mut roots = vec![];
+        for root in &self.args.root {
+            let node_ids = sgc.node_set.lookup_symbol_or_pretty(root);
+            if node_ids.is_empty() {
+                return Err(ServerError::StickyProblem(ErrorDetails::new(
+                    ErrorKind::NotFound,
+                    ErrorLayer::BadInput,
+                    format!("reachable-from root {} is not in the graph", root),
+                )));
+            }
+            roots.extend(node_ids);
+        }
+        Ok(roots)
+    }
+}
+
+#[async_trait]
+impl PipelineCommand for ReachableFromCommand {
+    async fn execute(
+        &self,
+        _server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let sgc = match input {
+            PipelineValues::SymbolGraphCollection(sgc) => sgc,
+            _ => {
+                return Err(ServerError::StickyProblem(
+                    ErrorDetails::new(
+                        ErrorKind::BadQuery,
+                        ErrorLayer::ConfigLayer,
+                        "reachable-from needs a SymbolGraphCollection".to_string(),
+                    )
+                    .with_hint(
+                        "Use `explain` to see what each pipeline stage consumes and produces.",
+                    ),
+                ));
+            }
+        };
+
+        // Union all of the graphs into a single adjacency map, keeping the
+        // nodes in first-seen order so our output is stable.
+        let mut nodes = vec![];
+        let mut adjacency: HashMap<SymbolGraphNodeId, Vec<SymbolGraphNodeId>> = HashMap::new();
+        for graph in &sgc.graphs {
+            for node_id in graph.list_nodes() {
+                if let Entry::Vacant(e) = adjacency.entry(node_id.clone()) {
+                    e.insert(vec![]);
+                    nodes.push(node_id);
+                }
+            }
+            for (source, target, edge_id) in graph.list_edges() {
+                let kind = &sgc.edge_set.get(&edge_id).kind;
+                if !self.args.edge_kind.is_empty()
+                    && !self.args.edge_kind.iter().any(|k| k.matches(kind))
+                {
+                    continue;
+                }
+                let (from, to) = if self.args.reverse {
+                    (target, source)
+                } else {
+                    (source, target)
+                };
+                adjacency.entry(from).or_default().push(to);
+            }
+        }
+
+        let roots = self.resolve_roots(&sgc)?;
+
+        // Multi-source breadth-first search, remembering the distance and the
+        // closest root for each reached node.
+        let mut reached: HashMap<SymbolGraphNodeId, (u32, SymbolGraphNodeId)> = HashMap::new();
+        let mut pending = VecDeque::new();
+        for root in roots {
+            if let Entry::Vacant(e) = reached.entry(root.clone()) {
+                e.insert((0, root.clone()));
+                pending.push_back(root);
+            }
+        }
+        while let Some(node_id) = pending.pop_front() {
+            let (distance, root) = reached[&node_id].clone();
+            if self.args.max_distance != 0 && distance >= self.args.max_distance {
+                continue;
+            }
+            for next_id in adjacency.get(&node_id).into_iter().flatten() {
+                if let Entry::Vacant(e) = reached.entry(next_id.clone()) {
+                    e.insert((distance + 1, root.clone()));
+                    pending.push_back(next_id.clone());
+                }
+            }
+        }
+
+        let mut table = DataTable::new(vec![
+            ("symbol", DataColumnType::SymbolRef),
+            ("pretty", DataColumnType::String),
+            ("path", DataColumnType::Path),
+            ("distance", DataColumnType::Int),
+            ("root", DataColumnType::SymbolRef),
+        ]);
+        let mut rows: Vec<(Option<u32>, Vec<DataCell>)> = vec![];
+        for node_id in &nodes {
+            let info = sgc.node_set.get(node_id);
+            let reach = reached.get(node_id);
+            let wanted = match self.args.show {
+                ReachableShow::All => true,
+                ReachableShow::Reachable => reach.is_some(),
+                ReachableShow::Unreachable => reach.is_none(),
+            };
+            if !wanted {
+                continue;
+            }
+            let path_cell = match info.get_def_path() {
+                Some(path) => DataCell::String(path.clone()),
+                None => DataCell::Null,
+            };
+            let (distance_cell, root_cell) = match reach {
+                Some((distance, root)) => (
+                    DataCell::Int(*distance as i64),
+                    DataCell::Symbol(sgc.node_set.get(root).symbol),
+                ),
+                None => (DataCell::Null, DataCell::Null),
+            };
+            rows.push((
+                reach.map(|(distance, _)| *distance),
+                vec![
+                    DataCell::Symbol(info.symbol),
+                    DataCell::String(info.get_pretty().to_string()),
+                    path_cell,
+                    distance_cell,
+                    root_cell,
+                ],
+            ));
+        }
+        // Reachable symbols by distance, then the unreachable ones.
+        rows.sort_by_key(|(distance, _)| distance.unwrap_or(u32::MAX));
+        for (_, row) in rows {
+            table.push_row(row)?;
+        }
+
+        Ok(PipelineValues::DataTable(table))
+    }
+}
@@ -0,0 +1,286 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use async_trait::async_trait;
+use clap::{Args, ValueEnum};
+use petgraph::{algo::tarjan_scc, graph::DiGraph};
+use ustr::ustr;
+
+use super::{
+    data_table::{DataCell, DataColumnType, DataTable},
+    interface::{PipelineCommand, PipelineValues, TextFile},
+};
+use crate::{
+    abstract_server::{
+        AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, SearchfoxIndexRoot,
+        ServerError,
+    },
+    file_format::refcount_graph::{OwnershipEdge, RefcountGraph, REFCOUNT_GRAPH_FILENAME},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum RefcountCyclesFormat {
+    /// A DataTable with a row per member edge of each cycle.
+    Table,
+    /// A graphviz DOT TextFile with a cluster per cycle.
+    Dot,
+}
+
+/// Report candidate reference cycles between refcounted classes, which are
+/// the strongly connected components of the ownership graph whose edges are
+/// the members holding strong references, ex: `RefPtr<Foo>` and
+/// `nsCOMPtr<nsIFoo>` fields, as indexed by crossref into
+/// `refcount-graph.json` from the field types and the ontology's pointer and
+/// refcount rules.  Each cycle lists its member edges.
+///
+/// A cycle is only a candidate: whether it leaks depends on whether something
+/// breaks it at runtime.  Cycles where every class is cycle collected and
+/// every member is traversed by the cycle collector are left out unless
+/// `--all` is passed.
+#[derive(Debug, Args)]
+pub struct RefcountCycles {
+    /// Only report cycles involving the classes with these pretty names.
+    #[clap(value_parser)]
+    classes: Vec<String>,
+
+    /// Treat a strong reference to a class as possibly owning any of its
+    /// refcounted subclasses, which finds cycles through XPCOM interfaces and
+    /// base classes at the cost of more (and bigger) candidates.
+    #[clap(long, value_parser)]
+    subclasses: bool,
+
+    /// Also report the cycles the cycle collector can already collect.
+    #[clap(long, value_parser)]
+    all: bool,
+
+    #[clap(long, value_parser, value_enum, default_value = "table")]
+    format: RefcountCyclesFormat,
+}
+
+#[derive(Debug)]
+pub struct RefcountCyclesCommand {
+    pub args: RefcountCycles,
+}
+
+/// An edge of the ownership graph, which is a member edge whose target may
+/// have been replaced by a subclass for `--subclasses`.
+struct CycleEdge<'a> {
+    from: &'a str,
+    to: &'a str,
+    member: &'a OwnershipEdge,
+}
+
+struct Cycle<'a> {
+    members: BTreeSet<&'a str>,
+    edges: Vec<CycleEdge<'a>>,
+    /// Whether every class is cycle collected and every edge is traversed.
+    collected: bool,
+}
+
+fn dot_quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+impl RefcountCyclesCommand {
+    fn find_cycles<'a>(&self, graph: &'a RefcountGraph) -> Vec<Cycle<'a>> {
+        let subclasses = if self.args.subclasses {
+            graph.subclasses()
+        } else {
+            BTreeMap::new()
+        };
+        let mut edges = vec![];
+        for member in &graph.edges {
+            let subs = subclasses.get(member.to.as_str()).into_iter().flatten();
+            for to in std::iter::once(member.to.as_str()).chain(subs.copied()) {
+                edges.push(CycleEdge {
+                    from: &member.from,
+                    to,
+                    member,
+                });
+            }
+        }
+
+        let mut digraph: DiGraph<&str, ()> = DiGraph::new();
+        let mut ixs = BTreeMap::new();
+        for edge in &edges {
+            for sym in [edge.from, edge.to] {
+                if !ixs.contains_key(sym) {
+                    ixs.insert(sym, digraph.add_node(sym));
+                }
+            }
+            digraph.update_edge(ixs[edge.from], ixs[edge.to], ());
+        }
+
+        let mut component_of = BTreeMap::new();
+        for (i, comp) in tarjan_scc(&digraph).into_iter().enumerate() {
+            for ix in comp {
+                component_of.insert(digraph[ix], i);
+            }
+        }
+        let mut cycles: BTreeMap<usize, Cycle> = BTreeMap::new();
+        for edge in edges {
+            let comp = component_of[edge.from];
+            if comp != component_of[edge.to] {
+                continue;
+            }
+            let cycle = cycles.entry(comp).or_insert_with(|| Cycle {
+                members: BTreeSet::new(),
+                edges: vec![],
+                collected: true,
+            });
+            cycle.members.insert(edge.from);
+            cycle.edges.push(edge);
+        }
+
+        let mut cycles: Vec<Cycle> = cycles
+            .into_values()
+            .map(|mut cycle| {
+                cycle.collected = cycle
+                    .members
+                    .iter()
+                    .all(|sym| graph.classes.get(*sym).map_or(false, |class| class.cc))
+                    && cycle.edges.iter().all(|edge| edge.member.traversed);
+                cycle
+            })
+            .filter(|cycle| self.args.all || !cycle.collected)
+            .filter(|cycle| {
+                self.args.classes.is_empty()
+                    || cycle.members.iter().any(|sym| {
+                        graph
+                            .classes
+                            .get(*sym)
+                            .map_or(false, |class| self.args.classes.contains(&class.pretty))
+                    })
+            })
+            .collect();
+        // Biggest cycles first since they're the most interesting.
+        cycles.sort_by(|a, b| {
+            b.members
+                .len()
+                .cmp(&a.members.len())
+                .then_with(|| a.members.cmp(&b.members))
+        });
+        cycles
+    }
+
+    fn to_table(&self, cycles: &[Cycle]) -> Result<DataTable> {
+        let mut table = DataTable::new(vec![
+            ("cycle", DataColumnType::Int),
+            ("size", DataColumnType::Int),
+            ("collected", DataColumnType::Int),
+            ("from", DataColumnType::SymbolRef),
+            ("field", DataColumnType::String),
+            ("type", DataColumnType::String),
+            ("to", DataColumnType::SymbolRef),
+            ("traversed", DataColumnType::Int),
+        ]);
+        for (i, cycle) in cycles.iter().enumerate() {
+            for edge in &cycle.edges {
+                table.push_row(vec![
+                    DataCell::Int(i as i64),
+                    DataCell::Int(cycle.members.len() as i64),
+                    DataCell::Int(cycle.collected as i64),
+                    DataCell::Symbol(ustr(edge.from)),
+                    DataCell::String(edge.member.field_pretty.clone()),
+                    DataCell::String(edge.member.type_pretty.clone()),
+                    DataCell::Symbol(ustr(edge.to)),
+                    DataCell::Int(edge.member.traversed as i64),
+                ])?;
+            }
+        }
+        Ok(table)
+    }
+
+    /// Members the cycle collector doesn't traverse are red.
+    fn to_dot(&self, graph: &RefcountGraph, cycles: &[Cycle]) -> String {
+        let mut dot =
+            "digraph g {\n  node [shape=box, fontname=\"Courier New\", fontsize=10];\n".to_string();
+        for (i, cycle) in cycles.iter().enumerate() {
+            dot.push_str(&format!("  subgraph cluster_{} {{\n", i));
+            for sym in &cycle.members {
+                let label = graph
+                    .classes
+                    .get(*sym)
+                    .map_or(*sym, |class| class.pretty.as_str());
+                dot.push_str(&format!(
+                    "    {} [label={}];\n",
+                    dot_quote(sym),
+                    dot_quote(label)
+                ));
+            }
+            dot.push_str("  }\n");
+            for edge in &cycle.edges {
+                let color = if edge.member.traversed {
+                    "black"
+                } else {
+                    "red"
+                };
+                dot.push_str(&format!(
+                    "  {} -> {} [label={}, color={}];\n",
+                    dot_quote(edge.from),
+                    dot_quote(edge.to),
+                    dot_quote(&edge.member.field_pretty),
+                    color
+                ));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[async_trait]
+impl PipelineCommand for RefcountCyclesCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        if !matches!(input, PipelineValues::Void) {
+            return Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::BadQuery,
+                    ErrorLayer::ConfigLayer,
+                    "refcount-cycles needs no input".to_string(),
+                )
+                .with_hint("Use `explain` to see what each pipeline stage consumes and produces."),
+            ));
+        }
+
+        let index_path =
+            server.translate_path(SearchfoxIndexRoot::IndexRoot, REFCOUNT_GRAPH_FILENAME)?;
+        let Some(graph) = RefcountGraph::load(&index_path)? else {
+            return Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::IndexMissingCapability,
+                    ErrorLayer::DataLayer,
+                    "This index has no refcount ownership graph".to_string(),
+                )
+                .with_hint("Re-index the tree to build `refcount-graph.json`."),
+            ));
+        };
+
+        for pretty in &self.args.classes {
+            if !graph.classes.values().any(|class| &class.pretty == pretty) {
+                return Err(ServerError::StickyProblem(
+                    ErrorDetails::new(
+                        ErrorKind::NotFound,
+                        ErrorLayer::BadInput,
+                        format!("No refcounted class named '{}'", pretty),
+                    )
+                    .with_hint(
+                        "Pass the class's fully qualified name, ex: `mozilla::dom::Document`.",
+                    ),
+                ));
+            }
+        }
+
+        let cycles = self.find_cycles(&graph);
+        Ok(match self.args.format {
+            RefcountCyclesFormat::Table => PipelineValues::DataTable(self.to_table(&cycles)?),
+            RefcountCyclesFormat::Dot => PipelineValues::TextFile(TextFile {
+                mime_type: "text/x-dot".to_string(),
+                contents: self.to_dot(&graph, &cycles),
+            }),
+        })
+    }
+}
@@ -0,0 +1,262 @@
+use async_trait::async_trait;
+use clap::Args;
+use lazy_static::lazy_static;
+use regex::{Regex, RegexBuilder};
+
+use super::{
+    interface::{FlattenedResultsBundle, FlattenedResultsByFile, PipelineCommand, PipelineValues},
+    path_filter::{PathFilter, PathFilterArgs},
+};
+use crate::abstract_server::{
+    AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, FileMatches, Result, ServerError,
+    TextBounds, TextMatchInFile, TextMatches, TextMatchesByFile,
+};
+
+/// Narrow down the results of a prior search without re-running the search
+/// over the whole tree, for iteratively refining results.  Only the hits whose
+/// lines match the `text` or `re` pattern (or that don't, with `--invert`) and
+/// whose paths pass the path filters are kept:
+/// - TextMatches from `search-text` are filtered by line.
+/// - FlattenedResultsBundles from `compile-results` are filtered by line span,
+///   dropping any files and kind groups left without hits.
+/// - FileMatches from `search-files` are searched for the pattern, producing
+///   TextMatches, which makes this a way to search within a set of files.
+///   Without a pattern, the FileMatches are just filtered by path.
+#[derive(Debug, Args)]
+pub struct Refine {
+    /// Text the lines must contain; this will be regexp escaped.
+    #[clap(value_parser)]
+    text: Option<String>,
+
+    /// A regular expression the lines must match.  This can't be used if
+    /// `text` is used.
+    #[clap(long, value_parser)]
+    re: Option<String>,
+
+    /// Should this be case-sensitive?  By default we are case-insensitive.
+    #[clap(short, long, value_parser)]
+    case_sensitive: bool,
+
+    /// Keep the lines that don't match the pattern instead.
+    #[clap(long, value_parser)]
+    invert: bool,
+
+    #[clap(flatten)]
+    path_filter: PathFilterArgs,
+}
+
+#[derive(Debug)]
+pub struct RefineCommand {
+    pub args: Refine,
+}
+
+/// Strip the markup from a line of formatted HTML so the pattern only sees the
+/// source text.
+fn html_line_text(html: &str) -> String {
+    lazy_static! {
+        static ref TAG_RE: Regex = Regex::new(r"<[^>]*>").unwrap();
+    }
+    TAG_RE
+        .replace_all(html, "")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+fn span_count(by_file: &[FlattenedResultsByFile]) -> usize {
+    by_file.iter().map(|f| f.line_spans.len()).sum()
+}
+
+impl RefineCommand {
+    fn line_matches(&self, re: &Option<Regex>, line: &str) -> bool {
+        match re {
+            Some(re) => re.is_match(line) != self.args.invert,
+            None => true,
+        }
+    }
+
+    fn refine_text_matches(
+        &self,
+        re: &Option<Regex>,
+        path_filter: &PathFilter,
+        mut matches: TextMatches,
+    ) -> TextMatches {
+        matches.by_file.retain(|by_file| {
+            path_filter.matches_classified(&by_file.file, by_file.classification)
+        });
+        for by_file in matches.by_file.iter_mut() {
+            by_file
+                .matches
+                .retain(|m| self.line_matches(re, &m.line_str));
+        }
+        matches
+            .by_file
+            .retain(|by_file| !by_file.matches.is_empty());
+        matches
+    }
+
+    fn refine_bundle(
+        &self,
+        re: &Option<Regex>,
+        path_filter: &PathFilter,
+        mut bundle: FlattenedResultsBundle,
+    ) -> FlattenedResultsBundle {
+        let is_html = bundle.content_type == "text/html";
+        for path_kind_group in bundle.path_kind_results.iter_mut() {
+            for kind_group in path_kind_group.kind_groups.iter_mut() {
+                let before = span_count(&kind_group.by_file);
+                kind_group
+                    .by_file
+                    .retain(|by_file| path_filter.matches(&by_file.file));
+                for by_file in kind_group.by_file.iter_mut() {
+                    by_file.line_spans.retain(|span| {
+                        if is_html {
+                            self.line_matches(re, &html_line_text(&span.contents))
+                        } else {
+                            self.line_matches(re, &span.contents)
+                        }
+                    });
+                }
+                kind_group
+                    .by_file
+                    .retain(|by_file| !by_file.line_spans.is_empty());
+                // The facets summarize the hits we started with, so they no
+                // longer apply if we removed any.
+                if span_count(&kind_group.by_file) != before {
+                    kind_group.facets.clear();
+                }
+            }
+            path_kind_group
+                .kind_groups
+                .retain(|kind_group| !kind_group.by_file.is_empty());
+            let kind_groups = &path_kind_group.kind_groups;
+            path_kind_group.file_names.retain(|file| {
+                kind_groups
+                    .iter()
+                    .any(|kind_group| kind_group.by_file.iter().any(|f| f.file == *file))
+            });
+        }
+        bundle
+            .path_kind_results
+            .retain(|path_kind_group| !path_kind_group.kind_groups.is_empty());
+        bundle
+    }
+
+    async fn search_file_matches(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        re: &Regex,
+        matches: FileMatches,
+    ) -> Result<TextMatches> {
+        let mut by_file = vec![];
+        for fm in matches.file_matches {
+            if fm.concise.is_dir {
+                continue;
+            }
+            // Binary and missing files are just not searchable.
+            let Ok(source) = server.fetch_raw_source(&fm.path).await else {
+                continue;
+            };
+            let mut file_matches = TextMatchesByFile {
+                file: fm.path,
+                path_kind: fm.concise.path_kind,
+                classification: fm.concise.classification,
+                matches: vec![],
+            };
+            for (i_line, line) in source.lines().enumerate() {
+                // Like livegrep, we only report the first match on a line.
+                let bounds = match (re.find(line), self.args.invert) {
+                    (Some(m), false) => TextBounds {
+                        start: m.start() as i32,
+                        end_exclusive: m.end() as i32,
+                    },
+                    (None, true) => TextBounds {
+                        start: 0,
+                        end_exclusive: 0,
+                    },
+                    _ => continue,
+                };
+                file_matches.matches.push(TextMatchInFile {
+                    line_num: i_line as u32 + 1,
+                    bounds,
+                    line_str: line.to_string(),
+                });
+            }
+            if !file_matches.matches.is_empty() {
+                by_file.push(file_matches);
+            }
+        }
+        Ok(TextMatches {
+            by_file,
+            page: None,
+        })
+    }
+}
+
+#[async_trait]
+impl PipelineCommand for RefineCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let pattern = match (&self.args.text, &self.args.re) {
+            (Some(_), Some(_)) => {
+                return Err(ServerError::StickyProblem(ErrorDetails::new(
+                    ErrorKind::BadQuery,
+                    ErrorLayer::BadInput,
+                    "refine takes either `text` or `re`, not both".to_string(),
+                )));
+            }
+            (Some(text), None) => Some(regex::escape(text)),
+            (None, Some(re)) => Some(re.clone()),
+            (None, None) => None,
+        };
+        let re = match pattern {
+            Some(pattern) => Some(
+                RegexBuilder::new(&pattern)
+                    .case_insensitive(!self.args.case_sensitive)
+                    .build()?,
+            ),
+            None => None,
+        };
+        let path_filter = self
+            .args
+            .path_filter
+            .compile(&server.tree_info()?.path_sets)?;
+
+        match input {
+            PipelineValues::TextMatches(matches) => Ok(PipelineValues::TextMatches(
+                self.refine_text_matches(&re, &path_filter, matches),
+            )),
+            PipelineValues::FlattenedResultsBundle(bundle) => {
+                Ok(PipelineValues::FlattenedResultsBundle(self.refine_bundle(
+                    &re,
+                    &path_filter,
+                    bundle,
+                )))
+            }
+            PipelineValues::FileMatches(mut matches) => {
+                matches.file_matches.retain(|fm| {
+                    path_filter.matches_classified(&fm.path, fm.concise.classification)
+                });
+                match &re {
+                    Some(re) => Ok(PipelineValues::TextMatches(
+                        self.search_file_matches(server, re, matches).await?,
+                    )),
+                    None => Ok(PipelineValues::FileMatches(matches)),
+                }
+            }
+            _ => Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::BadQuery,
+                    ErrorLayer::ConfigLayer,
+                    "refine needs TextMatches, FlattenedResultsBundle, or FileMatches".to_string(),
+                )
+                .with_hint("Use `explain` to see what each pipeline stage consumes and produces."),
+            )),
+        }
+    }
+}
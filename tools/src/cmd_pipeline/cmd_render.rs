@@ -4,9 +4,11 @@ use clap::Args;
 use super::interface::{PipelineCommand, PipelineValues};
 use crate::{
     abstract_server::{
-        AbstractServer, ErrorDetails, ErrorLayer, Result, SearchfoxIndexRoot, ServerError,
+        AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, SearchfoxIndexRoot,
+        ServerError, TreeInfo,
     },
-    file_utils::write_file_ensuring_parent_dir,
+    file_format::asset_manifest::{AssetManifest, ASSET_MANIFEST_FILENAME},
+    file_utils::{write_file_ensuring_parent_dir, write_gzipped_file_ensuring_parent_dir},
     templating::builder::{
         build_and_parse_help_index, build_and_parse_search_template, build_and_parse_settings,
     },
@@ -31,6 +33,28 @@ pub struct RenderCommand {
     pub args: Render,
 }
 
+/// Write the rendered page, along with a gzipped copy if the tree wants them.
+/// The uncompressed page is always kept because the web-server reads the
+/// templates uncompressed and nginx serves the pages via `alias`.
+fn write_page(tree_info: &TreeInfo, output_path: &str, rendered: &str) -> Result<()> {
+    if tree_info.precompress_html {
+        write_gzipped_file_ensuring_parent_dir(output_path, rendered, false)
+    } else {
+        write_file_ensuring_parent_dir(output_path, rendered)
+    }
+}
+
+/// The tree's asset manifest, for the `ASSET_MANIFEST` global that the
+/// `asset_url` filter fingerprints static URLs with.  This is read at render
+/// time so that pages rendered after `render asset-manifest` use its hashes.
+pub(crate) fn load_asset_manifest(
+    server: &(dyn AbstractServer + Send + Sync),
+) -> Result<AssetManifest> {
+    let path =
+        server.translate_path(SearchfoxIndexRoot::IndexTemplates, ASSET_MANIFEST_FILENAME)?;
+    Ok(AssetManifest::load_or_default(&path)?)
+}
+
 #[async_trait]
 impl PipelineCommand for RenderCommand {
     async fn execute(
@@ -41,26 +65,41 @@ impl PipelineCommand for RenderCommand {
         let tree_info = server.tree_info()?;
 
         match self.args.task.as_str() {
+            // Hash the static assets so the templates rendered after this can
+            // use fingerprinted URLs via the `asset_url` filter.  This runs
+            // before everything else in `output.sh` so that every page of the
+            // index agrees on the hashes.
+            "asset-manifest" => {
+                let static_dir = server.translate_path(SearchfoxIndexRoot::StaticAssets, "")?;
+                let manifest = AssetManifest::build(&static_dir)?;
+                let output_path = server
+                    .translate_path(SearchfoxIndexRoot::IndexTemplates, ASSET_MANIFEST_FILENAME)?;
+                manifest.write(&output_path)?;
+                Ok(PipelineValues::Void)
+            }
             "search-template" => {
                 let template = build_and_parse_search_template();
+                let asset_manifest = load_asset_manifest(server)?;
 
                 let liquid_globals = liquid::object!({
                     "tree": tree_info.name,
                     // the header always needs this
                     "query": "",
+                    "ASSET_MANIFEST": asset_manifest.assets,
                 });
                 let rendered = match template.render(&liquid_globals) {
                     Ok(r) => r,
                     Err(e) => {
-                        return Err(ServerError::StickyProblem(ErrorDetails {
-                            layer: ErrorLayer::ConfigLayer,
-                            message: format!("Template problems: {}", e),
-                        }));
+                        return Err(ServerError::StickyProblem(ErrorDetails::new(
+                            ErrorKind::Internal,
+                            ErrorLayer::ConfigLayer,
+                            format!("Template problems: {}", e),
+                        )));
                     }
                 };
                 let output_path =
                     server.translate_path(SearchfoxIndexRoot::IndexTemplates, "search.html")?;
-                write_file_ensuring_parent_dir(&output_path, &rendered)?;
+                write_page(&tree_info, &output_path, &rendered)?;
                 Ok(PipelineValues::Void)
             }
             "help" => {
@@ -69,53 +108,60 @@ impl PipelineCommand for RenderCommand {
                 let content_path =
                     server.translate_path(SearchfoxIndexRoot::ConfigRepo, "help.html")?;
                 let content = std::fs::read_to_string(content_path)?;
+                let asset_manifest = load_asset_manifest(server)?;
 
                 let liquid_globals = liquid::object!({
                     "tree": tree_info.name,
                     // the header always needs this
                     "query": "",
                     "content": content,
+                    "ASSET_MANIFEST": asset_manifest.assets,
                 });
                 let rendered = match template.render(&liquid_globals) {
                     Ok(r) => r,
                     Err(e) => {
-                        return Err(ServerError::StickyProblem(ErrorDetails {
-                            layer: ErrorLayer::ConfigLayer,
-                            message: format!("Template problems: {}", e),
-                        }));
+                        return Err(ServerError::StickyProblem(ErrorDetails::new(
+                            ErrorKind::Internal,
+                            ErrorLayer::ConfigLayer,
+                            format!("Template problems: {}", e),
+                        )));
                     }
                 };
                 let output_path =
                     server.translate_path(SearchfoxIndexRoot::IndexTemplates, "help.html")?;
-                write_file_ensuring_parent_dir(&output_path, &rendered)?;
+                write_page(&tree_info, &output_path, &rendered)?;
                 Ok(PipelineValues::Void)
             }
             "settings" => {
                 let template = build_and_parse_settings();
+                let asset_manifest = load_asset_manifest(server)?;
 
                 let liquid_globals = liquid::object!({
                     "tree": tree_info.name,
                     // the header always needs this
                     "query": "",
+                    "ASSET_MANIFEST": asset_manifest.assets,
                 });
                 let rendered = match template.render(&liquid_globals) {
                     Ok(r) => r,
                     Err(e) => {
-                        return Err(ServerError::StickyProblem(ErrorDetails {
-                            layer: ErrorLayer::ConfigLayer,
-                            message: format!("Template problems: {}", e),
-                        }));
+                        return Err(ServerError::StickyProblem(ErrorDetails::new(
+                            ErrorKind::Internal,
+                            ErrorLayer::ConfigLayer,
+                            format!("Template problems: {}", e),
+                        )));
                     }
                 };
                 let output_path =
                     server.translate_path(SearchfoxIndexRoot::IndexPages, "settings.html")?;
-                write_file_ensuring_parent_dir(&output_path, &rendered)?;
+                write_page(&tree_info, &output_path, &rendered)?;
                 Ok(PipelineValues::Void)
             }
-            unknown => Err(ServerError::StickyProblem(ErrorDetails {
-                layer: ErrorLayer::ConfigLayer,
-                message: format!("Unknown task type: {}", unknown),
-            })),
+            unknown => Err(ServerError::StickyProblem(ErrorDetails::new(
+                ErrorKind::Internal,
+                ErrorLayer::ConfigLayer,
+                format!("Unknown task type: {}", unknown),
+            ))),
         }
     }
 }
@@ -0,0 +1,60 @@
+use async_trait::async_trait;
+use clap::Args;
+use serde_json::json;
+
+use super::interface::{JsonValue, PipelineCommand, PipelineValues};
+use crate::{
+    abstract_server::{AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError},
+    query::post_index_hooks::run_post_index_hooks,
+};
+
+/// Run the tree's post-index hooks (see `query/post_index_hooks.rs`), storing
+/// their outputs under the index.  This is run by `post-index-hooks.sh` as part
+/// of indexing.  Produces a JsonValue summarizing the runs, or an error naming
+/// the hooks that failed after running all of them.
+#[derive(Debug, Args)]
+pub struct RunPostIndexHooks {
+    /// Only run the named hook(s) instead of all of them.
+    #[clap(long, value_parser)]
+    hook: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct RunPostIndexHooksCommand {
+    pub args: RunPostIndexHooks,
+}
+
+#[async_trait]
+impl PipelineCommand for RunPostIndexHooksCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        _input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let runs = run_post_index_hooks(server, &self.args.hook).await?;
+
+        let failed: Vec<&str> = runs
+            .iter()
+            .filter(|run| run.error.is_some())
+            .map(|run| run.hook.as_str())
+            .collect();
+        if !failed.is_empty() {
+            return Err(ServerError::StickyProblem(ErrorDetails::new(
+                ErrorKind::Internal,
+                ErrorLayer::RuntimeInvariantViolation,
+                format!("Post-index hooks failed: {}", failed.join(", ")),
+            )));
+        }
+
+        Ok(PipelineValues::JsonValue(JsonValue {
+            value: json!(runs
+                .iter()
+                .map(|run| json!({
+                    "hook": run.hook,
+                    "ranAt": run.ran_at,
+                    "durationMs": run.duration_ms,
+                }))
+                .collect::<Vec<_>>()),
+        }))
+    }
+}
@@ -0,0 +1,65 @@
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+use clap::Args;
+use serde_json::to_value;
+
+use super::{
+    builder::{build_pipeline_graph, CommandSafetyLevel},
+    cmd_query::parse_param,
+    interface::{JsonValue, PipelineCommand, PipelineValues},
+};
+use crate::{
+    abstract_server::{AbstractServer, Result},
+    query::pipeline_spec::load_pipeline_spec,
+};
+
+/// Run a pipeline described by a YAML or JSON pipeline spec file; see
+/// `query/pipeline_spec.rs` for the format.  Because this reads arbitrary
+/// files, it's only available to searchfox-tool and not the web, and so the
+/// spec can use the other searchfox-tool-only commands too.
+#[derive(Debug, Args)]
+pub struct RunSpec {
+    /// Path to the spec file; `.json` files are parsed as JSON, anything else
+    /// as YAML.
+    #[clap(value_parser)]
+    path: String,
+
+    /// Spec variables of the form `name=value`, overriding the spec's `vars`.
+    #[clap(long, value_parser = parse_param)]
+    var: Vec<(String, String)>,
+
+    /// Output the constructed pipeline instead of running the pipeline.
+    #[clap(short, long, value_parser)]
+    dump_pipeline: bool,
+}
+
+#[derive(Debug)]
+pub struct RunSpecCommand {
+    pub args: RunSpec,
+    /// The safety level `run-spec` itself was built with, which the spec's
+    /// commands are built with too.
+    pub safety: CommandSafetyLevel,
+}
+
+#[async_trait]
+impl PipelineCommand for RunSpecCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        _input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let vars: BTreeMap<String, String> = self.args.var.iter().cloned().collect();
+        let pipeline_plan = load_pipeline_spec(&self.args.path)?.to_group_builder(&vars)?;
+
+        if self.args.dump_pipeline {
+            return Ok(PipelineValues::JsonValue(JsonValue {
+                value: to_value(pipeline_plan)?,
+            }));
+        }
+
+        let graph = build_pipeline_graph(server.clonify(), pipeline_plan, self.safety)?;
+
+        graph.run(true).await
+    }
+}
@@ -0,0 +1,296 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use async_trait::async_trait;
+use clap::{Args, ValueEnum};
+
+use super::{
+    data_table::{DataCell, DataColumnType, DataTable},
+    interface::{PipelineCommand, PipelineValues, TextFile},
+};
+use crate::{
+    abstract_server::{
+        AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, SearchfoxIndexRoot,
+        ServerError,
+    },
+    file_format::rust_crates::{RustCrateIndex, RUST_CRATES_FILENAME},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum RustCrateGraphFormat {
+    /// A DataTable with a row per crate.
+    Crates,
+    /// A DataTable with a row per pair of crates with a dependency or
+    /// cross-crate references between them.
+    Edges,
+    /// A graphviz DOT TextFile of the crates and their dependencies.
+    Dot,
+}
+
+/// Render the dependency graph of the tree's in-tree Rust crates, as captured
+/// from Cargo metadata at index time, with how many symbols each crate
+/// defines and how many times each crate uses the symbols of the others.
+/// Requires the tree's build to save `cargo metadata --format-version 1`
+/// output as `cargo-metadata.json` in the index directory.
+///
+/// Crate names have any "-" normalized to "_".  Rust symbols carry their
+/// crate and module path in their structured info's "crate" and "modulePath".
+#[derive(Debug, Args)]
+pub struct RustCrateGraph {
+    /// Only include these crates and the crates they transitively depend on
+    /// (or with `--reverse`, the crates depending on them).
+    #[clap(value_parser)]
+    crates: Vec<String>,
+
+    /// Follow the crates depending on the given crates instead of their
+    /// dependencies.
+    #[clap(long, value_parser)]
+    reverse: bool,
+
+    /// Also follow dev-dependencies.
+    #[clap(long, value_parser)]
+    dev: bool,
+
+    #[clap(long, value_parser, value_enum, default_value = "crates")]
+    format: RustCrateGraphFormat,
+}
+
+#[derive(Debug)]
+pub struct RustCrateGraphCommand {
+    pub args: RustCrateGraph,
+}
+
+/// The relationship between two crates.
+#[derive(Default)]
+struct CrateEdge {
+    /// "normal" or "dev" if the first crate declares a dependency on the
+    /// second.
+    dep: Option<&'static str>,
+    /// How many times the first crate uses the second's symbols.
+    refs: u64,
+}
+
+fn dot_quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+impl RustCrateGraphCommand {
+    /// The crates to include, per the positional crates and `--reverse`.
+    fn included_crates<'a>(&self, index: &'a RustCrateIndex) -> Result<BTreeSet<&'a str>> {
+        if self.args.crates.is_empty() {
+            return Ok(index.crates.keys().map(|name| name.as_str()).collect());
+        }
+
+        let mut pending = vec![];
+        for name in &self.args.crates {
+            match index.resolve_crate_name(&name.replace('-', "_")) {
+                Some(name) => pending.push(name),
+                None => {
+                    return Err(ServerError::StickyProblem(
+                        ErrorDetails::new(
+                            ErrorKind::NotFound,
+                            ErrorLayer::BadInput,
+                            format!("No in-tree crate named '{}'", name),
+                        )
+                        .with_hint("Run `rust-crate-graph` without arguments to list the crates."),
+                    ))
+                }
+            }
+        }
+
+        let mut included = BTreeSet::new();
+        while let Some(name) = pending.pop() {
+            if !included.insert(name) {
+                continue;
+            }
+            if self.args.reverse {
+                for (other, krate) in &index.crates {
+                    if krate.deps.contains(name) || (self.args.dev && krate.dev_deps.contains(name))
+                    {
+                        pending.push(other.as_str());
+                    }
+                }
+            } else {
+                // The dependencies only name in-tree crates.
+                let krate = &index.crates[name];
+                let dev_deps = krate.dev_deps.iter().filter(|_| self.args.dev);
+                pending.extend(krate.deps.iter().chain(dev_deps).map(|dep| dep.as_str()));
+            }
+        }
+        Ok(included)
+    }
+
+    /// The edges between the included crates, keyed by (from, to).
+    fn edges<'a>(
+        &self,
+        index: &'a RustCrateIndex,
+        included: &BTreeSet<&'a str>,
+    ) -> BTreeMap<(&'a str, &'a str), CrateEdge> {
+        let mut edges: BTreeMap<(&str, &str), CrateEdge> = BTreeMap::new();
+        for (name, krate) in &index.crates {
+            if !included.contains(name.as_str()) {
+                continue;
+            }
+            let deps = krate.deps.iter().map(|dep| (dep, "normal"));
+            let dev_deps = krate.dev_deps.iter().map(|dep| (dep, "dev"));
+            for (dep, kind) in deps.chain(dev_deps) {
+                if let Some(dep) = included.get(dep.as_str()) {
+                    edges
+                        .entry((name.as_str(), *dep))
+                        .or_default()
+                        .dep
+                        .get_or_insert(kind);
+                }
+            }
+            for (other, refs) in &krate.refs {
+                if let Some(other) = included.get(other.as_str()) {
+                    edges.entry((name.as_str(), *other)).or_default().refs += refs;
+                }
+            }
+        }
+        edges
+    }
+
+    fn to_crates_table(
+        &self,
+        index: &RustCrateIndex,
+        included: &BTreeSet<&str>,
+        edges: &BTreeMap<(&str, &str), CrateEdge>,
+    ) -> Result<DataTable> {
+        let mut table = DataTable::new(vec![
+            ("crate", DataColumnType::String),
+            ("dir", DataColumnType::Path),
+            ("symbols", DataColumnType::Int),
+            ("deps", DataColumnType::Int),
+            ("dependents", DataColumnType::Int),
+            ("refs_out", DataColumnType::Int),
+            ("refs_in", DataColumnType::Int),
+        ]);
+        for name in included {
+            let krate = &index.crates[*name];
+            let (mut deps, mut dependents, mut refs_out, mut refs_in) = (0, 0, 0, 0);
+            for ((from, to), edge) in edges {
+                if from == name {
+                    deps += edge.dep.is_some() as i64;
+                    refs_out += edge.refs as i64;
+                }
+                if to == name {
+                    dependents += edge.dep.is_some() as i64;
+                    refs_in += edge.refs as i64;
+                }
+            }
+            table.push_row(vec![
+                DataCell::String(name.to_string()),
+                DataCell::String(krate.dir.clone()),
+                DataCell::Int(krate.symbols as i64),
+                DataCell::Int(deps),
+                DataCell::Int(dependents),
+                DataCell::Int(refs_out),
+                DataCell::Int(refs_in),
+            ])?;
+        }
+        Ok(table)
+    }
+
+    fn to_edges_table(&self, edges: &BTreeMap<(&str, &str), CrateEdge>) -> Result<DataTable> {
+        let mut table = DataTable::new(vec![
+            ("from", DataColumnType::String),
+            ("to", DataColumnType::String),
+            ("dep", DataColumnType::String),
+            ("refs", DataColumnType::Int),
+        ]);
+        for ((from, to), edge) in edges {
+            table.push_row(vec![
+                DataCell::String(from.to_string()),
+                DataCell::String(to.to_string()),
+                edge.dep
+                    .map_or(DataCell::Null, |dep| DataCell::String(dep.to_string())),
+                DataCell::Int(edge.refs as i64),
+            ])?;
+        }
+        Ok(table)
+    }
+
+    /// Dev-dependencies are dashed and references between crates without a
+    /// declared dependency (ex: via re-exports) are dotted.
+    fn to_dot(
+        &self,
+        index: &RustCrateIndex,
+        included: &BTreeSet<&str>,
+        edges: &BTreeMap<(&str, &str), CrateEdge>,
+    ) -> String {
+        let mut dot =
+            "digraph g {\n  node [shape=box, fontname=\"Courier New\", fontsize=10];\n".to_string();
+        for name in included {
+            let label = format!("{}\n{} symbols", name, index.crates[*name].symbols);
+            dot.push_str(&format!(
+                "  {} [label={}];\n",
+                dot_quote(name),
+                dot_quote(&label)
+            ));
+        }
+        for ((from, to), edge) in edges {
+            let style = match edge.dep {
+                Some("normal") => "solid",
+                Some(_) => "dashed",
+                None => "dotted",
+            };
+            dot.push_str(&format!(
+                "  {} -> {} [style={}, label=\"{}\"];\n",
+                dot_quote(from),
+                dot_quote(to),
+                style,
+                edge.refs
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[async_trait]
+impl PipelineCommand for RustCrateGraphCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        if !matches!(input, PipelineValues::Void) {
+            return Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::BadQuery,
+                    ErrorLayer::ConfigLayer,
+                    "rust-crate-graph needs no input".to_string(),
+                )
+                .with_hint("Use `explain` to see what each pipeline stage consumes and produces."),
+            ));
+        }
+
+        let index_path =
+            server.translate_path(SearchfoxIndexRoot::IndexRoot, RUST_CRATES_FILENAME)?;
+        let Some(index) = RustCrateIndex::load(&index_path)? else {
+            return Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::IndexMissingCapability,
+                    ErrorLayer::DataLayer,
+                    "This index has no Rust crate graph".to_string(),
+                )
+                .with_hint(
+                    "Save `cargo metadata --format-version 1` output as `cargo-metadata.json` in the index directory during the build and re-index.",
+                ),
+            ));
+        };
+
+        let included = self.included_crates(&index)?;
+        let edges = self.edges(&index, &included);
+        Ok(match self.args.format {
+            RustCrateGraphFormat::Crates => {
+                PipelineValues::DataTable(self.to_crates_table(&index, &included, &edges)?)
+            }
+            RustCrateGraphFormat::Edges => PipelineValues::DataTable(self.to_edges_table(&edges)?),
+            RustCrateGraphFormat::Dot => PipelineValues::TextFile(TextFile {
+                mime_type: "text/x-dot".to_string(),
+                contents: self.to_dot(&index, &included, &edges),
+            }),
+        })
+    }
+}
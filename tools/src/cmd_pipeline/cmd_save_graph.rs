@@ -0,0 +1,61 @@
+use std::fs::File;
+use std::io::BufWriter;
+
+use async_trait::async_trait;
+use clap::Args;
+
+use super::interface::{PipelineCommand, PipelineValues};
+use crate::abstract_server::{
+    AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError,
+};
+
+/// Save the SymbolGraphCollection from `traverse`, `find-paths`, or other graph
+/// constructing commands to disk as a snapshot that `load-graph` can reload
+/// later.  This lets iterative analysis of huge graphs re-run only the stages
+/// downstream of graph construction, like `graph` with different layout or
+/// hierarchy options, without re-querying the index.
+///
+/// The SymbolGraphCollection is passed through unchanged so that further
+/// stages can still be chained.  Hierarchical graphs are not saved; `graph`
+/// re-derives them after loading.
+#[derive(Debug, Args)]
+pub struct SaveGraph {
+    /// Where to write the graph snapshot.
+    #[clap(value_parser)]
+    output: String,
+}
+
+#[derive(Debug)]
+pub struct SaveGraphCommand {
+    pub args: SaveGraph,
+}
+
+#[async_trait]
+impl PipelineCommand for SaveGraphCommand {
+    async fn execute(
+        &self,
+        _server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let graphs = match input {
+            PipelineValues::SymbolGraphCollection(sgc) => sgc,
+            _ => {
+                return Err(ServerError::StickyProblem(
+                    ErrorDetails::new(
+                        ErrorKind::BadQuery,
+                        ErrorLayer::ConfigLayer,
+                        "save-graph needs a SymbolGraphCollection".to_string(),
+                    )
+                    .with_hint(
+                        "Use `explain` to see what each pipeline stage consumes and produces.",
+                    ),
+                ));
+            }
+        };
+
+        let file = File::create(&self.args.output)?;
+        serde_json::to_writer(BufWriter::new(file), &graphs.to_snapshot())?;
+
+        Ok(PipelineValues::SymbolGraphCollection(graphs))
+    }
+}
@@ -0,0 +1,154 @@
+use async_trait::async_trait;
+use clap::Args;
+use regex::Regex;
+use serde_json::Value;
+use ustr::ustr;
+
+use super::{
+    interface::{
+        PipelineCommand, PipelineValues, SymbolCrossrefInfo, SymbolCrossrefInfoList,
+        SymbolMetaFlags, SymbolQuality, SymbolRelation,
+    },
+    pagination::PaginationArgs,
+    transforms::path_glob_transform,
+};
+
+use crate::{
+    abstract_server::{
+        AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, SearchfoxIndexRoot,
+        ServerError,
+    },
+    file_format::symbol_attributes::{SymbolAttributes, SYMBOL_ATTRIBUTES_FILENAME},
+};
+
+/// Find the symbols bearing all of the given attribute-like annotations, ex:
+/// `MOZ_CAN_RUN_SCRIPT` or Rust's `#[deprecated]`, producing their crossref
+/// data as a SymbolCrossrefInfoList.  Attributes are matched case-insensitively
+/// and regardless of how they were written, so "[[nodiscard]]" and "nodiscard"
+/// are the same.  This uses the `symbol-attributes.json` index that crossref
+/// builds from the `attributes` of structured records.
+#[derive(Debug, Args)]
+pub struct SearchAttributes {
+    /// Attributes the symbols must all bear.
+    #[clap(value_parser, required = true)]
+    attributes: Vec<String>,
+
+    /// Only include symbols defined (or declared, if they have no definition)
+    /// in files matching this path glob.
+    #[clap(long, value_parser)]
+    path: Option<String>,
+
+    /// Only include symbols defined (or declared, if they have no definition)
+    /// in files matching this regexp.
+    #[clap(long, value_parser, conflicts_with = "path")]
+    pathre: Option<String>,
+
+    #[clap(short, long, value_parser, default_value = "1000")]
+    limit: usize,
+
+    #[clap(flatten)]
+    pagination: PaginationArgs,
+}
+
+#[derive(Debug)]
+pub struct SearchAttributesCommand {
+    pub args: SearchAttributes,
+}
+
+/// The paths where the symbol is defined, falling back to where it's declared.
+fn definition_paths(crossref_info: &Value) -> Vec<&str> {
+    for kind in ["/defs", "/decls"] {
+        if let Some(Value::Array(hits)) = crossref_info.pointer(kind) {
+            let paths: Vec<&str> = hits.iter().filter_map(|hit| hit["path"].as_str()).collect();
+            if !paths.is_empty() {
+                return paths;
+            }
+        }
+    }
+    vec![]
+}
+
+#[async_trait]
+impl PipelineCommand for SearchAttributesCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        if !matches!(input, PipelineValues::Void) {
+            return Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::BadQuery,
+                    ErrorLayer::ConfigLayer,
+                    "search-attributes needs no input".to_string(),
+                )
+                .with_hint("Use `explain` to see what each pipeline stage consumes and produces."),
+            ));
+        }
+
+        let index_path =
+            server.translate_path(SearchfoxIndexRoot::IndexRoot, SYMBOL_ATTRIBUTES_FILENAME)?;
+        let Some(index) = SymbolAttributes::load(&index_path)? else {
+            return Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::IndexMissingCapability,
+                    ErrorLayer::DataLayer,
+                    "This index has no symbol attributes".to_string(),
+                )
+                .with_hint("Re-index the tree to build `symbol-attributes.json`."),
+            ));
+        };
+
+        let path_re = match (&self.args.path, &self.args.pathre) {
+            (Some(path), _) => Some(Regex::new(&path_glob_transform(path))?),
+            (None, Some(pathre)) => Some(Regex::new(pathre)?),
+            (None, None) => None,
+        };
+
+        let (first, rest) = self.args.attributes.split_first().unwrap();
+        let symbols: Vec<&str> = index
+            .symbols_with(first)
+            .filter(|sym| {
+                rest.iter()
+                    .all(|attr| index.symbols_with(attr).any(|other| other == *sym))
+            })
+            .collect();
+
+        let mut symbol_crossref_infos = vec![];
+        for symbol in symbols {
+            if symbol_crossref_infos.len() >= self.args.limit {
+                break;
+            }
+            let info = server.crossref_lookup(symbol, false).await?;
+            if info.is_null() {
+                continue;
+            }
+            if let Some(re) = &path_re {
+                if !definition_paths(&info).iter().any(|path| re.is_match(path)) {
+                    continue;
+                }
+            }
+            symbol_crossref_infos.push(SymbolCrossrefInfo {
+                symbol: ustr(symbol),
+                crossref_info: info,
+                relation: SymbolRelation::Queried,
+                quality: SymbolQuality::ExplicitSymbol,
+                overloads_hit: vec![],
+                flags: SymbolMetaFlags::default(),
+                score: None,
+            });
+        }
+
+        let (symbol_crossref_infos, page) = self
+            .args
+            .pagination
+            .paginate(symbol_crossref_infos, |info| info.symbol.to_string())?;
+        Ok(PipelineValues::SymbolCrossrefInfoList(
+            SymbolCrossrefInfoList {
+                symbol_crossref_infos,
+                unknown_symbols: vec![],
+                page,
+            },
+        ))
+    }
+}
@@ -4,6 +4,8 @@ use itertools::Itertools;
 
 use super::{
     interface::{BatchGroupItem, BatchGroups, PipelineCommand, PipelineValues},
+    pagination::PaginationArgs,
+    path_filter::PathFilterArgs,
     transforms::path_glob_transform,
 };
 
@@ -34,6 +36,12 @@ pub struct SearchFiles {
 
     #[clap(long, short, value_parser, value_enum)]
     group_by: Option<GroupFilesBy>,
+
+    #[clap(flatten)]
+    path_filter: PathFilterArgs,
+
+    #[clap(flatten)]
+    pagination: PaginationArgs,
 }
 
 #[derive(Debug)]
@@ -67,9 +75,25 @@ impl PipelineCommand for SearchFilesCommand {
             self.args.limit
         };
 
-        let matches = server
+        let path_filter = self
+            .args
+            .path_filter
+            .compile(&server.tree_info()?.path_sets)?;
+
+        let mut matches = server
             .search_files(&pathre_pattern, self.args.include_dirs, use_limit)
             .await?;
+        if !path_filter.is_empty() {
+            matches
+                .file_matches
+                .retain(|fm| path_filter.matches_classified(&fm.path, fm.concise.classification));
+        }
+        let (file_matches, page) = self
+            .args
+            .pagination
+            .paginate(matches.file_matches, |fm| fm.path.to_string())?;
+        matches.file_matches = file_matches;
+        matches.page = page;
 
         match self.args.group_by {
             Some(GroupFilesBy::Directory) => {
@@ -82,6 +106,7 @@ impl PipelineCommand for SearchFilesCommand {
                         name: dir.to_string(),
                         value: PipelineValues::FileMatches(FileMatches {
                             file_matches: matches,
+                            page: None,
                         }),
                     })
                     .collect();
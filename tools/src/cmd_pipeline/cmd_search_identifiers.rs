@@ -1,11 +1,34 @@
 use async_trait::async_trait;
-use clap::Args;
+use clap::{Args, ValueEnum};
 
-use super::interface::{
-    PipelineCommand, PipelineValues, SymbolList, SymbolQuality, SymbolWithContext,
+use super::{
+    interface::{PipelineCommand, PipelineValues, SymbolList, SymbolQuality, SymbolWithContext},
+    pagination::PaginationArgs,
 };
 
-use crate::abstract_server::{AbstractServer, Result};
+use crate::{
+    abstract_server::{AbstractServer, Result},
+    unicode_folding::normalize_identifier_query,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum IdentifierCase {
+    /// Case-insensitive if the identifier is all lowercase, otherwise
+    /// case-sensitive, like vim's `smartcase`.
+    Smart,
+    Sensitive,
+    Insensitive,
+}
+
+impl IdentifierCase {
+    pub fn ignore_case(&self, identifier: &str) -> bool {
+        match self {
+            IdentifierCase::Smart => !identifier.chars().any(char::is_uppercase),
+            IdentifierCase::Sensitive => false,
+            IdentifierCase::Insensitive => true,
+        }
+    }
+}
 
 /// Return the crossref data for one or more symbols received via pipeline or as
 /// explicit arguments.
@@ -19,9 +42,27 @@ pub struct SearchIdentifiers {
     #[clap(short, long, value_parser)]
     exact_match: bool,
 
-    /// Should this be case-sensitive?  By default we are case-insensitive.
-    #[clap(short, long, value_parser)]
-    case_sensitive: bool,
+    /// Match identifiers that contain the identifier anywhere instead of just
+    /// as a prefix, ex: "ScrollContainer" finds "nsScrollContainerFrame".  This
+    /// needs the index's identifier suffix array.
+    #[clap(long, value_parser, conflicts_with = "exact_match")]
+    substring: bool,
+
+    /// How to treat case.  The identifiers index is sorted case-insensitively
+    /// and keeps each identifier's original case, so all of these are equally
+    /// fast.
+    #[clap(long, value_parser, value_enum, default_value = "smart")]
+    case: IdentifierCase,
+
+    /// Also treat characters that look alike as the same, ex: Cyrillic "а" as
+    /// Latin "a", and ignore zero-width characters.
+    #[clap(long, value_parser)]
+    fold_confusables: bool,
+
+    /// Search for the identifiers exactly as given instead of normalizing them
+    /// to NFKC like the indexed identifiers, for exact audits.
+    #[clap(long, value_parser, conflicts_with = "fold_confusables")]
+    exact_unicode: bool,
 
     /// Should this only match types as indicated by the `T_` convention?
     /// Currently a hack and this should instead be handled by having the
@@ -37,6 +78,9 @@ pub struct SearchIdentifiers {
 
     #[clap(short, long, value_parser, default_value = "1000")]
     limit: usize,
+
+    #[clap(flatten)]
+    pagination: PaginationArgs,
 }
 
 #[derive(Debug)]
@@ -70,20 +114,27 @@ impl PipelineCommand for SearchIdentifiersCommand {
 
         let mut symbols: Vec<SymbolWithContext> = vec![];
         for id in identifier_list {
+            let id = if self.args.exact_unicode {
+                id
+            } else {
+                normalize_identifier_query(&id, self.args.fold_confusables)
+            };
             // Skip any identifiers that are shorter than our minimum length.
             if id.len() < self.args.min_length {
                 continue;
             }
 
-            for (sym, from_ident) in server
-                .search_identifiers(
-                    &id,
-                    self.args.exact_match,
-                    !self.args.case_sensitive,
-                    self.args.limit,
-                )
-                .await?
-            {
+            let ignore_case = self.args.case.ignore_case(&id);
+            let results = if self.args.substring {
+                server
+                    .search_identifier_substrings(&id, ignore_case, self.args.limit)
+                    .await?
+            } else {
+                server
+                    .search_identifiers(&id, self.args.exact_match, ignore_case, self.args.limit)
+                    .await?
+            };
+            for (sym, from_ident) in results {
                 if self.args.types_only && !sym.starts_with("T_") {
                     continue;
                 }
@@ -96,6 +147,17 @@ impl PipelineCommand for SearchIdentifiersCommand {
                 ) {
                     (true, _, _, _) => SymbolQuality::ExplicitIdentifier,
                     (false, true, _, _) => SymbolQuality::ExactIdentifier,
+                    (_, _, searched, result)
+                        if self.args.substring
+                            && !result
+                                .to_ascii_lowercase()
+                                .starts_with(&searched.to_ascii_lowercase()) =>
+                    {
+                        SymbolQuality::IdentifierSubstring(
+                            searched.len() as u32,
+                            (result.len() - searched.len()) as u32,
+                        )
+                    }
                     (_, _, searched, result) => SymbolQuality::IdentifierPrefix(
                         searched.len() as u32,
                         (result.len() - searched.len()) as u32,
@@ -109,6 +171,10 @@ impl PipelineCommand for SearchIdentifiersCommand {
             }
         }
 
-        Ok(PipelineValues::SymbolList(SymbolList { symbols }))
+        let (symbols, page) = self
+            .args
+            .pagination
+            .paginate(symbols, |s| s.symbol.to_string())?;
+        Ok(PipelineValues::SymbolList(SymbolList { symbols, page }))
     }
 }
@@ -3,16 +3,24 @@ use clap::Args;
 
 use super::{
     interface::{PipelineCommand, PipelineValues},
+    pagination::PaginationArgs,
+    path_filter::PathFilterArgs,
+    token_scope::{ScopedLines, TokenScope},
     transforms::path_glob_transform,
 };
 
-use crate::abstract_server::{AbstractServer, ErrorDetails, ErrorLayer, Result, ServerError};
+use crate::{
+    abstract_server::{AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError},
+    languages::select_formatting,
+    unicode_folding::text_search_regexp,
+};
 
 /// Perform a fulltext search against our livegrep/codesearch server over gRPC.
 /// This is local-only at this time.
 #[derive(Debug, Args)]
 pub struct SearchText {
-    /// Text to search for; this will be regexp escaped.
+    /// Text to search for; this will be regexp escaped and will also match the
+    /// text's other Unicode normalization forms.
     #[clap(value_parser)]
     text: Option<String>,
 
@@ -35,6 +43,35 @@ pub struct SearchText {
 
     #[clap(short, long, value_parser, default_value = "0")]
     limit: usize,
+
+    /// Also match `text` with characters that look alike, ex: Cyrillic "а" for
+    /// Latin "a".
+    #[clap(long, value_parser)]
+    fold_confusables: bool,
+
+    /// Match `text` exactly as given instead of in any of its Unicode
+    /// normalization forms, for exact audits.
+    #[clap(long, value_parser, conflicts_with = "fold_confusables")]
+    exact_unicode: bool,
+
+    /// Search by reading the files directly instead of using livegrep.  Plain
+    /// `text` searches will use the trigram bloom skip index to avoid reading
+    /// files that can't contain the text; `re` searches must read every file.
+    #[clap(long, value_parser)]
+    scan: bool,
+
+    /// Only keep matches within comments, within string literals, or in code
+    /// outside of both.  This requires reading each file with matches, and
+    /// happens after `limit` is applied.
+    #[clap(long, value_parser, value_enum)]
+    scope: Option<TokenScope>,
+
+    #[clap(flatten)]
+    path_filter: PathFilterArgs,
+
+    /// Text matches are paginated by file.
+    #[clap(flatten)]
+    pagination: PaginationArgs,
 }
 
 #[derive(Debug)]
@@ -42,6 +79,24 @@ pub struct SearchTextCommand {
     pub args: SearchText,
 }
 
+/// Pick the literal that the trigram bloom skip index can check for a scan.
+/// We can only use the skip index when we know a literal that every match must
+/// contain, which isn't the case when the text has other normalization forms.
+/// The blooms are only ASCII case-folded, so case-insensitive searches for text
+/// with non-ASCII characters (ex: "Д" matching "д") can't use them either.
+fn bloom_literal<'a>(
+    has_re: bool,
+    text: Option<&'a str>,
+    re_pattern: &str,
+    case_insensitive: bool,
+) -> Option<&'a str> {
+    let text = text.filter(|_| !has_re)?;
+    if case_insensitive && !text.is_ascii() {
+        return None;
+    }
+    (re_pattern == regex::escape(text)).then_some(text)
+}
+
 #[async_trait]
 impl PipelineCommand for SearchTextCommand {
     async fn execute(
@@ -52,12 +107,17 @@ impl PipelineCommand for SearchTextCommand {
         let re_pattern = if let Some(re) = &self.args.re {
             re.clone()
         } else if let Some(text) = &self.args.text {
-            regex::escape(text)
+            if self.args.exact_unicode {
+                regex::escape(text)
+            } else {
+                text_search_regexp(text, self.args.fold_confusables)
+            }
         } else {
-            return Err(ServerError::StickyProblem(ErrorDetails {
-                layer: ErrorLayer::BadInput,
-                message: "Missing search text or `re` pattern!".to_string(),
-            }));
+            return Err(ServerError::StickyProblem(ErrorDetails::new(
+                ErrorKind::BadQuery,
+                ErrorLayer::BadInput,
+                "Missing search text or `re` pattern!".to_string(),
+            )));
         };
 
         let pathre_pattern = if let Some(pathre) = &self.args.pathre {
@@ -68,15 +128,89 @@ impl PipelineCommand for SearchTextCommand {
             "".to_string()
         };
 
-        let matches = server
-            .search_text(
+        let path_filter = self
+            .args
+            .path_filter
+            .compile(&server.tree_info()?.path_sets)?;
+
+        let mut matches = if self.args.scan {
+            let literal = bloom_literal(
+                self.args.re.is_some(),
+                self.args.text.as_deref(),
                 &re_pattern,
                 !self.args.case_sensitive,
-                &pathre_pattern,
-                self.args.limit,
-            )
-            .await?;
+            );
+            server
+                .search_text_scan(
+                    &re_pattern,
+                    literal,
+                    !self.args.case_sensitive,
+                    &pathre_pattern,
+                    self.args.limit,
+                )
+                .await?
+        } else {
+            server
+                .search_text(
+                    &re_pattern,
+                    !self.args.case_sensitive,
+                    &pathre_pattern,
+                    self.args.limit,
+                )
+                .await?
+        };
+        if !path_filter.is_empty() {
+            matches
+                .by_file
+                .retain(|tmbf| path_filter.matches_classified(&tmbf.file, tmbf.classification));
+        }
+        if let Some(scope) = self.args.scope {
+            for tmbf in matches.by_file.iter_mut() {
+                let scoped = match server.fetch_raw_source(&tmbf.file).await {
+                    Ok(source) => ScopedLines::new(select_formatting(&tmbf.file), &source, scope),
+                    Err(_) => {
+                        tmbf.matches.clear();
+                        continue;
+                    }
+                };
+                tmbf.matches.retain(|m| {
+                    scoped.contains(
+                        m.line_num,
+                        m.bounds.start as usize,
+                        m.bounds.end_exclusive as usize,
+                    )
+                });
+            }
+            matches.by_file.retain(|tmbf| !tmbf.matches.is_empty());
+        }
+        let (by_file, page) = self
+            .args
+            .pagination
+            .paginate(matches.by_file, |tmbf| tmbf.file.to_string())?;
+        matches.by_file = by_file;
+        matches.page = page;
 
         Ok(PipelineValues::TextMatches(matches))
     }
 }
+
+#[test]
+fn test_bloom_literal() {
+    assert_eq!(
+        bloom_literal(false, Some("nsIFoo"), "nsIFoo", true),
+        Some("nsIFoo")
+    );
+    assert_eq!(
+        bloom_literal(false, Some("a.b"), r"a\.b", true),
+        Some("a.b")
+    );
+    // Other normalization forms.
+    assert_eq!(
+        bloom_literal(false, Some("caf\u{e9}"), "caf(?:\u{e9}|e\u{301})", false),
+        None
+    );
+    assert_eq!(bloom_literal(true, Some("nsIFoo"), "nsIFoo", false), None);
+    // Non-ASCII text can only use the blooms when matching case exactly.
+    assert_eq!(bloom_literal(false, Some("ДДД"), "ДДД", false), Some("ДДД"));
+    assert_eq!(bloom_literal(false, Some("ДДД"), "ДДД", true), None);
+}
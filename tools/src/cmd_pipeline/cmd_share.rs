@@ -0,0 +1,46 @@
+use async_trait::async_trait;
+use clap::Args;
+use serde_json::json;
+
+use super::interface::{JsonValue, PipelineCommand, PipelineValues};
+use crate::{
+    abstract_server::{AbstractServer, Result},
+    query::shared_analyses::share_analysis,
+};
+
+/// Store a pipeline so that it can be shared as a link that the
+/// pipeline-server re-runs against the same indexed revision (see
+/// `query/shared_analyses.rs`).  The pipeline is given as a single argument
+/// whose commands are delimited by `|`, and only commands the pipeline-server
+/// allows can be used.  Produces a JsonValue with the token and the link's
+/// path.
+#[derive(Debug, Args)]
+pub struct Share {
+    /// The pipeline to share, ex: "search-identifiers Foo | crossref-lookup".
+    #[clap(value_parser)]
+    pipeline: String,
+}
+
+#[derive(Debug)]
+pub struct ShareCommand {
+    pub args: Share,
+}
+
+#[async_trait]
+impl PipelineCommand for ShareCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        _input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let (token, analysis) = share_analysis(server, &self.args.pipeline)?;
+        Ok(PipelineValues::JsonValue(JsonValue {
+            value: json!({
+                "token": token,
+                "url": format!("/{}/shared/{}", analysis.tree, token),
+                "revision": analysis.revision,
+                "pipeline": analysis.pipeline,
+            }),
+        }))
+    }
+}
@@ -0,0 +1,56 @@
+use async_trait::async_trait;
+use clap::Args;
+use serde_json::Value;
+
+use super::interface::{JsonValue, PipelineCommand, PipelineValues};
+use crate::{
+    abstract_server::{AbstractServer, Result},
+    file_format::index_manifest::IndexManifest,
+};
+
+/// Report what the index was generated from (source revision, mozsearch and
+/// config revisions, config hash, tool versions, build times, and analysis
+/// inputs) as recorded in the index's `index-manifest.json` at build time.
+///
+/// Indices built before manifests existed report a manifest with a `format`
+/// of 0 that only contains what can be determined from the index itself.  The
+/// output also includes the index's probed `capabilities`, which say which
+/// optional data (ex: structured info, blame, coverage) the index has.
+#[derive(Debug, Args)]
+pub struct ShowIndexInfo {}
+
+#[derive(Debug)]
+pub struct ShowIndexInfoCommand {
+    pub args: ShowIndexInfo,
+}
+
+#[async_trait]
+impl PipelineCommand for ShowIndexInfoCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        _input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let manifest = match server.index_manifest().await? {
+            Some(manifest) => manifest,
+            None => {
+                let tree_info = server.tree_info()?;
+                IndexManifest {
+                    tree: Some(tree_info.name),
+                    revision: tree_info.revision,
+                    ..IndexManifest::default()
+                }
+            }
+        };
+
+        let mut value = serde_json::to_value(manifest)?;
+        if let Value::Object(obj) = &mut value {
+            obj.insert(
+                "capabilities".to_string(),
+                serde_json::to_value(server.capabilities())?,
+            );
+        }
+
+        Ok(PipelineValues::JsonValue(JsonValue { value }))
+    }
+}
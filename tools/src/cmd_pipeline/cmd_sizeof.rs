@@ -0,0 +1,222 @@
+use async_trait::async_trait;
+use clap::Args;
+use serde_json::Value;
+use ustr::ustr;
+
+use super::{
+    data_table::{DataCell, DataColumnType, DataTable},
+    interface::{PipelineCommand, PipelineValues},
+    path_filter::{PathFilter, PathFilterArgs},
+    record_layouts::{layout_for_platform, lookup_record_syms, own_holes, record_platforms},
+};
+use crate::{
+    abstract_server::{AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError},
+    file_format::{analysis::parse_structured_value, structured_columns::StructuredColumn},
+};
+
+/// Report the sizes of a batch of types for memory-usage reviews, ex: the
+/// types that come up the most in allocation profiles, as one compact
+/// DataTable with a row per type and group of platforms with the same layout.
+/// Each row has the size, the number of fields, and the bytes lost to holes
+/// between the type's own fields and to padding after its last field; see
+/// `format-symbols` for the full layout of a type.
+///
+/// The types are the given pretty names or symbols, or the symbols piped in
+/// from a command like `search-identifiers`.  If neither is given, every class
+/// and struct defined in the files matching the path filter is reported, which
+/// is only available for local indices with a `structured-columns` file.
+#[derive(Debug, Args)]
+pub struct Sizeof {
+    /// The types, as pretty names (ex: "mozilla::dom::Element") or symbols.
+    #[clap(value_parser)]
+    types: Vec<String>,
+
+    #[clap(flatten)]
+    path_filter: PathFilterArgs,
+
+    /// Report each platform separately instead of grouping platforms with the
+    /// same layout.
+    #[clap(long, value_parser)]
+    ungrouped: bool,
+
+    /// The maximum number of types to report when reporting the types matching
+    /// the path filter.
+    #[clap(long, value_parser, default_value = "1000")]
+    limit: usize,
+}
+
+#[derive(Debug)]
+pub struct SizeofCommand {
+    pub args: Sizeof,
+}
+
+/// The summary of one platform's layout of a type.
+#[derive(Clone, Debug, PartialEq)]
+struct LayoutSummary {
+    size_bytes: Option<u32>,
+    fields: usize,
+    hole_bytes: u32,
+    padding_bytes: u32,
+}
+
+impl SizeofCommand {
+    /// The crossref info (with "meta" and "defs") of the records for the
+    /// positional types.
+    async fn lookup_types(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+    ) -> Result<Vec<(String, Value)>> {
+        let mut records = vec![];
+        for ty in &self.args.types {
+            let syms = lookup_record_syms(server, ty).await?;
+            if syms.is_empty() {
+                return Err(ServerError::StickyProblem(
+                    ErrorDetails::new(
+                        ErrorKind::NotFound,
+                        ErrorLayer::BadInput,
+                        format!("No layout data found for type {}", ty),
+                    )
+                    .with_hint("Pass the type's fully qualified name or its symbol."),
+                ));
+            }
+            for sym in syms {
+                let info = server.crossref_lookup_keys(&sym, &["meta", "defs"]).await?;
+                records.push((sym, info));
+            }
+        }
+        Ok(records)
+    }
+
+    /// The crossref info of the classes and structs defined in the files
+    /// matching the path filter.
+    async fn scan_types(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        path_filter: &PathFilter,
+    ) -> Result<Vec<(String, Value)>> {
+        let is_record = |kind: &Value| matches!(kind.as_str(), Some("class" | "struct"));
+        let syms = match server
+            .scan_structured_column(StructuredColumn::Kind, &is_record, usize::MAX)
+            .await
+        {
+            Err(ServerError::Unsupported) => {
+                return Err(ServerError::StickyProblem(ErrorDetails::new(
+                    ErrorKind::IndexMissingCapability,
+                    ErrorLayer::ConfigLayer,
+                    "sizeof without types requires a local index with structured columns"
+                        .to_string(),
+                )));
+            }
+            result => result?,
+        };
+
+        let mut records = vec![];
+        for sym in syms {
+            if records.len() >= self.args.limit {
+                break;
+            }
+            let info = server.crossref_lookup_keys(&sym, &["meta", "defs"]).await?;
+            if def_path(&info).map_or(false, |path| path_filter.matches(path)) {
+                records.push((sym, info));
+            }
+        }
+        Ok(records)
+    }
+
+    /// The layout summaries of the record, each with the platforms it's for.
+    fn summarize(&self, meta: &Value) -> Vec<(LayoutSummary, Vec<String>)> {
+        let Ok(record) = parse_structured_value(meta.clone()) else {
+            return vec![];
+        };
+        let mut groups: Vec<(LayoutSummary, Vec<String>)> = vec![];
+        for platform in record_platforms(&record) {
+            let layout = layout_for_platform(&record, platform.as_deref());
+            let (hole_bytes, padding_bytes) = own_holes(&layout);
+            let summary = LayoutSummary {
+                size_bytes: layout.size_bytes,
+                fields: layout.fields.len(),
+                hole_bytes,
+                padding_bytes,
+            };
+            let label = platform.unwrap_or_else(|| "*".to_string());
+            match groups.iter_mut().find(|(s, _)| *s == summary) {
+                Some((_, labels)) if !self.args.ungrouped => labels.push(label),
+                _ => groups.push((summary, vec![label])),
+            }
+        }
+        groups
+    }
+}
+
+fn def_path(info: &Value) -> Option<&str> {
+    info.pointer("/defs/0/path").and_then(|path| path.as_str())
+}
+
+#[async_trait]
+impl PipelineCommand for SizeofCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let path_filter = self
+            .args
+            .path_filter
+            .compile(&server.tree_info()?.path_sets)?;
+
+        let records = match input {
+            PipelineValues::SymbolCrossrefInfoList(scil) => scil
+                .symbol_crossref_infos
+                .into_iter()
+                .map(|info| (info.symbol.to_string(), info.crossref_info))
+                .collect(),
+            PipelineValues::Void if !self.args.types.is_empty() => {
+                self.lookup_types(server).await?
+            }
+            PipelineValues::Void if !path_filter.is_empty() => {
+                self.scan_types(server, &path_filter).await?
+            }
+            _ => {
+                return Err(ServerError::StickyProblem(
+                    ErrorDetails::new(
+                        ErrorKind::BadQuery,
+                        ErrorLayer::ConfigLayer,
+                        "sizeof needs types, a path filter, or a SymbolCrossrefInfoList"
+                            .to_string(),
+                    )
+                    .with_hint(
+                        "Use `explain` to see what each pipeline stage consumes and produces.",
+                    ),
+                ));
+            }
+        };
+
+        let mut table = DataTable::new(vec![
+            ("type", DataColumnType::SymbolRef),
+            ("platforms", DataColumnType::String),
+            ("size", DataColumnType::Int),
+            ("fields", DataColumnType::Int),
+            ("holes", DataColumnType::Int),
+            ("padding", DataColumnType::Int),
+        ]);
+        for (sym, info) in records {
+            if !path_filter.is_empty() && !def_path(&info).map_or(false, |p| path_filter.matches(p))
+            {
+                continue;
+            }
+            for (summary, platforms) in self.summarize(&info["meta"]) {
+                table.push_row(vec![
+                    DataCell::Symbol(ustr(&sym)),
+                    DataCell::String(platforms.join(",")),
+                    summary
+                        .size_bytes
+                        .map_or(DataCell::Null, |size| DataCell::Int(size as i64)),
+                    DataCell::Int(summary.fields as i64),
+                    DataCell::Int(summary.hole_bytes as i64),
+                    DataCell::Int(summary.padding_bytes as i64),
+                ])?;
+            }
+        }
+        Ok(PipelineValues::DataTable(table))
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+use clap::{Args, ValueEnum};
+use itertools::Itertools;
+use ustr::ustr;
+
+use super::{
+    data_table::{DataCell, DataColumnType, DataTable},
+    interface::{PipelineCommand, PipelineValues},
+};
+use crate::{
+    abstract_server::{
+        AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, SearchfoxIndexRoot,
+        ServerError,
+    },
+    file_format::static_inits::{StaticInit, StaticInitIndex, STATIC_INITS_FILENAME},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum StaticInitKind {
+    /// Namespace scope variables and static data members, which are
+    /// initialized at startup, ex: global constructors.
+    Global,
+    /// Function-scope statics, which are initialized on first use.
+    StaticLocal,
+    /// `thread_local` variables, which are initialized per thread.
+    ThreadLocal,
+}
+
+impl StaticInitKind {
+    fn analysis_kind(&self) -> &'static str {
+        match self {
+            StaticInitKind::Global => "global",
+            StaticInitKind::StaticLocal => "staticLocal",
+            StaticInitKind::ThreadLocal => "threadLocal",
+        }
+    }
+}
+
+/// List the C++ variables with static or thread storage whose initialization
+/// or destruction runs code, ex: global constructors and static locals with
+/// dynamic initialization, since these affect startup and shutdown.  Each row
+/// gives the variable's definition, which is where its initializer is, and
+/// the constructors, functions, and globals the initializer references.
+///
+/// With `--by-dir`, instead summarize how many such variables each directory
+/// (or library, with `--dir-depth`) has.
+#[derive(Debug, Args)]
+pub struct StaticInits {
+    /// Only include variables defined under this path prefix.
+    #[clap(long, value_parser)]
+    path_prefix: Option<String>,
+
+    /// Only include variables of this kind.
+    #[clap(long, value_parser, value_enum)]
+    kind: Option<StaticInitKind>,
+
+    /// Only include variables whose initializer runs code, leaving out the
+    /// constant-initialized ones that only have a non-trivial destructor.
+    #[clap(long, value_parser)]
+    dynamic_only: bool,
+
+    /// Summarize the variables per directory instead of listing them.
+    #[clap(long, value_parser)]
+    by_dir: bool,
+
+    /// For `--by-dir`, truncate the directories to this many path components
+    /// to aggregate at a coarser granularity, ex: 2 for "dom/media".  0 means
+    /// use the full directory.
+    #[clap(long, value_parser, default_value = "0")]
+    dir_depth: usize,
+}
+
+#[derive(Debug)]
+pub struct StaticInitsCommand {
+    pub args: StaticInits,
+}
+
+/// Per-directory counts for `--by-dir`.
+#[derive(Default)]
+struct DirCounts {
+    total: i64,
+    dynamic_init: i64,
+    destructor: i64,
+    static_local: i64,
+}
+
+impl StaticInitsCommand {
+    fn matches(&self, init: &StaticInit) -> bool {
+        if let Some(prefix) = &self.args.path_prefix {
+            if !init.path.starts_with(prefix.as_str()) {
+                return false;
+            }
+        }
+        if let Some(kind) = self.args.kind {
+            if init.kind != kind.analysis_kind() {
+                return false;
+            }
+        }
+        !self.args.dynamic_only || init.dynamic_init
+    }
+
+    fn summary_dir(&self, path: &str) -> String {
+        let dir = path.rsplit_once('/').map_or("", |(dir, _)| dir);
+        if self.args.dir_depth == 0 {
+            return dir.to_string();
+        }
+        dir.split('/')
+            .take(self.args.dir_depth)
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    fn list_table(&self, inits: &[&StaticInit]) -> Result<DataTable> {
+        let mut table = DataTable::new(vec![
+            ("path", DataColumnType::Path),
+            ("line", DataColumnType::Int),
+            ("symbol", DataColumnType::SymbolRef),
+            ("kind", DataColumnType::String),
+            ("dynamic_init", DataColumnType::Int),
+            ("destructor", DataColumnType::Int),
+            ("refs", DataColumnType::String),
+        ]);
+        for init in inits {
+            table.push_row(vec![
+                DataCell::String(init.path.clone()),
+                DataCell::Int(init.line as i64),
+                DataCell::Symbol(ustr(&init.sym)),
+                DataCell::String(init.kind.clone()),
+                DataCell::Int(init.dynamic_init as i64),
+                DataCell::Int(init.destructor as i64),
+                DataCell::String(init.refs.iter().map(|r| r.pretty.as_str()).join(", ")),
+            ])?;
+        }
+        Ok(table)
+    }
+
+    fn summary_table(&self, inits: &[&StaticInit]) -> Result<DataTable> {
+        let mut dirs: BTreeMap<String, DirCounts> = BTreeMap::new();
+        for init in inits {
+            let counts = dirs.entry(self.summary_dir(&init.path)).or_default();
+            counts.total += 1;
+            counts.dynamic_init += init.dynamic_init as i64;
+            counts.destructor += init.destructor as i64;
+            counts.static_local += (init.kind == "staticLocal") as i64;
+        }
+        // The directories with the most startup work first.
+        let mut dirs: Vec<(String, DirCounts)> = dirs.into_iter().collect();
+        dirs.sort_by(|(a_dir, a), (b_dir, b)| {
+            (b.total - b.static_local)
+                .cmp(&(a.total - a.static_local))
+                .then_with(|| a_dir.cmp(b_dir))
+        });
+
+        let mut table = DataTable::new(vec![
+            ("dir", DataColumnType::Path),
+            ("vars", DataColumnType::Int),
+            ("dynamic_init", DataColumnType::Int),
+            ("destructor", DataColumnType::Int),
+            ("static_local", DataColumnType::Int),
+        ]);
+        for (dir, counts) in dirs {
+            table.push_row(vec![
+                DataCell::String(dir),
+                DataCell::Int(counts.total),
+                DataCell::Int(counts.dynamic_init),
+                DataCell::Int(counts.destructor),
+                DataCell::Int(counts.static_local),
+            ])?;
+        }
+        Ok(table)
+    }
+}
+
+#[async_trait]
+impl PipelineCommand for StaticInitsCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        if !matches!(input, PipelineValues::Void) {
+            return Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::BadQuery,
+                    ErrorLayer::ConfigLayer,
+                    "static-inits needs no input".to_string(),
+                )
+                .with_hint("Use `explain` to see what each pipeline stage consumes and produces."),
+            ));
+        }
+
+        let index_path =
+            server.translate_path(SearchfoxIndexRoot::IndexRoot, STATIC_INITS_FILENAME)?;
+        let Some(index) = StaticInitIndex::load(&index_path)? else {
+            return Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::IndexMissingCapability,
+                    ErrorLayer::DataLayer,
+                    "This index has no static initializer index".to_string(),
+                )
+                .with_hint("Re-index the tree to build `static-inits.json`."),
+            ));
+        };
+
+        let inits: Vec<&StaticInit> = index
+            .inits
+            .iter()
+            .filter(|init| self.matches(init))
+            .collect();
+        let table = if self.args.by_dir {
+            self.summary_table(&inits)?
+        } else {
+            self.list_table(&inits)?
+        };
+        Ok(PipelineValues::DataTable(table))
+    }
+}
@@ -0,0 +1,89 @@
+use async_trait::async_trait;
+use clap::Args;
+use serde_json::{Map, Value};
+use ustr::{ustr, Ustr};
+
+use super::interface::{JsonValue, JsonValueList, PipelineCommand, PipelineValues};
+
+use crate::{
+    abstract_server::{AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError},
+    file_format::structured_columns::StructuredColumn,
+};
+
+/// Look up specific pieces of structured info (sizes, supers, fields,
+/// platforms) for symbols without retrieving their full crossref entries.  On
+/// local indices this is served from the columnar `structured-columns` file.
+#[derive(Debug, Args)]
+pub struct StructuredColumns {
+    /// Explicit symbols to lookup.
+    #[clap(value_parser)]
+    symbols: Vec<String>,
+
+    /// The columns to retrieve; defaults to all of them.
+    #[clap(long, value_parser, value_enum)]
+    column: Vec<StructuredColumn>,
+}
+
+#[derive(Debug)]
+pub struct StructuredColumnsCommand {
+    pub args: StructuredColumns,
+}
+
+#[async_trait]
+impl PipelineCommand for StructuredColumnsCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let symbols: Vec<Ustr> = match input {
+            PipelineValues::SymbolList(sl) => sl.symbols.into_iter().map(|s| s.symbol).collect(),
+            PipelineValues::SymbolCrossrefInfoList(scil) => scil
+                .symbol_crossref_infos
+                .into_iter()
+                .map(|info| info.symbol)
+                .collect(),
+            PipelineValues::Void => self.args.symbols.iter().map(|s| ustr(s)).collect(),
+            _ => {
+                return Err(ServerError::StickyProblem(
+                    ErrorDetails::new(
+                        ErrorKind::BadQuery,
+                        ErrorLayer::ConfigLayer,
+                        "structured-columns needs a Void, SymbolList, or SymbolCrossrefInfoList"
+                            .to_string(),
+                    )
+                    .with_hint(
+                        "Use `explain` to see what each pipeline stage consumes and produces.",
+                    ),
+                ));
+            }
+        };
+
+        let columns = if self.args.column.is_empty() {
+            vec![
+                StructuredColumn::Kind,
+                StructuredColumn::SizeBytes,
+                StructuredColumn::Supers,
+                StructuredColumn::Fields,
+                StructuredColumn::Platforms,
+            ]
+        } else {
+            self.args.column.clone()
+        };
+
+        let mut values = vec![];
+        for sym in symbols {
+            let mut obj = Map::new();
+            obj.insert("sym".to_string(), Value::from(sym.as_str()));
+            for column in &columns {
+                let val = server.structured_column_lookup(&sym, *column).await?;
+                obj.insert(column.name().to_string(), val);
+            }
+            values.push(JsonValue {
+                value: Value::Object(obj),
+            });
+        }
+
+        Ok(PipelineValues::JsonValueList(JsonValueList { values }))
+    }
+}
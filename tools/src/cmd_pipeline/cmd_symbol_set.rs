@@ -0,0 +1,167 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+use async_trait::async_trait;
+use clap::{Args, ValueEnum};
+
+use super::interface::{PipelineJunctionCommand, PipelineValues, SymbolCrossrefInfoList};
+
+use crate::abstract_server::{
+    AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum SymbolSetOp {
+    /// Symbols present in any input.
+    Union,
+    /// Symbols present in every input.
+    Intersect,
+    /// Symbols present in the base input but not in any of the others.
+    Subtract,
+}
+
+/// Junction combining the SymbolCrossrefInfoLists produced by multiple
+/// sub-pipelines with a set operation keyed by symbol, enabling queries like
+/// "classes under dom/ that are NOT referenced from js/".  Results keep the
+/// order in which they were first seen (in the base input for `intersect` and
+/// `subtract`), and the flags of all of the inputs' infos for a symbol are
+/// combined.  Missing (Void) inputs are treated as empty.
+#[derive(Debug, Args)]
+pub struct SymbolSet {
+    #[clap(value_parser, value_enum)]
+    op: SymbolSetOp,
+
+    /// The name of the input that `subtract` subtracts the others from and
+    /// whose order `intersect` uses.  Defaults to the first input.
+    #[clap(long, value_parser)]
+    base: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct SymbolSetCommand {
+    pub args: SymbolSet,
+}
+
+/// Apply the set operation to the lists, where `base` is the index of the base
+/// list, calling `merge` to fold later duplicates of an item into the item we
+/// keep.
+fn combine<T, K: Eq + Hash>(
+    op: SymbolSetOp,
+    mut lists: Vec<Vec<T>>,
+    base: usize,
+    key: impl Fn(&T) -> K,
+    merge: impl Fn(&mut T, T),
+) -> Vec<T> {
+    if lists.is_empty() {
+        return vec![];
+    }
+    let base_list = lists.remove(base);
+    let others: Vec<HashSet<K>> = lists
+        .iter()
+        .map(|list| list.iter().map(&key).collect())
+        .collect();
+
+    let mut results: Vec<T> = vec![];
+    let mut positions: HashMap<K, usize> = HashMap::new();
+    let mut fold_in = |results: &mut Vec<T>, item: T| match positions.get(&key(&item)) {
+        Some(&pos) => merge(&mut results[pos], item),
+        None => {
+            positions.insert(key(&item), results.len());
+            results.push(item);
+        }
+    };
+
+    match op {
+        SymbolSetOp::Union => {
+            for item in base_list.into_iter().chain(lists.into_iter().flatten()) {
+                fold_in(&mut results, item);
+            }
+        }
+        SymbolSetOp::Intersect => {
+            for item in base_list {
+                if others.iter().all(|other| other.contains(&key(&item))) {
+                    fold_in(&mut results, item);
+                }
+            }
+            let kept: HashSet<K> = results.iter().map(&key).collect();
+            for item in lists.into_iter().flatten() {
+                if kept.contains(&key(&item)) {
+                    fold_in(&mut results, item);
+                }
+            }
+        }
+        SymbolSetOp::Subtract => {
+            for item in base_list {
+                if !others.iter().any(|other| other.contains(&key(&item))) {
+                    fold_in(&mut results, item);
+                }
+            }
+        }
+    }
+    results
+}
+
+#[async_trait]
+impl PipelineJunctionCommand for SymbolSetCommand {
+    async fn execute(
+        &self,
+        _server: &(dyn AbstractServer + Send + Sync),
+        input: Vec<(String, PipelineValues)>,
+    ) -> Result<PipelineValues> {
+        let base = match &self.args.base {
+            Some(base_name) => input
+                .iter()
+                .position(|(name, _)| name == base_name)
+                .ok_or_else(|| {
+                    ServerError::StickyProblem(ErrorDetails::new(
+                        ErrorKind::BadQuery,
+                        ErrorLayer::ConfigLayer,
+                        format!("symbol-set has no input named {}", base_name),
+                    ))
+                })?,
+            None => 0,
+        };
+
+        let mut infos = vec![];
+        let mut unknowns = vec![];
+        for (_name, pipe_value) in input {
+            match pipe_value {
+                PipelineValues::SymbolCrossrefInfoList(scil) => {
+                    infos.push(scil.symbol_crossref_infos);
+                    unknowns.push(scil.unknown_symbols);
+                }
+                PipelineValues::Void => {
+                    infos.push(vec![]);
+                    unknowns.push(vec![]);
+                }
+                _ => {
+                    return Err(ServerError::StickyProblem(
+                        ErrorDetails::new(
+                            ErrorKind::BadQuery,
+                            ErrorLayer::ConfigLayer,
+                            "symbol-set needs SymbolCrossrefInfoList inputs".to_string(),
+                        )
+                        .with_hint(
+                            "Use `explain` to see what each pipeline stage consumes and produces.",
+                        ),
+                    ));
+                }
+            }
+        }
+
+        let op = self.args.op;
+        Ok(PipelineValues::SymbolCrossrefInfoList(
+            SymbolCrossrefInfoList {
+                symbol_crossref_infos: combine(
+                    op,
+                    infos,
+                    base,
+                    |info| info.symbol,
+                    |existing, dupe| existing.flags |= dupe.flags,
+                ),
+                unknown_symbols: combine(op, unknowns, base, |s| s.clone(), |_, _| {}),
+                page: None,
+            },
+        ))
+    }
+}
@@ -0,0 +1,95 @@
+use async_trait::async_trait;
+use clap::Args;
+use ustr::Ustr;
+
+use super::{
+    data_table::{DataCell, DataColumnType, DataTable},
+    interface::{PipelineCommand, PipelineValues},
+    test_mapping::{map_symbols_to_tests, TestFileHit},
+};
+use crate::abstract_server::{
+    AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError,
+};
+
+/// Map the received symbols to the test files that reference them, to help
+/// answer "which tests should I run for this change", ex:
+/// `search-identifiers nsFoo::Bar | crossref-lookup | tests-for-symbol`.
+///
+/// Test files (per the tree's path classification) that use a symbol directly
+/// are at depth 0.  With `--depth`, the functions containing the symbol's
+/// non-test uses are treated as callers and their test uses are reported at
+/// depth 1, and so on.  Produces a DataTable with a row per test file, closest
+/// first, along with the symbol whose use put the file in the list.
+#[derive(Debug, Args)]
+pub struct TestsForSymbol {
+    /// How many levels of callers to follow from the received symbols.
+    #[clap(long, value_parser, default_value = "1")]
+    depth: u32,
+
+    /// Maximum number of callers to look up at each level, since popular
+    /// symbols can have an enormous number of callers whose tests are unlikely
+    /// to be specifically relevant.
+    #[clap(long, value_parser, default_value = "200")]
+    max_callers: usize,
+
+    #[clap(short, long, value_parser, default_value = "500")]
+    limit: usize,
+}
+
+#[derive(Debug)]
+pub struct TestsForSymbolCommand {
+    pub args: TestsForSymbol,
+}
+
+#[async_trait]
+impl PipelineCommand for TestsForSymbolCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let scil = match input {
+            PipelineValues::SymbolCrossrefInfoList(scil) => scil,
+            _ => {
+                return Err(ServerError::StickyProblem(
+                    ErrorDetails::new(
+                        ErrorKind::BadQuery,
+                        ErrorLayer::ConfigLayer,
+                        "tests-for-symbol needs a SymbolCrossrefInfoList".to_string(),
+                    )
+                    .with_hint(
+                        "Use `explain` to see what each pipeline stage consumes and produces.",
+                    ),
+                ));
+            }
+        };
+
+        let symbols: Vec<_> = scil
+            .symbol_crossref_infos
+            .iter()
+            .map(|info| (info.symbol, &info.crossref_info))
+            .collect();
+        let hits =
+            map_symbols_to_tests(server, &symbols, self.args.depth, self.args.max_callers).await?;
+
+        let mut rows: Vec<(Ustr, TestFileHit)> = hits.into_iter().collect();
+        rows.sort_by_key(|(path, hit)| (hit.depth, *path));
+
+        let mut table = DataTable::new(vec![
+            ("path", DataColumnType::Path),
+            ("depth", DataColumnType::Int),
+            ("via", DataColumnType::SymbolRef),
+            ("uses", DataColumnType::Int),
+        ]);
+        for (path, hit) in rows.into_iter().take(self.args.limit) {
+            table.push_row(vec![
+                DataCell::String(path.to_string()),
+                DataCell::Int(hit.depth as i64),
+                DataCell::Symbol(hit.via),
+                DataCell::Int(hit.uses as i64),
+            ])?;
+        }
+
+        Ok(PipelineValues::DataTable(table))
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use clap::Args;
+use regex::Regex;
+
+use super::{
+    data_table::{DataCell, DataColumnType, DataTable},
+    interface::{PipelineCommand, PipelineValues},
+    transforms::path_glob_transform,
+};
+use crate::{
+    abstract_server::{
+        AbstractServer, BlameLine, ErrorDetails, ErrorKind, ErrorLayer, Result, SearchfoxIndexRoot,
+        ServerError,
+    },
+    file_format::todo_comments::{TodoIndex, TODO_INDEX_FILENAME},
+};
+
+/// List the TODO, FIXME, and XXX comments in the tree so teams can triage
+/// stale TODOs, ex: `todos --path "dom/media/**" --older-than-days 730`.  This
+/// uses the `todo-comments.json` index that crossref builds, which records the
+/// `TODO(owner)` convention and any bug number mentioned by the comment.
+///
+/// When the index has blame, each comment is attributed to the author of the
+/// revision that last changed its line, and the comments are listed stalest
+/// first; otherwise they're listed in path order.  Produces a DataTable.
+#[derive(Debug, Args)]
+pub struct Todos {
+    /// Only include comments in files matching this path glob.
+    #[clap(long, value_parser)]
+    path: Option<String>,
+
+    /// Only include comments in files matching this regexp.
+    #[clap(long, value_parser, conflicts_with = "path")]
+    pathre: Option<String>,
+
+    /// Only include comments with these tags, ex: "FIXME".
+    #[clap(long, value_parser)]
+    tag: Vec<String>,
+
+    /// Only include comments whose blame author or `TODO(owner)` contains
+    /// this, case-insensitively.
+    #[clap(long, value_parser)]
+    author: Option<String>,
+
+    /// Only include comments whose line was last changed at least this many
+    /// days ago.  Requires blame.
+    #[clap(long, value_parser)]
+    older_than_days: Option<u64>,
+
+    #[clap(short, long, value_parser, default_value = "1000")]
+    limit: usize,
+}
+
+#[derive(Debug)]
+pub struct TodosCommand {
+    pub args: Todos,
+}
+
+#[async_trait]
+impl PipelineCommand for TodosCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        if !matches!(input, PipelineValues::Void) {
+            return Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::BadQuery,
+                    ErrorLayer::ConfigLayer,
+                    "todos needs no input".to_string(),
+                )
+                .with_hint("Use `explain` to see what each pipeline stage consumes and produces."),
+            ));
+        }
+
+        let has_blame = server.capabilities().blame;
+        if self.args.older_than_days.is_some() && !has_blame {
+            return Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::IndexMissingCapability,
+                    ErrorLayer::ConfigLayer,
+                    "todos --older-than-days requires a local index with blame".to_string(),
+                )
+                .with_hint("Configure the tree's `git_blame_path` and re-index."),
+            ));
+        }
+
+        let index_path =
+            server.translate_path(SearchfoxIndexRoot::IndexRoot, TODO_INDEX_FILENAME)?;
+        let Some(index) = TodoIndex::load(&index_path)? else {
+            return Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::IndexMissingCapability,
+                    ErrorLayer::DataLayer,
+                    "This index has no TODO comments index".to_string(),
+                )
+                .with_hint("Re-index the tree to build `todo-comments.json`."),
+            ));
+        };
+
+        let path_re = match (&self.args.path, &self.args.pathre) {
+            (Some(path), _) => Some(Regex::new(&path_glob_transform(path))?),
+            (None, Some(pathre)) => Some(Regex::new(pathre)?),
+            (None, None) => None,
+        };
+        let author = self.args.author.as_ref().map(|a| a.to_lowercase());
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs() as i64);
+        let cutoff = self
+            .args
+            .older_than_days
+            .map(|days| now - (days * 24 * 60 * 60) as i64);
+
+        let mut blames: BTreeMap<String, Vec<BlameLine>> = BTreeMap::new();
+        let mut rows = vec![];
+        for todo in index.todos {
+            if let Some(path_re) = &path_re {
+                if !path_re.is_match(&todo.path) {
+                    continue;
+                }
+            }
+            if !self.args.tag.is_empty()
+                && !self
+                    .args
+                    .tag
+                    .iter()
+                    .any(|tag| tag.eq_ignore_ascii_case(&todo.tag))
+            {
+                continue;
+            }
+
+            let blame = if has_blame {
+                if !blames.contains_key(&todo.path) {
+                    // Files added since the blame was built have no blame.
+                    let lines = server.fetch_blame(&todo.path).await.unwrap_or_default();
+                    blames.insert(todo.path.clone(), lines);
+                }
+                blames[&todo.path].get(todo.line as usize - 1).cloned()
+            } else {
+                None
+            };
+
+            if let Some(author) = &author {
+                let matches = |name: &str| name.to_lowercase().contains(author.as_str());
+                if !blame.as_ref().map_or(false, |b| matches(&b.author))
+                    && !todo.owner.as_deref().map_or(false, matches)
+                {
+                    continue;
+                }
+            }
+            if let Some(cutoff) = cutoff {
+                if !blame.as_ref().map_or(false, |b| b.time <= cutoff) {
+                    continue;
+                }
+            }
+            rows.push((todo, blame));
+        }
+
+        if has_blame {
+            // Stalest first, with comments lacking blame last.
+            rows.sort_by_key(|(_, blame)| blame.as_ref().map_or(i64::MAX, |b| b.time));
+        }
+
+        let mut table = DataTable::new(vec![
+            ("path", DataColumnType::Path),
+            ("line", DataColumnType::Int),
+            ("tag", DataColumnType::String),
+            ("owner", DataColumnType::String),
+            ("bug", DataColumnType::Int),
+            ("text", DataColumnType::String),
+            ("author", DataColumnType::String),
+            ("age_days", DataColumnType::Int),
+            ("rev", DataColumnType::String),
+        ]);
+        for (todo, blame) in rows.into_iter().take(self.args.limit) {
+            let (author, age_days, rev) = match blame {
+                Some(blame) => (
+                    DataCell::String(blame.author),
+                    DataCell::Int((now - blame.time) / (24 * 60 * 60)),
+                    DataCell::String(blame.rev),
+                ),
+                None => (DataCell::Null, DataCell::Null, DataCell::Null),
+            };
+            table.push_row(vec![
+                DataCell::String(todo.path),
+                DataCell::Int(todo.line as i64),
+                DataCell::String(todo.tag),
+                todo.owner.map_or(DataCell::Null, DataCell::String),
+                todo.bug
+                    .map_or(DataCell::Null, |bug| DataCell::Int(bug as i64)),
+                DataCell::String(todo.text),
+                author,
+                age_days,
+                rev,
+            ])?;
+        }
+
+        Ok(PipelineValues::DataTable(table))
+    }
+}
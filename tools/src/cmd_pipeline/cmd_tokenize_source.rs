@@ -3,7 +3,7 @@ use clap::{Args, ValueEnum};
 
 use super::interface::{JsonValue, JsonValueList, PipelineCommand, PipelineValues, TextFile};
 use crate::{
-    abstract_server::{AbstractServer, ErrorDetails, ErrorLayer, Result, ServerError},
+    abstract_server::{AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError},
     tree_sitter_support::cst_tokenizer::hypertokenize_source_file,
 };
 
@@ -43,10 +43,11 @@ impl PipelineCommand for TokenizeSourceCommand {
         let token_lines = match hypertokenize_source_file(&self.args.file, &source_str) {
             Ok(content) => content,
             Err(e) => {
-                return Err(ServerError::StickyProblem(ErrorDetails {
-                    layer: ErrorLayer::DataLayer,
-                    message: e,
-                }));
+                return Err(ServerError::StickyProblem(ErrorDetails::new(
+                    ErrorKind::Internal,
+                    ErrorLayer::DataLayer,
+                    e,
+                )));
             }
         };
 
@@ -9,6 +9,7 @@ use ustr::{ustr, Ustr};
 
 use super::{
     interface::{OverloadInfo, OverloadKind, PipelineCommand, PipelineValues, SymbolMetaFlags},
+    path_filter::PathFilterArgs,
     symbol_graph::{
         DerivedSymbolInfo, NamedSymbolGraph, SymbolBadge, SymbolGraphCollection,
         SymbolGraphEdgeSet, SymbolGraphNodeSet,
@@ -16,7 +17,7 @@ use super::{
 };
 
 use crate::{
-    abstract_server::{AbstractServer, ErrorDetails, ErrorLayer, Result, ServerError},
+    abstract_server::{AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError},
     cmd_pipeline::symbol_graph::{EdgeDetail, EdgeKind},
     file_format::{
         analysis::{
@@ -63,6 +64,11 @@ pub struct Traverse {
     #[clap(long, value_parser)]
     retain_all_symbol_data: bool,
 
+    /// If specified, record which symbol and edge caused each symbol to be
+    /// included and emit it as "provenance" in the JSON output.
+    #[clap(long, value_parser)]
+    provenance: bool,
+
     /// Maximum number of nodes in a resulting graph.  When paths are involved,
     /// we may opt to add the entirety of the path that puts the graph over the
     /// node limit rather than omitting it.
@@ -101,6 +107,12 @@ pub struct Traverse {
     /// of the uses.
     #[clap(long, value_parser, default_value = "24")]
     pub skip_field_member_uses_at_count: u32,
+
+    /// Symbols whose definitions fail this path filter will still be shown in
+    /// the graph if we encounter them, but we won't traverse their edges.  The
+    /// initial symbols are always traversed.
+    #[clap(flatten)]
+    pub path_filter: PathFilterArgs,
 }
 
 #[derive(Debug)]
@@ -164,14 +176,26 @@ impl PipelineCommand for TraverseCommand {
         let cil = match input {
             PipelineValues::SymbolCrossrefInfoList(cil) => cil,
             _ => {
-                return Err(ServerError::StickyProblem(ErrorDetails {
-                    layer: ErrorLayer::ConfigLayer,
-                    message: "traverse needs a CrossrefInfoList".to_string(),
-                }));
+                return Err(ServerError::StickyProblem(
+                    ErrorDetails::new(
+                        ErrorKind::BadQuery,
+                        ErrorLayer::ConfigLayer,
+                        "traverse needs a CrossrefInfoList".to_string(),
+                    )
+                    .with_hint(
+                        "Use `explain` to see what each pipeline stage consumes and produces.",
+                    ),
+                ));
             }
         };
 
+        let path_filter = self
+            .args
+            .path_filter
+            .compile(&server.tree_info()?.path_sets)?;
+
         let mut sym_node_set = SymbolGraphNodeSet::new();
+        sym_node_set.record_provenance = self.args.provenance;
         let mut sym_edge_set = SymbolGraphEdgeSet::new();
         let mut graph = NamedSymbolGraph::new("only".to_string());
 
@@ -321,6 +345,21 @@ impl PipelineCommand for TraverseCommand {
             // really need that.
             let (sym_id, sym_info) = sym_node_set.ensure_symbol(&sym, server, depth).await?;
 
+            let path_filtered = match sym_info.get_def_path() {
+                Some(def_path) => {
+                    !path_filter.matches_classified(def_path, sym_info.get_def_classification())
+                }
+                None => false,
+            };
+            // only skip if this isn't the requested symbol (depth == 0)
+            if depth > 0 && path_filtered {
+                trace!(sym = %sym, "not traversing because of path filter");
+                if !self.args.retain_all_symbol_data {
+                    sym_info.reduce_memory_usage_by_dropping_non_jumpref_info();
+                }
+                continue;
+            }
+
             if let Some(stop_at_label) = &stop_at_class_label {
                 if let Some(labels_json) = sym_info.crossref_info.pointer("/meta/labels").cloned() {
                     let labels: Vec<Ustr> = from_value(labels_json).unwrap();
@@ -363,7 +402,13 @@ impl PipelineCommand for TraverseCommand {
                         for ptr_info in field.pointer_info {
                             show_field = true;
                             let (target_id, target_info) = sym_node_set
-                                .ensure_symbol(&ptr_info.sym, server, next_depth)
+                                .ensure_symbol_via(
+                                    &ptr_info.sym,
+                                    server,
+                                    next_depth,
+                                    sym,
+                                    "pointer",
+                                )
                                 .await?;
                             if next_depth < max_depth && considered.insert(ptr_info.sym) {
                                 trace!(sym = ptr_info.sym.as_str(), "scheduling pointee sym");
@@ -397,7 +442,7 @@ impl PipelineCommand for TraverseCommand {
 
                         if show_field {
                             let (field_id, field_info) = sym_node_set
-                                .ensure_symbol(&field.sym, server, next_depth)
+                                .ensure_symbol_via(&field.sym, server, next_depth, sym, "field")
                                 .await?;
                             field_info.effective_subsystem = effective_subsystem;
                             for label in field.labels {
@@ -432,10 +477,11 @@ impl PipelineCommand for TraverseCommand {
 
             if depth as i32 <= traverse_field_member_uses && next_depth < max_depth {
                 let bad_data = || {
-                    ServerError::StickyProblem(ErrorDetails {
-                        layer: ErrorLayer::DataLayer,
-                        message: format!("Bad edge info in sym {sym} on field-member-uses"),
-                    })
+                    ServerError::StickyProblem(ErrorDetails::new(
+                        ErrorKind::Internal,
+                        ErrorLayer::DataLayer,
+                        format!("Bad edge info in sym {sym} on field-member-uses"),
+                    ))
                 };
 
                 // Find the places where this type is used as a field member.
@@ -471,7 +517,13 @@ impl PipelineCommand for TraverseCommand {
                         let target_sym = ustr(target_sym_str);
 
                         let (_target_id, target_info) = sym_node_set
-                            .ensure_symbol(&target_sym, server, next_depth)
+                            .ensure_symbol_via(
+                                &target_sym,
+                                server,
+                                next_depth,
+                                sym,
+                                "field-member-use",
+                            )
                             .await?;
 
                         // we already considered depth in the outer condition
@@ -567,14 +619,20 @@ impl PipelineCommand for TraverseCommand {
                     };
                 if should_traverse {
                     let (owner_id, owner_info) = sym_node_set
-                        .ensure_symbol(&slot_owner.sym, server, next_depth)
+                        .ensure_symbol_via(&slot_owner.sym, server, next_depth, sym, "slot-owner")
                         .await?;
 
                     // Handle the case where we need to traverse a slot
                     if let Some(other_slot) = traverse_slot {
                         if let Some(other_sym) = owner_info.get_binding_slot_sym(other_slot) {
                             let (other_id, other_info) = sym_node_set
-                                .ensure_symbol(&other_sym, server, next_depth)
+                                .ensure_symbol_via(
+                                    &other_sym,
+                                    server,
+                                    next_depth,
+                                    sym,
+                                    "binding-slot",
+                                )
                                 .await?;
                             if outbound_edge {
                                 sym_edge_set.ensure_edge_in_graph(
@@ -682,7 +740,7 @@ impl PipelineCommand for TraverseCommand {
                             skip_after_slots = true;
                         }
                         let (rel_id, _) = sym_node_set
-                            .ensure_symbol(&slot.sym, server, next_depth)
+                            .ensure_symbol_via(&slot.sym, server, next_depth, sym, "binding-slot")
                             .await?;
                         if outbound_edge {
                             sym_edge_set.ensure_edge_in_graph(
@@ -730,7 +788,13 @@ impl PipelineCommand for TraverseCommand {
                     if should_traverse {
                         for rel_sym in slot.syms {
                             let (rel_id, _) = sym_node_set
-                                .ensure_symbol(&rel_sym, server, next_depth)
+                                .ensure_symbol_via(
+                                    &rel_sym,
+                                    server,
+                                    next_depth,
+                                    sym,
+                                    "ontology-slot",
+                                )
                                 .await?;
                             if upwards {
                                 sym_edge_set.ensure_edge_in_graph(
@@ -769,10 +833,11 @@ impl PipelineCommand for TraverseCommand {
 
             if traverse_subclasses && cur_traversals.contains(Traversals::Subclass) {
                 let bad_data = || {
-                    ServerError::StickyProblem(ErrorDetails {
-                        layer: ErrorLayer::DataLayer,
-                        message: format!("Bad edge info in sym {sym} on meta subclasses"),
-                    })
+                    ServerError::StickyProblem(ErrorDetails::new(
+                        ErrorKind::Internal,
+                        ErrorLayer::DataLayer,
+                        format!("Bad edge info in sym {sym} on meta subclasses"),
+                    ))
                 };
 
                 let sym_info = sym_node_set.get(&sym_id);
@@ -788,7 +853,7 @@ impl PipelineCommand for TraverseCommand {
                     let target_sym = ustr(target_sym_str);
 
                     let (target_id, target_info) = sym_node_set
-                        .ensure_symbol(&target_sym, server, next_depth)
+                        .ensure_symbol_via(&target_sym, server, next_depth, sym, "subclass")
                         .await?;
 
                     sym_edge_set.ensure_edge_in_graph(
@@ -815,10 +880,11 @@ impl PipelineCommand for TraverseCommand {
 
             if traverse_superclasses && cur_traversals.contains(Traversals::Super) {
                 let bad_data = || {
-                    ServerError::StickyProblem(ErrorDetails {
-                        layer: ErrorLayer::DataLayer,
-                        message: format!("Bad edge info in sym {sym} on meta superclasses"),
-                    })
+                    ServerError::StickyProblem(ErrorDetails::new(
+                        ErrorKind::Internal,
+                        ErrorLayer::DataLayer,
+                        format!("Bad edge info in sym {sym} on meta superclasses"),
+                    ))
                 };
 
                 let sym_info = sym_node_set.get(&sym_id);
@@ -834,7 +900,7 @@ impl PipelineCommand for TraverseCommand {
                     let target_sym = ustr(target_sym_str);
 
                     let (target_id, target_info) = sym_node_set
-                        .ensure_symbol(&target_sym, server, next_depth)
+                        .ensure_symbol_via(&target_sym, server, next_depth, sym, "super")
                         .await?;
 
                     if let Some(Value::Array(labels_json)) =
@@ -877,10 +943,11 @@ impl PipelineCommand for TraverseCommand {
 
             if traverse_overrides {
                 let bad_data = || {
-                    ServerError::StickyProblem(ErrorDetails {
-                        layer: ErrorLayer::DataLayer,
-                        message: format!("Bad edge info in sym {sym} on meta overrides"),
-                    })
+                    ServerError::StickyProblem(ErrorDetails::new(
+                        ErrorKind::Internal,
+                        ErrorLayer::DataLayer,
+                        format!("Bad edge info in sym {sym} on meta overrides"),
+                    ))
                 };
 
                 let sym_info = sym_node_set.get(&sym_id);
@@ -896,7 +963,7 @@ impl PipelineCommand for TraverseCommand {
                     let target_sym = ustr(target_sym_str);
 
                     let (target_id, target_info) = sym_node_set
-                        .ensure_symbol(&target_sym, server, next_depth)
+                        .ensure_symbol_via(&target_sym, server, next_depth, sym, "overrides")
                         .await?;
 
                     if considered.insert(target_info.symbol) {
@@ -931,10 +998,11 @@ impl PipelineCommand for TraverseCommand {
 
             if traverse_overridden_by {
                 let bad_data = || {
-                    ServerError::StickyProblem(ErrorDetails {
-                        layer: ErrorLayer::DataLayer,
-                        message: format!("Bad edge info in sym {sym} on meta overriddenBy"),
-                    })
+                    ServerError::StickyProblem(ErrorDetails::new(
+                        ErrorKind::Internal,
+                        ErrorLayer::DataLayer,
+                        format!("Bad edge info in sym {sym} on meta overriddenBy"),
+                    ))
                 };
 
                 let sym_info = sym_node_set.get(&sym_id);
@@ -950,7 +1018,7 @@ impl PipelineCommand for TraverseCommand {
                     let target_sym = ustr(target_sym_str);
 
                     let (target_id, target_info) = sym_node_set
-                        .ensure_symbol(&target_sym, server, next_depth)
+                        .ensure_symbol_via(&target_sym, server, next_depth, sym, "overridden-by")
                         .await?;
 
                     if considered.insert(target_info.symbol) {
@@ -976,10 +1044,11 @@ impl PipelineCommand for TraverseCommand {
 
             if traverse_callees {
                 let bad_data = || {
-                    ServerError::StickyProblem(ErrorDetails {
-                        layer: ErrorLayer::DataLayer,
-                        message: format!("Bad edge info in sym {sym} on callees"),
-                    })
+                    ServerError::StickyProblem(ErrorDetails::new(
+                        ErrorKind::Internal,
+                        ErrorLayer::DataLayer,
+                        format!("Bad edge info in sym {sym} on callees"),
+                    ))
                 };
 
                 let sym_info = sym_node_set.get_mut(&sym_id);
@@ -1014,7 +1083,7 @@ impl PipelineCommand for TraverseCommand {
                     }
 
                     let (target_id, target_info) = sym_node_set
-                        .ensure_symbol(&target_sym, server, next_depth)
+                        .ensure_symbol_via(&target_sym, server, next_depth, sym, "callee")
                         .await?;
 
                     if target_info.is_callable() {
@@ -1039,10 +1108,11 @@ impl PipelineCommand for TraverseCommand {
 
             if traverse_uses {
                 let bad_data = || {
-                    ServerError::StickyProblem(ErrorDetails {
-                        layer: ErrorLayer::DataLayer,
-                        message: format!("Bad edge info in sym {sym} on callees"),
-                    })
+                    ServerError::StickyProblem(ErrorDetails::new(
+                        ErrorKind::Internal,
+                        ErrorLayer::DataLayer,
+                        format!("Bad edge info in sym {sym} on callees"),
+                    ))
                 };
 
                 let sym_info = sym_node_set.get_mut(&sym_id);
@@ -1137,7 +1207,7 @@ impl PipelineCommand for TraverseCommand {
                         let source_sym = ustr(source_sym_str);
 
                         let (source_id, source_info) = sym_node_set
-                            .ensure_symbol(&source_sym, server, next_depth)
+                            .ensure_symbol_via(&source_sym, server, next_depth, sym, "use")
                             .await?;
 
                         if source_info.is_callable() {
@@ -1179,6 +1249,8 @@ impl PipelineCommand for TraverseCommand {
             // expect it to have an order of magnitude more data than we want
             // in the result set.  So we build a new node set and graph.
             let mut paths_node_set = SymbolGraphNodeSet::new();
+            // Provenance is carried over from the original node set.
+            paths_node_set.record_provenance = self.args.provenance;
             let mut paths_edge_set = SymbolGraphEdgeSet::new();
             let mut paths_graph = NamedSymbolGraph::new("paths".to_string());
 
@@ -0,0 +1,173 @@
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use async_trait::async_trait;
+use clap::Args;
+
+use super::{
+    data_table::{DataCell, DataColumnType, DataTable},
+    interface::{PipelineCommand, PipelineValues},
+};
+use crate::{
+    abstract_server::{
+        AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, SearchfoxIndexRoot,
+        ServerError,
+    },
+    file_format::symbol_kind_stats::{SymbolKindStats, SYMBOL_KIND_STATS_FILENAME},
+};
+
+/// Compare the per-directory symbol kind counts of two index generations, so
+/// that build and metrics teams can watch the growth of code constructs, ex:
+/// virtual methods, over time.  Each generation's counts are written by
+/// crossref to `symbol-kind-stats.json` in the index directory, so comparing
+/// against an older generation requires an archived copy of that file (or the
+/// older index directory).
+///
+/// Produces a DataTable with a row per directory and kind whose count changed,
+/// biggest changes first.
+#[derive(Debug, Args)]
+pub struct Trend {
+    /// The `symbol-kind-stats.json` of the generation to compare against, or
+    /// the index directory containing it.
+    #[clap(value_parser)]
+    base: String,
+
+    /// The `symbol-kind-stats.json` (or index directory) of the generation to
+    /// compare, instead of the current index.
+    #[clap(long, value_parser)]
+    head: Option<String>,
+
+    /// Only include these kinds, ex: "class" or "virtualMethod".
+    #[clap(long, value_parser)]
+    kind: Vec<String>,
+
+    /// Only include directories under this path prefix.
+    #[clap(long, value_parser)]
+    path_prefix: Option<String>,
+
+    /// Truncate the directories to this many path components to aggregate at
+    /// a coarser granularity, ex: 2 for "dom/media".  0 means use the full
+    /// directory.
+    #[clap(long, value_parser, default_value = "0")]
+    dir_depth: usize,
+
+    /// Also include the rows whose count didn't change.
+    #[clap(long, value_parser)]
+    all: bool,
+}
+
+#[derive(Debug)]
+pub struct TrendCommand {
+    pub args: Trend,
+}
+
+/// Load the stats from the file or from the index directory at the path.
+fn load_generation(path: &str) -> Result<SymbolKindStats> {
+    let file_path = if Path::new(path).is_dir() {
+        format!(
+            "{}/{}",
+            path.trim_end_matches('/'),
+            SYMBOL_KIND_STATS_FILENAME
+        )
+    } else {
+        path.to_string()
+    };
+    match SymbolKindStats::load(&file_path)? {
+        Some(stats) => Ok(stats),
+        None => Err(ServerError::StickyProblem(
+            ErrorDetails::new(
+                ErrorKind::NotFound,
+                ErrorLayer::BadInput,
+                format!("No symbol kind stats found at {}", file_path),
+            )
+            .with_hint("Pass an archived `symbol-kind-stats.json` or an index directory."),
+        )),
+    }
+}
+
+#[async_trait]
+impl PipelineCommand for TrendCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        if !matches!(input, PipelineValues::Void) {
+            return Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::BadQuery,
+                    ErrorLayer::ConfigLayer,
+                    "trend needs no input".to_string(),
+                )
+                .with_hint("Use `explain` to see what each pipeline stage consumes and produces."),
+            ));
+        }
+
+        let base = load_generation(&self.args.base)?;
+        let head = match &self.args.head {
+            Some(head) => load_generation(head)?,
+            None => {
+                let index_path = server
+                    .translate_path(SearchfoxIndexRoot::IndexRoot, SYMBOL_KIND_STATS_FILENAME)?;
+                let Some(stats) = SymbolKindStats::load(&index_path)? else {
+                    return Err(ServerError::StickyProblem(
+                        ErrorDetails::new(
+                            ErrorKind::IndexMissingCapability,
+                            ErrorLayer::DataLayer,
+                            "This index has no symbol kind stats".to_string(),
+                        )
+                        .with_hint("Re-index the tree to build `symbol-kind-stats.json`."),
+                    ));
+                };
+                stats
+            }
+        };
+
+        let prefix = self.args.path_prefix.as_deref().unwrap_or_default();
+        let base_totals = base.totals(self.args.dir_depth, prefix);
+        let head_totals = head.totals(self.args.dir_depth, prefix);
+        let keys: BTreeSet<&(String, String)> =
+            base_totals.keys().chain(head_totals.keys()).collect();
+
+        let mut rows = vec![];
+        for key in keys {
+            let (dir, kind) = key;
+            if !self.args.kind.is_empty() && !self.args.kind.contains(kind) {
+                continue;
+            }
+            let base_count = base_totals.get(key).copied().unwrap_or(0) as i64;
+            let head_count = head_totals.get(key).copied().unwrap_or(0) as i64;
+            if base_count == head_count && !self.args.all {
+                continue;
+            }
+            rows.push((dir, kind, base_count, head_count));
+        }
+        // The biggest changes first; the keys are already in order for ties.
+        rows.sort_by_key(|(_, _, base_count, head_count)| -(head_count - base_count).abs());
+
+        let mut table = DataTable::new(vec![
+            ("dir", DataColumnType::Path),
+            ("kind", DataColumnType::String),
+            ("base", DataColumnType::Int),
+            ("head", DataColumnType::Int),
+            ("delta", DataColumnType::Int),
+            ("percent", DataColumnType::Float),
+        ]);
+        for (dir, kind, base_count, head_count) in rows {
+            let delta = head_count - base_count;
+            table.push_row(vec![
+                DataCell::String(dir.clone()),
+                DataCell::String(kind.clone()),
+                DataCell::Int(base_count),
+                DataCell::Int(head_count),
+                DataCell::Int(delta),
+                if base_count == 0 {
+                    DataCell::Null
+                } else {
+                    DataCell::Float(delta as f64 * 100.0 / base_count as f64)
+                },
+            ])?;
+        }
+        Ok(PipelineValues::DataTable(table))
+    }
+}
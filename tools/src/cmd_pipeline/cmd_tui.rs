@@ -0,0 +1,541 @@
+use std::io::{stdout, Stdout};
+
+use async_trait::async_trait;
+use clap::{Args, Parser};
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout},
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState},
+    Terminal,
+};
+use serde_json::{to_value, Value};
+use ustr::ustr;
+
+use super::{
+    builder::{fab_command_from_opts, CommandSafetyLevel},
+    interface::{
+        PipelineCommand, PipelineValues, SymbolList, SymbolQuality, SymbolTreeTableClassSize,
+        SymbolTreeTableItem, SymbolTreeTableList, SymbolWithContext,
+    },
+    parser::ToolOpts,
+};
+use crate::abstract_server::{
+    AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError,
+};
+
+/// Interactively browse the pipeline's output in the terminal.  Tables from
+/// `format-symbols` are shown as expandable class/field rows; everything else
+/// is shown as an expandable JSON tree.
+///
+/// Keys:
+/// - up/down (or j/k) to move, right/left (or l/h) to expand/collapse.
+/// - `s` to sort by the next column, `S` to reverse the sort.
+/// - `|` to pipe the selected row's symbol into another pipeline, like
+///   `crossref-lookup | traverse | graph --format=json`.  The result is pushed
+///   as a new view and backspace returns to the previous view.
+/// - `q` to quit.
+#[derive(Debug, Args)]
+pub struct Tui {}
+
+#[derive(Debug)]
+pub struct TuiCommand {
+    pub args: Tui,
+}
+
+struct TuiNode {
+    cells: Vec<String>,
+    symbol: Option<String>,
+    children: Vec<TuiNode>,
+    expanded: bool,
+}
+
+impl TuiNode {
+    fn new(cells: Vec<String>, symbol: Option<String>) -> Self {
+        TuiNode {
+            cells,
+            symbol,
+            children: vec![],
+            expanded: false,
+        }
+    }
+}
+
+struct TuiView {
+    title: String,
+    headers: Vec<String>,
+    roots: Vec<TuiNode>,
+    state: TableState,
+    sort_col: Option<usize>,
+    sort_desc: bool,
+}
+
+impl TuiView {
+    fn new(title: String, headers: Vec<String>, roots: Vec<TuiNode>) -> Self {
+        let mut state = TableState::default();
+        state.select(Some(0));
+        TuiView {
+            title,
+            headers,
+            roots,
+            state,
+            sort_col: None,
+            sort_desc: false,
+        }
+    }
+
+    /// Return the index paths and depths of all currently visible rows.
+    fn visible(&self) -> Vec<(Vec<usize>, usize)> {
+        fn walk(nodes: &[TuiNode], path: &mut Vec<usize>, out: &mut Vec<(Vec<usize>, usize)>) {
+            for (i, node) in nodes.iter().enumerate() {
+                path.push(i);
+                out.push((path.clone(), path.len() - 1));
+                if node.expanded {
+                    walk(&node.children, path, out);
+                }
+                path.pop();
+            }
+        }
+        let mut out = vec![];
+        walk(&self.roots, &mut vec![], &mut out);
+        out
+    }
+
+    fn node_mut(&mut self, path: &[usize]) -> Option<&mut TuiNode> {
+        let (first, rest) = path.split_first()?;
+        let mut node = self.roots.get_mut(*first)?;
+        for i in rest {
+            node = node.children.get_mut(*i)?;
+        }
+        Some(node)
+    }
+
+    fn selected_path(&self) -> Option<Vec<usize>> {
+        let visible = self.visible();
+        self.state
+            .selected()
+            .and_then(|i| visible.get(i).map(|(p, _)| p.clone()))
+    }
+
+    fn move_selection(&mut self, delta: i64) {
+        let count = self.visible().len() as i64;
+        if count == 0 {
+            return;
+        }
+        let cur = self.state.selected().unwrap_or(0) as i64;
+        self.state
+            .select(Some((cur + delta).clamp(0, count - 1) as usize));
+    }
+
+    fn set_expanded(&mut self, expanded: bool) {
+        if let Some(path) = self.selected_path() {
+            if let Some(node) = self.node_mut(&path) {
+                node.expanded = expanded && !node.children.is_empty();
+            }
+        }
+    }
+
+    fn sort(&mut self) {
+        fn sort_nodes(nodes: &mut [TuiNode], col: usize, desc: bool) {
+            nodes.sort_by(|a, b| {
+                let a_cell = a.cells.get(col).map_or("", |s| s.as_str());
+                let b_cell = b.cells.get(col).map_or("", |s| s.as_str());
+                // Sort numerically when both cells are numbers so offsets and
+                // sizes sort sensibly.
+                let ord = match (a_cell.parse::<f64>(), b_cell.parse::<f64>()) {
+                    (Ok(a_num), Ok(b_num)) => a_num
+                        .partial_cmp(&b_num)
+                        .unwrap_or(std::cmp::Ordering::Equal),
+                    _ => a_cell.cmp(b_cell),
+                };
+                if desc {
+                    ord.reverse()
+                } else {
+                    ord
+                }
+            });
+            for node in nodes.iter_mut() {
+                sort_nodes(&mut node.children, col, desc);
+            }
+        }
+        if let Some(col) = self.sort_col {
+            sort_nodes(&mut self.roots, col, self.sort_desc);
+        }
+    }
+}
+
+fn json_summary(val: &Value) -> String {
+    match val {
+        Value::Object(obj) => format!("{{{} keys}}", obj.len()),
+        Value::Array(arr) => format!("[{} items]", arr.len()),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn json_symbol(val: &Value) -> Option<String> {
+    ["sym", "symbol"]
+        .iter()
+        .find_map(|key| val.get(key).and_then(|s| s.as_str()))
+        .map(|s| s.to_string())
+}
+
+fn json_node(label: String, val: &Value) -> TuiNode {
+    let mut node = TuiNode::new(vec![label, json_summary(val)], json_symbol(val));
+    match val {
+        Value::Object(obj) => {
+            for (k, v) in obj {
+                node.children.push(json_node(k.clone(), v));
+            }
+        }
+        Value::Array(arr) => {
+            for (i, v) in arr.iter().enumerate() {
+                node.children.push(json_node(i.to_string(), v));
+            }
+        }
+        _ => {}
+    }
+    node
+}
+
+fn offsets_cells(cells: &[Option<String>]) -> Vec<String> {
+    cells
+        .iter()
+        .map(|c| c.clone().unwrap_or_default())
+        .collect()
+}
+
+fn view_from_tables(title: String, sttl: &SymbolTreeTableList) -> TuiView {
+    let mut headers = vec!["Name".to_string(), "Type".to_string()];
+    let mut roots = vec![];
+    let mut platforms_seen = false;
+    for table in &sttl.tables {
+        if !platforms_seen {
+            headers.extend(table.platforms.iter().cloned());
+            headers.push("Line".to_string());
+            platforms_seen = true;
+        }
+        for row in &table.rows {
+            let mut class_cells = vec![row.labeled_name()];
+            if !row.sizes.is_empty() {
+                class_cells.push("".to_string());
+                class_cells.extend(row.sizes.iter().map(|size| match size {
+                    Some(SymbolTreeTableClassSize {
+                        size,
+                        delta: Some(delta),
+                    }) => format!("{} ({})", size, delta),
+                    Some(size) => size.size.clone(),
+                    None => "".to_string(),
+                }));
+            }
+            let mut class_node = TuiNode::new(class_cells, Some(row.symbols.clone()));
+            class_node.expanded = true;
+            for item in &row.items {
+                let (cells, symbol) = match item {
+                    SymbolTreeTableItem::Field(field) => {
+                        let mut cells = vec![
+                            field.name.clone(),
+                            field
+                                .types
+                                .iter()
+                                .map(|t| t.name.as_str())
+                                .collect::<Vec<_>>()
+                                .join(", "),
+                        ];
+                        cells.extend(field.offset_and_size.iter().map(|os| match os {
+                            Some(os) => match &os.align {
+                                Some(align) => {
+                                    format!("{} ({}, align {})", os.offset, os.size, align)
+                                }
+                                None => format!("{} ({})", os.offset, os.size),
+                            },
+                            None => "".to_string(),
+                        }));
+                        cells.push(field.lines.join(", "));
+                        (cells, Some(field.symbols.clone()))
+                    }
+                    SymbolTreeTableItem::Hole(holes) => {
+                        let mut cells = vec!["(hole)".to_string(), "".to_string()];
+                        cells.extend(offsets_cells(holes));
+                        (cells, None)
+                    }
+                    SymbolTreeTableItem::Cells(values) => {
+                        let mut cells = vec!["".to_string(), "".to_string()];
+                        cells.extend(offsets_cells(values));
+                        (cells, None)
+                    }
+                    SymbolTreeTableItem::EndPadding(padding) => {
+                        let mut cells = vec!["(end padding)".to_string(), "".to_string()];
+                        cells.extend(offsets_cells(padding));
+                        (cells, None)
+                    }
+                    SymbolTreeTableItem::Method(method) => (
+                        vec![
+                            format!("{}{}", method.name, method.signature),
+                            method.flags.join(" "),
+                        ],
+                        Some(method.symbols.clone()),
+                    ),
+                    SymbolTreeTableItem::Warning(warning) => (vec![warning.clone()], None),
+                };
+                class_node.children.push(TuiNode::new(cells, symbol));
+            }
+            roots.push(class_node);
+        }
+    }
+    TuiView::new(title, headers, roots)
+}
+
+fn view_from_values(title: String, values: &PipelineValues) -> TuiView {
+    if let PipelineValues::SymbolTreeTableList(sttl) = values {
+        return view_from_tables(title, sttl);
+    }
+
+    let json = to_value(values).unwrap_or(Value::Null);
+    // `PipelineValues` serializes as `{ "Variant": payload }`, so skip a level.
+    let (variant, payload) = match &json {
+        Value::Object(obj) if obj.len() == 1 => {
+            let (k, v) = obj.iter().next().unwrap();
+            (k.clone(), v.clone())
+        }
+        _ => ("Value".to_string(), json.clone()),
+    };
+    let mut root = json_node(variant, &payload);
+    root.expanded = true;
+    TuiView::new(
+        title,
+        vec!["Key".to_string(), "Value".to_string()],
+        vec![root],
+    )
+}
+
+fn tui_error(message: String) -> ServerError {
+    ServerError::StickyProblem(ErrorDetails::new(
+        ErrorKind::Internal,
+        ErrorLayer::RuntimeInvariantViolation,
+        message,
+    ))
+}
+
+/// Run the given pipeline string with the selected symbol (if any) as input.
+/// We only allow web-safe commands, which notably excludes nesting `tui`.
+async fn run_piped(
+    server: &(dyn AbstractServer + Send + Sync),
+    pipeline: &str,
+    symbol: Option<&str>,
+) -> Result<PipelineValues> {
+    let all_args = shell_words::split(pipeline).map_err(|e| tui_error(e.to_string()))?;
+    let mut value = match symbol {
+        Some(sym) => PipelineValues::SymbolList(SymbolList {
+            symbols: vec![SymbolWithContext {
+                symbol: ustr(sym),
+                quality: SymbolQuality::ExplicitSymbol,
+                from_identifier: None,
+            }],
+            page: None,
+        }),
+        None => PipelineValues::Void,
+    };
+    for arg_slices in all_args.split(|v| v == "|") {
+        let mut fake_args = vec!["searchfox-tool".to_string()];
+        fake_args.extend(arg_slices.iter().cloned());
+        let opts = ToolOpts::try_parse_from(fake_args).map_err(|e| tui_error(e.to_string()))?;
+        let cmd = fab_command_from_opts(opts, CommandSafetyLevel::WebSafety)?;
+        value = cmd.execute(server, value).await?;
+    }
+    Ok(value)
+}
+
+fn draw(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    view: &mut TuiView,
+    status: &str,
+) -> Result<()> {
+    let visible = view.visible();
+    let headers = view.headers.clone();
+    let title = view.title.clone();
+    let rows: Vec<Row> = visible
+        .iter()
+        .map(|(path, depth)| {
+            let node = {
+                let mut node = &view.roots[path[0]];
+                for i in &path[1..] {
+                    node = &node.children[*i];
+                }
+                node
+            };
+            let marker = if node.children.is_empty() {
+                "  "
+            } else if node.expanded {
+                "▾ "
+            } else {
+                "▸ "
+            };
+            let mut cells = vec![Cell::from(format!(
+                "{}{}{}",
+                "  ".repeat(*depth),
+                marker,
+                node.cells.first().map_or("", |s| s.as_str())
+            ))];
+            cells.extend(node.cells.iter().skip(1).map(|c| Cell::from(c.clone())));
+            Row::new(cells)
+        })
+        .collect();
+
+    let col_count = headers.len().max(1) as u32;
+    let widths: Vec<Constraint> = (0..col_count)
+        .map(|i| {
+            if i == 0 {
+                Constraint::Percentage(40)
+            } else {
+                Constraint::Ratio(1, col_count)
+            }
+        })
+        .collect();
+    let header_cells: Vec<Cell> = headers
+        .iter()
+        .enumerate()
+        .map(|(i, h)| {
+            let label = match (view.sort_col, view.sort_desc) {
+                (Some(col), false) if col == i => format!("{} ▲", h),
+                (Some(col), true) if col == i => format!("{} ▼", h),
+                _ => h.clone(),
+            };
+            Cell::from(label)
+        })
+        .collect();
+
+    let table = Table::new(rows, widths)
+        .header(Row::new(header_cells).style(Style::default().add_modifier(Modifier::BOLD)))
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+    terminal.draw(|f| {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(3), Constraint::Length(1)])
+            .split(f.size());
+        f.render_stateful_widget(table, chunks[0], &mut view.state);
+        f.render_widget(Paragraph::new(Line::from(status.to_string())), chunks[1]);
+    })?;
+    Ok(())
+}
+
+const HELP: &str = "q quit | ←→ collapse/expand | s/S sort | | pipe selection | ⌫ back";
+
+impl TuiCommand {
+    async fn event_loop(
+        &self,
+        terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+        server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<()> {
+        let mut views = vec![view_from_values("results".to_string(), &input)];
+        // When Some, we're prompting for a pipeline to pipe the selection into.
+        let mut prompt: Option<String> = None;
+        let mut status = HELP.to_string();
+
+        loop {
+            let status_line = match &prompt {
+                Some(p) => format!("pipe into: {}", p),
+                None => status.clone(),
+            };
+            draw(terminal, views.last_mut().unwrap(), &status_line)?;
+
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            if let Some(p) = prompt.as_mut() {
+                match key.code {
+                    KeyCode::Esc => prompt = None,
+                    KeyCode::Backspace => {
+                        p.pop();
+                    }
+                    KeyCode::Char(c) => p.push(c),
+                    KeyCode::Enter => {
+                        let pipeline = prompt.take().unwrap();
+                        let view = views.last().unwrap();
+                        let symbol = view.selected_path().and_then(|path| {
+                            let mut node = &view.roots[path[0]];
+                            for i in &path[1..] {
+                                node = &node.children[*i];
+                            }
+                            node.symbol.clone()
+                        });
+                        match run_piped(server, &pipeline, symbol.as_deref()).await {
+                            Ok(result) => {
+                                views.push(view_from_values(pipeline, &result));
+                                status = HELP.to_string();
+                            }
+                            Err(err) => status = format!("Error: {:?}", err),
+                        }
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+
+            let view = views.last_mut().unwrap();
+            match key.code {
+                KeyCode::Char('q') => return Ok(()),
+                KeyCode::Up | KeyCode::Char('k') => view.move_selection(-1),
+                KeyCode::Down | KeyCode::Char('j') => view.move_selection(1),
+                KeyCode::PageUp => view.move_selection(-20),
+                KeyCode::PageDown => view.move_selection(20),
+                KeyCode::Right | KeyCode::Char('l') | KeyCode::Enter => view.set_expanded(true),
+                KeyCode::Left | KeyCode::Char('h') => view.set_expanded(false),
+                KeyCode::Char('s') => {
+                    let col_count = view.headers.len().max(1);
+                    view.sort_col = Some(view.sort_col.map_or(0, |c| (c + 1) % col_count));
+                    view.sort();
+                }
+                KeyCode::Char('S') => {
+                    view.sort_desc = !view.sort_desc;
+                    if view.sort_col.is_none() {
+                        view.sort_col = Some(0);
+                    }
+                    view.sort();
+                }
+                KeyCode::Char('|') => prompt = Some(String::new()),
+                KeyCode::Backspace => {
+                    if views.len() > 1 {
+                        views.pop();
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl PipelineCommand for TuiCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        enable_raw_mode()?;
+        execute!(stdout(), EnterAlternateScreen)?;
+        let mut terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
+
+        let result = self.event_loop(&mut terminal, server, input).await;
+
+        // Always try and restore the terminal, even if we errored.
+        disable_raw_mode()?;
+        execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+        terminal.show_cursor()?;
+
+        result.map(|_| PipelineValues::Void)
+    }
+}
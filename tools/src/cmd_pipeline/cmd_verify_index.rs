@@ -0,0 +1,268 @@
+use std::collections::{BTreeMap, HashMap};
+
+use async_trait::async_trait;
+use clap::{Args, ValueEnum};
+use serde_json::{json, Value};
+use ustr::Ustr;
+
+use super::interface::{JsonValue, PipelineCommand, PipelineValues};
+use crate::{
+    abstract_server::{
+        AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, HtmlFileRoot, Result, ServerError,
+    },
+    file_format::analysis::{parse_structured_value, PathSearchResult},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum IndexCheck {
+    /// Every crossref def points at an existing file and line.
+    Defs,
+    /// Every structured super and field symbol has a crossref entry.
+    Structured,
+    /// Every indexed file has a formatted HTML page.
+    Html,
+}
+
+/// Cross-check the crossref database against the file list and the formatted
+/// HTML output to catch indexer bugs before an index is deployed.  Reports the
+/// number of inconsistencies of each kind along with some samples as a
+/// JsonValue.  This scans the entire index and so is only supported for local
+/// indices.
+#[derive(Debug, Args)]
+pub struct VerifyIndex {
+    /// The checks to run; may be specified multiple times.  Defaults to all.
+    #[clap(long, value_parser, value_enum)]
+    check: Vec<IndexCheck>,
+
+    /// Only check the first this many crossref symbols.  0 means no limit.
+    #[clap(long, value_parser, default_value = "0")]
+    max_symbols: usize,
+
+    /// The number of sample problems to report per kind of problem.
+    #[clap(long, value_parser, default_value = "10")]
+    max_samples: usize,
+
+    /// Produce an error instead of the report if any problems were found, so
+    /// that scripts can stop before deploying a bad index.
+    #[clap(long, value_parser)]
+    fail_on_problems: bool,
+}
+
+#[derive(Debug)]
+pub struct VerifyIndexCommand {
+    pub args: VerifyIndex,
+}
+
+#[derive(Default)]
+struct ProblemTally {
+    count: u64,
+    samples: Vec<String>,
+}
+
+struct Problems {
+    max_samples: usize,
+    by_kind: BTreeMap<&'static str, ProblemTally>,
+}
+
+impl Problems {
+    fn report(&mut self, kind: &'static str, sample: String) {
+        let tally = self.by_kind.entry(kind).or_default();
+        tally.count += 1;
+        if tally.samples.len() < self.max_samples {
+            tally.samples.push(sample);
+        }
+    }
+
+    fn total(&self) -> u64 {
+        self.by_kind.values().map(|tally| tally.count).sum()
+    }
+
+    fn to_json(&self) -> Value {
+        Value::Object(
+            self.by_kind
+                .iter()
+                .map(|(kind, tally)| {
+                    (
+                        kind.to_string(),
+                        json!({
+                            "count": tally.count,
+                            "samples": tally.samples,
+                        }),
+                    )
+                })
+                .collect(),
+        )
+    }
+}
+
+const FILE_MATCH_LIMIT: usize = 2_000_000;
+
+impl VerifyIndexCommand {
+    fn wants(&self, check: IndexCheck) -> bool {
+        self.args.check.is_empty() || self.args.check.contains(&check)
+    }
+
+    /// Check that each def of the symbol is in an indexed file and within the
+    /// bounds of that file, caching line counts in `line_counts`.
+    async fn check_defs(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        sym: &str,
+        defs: &Value,
+        line_counts: &mut HashMap<Ustr, Option<usize>>,
+        problems: &mut Problems,
+    ) -> Result<()> {
+        let Ok(path_results) = serde_json::from_value::<Vec<PathSearchResult>>(defs.clone()) else {
+            problems.report("def-malformed", sym.to_string());
+            return Ok(());
+        };
+        for path_result in path_results {
+            let line_count = match line_counts.get(&path_result.path) {
+                Some(Some(count)) => *count,
+                Some(None) => {
+                    let count = match server.fetch_raw_source(&path_result.path).await {
+                        Ok(source) => source.lines().count(),
+                        // Generated files may not be available; don't complain.
+                        Err(_) => usize::MAX,
+                    };
+                    line_counts.insert(path_result.path, Some(count));
+                    count
+                }
+                None => {
+                    problems.report(
+                        "def-file-missing",
+                        format!("{} -> {}", sym, path_result.path),
+                    );
+                    continue;
+                }
+            };
+            for line in path_result.lines {
+                if line.lineno == 0 || line.lineno as usize > line_count {
+                    problems.report(
+                        "def-line-out-of-range",
+                        format!("{} -> {}#{}", sym, path_result.path, line.lineno),
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that the supers and fields of the symbol's structured info have
+    /// their own crossref entries.
+    async fn check_structured(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        sym: &str,
+        meta: &Value,
+        problems: &mut Problems,
+    ) -> Result<()> {
+        let Ok(structured) = parse_structured_value(meta.clone()) else {
+            problems.report("structured-malformed", sym.to_string());
+            return Ok(());
+        };
+        for super_info in &structured.supers {
+            if server
+                .crossref_lookup_keys(&super_info.sym, &["meta"])
+                .await?
+                .is_null()
+            {
+                problems.report("super-unresolved", format!("{} -> {}", sym, super_info.sym));
+            }
+        }
+        for field in &structured.fields {
+            if server
+                .crossref_lookup_keys(&field.sym, &["meta"])
+                .await?
+                .is_null()
+            {
+                problems.report("field-unresolved", format!("{} -> {}", sym, field.sym));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl PipelineCommand for VerifyIndexCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        _input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let mut problems = Problems {
+            max_samples: self.args.max_samples,
+            by_kind: BTreeMap::new(),
+        };
+
+        let files: Vec<Ustr> = server
+            .search_files("", false, FILE_MATCH_LIMIT)
+            .await?
+            .file_matches
+            .into_iter()
+            .map(|fm| fm.path)
+            .collect();
+
+        if self.wants(IndexCheck::Html) {
+            for path in &files {
+                if server
+                    .fetch_html(HtmlFileRoot::FormattedFile, path)
+                    .await
+                    .is_err()
+                {
+                    problems.report("html-missing", path.to_string());
+                }
+            }
+        }
+
+        let mut symbols_checked = 0;
+        if self.wants(IndexCheck::Defs) || self.wants(IndexCheck::Structured) {
+            let limit = match self.args.max_symbols {
+                0 => usize::MAX,
+                n => n,
+            };
+            // Line counts are computed lazily since only files with defs need
+            // them.
+            let mut line_counts: HashMap<Ustr, Option<usize>> =
+                files.iter().map(|path| (*path, None)).collect();
+            for sym in server.list_crossref_symbols(limit).await? {
+                symbols_checked += 1;
+                let info = server.crossref_lookup_keys(&sym, &["meta", "defs"]).await?;
+                if self.wants(IndexCheck::Defs) {
+                    if let Some(defs) = info.get("defs") {
+                        self.check_defs(server, &sym, defs, &mut line_counts, &mut problems)
+                            .await?;
+                    }
+                }
+                if self.wants(IndexCheck::Structured) {
+                    if let Some(meta) = info.get("meta") {
+                        self.check_structured(server, &sym, meta, &mut problems)
+                            .await?;
+                    }
+                }
+            }
+        }
+
+        let total = problems.total();
+        if self.args.fail_on_problems && total > 0 {
+            return Err(ServerError::StickyProblem(ErrorDetails::new(
+                ErrorKind::Internal,
+                ErrorLayer::DataLayer,
+                format!(
+                    "verify-index found {} problems: {}",
+                    total,
+                    problems.to_json()
+                ),
+            )));
+        }
+
+        Ok(PipelineValues::JsonValue(JsonValue {
+            value: json!({
+                "files_checked": files.len(),
+                "symbols_checked": symbols_checked,
+                "problem_count": total,
+                "problems": problems.to_json(),
+            }),
+        }))
+    }
+}
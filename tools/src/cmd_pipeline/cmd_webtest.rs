@@ -11,7 +11,9 @@ use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
 
 use super::interface::{PipelineCommand, PipelineValues};
 
-use crate::abstract_server::{AbstractServer, ErrorDetails, ErrorLayer, Result, ServerError};
+use crate::abstract_server::{
+    AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError,
+};
 
 /// Runs the specified
 #[derive(Debug, Args)]
@@ -237,17 +239,19 @@ impl PipelineCommand for WebtestCommand {
         _input: PipelineValues,
     ) -> Result<PipelineValues> {
         let passed = self.setup_webdriver_and_run_tests().await.map_err(|e| {
-            ServerError::TransientProblem(ErrorDetails {
-                layer: ErrorLayer::ConfigLayer,
-                message: e,
-            })
+            ServerError::TransientProblem(ErrorDetails::new(
+                ErrorKind::Internal,
+                ErrorLayer::ConfigLayer,
+                e,
+            ))
         })?;
 
         if !passed {
-            return Err(ServerError::TransientProblem(ErrorDetails {
-                layer: ErrorLayer::ConfigLayer,
-                message: "Test failed".to_string(),
-            }));
+            return Err(ServerError::TransientProblem(ErrorDetails::new(
+                ErrorKind::Internal,
+                ErrorLayer::ConfigLayer,
+                "Test failed".to_string(),
+            )));
         }
 
         Ok(PipelineValues::Void)
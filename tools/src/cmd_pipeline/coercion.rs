@@ -0,0 +1,144 @@
+use std::collections::BTreeSet;
+
+use tokio_stream::StreamExt;
+use ustr::ustr;
+
+use super::{
+    data_table::DataTable,
+    interface::{
+        PipelineValues, SymbolCrossrefInfo, SymbolCrossrefInfoList, SymbolMetaFlags, SymbolQuality,
+        SymbolRelation,
+    },
+    signatures::{command_signature, ANY},
+};
+use crate::abstract_server::{AbstractServer, FileMatches, Result};
+
+/// The standard conversions between `PipelineValues` variants, as (from, to)
+/// pairs of variant names.  When a command is piped a variant it doesn't
+/// accept but that converts to one it does, the pipeline converts it rather
+/// than having the command fail with a "needs a ..." error.
+pub const COERCIONS: &[(&str, &str)] = &[
+    // The symbols defined in the files.
+    ("FileMatches", "SymbolCrossrefInfoList"),
+    // A row per class and per item, see
+    // `DataTable::from_symbol_tree_table_list`.
+    ("SymbolTreeTableList", "DataTable"),
+];
+
+/// The variant to convert the `from` variant to for a command accepting
+/// `inputs`, if the command doesn't accept it as-is but a standard conversion
+/// to an accepted variant exists.
+pub fn coercion_for(from: &str, inputs: &[&str]) -> Option<&'static str> {
+    if inputs.is_empty() || inputs.contains(&ANY) || inputs.contains(&from) {
+        return None;
+    }
+    COERCIONS
+        .iter()
+        .find(|(f, to)| *f == from && inputs.contains(to))
+        .map(|(_, to)| *to)
+}
+
+/// Convert the input for the named command if its signature calls for it,
+/// otherwise return it unchanged.  Commands without a signature are left to
+/// cope with their input themselves.
+pub async fn coerce_input(
+    server: &(dyn AbstractServer + Send + Sync),
+    command: &str,
+    input: PipelineValues,
+) -> Result<PipelineValues> {
+    let Some(sig) = command_signature(command) else {
+        return Ok(input);
+    };
+    if coercion_for(input.variant_name(), sig.inputs).is_none() {
+        return Ok(input);
+    }
+    Ok(match input {
+        PipelineValues::FileMatches(fm) => PipelineValues::SymbolCrossrefInfoList(
+            file_matches_to_crossref_infos(server, fm).await?,
+        ),
+        PipelineValues::SymbolTreeTableList(sttl) => {
+            PipelineValues::DataTable(DataTable::from_symbol_tree_table_list(&sttl)?)
+        }
+        input => input,
+    })
+}
+
+/// Look up the crossref info of the symbols defined in the files, in order of
+/// the files and then of the definitions.
+async fn file_matches_to_crossref_infos(
+    server: &(dyn AbstractServer + Send + Sync),
+    fm: FileMatches,
+) -> Result<SymbolCrossrefInfoList> {
+    let mut seen = BTreeSet::new();
+    let mut symbol_crossref_infos = vec![];
+    let mut unknown_symbols = vec![];
+    for file_match in fm.file_matches {
+        if file_match.concise.is_dir {
+            continue;
+        }
+        // Not every file has analysis data, which is fine.
+        let Ok(mut records) = server.fetch_raw_analysis(&file_match.path).await else {
+            continue;
+        };
+        let mut syms = vec![];
+        while let Some(val) = records.next().await {
+            if !val["target"].is_number() || val["kind"].as_str() != Some("def") {
+                continue;
+            }
+            if let Some(sym) = val["sym"].as_str() {
+                // Definitions can be for multiple symbols, ex: across
+                // platforms.
+                syms.extend(sym.split(',').map(|s| s.to_string()));
+            }
+        }
+
+        for sym in syms {
+            if !seen.insert(sym.clone()) {
+                continue;
+            }
+            let info = server.crossref_lookup(&sym, false).await?;
+            if info.is_null() {
+                unknown_symbols.push(sym);
+                continue;
+            }
+            symbol_crossref_infos.push(SymbolCrossrefInfo {
+                symbol: ustr(&sym),
+                crossref_info: info,
+                relation: SymbolRelation::Queried,
+                quality: SymbolQuality::ExplicitSymbol,
+                overloads_hit: vec![],
+                flags: SymbolMetaFlags::default(),
+                score: None,
+            });
+        }
+    }
+
+    Ok(SymbolCrossrefInfoList {
+        symbol_crossref_infos,
+        unknown_symbols,
+        page: None,
+    })
+}
+
+#[test]
+fn test_coercion_for() {
+    let format_symbols = &["SymbolCrossrefInfoList"];
+    assert_eq!(
+        coercion_for("FileMatches", format_symbols),
+        Some("SymbolCrossrefInfoList")
+    );
+    assert_eq!(coercion_for("SymbolCrossrefInfoList", format_symbols), None);
+    assert_eq!(coercion_for("TextMatches", format_symbols), None);
+    // Commands that accept the variant as-is or ignore their input are left
+    // alone.
+    assert_eq!(
+        coercion_for("FileMatches", &["Void", "FileMatches", "DataTable"]),
+        None
+    );
+    assert_eq!(coercion_for("FileMatches", &[]), None);
+    assert_eq!(coercion_for("SymbolTreeTableList", &[ANY]), None);
+    assert_eq!(
+        coercion_for("SymbolTreeTableList", &["DataTable"]),
+        Some("DataTable")
+    );
+}
@@ -0,0 +1,508 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+use ustr::Ustr;
+
+use super::interface::{
+    SymbolTreeTable, SymbolTreeTableItem, SymbolTreeTableList, SymbolTreeTableNode,
+};
+use crate::abstract_server::{ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError};
+
+/// The type of the values in a `DataTable` column.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DataColumnType {
+    Int,
+    Float,
+    String,
+    /// A searchfox symbol, which presentation layers can link.
+    SymbolRef,
+    /// A source path or directory (optionally with a `#line` suffix), stored
+    /// as a string.  Rows whose paths aren't allowed by the access policy are
+    /// filtered out, so any column holding paths must use this type.
+    Path,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct DataColumn {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub column_type: DataColumnType,
+}
+
+/// A single value in a `DataTable`.  Any column may contain `Null` to indicate
+/// a missing value.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum DataCell {
+    Null,
+    Int(i64),
+    Float(f64),
+    String(String),
+    Symbol(Ustr),
+}
+
+impl DataCell {
+    fn matches(&self, column_type: DataColumnType) -> bool {
+        matches!(
+            (self, column_type),
+            (DataCell::Null, _)
+                | (DataCell::Int(_), DataColumnType::Int)
+                | (DataCell::Float(_), DataColumnType::Float)
+                | (DataCell::String(_), DataColumnType::String)
+                | (DataCell::String(_), DataColumnType::Path)
+                | (DataCell::Symbol(_), DataColumnType::SymbolRef)
+        )
+    }
+
+    /// The value as displayed in text formats; `Null` is the empty string.
+    pub fn to_text(&self) -> String {
+        match self {
+            DataCell::Null => "".to_string(),
+            DataCell::Int(i) => i.to_string(),
+            DataCell::Float(f) => f.to_string(),
+            DataCell::String(s) => s.clone(),
+            DataCell::Symbol(sym) => sym.to_string(),
+        }
+    }
+}
+
+/// Tabular data with typed columns, for analytic commands (stats, audits,
+/// matrices) that want to produce data rather than presentation.  Use
+/// `format-table` to turn this into a `SymbolTreeTable`, CSV, or JSON.
+#[derive(Clone, Debug, Serialize)]
+pub struct DataTable {
+    pub columns: Vec<DataColumn>,
+    pub rows: Vec<Vec<DataCell>>,
+}
+
+impl DataTable {
+    pub fn new(columns: Vec<(&str, DataColumnType)>) -> Self {
+        Self {
+            columns: columns
+                .into_iter()
+                .map(|(name, column_type)| DataColumn {
+                    name: name.to_string(),
+                    column_type,
+                })
+                .collect(),
+            rows: vec![],
+        }
+    }
+
+    /// Append a row, checking that it has a cell of the right type for each
+    /// column.
+    pub fn push_row(&mut self, row: Vec<DataCell>) -> Result<()> {
+        let problem = if row.len() != self.columns.len() {
+            Some(format!(
+                "DataTable row has {} cells but there are {} columns",
+                row.len(),
+                self.columns.len()
+            ))
+        } else {
+            row.iter()
+                .zip(self.columns.iter())
+                .find(|(cell, column)| !cell.matches(column.column_type))
+                .map(|(cell, column)| {
+                    format!(
+                        "DataTable cell {:?} doesn't match {:?} column {}",
+                        cell, column.column_type, column.name
+                    )
+                })
+        };
+        if let Some(message) = problem {
+            return Err(ServerError::StickyProblem(ErrorDetails::new(
+                ErrorKind::Internal,
+                ErrorLayer::RuntimeInvariantViolation,
+                message,
+            )));
+        }
+        self.rows.push(row);
+        Ok(())
+    }
+
+    /// Append a column with a cell for each existing row, checking types the
+    /// same way `push_row` does.
+    pub fn push_column(
+        &mut self,
+        name: &str,
+        column_type: DataColumnType,
+        cells: Vec<DataCell>,
+    ) -> Result<()> {
+        let problem = if cells.len() != self.rows.len() {
+            Some(format!(
+                "DataTable column {} has {} cells but there are {} rows",
+                name,
+                cells.len(),
+                self.rows.len()
+            ))
+        } else {
+            cells
+                .iter()
+                .find(|cell| !cell.matches(column_type))
+                .map(|cell| {
+                    format!(
+                        "DataTable cell {:?} doesn't match {:?} column {}",
+                        cell, column_type, name
+                    )
+                })
+        };
+        if let Some(message) = problem {
+            return Err(ServerError::StickyProblem(ErrorDetails::new(
+                ErrorKind::Internal,
+                ErrorLayer::RuntimeInvariantViolation,
+                message,
+            )));
+        }
+        self.columns.push(DataColumn {
+            name: name.to_string(),
+            column_type,
+        });
+        for (row, cell) in self.rows.iter_mut().zip(cells) {
+            row.push(cell);
+        }
+        Ok(())
+    }
+
+    /// Produce CSV per RFC 4180 with a header row of column names.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::new();
+        let mut push_line = |fields: Vec<String>| {
+            let escaped: Vec<String> = fields.iter().map(|f| csv_escape(f)).collect();
+            csv.push_str(&escaped.join(","));
+            csv.push_str("\r\n");
+        };
+        push_line(self.columns.iter().map(|c| c.name.clone()).collect());
+        for row in &self.rows {
+            push_line(row.iter().map(|cell| cell.to_text()).collect());
+        }
+        csv
+    }
+
+    /// Produce a JSON array with one object per row, keyed by column name.
+    pub fn to_json_records(&self) -> Value {
+        Value::Array(
+            self.rows
+                .iter()
+                .map(|row| {
+                    let mut obj = Map::new();
+                    for (column, cell) in self.columns.iter().zip(row.iter()) {
+                        obj.insert(
+                            column.name.clone(),
+                            serde_json::to_value(cell).unwrap_or(Value::Null),
+                        );
+                    }
+                    Value::Object(obj)
+                })
+                .collect(),
+        )
+    }
+
+    /// Produce a `SymbolTreeTable` for display, where the first column labels
+    /// each row and the remaining columns are displayed as cells.  The first
+    /// symbol-ref cell in each row becomes the row's symbol.
+    pub fn to_symbol_tree_table_list(&self) -> SymbolTreeTableList {
+        let mut stt = SymbolTreeTable::new();
+        stt.platforms = self
+            .columns
+            .iter()
+            .skip(1)
+            .map(|c| c.name.clone())
+            .collect();
+
+        for row in &self.rows {
+            let label = row.first().map(|cell| cell.to_text()).unwrap_or_default();
+            let symbols = row
+                .iter()
+                .find_map(|cell| match cell {
+                    DataCell::Symbol(sym) => Some(sym.to_string()),
+                    _ => None,
+                })
+                .unwrap_or_default();
+            let mut node = SymbolTreeTableNode::new(label, symbols);
+            node.items.push(SymbolTreeTableItem::Cells(
+                row.iter()
+                    .skip(1)
+                    .map(|cell| match cell {
+                        DataCell::Null => None,
+                        cell => Some(cell.to_text()),
+                    })
+                    .collect(),
+            ));
+            stt.rows.push(node);
+        }
+
+        SymbolTreeTableList {
+            tables: vec![stt],
+            class_name: None,
+        }
+    }
+    /// Flatten a `SymbolTreeTableList` into a table with a row per class and
+    /// a row per item beneath it, plus a column per platform group (the union
+    /// of the tables' groups) holding the sizes, offsets, or cell values.
+    /// This is the inverse of `to_symbol_tree_table_list` in spirit, letting
+    /// DataTable consumers like `format-table --format=csv` process the output
+    /// of `format-symbols`.
+    pub fn from_symbol_tree_table_list(sttl: &SymbolTreeTableList) -> Result<DataTable> {
+        let mut platforms: Vec<&str> = vec![];
+        for stt in &sttl.tables {
+            for platform in &stt.platforms {
+                if !platforms.contains(&platform.as_str()) {
+                    platforms.push(platform);
+                }
+            }
+        }
+        let mut columns = vec![
+            ("class", DataColumnType::String),
+            ("member", DataColumnType::String),
+            ("type", DataColumnType::String),
+            ("symbol", DataColumnType::SymbolRef),
+        ];
+        columns.extend(platforms.iter().map(|p| (*p, DataColumnType::String)));
+        let mut table = DataTable::new(columns);
+
+        let text = |s: String| {
+            if s.is_empty() {
+                DataCell::Null
+            } else {
+                DataCell::String(s)
+            }
+        };
+        // Rows can be for several symbols (ex: across platforms); use the
+        // first.
+        let symbol = |symbols: &str| match symbols.split(',').next() {
+            Some(sym) if !sym.is_empty() => DataCell::Symbol(ustr::ustr(sym)),
+            _ => DataCell::Null,
+        };
+        for stt in &sttl.tables {
+            let platform_cells = |values: Vec<Option<String>>| {
+                platforms
+                    .iter()
+                    .map(|platform| {
+                        stt.platforms
+                            .iter()
+                            .position(|p| p == platform)
+                            .and_then(|i| values.get(i).cloned().flatten())
+                            .map_or(DataCell::Null, text)
+                    })
+                    .collect::<Vec<_>>()
+            };
+            for node in &stt.rows {
+                let mut row = vec![
+                    DataCell::String(node.labeled_name()),
+                    DataCell::Null,
+                    DataCell::Null,
+                    symbol(&node.symbols),
+                ];
+                row.extend(platform_cells(
+                    node.sizes
+                        .iter()
+                        .map(|size| {
+                            size.as_ref().map(|size| match &size.delta {
+                                Some(delta) => format!("{} ({})", size.size, delta),
+                                None => size.size.clone(),
+                            })
+                        })
+                        .collect(),
+                ));
+                table.push_row(row)?;
+
+                for item in &node.items {
+                    let (member, ty, symbols, values) = match item {
+                        SymbolTreeTableItem::Field(field) => (
+                            field.name.clone(),
+                            field
+                                .types
+                                .iter()
+                                .map(|t| t.name.as_str())
+                                .collect::<Vec<_>>()
+                                .join(", "),
+                            field.symbols.as_str(),
+                            field
+                                .offset_and_size
+                                .iter()
+                                .map(|os| {
+                                    os.as_ref().map(|os| format!("{} / {}", os.offset, os.size))
+                                })
+                                .collect(),
+                        ),
+                        SymbolTreeTableItem::Hole(values) => {
+                            ("(hole)".to_string(), "".to_string(), "", values.clone())
+                        }
+                        SymbolTreeTableItem::EndPadding(values) => (
+                            "(end padding)".to_string(),
+                            "".to_string(),
+                            "",
+                            values.clone(),
+                        ),
+                        SymbolTreeTableItem::Method(method) => (
+                            format!("{}{}", method.name, method.signature),
+                            method.flags.join(" "),
+                            method.symbols.as_str(),
+                            vec![],
+                        ),
+                        SymbolTreeTableItem::Cells(values) => {
+                            ("".to_string(), "".to_string(), "", values.clone())
+                        }
+                        SymbolTreeTableItem::Warning(warning) => {
+                            ("(warning)".to_string(), warning.clone(), "", vec![])
+                        }
+                    };
+                    let mut row = vec![
+                        DataCell::String(node.labeled_name()),
+                        text(member),
+                        text(ty),
+                        symbol(symbols),
+                    ];
+                    row.extend(platform_cells(values));
+                    table.push_row(row)?;
+                }
+            }
+        }
+        Ok(table)
+    }
+}
+
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+fn example_table() -> DataTable {
+    let mut table = DataTable::new(vec![
+        ("class", DataColumnType::SymbolRef),
+        ("fields", DataColumnType::Int),
+        ("fill", DataColumnType::Float),
+        ("note", DataColumnType::String),
+    ]);
+    table
+        .push_row(vec![
+            DataCell::Symbol(ustr::ustr("T_Foo")),
+            DataCell::Int(3),
+            DataCell::Float(0.75),
+            DataCell::String("has \"holes\", sadly".to_string()),
+        ])
+        .unwrap();
+    table
+        .push_row(vec![
+            DataCell::Symbol(ustr::ustr("T_Bar")),
+            DataCell::Int(0),
+            DataCell::Null,
+            DataCell::String("".to_string()),
+        ])
+        .unwrap();
+    table
+}
+
+#[test]
+fn test_push_row_checks_types() {
+    let mut table = example_table();
+    assert!(table.push_row(vec![DataCell::Int(1)]).is_err());
+    assert!(table
+        .push_row(vec![
+            DataCell::Int(1),
+            DataCell::Int(1),
+            DataCell::Null,
+            DataCell::Null
+        ])
+        .is_err());
+    assert_eq!(table.rows.len(), 2);
+}
+
+#[test]
+fn test_push_column() {
+    let mut table = example_table();
+    assert!(table
+        .push_column("bad", DataColumnType::Int, vec![DataCell::Int(1)])
+        .is_err());
+    assert!(table
+        .push_column(
+            "bad",
+            DataColumnType::Int,
+            vec![DataCell::Int(1), DataCell::Float(1.0)]
+        )
+        .is_err());
+    table
+        .push_column(
+            "big",
+            DataColumnType::Int,
+            vec![DataCell::Int(1), DataCell::Null],
+        )
+        .unwrap();
+    assert_eq!(table.columns.len(), 5);
+    assert_eq!(table.rows[0][4], DataCell::Int(1));
+    assert_eq!(table.rows[1][4], DataCell::Null);
+}
+
+#[test]
+fn test_to_csv() {
+    assert_eq!(
+        example_table().to_csv(),
+        "class,fields,fill,note\r\n\
+         T_Foo,3,0.75,\"has \"\"holes\"\", sadly\"\r\n\
+         T_Bar,0,,\r\n"
+    );
+}
+
+#[test]
+fn test_to_json_records() {
+    assert_eq!(
+        example_table().to_json_records(),
+        serde_json::json!([
+            { "class": "T_Foo", "fields": 3, "fill": 0.75, "note": "has \"holes\", sadly" },
+            { "class": "T_Bar", "fields": 0, "fill": null, "note": "" },
+        ])
+    );
+}
+
+#[test]
+fn test_to_symbol_tree_table_list() {
+    let sttl = example_table().to_symbol_tree_table_list();
+    let stt = &sttl.tables[0];
+    assert_eq!(stt.platforms, vec!["fields", "fill", "note"]);
+    assert_eq!(stt.rows[1].name, "T_Bar");
+    assert_eq!(stt.rows[1].symbols, "T_Bar");
+    match &stt.rows[1].items[0] {
+        SymbolTreeTableItem::Cells(cells) => {
+            assert_eq!(
+                cells,
+                &vec![Some("0".to_string()), None, Some("".to_string())]
+            );
+        }
+        _ => panic!("expected cells"),
+    }
+}
+
+#[test]
+fn test_from_symbol_tree_table_list() {
+    let table =
+        DataTable::from_symbol_tree_table_list(&example_table().to_symbol_tree_table_list())
+            .unwrap();
+    assert_eq!(
+        table
+            .columns
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect::<Vec<_>>(),
+        vec!["class", "member", "type", "symbol", "fields", "fill", "note"]
+    );
+    // A class row and a cells row for each of the original rows.
+    assert_eq!(table.rows.len(), 4);
+    assert_eq!(table.rows[0][3], DataCell::Symbol(ustr::ustr("T_Foo")));
+    assert_eq!(table.rows[0][4], DataCell::Null);
+    assert_eq!(
+        table.rows[3],
+        vec![
+            DataCell::String("T_Bar".to_string()),
+            DataCell::Null,
+            DataCell::Null,
+            DataCell::Null,
+            DataCell::String("0".to_string()),
+            DataCell::Null,
+            DataCell::Null,
+        ]
+    );
+}
@@ -0,0 +1,124 @@
+use clap::{Arg, ArgAction, Command, CommandFactory};
+use serde_json::{json, Map, Value};
+
+use super::{
+    parser::JunctionOpts,
+    plugins::tool_command_with_plugins,
+    signatures::{command_signature, junction_signature, CommandSignature},
+};
+
+/// Machine-readable description of clap definitions so that the web UI's query
+/// builder and external tooling don't have to scrape `--help` output.
+pub trait ToHelpJson {
+    fn help_json(&self) -> Value;
+}
+
+impl ToHelpJson for Arg {
+    fn help_json(&self) -> Value {
+        let possible_values: Vec<Value> = self
+            .get_possible_values()
+            .iter()
+            .filter(|pv| !pv.is_hide_set())
+            .map(|pv| {
+                json!({
+                    "name": pv.get_name(),
+                    "help": pv.get_help().map(|h| h.to_string()),
+                })
+            })
+            .collect();
+        let default_values: Vec<String> = self
+            .get_default_values()
+            .iter()
+            .map(|v| v.to_string_lossy().to_string())
+            .collect();
+
+        json!({
+            "name": self.get_id().as_str(),
+            "long": self.get_long(),
+            "short": self.get_short(),
+            "help": self.get_help().map(|h| h.to_string()),
+            "positional": self.is_positional(),
+            "required": self.is_required_set(),
+            "flag": matches!(self.get_action(), ArgAction::SetTrue | ArgAction::SetFalse),
+            "multiple": matches!(self.get_action(), ArgAction::Append),
+            "default_values": default_values,
+            "possible_values": possible_values,
+        })
+    }
+}
+
+impl ToHelpJson for Command {
+    fn help_json(&self) -> Value {
+        let args: Vec<Value> = self
+            .get_arguments()
+            .filter(|arg| !arg.is_hide_set())
+            .map(|arg| arg.help_json())
+            .collect();
+        let subcommands: Vec<Value> = self.get_subcommands().map(|c| c.help_json()).collect();
+
+        json!({
+            "name": self.get_name(),
+            "about": self.get_about().map(|a| a.to_string()),
+            "args": args,
+            "subcommands": subcommands,
+        })
+    }
+}
+
+fn with_signature(mut value: Value, sig: Option<CommandSignature>) -> Value {
+    if let (Value::Object(obj), Some(sig)) = (&mut value, sig) {
+        obj.insert("inputs".to_string(), json!(sig.inputs));
+        obj.insert("outputs".to_string(), json!(sig.outputs));
+    }
+    value
+}
+
+/// Describe all pipeline commands and junctions, or just the named one, along
+/// with the `PipelineValues` variants they consume and produce.
+pub fn pipeline_help_json(only: Option<&str>) -> Option<Value> {
+    let wanted = |c: &&Command| only.map_or(true, |name| c.get_name() == name);
+
+    let tool = tool_command_with_plugins();
+    let commands: Vec<Value> = tool
+        .get_subcommands()
+        .filter(wanted)
+        .map(|c| with_signature(c.help_json(), command_signature(c.get_name())))
+        .collect();
+    let junction_opts = JunctionOpts::command();
+    let junctions: Vec<Value> = junction_opts
+        .get_subcommands()
+        .filter(wanted)
+        .map(|c| with_signature(c.help_json(), junction_signature(c.get_name())))
+        .collect();
+
+    if commands.is_empty() && junctions.is_empty() {
+        return None;
+    }
+
+    let mut result = Map::new();
+    result.insert("commands".to_string(), Value::Array(commands));
+    result.insert("junctions".to_string(), Value::Array(junctions));
+    Some(Value::Object(result))
+}
+
+#[test]
+fn test_pipeline_help_json() {
+    let value = pipeline_help_json(Some("format-symbols")).unwrap();
+    let cmd = &value["commands"][0];
+    assert_eq!(cmd["name"], "format-symbols");
+    assert_eq!(cmd["inputs"], json!(["SymbolCrossrefInfoList"]));
+
+    let mode = cmd["args"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|arg| arg["name"] == "mode")
+        .unwrap();
+    assert_eq!(mode["default_values"], json!(["field-layout"]));
+    assert_eq!(mode["possible_values"][0]["name"], "field-layout");
+
+    let all = pipeline_help_json(None).unwrap();
+    assert!(!all["junctions"].as_array().unwrap().is_empty());
+
+    assert!(pipeline_help_json(Some("no-such-command")).is_none());
+}
@@ -0,0 +1,426 @@
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+
+use serde_json::Value;
+use ustr::{ustr, Ustr};
+
+use super::{
+    interface::{
+        SymbolTreeTable, SymbolTreeTableItem, SymbolTreeTableNode, SymbolTreeTableWarning,
+    },
+    record_layouts::load_record,
+};
+use crate::{
+    abstract_server::{AbstractServer, Result},
+    file_format::analysis::{
+        AnalysisStructured, BindingImplKind, BindingOwnerLang, BindingSlotKind, BindingSlotLang,
+        StructuredBindingSlotInfo,
+    },
+};
+
+/// How a concrete class implements an IDL member in the `idl-impls` matrix.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImplState {
+    /// The class defines the method itself.
+    Impl,
+    /// The class defines the method via an `NS_FORWARD_*` macro.
+    Forwarded,
+    /// A superclass that implements the interface defines the method.
+    Inherited,
+    Missing,
+}
+
+impl ImplState {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ImplState::Impl => "impl",
+            ImplState::Forwarded => "forwarded",
+            ImplState::Inherited => "inherited",
+            ImplState::Missing => "missing",
+        }
+    }
+}
+
+/// Whether a method definition's line is an `NS_FORWARD_*` macro forwarding
+/// the interface's methods to another object.
+pub fn is_forwarded_def(line: &str) -> bool {
+    line.contains("NS_FORWARD_")
+}
+
+/// Whether the declaration of an XPIDL method's C++ binding is for a
+/// `[notxpcom]` method, which doesn't return nsresult and so is declared with
+/// `NS_IMETHOD_(type)` or, with `[nostdcall]`, as a plain virtual method.
+pub fn is_notxpcom_decl(line: &str) -> bool {
+    line.contains("NS_IMETHOD_(") || (line.contains("virtual") && !line.contains("NS_IMETHOD"))
+}
+
+fn is_js_path(path: &str) -> bool {
+    path.ends_with(".js") || path.ends_with(".mjs") || path.ends_with(".jsm")
+}
+
+fn def_paths(info: &Value) -> BTreeSet<String> {
+    info["defs"]
+        .as_array()
+        .map(|defs| {
+            defs.iter()
+                .filter_map(|def| def["path"].as_str())
+                .map(|path| path.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The C++ binding slots of an IDL member that implementations override,
+/// skipping WebIDL's generated glue, which isn't implemented by hand.
+fn cpp_member_slots(member: &AnalysisStructured) -> Vec<&StructuredBindingSlotInfo> {
+    member
+        .binding_slots
+        .iter()
+        .filter(|slot| {
+            slot.props.slot_lang == BindingSlotLang::Cpp
+                && matches!(
+                    slot.props.slot_kind,
+                    BindingSlotKind::Method | BindingSlotKind::Getter | BindingSlotKind::Setter
+                )
+                && slot.props.impl_kind != Some(BindingImplKind::Binding)
+        })
+        .collect()
+}
+
+fn js_member_slot(member: &AnalysisStructured) -> Option<Ustr> {
+    member
+        .binding_slots
+        .iter()
+        .find(|slot| {
+            slot.props.slot_lang == BindingSlotLang::JS
+                && matches!(
+                    slot.props.slot_kind,
+                    BindingSlotKind::Method | BindingSlotKind::Attribute
+                )
+        })
+        .map(|slot| slot.sym)
+}
+
+/// A row of the matrix before its cells are laid out.
+struct MemberRow {
+    name: String,
+    sym: Ustr,
+    notxpcom: bool,
+    /// The classes defining the member, if it has a C++ slot.
+    cpp_impls: Option<HashMap<Ustr, ImplState>>,
+    /// The files defining the member's JS property, if it has a JS slot.
+    js_def_paths: Option<BTreeSet<String>>,
+}
+
+/// Builds the `--mode=idl-impls` matrix of `format-symbols` for an XPIDL or
+/// WebIDL interface: a row for each of the interface's methods and attributes
+/// and a column for each concrete class implementing it, found by following
+/// the cross-language binding slots from the IDL symbols to their C++
+/// counterparts and then the subclass and override edges.  JS implementations
+/// get a column for each JS file that both mentions the interface and defines
+/// one of its members.
+pub struct IdlImplMatrix {
+    pub max_impls: usize,
+}
+
+impl IdlImplMatrix {
+    /// Find the IDL interface record for a symbol, which may be the IDL
+    /// interface itself or one of its bindings.
+    async fn lookup_interface(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        sym: &str,
+    ) -> Result<Option<AnalysisStructured>> {
+        let Some(record) = load_record(server, sym).await? else {
+            return Ok(None);
+        };
+        if record.impl_kind.as_str() == "idl" {
+            return Ok(Some(record));
+        }
+        match &record.slot_owner {
+            Some(owner) if owner.props.owner_lang == BindingOwnerLang::Idl => {
+                load_record(server, &owner.sym).await
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Find the concrete C++ classes implementing the interface in
+    /// breadth-first order, along with each class's superclass on its path
+    /// from the interface's C++ class.  Classes that are themselves bindings of
+    /// an IDL interface (ex: XPIDL interfaces extending this one) are followed
+    /// but aren't concrete.  Returns whether the search was truncated.
+    async fn find_cpp_impls(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        iface: &AnalysisStructured,
+        impls: &mut Vec<(Ustr, String)>,
+        parents: &mut HashMap<Ustr, Ustr>,
+    ) -> Result<bool> {
+        let mut pending: VecDeque<Ustr> = iface
+            .binding_slots
+            .iter()
+            .filter(|slot| {
+                slot.props.slot_lang == BindingSlotLang::Cpp
+                    && slot.props.slot_kind == BindingSlotKind::Class
+            })
+            .map(|slot| slot.sym)
+            .collect();
+        let mut seen: HashSet<Ustr> = pending.iter().copied().collect();
+
+        while let Some(class) = pending.pop_front() {
+            let Some(record) = load_record(server, &class).await? else {
+                continue;
+            };
+            let is_binding = record.impl_kind.as_str() == "idl"
+                || record.slot_owner.as_ref().map_or(false, |owner| {
+                    owner.props.owner_lang == BindingOwnerLang::Idl
+                        && owner.props.impl_kind != Some(BindingImplKind::Impl)
+                });
+            if !is_binding {
+                if impls.len() >= self.max_impls {
+                    return Ok(true);
+                }
+                impls.push((class, record.pretty.to_string()));
+            }
+            for sub in record.subclass_syms {
+                if seen.insert(sub) {
+                    parents.insert(sub, class);
+                    pending.push_back(sub);
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    /// Find the classes defining an implementation of a C++ binding method by
+    /// following its override edges.
+    async fn find_method_impls(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        method: Ustr,
+    ) -> Result<HashMap<Ustr, ImplState>> {
+        let mut impls = HashMap::new();
+        let mut pending = vec![method];
+        let mut seen: HashSet<Ustr> = HashSet::from([method]);
+        while let Some(sym) = pending.pop() {
+            let info = server.crossref_lookup_keys(&sym, &["meta", "defs"]).await?;
+            if let Some(class) = info.pointer("/meta/parentsym").and_then(|c| c.as_str()) {
+                let line = info
+                    .pointer("/defs/0/lines/0/line")
+                    .and_then(|l| l.as_str())
+                    .unwrap_or_default();
+                let state = if is_forwarded_def(line) {
+                    ImplState::Forwarded
+                } else {
+                    ImplState::Impl
+                };
+                impls.insert(ustr(class), state);
+            }
+            if let Some(Value::Array(overrides)) = info.pointer("/meta/overriddenBy") {
+                for next in overrides.iter().filter_map(|o| o.as_str()) {
+                    if seen.insert(ustr(next)) {
+                        pending.push(ustr(next));
+                    }
+                }
+            }
+        }
+        Ok(impls)
+    }
+
+    async fn member_rows(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        iface: &AnalysisStructured,
+    ) -> Result<Vec<MemberRow>> {
+        let members = iface
+            .methods
+            .iter()
+            .map(|m| m.sym)
+            .chain(iface.fields.iter().map(|f| f.sym));
+
+        let mut rows = vec![];
+        for member_sym in members {
+            let Some(member) = load_record(server, &member_sym).await? else {
+                continue;
+            };
+            let js_def_paths = match js_member_slot(&member) {
+                Some(js_sym) => Some(def_paths(
+                    &server.crossref_lookup_keys(&js_sym, &["defs"]).await?,
+                )),
+                None => None,
+            };
+
+            let cpp_slots = cpp_member_slots(&member);
+            if cpp_slots.is_empty() {
+                // Constants aren't implemented by anything.
+                if js_def_paths.is_some() {
+                    rows.push(MemberRow {
+                        name: member.pretty.to_string(),
+                        sym: member_sym,
+                        notxpcom: false,
+                        cpp_impls: None,
+                        js_def_paths,
+                    });
+                }
+                continue;
+            }
+            for slot in cpp_slots {
+                let name = match slot.props.slot_kind {
+                    BindingSlotKind::Getter => format!("{} (getter)", member.pretty),
+                    BindingSlotKind::Setter => format!("{} (setter)", member.pretty),
+                    _ => member.pretty.to_string(),
+                };
+                // Only XPIDL slots lack an impl kind, and only XPIDL has
+                // `[notxpcom]`.
+                let notxpcom = slot.props.impl_kind.is_none() && {
+                    let info = server.crossref_lookup_keys(&slot.sym, &["decls"]).await?;
+                    info.pointer("/decls/0/lines/0/line")
+                        .and_then(|l| l.as_str())
+                        .map_or(false, is_notxpcom_decl)
+                };
+                rows.push(MemberRow {
+                    name,
+                    sym: member_sym,
+                    notxpcom,
+                    cpp_impls: Some(self.find_method_impls(server, slot.sym).await?),
+                    js_def_paths: js_def_paths.clone(),
+                });
+            }
+        }
+        Ok(rows)
+    }
+
+    /// The JS files that mention the interface's name and define at least one
+    /// of its members.
+    async fn find_js_impls(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        iface: &AnalysisStructured,
+        rows: &[MemberRow],
+    ) -> Result<Vec<String>> {
+        let Some(name_slot) = iface.binding_slots.iter().find(|slot| {
+            slot.props.slot_lang == BindingSlotLang::JS
+                && slot.props.slot_kind == BindingSlotKind::InterfaceName
+        }) else {
+            return Ok(vec![]);
+        };
+        let info = server
+            .crossref_lookup_keys(&name_slot.sym, &["uses"])
+            .await?;
+        let mentioning: BTreeSet<&str> = info["uses"]
+            .as_array()
+            .map(|uses| {
+                uses.iter()
+                    .filter_map(|use_| use_["path"].as_str())
+                    .filter(|path| is_js_path(path))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let defining: BTreeSet<&str> = rows
+            .iter()
+            .filter_map(|row| row.js_def_paths.as_ref())
+            .flatten()
+            .map(|path| path.as_str())
+            .collect();
+        Ok(mentioning
+            .intersection(&defining)
+            .take(self.max_impls)
+            .map(|path| path.to_string())
+            .collect())
+    }
+
+    /// Build the matrix for the interface, or None if the symbol isn't an IDL
+    /// interface or one of its bindings.
+    pub async fn build(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        sym: &str,
+    ) -> Result<Option<SymbolTreeTable>> {
+        let Some(iface) = self.lookup_interface(server, sym).await? else {
+            return Ok(None);
+        };
+
+        let mut cpp_impls = vec![];
+        let mut parents = HashMap::new();
+        let truncated = self
+            .find_cpp_impls(server, &iface, &mut cpp_impls, &mut parents)
+            .await?;
+        let rows = self.member_rows(server, &iface).await?;
+        let js_impls = self.find_js_impls(server, &iface, &rows).await?;
+
+        let mut stt = SymbolTreeTable::new();
+        stt.platforms = cpp_impls
+            .iter()
+            .map(|(_, pretty)| pretty.clone())
+            .chain(js_impls.iter().map(|path| format!("JS: {}", path)))
+            .collect();
+        if truncated {
+            stt.warnings.push(SymbolTreeTableWarning {
+                kind: "too-many-impls".to_string(),
+                pretty: iface.pretty.to_string(),
+                platform: None,
+                message: format!(
+                    "Only the first {} implementations of {} are shown",
+                    self.max_impls, iface.pretty
+                ),
+            });
+        }
+
+        for row in rows {
+            let mut cells = vec![];
+            for (class, _) in &cpp_impls {
+                let Some(impls) = &row.cpp_impls else {
+                    cells.push(None);
+                    continue;
+                };
+                let mut state = impls.get(class).copied().unwrap_or(ImplState::Missing);
+                let mut ancestor = parents.get(class);
+                while state == ImplState::Missing {
+                    let Some(cur) = ancestor else {
+                        break;
+                    };
+                    if impls.contains_key(cur) {
+                        state = ImplState::Inherited;
+                    }
+                    ancestor = parents.get(cur);
+                }
+                cells.push(Some(if row.notxpcom {
+                    format!("{}, notxpcom", state.label())
+                } else {
+                    state.label().to_string()
+                }));
+            }
+            for path in &js_impls {
+                cells.push(row.js_def_paths.as_ref().map(|def_paths| {
+                    if def_paths.contains(path) {
+                        ImplState::Impl.label().to_string()
+                    } else {
+                        ImplState::Missing.label().to_string()
+                    }
+                }));
+            }
+
+            let mut node = SymbolTreeTableNode::new(row.name, row.sym.to_string());
+            node.items.push(SymbolTreeTableItem::Cells(cells));
+            stt.rows.push(node);
+        }
+
+        Ok(Some(stt))
+    }
+}
+
+#[test]
+fn test_idl_impl_line_heuristics() {
+    assert!(is_forwarded_def("  NS_FORWARD_NSIFOO(mInner->)"));
+    assert!(is_forwarded_def("NS_FORWARD_SAFE_NSIFOO(mInner)"));
+    assert!(!is_forwarded_def("NS_IMETHODIMP Foo::Bar() {"));
+
+    assert!(is_notxpcom_decl(
+        "  NS_IMETHOD_(nsrefcnt) AddRef(void) = 0;"
+    ));
+    assert!(is_notxpcom_decl("  virtual bool IsFoo(void) = 0;"));
+    assert!(!is_notxpcom_decl("  NS_IMETHOD GetFoo(int32_t *aFoo) = 0;"));
+    assert!(!is_notxpcom_decl(
+        "  JS_HAZ_CAN_RUN_SCRIPT NS_IMETHOD Run(void) = 0;"
+    ));
+}
@@ -1,23 +1,32 @@
 use async_trait::async_trait;
 use bitflags::bitflags;
 use clap::{Args, ValueEnum};
-use serde::{ser::SerializeStruct, Serialize, Serializer};
+use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
 use serde_json::{json, to_string_pretty, Value};
 use std::{
     cmp::Ordering,
     collections::{BTreeMap, HashMap, HashSet},
-    fmt::Debug,
+    fmt::{self, Debug},
+    time::Instant,
 };
 use tracing::{trace, trace_span, Instrument};
 use ustr::{ustr, Ustr, UstrMap};
 
 pub use crate::abstract_server::{AbstractServer, Result};
 use crate::{
-    abstract_server::{FileMatches, TextMatches},
-    file_format::crossref_converter::convert_crossref_value_to_sym_info_rep,
+    abstract_server::{AccessPolicy, FileMatches, PageInfo, ResourceUsage, TextMatches},
+    file_format::{
+        analysis::FileClassification, crossref_converter::convert_crossref_value_to_sym_info_rep,
+    },
 };
 
-use super::symbol_graph::{SymbolGraphCollection, SymbolGraphNodeSet};
+use super::coercion::coerce_input;
+use super::data_table::{DataCell, DataColumnType, DataTable};
+use super::output_sink::OutputSink;
+use super::ranking::RankScore;
+use super::symbol_graph::{
+    EdgeDetail, SymbolGraphCollection, SymbolGraphNodeSet, SymbolProvenance,
+};
 
 #[derive(Clone, Debug, PartialEq, ValueEnum)]
 pub enum RecordType {
@@ -70,6 +79,26 @@ pub struct SymbolTreeTable {
 
     /// Symbols to put into SYM_INFO, in addition to node_set.
     pub extra_syms: HashMap<String, Value>,
+
+    /// Problems with the underlying data that may make the table misleading.
+    pub warnings: Vec<SymbolTreeTableWarning>,
+}
+
+/// A non-fatal anomaly in the analysis data noticed while building a table,
+/// ex: a field that extends past the end of its class.  These don't stop the
+/// table from being built but mean its contents are suspect.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SymbolTreeTableWarning {
+    /// A stable identifier for the kind of anomaly for automated checks:
+    /// "missing-size", "field-beyond-class-size", "overlapping-fields", or
+    /// "too-many-impls".
+    pub kind: String,
+    /// The pretty name of the class or field involved.
+    pub pretty: String,
+    /// The platform the anomaly was seen on, if the data is per-platform.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub platform: Option<String>,
+    pub message: String,
 }
 
 #[derive(Serialize)]
@@ -159,7 +188,20 @@ pub enum BasicMarkup {
 pub struct SymbolTreeTableNode {
     pub name: String,
     pub symbols: String,
+    /// A label for how the class's fields are laid out, if the language lets
+    /// types choose, ex: "repr(C)" for Rust.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layout: Option<String>,
+    /// The tags the class has in the tree's annotation store.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// The class's size for each platform group column, if known.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sizes: Vec<Option<SymbolTreeTableClassSize>>,
     pub items: Vec<SymbolTreeTableItem>,
+    /// Why this row's symbol was included, if provenance was requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<SymbolProvenance>,
 }
 
 impl SymbolTreeTableNode {
@@ -167,19 +209,65 @@ impl SymbolTreeTableNode {
         Self {
             name,
             symbols,
+            layout: None,
+            tags: vec![],
+            sizes: vec![],
             items: vec![],
+            provenance: None,
+        }
+    }
+
+    /// The name with the layout label, for plain text output.
+    pub fn labeled_name(&self) -> String {
+        match &self.layout {
+            Some(layout) => format!("{} {}", self.name, layout),
+            None => self.name.clone(),
         }
     }
 }
 
+#[derive(Serialize)]
+pub struct SymbolTreeTableClassSize {
+    pub size: String,
+    /// The difference between the class's size and the sum of the sizes of
+    /// its own fields, which covers base classes, holes, and padding, if
+    /// non-zero.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delta: Option<String>,
+}
+
+impl SymbolTreeTableClassSize {
+    pub fn new(size: String, delta: Option<String>) -> Self {
+        Self { size, delta }
+    }
+}
+
 #[derive(Serialize)]
 pub enum SymbolTreeTableItem {
     Field(SymbolTreeTableField),
     Hole(Vec<Option<String>>),
     EndPadding(Vec<Option<String>>),
+    Method(SymbolTreeTableMethod),
+    /// Generic per-column values, as produced from a `DataTable`.
+    Cells(Vec<Option<String>>),
     Warning(String),
 }
 
+/// A member function listed beneath a class's fields.
+#[derive(Clone, Serialize)]
+pub struct SymbolTreeTableMethod {
+    pub name: String,
+    pub symbols: String,
+    /// The argument list, like "(int aIndex, nsIFoo* aFoo)".
+    pub signature: String,
+    /// Props of interest like "virtual" or "static".
+    pub flags: Vec<String>,
+    #[serde(rename = "defPath", skip_serializing_if = "Option::is_none")]
+    pub def_path: Option<String>,
+    #[serde(rename = "defLineno", skip_serializing_if = "Option::is_none")]
+    pub def_lineno: Option<u64>,
+}
+
 #[derive(Serialize)]
 pub struct SymbolTreeTableField {
     pub name: String,
@@ -188,6 +276,20 @@ pub struct SymbolTreeTableField {
     pub lines: Vec<String>,
     #[serde(rename = "offsetAndSize")]
     pub offset_and_size: Vec<Option<SymbolTreeTableFieldOffsetAndSize>>,
+    /// The source spans of the field's declaration(s), if requested.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub spans: Vec<SourceSpan>,
+}
+
+/// A span of source text.  Columns are 0-based with the end column exclusive,
+/// and are None when we only know the lines.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SourceSpan {
+    pub path: String,
+    pub start_lineno: u64,
+    pub start_col: Option<u32>,
+    pub end_lineno: u64,
+    pub end_col: Option<u32>,
 }
 
 impl SymbolTreeTableField {
@@ -198,6 +300,7 @@ impl SymbolTreeTableField {
             types: vec![],
             lines: vec![],
             offset_and_size: vec![],
+            spans: vec![],
         }
     }
 }
@@ -218,11 +321,19 @@ impl SymbolTreeTableFieldType {
 pub struct SymbolTreeTableFieldOffsetAndSize {
     pub offset: String,
     pub size: String,
+    /// The field's alignment, if requested.  Inferred alignments are prefixed
+    /// with "~".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub align: Option<String>,
 }
 
 impl SymbolTreeTableFieldOffsetAndSize {
     pub fn new(offset: String, size: String) -> Self {
-        Self { offset, size }
+        Self {
+            offset,
+            size,
+            align: None,
+        }
     }
 }
 
@@ -239,6 +350,7 @@ impl SymbolTreeTable {
             platforms: vec![],
             rows: vec![],
             extra_syms: HashMap::new(),
+            warnings: vec![],
         }
     }
 }
@@ -260,6 +372,12 @@ impl Serialize for SymbolTreeTable {
         )?;
         stt.serialize_field("platforms", &self.platforms)?;
         stt.serialize_field("rows", &self.rows)?;
+        if !self.warnings.is_empty() {
+            stt.serialize_field("warnings", &self.warnings)?;
+        }
+        if self.node_set.record_provenance {
+            stt.serialize_field("provenance", &self.node_set.provenance_to_json())?;
+        }
         stt.end()
     }
 }
@@ -282,9 +400,141 @@ pub enum PipelineValues {
     TextFile(TextFile),
     BatchGroups(BatchGroups),
     SymbolTreeTableList(SymbolTreeTableList),
+    SymbolHitGroups(SymbolHitGroups),
+    SymbolExamplesList(SymbolExamplesList),
+    DataTable(DataTable),
     Void,
 }
 
+impl PipelineValues {
+    /// The name of the variant, as used in `CommandSignature`s.
+    pub fn variant_name(&self) -> &'static str {
+        match self {
+            PipelineValues::IdentifierList(_) => "IdentifierList",
+            PipelineValues::SymbolList(_) => "SymbolList",
+            PipelineValues::SymbolCrossrefInfoList(_) => "SymbolCrossrefInfoList",
+            PipelineValues::SymbolGraphCollection(_) => "SymbolGraphCollection",
+            PipelineValues::JsonValue(_) => "JsonValue",
+            PipelineValues::JsonValueList(_) => "JsonValueList",
+            PipelineValues::JsonRecords(_) => "JsonRecords",
+            PipelineValues::FileMatches(_) => "FileMatches",
+            PipelineValues::TextMatches(_) => "TextMatches",
+            PipelineValues::HtmlExcerpts(_) => "HtmlExcerpts",
+            PipelineValues::FlattenedResultsBundle(_) => "FlattenedResultsBundle",
+            PipelineValues::GraphResultsBundle(_) => "GraphResultsBundle",
+            PipelineValues::TextFile(_) => "TextFile",
+            PipelineValues::BatchGroups(_) => "BatchGroups",
+            PipelineValues::SymbolTreeTableList(_) => "SymbolTreeTableList",
+            PipelineValues::SymbolHitGroups(_) => "SymbolHitGroups",
+            PipelineValues::SymbolExamplesList(_) => "SymbolExamplesList",
+            PipelineValues::DataTable(_) => "DataTable",
+            PipelineValues::Void => "Void",
+        }
+    }
+
+    /// The SYM_INFO jumpref data for rendering these values as HTML, if any.
+    pub fn sym_info(&self) -> Value {
+        match self {
+            PipelineValues::GraphResultsBundle(grb) => grb.symbols.clone(),
+            PipelineValues::SymbolTreeTableList(sttl) => sttl.unioned_node_sets_as_jumprefs(),
+            _ => Value::Object(Default::default()),
+        }
+    }
+
+    /// Remove everything about paths the policy doesn't allow.  Pipelines run
+    /// against a server wrapped by `restrict_server` should already only see
+    /// allowed paths, but commands may derive paths from other sources, so the
+    /// pipeline-server also applies this to the final values before
+    /// serializing them.
+    pub fn apply_access_policy(&mut self, policy: &AccessPolicy) {
+        let filter_node_set = |node_set: &mut SymbolGraphNodeSet| {
+            for info in node_set.symbol_crossref_infos.iter_mut() {
+                policy.filter_json(&mut info.crossref_info);
+            }
+        };
+        match self {
+            PipelineValues::SymbolCrossrefInfoList(scil) => {
+                for info in scil.symbol_crossref_infos.iter_mut() {
+                    policy.filter_json(&mut info.crossref_info);
+                }
+            }
+            PipelineValues::SymbolGraphCollection(sgc) => {
+                filter_node_set(&mut sgc.node_set);
+                for edge in sgc.edge_set.edge_infos.iter_mut() {
+                    edge.data.retain(|detail| match detail {
+                        EdgeDetail::Jump(jump) => policy.allows_jump(jump),
+                        EdgeDetail::HoverClass(_) => true,
+                    });
+                }
+            }
+            PipelineValues::JsonValue(jv) => policy.filter_json(&mut jv.value),
+            PipelineValues::JsonValueList(jvl) => {
+                for jv in jvl.values.iter_mut() {
+                    policy.filter_json(&mut jv.value);
+                }
+            }
+            PipelineValues::JsonRecords(jr) => jr.by_file.retain(|f| policy.allows(&f.file)),
+            PipelineValues::FileMatches(fm) => fm.file_matches.retain(|m| policy.allows(&m.path)),
+            PipelineValues::TextMatches(tm) => tm.by_file.retain(|f| policy.allows(&f.file)),
+            PipelineValues::HtmlExcerpts(he) => he.by_file.retain(|f| policy.allows(&f.file)),
+            PipelineValues::FlattenedResultsBundle(frb) => {
+                for path_kind_group in frb.path_kind_results.iter_mut() {
+                    path_kind_group
+                        .file_names
+                        .retain(|path| policy.allows(path));
+                    for kind_group in path_kind_group.kind_groups.iter_mut() {
+                        kind_group.by_file.retain(|f| policy.allows(&f.file));
+                    }
+                }
+            }
+            PipelineValues::GraphResultsBundle(grb) => policy.filter_json(&mut grb.symbols),
+            PipelineValues::BatchGroups(bg) => {
+                for item in bg.groups.iter_mut() {
+                    item.value.apply_access_policy(policy);
+                }
+            }
+            PipelineValues::SymbolTreeTableList(sttl) => {
+                for table in sttl.tables.iter_mut() {
+                    filter_node_set(&mut table.node_set);
+                    for value in table.extra_syms.values_mut() {
+                        policy.filter_json(value);
+                    }
+                }
+            }
+            PipelineValues::SymbolHitGroups(shg) => {
+                for group in shg.groups.iter_mut() {
+                    group.hits.retain(|hit| policy.allows(&hit.path));
+                }
+                shg.unresolved.retain(|hit| policy.allows(&hit.path));
+            }
+            PipelineValues::SymbolExamplesList(sel) => {
+                for examples in sel.symbols.iter_mut() {
+                    examples.examples.retain(|ex| policy.allows(&ex.path));
+                }
+            }
+            PipelineValues::DataTable(dt) => {
+                let path_columns: Vec<usize> = dt
+                    .columns
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, column)| column.column_type == DataColumnType::Path)
+                    .map(|(i, _)| i)
+                    .collect();
+                dt.rows.retain(|row| {
+                    path_columns.iter().all(|i| match row.get(*i) {
+                        Some(DataCell::String(path)) => policy.allows_jump(path),
+                        _ => true,
+                    })
+                });
+            }
+            PipelineValues::IdentifierList(_)
+            | PipelineValues::SymbolList(_)
+            | PipelineValues::TextFile(_)
+            | PipelineValues::Void => {}
+        }
+    }
+}
+
 /// A list of (searchfox) identifiers.
 #[derive(Serialize)]
 pub struct IdentifierList {
@@ -302,6 +552,9 @@ pub struct SymbolWithContext {
 #[derive(Serialize)]
 pub struct SymbolList {
     pub symbols: Vec<SymbolWithContext>,
+    /// Present if the symbols were paginated or sampled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<PageInfo>,
 }
 
 /// Metadata about how we got to this symbol from the root query.  Intended to
@@ -362,6 +615,10 @@ pub enum SymbolQuality {
     /// identifier beyond the match point.  The latter number should always be
     /// at least 1, as 0 would make this `ExactIdentifier`.
     IdentifierPrefix(u32, u32),
+    /// We did identifier substring search and the needle was found somewhere
+    /// other than at the start of the identifier; the values are as for
+    /// `IdentifierPrefix`.  These rank below prefix matches of the same length.
+    IdentifierSubstring(u32, u32),
 }
 
 impl SymbolQuality {
@@ -373,6 +630,7 @@ impl SymbolQuality {
             SymbolQuality::ExplicitIdentifier => 1,
             SymbolQuality::ExactIdentifier => 2,
             SymbolQuality::IdentifierPrefix(_matched, extra) => 2 + extra,
+            SymbolQuality::IdentifierSubstring(_matched, extra) => 3 + extra,
         }
     }
 }
@@ -391,7 +649,7 @@ impl Ord for SymbolQuality {
     }
 }
 
-#[derive(Clone, Serialize)]
+#[derive(Clone, Deserialize, Serialize)]
 pub enum OverloadKind {
     /// There's just too many overrides!  This would happen for
     /// nsISupports::AddRef for example.
@@ -425,7 +683,7 @@ pub enum OverloadKind {
 ///
 /// This is not currently intended to be used for `compile-results`, but could
 /// perhaps be adapted for that.
-#[derive(Clone, Serialize)]
+#[derive(Clone, Deserialize, Serialize)]
 pub struct OverloadInfo {
     pub kind: OverloadKind,
     /// The symbol, if any, this overload is associated with beyond the owner
@@ -486,6 +744,9 @@ pub struct SymbolCrossrefInfo {
     pub overloads_hit: Vec<OverloadInfo>,
     #[serde(rename = "type", skip_serializing_if = "SymbolMetaFlags::is_empty")]
     pub flags: SymbolMetaFlags,
+    /// The ranking score, if the results were ranked via `--rank-profile`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<RankScore>,
 }
 
 impl SymbolCrossrefInfo {
@@ -521,6 +782,9 @@ impl SymbolCrossrefInfo {
 pub struct SymbolCrossrefInfoList {
     pub symbol_crossref_infos: Vec<SymbolCrossrefInfo>,
     pub unknown_symbols: Vec<String>,
+    /// Present if the symbols were paginated or sampled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<PageInfo>,
 }
 
 /// router.py-style mozsearch compiled results that has top-level path-kind
@@ -764,6 +1028,78 @@ impl FlattenedLineSpan {
     }
 }
 
+/// Hits grouped by the symbol they resolve to, as produced by `group-hits`.
+#[derive(Serialize)]
+pub struct SymbolHitGroups {
+    pub groups: Vec<SymbolHitGroup>,
+    /// Fulltext hits that could not be mapped to any symbol.
+    pub unresolved: Vec<SymbolHit>,
+}
+
+#[derive(Serialize)]
+pub struct SymbolHitGroup {
+    pub symbol: Ustr,
+    pub pretty: Ustr,
+    /// The number of distinct (path, line) hits after collapsing duplicates.
+    pub hit_count: u32,
+    pub file_count: u32,
+    /// How many redundant hits were folded into existing hits.
+    pub duplicates_collapsed: u32,
+    /// Per-kind ("defs", "decls", "uses", etc.) hit counts.  A single hit can
+    /// count towards multiple kinds.
+    pub kind_counts: BTreeMap<String, u32>,
+    /// Human-readable summary like "12 uses across 3 files", naming the hits'
+    /// kind if they all share one.
+    pub summary: String,
+    /// The hits themselves, which may be truncated or omit generated files
+    /// depending on `group-hits` arguments; the counts above are unaffected.
+    pub hits: Vec<SymbolHit>,
+}
+
+#[derive(Serialize)]
+pub struct SymbolHit {
+    pub path: Ustr,
+    pub path_kind: Ustr,
+    #[serde(skip_serializing_if = "FileClassification::is_normal")]
+    pub classification: FileClassification,
+    pub lineno: u32,
+    pub kinds: Vec<String>,
+    pub line: String,
+}
+
+/// Representative usage examples for symbols, as produced by `find-examples`.
+#[derive(Serialize)]
+pub struct SymbolExamplesList {
+    pub symbols: Vec<SymbolExamples>,
+}
+
+#[derive(Serialize)]
+pub struct SymbolExamples {
+    pub symbol: Ustr,
+    pub pretty: Ustr,
+    /// The total number of candidate uses we chose the examples from.
+    pub candidate_count: u32,
+    pub examples: Vec<SymbolExample>,
+}
+
+#[derive(Serialize)]
+pub struct SymbolExample {
+    pub path: Ustr,
+    #[serde(skip_serializing_if = "FileClassification::is_normal")]
+    pub classification: FileClassification,
+    pub lineno: u32,
+    /// The pretty name of the enclosing symbol, if known.
+    pub context: Ustr,
+    /// The use line plus the requested amount of surrounding context.
+    pub lines: Vec<ExampleLine>,
+}
+
+#[derive(Serialize)]
+pub struct ExampleLine {
+    pub lineno: u32,
+    pub line: String,
+}
+
 /// Rendered graphs and associated metadata.
 #[derive(Serialize)]
 pub struct GraphResultsBundle {
@@ -877,6 +1213,62 @@ pub struct ServerPipeline {
     pub server_kind: String,
     pub server: Box<dyn AbstractServer + Send + Sync>,
     pub commands: Vec<Box<dyn PipelineCommand + Send + Sync>>,
+    /// The arguments each command was parsed from, for reporting.
+    pub command_args: Vec<String>,
+    /// Whether `--timing` was requested.
+    pub timing: bool,
+    /// Where to write the final value if `--output-file` was requested.
+    pub output_sink: Option<OutputSink>,
+}
+
+/// How long a pipeline stage took and the resources it used.
+#[derive(Clone, Debug, Serialize)]
+pub struct StageTiming {
+    pub command: String,
+    pub wall_us: u64,
+    #[serde(flatten)]
+    pub usage: ResourceUsage,
+}
+
+/// Per-stage timing for a `ServerPipeline` run, as reported by `--timing`.
+/// Stages after a failing stage don't run and so aren't listed.  Resource usage
+/// is only tracked by local indices.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct PipelineTiming {
+    pub stages: Vec<StageTiming>,
+    pub total_wall_us: u64,
+    pub total: ResourceUsage,
+}
+
+impl fmt::Display for PipelineTiming {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:>10} {:>8} {:>12} {:>6}  stage",
+            "wall ms", "symbols", "bytes read", "cache"
+        )?;
+        let rows = self
+            .stages
+            .iter()
+            .map(|stage| (stage.wall_us, &stage.usage, stage.command.as_str()))
+            .chain(std::iter::once((
+                self.total_wall_us,
+                &self.total,
+                "(total)",
+            )));
+        for (wall_us, usage, command) in rows {
+            writeln!(
+                f,
+                "{:>10.1} {:>8} {:>12} {:>6}  {}",
+                wall_us as f64 / 1000.0,
+                usage.symbols_fetched,
+                usage.bytes_read,
+                usage.cache_hits,
+                command
+            )?;
+        }
+        Ok(())
+    }
 }
 
 /// A linear pipeline sequence that potentially runs in parallel with other
@@ -983,22 +1375,54 @@ pub struct ServerPipelineGraph {
 
 impl ServerPipeline {
     pub async fn run(&self, traced: bool) -> Result<PipelineValues> {
+        self.run_timed(traced).await.0
+    }
+
+    /// Run the pipeline, also measuring the wall time of each stage and the
+    /// resources the server used on its behalf.
+    pub async fn run_timed(&self, traced: bool) -> (Result<PipelineValues>, PipelineTiming) {
+        let counters = self.server.resource_counters();
+        let snapshot = || counters.as_ref().map(|c| c.snapshot()).unwrap_or_default();
+        let mut timing = PipelineTiming::default();
+        let run_started = Instant::now();
+        let run_usage = snapshot();
+
         let mut cur_values = PipelineValues::Void;
 
-        for cmd in &self.commands {
+        for (i, cmd) in self.commands.iter().enumerate() {
             let span = trace_span!("run_pipeline_step", cmd = ?cmd);
 
-            match cmd
-                .execute(self.server.as_ref(), cur_values)
-                .instrument(span.clone())
-                .await
-            {
+            let stage_started = Instant::now();
+            let stage_usage = snapshot();
+            // The command name is the first word of its arguments.
+            let name = self
+                .command_args
+                .get(i)
+                .and_then(|args| args.split_whitespace().next())
+                .unwrap_or_default();
+            let result = match coerce_input(self.server.as_ref(), name, cur_values).await {
+                Ok(input) => {
+                    cmd.execute(self.server.as_ref(), input)
+                        .instrument(span.clone())
+                        .await
+                }
+                Err(err) => Err(err),
+            };
+            timing.stages.push(StageTiming {
+                command: self.command_args.get(i).cloned().unwrap_or_default(),
+                wall_us: stage_started.elapsed().as_micros() as u64,
+                usage: snapshot().since(&stage_usage),
+            });
+            timing.total_wall_us = run_started.elapsed().as_micros() as u64;
+            timing.total = snapshot().since(&run_usage);
+
+            match result {
                 Ok(next_values) => {
                     cur_values = next_values;
                 }
                 Err(err) => {
                     trace!(err = ?err);
-                    return Err(err);
+                    return (Err(err), timing);
                 }
             }
 
@@ -1009,7 +1433,7 @@ impl ServerPipeline {
             }
         }
 
-        Ok(cur_values)
+        (Ok(cur_values), timing)
     }
 }
 
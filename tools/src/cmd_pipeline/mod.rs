@@ -1,33 +1,115 @@
 extern crate clap;
 
 pub mod builder;
+pub mod coercion;
+pub mod data_table;
+pub mod help_json;
+pub mod idl_impls;
 pub mod interface;
+pub mod output_sink;
+pub mod pagination;
 pub mod parser;
+pub mod path_filter;
+pub mod permalink;
+pub mod plugins;
+pub mod ranking;
+pub mod record_layouts;
+pub mod signatures;
 pub mod symbol_graph;
+pub mod test_mapping;
+pub mod token_scope;
 pub mod transforms;
 
+mod cmd_annotate;
+mod cmd_annotations;
+mod cmd_api_surface;
+mod cmd_audit_external_links;
 mod cmd_augment_results;
+mod cmd_badge;
 mod cmd_batch_render;
+mod cmd_bug_refs;
 mod cmd_cat_html;
+mod cmd_cat_source;
+mod cmd_check_layering;
+mod cmd_check_layout_budget;
 mod cmd_compile_results;
+mod cmd_completions;
+mod cmd_convert_lsif;
 mod cmd_crossref_expand;
 mod cmd_crossref_lookup;
+mod cmd_describe_file;
+mod cmd_diff_file;
+mod cmd_doc_symbol;
+mod cmd_explain;
+mod cmd_export_bundle;
+mod cmd_export_scip;
+mod cmd_field_at;
+mod cmd_file_log;
 mod cmd_filter_analysis;
+mod cmd_find_duplicates;
+mod cmd_find_examples;
+mod cmd_find_field_type;
+mod cmd_find_overrides;
+mod cmd_find_paths;
+mod cmd_flaky_heatmap;
 mod cmd_format_symbols;
+mod cmd_format_table;
 mod cmd_fuse_crossrefs;
+mod cmd_gc_index;
+mod cmd_generate_tags;
 mod cmd_graph;
+mod cmd_graph_cycles;
+mod cmd_graph_metrics;
+mod cmd_group_hits;
+mod cmd_help_json;
+mod cmd_hex_dump;
+mod cmd_hook_results;
+mod cmd_ingest_query_logs;
+mod cmd_ingest_test_stats;
+mod cmd_js_module_graph;
 mod cmd_jumpref_lookup;
+mod cmd_l10n_xref;
+mod cmd_language_stats;
+mod cmd_line_history;
+mod cmd_load_graph;
+mod cmd_lock_report;
+mod cmd_map_script;
 mod cmd_merge_analyses;
+mod cmd_notify;
+mod cmd_offsetof;
+mod cmd_ownership_report;
+mod cmd_permalinks;
+mod cmd_popular_queries;
+mod cmd_precompute;
 mod cmd_prod_filter;
 mod cmd_query;
+mod cmd_reachable_from;
+mod cmd_refcount_cycles;
+mod cmd_refine;
 mod cmd_render;
+mod cmd_run_post_index_hooks;
+mod cmd_run_spec;
+mod cmd_rust_crate_graph;
+mod cmd_save_graph;
 mod cmd_search;
+mod cmd_search_attributes;
 mod cmd_search_files;
 mod cmd_search_identifiers;
 mod cmd_search_text;
+mod cmd_share;
 mod cmd_show_html;
+mod cmd_show_index_info;
+mod cmd_sizeof;
+mod cmd_static_inits;
+mod cmd_structured_columns;
+mod cmd_symbol_set;
+mod cmd_tests_for_symbol;
+mod cmd_todos;
 mod cmd_tokenize_source;
 mod cmd_traverse;
+mod cmd_trend;
+mod cmd_tui;
+mod cmd_verify_index;
 mod cmd_webtest;
 
 pub use builder::build_pipeline;
@@ -0,0 +1,345 @@
+use std::path::Path;
+
+use clap::ValueEnum;
+use serde_json::{to_string_pretty, to_value, Value};
+
+use super::{data_table::DataTable, interface::PipelineValues};
+use crate::abstract_server::{ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError};
+
+/// The formats a pipeline's final value can be written to a file in with
+/// `--output-file`.  Not every value can be rendered in every format; see
+/// `available_formats`.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum OutputFileFormat {
+    /// Pretty-printed JSON, which is available for every value.
+    Json,
+    /// An HTML document, for HTML files, excerpts, and tables.
+    Html,
+    /// CSV with a header row, for tables.
+    Csv,
+    /// Graphviz DOT, for the graphs commands emit as DOT.
+    Dot,
+    /// Plain text, for text files, identifiers, and tables.
+    Txt,
+}
+
+impl OutputFileFormat {
+    /// The format conventionally used for files with the extension, if any.
+    pub fn from_extension(path: &str) -> Option<Self> {
+        let ext = Path::new(path).extension()?.to_str()?.to_lowercase();
+        Some(match ext.as_str() {
+            "json" => OutputFileFormat::Json,
+            "html" | "htm" => OutputFileFormat::Html,
+            "csv" => OutputFileFormat::Csv,
+            "dot" | "gv" => OutputFileFormat::Dot,
+            "txt" | "text" => OutputFileFormat::Txt,
+            _ => return None,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            OutputFileFormat::Json => "json",
+            OutputFileFormat::Html => "html",
+            OutputFileFormat::Csv => "csv",
+            OutputFileFormat::Dot => "dot",
+            OutputFileFormat::Txt => "txt",
+        }
+    }
+}
+
+/// Where and how to write a pipeline's final value, as requested by the
+/// global `--output-file` and `--output-file-format` flags.  Centralizing this
+/// means commands only have to produce values and never print or write them
+/// themselves.
+#[derive(Clone, Debug)]
+pub struct OutputSink {
+    pub path: String,
+    pub format: OutputFileFormat,
+}
+
+impl OutputSink {
+    /// Create a sink for the path, inferring the format from the path's
+    /// extension if it isn't given.
+    pub fn new(path: &str, format: Option<OutputFileFormat>) -> Result<Self> {
+        match format.or_else(|| OutputFileFormat::from_extension(path)) {
+            Some(format) => Ok(OutputSink {
+                path: path.to_string(),
+                format,
+            }),
+            None => Err(ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::BadQuery,
+                    ErrorLayer::BadInput,
+                    format!("Can't tell what format to write {} in", path),
+                )
+                .with_hint("Pass `--output-file-format` or use a .json, .html, .csv, .dot, or .txt extension."),
+            )),
+        }
+    }
+
+    /// Render the value in the sink's format, or explain which formats it
+    /// could be written in instead.
+    pub fn render(&self, values: &PipelineValues) -> Result<String> {
+        if let Some(contents) = render_as(values, self.format)? {
+            return Ok(contents);
+        }
+        let available: Vec<&str> = available_formats(values).iter().map(|f| f.name()).collect();
+        Err(ServerError::StickyProblem(
+            ErrorDetails::new(
+                ErrorKind::BadQuery,
+                ErrorLayer::ConfigLayer,
+                format!(
+                    "A {} can't be written as {}",
+                    values.variant_name(),
+                    self.format.name()
+                ),
+            )
+            .with_hint(format!(
+                "It can be written as {}; `format-table` and `graph --format` convert to other formats.",
+                available.join(", ")
+            )),
+        ))
+    }
+
+    pub fn write(&self, values: &PipelineValues) -> Result<()> {
+        let contents = self.render(values)?;
+        std::fs::write(&self.path, contents)?;
+        Ok(())
+    }
+}
+
+/// The formats the value can be rendered in.
+pub fn available_formats(values: &PipelineValues) -> Vec<OutputFileFormat> {
+    OutputFileFormat::value_variants()
+        .iter()
+        .copied()
+        .filter(|format| can_render_as(values, *format))
+        .collect()
+}
+
+fn can_render_as(values: &PipelineValues, format: OutputFileFormat) -> bool {
+    match format {
+        OutputFileFormat::Json => true,
+        OutputFileFormat::Html => {
+            matches!(
+                values,
+                PipelineValues::HtmlExcerpts(_)
+                    | PipelineValues::DataTable(_)
+                    | PipelineValues::SymbolTreeTableList(_)
+            ) || is_text_file(values, "text/html")
+        }
+        OutputFileFormat::Csv => {
+            matches!(
+                values,
+                PipelineValues::DataTable(_) | PipelineValues::SymbolTreeTableList(_)
+            ) || is_text_file(values, "text/csv")
+        }
+        OutputFileFormat::Dot => is_text_file(values, "text/x-dot"),
+        OutputFileFormat::Txt => matches!(
+            values,
+            PipelineValues::TextFile(_)
+                | PipelineValues::IdentifierList(_)
+                | PipelineValues::DataTable(_)
+                | PipelineValues::SymbolTreeTableList(_)
+        ),
+    }
+}
+
+fn is_text_file(values: &PipelineValues, mime_type: &str) -> bool {
+    matches!(values, PipelineValues::TextFile(tf) if tf.mime_type == mime_type)
+}
+
+/// Render the value in the format, or None if it can't be.
+fn render_as(values: &PipelineValues, format: OutputFileFormat) -> Result<Option<String>> {
+    if !can_render_as(values, format) {
+        return Ok(None);
+    }
+    // Text files that are already in the format are written as-is.
+    if let PipelineValues::TextFile(tf) = values {
+        if format != OutputFileFormat::Json {
+            return Ok(Some(tf.contents.clone()));
+        }
+    }
+    let table = match values {
+        PipelineValues::DataTable(dt) => Some(dt.clone()),
+        PipelineValues::SymbolTreeTableList(sttl) => {
+            Some(DataTable::from_symbol_tree_table_list(sttl)?)
+        }
+        _ => None,
+    };
+
+    Ok(Some(match (format, table) {
+        (OutputFileFormat::Json, _) => {
+            let mut contents = to_string_pretty(&values_to_json(values)?)?;
+            contents.push('\n');
+            contents
+        }
+        (OutputFileFormat::Csv, Some(table)) => table.to_csv(),
+        (OutputFileFormat::Txt, Some(table)) => table_to_text(&table),
+        (OutputFileFormat::Html, Some(table)) => table_to_html(&table),
+        (OutputFileFormat::Html, None) => {
+            let mut contents = String::new();
+            if let PipelineValues::HtmlExcerpts(he) = values {
+                for file_excerpts in &he.by_file {
+                    for excerpt in &file_excerpts.excerpts {
+                        contents.push_str(excerpt);
+                        contents.push('\n');
+                    }
+                }
+            }
+            contents
+        }
+        (OutputFileFormat::Txt, None) => match values {
+            PipelineValues::IdentifierList(il) => il
+                .identifiers
+                .iter()
+                .map(|identifier| format!("{}\n", identifier))
+                .collect(),
+            _ => "".to_string(),
+        },
+        _ => return Ok(None),
+    }))
+}
+
+/// The JSON for the value, which is the same JSON searchfox-tool prints except
+/// that values it prints one at a time are gathered into an array.
+fn values_to_json(values: &PipelineValues) -> Result<Value> {
+    Ok(match values {
+        PipelineValues::Void => Value::Null,
+        PipelineValues::SymbolCrossrefInfoList(scil) => Value::Array(
+            scil.symbol_crossref_infos
+                .iter()
+                .map(|info| info.crossref_info.clone())
+                .collect(),
+        ),
+        PipelineValues::SymbolGraphCollection(sgc) => sgc.to_json(),
+        PipelineValues::JsonValue(jv) => jv.value.clone(),
+        PipelineValues::JsonRecords(jr) => Value::Array(
+            jr.by_file
+                .iter()
+                .flat_map(|file_records| file_records.records.iter().cloned())
+                .collect(),
+        ),
+        // Everything else serializes as an object keyed by the variant name.
+        values => match to_value(values)? {
+            Value::Object(mut obj) => obj.remove(values.variant_name()).unwrap_or(Value::Null),
+            value => value,
+        },
+    })
+}
+
+/// Tab-separated lines with a header line of column names.
+fn table_to_text(table: &DataTable) -> String {
+    let mut text = String::new();
+    let names: Vec<&str> = table.columns.iter().map(|c| c.name.as_str()).collect();
+    text.push_str(&names.join("\t"));
+    text.push('\n');
+    for row in &table.rows {
+        let cells: Vec<String> = row.iter().map(|cell| cell.to_text()).collect();
+        text.push_str(&cells.join("\t"));
+        text.push('\n');
+    }
+    text
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn table_to_html(table: &DataTable) -> String {
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"></head>\n<body>\n<table>\n<tr>",
+    );
+    for column in &table.columns {
+        html.push_str(&format!("<th>{}</th>", escape_html(&column.name)));
+    }
+    html.push_str("</tr>\n");
+    for row in &table.rows {
+        html.push_str("<tr>");
+        for cell in row {
+            html.push_str(&format!("<td>{}</td>", escape_html(&cell.to_text())));
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>\n</body>\n</html>\n");
+    html
+}
+
+#[test]
+fn test_output_file_format_from_extension() {
+    assert_eq!(
+        OutputFileFormat::from_extension("out/report.CSV"),
+        Some(OutputFileFormat::Csv)
+    );
+    assert_eq!(
+        OutputFileFormat::from_extension("graph.gv"),
+        Some(OutputFileFormat::Dot)
+    );
+    assert_eq!(OutputFileFormat::from_extension("report"), None);
+    assert!(OutputSink::new("report", None).is_err());
+    assert_eq!(
+        OutputSink::new("report", Some(OutputFileFormat::Txt))
+            .unwrap()
+            .format,
+        OutputFileFormat::Txt
+    );
+}
+
+#[test]
+fn test_output_sink_negotiation() {
+    use super::data_table::{DataCell, DataColumnType};
+    use super::interface::TextFile;
+
+    let mut table = DataTable::new(vec![
+        ("name", DataColumnType::String),
+        ("count", DataColumnType::Int),
+    ]);
+    table
+        .push_row(vec![DataCell::String("a<b".to_string()), DataCell::Int(2)])
+        .unwrap();
+    let table = PipelineValues::DataTable(table);
+    assert_eq!(
+        available_formats(&table),
+        vec![
+            OutputFileFormat::Json,
+            OutputFileFormat::Html,
+            OutputFileFormat::Csv,
+            OutputFileFormat::Txt
+        ]
+    );
+    let sink = |format| OutputSink {
+        path: "out".to_string(),
+        format,
+    };
+    assert_eq!(
+        sink(OutputFileFormat::Csv).render(&table).unwrap(),
+        "name,count\r\na<b,2\r\n"
+    );
+    assert_eq!(
+        sink(OutputFileFormat::Txt).render(&table).unwrap(),
+        "name\tcount\na<b\t2\n"
+    );
+    assert!(sink(OutputFileFormat::Html)
+        .render(&table)
+        .unwrap()
+        .contains("<td>a&lt;b</td><td>2</td>"));
+    assert!(sink(OutputFileFormat::Dot).render(&table).is_err());
+
+    let dot = PipelineValues::TextFile(TextFile {
+        mime_type: "text/x-dot".to_string(),
+        contents: "digraph g {}\n".to_string(),
+    });
+    assert_eq!(
+        sink(OutputFileFormat::Dot).render(&dot).unwrap(),
+        "digraph g {}\n"
+    );
+    assert!(sink(OutputFileFormat::Csv).render(&dot).is_err());
+    assert_eq!(
+        sink(OutputFileFormat::Json).render(&dot).unwrap(),
+        "{\n  \"mime_type\": \"text/x-dot\",\n  \"contents\": \"digraph g {}\\n\"\n}\n"
+    );
+}
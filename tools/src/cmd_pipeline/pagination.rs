@@ -0,0 +1,190 @@
+use clap::Args;
+
+use crate::abstract_server::{ErrorDetails, ErrorKind, ErrorLayer, PageInfo, Result, ServerError};
+
+/// Pagination arguments shared by search and traversal commands whose results
+/// can be too large to return at once.  Commands should `#[clap(flatten)]` this
+/// into their args and call `PaginationArgs::paginate` on their final results.
+///
+/// Paginated results are sorted by a stable per-command key (ex: the path for
+/// file results, the symbol for symbol results) so that the same query always
+/// produces the same pages.  Cursors encode the key of the last result of a
+/// page, so they keep working even if results are added or removed between
+/// requests, unlike offsets.  When none of these arguments are used, results
+/// are left in the command's usual order.
+///
+/// Note that pagination happens after the command's own `--limit`, so callers
+/// that want to page through everything should raise that limit.
+#[derive(Debug, Default, Args)]
+pub struct PaginationArgs {
+    /// Skip this many results, after those skipped by `--cursor`.
+    #[clap(long, value_parser, default_value = "0")]
+    pub offset: usize,
+
+    /// Return at most this many results plus a `next_cursor` for the next page.
+    #[clap(long, value_parser)]
+    pub page_size: Option<usize>,
+
+    /// Resume after the last result of a previous page, as given by that
+    /// page's `next_cursor`.
+    #[clap(long, value_parser)]
+    pub cursor: Option<String>,
+
+    /// Return an evenly spaced sample of this many results, for a quick
+    /// preview of an enormous result set.  The sample is deterministic.
+    #[clap(long, value_parser, conflicts_with = "page_size")]
+    pub sample: Option<usize>,
+}
+
+fn encode_cursor(key: &str) -> String {
+    key.bytes().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn decode_cursor(cursor: &str) -> Result<String> {
+    let bad_cursor = || {
+        ServerError::StickyProblem(
+            ErrorDetails::new(
+                ErrorKind::BadQuery,
+                ErrorLayer::BadInput,
+                format!("Invalid pagination cursor: {}", cursor),
+            )
+            .with_hint("Cursors must be passed exactly as given by `next_cursor`."),
+        )
+    };
+    if cursor.len() % 2 != 0 || !cursor.is_ascii() {
+        return Err(bad_cursor());
+    }
+    let bytes = (0..cursor.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&cursor[i..i + 2], 16))
+        .collect::<std::result::Result<Vec<u8>, _>>()
+        .map_err(|_| bad_cursor())?;
+    String::from_utf8(bytes).map_err(|_| bad_cursor())
+}
+
+impl PaginationArgs {
+    pub fn is_empty(&self) -> bool {
+        self.offset == 0
+            && self.page_size.is_none()
+            && self.cursor.is_none()
+            && self.sample.is_none()
+    }
+
+    /// Sort `items` by `key` and apply the cursor, offset, page size, and
+    /// sampling, returning the page and information about it.  If no
+    /// pagination was requested, the items are returned unchanged and without
+    /// page information.
+    pub fn paginate<T, F>(&self, items: Vec<T>, key: F) -> Result<(Vec<T>, Option<PageInfo>)>
+    where
+        F: Fn(&T) -> String,
+    {
+        if self.is_empty() {
+            return Ok((items, None));
+        }
+
+        let total = items.len();
+        let mut keyed: Vec<(String, T)> =
+            items.into_iter().map(|item| (key(&item), item)).collect();
+        // The sort is stable, so items with the same key keep their relative
+        // order, although a cursor can't land between them.
+        keyed.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let after_cursor = match &self.cursor {
+            Some(cursor) => {
+                let cursor_key = decode_cursor(cursor)?;
+                keyed.partition_point(|(key, _)| *key <= cursor_key)
+            }
+            None => 0,
+        };
+        let start = (after_cursor + self.offset).min(total);
+        let mut remaining: Vec<(String, T)> = keyed.drain(start..).collect();
+
+        let (page, next_cursor, sampled) = match (self.sample, self.page_size) {
+            (Some(sample), _) if sample < remaining.len() => {
+                let len = remaining.len();
+                let page = remaining
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(i, _)| i * sample / len != (i + 1) * sample / len)
+                    .map(|(_, keyed)| keyed)
+                    .collect();
+                (page, None, true)
+            }
+            (_, Some(page_size)) if page_size < remaining.len() => {
+                remaining.truncate(page_size);
+                let next_cursor = remaining.last().map(|(key, _)| encode_cursor(key));
+                (remaining, next_cursor, false)
+            }
+            _ => (remaining, None, false),
+        };
+
+        let page: Vec<T> = page.into_iter().map(|(_, item)| item).collect();
+        let info = PageInfo {
+            total,
+            offset: start,
+            count: page.len(),
+            next_cursor,
+            sampled,
+        };
+        Ok((page, Some(info)))
+    }
+}
+
+#[cfg(test)]
+fn test_keys(args: &PaginationArgs, items: &[&str]) -> (Vec<String>, PageInfo) {
+    let items = items.iter().map(|s| s.to_string()).collect();
+    let (page, info) = args.paginate(items, |s| s.clone()).unwrap();
+    (page, info.unwrap())
+}
+
+#[test]
+fn test_paginate() {
+    let items = ["e", "a", "d", "b", "c"];
+
+    let unpaged = PaginationArgs::default().paginate(vec![3, 1, 2], |n| n.to_string());
+    assert_eq!(unpaged.unwrap(), (vec![3, 1, 2], None));
+
+    let mut args = PaginationArgs {
+        page_size: Some(2),
+        ..PaginationArgs::default()
+    };
+    let (page, info) = test_keys(&args, &items);
+    assert_eq!(page, vec!["a", "b"]);
+    assert_eq!((info.total, info.offset, info.count), (5, 0, 2));
+
+    args.cursor = info.next_cursor;
+    let (page, info) = test_keys(&args, &items);
+    assert_eq!(page, vec!["c", "d"]);
+    assert_eq!(info.offset, 2);
+
+    // The cursor still works if the result it names has since gone away.
+    let (page, _) = test_keys(&args, &["a", "c", "d", "e"]);
+    assert_eq!(page, vec!["c", "d"]);
+
+    args.cursor = info.next_cursor;
+    let (page, info) = test_keys(&args, &items);
+    assert_eq!(page, vec!["e"]);
+    assert_eq!(info.next_cursor, None);
+
+    args.cursor = Some("nothex".to_string());
+    assert!(args.paginate(vec![1], |n| n.to_string()).is_err());
+}
+
+#[test]
+fn test_sample() {
+    let items: Vec<String> = (0..10).map(|i| format!("{:02}", i)).collect();
+    let args = PaginationArgs {
+        sample: Some(3),
+        ..PaginationArgs::default()
+    };
+    let (page, info) = args.paginate(items.clone(), |s| s.clone()).unwrap();
+    assert_eq!(page, vec!["03", "06", "09"]);
+    let info = info.unwrap();
+    assert!(info.sampled);
+    assert_eq!((info.total, info.count), (10, 3));
+
+    // Sampling fewer results than requested just returns them all.
+    let (page, info) = args.paginate(items[..2].to_vec(), |s| s.clone()).unwrap();
+    assert_eq!(page.len(), 2);
+    assert!(!info.unwrap().sampled);
+}
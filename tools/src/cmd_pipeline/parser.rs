@@ -1,28 +1,97 @@
 use clap::{Parser, Subcommand, ValueEnum};
 
+use super::cmd_annotate::Annotate;
+use super::cmd_annotations::Annotations;
+use super::cmd_api_surface::ApiSurface;
+use super::cmd_audit_external_links::AuditExternalLinks;
 use super::cmd_augment_results::AugmentResults;
+use super::cmd_badge::Badge;
 use super::cmd_batch_render::BatchRender;
+use super::cmd_bug_refs::BugRefs;
 use super::cmd_cat_html::CatHtml;
+use super::cmd_cat_source::CatSource;
+use super::cmd_check_layering::CheckLayering;
+use super::cmd_check_layout_budget::CheckLayoutBudget;
 use super::cmd_compile_results::CompileResults;
+use super::cmd_completions::Completions;
+use super::cmd_convert_lsif::ConvertLsif;
 use super::cmd_crossref_expand::CrossrefExpand;
 use super::cmd_crossref_lookup::CrossrefLookup;
+use super::cmd_describe_file::DescribeFile;
+use super::cmd_diff_file::DiffFile;
+use super::cmd_doc_symbol::DocSymbol;
+use super::cmd_explain::Explain;
+use super::cmd_export_bundle::ExportBundle;
+use super::cmd_export_scip::ExportScip;
+use super::cmd_field_at::FieldAt;
+use super::cmd_file_log::FileLog;
 use super::cmd_filter_analysis::FilterAnalysis;
+use super::cmd_find_duplicates::FindDuplicates;
+use super::cmd_find_examples::FindExamples;
+use super::cmd_find_field_type::FindFieldType;
+use super::cmd_find_overrides::FindOverrides;
+use super::cmd_find_paths::FindPaths;
+use super::cmd_flaky_heatmap::FlakyHeatmap;
 use super::cmd_format_symbols::FormatSymbols;
+use super::cmd_format_table::FormatTable;
 use super::cmd_fuse_crossrefs::FuseCrossrefs;
+use super::cmd_gc_index::GcIndex;
+use super::cmd_generate_tags::GenerateTags;
 use super::cmd_graph::Graph;
+use super::cmd_graph_cycles::GraphCycles;
+use super::cmd_graph_metrics::GraphMetrics;
+use super::cmd_group_hits::GroupHits;
+use super::cmd_help_json::HelpJson;
+use super::cmd_hex_dump::HexDump;
+use super::cmd_hook_results::HookResults;
+use super::cmd_ingest_query_logs::IngestQueryLogs;
+use super::cmd_ingest_test_stats::IngestTestStats;
+use super::cmd_js_module_graph::JsModuleGraph;
 use super::cmd_jumpref_lookup::JumprefLookup;
+use super::cmd_l10n_xref::L10nXref;
+use super::cmd_language_stats::LanguageStats;
+use super::cmd_line_history::LineHistory;
+use super::cmd_load_graph::LoadGraph;
+use super::cmd_lock_report::LockReport;
+use super::cmd_map_script::MapScript;
 use super::cmd_merge_analyses::MergeAnalyses;
+use super::cmd_notify::Notify;
+use super::cmd_offsetof::Offsetof;
+use super::cmd_ownership_report::OwnershipReport;
+use super::cmd_permalinks::Permalinks;
+use super::cmd_popular_queries::PopularQueries;
+use super::cmd_precompute::Precompute;
 use super::cmd_prod_filter::ProductionFilter;
 use super::cmd_query::Query;
+use super::cmd_reachable_from::ReachableFrom;
+use super::cmd_refcount_cycles::RefcountCycles;
+use super::cmd_refine::Refine;
 use super::cmd_render::Render;
+use super::cmd_run_post_index_hooks::RunPostIndexHooks;
+use super::cmd_run_spec::RunSpec;
+use super::cmd_rust_crate_graph::RustCrateGraph;
+use super::cmd_save_graph::SaveGraph;
 use super::cmd_search::Search;
+use super::cmd_search_attributes::SearchAttributes;
 use super::cmd_search_files::SearchFiles;
 use super::cmd_search_identifiers::SearchIdentifiers;
 use super::cmd_search_text::SearchText;
+use super::cmd_share::Share;
 use super::cmd_show_html::ShowHtml;
+use super::cmd_show_index_info::ShowIndexInfo;
+use super::cmd_sizeof::Sizeof;
+use super::cmd_static_inits::StaticInits;
+use super::cmd_structured_columns::StructuredColumns;
+use super::cmd_symbol_set::SymbolSet;
+use super::cmd_tests_for_symbol::TestsForSymbol;
+use super::cmd_todos::Todos;
 use super::cmd_tokenize_source::TokenizeSource;
 use super::cmd_traverse::Traverse;
+use super::cmd_trend::Trend;
+use super::cmd_tui::Tui;
+use super::cmd_verify_index::VerifyIndex;
 use super::cmd_webtest::Webtest;
+use super::output_sink::OutputFileFormat;
 
 #[derive(Clone, Debug, PartialEq, ValueEnum)]
 pub enum OutputFormat {
@@ -56,33 +125,136 @@ pub struct ToolOpts {
     #[clap(long, short, value_parser, value_enum, default_value = "concise")]
     pub output_format: OutputFormat,
 
+    /// Write the pipeline's final value to this file instead of printing it.
+    #[clap(long, value_parser)]
+    pub output_file: Option<String>,
+
+    /// The format to write `--output-file` in.  Inferred from the file's
+    /// extension if not given.
+    #[clap(long, value_parser, value_enum)]
+    pub output_file_format: Option<OutputFileFormat>,
+
+    /// Report how long each pipeline stage took and the symbols, bytes, and
+    /// cache hits it used.
+    #[clap(long, value_parser)]
+    pub timing: bool,
+
+    /// How many times to retry transient failures when querying a remote
+    /// server.
+    #[clap(long, value_parser, default_value = "3")]
+    pub remote_retries: u32,
+
+    /// The timeout in seconds for each request to a remote server.
+    #[clap(long, value_parser, default_value = "30")]
+    pub remote_timeout: u64,
+
+    /// The total time in seconds to spend on a request to a remote server,
+    /// including retries.
+    #[clap(long, value_parser, default_value = "120")]
+    pub remote_budget: u64,
+
+    /// How many consecutive failures of a remote endpoint make us stop
+    /// contacting it for a while.
+    #[clap(long, value_parser, default_value = "5")]
+    pub remote_breaker_threshold: u32,
+
     #[clap(subcommand)]
     pub cmd: Command,
 }
 
 #[derive(Debug, Subcommand)]
 pub enum Command {
+    Annotate(Annotate),
+    Annotations(Annotations),
+    ApiSurface(ApiSurface),
+    AuditExternalLinks(AuditExternalLinks),
     AugmentResults(AugmentResults),
+    Badge(Badge),
     BatchRender(BatchRender),
+    BugRefs(BugRefs),
     CatHtml(CatHtml),
+    CatSource(CatSource),
+    CheckLayering(CheckLayering),
+    CheckLayoutBudget(CheckLayoutBudget),
+    Completions(Completions),
+    ConvertLsif(ConvertLsif),
     CrossrefExpand(CrossrefExpand),
     CrossrefLookup(CrossrefLookup),
+    DescribeFile(DescribeFile),
+    DiffFile(DiffFile),
+    DocSymbol(DocSymbol),
+    Explain(Explain),
+    ExportBundle(ExportBundle),
+    ExportScip(ExportScip),
+    FieldAt(FieldAt),
+    FileLog(FileLog),
     FilterAnalysis(FilterAnalysis),
+    FindDuplicates(FindDuplicates),
+    FindExamples(FindExamples),
+    FindFieldType(FindFieldType),
+    FindOverrides(FindOverrides),
+    FindPaths(FindPaths),
+    FlakyHeatmap(FlakyHeatmap),
     FormatSymbols(FormatSymbols),
+    FormatTable(FormatTable),
+    GcIndex(GcIndex),
+    GenerateTags(GenerateTags),
     Graph(Graph),
+    GraphCycles(GraphCycles),
+    GraphMetrics(GraphMetrics),
+    GroupHits(GroupHits),
+    HelpJson(HelpJson),
+    HexDump(HexDump),
+    HookResults(HookResults),
+    IngestQueryLogs(IngestQueryLogs),
+    IngestTestStats(IngestTestStats),
+    JsModuleGraph(JsModuleGraph),
     JumprefLookup(JumprefLookup),
+    L10nXref(L10nXref),
+    LanguageStats(LanguageStats),
+    LineHistory(LineHistory),
+    LoadGraph(LoadGraph),
+    LockReport(LockReport),
+    MapScript(MapScript),
     MergeAnalyses(MergeAnalyses),
+    Notify(Notify),
+    Offsetof(Offsetof),
+    OwnershipReport(OwnershipReport),
+    Permalinks(Permalinks),
+    PopularQueries(PopularQueries),
+    Precompute(Precompute),
     ProductionFilter(ProductionFilter),
     Query(Query),
+    ReachableFrom(ReachableFrom),
+    RefcountCycles(RefcountCycles),
+    Refine(Refine),
     Render(Render),
+    RunPostIndexHooks(RunPostIndexHooks),
+    RunSpec(RunSpec),
+    RustCrateGraph(RustCrateGraph),
+    SaveGraph(SaveGraph),
     Search(Search),
+    SearchAttributes(SearchAttributes),
     SearchFiles(SearchFiles),
     SearchIdentifiers(SearchIdentifiers),
     SearchText(SearchText),
+    Share(Share),
     ShowHtml(ShowHtml),
+    ShowIndexInfo(ShowIndexInfo),
+    Sizeof(Sizeof),
+    StaticInits(StaticInits),
+    StructuredColumns(StructuredColumns),
+    TestsForSymbol(TestsForSymbol),
+    Todos(Todos),
     TokenizeSource(TokenizeSource),
     Traverse(Traverse),
+    Trend(Trend),
+    Tui(Tui),
+    VerifyIndex(VerifyIndex),
     Webtest(Webtest),
+    /// A command provided by a plugin; see `plugins.rs`.
+    #[clap(external_subcommand)]
+    External(Vec<String>),
 }
 
 #[derive(Debug, Parser)]
@@ -95,4 +267,5 @@ pub struct JunctionOpts {
 pub enum JunctionCommand {
     CompileResults(CompileResults),
     FuseCrossrefs(FuseCrossrefs),
+    SymbolSet(SymbolSet),
 }
@@ -0,0 +1,177 @@
+use std::collections::BTreeMap;
+
+use clap::Args;
+use regex::Regex;
+
+use super::transforms::path_glob_transform;
+use crate::{
+    abstract_server::{ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError},
+    file_format::analysis::FileClassification,
+};
+
+/// Path filtering arguments shared by commands that want to constrain their
+/// results by path.  Commands should `#[clap(flatten)]` this into their args
+/// and then call `PathFilterArgs::compile` once at the start of `execute`.
+///
+/// Each pattern is a searchfox path glob (see `path_glob_transform`) which is
+/// unanchored unless it starts with `^` and/or ends with `$`.  This matches how
+/// people already write path constraints in the search UI.
+#[derive(Debug, Default, Args)]
+pub struct PathFilterArgs {
+    /// Only include paths matching one of these patterns.
+    #[clap(long = "include-path", value_parser)]
+    pub include_path: Vec<String>,
+
+    /// Exclude paths matching any of these patterns.  Exclusions win over
+    /// inclusions.
+    #[clap(long = "exclude-path", value_parser)]
+    pub exclude_path: Vec<String>,
+
+    /// Only include paths in one of these named path sets from the tree's
+    /// `path_sets` config, like "tests" or "third_party".
+    #[clap(long = "path-set", value_parser)]
+    pub path_set: Vec<String>,
+
+    /// Exclude paths in any of these named path sets from the tree's
+    /// `path_sets` config.
+    #[clap(long = "exclude-path-set", value_parser)]
+    pub exclude_path_set: Vec<String>,
+
+    /// Exclude files classified as generated at indexing time.
+    #[clap(long, value_parser)]
+    pub no_generated: bool,
+
+    /// Exclude files classified as vendored/third-party at indexing time.
+    #[clap(long, value_parser)]
+    pub no_third_party: bool,
+
+    /// Exclude files classified as tests at indexing time.
+    #[clap(long, value_parser)]
+    pub no_tests: bool,
+}
+
+/// Compiled form of `PathFilterArgs`.
+#[derive(Debug, Default)]
+pub struct PathFilter {
+    include: Vec<Regex>,
+    exclude: Vec<Regex>,
+    exclude_classifications: Vec<FileClassification>,
+}
+
+fn pattern_to_regex(pattern: &str) -> Result<Regex> {
+    Ok(Regex::new(&path_glob_transform(pattern))?)
+}
+
+fn expand_path_sets(
+    names: &[String],
+    path_sets: &BTreeMap<String, Vec<String>>,
+) -> Result<Vec<Regex>> {
+    let mut regexes = vec![];
+    for name in names {
+        let patterns = path_sets.get(name).ok_or_else(|| {
+            ServerError::StickyProblem(ErrorDetails::new(
+                ErrorKind::NotFound,
+                ErrorLayer::BadInput,
+                format!("Unknown path set: {}", name),
+            ))
+        })?;
+        for pattern in patterns {
+            // Path sets come from config, so a bad pattern is a config problem
+            // and not the user's fault.
+            let re = pattern_to_regex(pattern).map_err(|_| {
+                ServerError::StickyProblem(ErrorDetails::new(
+                    ErrorKind::Internal,
+                    ErrorLayer::ConfigLayer,
+                    format!("Bad pattern in path set {}: {}", name, pattern),
+                ))
+            })?;
+            regexes.push(re);
+        }
+    }
+    Ok(regexes)
+}
+
+impl PathFilterArgs {
+    /// Compile the arguments into a `PathFilter`, resolving any named path sets
+    /// against the provided `path_sets` map, which should come from
+    /// `TreeInfo::path_sets`.
+    pub fn compile(&self, path_sets: &BTreeMap<String, Vec<String>>) -> Result<PathFilter> {
+        let mut include = vec![];
+        for pattern in &self.include_path {
+            include.push(pattern_to_regex(pattern)?);
+        }
+        include.extend(expand_path_sets(&self.path_set, path_sets)?);
+
+        let mut exclude = vec![];
+        for pattern in &self.exclude_path {
+            exclude.push(pattern_to_regex(pattern)?);
+        }
+        exclude.extend(expand_path_sets(&self.exclude_path_set, path_sets)?);
+
+        let mut exclude_classifications = vec![];
+        if self.no_generated {
+            exclude_classifications.push(FileClassification::Generated);
+        }
+        if self.no_third_party {
+            exclude_classifications.push(FileClassification::ThirdParty);
+        }
+        if self.no_tests {
+            exclude_classifications.push(FileClassification::Test);
+        }
+
+        Ok(PathFilter {
+            include,
+            exclude,
+            exclude_classifications,
+        })
+    }
+}
+
+impl PathFilter {
+    /// Returns true if the filter doesn't actually constrain anything, letting
+    /// callers skip work.
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty()
+            && self.exclude.is_empty()
+            && self.exclude_classifications.is_empty()
+    }
+
+    /// Like `matches` but also checks the file's classification.  Commands
+    /// should use this whenever they have a classification available.
+    pub fn matches_classified(&self, path: &str, classification: FileClassification) -> bool {
+        !self.exclude_classifications.contains(&classification) && self.matches(path)
+    }
+
+    pub fn matches(&self, path: &str) -> bool {
+        if self.exclude.iter().any(|re| re.is_match(path)) {
+            return false;
+        }
+        self.include.is_empty() || self.include.iter().any(|re| re.is_match(path))
+    }
+}
+
+#[test]
+fn test_path_filter() {
+    let mut path_sets = BTreeMap::new();
+    path_sets.insert("tests".to_string(), vec!["/tests/".to_string()]);
+
+    let args = PathFilterArgs {
+        include_path: vec!["^dom/".to_string()],
+        exclude_path: vec!["*.h$".to_string()],
+        exclude_path_set: vec!["tests".to_string()],
+        no_generated: true,
+        ..PathFilterArgs::default()
+    };
+    let filter = args.compile(&path_sets).unwrap();
+    assert!(filter.matches("dom/base/Element.cpp"));
+    assert!(!filter.matches("dom/base/Element.h"));
+    assert!(!filter.matches("dom/base/tests/test_element.cpp"));
+    assert!(!filter.matches("js/src/jsapi.cpp"));
+    assert!(!filter.matches_classified("dom/base/Gen.cpp", FileClassification::Generated));
+
+    let bad_set = PathFilterArgs {
+        path_set: vec!["nope".to_string()],
+        ..PathFilterArgs::default()
+    };
+    assert!(bad_set.compile(&path_sets).is_err());
+}
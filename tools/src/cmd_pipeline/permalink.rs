@@ -0,0 +1,130 @@
+use serde_json::{Map, Value};
+
+use crate::url_encode_path::url_encode_path;
+
+/// Builds canonical searchfox URLs for paths and symbols in a given tree,
+/// pinned to a specific revision when one is known.
+pub struct PermalinkBuilder {
+    pub base_url: String,
+    pub tree_name: String,
+    pub revision: Option<String>,
+}
+
+/// Keys that hold a searchfox path in our structured outputs.
+const PATH_KEYS: &[&str] = &["path"];
+/// Keys that hold a 1-based line number; `lno` is used by crossref, `lineno`
+/// by the pipeline's own types, and `line_num` by `TextMatchInFile`.
+const LINE_KEYS: &[&str] = &["lno", "lineno", "line_num"];
+/// Keys that hold a (possibly comma-delimited) searchfox symbol.
+const SYMBOL_KEYS: &[&str] = &["sym", "symbol"];
+
+impl PermalinkBuilder {
+    pub fn file_link(&self, path: &str, lineno: Option<u64>) -> String {
+        let encoded_path = url_encode_path(path);
+        let mut link = match &self.revision {
+            Some(rev) => format!(
+                "{}/{}/rev/{}/{}",
+                self.base_url, self.tree_name, rev, encoded_path
+            ),
+            None => format!(
+                "{}/{}/source/{}",
+                self.base_url, self.tree_name, encoded_path
+            ),
+        };
+        if let Some(lineno) = lineno {
+            link.push_str(&format!("#{}", lineno));
+        }
+        link
+    }
+
+    pub fn symbol_link(&self, symbol: &str) -> String {
+        format!(
+            "{}/{}/search?q=symbol:{}",
+            self.base_url,
+            self.tree_name,
+            urlencoding::encode(symbol)
+        )
+    }
+
+    /// Recursively walk `value`, adding a "permalink" key alongside every path
+    /// (using any line number in the same object, or a line number with the
+    /// path inherited from an enclosing object) and a "symbol_permalink" key
+    /// alongside every symbol.
+    pub fn augment(&self, value: &mut Value) {
+        self.augment_with_path(value, None);
+    }
+
+    fn augment_with_path(&self, value: &mut Value, inherited_path: Option<&str>) {
+        match value {
+            Value::Object(obj) => {
+                let path = first_str(obj, PATH_KEYS)
+                    .or(inherited_path)
+                    .map(|p| p.to_string());
+                let lineno = LINE_KEYS.iter().find_map(|k| obj.get(*k)?.as_u64());
+                if let Some(path) = &path {
+                    if obj.contains_key("path") || lineno.is_some() {
+                        let link = self.file_link(path, lineno);
+                        obj.insert("permalink".to_string(), Value::String(link));
+                    }
+                }
+                if let Some(symbol) = first_str(obj, SYMBOL_KEYS) {
+                    let link = self.symbol_link(symbol);
+                    obj.insert("symbol_permalink".to_string(), Value::String(link));
+                }
+                for (key, child) in obj.iter_mut() {
+                    if key == "permalink" || key == "symbol_permalink" {
+                        continue;
+                    }
+                    self.augment_with_path(child, path.as_deref());
+                }
+            }
+            Value::Array(arr) => {
+                for child in arr.iter_mut() {
+                    self.augment_with_path(child, inherited_path);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn first_str<'a>(obj: &'a Map<String, Value>, keys: &[&str]) -> Option<&'a str> {
+    keys.iter().find_map(|k| obj.get(*k)?.as_str())
+}
+
+#[test]
+fn test_augment_permalinks() {
+    let builder = PermalinkBuilder {
+        base_url: "https://searchfox.org".to_string(),
+        tree_name: "mozilla-central".to_string(),
+        revision: Some("abc123".to_string()),
+    };
+    let mut value = serde_json::json!({
+        "symbol": "_ZN3FooC1Ev",
+        "uses": [
+            { "path": "dom/Foo Bar.cpp", "lines": [{ "lno": 12, "line": "Foo();" }] }
+        ]
+    });
+    builder.augment(&mut value);
+    assert_eq!(
+        value["symbol_permalink"],
+        "https://searchfox.org/mozilla-central/search?q=symbol:_ZN3FooC1Ev"
+    );
+    assert_eq!(
+        value["uses"][0]["permalink"],
+        "https://searchfox.org/mozilla-central/rev/abc123/dom/Foo%20Bar.cpp"
+    );
+    assert_eq!(
+        value["uses"][0]["lines"][0]["permalink"],
+        "https://searchfox.org/mozilla-central/rev/abc123/dom/Foo%20Bar.cpp#12"
+    );
+
+    let unpinned = PermalinkBuilder {
+        revision: None,
+        ..builder
+    };
+    assert_eq!(
+        unpinned.file_link("a/b.h", Some(3)),
+        "https://searchfox.org/mozilla-central/source/a/b.h#3"
+    );
+}
@@ -0,0 +1,161 @@
+//! Registration of pipeline commands implemented outside of this crate, so that
+//! downstream deployments can add their own commands (ex: company-specific
+//! audits) without patching it.
+//!
+//! A deployment builds its own binary that calls `register_plugin` for each of
+//! its commands before building any pipelines.  Plugin commands are parsed as
+//! clap external subcommands of `ToolOpts`, so global options like `--server`
+//! keep working, and they show up in `help-json`, `completions`, and `explain`
+//! alongside the built-in commands.
+
+use std::sync::{Arc, RwLock};
+
+use async_trait::async_trait;
+use clap::{ArgMatches, Command as ClapCommand, CommandFactory};
+use lazy_static::lazy_static;
+
+use super::{
+    builder::CommandSafetyLevel,
+    interface::{PipelineCommand, PipelineValues},
+    parser::ToolOpts,
+    signatures::{CommandSignature, ANY},
+};
+use crate::abstract_server::{
+    AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError,
+};
+
+/// A pipeline command provided by a downstream deployment.
+pub trait PipelineCommandPlugin: Send + Sync {
+    /// The clap definition of the command.  Its name is the name used in
+    /// pipelines and it must not collide with a built-in command.
+    fn command(&self) -> ClapCommand;
+
+    /// The `PipelineValues` variants the command accepts and produces.  Input
+    /// that doesn't match is rejected before the command is executed.
+    fn signature(&self) -> CommandSignature;
+
+    /// Whether the command may be used in pipelines built from web queries.
+    fn web_safe(&self) -> bool {
+        false
+    }
+
+    /// Create the command from its parsed arguments.
+    fn build(&self, matches: &ArgMatches) -> Result<Box<dyn PipelineCommand + Send + Sync>>;
+}
+
+lazy_static! {
+    static ref PLUGINS: RwLock<Vec<Arc<dyn PipelineCommandPlugin>>> = RwLock::new(vec![]);
+}
+
+fn plugin_problem(kind: ErrorKind, layer: ErrorLayer, message: String) -> ServerError {
+    ServerError::StickyProblem(ErrorDetails::new(kind, layer, message))
+}
+
+/// Register a plugin command, failing if its name is already taken.
+pub fn register_plugin(plugin: Arc<dyn PipelineCommandPlugin>) -> Result<()> {
+    let name = plugin.command().get_name().to_string();
+    if ToolOpts::command().find_subcommand(&name).is_some() || find_plugin(&name).is_some() {
+        return Err(plugin_problem(
+            ErrorKind::Internal,
+            ErrorLayer::ConfigLayer,
+            format!("A pipeline command named {} already exists", name),
+        ));
+    }
+    PLUGINS.write().unwrap().push(plugin);
+    Ok(())
+}
+
+pub fn find_plugin(name: &str) -> Option<Arc<dyn PipelineCommandPlugin>> {
+    PLUGINS
+        .read()
+        .unwrap()
+        .iter()
+        .find(|plugin| plugin.command().get_name() == name)
+        .cloned()
+}
+
+/// The clap definitions of all registered plugins.
+pub fn plugin_commands() -> Vec<ClapCommand> {
+    PLUGINS
+        .read()
+        .unwrap()
+        .iter()
+        .map(|plugin| plugin.command())
+        .collect()
+}
+
+/// `ToolOpts::command()` with the plugin commands added as subcommands, for
+/// help and completion generation.
+pub fn tool_command_with_plugins() -> ClapCommand {
+    ToolOpts::command().subcommands(plugin_commands())
+}
+
+/// Create a command from an external subcommand's args, where the first arg
+/// is the command name.
+pub fn fab_plugin_command(
+    args: Vec<String>,
+    safety: CommandSafetyLevel,
+) -> Result<Box<dyn PipelineCommand + Send + Sync>> {
+    let name = args.first().cloned().unwrap_or_default();
+    let plugin = find_plugin(&name).ok_or_else(|| {
+        plugin_problem(
+            ErrorKind::BadQuery,
+            ErrorLayer::BadInput,
+            format!("Unknown pipeline command: {}", name),
+        )
+    })?;
+    if matches!(safety, CommandSafetyLevel::WebSafety) && !plugin.web_safe() {
+        return Err(plugin_problem(
+            ErrorKind::BadQuery,
+            ErrorLayer::BadInput,
+            "Command not allowed in this context".to_string(),
+        ));
+    }
+
+    let matches = plugin
+        .command()
+        .no_binary_name(true)
+        .try_get_matches_from(&args[1..])
+        .map_err(|e| plugin_problem(ErrorKind::BadQuery, ErrorLayer::BadInput, e.to_string()))?;
+
+    Ok(Box::new(PluginInvocation {
+        name,
+        signature: plugin.signature(),
+        command: plugin.build(&matches)?,
+    }))
+}
+
+/// Wraps a plugin's command to check its input against its signature.
+#[derive(Debug)]
+struct PluginInvocation {
+    name: String,
+    signature: CommandSignature,
+    command: Box<dyn PipelineCommand + Send + Sync>,
+}
+
+#[async_trait]
+impl PipelineCommand for PluginInvocation {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let inputs = self.signature.inputs;
+        let variant = input.variant_name();
+        // An empty input list means the command ignores its input.
+        if !inputs.is_empty() && !inputs.contains(&ANY) && !inputs.contains(&variant) {
+            return Err(plugin_problem(
+                ErrorKind::BadQuery,
+                ErrorLayer::ConfigLayer,
+                format!(
+                    "{} needs a {} but got a {}",
+                    self.name,
+                    inputs.join(" or "),
+                    variant
+                ),
+            ));
+        }
+
+        self.command.execute(server, input).await
+    }
+}
@@ -0,0 +1,162 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::interface::SymbolCrossrefInfo;
+
+/// Named sets of ranking weights that can be selected via `--rank-profile`.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum RankProfile {
+    /// Don't rank; preserve the order results were produced in.
+    None,
+    /// A general purpose blend of all signals.
+    Balanced,
+    /// Strongly prefer symbols that have definitions in normal, shallow paths.
+    Definitions,
+    /// Strongly prefer symbols with many uses across the tree and that people
+    /// ask about often.
+    Popular,
+}
+
+/// Weights for each of our ranking signals.  Every signal is normalized so
+/// that larger is better before the weight is applied, and the score is the
+/// weighted sum.
+#[derive(Clone, Debug)]
+pub struct RankWeights {
+    /// How closely the symbol matched what was typed; see
+    /// `SymbolQuality::numeric_rank`.
+    pub quality: f64,
+    /// Whether the symbol has a definition in the tree at all.
+    pub has_definition: f64,
+    /// How shallow the definition's path is.
+    pub path_depth: f64,
+    /// The (log-scaled) number of uses of the symbol in the tree.
+    pub popularity: f64,
+    /// The (log-scaled) number of times people asked about the symbol per the
+    /// ingested query logs; see `file_format/query_popularity.rs`.
+    pub query_popularity: f64,
+    /// Whether the definition is in non-test, non-generated, non-third-party
+    /// code.
+    pub normal_code: f64,
+}
+
+impl RankProfile {
+    pub fn weights(&self) -> Option<RankWeights> {
+        match self {
+            RankProfile::None => None,
+            RankProfile::Balanced => Some(RankWeights {
+                quality: 2.0,
+                has_definition: 1.0,
+                path_depth: 0.25,
+                popularity: 0.5,
+                query_popularity: 0.5,
+                normal_code: 1.0,
+            }),
+            RankProfile::Definitions => Some(RankWeights {
+                quality: 1.0,
+                has_definition: 4.0,
+                path_depth: 0.5,
+                popularity: 0.1,
+                query_popularity: 0.1,
+                normal_code: 2.0,
+            }),
+            RankProfile::Popular => Some(RankWeights {
+                quality: 1.0,
+                has_definition: 0.5,
+                path_depth: 0.1,
+                popularity: 2.0,
+                query_popularity: 2.0,
+                normal_code: 0.5,
+            }),
+        }
+    }
+}
+
+/// A symbol's ranking score along with the (unweighted) signals that went into
+/// it, so that UIs can explain the ordering.
+#[derive(Clone, Serialize)]
+pub struct RankScore {
+    pub score: f64,
+    pub signals: BTreeMap<&'static str, f64>,
+}
+
+fn count_lines(crossref_info: &Value, kind: &str) -> usize {
+    match crossref_info.get(kind) {
+        Some(Value::Array(path_containers)) => path_containers
+            .iter()
+            .map(|pc| pc["lines"].as_array().map_or(0, |lines| lines.len()))
+            .sum(),
+        _ => 0,
+    }
+}
+
+pub fn score_symbol(
+    info: &SymbolCrossrefInfo,
+    query_hits: u64,
+    weights: &RankWeights,
+) -> RankScore {
+    let def_path = info
+        .crossref_info
+        .pointer("/defs/0/path")
+        .and_then(|p| p.as_str());
+    // Crossref only emits the classification for non-normal files.
+    let def_is_normal = def_path.is_some()
+        && info
+            .crossref_info
+            .pointer("/defs/0/classification")
+            .is_none();
+
+    let mut signals = BTreeMap::new();
+    signals.insert("quality", -(info.quality.numeric_rank() as f64));
+    signals.insert("has_definition", if def_path.is_some() { 1.0 } else { 0.0 });
+    signals.insert(
+        "path_depth",
+        -(def_path.map_or(0, |path| path.matches('/').count()) as f64),
+    );
+    signals.insert(
+        "popularity",
+        (1.0 + count_lines(&info.crossref_info, "uses") as f64).ln(),
+    );
+    signals.insert("query_popularity", (1.0 + query_hits as f64).ln());
+    signals.insert("normal_code", if def_is_normal { 1.0 } else { 0.0 });
+
+    let score = signals["quality"] * weights.quality
+        + signals["has_definition"] * weights.has_definition
+        + signals["path_depth"] * weights.path_depth
+        + signals["popularity"] * weights.popularity
+        + signals["query_popularity"] * weights.query_popularity
+        + signals["normal_code"] * weights.normal_code;
+
+    RankScore { score, signals }
+}
+
+/// Score and stably sort the given symbols best-first according to the
+/// profile, using `query_hits` to look up how often each symbol was asked
+/// about (ex: `AbstractServer::symbol_query_hits`).  This is a no-op for
+/// `RankProfile::None`.
+pub fn rank_symbol_crossref_infos(
+    infos: &mut [SymbolCrossrefInfo],
+    profile: RankProfile,
+    query_hits: impl Fn(&str) -> u64,
+) {
+    let Some(weights) = profile.weights() else {
+        return;
+    };
+    for info in infos.iter_mut() {
+        // The logs record both symbols and the identifiers people typed.
+        let pretty = info.get_pretty();
+        let mut hits = query_hits(info.symbol.as_str());
+        if pretty != info.symbol {
+            hits += query_hits(pretty.as_str());
+        }
+        info.score = Some(score_symbol(info, hits, &weights));
+    }
+    infos.sort_by(|a, b| {
+        let a_score = a.score.as_ref().map_or(0.0, |s| s.score);
+        let b_score = b.score.as_ref().map_or(0.0, |s| s.score);
+        b_score.partial_cmp(&a_score).unwrap_or(Ordering::Equal)
+    });
+}
@@ -0,0 +1,152 @@
+use crate::{
+    abstract_server::{AbstractServer, Result},
+    file_format::analysis::{parse_structured_value, AnalysisStructured, StructuredFieldInfo},
+};
+
+/// The layout-relevant parts of one platform's variant of a structured record.
+pub struct PlatformLayout {
+    pub size_bytes: Option<u32>,
+    pub own_vf_ptr_bytes: Option<u32>,
+    pub fields: Vec<StructuredFieldInfo>,
+    /// The superclass symbols and their offsets.
+    pub supers: Vec<(String, u32)>,
+}
+
+/// Pick the variant of the record for the platform, falling back to the
+/// record's platform-independent data.
+pub fn layout_for_platform(record: &AnalysisStructured, platform: Option<&str>) -> PlatformLayout {
+    let per_platform = record.per_platform();
+    let variant = per_platform
+        .iter()
+        .find(|(p, _)| p.as_deref() == platform)
+        .or_else(|| per_platform.iter().find(|(p, _)| p.is_none()))
+        .map_or(record, |(_, variant)| *variant);
+    PlatformLayout {
+        size_bytes: variant.size_bytes,
+        own_vf_ptr_bytes: variant.own_vf_ptr_bytes,
+        fields: variant.fields.clone(),
+        supers: variant
+            .supers
+            .iter()
+            .map(|sup| (sup.sym.to_string(), sup.offset_bytes))
+            .collect(),
+    }
+}
+
+/// The platforms the record has layouts for, with None for records without
+/// per-platform data.
+pub fn record_platforms(record: &AnalysisStructured) -> Vec<Option<String>> {
+    record
+        .per_platform()
+        .into_iter()
+        .map(|(platform, _)| platform)
+        .collect()
+}
+
+/// The unqualified name of the field, ex: "mRect" for "nsIFrame::mRect".
+pub fn field_name(field: &StructuredFieldInfo) -> &str {
+    field
+        .pretty
+        .rsplit("::")
+        .next()
+        .unwrap_or(field.pretty.as_str())
+}
+
+/// The total bytes of the holes between the record's own fields and the bytes
+/// of padding after its last field.  The space before the first field is taken
+/// up by superclasses and the vtable pointer, so it doesn't count as a hole.
+pub fn own_holes(layout: &PlatformLayout) -> (u32, u32) {
+    let mut fields: Vec<&StructuredFieldInfo> = layout
+        .fields
+        .iter()
+        // Zero-size entries, like `[[no_unique_address]]` members of empty
+        // types, share their offset with other fields.
+        .filter(|field| field.size_bytes != Some(0))
+        .collect();
+    fields.sort_by_key(|field| {
+        (
+            field.offset_bytes,
+            field.bit_positions.as_ref().map_or(0, |pos| pos.begin),
+        )
+    });
+
+    let mut holes = 0;
+    let mut last_end = None;
+    for field in fields {
+        if let Some(last_end) = last_end {
+            if field.offset_bytes > last_end {
+                holes += field.offset_bytes - last_end;
+            }
+        }
+        let end = match (&field.bit_positions, field.size_bytes) {
+            (Some(pos), _) => field.offset_bytes + (pos.begin + pos.width + 7) / 8,
+            (None, Some(size)) => field.offset_bytes + size,
+            (None, None) => field.offset_bytes,
+        };
+        // Never move the end backwards for fields that reuse space.
+        last_end = Some(last_end.map_or(end, |last_end: u32| last_end.max(end)));
+    }
+
+    let padding = match (layout.size_bytes, last_end) {
+        (Some(size), Some(last_end)) if size > last_end => size - last_end,
+        _ => 0,
+    };
+    (holes, padding)
+}
+
+pub async fn load_record(
+    server: &(dyn AbstractServer + Send + Sync),
+    sym: &str,
+) -> Result<Option<AnalysisStructured>> {
+    let info = server.crossref_lookup_keys(sym, &["meta"]).await?;
+    if !info["meta"].is_object() {
+        return Ok(None);
+    }
+    Ok(parse_structured_value(info["meta"].clone()).ok())
+}
+
+/// Find the symbols of the records with layouts for the class, which is either
+/// a symbol or the pretty name of a record.
+pub async fn lookup_record_syms(
+    server: &(dyn AbstractServer + Send + Sync),
+    class: &str,
+) -> Result<Vec<String>> {
+    let info = server.crossref_lookup_keys(class, &["meta"]).await?;
+    if info["meta"].is_object() {
+        return Ok(vec![class.to_string()]);
+    }
+
+    let mut syms = vec![];
+    for (sym, pretty) in server.search_identifiers(class, true, false, 100).await? {
+        if pretty.as_str() != class {
+            continue;
+        }
+        let info = server.crossref_lookup_keys(&sym, &["meta"]).await?;
+        if info["meta"]["fields"].is_array() || info["meta"]["variants"].is_array() {
+            syms.push(sym.to_string());
+        }
+    }
+    Ok(syms)
+}
+
+#[test]
+fn test_own_holes() {
+    let fields: Vec<StructuredFieldInfo> = serde_json::from_str(
+        r#"[
+  {"pretty": "Foo::mFlag", "offsetBytes": 8, "sizeBytes": 1},
+  {"pretty": "Foo::mBits", "offsetBytes": 9, "bitPositions": {"begin": 0, "width": 3}},
+  {"pretty": "Foo::mPtr", "offsetBytes": 16, "sizeBytes": 8},
+  {"pretty": "Foo::mEmpty", "offsetBytes": 16, "sizeBytes": 0},
+  {"pretty": "Foo::mCount", "offsetBytes": 24, "sizeBytes": 4}
+]"#,
+    )
+    .unwrap();
+    let layout = PlatformLayout {
+        size_bytes: Some(32),
+        own_vf_ptr_bytes: Some(8),
+        fields,
+        supers: vec![],
+    };
+    // 6 bytes between mBits and mPtr, and 4 bytes after mCount.
+    assert_eq!(own_holes(&layout), (6, 4));
+}
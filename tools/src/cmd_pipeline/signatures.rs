@@ -0,0 +1,288 @@
+use serde::Serialize;
+
+use super::coercion::coercion_for;
+
+/// Placeholder variant name for commands whose output depends on what they end
+/// up running (ex: `query`) or that accept any input (ex: `tui`), which we
+/// treat as compatible with everything.
+pub const ANY: &str = "*";
+
+/// The `PipelineValues` variants a command accepts as input and the variants it
+/// may produce as output.  This is a static approximation maintained by hand
+/// for the `explain` command; the commands themselves remain the source of
+/// truth and will error at runtime on unexpected input.
+///
+/// An empty `inputs` means the command ignores its input.  "Void" in `inputs`
+/// means the command can start a pipeline.
+#[derive(Clone, Debug, Serialize)]
+pub struct CommandSignature {
+    pub inputs: &'static [&'static str],
+    pub outputs: &'static [&'static str],
+}
+
+const fn sig(
+    inputs: &'static [&'static str],
+    outputs: &'static [&'static str],
+) -> CommandSignature {
+    CommandSignature { inputs, outputs }
+}
+
+/// Look up the signature of a pipeline command by its (kebab-case) name as
+/// used on the command line, including registered plugin commands.  Returns
+/// None for unknown commands;
+/// `test_all_commands_have_signatures` ensures this stays in sync with
+/// `Command`.
+pub fn command_signature(name: &str) -> Option<CommandSignature> {
+    Some(match name {
+        "annotate" => sig(
+            &["Void", "SymbolList", "SymbolCrossrefInfoList"],
+            &["JsonValue"],
+        ),
+        "annotations" => sig(
+            &["Void", "SymbolList", "SymbolCrossrefInfoList"],
+            &["SymbolList", "SymbolCrossrefInfoList", "JsonValue"],
+        ),
+        "api-surface" => sig(&[], &["DataTable"]),
+        "audit-external-links" => sig(&["Void", "FileMatches"], &["DataTable"]),
+        "augment-results" => sig(&["FlattenedResultsBundle"], &["FlattenedResultsBundle"]),
+        "badge" => sig(
+            &["Void", "JsonValue", "SymbolCrossrefInfoList"],
+            &["TextFile"],
+        ),
+        "batch-render" => sig(&["BatchGroups"], &["Void"]),
+        "bug-refs" => sig(&[], &["DataTable"]),
+        "cat-html" => sig(&[], &["TextFile"]),
+        "cat-source" => sig(&[], &["JsonValue", "TextFile"]),
+        "check-layering" => sig(&[], &["JsonValue"]),
+        "check-layout-budget" => sig(&[], &["JsonValue"]),
+        "completions" => sig(&[], &["TextFile"]),
+        "convert-lsif" => sig(&[], &["JsonValue"]),
+        "crossref-expand" => sig(&["SymbolCrossrefInfoList"], &["SymbolCrossrefInfoList"]),
+        "crossref-lookup" => sig(&["Void", "SymbolList"], &["SymbolCrossrefInfoList"]),
+        "describe-file" => sig(&["Void", "FileMatches"], &["JsonValue"]),
+        "diff-file" => sig(&[], &["JsonValue"]),
+        "doc-symbol" => sig(&["Void", "SymbolCrossrefInfoList"], &["JsonValueList"]),
+        "explain" => sig(&[], &["JsonValue"]),
+        "export-bundle" => sig(
+            &["Void", "SymbolCrossrefInfoList"],
+            &["JsonValue", "TextFile"],
+        ),
+        "export-scip" => sig(&["Void", "FileMatches"], &["JsonValue"]),
+        "field-at" => sig(&[], &["DataTable"]),
+        "file-log" => sig(&[], &["DataTable"]),
+        "filter-analysis" => sig(&[], &["JsonRecords"]),
+        "find-duplicates" => sig(&["Void", "FileMatches"], &["DataTable"]),
+        "find-examples" => sig(&["SymbolCrossrefInfoList"], &["SymbolExamplesList"]),
+        "find-field-type" => sig(&[], &["SymbolCrossrefInfoList"]),
+        "find-overrides" => sig(
+            &["Void", "SymbolList", "SymbolCrossrefInfoList"],
+            &["JsonValueList"],
+        ),
+        "find-paths" => sig(
+            &["SymbolGraphCollection"],
+            &["JsonValueList", "SymbolGraphCollection"],
+        ),
+        "flaky-heatmap" => sig(&["SymbolCrossrefInfoList"], &["DataTable"]),
+        "format-symbols" => sig(&["SymbolCrossrefInfoList"], &["SymbolTreeTableList"]),
+        "format-table" => sig(
+            &["DataTable"],
+            &["SymbolTreeTableList", "TextFile", "JsonValue"],
+        ),
+        "gc-index" => sig(&[], &["JsonValue"]),
+        "generate-tags" => sig(&["Void", "FileMatches"], &["TextFile"]),
+        "graph" => sig(
+            &["SymbolGraphCollection"],
+            &["SymbolGraphCollection", "GraphResultsBundle", "TextFile"],
+        ),
+        "graph-cycles" => sig(&["SymbolGraphCollection"], &["DataTable", "TextFile"]),
+        "graph-metrics" => sig(&["SymbolGraphCollection"], &["DataTable"]),
+        "group-hits" => sig(
+            &["SymbolCrossrefInfoList", "TextMatches"],
+            &["SymbolHitGroups"],
+        ),
+        "help-json" => sig(&[], &["JsonValue"]),
+        "hex-dump" => sig(&[], &["JsonValue"]),
+        "hook-results" => sig(&[], &["JsonValue"]),
+        "ingest-query-logs" => sig(&[], &["JsonValue"]),
+        "ingest-test-stats" => sig(&[], &["JsonValue"]),
+        "js-module-graph" => sig(&[], &["DataTable", "TextFile"]),
+        "jumpref-lookup" => sig(&["Void", "SymbolList"], &["JsonValueList"]),
+        "l10n-xref" => sig(&[], &["JsonValue", "DataTable"]),
+        "language-stats" => sig(&["Void", "FileMatches"], &["DataTable"]),
+        "line-history" => sig(&[], &["DataTable"]),
+        "load-graph" => sig(&[], &["SymbolGraphCollection"]),
+        "lock-report" => sig(&[], &["DataTable"]),
+        "map-script" => sig(
+            &[
+                "JsonValueList",
+                "SymbolList",
+                "SymbolCrossrefInfoList",
+                "DataTable",
+            ],
+            &[
+                "JsonValueList",
+                "SymbolList",
+                "SymbolCrossrefInfoList",
+                "DataTable",
+            ],
+        ),
+        "merge-analyses" => sig(&[], &["JsonRecords"]),
+        "notify" => sig(&[ANY], &["Void"]),
+        "offsetof" => sig(&[], &["JsonValue"]),
+        "ownership-report" => sig(&["Void", "FileMatches"], &["DataTable"]),
+        "permalinks" => sig(&[ANY], &["JsonValue"]),
+        "popular-queries" => sig(&[], &["DataTable"]),
+        "precompute" => sig(&[], &["JsonValue"]),
+        "production-filter" => sig(
+            &["JsonRecords", "HtmlExcerpts", "JsonValue"],
+            &["JsonRecords", "HtmlExcerpts", "JsonValue"],
+        ),
+        "query" => sig(&[], &[ANY]),
+        "reachable-from" => sig(&["SymbolGraphCollection"], &["DataTable"]),
+        "refcount-cycles" => sig(&[], &["DataTable", "TextFile"]),
+        "refine" => sig(
+            &["TextMatches", "FlattenedResultsBundle", "FileMatches"],
+            &["TextMatches", "FlattenedResultsBundle", "FileMatches"],
+        ),
+        "render" => sig(&[], &["Void"]),
+        "run-post-index-hooks" => sig(&[], &["JsonValue"]),
+        "run-spec" => sig(&[], &[ANY]),
+        "rust-crate-graph" => sig(&[], &["DataTable", "TextFile"]),
+        "save-graph" => sig(&["SymbolGraphCollection"], &["SymbolGraphCollection"]),
+        "search" => sig(&["Void", "JsonValue"], &["JsonValue"]),
+        "search-attributes" => sig(&[], &["SymbolCrossrefInfoList"]),
+        "search-files" => sig(&[], &["FileMatches", "BatchGroups"]),
+        "search-identifiers" => sig(&["Void", "IdentifierList"], &["SymbolList"]),
+        "search-text" => sig(&[], &["TextMatches"]),
+        "share" => sig(&[], &["JsonValue"]),
+        "show-html" => sig(&["JsonRecords"], &["HtmlExcerpts"]),
+        "show-index-info" => sig(&[], &["JsonValue"]),
+        "sizeof" => sig(&["Void", "SymbolCrossrefInfoList"], &["DataTable"]),
+        "static-inits" => sig(&[], &["DataTable"]),
+        "structured-columns" => sig(
+            &["Void", "SymbolList", "SymbolCrossrefInfoList"],
+            &["JsonValueList"],
+        ),
+        "tests-for-symbol" => sig(&["SymbolCrossrefInfoList"], &["DataTable"]),
+        "todos" => sig(&[], &["DataTable"]),
+        "tokenize-source" => sig(&[], &["TextFile", "JsonValueList"]),
+        "traverse" => sig(&["SymbolCrossrefInfoList"], &["SymbolGraphCollection"]),
+        "trend" => sig(&[], &["DataTable"]),
+        "tui" => sig(&[ANY], &["Void"]),
+        "verify-index" => sig(&[], &["JsonValue"]),
+        "webtest" => sig(&[], &["Void"]),
+        _ => return super::plugins::find_plugin(name).map(|plugin| plugin.signature()),
+    })
+}
+
+/// Like `command_signature` but for junction commands.
+pub fn junction_signature(name: &str) -> Option<CommandSignature> {
+    Some(match name {
+        "compile-results" => sig(
+            &["FileMatches", "SymbolCrossrefInfoList", "TextMatches"],
+            &["FlattenedResultsBundle"],
+        ),
+        "fuse-crossrefs" => sig(&["SymbolCrossrefInfoList"], &["SymbolCrossrefInfoList"]),
+        "symbol-set" => sig(&["SymbolCrossrefInfoList"], &["SymbolCrossrefInfoList"]),
+        _ => return None,
+    })
+}
+
+/// Check whether a stage with signature `next` can consume the output of a
+/// stage with signature `prev` (or the start of a pipeline if `prev` is None),
+/// returning a human readable description of the problem if not.
+pub fn check_stage_compat(
+    prev: Option<(&str, &CommandSignature)>,
+    next_name: &str,
+    next: &CommandSignature,
+) -> Option<String> {
+    let Some((prev_name, prev)) = prev else {
+        if next.inputs.is_empty() || next.inputs.contains(&"Void") || next.inputs.contains(&ANY) {
+            return None;
+        }
+        return Some(format!(
+            "{} needs a {} but is the first stage",
+            next_name,
+            next.inputs.join(" or ")
+        ));
+    };
+
+    if next.inputs.is_empty() {
+        if prev.outputs.iter().all(|o| *o == "Void") {
+            return None;
+        }
+        return Some(format!(
+            "{} ignores its input, discarding the output of {}",
+            next_name, prev_name
+        ));
+    }
+
+    let compatible = next.inputs.contains(&ANY)
+        || prev.outputs.iter().any(|o| {
+            *o == ANY || next.inputs.contains(o) || coercion_for(o, next.inputs).is_some()
+        });
+    if compatible {
+        None
+    } else {
+        Some(format!(
+            "{} needs a {} but {} emits {}",
+            next_name,
+            next.inputs.join(" or "),
+            prev_name,
+            prev.outputs.join(" or ")
+        ))
+    }
+}
+
+#[test]
+fn test_check_stage_compat() {
+    let lookup = sig(&["Void", "SymbolList"], &["SymbolCrossrefInfoList"]);
+    let format = sig(&["SymbolCrossrefInfoList"], &["SymbolTreeTableList"]);
+    let cat = sig(&[], &["TextFile"]);
+
+    assert_eq!(check_stage_compat(None, "crossref-lookup", &lookup), None);
+    assert!(check_stage_compat(None, "format-symbols", &format).is_some());
+    assert_eq!(
+        check_stage_compat(
+            Some(("crossref-lookup", &lookup)),
+            "format-symbols",
+            &format
+        ),
+        None
+    );
+    // FileMatches are converted to the symbols defined in the files.
+    let files = sig(&["Void"], &["FileMatches"]);
+    assert_eq!(
+        check_stage_compat(Some(("search-files", &files)), "format-symbols", &format),
+        None
+    );
+    assert_eq!(
+        check_stage_compat(Some(("cat-html", &cat)), "format-symbols", &format),
+        Some(
+            "format-symbols needs a SymbolCrossrefInfoList but cat-html emits TextFile".to_string()
+        )
+    );
+}
+
+#[test]
+fn test_all_commands_have_signatures() {
+    use super::parser::{JunctionOpts, ToolOpts};
+    use clap::CommandFactory;
+
+    for subcommand in ToolOpts::command().get_subcommands() {
+        let name = subcommand.get_name();
+        assert!(
+            command_signature(name).is_some(),
+            "{} has no signature",
+            name
+        );
+    }
+    for subcommand in JunctionOpts::command().get_subcommands() {
+        let name = subcommand.get_name();
+        assert!(
+            junction_signature(name).is_some(),
+            "{} has no signature",
+            name
+        );
+    }
+}
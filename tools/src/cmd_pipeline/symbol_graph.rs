@@ -6,20 +6,21 @@ use dot_structures::*;
 use graphviz_rust::printer::{DotPrinter, PrinterContext};
 use itertools::Itertools;
 use petgraph::{
-    algo::all_simple_paths,
+    algo::{all_simple_paths, tarjan_scc},
     graph::{DefaultIx, NodeIndex},
     Directed, Graph as PetGraph,
 };
 use serde::ser::SerializeStruct;
-use serde::{Serialize, Serializer};
+use serde::{Deserialize, Serialize, Serializer};
 use serde_json::{from_value, json, to_value, Value};
 use tracing::trace;
 use ustr::{ustr, Ustr, UstrMap};
 
 use crate::{
-    abstract_server::{AbstractServer, ErrorDetails, ErrorLayer, Result, ServerError},
+    abstract_server::{AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError},
     file_format::{
-        analysis::AnalysisStructured, analysis_manglings::split_pretty,
+        analysis::{parse_structured_value, AnalysisStructured, FileClassification},
+        analysis_manglings::split_pretty,
         crossref_converter::convert_crossref_value_to_sym_info_rep,
         ontology_mapping::label_to_badge_info,
     },
@@ -104,9 +105,25 @@ pub struct DerivedSymbolInfo {
     /// instead to store the SymbolNodeId of the first target here instead.
     pub effective_subsystem: Option<Ustr>,
     pub depth: u32,
+    /// Why this symbol was included; see `SymbolProvenance`.  This is only
+    /// populated if the owning node set has `record_provenance` set.
+    pub provenance: Option<SymbolProvenance>,
 }
 
-#[derive(Clone, Eq, Ord, PartialEq, PartialOrd)]
+/// Explains why a symbol was included in a graph or table: the symbol we were
+/// processing when we discovered it, the kind of edge we followed from that
+/// symbol, and the resulting depth.  Symbols keep the provenance of the first
+/// path by which they were discovered, and root symbols (the ones that were
+/// directly requested) have no provenance.
+#[derive(Clone, Debug, Serialize)]
+pub struct SymbolProvenance {
+    /// A short description of the edge like "callee", "field", or "super".
+    pub edge: &'static str,
+    pub parent: Ustr,
+    pub depth: u32,
+}
+
+#[derive(Clone, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
 pub struct SymbolBadge {
     // Priority for us to mess with the ordering of badges.
     pub pri: i32,
@@ -165,9 +182,13 @@ impl DerivedSymbolInfo {
     /// a lot we should potentially consider using interior mutability to cache
     /// this or have performed the conversion eagerly upon creation.
     pub fn get_structured(&self) -> Option<AnalysisStructured> {
-        match self.crossref_info.get("meta") {
-            Some(v) => from_value(v.clone()).ok(),
-            _ => None,
+        let meta = self.crossref_info.get("meta")?;
+        match parse_structured_value(meta.clone()) {
+            Ok(structured) => Some(structured),
+            Err(e) => {
+                warn!("Unable to use structured info for {}: {}", self.symbol, e);
+                None
+            }
         }
     }
 
@@ -223,6 +244,15 @@ impl DerivedSymbolInfo {
         }
     }
 
+    /// If this symbol has a definition, return the classification of the file
+    /// the definition lives in.
+    pub fn get_def_classification(&self) -> FileClassification {
+        match self.crossref_info.pointer("/defs/0/classification") {
+            Some(val) => from_value(val.clone()).unwrap_or_default(),
+            _ => FileClassification::Normal,
+        }
+    }
+
     /// If this symbol has a definition, return the definition's line number.
     /// This is intended to assist with lexically ordering fields within a
     /// structure/class.
@@ -233,6 +263,17 @@ impl DerivedSymbolInfo {
         }
     }
 
+    /// If this symbol has a definition, return the crossref "bounds" of the
+    /// definition's identifier.  Note that these are relative to the line with
+    /// leading whitespace trimmed.
+    pub fn get_def_bounds(&self) -> Option<(u32, u32)> {
+        let bounds = self.crossref_info.pointer("/defs/0/lines/0/bounds")?;
+        Some((
+            bounds.get(0)?.as_u64()? as u32,
+            bounds.get(1)?.as_u64()? as u32,
+        ))
+    }
+
     // Potentially reduce our memory usage by dropping our uses and calls fields
     // if they are present, as they won't be used for jumpref production.
     pub fn reduce_memory_usage_by_dropping_non_jumpref_info(&mut self) {
@@ -251,6 +292,7 @@ impl DerivedSymbolInfo {
             badges: vec![],
             effective_subsystem: None,
             depth,
+            provenance: None,
         }
     }
 }
@@ -287,10 +329,221 @@ impl Serialize for SymbolGraphCollection {
         )?;
         sgc.serialize_field("graphs", &graphs)?;
         sgc.serialize_field("hierarchicalGraphs", &hierarchical_graphs)?;
+        if self.node_set.record_provenance {
+            sgc.serialize_field("provenance", &self.node_set.provenance_to_json())?;
+        }
         sgc.end()
     }
 }
 
+/// Version of the `SymbolGraphCollection::to_snapshot` format, which should be
+/// bumped whenever the format changes incompatibly.
+pub const SYMBOL_GRAPH_SNAPSHOT_VERSION: u32 = 1;
+
+/// Lossless serialization of a `SymbolGraphCollection`, unlike its `Serialize`
+/// impl, which is intended for presentation.  Hierarchical graphs are not
+/// included because they are derived from the graphs by the `graph` command
+/// and depend on its arguments.
+#[derive(Deserialize, Serialize)]
+struct SymbolGraphSnapshot {
+    version: u32,
+    record_provenance: bool,
+    nodes: Vec<NodeSnapshot>,
+    edges: Vec<EdgeSnapshot>,
+    graphs: Vec<GraphSnapshot>,
+    overloads_hit: Vec<OverloadInfo>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct NodeSnapshot {
+    symbol: Ustr,
+    crossref_info: Value,
+    badges: Vec<SymbolBadge>,
+    effective_subsystem: Option<Ustr>,
+    depth: u32,
+    /// SymbolProvenance's edge is a `&'static str`, so we store it as
+    /// (edge, parent, depth) and intern the edge on load.
+    provenance: Option<(String, Ustr, u32)>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct EdgeSnapshot {
+    from: u32,
+    to: u32,
+    kind: EdgeKind,
+    data: Vec<EdgeDetail>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct GraphSnapshot {
+    name: String,
+    /// All of the nodes, including any without edges.
+    nodes: Vec<u32>,
+    /// (source node, target node, edge) triples.
+    edges: Vec<(u32, u32, u32)>,
+}
+
+fn make_bad_snapshot_err(message: String) -> ServerError {
+    ServerError::StickyProblem(
+        ErrorDetails::new(ErrorKind::BadQuery, ErrorLayer::BadInput, message)
+            .with_hint("Re-save the graph with `save-graph`."),
+    )
+}
+
+impl SymbolGraphCollection {
+    /// Serialize the node set, edge set, and graphs so that they can be
+    /// reloaded by `from_snapshot` without re-querying the index.
+    pub fn to_snapshot(&self) -> Value {
+        let snapshot = SymbolGraphSnapshot {
+            version: SYMBOL_GRAPH_SNAPSHOT_VERSION,
+            record_provenance: self.node_set.record_provenance,
+            nodes: self
+                .node_set
+                .symbol_crossref_infos
+                .iter()
+                .map(|info| NodeSnapshot {
+                    symbol: info.symbol,
+                    crossref_info: info.crossref_info.clone(),
+                    badges: info.badges.clone(),
+                    effective_subsystem: info.effective_subsystem,
+                    depth: info.depth,
+                    provenance: info
+                        .provenance
+                        .as_ref()
+                        .map(|p| (p.edge.to_string(), p.parent, p.depth)),
+                })
+                .collect(),
+            edges: self
+                .edge_set
+                .edge_infos
+                .iter()
+                .map(|info| EdgeSnapshot {
+                    from: info.from_id.0,
+                    to: info.to_id.0,
+                    kind: info.kind.clone(),
+                    data: info.data.clone(),
+                })
+                .collect(),
+            graphs: self
+                .graphs
+                .iter()
+                .map(|graph| GraphSnapshot {
+                    name: graph.name.clone(),
+                    nodes: graph.list_nodes().into_iter().map(|id| id.0).collect(),
+                    edges: graph
+                        .list_edges()
+                        .into_iter()
+                        .map(|(source, target, edge)| (source.0, target.0, edge.0))
+                        .collect(),
+                })
+                .collect(),
+            overloads_hit: self.overloads_hit.clone(),
+        };
+        to_value(snapshot).unwrap()
+    }
+
+    /// Rebuild a collection from the output of `to_snapshot`, validating that
+    /// the snapshot is internally consistent.
+    pub fn from_snapshot(value: Value) -> Result<Self> {
+        let version = value["version"].as_u64();
+        if version != Some(SYMBOL_GRAPH_SNAPSHOT_VERSION as u64) {
+            return Err(make_bad_snapshot_err(format!(
+                "Unsupported graph snapshot version {:?}, expected {}",
+                version, SYMBOL_GRAPH_SNAPSHOT_VERSION
+            )));
+        }
+        let snapshot: SymbolGraphSnapshot = from_value(value)?;
+
+        let mut node_set = SymbolGraphNodeSet::new();
+        node_set.record_provenance = snapshot.record_provenance;
+        for node in snapshot.nodes {
+            if node_set.symbol_to_index_map.contains_key(&node.symbol) {
+                return Err(make_bad_snapshot_err(format!(
+                    "Graph snapshot has duplicate symbol {}",
+                    node.symbol
+                )));
+            }
+            // We push directly rather than using `add_symbol` because the
+            // badges were already derived when the snapshot was taken.
+            node_set
+                .symbol_to_index_map
+                .insert(node.symbol, node_set.symbol_crossref_infos.len() as u32);
+            node_set.symbol_crossref_infos.push(DerivedSymbolInfo {
+                symbol: node.symbol,
+                crossref_info: node.crossref_info,
+                badges: node.badges,
+                effective_subsystem: node.effective_subsystem,
+                depth: node.depth,
+                provenance: node
+                    .provenance
+                    .map(|(edge, parent, depth)| SymbolProvenance {
+                        edge: ustr(&edge).as_str(),
+                        parent,
+                        depth,
+                    }),
+            });
+        }
+
+        let node_count = node_set.symbol_crossref_infos.len() as u32;
+        let check_node = |id: u32| {
+            if id < node_count {
+                Ok(SymbolGraphNodeId(id))
+            } else {
+                Err(make_bad_snapshot_err(format!(
+                    "Graph snapshot references missing node {}",
+                    id
+                )))
+            }
+        };
+
+        let mut edge_set = SymbolGraphEdgeSet::new();
+        for edge in snapshot.edges {
+            let from_id = check_node(edge.from)?;
+            let to_id = check_node(edge.to)?;
+            edge_set
+                .edge_lookup
+                .insert((edge.from, edge.to), edge_set.edge_infos.len() as u32);
+            edge_set.edge_infos.push(EdgeInfo {
+                from_id,
+                to_id,
+                kind: edge.kind,
+                data: edge.data,
+            });
+        }
+
+        let edge_count = edge_set.edge_infos.len() as u32;
+        let mut graphs = vec![];
+        for graph_snapshot in snapshot.graphs {
+            let mut graph = NamedSymbolGraph::new(graph_snapshot.name);
+            for id in graph_snapshot.nodes {
+                graph.ensure_node(check_node(id)?);
+            }
+            for (source, target, edge) in graph_snapshot.edges {
+                if edge >= edge_count {
+                    return Err(make_bad_snapshot_err(format!(
+                        "Graph snapshot references missing edge {}",
+                        edge
+                    )));
+                }
+                graph.ensure_edge(
+                    check_node(source)?,
+                    check_node(target)?,
+                    SymbolGraphEdgeId(edge),
+                );
+            }
+            graphs.push(graph);
+        }
+
+        Ok(SymbolGraphCollection {
+            node_set,
+            edge_set,
+            graphs,
+            overloads_hit: snapshot.overloads_hit,
+            hierarchical_graphs: vec![],
+        })
+    }
+}
+
 /// Escape double-quotes to safely use a string as an `esc` tagged value.
 ///
 /// Although graphviz-rust's dot-generator has a concept of `esc`, this does not
@@ -874,6 +1127,106 @@ impl NamedSymbolGraph {
         node_paths
     }
 
+    /// Find the strongly connected components of the graph that involve a
+    /// cycle, which is to say the components with more than one node plus any
+    /// nodes with an edge to themselves.  Components are in reverse
+    /// topological order, per `tarjan_scc`.
+    pub fn cyclic_components(&self) -> Vec<Vec<SymbolGraphNodeId>> {
+        tarjan_scc(&self.graph)
+            .into_iter()
+            .filter(|comp| comp.len() > 1 || self.graph.contains_edge(comp[0], comp[0]))
+            .map(|comp| {
+                comp.into_iter()
+                    .map(|ix| {
+                        SymbolGraphNodeId(*self.node_ix_to_id.get(&(ix.index() as u32)).unwrap())
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Find the simple paths from any of the `sources` to any of the `targets`
+    /// which never visit an `avoid` node and which visit every `through` node,
+    /// for taint-style questions like "can the network input handlers reach
+    /// this parser without going through the sandbox check?".  Paths are
+    /// limited to `max_length` edges and we stop once we have `max_paths`.
+    pub fn constrained_simple_paths(
+        &self,
+        sources: &[SymbolGraphNodeId],
+        targets: &[SymbolGraphNodeId],
+        avoid: &[SymbolGraphNodeId],
+        through: &[SymbolGraphNodeId],
+        max_length: usize,
+        max_paths: usize,
+    ) -> Vec<Vec<(SymbolGraphNodeId, SymbolGraphNodeId, SymbolGraphEdgeId)>> {
+        let ix_for = |id: &SymbolGraphNodeId| {
+            self.node_id_to_ix
+                .get(&id.0)
+                .map(|ix| NodeIndex::new(*ix as usize))
+        };
+        // If a node we must pass through isn't in the graph, no path can
+        // satisfy the constraints.
+        let through_ixs: Option<HashSet<NodeIndex>> = through.iter().map(ix_for).collect();
+        let Some(through_ixs) = through_ixs else {
+            return vec![];
+        };
+        let avoid_ixs: HashSet<NodeIndex> = avoid.iter().filter_map(ix_for).collect();
+        let target_ixs: HashSet<NodeIndex> = targets.iter().filter_map(ix_for).collect();
+
+        let mut paths = vec![];
+        for source_ix in sources.iter().filter_map(ix_for) {
+            if avoid_ixs.contains(&source_ix) {
+                continue;
+            }
+            // Depth-first search where `stack` holds the not-yet-visited
+            // neighbors of each node on `path`.
+            let mut path = vec![source_ix];
+            let mut stack = vec![self.graph.neighbors(source_ix)];
+            while let Some(neighbors) = stack.last_mut() {
+                if paths.len() >= max_paths {
+                    return paths;
+                }
+                let Some(next_ix) = neighbors.next() else {
+                    stack.pop();
+                    path.pop();
+                    continue;
+                };
+                if avoid_ixs.contains(&next_ix) || path.contains(&next_ix) {
+                    continue;
+                }
+                path.push(next_ix);
+                let passed_through = through_ixs.iter().all(|ix| path.contains(ix));
+                if target_ixs.contains(&next_ix) && passed_through {
+                    paths.push(self.node_ixs_to_id_edges(&path));
+                }
+                if path.len() <= max_length {
+                    stack.push(self.graph.neighbors(next_ix));
+                } else {
+                    path.pop();
+                }
+            }
+        }
+        paths
+    }
+
+    fn node_ixs_to_id_edges(
+        &self,
+        node_ixs: &[NodeIndex],
+    ) -> Vec<(SymbolGraphNodeId, SymbolGraphNodeId, SymbolGraphEdgeId)> {
+        node_ixs
+            .iter()
+            .tuple_windows()
+            .map(|(src, tgt)| {
+                let edge_ix = self.graph.find_edge(*src, *tgt).unwrap();
+                (
+                    SymbolGraphNodeId(*self.node_ix_to_id.get(&(src.index() as u32)).unwrap()),
+                    SymbolGraphNodeId(*self.node_ix_to_id.get(&(tgt.index() as u32)).unwrap()),
+                    self.graph[edge_ix].clone(),
+                )
+            })
+            .collect()
+    }
+
     /// XXX don't use this, use
     ///
     /// Variant of all_simple_paths that takes source and target sets and
@@ -1972,7 +2325,7 @@ impl HierarchicalRenderState {
 /// Wrapped u32 identifier for DerivedSymbolInfo nodes in a SymbolGraphNodeSet
 /// for type safety.  The values correspond to the index of the node in the
 /// `symbol_crossref_infos` vec in `SymbolGraphNodeSet`.
-#[derive(Clone, Eq, Hash, PartialEq)]
+#[derive(Clone, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct SymbolGraphNodeId(u32);
 
 /// Wrapped u32 identifier for EdgeInfo objects in a SymbolGraphNodeSet for type
@@ -1984,6 +2337,9 @@ pub struct SymbolGraphEdgeId(u32);
 pub struct SymbolGraphNodeSet {
     pub symbol_crossref_infos: Vec<DerivedSymbolInfo>,
     pub symbol_to_index_map: UstrMap<u32>,
+    /// Should `ensure_symbol_via` record provenance for new symbols?  This is
+    /// opt-in because it changes the serialized output.
+    pub record_provenance: bool,
 }
 
 pub struct SymbolGraphEdgeSet {
@@ -1991,7 +2347,7 @@ pub struct SymbolGraphEdgeSet {
     edge_lookup: HashMap<(u32, u32), u32>,
 }
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, Deserialize, PartialEq, Serialize)]
 pub enum EdgeDetail {
     /// Provide a source code jump.
     Jump(String),
@@ -2010,10 +2366,11 @@ pub struct EdgeInfo {
 }
 
 fn make_data_invariant_err() -> ServerError {
-    ServerError::StickyProblem(ErrorDetails {
-        layer: ErrorLayer::RuntimeInvariantViolation,
-        message: "SymbolGraphNodeSet desynchronized".to_string(),
-    })
+    ServerError::StickyProblem(ErrorDetails::new(
+        ErrorKind::Internal,
+        ErrorLayer::RuntimeInvariantViolation,
+        "SymbolGraphNodeSet desynchronized".to_string(),
+    ))
 }
 
 impl Default for SymbolGraphNodeSet {
@@ -2027,6 +2384,7 @@ impl SymbolGraphNodeSet {
         Self {
             symbol_crossref_infos: vec![],
             symbol_to_index_map: UstrMap::default(),
+            record_provenance: false,
         }
     }
 
@@ -2211,6 +2569,21 @@ impl SymbolGraphNodeSet {
         }
     }
 
+    /// Look-up the nodes matching a user-provided name, which may be either a
+    /// symbol or a pretty identifier.  Pretty identifiers may match multiple
+    /// symbols (ex: overloads).
+    pub fn lookup_symbol_or_pretty(&self, name: &str) -> Vec<SymbolGraphNodeId> {
+        if let Some((node_id, _)) = self.lookup_symbol(&ustr(name)) {
+            return vec![node_id];
+        }
+        self.symbol_crossref_infos
+            .iter()
+            .enumerate()
+            .filter(|(_, info)| info.get_pretty().as_str() == name)
+            .map(|(index, _)| SymbolGraphNodeId(index as u32))
+            .collect()
+    }
+
     /// Add a symbol and return the unwrapped data that lookup_symbol would have provided.
     pub fn add_symbol(
         &mut self,
@@ -2273,6 +2646,41 @@ impl SymbolGraphNodeSet {
         Ok(self.add_symbol(DerivedSymbolInfo::new(*sym, info, depth)))
     }
 
+    /// Like `ensure_symbol`, but if the symbol is newly added and
+    /// `record_provenance` is set, record that we reached it from `parent` by
+    /// following an `edge`.
+    pub async fn ensure_symbol_via<'a>(
+        &'a mut self,
+        sym: &'a Ustr,
+        server: &'a (dyn AbstractServer + Send + Sync),
+        depth: u32,
+        parent: Ustr,
+        edge: &'static str,
+    ) -> Result<(SymbolGraphNodeId, &'a mut DerivedSymbolInfo)> {
+        let is_new = !self.symbol_to_index_map.contains_key(sym);
+        let record = self.record_provenance;
+        let (sym_id, sym_info) = self.ensure_symbol(sym, server, depth).await?;
+        if is_new && record {
+            sym_info.provenance = Some(SymbolProvenance {
+                edge,
+                parent,
+                depth,
+            });
+        }
+        Ok((sym_id, sym_info))
+    }
+
+    /// Return a sorted Object mapping from symbol identifiers to their
+    /// provenance for all symbols that have provenance.
+    pub fn provenance_to_json(&self) -> Value {
+        let provenance: BTreeMap<Ustr, &SymbolProvenance> = self
+            .symbol_crossref_infos
+            .iter()
+            .filter_map(|info| info.provenance.as_ref().map(|p| (info.symbol, p)))
+            .collect();
+        json!(provenance)
+    }
+
     /// Destructively return a sorted Object mapping from symbol identifiers to
     /// their jumpref info.  We sort the symbols for stability for testing
     /// purposes and for human readability reasons.  The destruction is that
@@ -2374,3 +2782,108 @@ impl SymbolGraphEdgeSet {
         graph.ensure_edge(source, target, edge_id);
     }
 }
+
+#[test]
+fn test_symbol_graph_snapshot_round_trip() {
+    let mut node_set = SymbolGraphNodeSet::new();
+    node_set.record_provenance = true;
+    for (sym, depth) in [("F_a", 0), ("F_b", 1), ("F_c", 1)] {
+        let (_, info) = node_set.add_symbol(DerivedSymbolInfo::new(ustr(sym), json!({}), depth));
+        if depth > 0 {
+            info.provenance = Some(SymbolProvenance {
+                edge: "callee",
+                parent: ustr("F_a"),
+                depth,
+            });
+        }
+    }
+    let mut edge_set = SymbolGraphEdgeSet::new();
+    let mut graph = NamedSymbolGraph::new("calls".to_string());
+    edge_set.ensure_edge_in_graph(
+        SymbolGraphNodeId(0),
+        SymbolGraphNodeId(1),
+        EdgeKind::Default,
+        vec![EdgeDetail::Jump("a.cpp#3".to_string())],
+        &mut graph,
+    );
+    graph.ensure_node(SymbolGraphNodeId(2));
+    let sgc = SymbolGraphCollection {
+        node_set,
+        edge_set,
+        graphs: vec![graph],
+        overloads_hit: vec![],
+        hierarchical_graphs: vec![],
+    };
+
+    let snapshot = sgc.to_snapshot();
+    let reloaded = SymbolGraphCollection::from_snapshot(snapshot.clone()).unwrap();
+    assert_eq!(reloaded.to_snapshot(), snapshot);
+    assert_eq!(reloaded.graph_to_json(0), sgc.graph_to_json(0));
+    assert_eq!(reloaded.graphs[0].list_nodes().len(), 3);
+    assert_eq!(
+        reloaded.node_set.provenance_to_json(),
+        sgc.node_set.provenance_to_json()
+    );
+
+    let mut future = snapshot;
+    future["version"] = json!(SYMBOL_GRAPH_SNAPSHOT_VERSION + 1);
+    assert!(SymbolGraphCollection::from_snapshot(future).is_err());
+}
+
+#[test]
+fn test_constrained_simple_paths() {
+    // 0 -> 1 -> 3 -> 4
+    // 0 -> 2 -> 3
+    let mut graph = NamedSymbolGraph::new("calls".to_string());
+    for (i, (source, target)) in [(0, 1), (0, 2), (1, 3), (2, 3), (3, 4)].iter().enumerate() {
+        graph.ensure_edge(
+            SymbolGraphNodeId(*source),
+            SymbolGraphNodeId(*target),
+            SymbolGraphEdgeId(i as u32),
+        );
+    }
+    let ids = |syms: &[u32]| -> Vec<SymbolGraphNodeId> {
+        syms.iter().map(|sym| SymbolGraphNodeId(*sym)).collect()
+    };
+    let find = |avoid: &[u32], through: &[u32], max_length: usize| -> Vec<Vec<u32>> {
+        let mut paths: Vec<Vec<u32>> = graph
+            .constrained_simple_paths(
+                &ids(&[0]),
+                &ids(&[4]),
+                &ids(avoid),
+                &ids(through),
+                max_length,
+                64,
+            )
+            .into_iter()
+            .map(|path| {
+                let (first, _, _) = &path[0];
+                let mut nodes = vec![first.0];
+                nodes.extend(path.iter().map(|(_, target, _)| target.0));
+                nodes
+            })
+            .collect();
+        paths.sort();
+        paths
+    };
+
+    assert_eq!(find(&[], &[], 8), vec![vec![0, 1, 3, 4], vec![0, 2, 3, 4]]);
+    // Avoided nodes are never visited.
+    assert_eq!(find(&[1], &[], 8), vec![vec![0, 2, 3, 4]]);
+    assert_eq!(find(&[3], &[], 8), Vec::<Vec<u32>>::new());
+    // Every path must pass through all the through nodes.
+    assert_eq!(find(&[], &[2], 8), vec![vec![0, 2, 3, 4]]);
+    assert_eq!(find(&[], &[1, 2], 8), Vec::<Vec<u32>>::new());
+    // A through node that isn't in the graph can't be passed through.
+    assert_eq!(find(&[], &[99], 8), Vec::<Vec<u32>>::new());
+    // The length limit is in edges.
+    assert_eq!(find(&[], &[], 3).len(), 2);
+    assert_eq!(find(&[], &[], 2), Vec::<Vec<u32>>::new());
+    // We stop once we have enough paths.
+    assert_eq!(
+        graph
+            .constrained_simple_paths(&ids(&[0]), &ids(&[4]), &[], &[], 8, 1)
+            .len(),
+        1
+    );
+}
@@ -0,0 +1,99 @@
+use std::collections::BTreeMap;
+
+use serde_json::{from_value, Value};
+use ustr::{Ustr, UstrSet};
+
+use crate::{
+    abstract_server::{AbstractServer, Result},
+    file_format::analysis::{FileClassification, PathSearchResult},
+};
+
+/// How a test file was reached from a symbol; the first (shallowest) way wins.
+pub struct TestFileHit {
+    /// 0 for tests that use the symbol directly, 1 for tests that use one of
+    /// its callers, and so on.
+    pub depth: u32,
+    /// The symbol whose use put the file in the list.
+    pub via: Ustr,
+    /// How many lines of the file use `via`.
+    pub uses: u32,
+}
+
+/// Record the test uses in a crossref "uses" value, returning the enclosing
+/// symbols of the non-test uses, which are the next level of callers.
+fn process_uses(
+    uses: Option<&Value>,
+    symbol: Ustr,
+    depth: u32,
+    hits: &mut BTreeMap<Ustr, TestFileHit>,
+) -> Result<Vec<Ustr>> {
+    let Some(uses) = uses else {
+        return Ok(vec![]);
+    };
+    let path_containers: Vec<PathSearchResult> = from_value(uses.clone())?;
+    let mut callers = vec![];
+    for path_container in path_containers {
+        if path_container.classification == FileClassification::Test {
+            let hit = hits.entry(path_container.path).or_insert(TestFileHit {
+                depth,
+                via: symbol,
+                uses: 0,
+            });
+            if hit.depth == depth {
+                hit.uses += path_container.lines.len() as u32;
+            }
+            continue;
+        }
+        callers.extend(
+            path_container
+                .lines
+                .iter()
+                .map(|result| result.contextsym)
+                .filter(|contextsym| !contextsym.is_empty()),
+        );
+    }
+    Ok(callers)
+}
+
+/// Map symbols, given with their crossref info, to the test files (per the
+/// tree's path classification) that use them.  Test files that use a symbol
+/// directly are at depth 0; up to `depth` levels of callers are then followed
+/// by treating the functions containing the non-test uses as callers, looking
+/// up at most `max_callers` of them per level.
+pub async fn map_symbols_to_tests(
+    server: &(dyn AbstractServer + Send + Sync),
+    symbols: &[(Ustr, &Value)],
+    depth: u32,
+    max_callers: usize,
+) -> Result<BTreeMap<Ustr, TestFileHit>> {
+    let mut hits = BTreeMap::new();
+    let mut seen = UstrSet::default();
+    let mut frontier = vec![];
+    for (symbol, crossref_info) in symbols {
+        seen.insert(*symbol);
+        frontier.extend(process_uses(
+            crossref_info.get("uses"),
+            *symbol,
+            0,
+            &mut hits,
+        )?);
+    }
+
+    for depth in 1..=depth {
+        let mut next = vec![];
+        let mut looked_up = 0;
+        for caller in frontier {
+            if looked_up >= max_callers {
+                break;
+            }
+            if seen.insert(caller) {
+                looked_up += 1;
+                let info = server.crossref_lookup_keys(&caller, &["uses"]).await?;
+                next.extend(process_uses(info.get("uses"), caller, depth, &mut hits)?);
+            }
+        }
+        frontier = next;
+    }
+
+    Ok(hits)
+}
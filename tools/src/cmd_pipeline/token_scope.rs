@@ -0,0 +1,142 @@
+use clap::ValueEnum;
+
+use crate::{
+    languages::FormatAs,
+    tokenize::{self, Token, TokenKind},
+};
+
+/// Which class of tokens a text search should be limited to, like `in:comments`
+/// in the query language.  Token classes come from the same tokenizers that the
+/// indexer uses to syntax highlight source files, so a match is classified the
+/// way it is displayed.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum TokenScope {
+    /// Only matches entirely within comments.
+    Comments,
+    /// Only matches entirely within string literals.
+    Strings,
+    /// Only matches outside of comments and string literals.
+    Code,
+}
+
+impl TokenScope {
+    fn includes(&self, kind: &TokenKind) -> bool {
+        let is_comment = matches!(kind, TokenKind::Comment);
+        let is_string = matches!(kind, TokenKind::StringLiteral);
+        match self {
+            TokenScope::Comments => is_comment,
+            TokenScope::Strings => is_string,
+            TokenScope::Code => !is_comment && !is_string,
+        }
+    }
+}
+
+fn tokenize_source(format: FormatAs, source: &str) -> Vec<Token> {
+    match format {
+        FormatAs::Binary => vec![],
+        FormatAs::CSS => tokenize::tokenize_css(source),
+        FormatAs::Plain => tokenize::tokenize_plain(source),
+        FormatAs::StaticPrefs => tokenize::tokenize_static_prefs(source),
+        FormatAs::FormatCLike(spec) => tokenize::tokenize_c_like(source, spec),
+        FormatAs::FormatTagLike(script_spec) => tokenize::tokenize_tag_like(source, script_spec),
+    }
+}
+
+/// The tokens of each line of a source file as byte ranges relative to the
+/// start of the line, along with whether they're in the scope.  Tokens that
+/// span lines, like block comments, are split at the line boundaries.
+pub struct ScopedLines {
+    lines: Vec<Vec<(usize, usize, bool)>>,
+}
+
+impl ScopedLines {
+    pub fn new(format: FormatAs, source: &str, scope: TokenScope) -> Self {
+        let line_starts: Vec<usize> = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        let mut lines = vec![vec![]; line_starts.len()];
+        for token in tokenize_source(format, source) {
+            if matches!(token.kind, TokenKind::Newline) {
+                continue;
+            }
+            let in_scope = scope.includes(&token.kind);
+            let mut line = line_starts.partition_point(|&start| start <= token.start) - 1;
+            let mut start = token.start;
+            while start < token.end {
+                let line_start = line_starts[line];
+                let line_end = line_starts.get(line + 1).copied().unwrap_or(source.len());
+                let end = token.end.min(line_end);
+                lines[line].push((start - line_start, end - line_start, in_scope));
+                start = end;
+                line += 1;
+            }
+        }
+        ScopedLines { lines }
+    }
+
+    /// Whether the match at the given byte bounds of the 1-based line is in the
+    /// scope, which means it touches at least one token in the scope and none
+    /// outside of it.  Whitespace between tokens doesn't count either way.
+    pub fn contains(&self, line_num: u32, start: usize, end: usize) -> bool {
+        let Some(tokens) = (line_num as usize)
+            .checked_sub(1)
+            .and_then(|i| self.lines.get(i))
+        else {
+            return false;
+        };
+        let end = end.max(start + 1);
+        let mut touched = false;
+        for (token_start, token_end, in_scope) in tokens {
+            if *token_start < end && start < *token_end {
+                if !in_scope {
+                    return false;
+                }
+                touched = true;
+            }
+        }
+        touched
+    }
+}
+
+#[cfg(test)]
+fn scoped_matches(source: &str, scope: TokenScope, needle: &str) -> Vec<(u32, usize)> {
+    let scoped = ScopedLines::new(crate::languages::select_formatting("a.cpp"), source, scope);
+    let mut found = vec![];
+    for (i, line) in source.lines().enumerate() {
+        for (start, _) in line.match_indices(needle) {
+            if scoped.contains(i as u32 + 1, start, start + needle.len()) {
+                found.push((i as u32 + 1, start));
+            }
+        }
+    }
+    found
+}
+
+#[test]
+fn test_scoped_lines() {
+    let source = "int foo = 1; // foo here\n\
+                  const char* s = \"foo\";\n\
+                  /* multi\n   foo */ foo();\n";
+
+    assert_eq!(
+        scoped_matches(source, TokenScope::Comments, "foo"),
+        vec![(1, 16), (4, 3)]
+    );
+    assert_eq!(
+        scoped_matches(source, TokenScope::Strings, "foo"),
+        vec![(2, 17)]
+    );
+    assert_eq!(
+        scoped_matches(source, TokenScope::Code, "foo"),
+        vec![(1, 4), (4, 10)]
+    );
+
+    // A match straddling code and a comment is in neither.
+    let scoped = ScopedLines::new(
+        crate::languages::select_formatting("a.cpp"),
+        source,
+        TokenScope::Code,
+    );
+    assert!(!scoped.contains(1, 8, 18));
+    assert!(!scoped.contains(99, 0, 1));
+}
@@ -23,6 +23,82 @@ pub fn path_glob_transform(s: &str) -> String {
     braced.to_string()
 }
 
+/// Normalize the whitespace in a C++ type so that types can be compared
+/// textually: whitespace is removed except for a single space between two
+/// words, so "const  Foo *" becomes "const Foo*".
+pub fn normalize_type_whitespace(s: &str) -> String {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let mut result = String::new();
+    let mut pending_space = false;
+    for c in s.chars() {
+        if c.is_whitespace() {
+            pending_space = true;
+            continue;
+        }
+        if pending_space && result.ends_with(is_word) && is_word(c) {
+            result.push(' ');
+        }
+        pending_space = false;
+        result.push(c);
+    }
+    result
+}
+
+/// Convert a C++ type pattern like "RefPtr<Document>" or "nsCOMPtr<*>" into an
+/// anchored regex to match against `normalize_type_whitespace`d types.  `*`
+/// matches anything and unqualified names in the pattern also match qualified
+/// names, so "RefPtr<Document>" matches "RefPtr<mozilla::dom::Document>".
+pub fn type_pattern_to_regex(pattern: &str) -> String {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let normalized = normalize_type_whitespace(pattern);
+    let mut result = "^".to_string();
+    let mut prev: Option<char> = None;
+    for c in normalized.chars() {
+        if c == '*' {
+            result.push_str(".*");
+        } else if is_word(c) && !prev.map_or(false, is_word) {
+            if prev != Some(':') {
+                result.push_str(r"(?:\w+::)*");
+            }
+            result.push(c);
+        } else {
+            result.push_str(&regex::escape(&c.to_string()));
+        }
+        prev = Some(c);
+    }
+    result.push('$');
+    result
+}
+
+#[test]
+fn test_type_pattern_to_regex() {
+    assert_eq!(
+        normalize_type_whitespace(" const  Foo < Bar > * "),
+        "const Foo<Bar>*"
+    );
+
+    let matches = |pattern: &str, ty: &str| {
+        Regex::new(&type_pattern_to_regex(pattern))
+            .unwrap()
+            .is_match(&normalize_type_whitespace(ty))
+    };
+    assert!(matches("RefPtr<Document>", "RefPtr<Document>"));
+    assert!(matches(
+        "RefPtr<Document>",
+        "RefPtr<mozilla::dom::Document>"
+    ));
+    assert!(matches("RefPtr<Document>", "mozilla::RefPtr< Document >"));
+    assert!(!matches("RefPtr<Document>", "RefPtr<DocumentFoo>"));
+    assert!(!matches("RefPtr<Document>", "nsTArray<RefPtr<Document>>"));
+    assert!(matches("nsCOMPtr<*>", "nsCOMPtr<nsIFoo>"));
+    assert!(!matches("nsCOMPtr<*>", "RefPtr<nsIFoo>"));
+    assert!(matches("*nsCOMPtr<*>*", "nsTArray<nsCOMPtr<nsIFoo>>"));
+    assert!(matches(
+        "mozilla::dom::Document*",
+        "mozilla::dom::Document *"
+    ));
+}
+
 #[test]
 fn test_path_glob_transform() {
     // Test coverage for the cases we documented on the help page.
@@ -0,0 +1,82 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeSet;
+use std::hash::{Hash, Hasher};
+
+use crate::languages::FormatAs;
+use crate::tokenize::{self, TokenKind};
+
+/// Tokenize source the same way the formatter does, normalizing the tokens so
+/// that copies of code that only differ in naming, literals, comments, or
+/// whitespace have the same token stream: identifiers (other than reserved
+/// words) become "$id" and literals become "$lit".
+pub fn normalized_tokens(format: &FormatAs, input: &str) -> Vec<String> {
+    tokenize::tokenize_as(format, input)
+        .into_iter()
+        .filter_map(|token| {
+            let text = input[token.start..token.end].trim();
+            match token.kind {
+                TokenKind::Comment | TokenKind::Newline => None,
+                _ if text.is_empty() => None,
+                TokenKind::Identifier(None) => Some("$id".to_string()),
+                TokenKind::StringLiteral | TokenKind::RegularExpressionLiteral => {
+                    Some("$lit".to_string())
+                }
+                _ if text.starts_with(|c: char| c.is_ascii_digit()) => Some("$lit".to_string()),
+                _ => Some(text.to_string()),
+            }
+        })
+        .collect()
+}
+
+/// Select fingerprints for a token stream by winnowing: hash every `k`-gram of
+/// tokens and keep the minimum hash of each `window` consecutive hashes.  Any
+/// run of at least `k + window - 1` tokens shared by two streams is guaranteed
+/// to contribute a shared fingerprint.
+pub fn winnow(tokens: &[String], k: usize, window: usize) -> BTreeSet<u64> {
+    let hashes: Vec<u64> = tokens
+        .windows(k.max(1))
+        .map(|gram| {
+            let mut hasher = DefaultHasher::new();
+            gram.hash(&mut hasher);
+            hasher.finish()
+        })
+        .collect();
+    if hashes.len() <= window {
+        return hashes.into_iter().min().into_iter().collect();
+    }
+    hashes
+        .windows(window.max(1))
+        .filter_map(|w| w.iter().min().copied())
+        .collect()
+}
+
+/// The Jaccard similarity of two fingerprint sets.
+pub fn similarity(a: &BTreeSet<u64>, b: &BTreeSet<u64>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f64 / union as f64
+}
+
+#[test]
+fn test_code_similarity() {
+    use crate::languages::select_formatting;
+
+    let format = select_formatting("dup.cpp");
+    let a = "int Sum(const int* aValues, int aCount) {\n  int total = 0; // running total\n  for (int i = 0; i < aCount; i++) {\n    total += aValues[i];\n  }\n  return total;\n}\n";
+    let renamed = "int Add(const int* v, int n) {\n  int acc = 1;\n  for (int j = 0; j < n; j++) {\n    acc += v[j];\n  }\n  return acc;\n}\n";
+    let different = "void Log(const char* aMsg) {\n  if (!aMsg) {\n    return;\n  }\n  printf(\"%s\\n\", aMsg);\n  fflush(stdout);\n}\n";
+
+    let tokens = normalized_tokens(&format, a);
+    assert_eq!(&tokens[..4], &["int", "$id", "(", "const"]);
+    assert!(!tokens.iter().any(|token| token.contains("running")));
+    assert_eq!(tokens, normalized_tokens(&format, renamed));
+
+    let fp_a = winnow(&tokens, 5, 4);
+    let fp_renamed = winnow(&normalized_tokens(&format, renamed), 5, 4);
+    let fp_different = winnow(&normalized_tokens(&format, different), 5, 4);
+    assert_eq!(similarity(&fp_a, &fp_renamed), 1.0);
+    assert!(similarity(&fp_a, &fp_different) < 0.2);
+    assert_eq!(similarity(&BTreeSet::new(), &BTreeSet::new()), 0.0);
+}
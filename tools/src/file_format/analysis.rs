@@ -212,13 +212,84 @@ where
     pub arg_ranges: Vec<SourceRange>,
 }
 
-/// See TargetTag for more info
+/// See TargetTag for more info.  The value of the tag doubles as the schema
+/// version of the structured record, so when bumping
+/// `STRUCTURED_SCHEMA_VERSION` this discriminant needs to be bumped in lockstep
+/// and a migration step added to `migrate_structured_value`.
 #[derive(Debug, Eq, PartialEq, Serialize_repr, Deserialize_repr)]
 #[repr(u8)]
 pub enum StructuredTag {
     Structured = 1,
 }
 
+/// The schema version of structured records (and the crossref "meta" which is
+/// just a serialized structured record) that this build emits.  Additive
+/// changes that serde can handle via `#[serde(default)]` do not require a bump;
+/// renames, type changes, and semantic changes do.
+pub const STRUCTURED_SCHEMA_VERSION: u64 = StructuredTag::Structured as u64;
+/// The oldest schema version `migrate_structured_value` knows how to upgrade.
+pub const MIN_STRUCTURED_SCHEMA_VERSION: u64 = 1;
+
+#[derive(Debug)]
+pub enum SchemaError {
+    /// The record was produced by a schema version we can't read, either
+    /// because it's too old to migrate or because it's from a newer indexer.
+    UnsupportedVersion { found: u64 },
+    /// The record claimed to be a supported version but didn't deserialize.
+    Malformed { version: u64, message: String },
+}
+
+impl std::fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SchemaError::UnsupportedVersion { found } => write!(
+                f,
+                "unsupported structured record schema version {}; this build supports versions {} through {}",
+                found, MIN_STRUCTURED_SCHEMA_VERSION, STRUCTURED_SCHEMA_VERSION
+            ),
+            SchemaError::Malformed { version, message } => write!(
+                f,
+                "malformed structured record (schema version {}): {}",
+                version, message
+            ),
+        }
+    }
+}
+
+/// Upgrade a raw structured record `Value` from whatever schema version it was
+/// written with to `STRUCTURED_SCHEMA_VERSION` so that it can be deserialized
+/// into an `AnalysisStructured`.  This lets us keep older indexes queryable
+/// after a format change without regenerating them.
+///
+/// Each migration step should take the record from version N to N + 1 and then
+/// fall through to the next step.
+pub fn migrate_structured_value(obj: Value) -> Result<Value, SchemaError> {
+    let found = match obj.get("structured") {
+        Some(Value::Number(n)) => n.as_u64().unwrap_or(0),
+        _ => 0,
+    };
+    if !(MIN_STRUCTURED_SCHEMA_VERSION..=STRUCTURED_SCHEMA_VERSION).contains(&found) {
+        return Err(SchemaError::UnsupportedVersion { found });
+    }
+
+    // There are no migrations yet; version 1 is the only version.  When adding
+    // version 2, this will look like:
+    //
+    // if found < 2 { migrate_structured_v1_to_v2(&mut obj); }
+    // obj["structured"] = json!(STRUCTURED_SCHEMA_VERSION);
+    Ok(obj)
+}
+
+/// Migrate and deserialize a raw structured record, producing a `SchemaError`
+/// that explains what went wrong rather than just failing opaquely.
+pub fn parse_structured_value(obj: Value) -> Result<AnalysisStructured<Ustr>, SchemaError> {
+    let obj = migrate_structured_value(obj)?;
+    from_value(obj).map_err(|e| SchemaError::Malformed {
+        version: STRUCTURED_SCHEMA_VERSION,
+        message: e.to_string(),
+    })
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct StructuredSuperInfo<StrT = Ustr>
 where
@@ -267,6 +338,54 @@ pub struct StructuredBitPositionInfo {
     pub width: u32,
 }
 
+/// For C++ variables with static (or thread) storage duration whose
+/// initialization or destruction runs code, ex: global constructors, which
+/// affect startup.  Variables with constant initialization and trivial
+/// destruction don't get one.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StructuredStaticInitInfo<StrT = Ustr>
+where
+    StrT: Clone + Debug + Default + Deref<Target = str> + FromStr + Hash + Ord + PartialEq,
+{
+    /// "global" for namespace scope variables and static data members,
+    /// "staticLocal" for function-scope statics, which are initialized on
+    /// first use, or "threadLocal".
+    #[serde(default)]
+    pub kind: StrT,
+    /// Whether the initializer runs code rather than being a constant.
+    #[serde(rename = "dynamicInit", default)]
+    pub dynamic_init: bool,
+    /// Whether a non-trivial destructor runs at shutdown.
+    #[serde(default)]
+    pub destructor: bool,
+    /// The functions, constructors, and variables the initializer references,
+    /// plus the destructor.
+    #[serde(default)]
+    pub syms: Vec<StrT>,
+}
+
+/// A thread safety annotation from clang's `-Wthread-safety` attributes, ex:
+/// `MOZ_GUARDED_BY(mMutex)` on a field or `MOZ_REQUIRES(mMutex)` on a method.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StructuredLockInfo<StrT = Ustr>
+where
+    StrT: Clone + Debug + Default + Deref<Target = str> + FromStr + Hash + Ord + PartialEq,
+{
+    /// One of "guarded_by", "pt_guarded_by", "requires", "requires_shared",
+    /// "excludes", "acquires", or "releases".
+    #[serde(default)]
+    pub kind: StrT,
+    /// The symbol of the capability (the mutex), if the annotation's argument
+    /// names a field or variable.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sym: Option<StrT>,
+    /// The capability's qualified name, or the source text of the argument if
+    /// it doesn't name a field or variable, or "this" for the annotated object
+    /// itself.
+    #[serde(default)]
+    pub pretty: StrT,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct StructuredOverrideInfo<StrT = Ustr> {
     #[serde(default)]
@@ -283,6 +402,10 @@ where
     /// If this field is defined in single line, "PATH#line" format is used,
     /// otherwise "PATH#line-line" format is used with first line and last line.
     ///
+    /// Each line may be followed by ":col" with the 0-based column of the
+    /// start of the declaration (for the first line) or the exclusive end (for
+    /// the last line), like "PATH#line:col-line:col".
+    ///
     /// If this field is defined in the same file as struct itself,
     /// PATH part is omitted.
     /// Otherwise PATH is the full path inside the repository.
@@ -305,6 +428,14 @@ where
     pub bit_positions: Option<StructuredBitPositionInfo>,
     #[serde(rename = "sizeBytes")]
     pub size_bytes: Option<u32>,
+    /// The alignment requirement of the field in bytes, if the indexer knows
+    /// it.  Consumers infer it from the offset and size when absent.
+    #[serde(
+        rename = "alignBytes",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub align_bytes: Option<u32>,
     #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
     pub labels: BTreeSet<StrT>,
     #[serde(default, rename = "pointerInfo", skip_serializing_if = "Vec::is_empty")]
@@ -546,6 +677,30 @@ where
     pub size_bytes: Option<u32>,
     #[serde(rename = "ownVFPtrBytes")]
     pub own_vf_ptr_bytes: Option<u32>,
+    /// For Rust types, the arguments of the type's `#[repr(...)]` attributes
+    /// (ex: "C, packed"), or "Rust" for the default repr.  See `rust_repr.rs`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repr: Option<StrT>,
+    /// For Rust symbols, the name of the crate defining the symbol, with any
+    /// "-" normalized to "_" as in `use` paths.
+    #[serde(rename = "crate", default, skip_serializing_if = "Option::is_none")]
+    pub crate_name: Option<StrT>,
+    /// For Rust symbols, the path of the module containing the symbol
+    /// starting with its crate, ex: "my_crate::foo::bar".
+    #[serde(
+        rename = "modulePath",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub module_path: Option<StrT>,
+    #[serde(
+        rename = "staticInit",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub static_init: Option<StructuredStaticInitInfo<StrT>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub locks: Vec<StructuredLockInfo<StrT>>,
 
     #[serde(rename = "bindingSlots", default)]
     pub binding_slots: Vec<StructuredBindingSlotInfo<StrT>>,
@@ -566,6 +721,10 @@ where
     pub props: Vec<StrT>,
     #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
     pub labels: BTreeSet<StrT>,
+    /// Attribute-like annotations on the symbol, ex: "moz_can_run_script" or
+    /// "deprecated".  See `symbol_attributes.rs`.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub attributes: BTreeSet<StrT>,
 
     // ### Derived by cross-referencing
     #[serde(rename = "idlsym", skip_serializing_if = "Option::is_none")]
@@ -581,6 +740,10 @@ where
         skip_serializing_if = "Vec::is_empty"
     )]
     pub overridden_by_syms: Vec<StrT>,
+    /// Documentation comment extracted from the source adjacent to the
+    /// definition by `doc_comments::extract_doc_comment`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc: Option<String>,
 
     #[serde(default)]
     pub variants: Vec<AnalysisStructured<StrT>>,
@@ -1161,7 +1324,13 @@ pub fn read_structured(
     // XXX this shouldn't be necessary thanks to our tag, so this should be removable
     obj.get("structured")?;
 
-    from_value(obj).ok()
+    match parse_structured_value(obj) {
+        Ok(structured) => Some(structured),
+        Err(e) => {
+            warn!("Skipping structured record: {}", e);
+            None
+        }
+    }
 }
 
 pub fn read_source(obj: Value, _loc: &Location, _i_size: usize) -> Option<AnalysisSource<Ustr>> {
@@ -1236,9 +1405,58 @@ pub struct SearchResult {
     pub peek_range: LineRange,
 }
 
+/// Coarse classification of a file that's decoupled from the human-readable
+/// (and tree-configurable) path kind names like "Generated code".  This gets
+/// computed at index time from the path kind configuration in
+/// `per-file-info.toml` and is what commands consult for filtering flags like
+/// `--no-generated`.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileClassification {
+    #[default]
+    Normal,
+    Generated,
+    ThirdParty,
+    Test,
+}
+
+impl FileClassification {
+    pub fn is_normal(&self) -> bool {
+        *self == FileClassification::Normal
+    }
+
+    /// Map a `[pathkind.KEY]` key from `per-file-info.toml` to a
+    /// classification for path kinds that don't explicitly specify one.
+    pub fn from_path_kind_key(key: &str) -> Self {
+        match key {
+            "generated" => FileClassification::Generated,
+            "third_party" => FileClassification::ThirdParty,
+            "test" => FileClassification::Test,
+            _ => FileClassification::Normal,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize)]
 pub struct PathSearchResult {
     pub path: Ustr,
     pub path_kind: Ustr,
+    /// Only emitted by crossref for non-normal files.
+    #[serde(default)]
+    pub classification: FileClassification,
     pub lines: Vec<SearchResult>,
 }
+
+#[test]
+fn test_migrate_structured_value() {
+    use serde_json::json;
+
+    let current = json!({"structured": STRUCTURED_SCHEMA_VERSION, "sym": "T_Foo", "kind": "class"});
+    let parsed = parse_structured_value(current).unwrap();
+    assert_eq!(&*parsed.sym, "T_Foo");
+
+    let future = json!({"structured": STRUCTURED_SCHEMA_VERSION + 1, "sym": "T_Foo"});
+    let err = parse_structured_value(future).unwrap_err();
+    assert!(matches!(err, SchemaError::UnsupportedVersion { .. }));
+    assert!(err.to_string().contains("supports versions"));
+}
@@ -0,0 +1,163 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+/// Content hashes of the files under mozsearch's `static/` directory, so that
+/// the pages can reference them via fingerprinted URLs like
+/// `/mozilla-central/static/css/mozsearch.css?v=0123456789abcdef`.  Because the
+/// URL changes whenever the contents do, nginx can serve the fingerprinted
+/// URLs with long-lived cache headers without browsers ever using a stale
+/// stylesheet or script.
+///
+/// `render asset-manifest` regenerates this as the first step of rendering a
+/// tree's pages and writes it to `asset-manifest.json` in the index's
+/// templates directory.  Loading the tree's config loads it for the pages
+/// rendered in Rust, and Liquid templates get it via the `ASSET_MANIFEST`
+/// global, which the `asset_url` filter looks the asset up in.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct AssetManifest {
+    /// The hashes keyed by the path relative to the static directory, ex:
+    /// "css/mozsearch.css".
+    #[serde(default)]
+    pub assets: BTreeMap<String, String>,
+}
+
+pub const ASSET_MANIFEST_FILENAME: &str = "asset-manifest.json";
+
+/// The URL of the asset at the path relative to the static directory for the
+/// tree, fingerprinted with the hash if we have one.  Without one, this is the
+/// plain URL, which is always correct but can't be cached as long.
+pub fn asset_url(tree_name: &str, asset_path: &str, hash: Option<&str>) -> String {
+    let asset_path = asset_path.trim_start_matches('/');
+    match hash {
+        Some(hash) => format!("/{}/static/{}?v={}", tree_name, asset_path, hash),
+        None => format!("/{}/static/{}", tree_name, asset_path),
+    }
+}
+
+/// FNV-1a over the contents, which is stable across builds (unlike
+/// `DefaultHasher`) so that every tool hashes an asset the same way.
+fn content_hash(contents: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in contents {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+impl AssetManifest {
+    /// Hash every file under the static directory.
+    pub fn build(static_dir: &str) -> std::io::Result<AssetManifest> {
+        let mut assets = BTreeMap::new();
+        for entry in WalkDir::new(static_dir) {
+            let entry = entry?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let Ok(rel_path) = entry.path().strip_prefix(static_dir) else {
+                continue;
+            };
+            let Some(rel_path) = rel_path.to_str() else {
+                continue;
+            };
+            let contents = std::fs::read(entry.path())?;
+            assets.insert(rel_path.to_string(), content_hash(&contents));
+        }
+        Ok(AssetManifest { assets })
+    }
+
+    pub fn load(path: &str) -> std::io::Result<Option<AssetManifest>> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        Ok(Some(serde_json::from_reader(BufReader::new(file))?))
+    }
+
+    /// Load the manifest at the path, or an empty one if there isn't one yet
+    /// because `render asset-manifest` hasn't run.
+    pub fn load_or_default(path: &str) -> std::io::Result<AssetManifest> {
+        Ok(AssetManifest::load(path)?.unwrap_or_default())
+    }
+
+    pub fn write(&self, path: &str) -> std::io::Result<()> {
+        if let Some(parent) = Path::new(path).parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = File::create(path)?;
+        serde_json::to_writer_pretty(BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    /// The URL of the asset at the path relative to the static directory for
+    /// the tree, fingerprinted if the manifest knows the asset.
+    pub fn url(&self, tree_name: &str, asset_path: &str) -> String {
+        let hash = self.assets.get(asset_path.trim_start_matches('/'));
+        asset_url(tree_name, asset_path, hash.map(String::as_str))
+    }
+}
+
+#[test]
+fn test_asset_manifest() {
+    let dir = std::env::temp_dir().join(format!("asset-manifest-test-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("css")).unwrap();
+    std::fs::write(dir.join("css/mozsearch.css"), "body {}").unwrap();
+    std::fs::write(dir.join("robots.txt"), "").unwrap();
+
+    let manifest = AssetManifest::build(dir.to_str().unwrap()).unwrap();
+    assert_eq!(
+        manifest.assets.keys().collect::<Vec<_>>(),
+        vec!["css/mozsearch.css", "robots.txt"]
+    );
+    // The FNV-1a offset basis, for the empty file.
+    assert_eq!(manifest.assets["robots.txt"], "cbf29ce484222325");
+    assert_eq!(
+        manifest.url("mozilla-central", "/css/mozsearch.css"),
+        format!(
+            "/mozilla-central/static/css/mozsearch.css?v={}",
+            content_hash(b"body {}")
+        )
+    );
+    assert_eq!(
+        manifest.url("mozilla-central", "js/search.js"),
+        "/mozilla-central/static/js/search.js"
+    );
+    assert_eq!(
+        asset_url("tests", "/js/search.js", Some("0123")),
+        "/tests/static/js/search.js?v=0123"
+    );
+
+    // Changing an asset changes its hash and only its hash.
+    std::fs::write(dir.join("css/mozsearch.css"), "body { color: red }").unwrap();
+    let changed = AssetManifest::build(dir.to_str().unwrap()).unwrap();
+    assert_ne!(
+        changed.assets["css/mozsearch.css"],
+        manifest.assets["css/mozsearch.css"]
+    );
+    assert_eq!(changed.assets["robots.txt"], manifest.assets["robots.txt"]);
+
+    let manifest_path = dir.join("templates").join(ASSET_MANIFEST_FILENAME);
+    let manifest_path = manifest_path.to_str().unwrap();
+    assert!(AssetManifest::load(manifest_path).unwrap().is_none());
+    assert!(AssetManifest::load_or_default(manifest_path)
+        .unwrap()
+        .assets
+        .is_empty());
+    manifest.write(manifest_path).unwrap();
+    assert_eq!(
+        AssetManifest::load(manifest_path).unwrap().unwrap().assets,
+        manifest.assets
+    );
+
+    // A corrupt manifest is an error rather than silently unfingerprinted URLs.
+    std::fs::write(manifest_path, "{").unwrap();
+    assert!(AssetManifest::load_or_default(manifest_path).is_err());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
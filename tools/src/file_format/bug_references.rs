@@ -0,0 +1,187 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::blame::LineData;
+use crate::languages::select_formatting;
+use crate::tokenize::{tokenize_as, TokenKind};
+
+lazy_static! {
+    static ref BUG_RE: Regex =
+        Regex::new(r"(?i)(?:\bbug\s*#?\s*|show_bug\.cgi\?id=|bugzil\.la/)(\d{3,8})").unwrap();
+}
+
+/// The bug numbers referenced by some text, ex: "Bug 1234567" or a Bugzilla
+/// URL, in order of appearance.
+pub fn bug_numbers(text: &str) -> impl Iterator<Item = u64> + '_ {
+    BUG_RE
+        .captures_iter(text)
+        .filter_map(|caps| caps[1].parse().ok())
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum BugReferenceSource {
+    /// A comment in the source mentions the bug.
+    Comment,
+    /// The commit message of the revision that last changed the lines
+    /// mentions the bug.
+    Commit,
+}
+
+/// A place in the tree that references a bug.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct BugReference {
+    pub source: BugReferenceSource,
+    pub path: String,
+    /// The 1-based line number of the comment, or of the first line the
+    /// revision last changed.
+    pub line: u32,
+    /// How many lines of the file the revision last changed, or 1 for
+    /// comments.
+    pub lines: u32,
+    /// The revision, for commit references.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rev: Option<String>,
+    /// The comment's line or the commit message's first line.
+    pub text: String,
+}
+
+/// Index of the bug references in the tree's comments and in the commit
+/// messages of its blame, which crossref writes to `bug-references.json` in
+/// the tree's index directory for the `bug-refs` command.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct BugReferenceIndex {
+    #[serde(default)]
+    pub bugs: BTreeMap<u64, Vec<BugReference>>,
+}
+
+pub const BUG_REFERENCES_FILENAME: &str = "bug-references.json";
+
+impl BugReferenceIndex {
+    pub fn load(path: &str) -> std::io::Result<Option<BugReferenceIndex>> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        Ok(Some(serde_json::from_reader(BufReader::new(file))?))
+    }
+
+    pub fn write(&self, path: &str) -> std::io::Result<()> {
+        let file = File::create(path)?;
+        serde_json::to_writer(BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    /// Add the bugs referenced by the comments in a file's contents.
+    pub fn add_comment_references(&mut self, path: &str, contents: &str) {
+        let format = select_formatting(path);
+        let mut line = 1;
+        let mut last_end = 0;
+        for token in tokenize_as(&format, contents) {
+            line += contents[last_end..token.start].matches('\n').count() as u32;
+            last_end = token.start;
+            if token.kind != TokenKind::Comment {
+                continue;
+            }
+            let comment = &contents[token.start..token.end];
+            let bugs: BTreeSet<u64> = bug_numbers(comment).collect();
+            for bug in bugs {
+                self.bugs.entry(bug).or_default().push(BugReference {
+                    source: BugReferenceSource::Comment,
+                    path: path.to_string(),
+                    line,
+                    lines: 1,
+                    rev: None,
+                    text: comment.trim().to_string(),
+                });
+            }
+        }
+    }
+
+    /// Add the bugs referenced by the commit messages of the revisions that
+    /// last changed the lines of a file, given the file's blame lines and a
+    /// way to look up a revision's commit message.  Each revision gets one
+    /// reference covering all of the lines it last changed.
+    pub fn add_commit_references<F>(&mut self, path: &str, blame_lines: &[String], mut message: F)
+    where
+        F: FnMut(&str) -> Option<String>,
+    {
+        // (rev, first line, line count) for each revision, in order of appearance.
+        let mut revs: Vec<(String, u32, u32)> = vec![];
+        let mut rev_index: BTreeMap<String, usize> = BTreeMap::new();
+        for (i, blame_line) in blame_lines.iter().enumerate() {
+            let rev = LineData::deserialize(blame_line).rev;
+            match rev_index.get(rev.as_ref()) {
+                Some(index) => revs[*index].2 += 1,
+                None => {
+                    rev_index.insert(rev.to_string(), revs.len());
+                    revs.push((rev.to_string(), i as u32 + 1, 1));
+                }
+            }
+        }
+
+        for (rev, line, lines) in revs {
+            let Some(message) = message(&rev) else {
+                continue;
+            };
+            let bugs: BTreeSet<u64> = bug_numbers(&message).collect();
+            for bug in bugs {
+                self.bugs.entry(bug).or_default().push(BugReference {
+                    source: BugReferenceSource::Commit,
+                    path: path.to_string(),
+                    line,
+                    lines,
+                    rev: Some(rev.clone()),
+                    text: message.lines().next().unwrap_or_default().to_string(),
+                });
+            }
+        }
+    }
+}
+
+#[test]
+fn test_bug_references() {
+    assert_eq!(
+        bug_numbers("Bug 123456 - Fix; see bugzil.la/234567 and bug #345678").collect::<Vec<_>>(),
+        vec![123456, 234567, 345678]
+    );
+
+    let mut index = BugReferenceIndex::default();
+    index.add_comment_references(
+        "bugs.cpp",
+        "// See bug 1234567.\nint x = 0; // bug 1234567 again, and bug 7654321\nconst char* s = \"bug 1111111\";\n",
+    );
+    let blame_lines: Vec<String> = ["abc:%:1:", "def:%:2:", "abc:%:3:"]
+        .iter()
+        .map(|line| line.to_string())
+        .collect();
+    index.add_commit_references("bugs.cpp", &blame_lines, |rev| match rev {
+        "abc" => Some("Bug 1234567 - Add x. r=someone\n\nMore details.".to_string()),
+        _ => Some("No bug: whitespace".to_string()),
+    });
+
+    assert_eq!(
+        index.bugs.keys().copied().collect::<Vec<_>>(),
+        vec![1234567, 7654321]
+    );
+    let refs = &index.bugs[&1234567];
+    assert_eq!(
+        refs.iter()
+            .map(|r| (r.source, r.line, r.lines))
+            .collect::<Vec<_>>(),
+        vec![
+            (BugReferenceSource::Comment, 1, 1),
+            (BugReferenceSource::Comment, 2, 1),
+            (BugReferenceSource::Commit, 1, 2),
+        ]
+    );
+    assert_eq!(refs[2].rev.as_deref(), Some("abc"));
+    assert_eq!(refs[2].text, "Bug 1234567 - Add x. r=someone");
+    assert_eq!(index.bugs[&7654321][0].line, 2);
+}
@@ -8,6 +8,9 @@ use serde::{Deserialize, Serialize};
 
 use git2::{Oid, Repository};
 
+use crate::file_format::asset_manifest::{AssetManifest, ASSET_MANIFEST_FILENAME};
+use crate::redaction::Redactor;
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum TreeCaching {
@@ -70,6 +73,11 @@ pub struct TreeConfigPaths {
     /// be mapped into `"__GENERATED__"`.  This will usually be a sub-directory
     /// of the `index_path` but exceptions could be possible.
     pub objdir_path: String,
+    /// Absolute path to a directory outside of the `index_path` for
+    /// deployment-local state that must survive reindexing, like the symbol
+    /// annotations set by `annotate`.  Commands needing it are unavailable if
+    /// this isn't configured.
+    pub state_path: Option<String>,
     /// List of the path prefixes where files may be missing at the point of
     /// gathering metadata.
     #[serde(default)]
@@ -93,11 +101,155 @@ pub struct TreeConfigPaths {
     /// that the build script will handle downloading or generating the indexes.
     #[serde(default)]
     pub scip_subtrees: BTreeMap<String, ScipSubtreeConfig>,
+    /// Named sets of searchfox path globs (ex: "tests", "third_party") that
+    /// pipeline commands can reference via `--path-set` and
+    /// `--exclude-path-set`.
+    #[serde(default)]
+    pub path_sets: BTreeMap<String, Vec<String>>,
+    /// Named query presets that can be run via `query run <name>` or the
+    /// pipeline-server's `/:tree/query/:preset` route.  These are merged over
+    /// the built-in presets from `query/query_presets.toml`.
+    #[serde(default)]
+    pub query_presets: BTreeMap<String, QueryPresetConfig>,
+    /// Named pipelines to run automatically after each index build (ex: a
+    /// padding audit), whose outputs are stored under the index.  See
+    /// `query/post_index_hooks.rs`.
+    #[serde(default)]
+    pub post_index_hooks: BTreeMap<String, PostIndexHookConfig>,
+    /// Hot symbols whose expensive query presets should be run at index time
+    /// by `precompute` so their results are cached.  See
+    /// `query/pipeline_cache.rs`.
+    #[serde(default)]
+    pub precompute: PrecomputeConfig,
+    /// Path prefixes only some requesters may see.  See
+    /// `abstract_server/access_control.rs`.
+    #[serde(default)]
+    pub access_control: AccessControlConfig,
+    /// Patterns of likely secrets to mask in rendered source and search
+    /// results.  See `redaction.rs`.
+    #[serde(default)]
+    pub redaction: RedactionConfig,
+    /// Write gzipped copies of the HTML pages rendered by `render` and
+    /// `batch-render` side-by-side with them so the web server can serve the
+    /// compressed files directly.
+    #[serde(default)]
+    pub precompress_html: bool,
+}
+
+/// A named, parameterized query or pipeline.  Exactly one of `query`,
+/// `pipeline`, or `spec` should be specified.  Parameters are referenced in the
+/// query/pipeline as `{name}` and are provided to specs as variables.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct QueryPresetConfig {
+    #[serde(default)]
+    pub description: String,
+    /// A `query-parser` style `term:value` query.
+    pub query: Option<String>,
+    /// A `|`-delimited pipeline of commands like searchfox-tool accepts.
+    pub pipeline: Option<String>,
+    /// Absolute path to a pipeline spec file; see `query/pipeline_spec.rs`.
+    pub spec: Option<String>,
+    /// The names of the parameters the spec uses; all must be provided unless
+    /// they have an entry in `defaults`.
+    #[serde(default)]
+    pub params: Vec<String>,
+    #[serde(default)]
+    pub defaults: BTreeMap<String, String>,
+}
+
+/// A pipeline for `run-post-index-hooks` to run after each index build.  This
+/// is specified like a query preset, with the preset's `defaults` providing the
+/// values for any `params`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PostIndexHookConfig {
+    #[serde(flatten)]
+    pub preset: QueryPresetConfig,
+    /// How many runs' outputs to keep around; older outputs are deleted.
+    #[serde(default = "default_post_index_hook_retention")]
+    pub retention: usize,
+}
+
+fn default_post_index_hook_retention() -> usize {
+    4
+}
+
+/// The query presets `precompute` should run for each of the hot symbols.
+/// Each preset must take exactly one parameter without a default, which the
+/// symbol is provided as.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PrecomputeConfig {
+    #[serde(default)]
+    pub presets: Vec<String>,
+    #[serde(default)]
+    pub symbols: Vec<String>,
+    /// Absolute path to a file listing additional hot symbols one per line.
+    pub symbols_file: Option<String>,
+    /// Also treat this many of the most popular symbols per the query logs
+    /// ingested by `ingest-query-logs` as hot.
+    #[serde(default)]
+    pub popular_symbols: usize,
+}
+
+/// Restricts path prefixes of the tree to requesters in specific groups.  The
+/// groups of a request are provided by the authenticating proxy in front of
+/// the servers as a comma-delimited list in the `groups_header` header, which
+/// the proxy must always overwrite so that clients can't provide it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AccessControlConfig {
+    #[serde(default = "default_groups_header")]
+    pub groups_header: String,
+    #[serde(default)]
+    pub restricted: Vec<RestrictedPathConfig>,
+}
+
+impl Default for AccessControlConfig {
+    fn default() -> Self {
+        AccessControlConfig {
+            groups_header: default_groups_header(),
+            restricted: vec![],
+        }
+    }
+}
+
+fn default_groups_header() -> String {
+    "X-Searchfox-Groups".to_string()
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RestrictedPathConfig {
+    /// The tree-relative path prefix, ex: "security/embargoed/".
+    pub prefix: String,
+    /// The groups allowed to see the paths; requesters in none of them won't.
+    pub groups: Vec<String>,
+}
+
+/// Rules for masking likely secrets, for trees indexing semi-private
+/// repositories.  Redaction is disabled unless there are rules.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RedactionConfig {
+    /// Also use the built-in rules for common key and token formats (ex: AWS
+    /// access keys, GitHub tokens, and private key blocks).
+    #[serde(default)]
+    pub builtin_rules: bool,
+    #[serde(default)]
+    pub rules: Vec<RedactionRuleConfig>,
+    /// Absolute path to a file to append a JSON line to for each redaction.
+    pub audit_log: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RedactionRuleConfig {
+    /// The name of the rule for the audit log, ex: "internal-hostname".
+    pub name: String,
+    /// The regexp to mask matches of.  If it has a capture group, only the
+    /// first group is masked.
+    pub pattern: String,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ScipSubtreeConfig {
-    /// The path the SCIP index can be found at.
+    /// The path the SCIP index can be found at.  This can also be an LSIF dump
+    /// with a ".lsif" extension for languages with an `LsifLanguageRule`.
     pub scip_index_path: String,
     /// The tree-relative path where the files referenced by the index can be
     /// found.  For example, if there's a JS subtree that lives at
@@ -126,6 +278,10 @@ pub struct GitData {
 pub struct TreeConfig {
     pub paths: TreeConfigPaths,
     pub git: Option<GitData>,
+    /// Compiled from `paths.redaction` if it has any rules.
+    pub redactor: Option<Redactor>,
+    /// Written by `render asset-manifest`, and empty before that has run.
+    pub asset_manifest: AssetManifest,
 }
 
 impl TreeConfig {
@@ -263,11 +419,35 @@ pub fn load(
     url_map_path: Option<String>,
     doc_trees_path: Option<String>,
 ) -> Config {
-    let config_file = File::open(config_path).unwrap();
+    try_load(
+        config_path,
+        need_indexes,
+        only_tree,
+        url_map_path,
+        doc_trees_path,
+    )
+    .unwrap()
+}
+
+/// Like `load`, but reports problems with the config file, the repositories it
+/// names, or its redaction rules as an error instead of panicking.  Used by
+/// long-running servers which need to survive being pointed at a bad config.
+pub fn try_load(
+    config_path: &str,
+    need_indexes: bool,
+    only_tree: Option<&str>,
+    url_map_path: Option<String>,
+    doc_trees_path: Option<String>,
+) -> Result<Config, String> {
+    let config_file = File::open(config_path)
+        .map_err(|e| format!("Unable to open config {}: {}", config_path, e))?;
     let mut reader = BufReader::new(&config_file);
     let mut input = String::new();
-    reader.read_to_string(&mut input).unwrap();
-    let config: ConfigJson = serde_json::from_str(&input).unwrap();
+    reader
+        .read_to_string(&mut input)
+        .map_err(|e| format!("Unable to read config {}: {}", config_path, e))?;
+    let config: ConfigJson = serde_json::from_str(&input)
+        .map_err(|e| format!("Unable to parse config {}: {}", config_path, e))?;
 
     let mut trees = BTreeMap::new();
     for (tree_name, paths) in config.trees {
@@ -279,11 +459,11 @@ pub fn load(
 
         let git = match (&paths.git_path, &paths.git_blame_path) {
             (Some(git_path), Some(git_blame_path)) => {
-                let repo = Repository::open(git_path).unwrap();
+                let repo = open_repo(&tree_name, git_path)?;
                 let mailmap = Mailmap::load(&repo);
                 let blame_ignore = BlameIgnoreList::load(&repo);
 
-                let blame_repo = Repository::open(git_blame_path).unwrap();
+                let blame_repo = open_repo(&tree_name, git_blame_path)?;
                 let (blame_map, hg_map) = if need_indexes {
                     index_blame(&blame_repo, None)
                 } else {
@@ -300,7 +480,7 @@ pub fn load(
                 })
             }
             (Some(git_path), &None) => {
-                let repo = Repository::open(git_path).unwrap();
+                let repo = open_repo(&tree_name, git_path)?;
                 let mailmap = Mailmap::load(&repo);
                 let blame_ignore = BlameIgnoreList::load(&repo);
 
@@ -316,16 +496,37 @@ pub fn load(
             _ => None,
         };
 
-        trees.insert(tree_name, TreeConfig { paths, git });
+        let redactor = Redactor::new(&paths.redaction)
+            .map_err(|e| format!("Bad redaction rule for tree {}: {}", tree_name, e))?;
+
+        let asset_manifest_path =
+            format!("{}/templates/{}", paths.index_path, ASSET_MANIFEST_FILENAME);
+        let asset_manifest = AssetManifest::load_or_default(&asset_manifest_path)
+            .map_err(|e| format!("Unable to load {}: {}", asset_manifest_path, e))?;
+
+        trees.insert(
+            tree_name,
+            TreeConfig {
+                paths,
+                git,
+                redactor,
+                asset_manifest,
+            },
+        );
     }
 
-    Config {
+    Ok(Config {
         trees,
         mozsearch_path: config.mozsearch_path,
         config_repo_path: config.config_repo,
         url_map_path,
         doc_trees_path,
-    }
+    })
+}
+
+fn open_repo(tree_name: &str, path: &str) -> Result<Repository, String> {
+    Repository::open(path)
+        .map_err(|e| format!("Unable to open repo {} for tree {}: {}", path, tree_name, e))
 }
 
 #[derive(Hash, Eq, PartialEq, Debug)]
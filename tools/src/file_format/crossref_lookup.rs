@@ -1,18 +1,21 @@
 extern crate memmap;
 
 use self::memmap::Mmap;
+use std::fmt;
 use std::fs::File;
 use std::str;
 use std::sync::Arc;
 use std::{cmp::Ordering, collections::HashMap};
 
-use serde_json::{from_slice, Value};
+use serde::de::{DeserializeSeed, IgnoredAny, MapAccess, Visitor};
+use serde_json::{from_slice, Deserializer, Map, Value};
 
 use crate::{
     abstract_server::Result,
-    abstract_server::{ErrorDetails, ErrorLayer, ServerError},
+    abstract_server::{ErrorDetails, ErrorKind, ErrorLayer, ServerError},
 };
 
+use super::analysis::migrate_structured_value;
 use super::config::Config;
 
 #[derive(Clone, Debug)]
@@ -28,10 +31,14 @@ const INLINE_STORED: u8 = b':';
 const EXTERNALLY_STORED: u8 = b'@';
 
 fn make_crossref_data_error(sym: &str) -> ServerError {
-    ServerError::StickyProblem(ErrorDetails {
-        layer: ErrorLayer::DataLayer,
-        message: format!("bad crossref data for symbol: {}", sym),
-    })
+    ServerError::StickyProblem(
+        ErrorDetails::new(
+            ErrorKind::Internal,
+            ErrorLayer::DataLayer,
+            format!("bad crossref data for symbol: {}", sym),
+        )
+        .with_context("symbol", sym),
+    )
 }
 
 // This implementation is a port of `crossrefs.py` (which was adapted from
@@ -197,12 +204,16 @@ impl CrossrefLookupMap {
         &[]
     }
 
-    pub fn lookup(&self, sym: &str) -> Result<Value> {
+    /// Locate the raw JSON bytes for `sym` without parsing them, also returning
+    /// whether the payload was stored inline.  The returned slice borrows
+    /// directly from the memory map, so this allocates nothing.  A miss is
+    /// returned as an empty slice.
+    fn locate_payload(&self, sym: &str) -> Result<(&[u8], bool)> {
         let payload = self.bisect_for_payload(sym.as_bytes());
         let payload_len = payload.len();
         // Finding nothing (a miss!) is not an error and so is an in-band null.
         if payload_len == 0 {
-            return Ok(Value::Null);
+            return Ok((&[], false));
         }
         // Let's also rule out results that are too short and therefore must be
         // an error.
@@ -213,7 +224,7 @@ impl CrossrefLookupMap {
         let marker_char = payload[0];
 
         if marker_char == INLINE_STORED {
-            return from_slice(&payload[1..]).or(Ok(Value::Null));
+            return Ok((&payload[1..], true));
         } else if marker_char != EXTERNALLY_STORED {
             // Fail if we're seeing something other than an external ref.
             return Err(make_crossref_data_error(sym));
@@ -234,8 +245,108 @@ impl CrossrefLookupMap {
         };
 
         let extra_bytes: &[u8] = self.extra_mm.as_ref();
-        Ok(from_slice(
-            &extra_bytes[brace_offset..brace_offset + length_with_newline - 1],
-        )?)
+        let raw = extra_bytes
+            .get(brace_offset..brace_offset + length_with_newline - 1)
+            .ok_or_else(|| make_crossref_data_error(sym))?;
+        Ok((raw, false))
+    }
+
+    /// Iterate over all of the symbols in the crossref in their sorted order.
+    /// This scans the entire inline file, so it's only appropriate for offline
+    /// uses like `verify-index`.
+    pub fn symbols(&self) -> impl Iterator<Item = &str> {
+        let bytes: &[u8] = self.inline_mm.as_ref();
+        bytes
+            .split(|b| *b == NEWLINE)
+            .filter_map(|line| match line.first() {
+                Some(&ID_START) => str::from_utf8(&line[1..]).ok(),
+                _ => None,
+            })
+    }
+
+    /// Return the raw JSON bytes for `sym` straight out of the memory map so
+    /// that callers can parse as much or as little as they need.  A miss is
+    /// returned as an empty slice.
+    pub fn lookup_raw(&self, sym: &str) -> Result<&[u8]> {
+        Ok(self.locate_payload(sym)?.0)
+    }
+
+    pub fn lookup(&self, sym: &str) -> Result<Value> {
+        let (raw, inline) = self.locate_payload(sym)?;
+        if raw.is_empty() {
+            return Ok(Value::Null);
+        }
+        if inline {
+            return from_slice(raw).or(Ok(Value::Null));
+        }
+        Ok(from_slice(raw)?)
+    }
+
+    /// Like `lookup` but only materializes the given top-level keys (ex:
+    /// "meta") of the crossref entry.  The values of all other keys are
+    /// scanned over without being allocated, which matters a lot for symbols
+    /// with many thousands of uses when the caller only wants the metadata.
+    pub fn lookup_keys(&self, sym: &str, keys: &[&str]) -> Result<Value> {
+        let (raw, inline) = self.locate_payload(sym)?;
+        if raw.is_empty() {
+            return Ok(Value::Null);
+        }
+        let mut de = Deserializer::from_slice(raw);
+        match SelectedKeys(keys).deserialize(&mut de) {
+            Ok(map) => Ok(Value::Object(map)),
+            Err(_) if inline => Ok(Value::Null),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Deserialize only the requested top-level keys of a JSON object, using
+/// `IgnoredAny` to skip over the values of all other keys without allocating.
+struct SelectedKeys<'k>(&'k [&'k str]);
+
+impl<'de, 'k> DeserializeSeed<'de> for SelectedKeys<'k> {
+    type Value = Map<String, Value>;
+
+    fn deserialize<D>(self, deserializer: D) -> std::result::Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, 'k> Visitor<'de> for SelectedKeys<'k> {
+    type Value = Map<String, Value>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a crossref JSON object")
+    }
+
+    fn visit_map<A>(self, mut access: A) -> std::result::Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut map = Map::new();
+        while let Some(key) = access.next_key::<String>()? {
+            if self.0.contains(&key.as_str()) {
+                let value: Value = access.next_value()?;
+                map.insert(key, value);
+            } else {
+                access.next_value::<IgnoredAny>()?;
+            }
+        }
+        Ok(map)
+    }
+}
+
+/// Run the "meta" of a crossref entry (which is a serialized structured record)
+/// through the structured record schema migration layer so that consumers only
+/// ever see the current schema, and so that an index produced by incompatible
+/// indexing tools results in a clear error instead of confusing failures
+/// further down the pipeline.
+pub fn migrate_crossref_meta(mut crossref: Value) -> Result<Value> {
+    if let Some(meta) = crossref.get_mut("meta") {
+        *meta = migrate_structured_value(meta.take())?;
     }
+    Ok(crossref)
 }
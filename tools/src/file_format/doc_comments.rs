@@ -0,0 +1,189 @@
+/// Extract the documentation comment associated with a definition found on the
+/// 1-based `def_lineno` of `lines`, returning the comment text with the comment
+/// syntax stripped.  We understand:
+/// - `///` line comments immediately preceding the definition.
+/// - `/** ... */` block comments immediately preceding the definition.
+/// - Python docstrings immediately following a `def` or `class` line.
+///
+/// Attribute/decorator lines like `#[derive(...)]` and `@property` between the
+/// comment and the definition are skipped over.  Lines are expected to be the
+/// raw source lines, but leading whitespace is ignored so the trimmed lines
+/// `crossref.rs` works with are fine too.
+///
+/// This is intentionally heuristic; the language analyzers are in a better
+/// position to do this properly and should be preferred if they ever start
+/// emitting this information.
+pub fn extract_doc_comment<S: AsRef<str>>(lines: &[S], def_lineno: u32) -> Option<String> {
+    if def_lineno == 0 || def_lineno as usize > lines.len() {
+        return None;
+    }
+    let def_idx = (def_lineno - 1) as usize;
+
+    let doc = extract_preceding_comment(lines, def_idx)
+        .or_else(|| extract_python_docstring(lines, def_idx))?;
+    if doc.is_empty() {
+        None
+    } else {
+        Some(doc)
+    }
+}
+
+fn is_attribute_line(line: &str) -> bool {
+    line.starts_with("#[") || line.starts_with('@')
+}
+
+fn extract_preceding_comment<S: AsRef<str>>(lines: &[S], def_idx: usize) -> Option<String> {
+    let mut idx = def_idx;
+    while idx > 0 && is_attribute_line(lines[idx - 1].as_ref().trim()) {
+        idx -= 1;
+    }
+    if idx == 0 {
+        return None;
+    }
+
+    let prev = lines[idx - 1].as_ref().trim();
+    if prev.starts_with("///") && !prev.starts_with("////") {
+        let mut doc_lines = vec![];
+        while idx > 0 {
+            let line = lines[idx - 1].as_ref().trim();
+            match line.strip_prefix("///") {
+                Some(rest) if !rest.starts_with('/') => {
+                    doc_lines.push(rest.strip_prefix(' ').unwrap_or(rest));
+                }
+                _ => break,
+            }
+            idx -= 1;
+        }
+        doc_lines.reverse();
+        return Some(doc_lines.join("\n").trim().to_string());
+    }
+
+    if prev.ends_with("*/") {
+        let end_idx = idx - 1;
+        let mut start_idx = end_idx;
+        loop {
+            let line = lines[start_idx].as_ref().trim();
+            if line.starts_with("/**") && !line.starts_with("/***") {
+                break;
+            }
+            // Either not a doc comment or we ran off the top of the file.
+            if line.starts_with("/*") || start_idx == 0 {
+                return None;
+            }
+            start_idx -= 1;
+        }
+
+        let doc_lines: Vec<&str> = lines[start_idx..=end_idx]
+            .iter()
+            .map(|line| {
+                let mut line = line.as_ref().trim();
+                line = line.strip_prefix("/**").unwrap_or(line);
+                line = line.strip_suffix("*/").unwrap_or(line);
+                line = line.trim();
+                line = line.strip_prefix('*').unwrap_or(line);
+                line.strip_prefix(' ').unwrap_or(line).trim_end()
+            })
+            .collect();
+        return Some(doc_lines.join("\n").trim().to_string());
+    }
+
+    None
+}
+
+fn extract_python_docstring<S: AsRef<str>>(lines: &[S], def_idx: usize) -> Option<String> {
+    let def_line = lines[def_idx].as_ref().trim();
+    if !(def_line.starts_with("def ")
+        || def_line.starts_with("async def ")
+        || def_line.starts_with("class "))
+    {
+        return None;
+    }
+
+    // The signature may span multiple lines; the docstring starts after the
+    // line that ends with the colon.
+    let mut idx = def_idx;
+    while idx < lines.len() && !lines[idx].as_ref().trim_end().ends_with(':') {
+        idx += 1;
+    }
+    idx += 1;
+    while idx < lines.len() && lines[idx].as_ref().trim().is_empty() {
+        idx += 1;
+    }
+    if idx >= lines.len() {
+        return None;
+    }
+
+    let first = lines[idx].as_ref().trim();
+    let first = first
+        .strip_prefix('r')
+        .or_else(|| first.strip_prefix('R'))
+        .unwrap_or(first);
+    let quote = if first.starts_with("\"\"\"") {
+        "\"\"\""
+    } else if first.starts_with("'''") {
+        "'''"
+    } else {
+        return None;
+    };
+
+    let first = &first[quote.len()..];
+    // Single-line docstring.
+    if let Some(end) = first.find(quote) {
+        return Some(first[..end].trim().to_string());
+    }
+
+    let mut doc_lines = vec![first.trim()];
+    for line in &lines[idx + 1..] {
+        let line = line.as_ref().trim();
+        if let Some(end) = line.find(quote) {
+            doc_lines.push(line[..end].trim());
+            return Some(doc_lines.join("\n").trim().to_string());
+        }
+        doc_lines.push(line);
+    }
+
+    // Unterminated docstring; don't make something up.
+    None
+}
+
+#[test]
+fn test_extract_doc_comment() {
+    let rust = [
+        "/// Frobs the widget.",
+        "///",
+        "/// Really.",
+        "#[inline]",
+        "fn frob() {}",
+    ];
+    assert_eq!(
+        extract_doc_comment(&rust, 5).as_deref(),
+        Some("Frobs the widget.\n\nReally.")
+    );
+
+    let cpp = [
+        "/**",
+        " * Returns the thing.",
+        " */",
+        "Thing* GetThing();",
+        "/* Not a doc comment. */",
+        "void Other();",
+    ];
+    assert_eq!(
+        extract_doc_comment(&cpp, 4).as_deref(),
+        Some("Returns the thing.")
+    );
+    assert_eq!(extract_doc_comment(&cpp, 6), None);
+
+    let python = [
+        "def frob(a,",
+        "         b):",
+        "    \"\"\"Frob a and b.",
+        "",
+        "    Returns nothing.\"\"\"",
+        "    pass",
+    ];
+    assert_eq!(
+        extract_doc_comment(&python, 1).as_deref(),
+        Some("Frob a and b.\n\nReturns nothing.")
+    );
+}
@@ -3,7 +3,8 @@ extern crate memmap;
 use self::memmap::Mmap;
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::BufRead;
+use std::io::{BufRead, BufWriter, Write};
+use std::path::Path;
 
 use std::str;
 use std::sync::Arc;
@@ -21,6 +22,64 @@ fn uppercase(s: &[u8]) -> Vec<u8> {
 #[derive(Clone, Debug)]
 pub struct IdentMap {
     mmap: Arc<Mmap>,
+    /// The suffix array written by `write_identifier_suffixes`, if the index
+    /// has one.
+    suffixes: Option<Arc<Mmap>>,
+}
+
+/// The suffix array of an identifiers file is stored next to it with this
+/// suffix appended to its name.
+pub const IDENTIFIER_SUFFIXES_EXTENSION: &str = "-suffixes";
+
+/// The end of the identifier that starts at or contains `pos`, which is the
+/// space separating it from its symbol.
+fn identifier_end(bytes: &[u8], pos: usize) -> usize {
+    bytes[pos..]
+        .iter()
+        .position(|b| *b == b' ' || *b == b'\n')
+        .map_or(bytes.len(), |p| pos + p)
+}
+
+fn cmp_uppercase(a: &[u8], b: &[u8]) -> std::cmp::Ordering {
+    a.iter()
+        .map(u8::to_ascii_uppercase)
+        .cmp(b.iter().map(u8::to_ascii_uppercase))
+}
+
+/// Write a suffix array for substring searches of the (already sorted)
+/// identifiers file: the offset of every suffix of every identifier as a
+/// little-endian u32, sorted case-insensitively by the suffix's text up to the
+/// end of its identifier.  Finding the identifiers containing a needle is then
+/// a binary search for the range of suffixes it's a prefix of.
+pub fn write_identifier_suffixes(identifiers: &[u8], path: &str) -> std::io::Result<()> {
+    let mut offsets: Vec<u32> = vec![];
+    let mut line_start = 0;
+    while line_start < identifiers.len() {
+        let id_end = identifier_end(identifiers, line_start);
+        for pos in line_start..id_end {
+            // Only start suffixes on UTF-8 character boundaries.
+            if (identifiers[pos] & 0xc0) != 0x80 {
+                offsets.push(pos as u32);
+            }
+        }
+        line_start = match identifiers[id_end..].iter().position(|b| *b == b'\n') {
+            Some(p) => id_end + p + 1,
+            None => identifiers.len(),
+        };
+    }
+    offsets.sort_unstable_by(|a, b| {
+        let (a, b) = (*a as usize, *b as usize);
+        cmp_uppercase(
+            &identifiers[a..identifier_end(identifiers, a)],
+            &identifiers[b..identifier_end(identifiers, b)],
+        )
+    });
+
+    let mut out = BufWriter::new(File::create(path)?);
+    for offset in offsets {
+        out.write_all(&offset.to_le_bytes())?;
+    }
+    out.flush()
 }
 
 #[derive(Serialize, Deserialize)]
@@ -63,10 +122,21 @@ impl IdentMap {
                 return None;
             }
         };
+        let suffixes_path = format!("{}{}", filename, IDENTIFIER_SUFFIXES_EXTENSION);
+        let suffixes = if Path::new(&suffixes_path).exists() {
+            File::open(&suffixes_path)
+                .and_then(|file| unsafe { Mmap::map(&file) })
+                .map_err(|e| warn!("Failed to mmap {}: {:?}", suffixes_path, e))
+                .ok()
+                .map(Arc::new)
+        } else {
+            None
+        };
         unsafe {
             match Mmap::map(&file) {
                 Ok(mmap) => Some(IdentMap {
                     mmap: Arc::new(mmap),
+                    suffixes,
                 }),
                 Err(e) => {
                     warn!("Failed to mmap {}: {:?}", filename, e);
@@ -192,6 +262,94 @@ impl IdentMap {
         result
     }
 
+    pub fn has_suffixes(&self) -> bool {
+        self.suffixes.is_some()
+    }
+
+    /// Find the identifiers containing the needle anywhere using the suffix
+    /// array, in identifier order.  Unlike `lookup`, identifiers with hierarchy
+    /// separators after the needle are included since the needle may itself be
+    /// in the middle of a qualified name.  Returns nothing if there is no
+    /// suffix array.
+    pub fn lookup_substring(
+        &self,
+        needle: &str,
+        ignore_case: bool,
+        max_results: usize,
+    ) -> Vec<IdentResult> {
+        let Some(suffixes) = &self.suffixes else {
+            return vec![];
+        };
+        let bytes = self.mmap.as_ref();
+        let offset_at = |i: usize| -> usize {
+            let mut offset = [0; 4];
+            offset.copy_from_slice(&suffixes[i * 4..i * 4 + 4]);
+            u32::from_le_bytes(offset) as usize
+        };
+        let suffix_at = |i: usize| {
+            let start = offset_at(i);
+            &bytes[start..identifier_end(bytes, start)]
+        };
+        let needle_bytes = needle.as_bytes();
+        let partition_point = |pred: &dyn Fn(&[u8]) -> bool| -> usize {
+            let (mut first, mut count) = (0, suffixes.len() / 4);
+            while count > 0 {
+                let step = count / 2;
+                if pred(suffix_at(first + step)) {
+                    first += step + 1;
+                    count -= step + 1;
+                } else {
+                    count = step;
+                }
+            }
+            first
+        };
+        let is_before = |suffix: &[u8]| cmp_uppercase(suffix, needle_bytes).is_lt();
+        let start = partition_point(&is_before);
+        let end = partition_point(&|suffix: &[u8]| {
+            is_before(suffix)
+                || (suffix.len() >= needle_bytes.len()
+                    && cmp_uppercase(&suffix[..needle_bytes.len()], needle_bytes).is_eq())
+        });
+
+        let mut line_starts: Vec<usize> = (start..end)
+            .map(|i| {
+                let offset = offset_at(i);
+                bytes[..offset]
+                    .iter()
+                    .rposition(|b| *b == b'\n')
+                    .map_or(0, |p| p + 1)
+            })
+            .collect();
+        line_starts.sort_unstable();
+        line_starts.dedup();
+
+        let mut result = vec![];
+        for line_start in line_starts {
+            let line_end = bytes[line_start..]
+                .iter()
+                .position(|b| *b == b'\n')
+                .map_or(bytes.len(), |p| line_start + p);
+            let Ok(line) = str::from_utf8(&bytes[line_start..line_end]) else {
+                continue;
+            };
+            let Some((id, symbol)) = line.rsplit_once(' ') else {
+                continue;
+            };
+            if !ignore_case && !id.contains(needle) {
+                continue;
+            }
+            result.push(IdentResult {
+                id: ustr(id),
+                symbol: ustr(symbol),
+            });
+            if result.len() == max_results {
+                break;
+            }
+        }
+        result
+    }
+
     pub fn lookup_json(
         &self,
         needle: &str,
@@ -203,3 +361,40 @@ impl IdentMap {
         to_string(&results).unwrap()
     }
 }
+
+#[test]
+fn test_lookup_substring() {
+    let identifiers = b"nsIFrame F_1\n\
+nsScrollContainerFrame T_2\n\
+nsScrollContainerFrame::ScrollTo F_3\n\
+ScrollContainer T_4\n\
+SCROLLCONTAINER_MAX V_5\n";
+    let dir = std::env::temp_dir();
+    let path = dir.join(format!("identifiers-{}", std::process::id()));
+    let path = path.to_str().unwrap();
+    std::fs::write(path, identifiers).unwrap();
+    write_identifier_suffixes(
+        identifiers,
+        &format!("{}{}", path, IDENTIFIER_SUFFIXES_EXTENSION),
+    )
+    .unwrap();
+
+    let map = IdentMap::new(path).unwrap();
+    assert!(map.has_suffixes());
+    let symbols = |results: Vec<IdentResult>| -> Vec<String> {
+        results.iter().map(|r| r.symbol.to_string()).collect()
+    };
+    assert_eq!(
+        symbols(map.lookup_substring("ScrollContainer", false, 10)),
+        vec!["T_2", "F_3", "T_4"]
+    );
+    assert_eq!(
+        symbols(map.lookup_substring("scrollcontainer", true, 10)),
+        vec!["T_2", "F_3", "T_4", "V_5"]
+    );
+    assert_eq!(
+        symbols(map.lookup_substring("Frame", false, 1)),
+        vec!["F_1"]
+    );
+    assert!(map.lookup_substring("Nope", true, 10).is_empty());
+}
@@ -0,0 +1,54 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, ErrorKind};
+
+use serde::{Deserialize, Serialize};
+
+/// The contents of `index-manifest.json` as written at the end of indexing by
+/// `scripts/write-index-manifest.py`, recording what the index was generated
+/// from.  Every field is optional so that manifests from older or partial
+/// indexing runs still parse.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct IndexManifest {
+    pub format: u32,
+    pub tree: Option<String>,
+    /// The git revision of the tree that was indexed.
+    pub revision: Option<String>,
+    pub mozsearch_revision: Option<String>,
+    pub config_revision: Option<String>,
+    /// SHA-256 of the config file used for indexing.
+    pub config_sha256: Option<String>,
+    /// Tool name to version string for the tools used by indexing.
+    pub tools: BTreeMap<String, Option<String>>,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+    /// The `analysis-*` directories (ex: per-platform analysis data) which were
+    /// present in the index directory.
+    pub analysis_inputs: Vec<String>,
+    pub scip_subtrees: Vec<String>,
+}
+
+pub const INDEX_MANIFEST_FILENAME: &str = "index-manifest.json";
+
+/// Load the manifest from the given path, returning None if there is no
+/// manifest because the index predates manifests.
+pub fn load_index_manifest(path: &str) -> std::io::Result<Option<IndexManifest>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    Ok(Some(serde_json::from_reader(BufReader::new(file))?))
+}
+
+#[test]
+fn test_partial_manifest() {
+    let manifest: IndexManifest =
+        serde_json::from_str(r#"{"format": 1, "revision": "abc", "tools": {"clang": null}}"#)
+            .unwrap();
+    assert_eq!(manifest.revision.as_deref(), Some("abc"));
+    assert_eq!(manifest.tools.get("clang"), Some(&None));
+    assert!(manifest.started_at.is_none());
+    assert!(manifest.analysis_inputs.is_empty());
+}
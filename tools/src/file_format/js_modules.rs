@@ -0,0 +1,279 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::languages::select_formatting;
+use crate::tokenize::{tokenize_as, TokenKind};
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JsImportKind {
+    /// A static ESM `import`.
+    Import,
+    /// An `export ... from` re-export.
+    ReExport,
+    /// A dynamic `import()`.
+    DynamicImport,
+    /// `ChromeUtils.importESModule` or the legacy `ChromeUtils.import`.
+    ChromeUtils,
+    /// A lazy module getter, ex: `ChromeUtils.defineESModuleGetters`.
+    LazyGetter,
+}
+
+/// An import edge from a JS module.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct JsImport {
+    pub kind: JsImportKind,
+    /// The module specifier as written, ex: "resource://gre/modules/Foo.sys.mjs".
+    pub specifier: String,
+    /// The 1-based line number of the specifier.
+    pub line: u32,
+    /// The tree-relative path the specifier resolves to, if it could be
+    /// resolved to a file in the tree.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+}
+
+/// Index of the import edges between the tree's JS modules, which crossref
+/// writes to `js-modules.json` in the tree's index directory for the
+/// `js-module-graph` command.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct JsModuleIndex {
+    /// The imports of each module with any imports, keyed by path.
+    #[serde(default)]
+    pub modules: BTreeMap<String, Vec<JsImport>>,
+}
+
+pub const JS_MODULE_INDEX_FILENAME: &str = "js-modules.json";
+
+lazy_static! {
+    static ref STATIC_IMPORT_RE: Regex =
+        Regex::new(r#"(?m)^\s*import\s+(?:[\w*${},\s]+?\s+from\s+)?["']([^"'\n]+)["']"#).unwrap();
+    static ref RE_EXPORT_RE: Regex = Regex::new(
+        r#"(?m)^\s*export\s+(?:\*(?:\s+as\s+[\w$]+)?|\{[^}]*\})\s+from\s+["']([^"'\n]+)["']"#
+    )
+    .unwrap();
+    static ref DYNAMIC_IMPORT_RE: Regex =
+        Regex::new(r#"\bimport\(\s*["']([^"'\n]+)["']\s*\)"#).unwrap();
+    static ref CHROME_UTILS_RE: Regex =
+        Regex::new(r#"\bChromeUtils\.(?:importESModule|import)\(\s*["']([^"'\n]+)["']"#).unwrap();
+    static ref LAZY_GETTERS_RE: Regex = Regex::new(
+        r#"\b(?:ChromeUtils\.defineESModuleGetters|XPCOMUtils\.defineLazyModuleGetters)\(\s*[^,]+,\s*\{([^}]*)\}"#
+    )
+    .unwrap();
+    static ref LAZY_GETTER_RE: Regex = Regex::new(
+        r#"\bChromeUtils\.defineModuleGetter\(\s*[^,]+,\s*["'][^"']*["']\s*,\s*["']([^"'\n]+)["']"#
+    )
+    .unwrap();
+    static ref QUOTED_RE: Regex = Regex::new(r#"["']([^"'\n]+)["']"#).unwrap();
+}
+
+/// Whether the path is a JS file that may import modules.
+pub fn is_js_module_path(path: &str) -> bool {
+    path.ends_with(".js") || path.ends_with(".mjs") || path.ends_with(".jsm")
+}
+
+/// Blank out the comments in JS source, keeping newlines so that offsets and
+/// line numbers are unchanged, so commented-out imports are ignored.
+fn strip_comments(path: &str, contents: &str) -> String {
+    let mut stripped = contents.to_string();
+    for token in tokenize_as(&select_formatting(path), contents) {
+        if token.kind == TokenKind::Comment {
+            let blank: String = contents[token.start..token.end]
+                .chars()
+                .map(|c| if c == '\n' { '\n' } else { ' ' })
+                .collect();
+            stripped.replace_range(token.start..token.end, &blank);
+        }
+    }
+    stripped
+}
+
+/// Extract the import edges from a JS module's contents, in order of
+/// appearance, without resolving them.
+pub fn extract_js_imports(path: &str, contents: &str) -> Vec<JsImport> {
+    let source = strip_comments(path, contents);
+    let mut imports = vec![];
+    let mut push = |kind: JsImportKind, m: regex::Match| {
+        imports.push(JsImport {
+            kind,
+            specifier: m.as_str().to_string(),
+            line: source[..m.start()].matches('\n').count() as u32 + 1,
+            target: None,
+        });
+    };
+
+    let simple_patterns: [(&Regex, JsImportKind); 5] = [
+        (&STATIC_IMPORT_RE, JsImportKind::Import),
+        (&RE_EXPORT_RE, JsImportKind::ReExport),
+        (&DYNAMIC_IMPORT_RE, JsImportKind::DynamicImport),
+        (&CHROME_UTILS_RE, JsImportKind::ChromeUtils),
+        (&LAZY_GETTER_RE, JsImportKind::LazyGetter),
+    ];
+    for (re, kind) in simple_patterns {
+        for caps in re.captures_iter(&source) {
+            push(kind, caps.get(1).unwrap());
+        }
+    }
+    for caps in LAZY_GETTERS_RE.captures_iter(&source) {
+        for quoted in QUOTED_RE.captures_iter(caps.get(1).unwrap().as_str()) {
+            let m = quoted.get(1).unwrap();
+            // Offset the match to be relative to the whole source.
+            let start = caps.get(1).unwrap().start() + m.start();
+            imports.push(JsImport {
+                kind: JsImportKind::LazyGetter,
+                specifier: m.as_str().to_string(),
+                line: source[..start].matches('\n').count() as u32 + 1,
+                target: None,
+            });
+        }
+    }
+
+    imports.sort_by_key(|import| import.line);
+    imports
+}
+
+/// Resolve a module specifier to a tree-relative path.  Relative specifiers
+/// are resolved against the importing module's directory, "moz-src:///" URLs
+/// are tree-relative already, and other URLs (ex: "resource://") are resolved
+/// with `resolve_url`, ex: via the tree's URL map.  Bare specifiers can't be
+/// resolved.
+pub fn resolve_specifier<F>(importer: &str, specifier: &str, resolve_url: F) -> Option<String>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    if let Some(path) = specifier.strip_prefix("moz-src:///") {
+        return Some(path.to_string());
+    }
+    if specifier.contains("://") {
+        return resolve_url(specifier);
+    }
+    if !specifier.starts_with("./") && !specifier.starts_with("../") {
+        return None;
+    }
+
+    let mut components: Vec<&str> = importer.split('/').collect();
+    components.pop();
+    for component in specifier.split('/') {
+        match component {
+            "." | "" => {}
+            ".." => {
+                components.pop()?;
+            }
+            _ => components.push(component),
+        }
+    }
+    Some(components.join("/"))
+}
+
+impl JsModuleIndex {
+    pub fn load(path: &str) -> std::io::Result<Option<JsModuleIndex>> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        Ok(Some(serde_json::from_reader(BufReader::new(file))?))
+    }
+
+    pub fn write(&self, path: &str) -> std::io::Result<()> {
+        let file = File::create(path)?;
+        serde_json::to_writer(BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    /// Add a module's imports, resolving their specifiers per
+    /// `resolve_specifier` and keeping only targets for which `is_file` is
+    /// true.
+    pub fn add_module<F, G>(&mut self, path: &str, contents: &str, resolve_url: F, is_file: G)
+    where
+        F: Fn(&str) -> Option<String>,
+        G: Fn(&str) -> bool,
+    {
+        let mut imports = extract_js_imports(path, contents);
+        if imports.is_empty() {
+            return;
+        }
+        for import in imports.iter_mut() {
+            import.target = resolve_specifier(path, &import.specifier, &resolve_url)
+                .filter(|target| is_file(target));
+        }
+        self.modules.insert(path.to_string(), imports);
+    }
+
+    /// The resolved import edges of the tree as (importer, import) pairs.
+    pub fn edges(&self) -> impl Iterator<Item = (&str, &JsImport)> {
+        self.modules.iter().flat_map(|(path, imports)| {
+            imports
+                .iter()
+                .filter(|import| import.target.is_some())
+                .map(move |import| (path.as_str(), import))
+        })
+    }
+}
+
+#[test]
+fn test_js_module_imports() {
+    let contents = r#"import { Foo, Bar as Baz } from "./foo.mjs";
+import * as utils from "../shared/utils.mjs";
+// import { Old } from "./old.mjs";
+export { Qux } from "./qux.mjs";
+const { AppConstants } = ChromeUtils.importESModule(
+  "resource://gre/modules/AppConstants.sys.mjs"
+);
+const lazy = {};
+ChromeUtils.defineESModuleGetters(lazy, {
+  Lazy: "resource://gre/modules/Lazy.sys.mjs",
+  Missing: "resource://gre/modules/Missing.sys.mjs",
+});
+async function load() {
+  return import("moz-src:///toolkit/dyn.mjs");
+}
+import "lit";
+"#;
+    let mut index = JsModuleIndex::default();
+    index.add_module(
+        "browser/components/app.mjs",
+        contents,
+        |url| {
+            url.strip_prefix("resource://gre/modules/")
+                .map(|name| format!("toolkit/modules/{}", name))
+        },
+        |path| path != "toolkit/modules/Missing.sys.mjs",
+    );
+
+    let imports = &index.modules["browser/components/app.mjs"];
+    assert_eq!(
+        imports
+            .iter()
+            .map(|import| (import.kind, import.line, import.target.as_deref()))
+            .collect::<Vec<_>>(),
+        vec![
+            (JsImportKind::Import, 1, Some("browser/components/foo.mjs")),
+            (JsImportKind::Import, 2, Some("browser/shared/utils.mjs")),
+            (
+                JsImportKind::ReExport,
+                4,
+                Some("browser/components/qux.mjs")
+            ),
+            (
+                JsImportKind::ChromeUtils,
+                6,
+                Some("toolkit/modules/AppConstants.sys.mjs")
+            ),
+            (
+                JsImportKind::LazyGetter,
+                10,
+                Some("toolkit/modules/Lazy.sys.mjs")
+            ),
+            (JsImportKind::LazyGetter, 11, None),
+            (JsImportKind::DynamicImport, 14, Some("toolkit/dyn.mjs")),
+            (JsImportKind::Import, 16, None),
+        ]
+    );
+    assert_eq!(index.edges().count(), 6);
+}
@@ -0,0 +1,152 @@
+//! Extraction of localization keys from Fluent (`.ftl`), DTD (`.dtd`), and
+//! properties (`.properties`) files, along with the text patterns that find
+//! uses of those keys from code.  Code refers to localization keys with string
+//! literals (ex: `data-l10n-id="foo"` or `GetStringFromName("foo")`) or, for
+//! DTDs, entity references (ex: `&foo;`), which the language analyzers don't
+//! record as symbols, so `l10n-xref` cross-references them with text searches.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Serialize;
+
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum L10nFormat {
+    Fluent,
+    Dtd,
+    Properties,
+}
+
+/// The regexp for the paths of localization files, for use with searches.
+pub const L10N_PATH_PATTERN: &str = r"\.(ftl|dtd|properties)$";
+
+/// The localization format of the file, if it is a localization file.
+pub fn l10n_format(path: &str) -> Option<L10nFormat> {
+    let (_, ext) = path.rsplit_once('.')?;
+    match ext {
+        "ftl" => Some(L10nFormat::Fluent),
+        "dtd" => Some(L10nFormat::Dtd),
+        "properties" => Some(L10nFormat::Properties),
+        _ => None,
+    }
+}
+
+/// A localization key defined in a localization file.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct L10nKey {
+    pub id: String,
+    /// 1-based line number of the definition.
+    pub lineno: u32,
+    /// The (first line of the) localized string.
+    pub value: String,
+}
+
+lazy_static! {
+    // Fluent messages start unindented; terms (`-brand-name`) can only be used
+    // from other Fluent strings so we skip them.
+    static ref FLUENT_MESSAGE: Regex =
+        Regex::new(r"^([a-zA-Z][a-zA-Z0-9_-]*)\s*=\s*(.*)$").unwrap();
+    static ref DTD_ENTITY: Regex =
+        Regex::new(r#"<!ENTITY\s+([^\s"']+)\s+(?:"([^"]*)|'([^']*))"#).unwrap();
+    static ref PROPERTIES_ENTRY: Regex =
+        Regex::new(r"^\s*([^#!\s=:][^=:\s]*)\s*[=:]\s*(.*)$").unwrap();
+}
+
+/// Extract the keys defined in a localization file.
+pub fn extract_l10n_keys(format: L10nFormat, source: &str) -> Vec<L10nKey> {
+    let mut keys = vec![];
+    for (i, line) in source.lines().enumerate() {
+        let captures = match format {
+            L10nFormat::Fluent => FLUENT_MESSAGE.captures(line),
+            L10nFormat::Dtd => DTD_ENTITY.captures(line),
+            L10nFormat::Properties => PROPERTIES_ENTRY.captures(line),
+        };
+        let Some(captures) = captures else {
+            continue;
+        };
+        let value = captures
+            .iter()
+            .skip(2)
+            .flatten()
+            .next()
+            .map_or("", |m| m.as_str());
+        keys.push(L10nKey {
+            id: captures[1].to_string(),
+            lineno: i as u32 + 1,
+            value: value.trim().to_string(),
+        });
+    }
+    keys
+}
+
+/// A regexp matching the definition of the key in a localization file of any
+/// format.
+pub fn l10n_definition_pattern(id: &str) -> String {
+    let id = regex::escape(id);
+    format!(r#"^{id}\s*=|<!ENTITY\s+{id}\s|^\s*{id}\s*[=:]"#, id = id)
+}
+
+/// A regexp matching uses of a key from code.
+pub fn l10n_use_pattern(format: L10nFormat, id: &str) -> String {
+    let id = regex::escape(id);
+    match format {
+        L10nFormat::Dtd => format!("&{};", id),
+        L10nFormat::Fluent | L10nFormat::Properties => format!("[\"'`]{}[\"'`]", id),
+    }
+}
+
+#[test]
+fn test_extract_l10n_keys() {
+    let ftl = "\
+# Comment
+-brand-name = Firefox
+about-dialog-title = About { -brand-name }
+    .title = Ignored attribute
+tabs-close =
+    { $tabs ->
+        [one] Close tab
+       *[other] Close { $tabs } tabs
+    }
+";
+    let keys = extract_l10n_keys(L10nFormat::Fluent, ftl);
+    assert_eq!(
+        keys.iter()
+            .map(|k| (k.id.as_str(), k.lineno))
+            .collect::<Vec<_>>(),
+        vec![("about-dialog-title", 3), ("tabs-close", 5)]
+    );
+    assert_eq!(keys[0].value, "About { -brand-name }");
+
+    let dtd = r#"<!ENTITY reloadCmd.label "Reload">
+<!ENTITY reloadCmd.accesskey 'R'>"#;
+    let keys = extract_l10n_keys(L10nFormat::Dtd, dtd);
+    assert_eq!(keys[0].id, "reloadCmd.label");
+    assert_eq!(keys[0].value, "Reload");
+    assert_eq!(keys[1].id, "reloadCmd.accesskey");
+    assert_eq!(keys[1].value, "R");
+
+    let properties = "# comment\nbrowser.title=Browser\n  spaced.key : Value\n! other\n";
+    let keys = extract_l10n_keys(L10nFormat::Properties, properties);
+    assert_eq!(
+        keys.iter()
+            .map(|k| (k.id.as_str(), k.value.as_str()))
+            .collect::<Vec<_>>(),
+        vec![("browser.title", "Browser"), ("spaced.key", "Value")]
+    );
+}
+
+#[test]
+fn test_l10n_patterns() {
+    let uses = Regex::new(&l10n_use_pattern(L10nFormat::Fluent, "tabs-close")).unwrap();
+    assert!(uses.is_match(r#"<button data-l10n-id="tabs-close"/>"#));
+    assert!(!uses.is_match(r#"data-l10n-id="tabs-close-all""#));
+
+    let uses = Regex::new(&l10n_use_pattern(L10nFormat::Dtd, "reloadCmd.label")).unwrap();
+    assert!(uses.is_match(r#"label="&reloadCmd.label;""#));
+    assert!(!uses.is_match(r#"label="&reloadCmdXlabel;""#));
+
+    let defs = Regex::new(&l10n_definition_pattern("browser.title")).unwrap();
+    assert!(defs.is_match("browser.title=Browser"));
+    assert!(defs.is_match("<!ENTITY browser.title \"Browser\">"));
+    assert!(!defs.is_match("browser.titles=Browser"));
+}
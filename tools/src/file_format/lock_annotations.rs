@@ -0,0 +1,305 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind};
+
+use serde::{Deserialize, Serialize};
+
+use super::analysis::StructuredLockInfo;
+
+/// A function that uses a guarded field.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct LockAccessor {
+    pub sym: String,
+    pub pretty: String,
+    /// The tree-relative path and 1-based line of the function's first use of
+    /// the field.
+    pub path: String,
+    pub line: u32,
+    /// How the function is known to hold the field's capability:
+    /// - "annotated": the function is annotated as requiring (or acquiring or
+    ///   releasing) the capability, ex: `MOZ_REQUIRES(mMutex)`.
+    /// - "locks": the function references the capability itself, which is
+    ///   presumably it taking the lock, ex: `MutexAutoLock lock(mMutex)`.
+    /// - "exempt": the function is a constructor or destructor or is annotated
+    ///   `MOZ_NO_THREAD_SAFETY_ANALYSIS`, so the analysis doesn't check it.
+    /// - "missing": none of the above, so the function likely lacks an
+    ///   annotation.
+    pub status: String,
+}
+
+/// A field annotated as guarded by a capability, ex: `MOZ_GUARDED_BY(mMutex)`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct GuardedField {
+    pub sym: String,
+    pub pretty: String,
+    /// The tree-relative path and 1-based line of the field's definition, or
+    /// an empty path if it wasn't found.
+    pub path: String,
+    pub line: u32,
+    /// "guarded_by" or "pt_guarded_by".
+    pub kind: String,
+    /// The symbol of the capability, if the annotation names a field or
+    /// variable.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capability_sym: Option<String>,
+    /// See `StructuredLockInfo::pretty`.
+    pub capability: String,
+    /// The functions using the field, ordered by symbol.
+    #[serde(default)]
+    pub accessors: Vec<LockAccessor>,
+}
+
+/// The lock annotations of a function, as relevant to its accesses of
+/// guarded fields.
+#[derive(Debug, Default)]
+struct AnnotatedFunction {
+    /// The keys of the capabilities the function requires, acquires, or
+    /// releases.
+    capabilities: BTreeSet<String>,
+    /// Whether the function is annotated `MOZ_NO_THREAD_SAFETY_ANALYSIS`.
+    no_analysis: bool,
+}
+
+/// Index of the fields annotated with clang's thread safety attributes and
+/// the functions accessing them, as captured in the `locks` of the clang
+/// plugin's structured records and crossref's uses.  crossref writes this to
+/// `lock-annotations.json` in the tree's index directory for the
+/// `lock-report` command.
+///
+/// Building the index takes three steps: `add_symbol` for every symbol with
+/// lock annotations, then `add_uses` for every symbol, then `finish`.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct LockAnnotationIndex {
+    /// Ordered by capability, then field.
+    #[serde(default)]
+    pub fields: Vec<GuardedField>,
+    #[serde(skip)]
+    functions: BTreeMap<String, AnnotatedFunction>,
+    /// The functions referencing each capability, keyed by capability symbol.
+    #[serde(skip)]
+    capability_users: BTreeMap<String, BTreeSet<String>>,
+    /// The indices into `fields` of each guarded field's annotations.
+    #[serde(skip)]
+    field_ixs: BTreeMap<String, Vec<usize>>,
+}
+
+pub const LOCK_ANNOTATIONS_FILENAME: &str = "lock-annotations.json";
+
+/// Capabilities are identified by their symbol where there is one, falling
+/// back to their pretty name for arguments like `sMutexes[0]`.
+fn capability_key(sym: Option<&str>, pretty: &str) -> String {
+    sym.unwrap_or(pretty).to_string()
+}
+
+/// Whether the pretty name is that of a constructor or destructor, ex:
+/// "mozilla::Foo::Foo" or "mozilla::Foo<T>::~Foo".
+fn is_constructor_or_destructor(pretty: &str) -> bool {
+    let mut segments = pretty.rsplit("::");
+    let (Some(name), Some(class)) = (segments.next(), segments.next()) else {
+        return false;
+    };
+    let class = class.split('<').next().unwrap_or_default();
+    name.starts_with('~') || name == class
+}
+
+impl LockAnnotationIndex {
+    pub fn load(path: &str) -> std::io::Result<Option<LockAnnotationIndex>> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        Ok(Some(serde_json::from_reader(BufReader::new(file))?))
+    }
+
+    pub fn write(&self, path: &str) -> std::io::Result<()> {
+        let file = File::create(path)?;
+        serde_json::to_writer(BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    /// Add a symbol with lock annotations, or which is annotated
+    /// `MOZ_NO_THREAD_SAFETY_ANALYSIS` per `no_analysis`.
+    pub fn add_symbol(
+        &mut self,
+        sym: &str,
+        pretty: &str,
+        locks: &[StructuredLockInfo],
+        no_analysis: bool,
+    ) {
+        for lock in locks {
+            let key = capability_key(lock.sym.as_deref(), &lock.pretty);
+            match &*lock.kind {
+                "guarded_by" | "pt_guarded_by" => {
+                    if let Some(capability_sym) = &lock.sym {
+                        self.capability_users
+                            .entry(capability_sym.to_string())
+                            .or_default();
+                    }
+                    self.field_ixs
+                        .entry(sym.to_string())
+                        .or_default()
+                        .push(self.fields.len());
+                    self.fields.push(GuardedField {
+                        sym: sym.to_string(),
+                        pretty: pretty.to_string(),
+                        path: String::new(),
+                        line: 0,
+                        kind: lock.kind.to_string(),
+                        capability_sym: lock.sym.map(|s| s.to_string()),
+                        capability: lock.pretty.to_string(),
+                        accessors: vec![],
+                    });
+                }
+                "requires" | "requires_shared" | "acquires" | "releases" => {
+                    self.functions
+                        .entry(sym.to_string())
+                        .or_default()
+                        .capabilities
+                        .insert(key);
+                }
+                _ => {}
+            }
+        }
+        if no_analysis {
+            self.functions
+                .entry(sym.to_string())
+                .or_default()
+                .no_analysis = true;
+        }
+    }
+
+    /// Add the definition and uses of a symbol, each use given as its path,
+    /// line, and the symbol and pretty name of the function containing it.
+    /// Only the uses of guarded fields and capabilities are kept.
+    pub fn add_uses<'a, I>(&mut self, sym: &str, def: Option<(&str, u32)>, uses: I)
+    where
+        I: IntoIterator<Item = (&'a str, u32, &'a str, &'a str)>,
+    {
+        if let Some(users) = self.capability_users.get_mut(sym) {
+            for (_, _, context_sym, _) in uses {
+                if !context_sym.is_empty() {
+                    users.insert(context_sym.to_string());
+                }
+            }
+            return;
+        }
+
+        let Some(ixs) = self.field_ixs.get(sym) else {
+            return;
+        };
+        let mut accessors: BTreeMap<&str, LockAccessor> = BTreeMap::new();
+        for (path, line, context_sym, context) in uses {
+            if context_sym.is_empty() {
+                continue;
+            }
+            accessors
+                .entry(context_sym)
+                .or_insert_with(|| LockAccessor {
+                    sym: context_sym.to_string(),
+                    pretty: context.to_string(),
+                    path: path.to_string(),
+                    line,
+                    status: String::new(),
+                });
+        }
+        for ix in ixs {
+            let field = &mut self.fields[*ix];
+            if let Some((path, line)) = def {
+                field.path = path.to_string();
+                field.line = line;
+            }
+            field.accessors = accessors.values().cloned().collect();
+        }
+    }
+
+    /// Determine the status of each accessor and sort the fields once all the
+    /// symbols and uses have been added.
+    pub fn finish(&mut self) {
+        for field in &mut self.fields {
+            let key = capability_key(field.capability_sym.as_deref(), &field.capability);
+            let users = field
+                .capability_sym
+                .as_ref()
+                .and_then(|sym| self.capability_users.get(sym));
+            for accessor in &mut field.accessors {
+                let function = self.functions.get(&accessor.sym);
+                let status = if function.map_or(false, |f| f.capabilities.contains(&key)) {
+                    "annotated"
+                } else if users.map_or(false, |users| users.contains(&accessor.sym)) {
+                    "locks"
+                } else if function.map_or(false, |f| f.no_analysis)
+                    || is_constructor_or_destructor(&accessor.pretty)
+                {
+                    "exempt"
+                } else {
+                    "missing"
+                };
+                accessor.status = status.to_string();
+            }
+        }
+        self.fields.sort_by(|a, b| {
+            (&a.capability, &a.pretty, &a.sym).cmp(&(&b.capability, &b.pretty, &b.sym))
+        });
+    }
+}
+
+#[test]
+fn test_lock_annotation_index() {
+    let guarded: Vec<StructuredLockInfo> = serde_json::from_str(
+        r#"[{"kind": "guarded_by", "sym": "F_<T_Foo>_mMutex", "pretty": "Foo::mMutex"}]"#,
+    )
+    .unwrap();
+    let requires: Vec<StructuredLockInfo> = serde_json::from_str(
+        r#"[{"kind": "requires", "sym": "F_<T_Foo>_mMutex", "pretty": "Foo::mMutex"}]"#,
+    )
+    .unwrap();
+    let mut index = LockAnnotationIndex::default();
+    index.add_symbol("F_<T_Foo>_mCount", "Foo::mCount", &guarded, false);
+    index.add_symbol("_ZN3Foo9IncLockedEv", "Foo::IncLocked", &requires, false);
+    index.add_symbol("_ZN3Foo5ResetEv", "Foo::Reset", &[], true);
+
+    index.add_uses(
+        "F_<T_Foo>_mMutex",
+        Some(("foo.h", 20)),
+        vec![("foo.cpp", 10, "_ZN3Foo3IncEv", "Foo::Inc")],
+    );
+    index.add_uses(
+        "F_<T_Foo>_mCount",
+        Some(("foo.h", 21)),
+        vec![
+            ("foo.cpp", 11, "_ZN3Foo3IncEv", "Foo::Inc"),
+            ("foo.cpp", 15, "_ZN3Foo9IncLockedEv", "Foo::IncLocked"),
+            ("foo.cpp", 20, "_ZN3Foo3GetEv", "Foo::Get"),
+            ("foo.cpp", 21, "_ZN3Foo3GetEv", "Foo::Get"),
+            ("foo.cpp", 25, "_ZN3Foo5ResetEv", "Foo::Reset"),
+            ("foo.cpp", 30, "_ZN3FooD1Ev", "Foo::~Foo"),
+        ],
+    );
+    // Symbols that aren't guarded fields or capabilities are ignored.
+    index.add_uses(
+        "_ZN3Foo3GetEv",
+        None,
+        vec![("bar.cpp", 5, "_Z3barv", "bar")],
+    );
+    index.finish();
+
+    assert_eq!(index.fields.len(), 1);
+    let field = &index.fields[0];
+    assert_eq!((field.path.as_str(), field.line), ("foo.h", 21));
+    assert_eq!(field.capability, "Foo::mMutex");
+    assert_eq!(
+        field
+            .accessors
+            .iter()
+            .map(|a| (a.pretty.as_str(), a.line, a.status.as_str()))
+            .collect::<Vec<_>>(),
+        vec![
+            ("Foo::Get", 20, "missing"),
+            ("Foo::Inc", 11, "locks"),
+            ("Foo::Reset", 25, "exempt"),
+            ("Foo::IncLocked", 15, "annotated"),
+            ("Foo::~Foo", 30, "exempt"),
+        ]
+    );
+}
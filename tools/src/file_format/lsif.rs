@@ -0,0 +1,446 @@
+//! Conversion of LSIF dumps into SCIP indexes so that projects whose language
+//! only has an LSIF indexer can be indexed by `scip-indexer` like any other
+//! SCIP subtree.  LSIF is a graph of JSON vertices and edges (see
+//! https://microsoft.github.io/language-server-protocol/specifications/lsif/0.6.0/specification/)
+//! whose ranges point at result sets that we treat as SCIP symbols, named by
+//! their monikers when they have them.
+//!
+//! LSIF monikers are opaque per-indexer identifiers rather than SCIP's
+//! structured descriptors, so each supported language has an
+//! `LsifLanguageRule` describing how to split its identifiers into
+//! descriptors.  The symbol kinds from the LSIF definition range tags decide
+//! the suffix of the last descriptor (ex: `Foo#` for a class).
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{self, BufRead};
+
+use protobuf::MessageField;
+use scip::types::{
+    descriptor::Suffix, Document, Index, Metadata, Occurrence, SymbolInformation, SymbolRole,
+    ToolInfo,
+};
+use serde_json::Value;
+
+/// How to map the LSIF output for a language onto SCIP.
+pub struct LsifLanguageRule {
+    /// The LSIF document `languageId`s the rule applies to.
+    pub language_ids: &'static [&'static str],
+    /// The SCIP tool name to report so that `scip-indexer` uses its support
+    /// for the language.
+    pub scip_tool_name: &'static str,
+    /// What separates the module path from the rest of a moniker identifier,
+    /// if the indexer includes one (ex: lsif-tsc's `src/foo:Bar.baz`).  The
+    /// module path's `/`-delimited pieces become namespaces.
+    pub module_separator: Option<&'static str>,
+    /// What separates the nested names in a moniker identifier.
+    pub member_separators: &'static [&'static str],
+    /// The suffix for the names enclosing the last one, which are usually
+    /// types but are modules for languages like Rust.
+    pub enclosing_suffix: Suffix,
+}
+
+pub const LSIF_LANGUAGE_RULES: &[LsifLanguageRule] = &[
+    LsifLanguageRule {
+        language_ids: &[
+            "typescript",
+            "typescriptreact",
+            "javascript",
+            "javascriptreact",
+        ],
+        scip_tool_name: "scip-typescript",
+        module_separator: Some(":"),
+        member_separators: &["."],
+        enclosing_suffix: Suffix::Type,
+    },
+    LsifLanguageRule {
+        language_ids: &["rust"],
+        scip_tool_name: "rust-analyzer",
+        module_separator: None,
+        member_separators: &["::"],
+        enclosing_suffix: Suffix::Namespace,
+    },
+    LsifLanguageRule {
+        language_ids: &["python"],
+        scip_tool_name: "scip-python",
+        module_separator: None,
+        member_separators: &["."],
+        enclosing_suffix: Suffix::Type,
+    },
+    LsifLanguageRule {
+        language_ids: &["java", "kotlin", "scala"],
+        scip_tool_name: "scip-java",
+        module_separator: None,
+        member_separators: &["#", "."],
+        enclosing_suffix: Suffix::Type,
+    },
+];
+
+pub fn lsif_language_rule(language_id: &str) -> Option<&'static LsifLanguageRule> {
+    LSIF_LANGUAGE_RULES
+        .iter()
+        .find(|rule| rule.language_ids.contains(&language_id))
+}
+
+/// Map an LSP `SymbolKind` from a definition range tag to the SCIP descriptor
+/// suffix for the defined name.
+fn suffix_for_symbol_kind(kind: Option<u64>) -> Suffix {
+    match kind {
+        // Module, Namespace, Package
+        Some(2..=4) => Suffix::Namespace,
+        // Class, Enum, Interface, Struct
+        Some(5) | Some(10) | Some(11) | Some(23) => Suffix::Type,
+        // Method, Constructor, Function
+        Some(6) | Some(9) | Some(12) => Suffix::Method,
+        _ => Suffix::Term,
+    }
+}
+
+/// Escape a descriptor name per the SCIP symbol grammar, which requires
+/// backticks around names that aren't simple identifiers.
+pub fn escape_descriptor_name(name: &str) -> String {
+    let simple = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '+' | '-' | '$'));
+    if simple {
+        name.to_string()
+    } else {
+        format!("`{}`", name.replace('`', "``"))
+    }
+}
+
+fn descriptor(name: &str, suffix: Suffix) -> String {
+    let name = escape_descriptor_name(name);
+    match suffix {
+        Suffix::Namespace | Suffix::Package => format!("{}/", name),
+        Suffix::Type => format!("{}#", name),
+        Suffix::Method => format!("{}().", name),
+        _ => format!("{}.", name),
+    }
+}
+
+/// Convert a moniker identifier into SCIP descriptors per the language rule.
+pub fn moniker_descriptors(rule: &LsifLanguageRule, identifier: &str, last: Suffix) -> String {
+    let (module, members) = match rule.module_separator {
+        Some(sep) => identifier.split_once(sep).unwrap_or(("", identifier)),
+        None => ("", identifier),
+    };
+
+    let mut names = vec![members.to_string()];
+    for sep in rule.member_separators {
+        names = names
+            .iter()
+            .flat_map(|name| name.split(sep).map(str::to_string).collect::<Vec<_>>())
+            .collect();
+    }
+    names.retain(|name| !name.is_empty());
+
+    let mut descriptors: String = module
+        .split('/')
+        .filter(|piece| !piece.is_empty())
+        .map(|piece| descriptor(piece, Suffix::Namespace))
+        .collect();
+    if let Some((last_name, enclosing)) = names.split_last() {
+        for name in enclosing {
+            descriptors.push_str(&descriptor(name, rule.enclosing_suffix));
+        }
+        descriptors.push_str(&descriptor(last_name, last));
+    }
+    descriptors
+}
+
+fn vertex_id(value: &Value) -> Option<u64> {
+    value.as_u64().or_else(|| value.as_str()?.parse().ok())
+}
+
+fn edge_targets(edge: &Value) -> Vec<u64> {
+    match edge.get("inVs") {
+        Some(Value::Array(in_vs)) => in_vs.iter().filter_map(vertex_id).collect(),
+        _ => edge.get("inV").and_then(vertex_id).into_iter().collect(),
+    }
+}
+
+fn hover_text(hover: &Value) -> Vec<String> {
+    let contents = &hover["result"]["contents"];
+    let pieces = match contents {
+        Value::Array(pieces) => pieces.iter().collect(),
+        piece => vec![piece],
+    };
+    pieces
+        .into_iter()
+        .filter_map(|piece| match piece {
+            Value::String(s) => Some(s.clone()),
+            Value::Object(obj) => match (obj.get("language"), obj.get("value")) {
+                (Some(Value::String(lang)), Some(Value::String(value))) => {
+                    Some(format!("```{}\n{}\n```", lang, value))
+                }
+                (_, Some(Value::String(value))) => Some(value.clone()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+/// Make `uri` relative to `project_root` if it's inside it.
+fn relative_path(uri: &str, project_root: &str) -> String {
+    let path = match uri.strip_prefix(project_root) {
+        Some(path) if !project_root.is_empty() => path.trim_start_matches('/'),
+        _ => uri.strip_prefix("file://").unwrap_or(uri),
+    };
+    match urlencoding::decode(path) {
+        Ok(decoded) => decoded.into_owned(),
+        Err(_) => path.to_string(),
+    }
+}
+
+fn lsif_range(range: &Value) -> Option<Vec<i32>> {
+    let pos = |key: &str, field: &str| range[key][field].as_i64().map(|v| v as i32);
+    let (start_line, start_char) = (pos("start", "line")?, pos("start", "character")?);
+    let (end_line, end_char) = (pos("end", "line")?, pos("end", "character")?);
+    Some(if start_line == end_line {
+        vec![start_line, start_char, end_char]
+    } else {
+        vec![start_line, start_char, end_line, end_char]
+    })
+}
+
+/// Convert an LSIF dump in its JSON lines form into a SCIP index.  Documents
+/// in languages without an `LsifLanguageRule` are skipped, and the index's
+/// tool name is that of the rule for the dump's most common language.
+pub fn lsif_to_scip(reader: impl BufRead) -> io::Result<Index> {
+    let mut vertices: HashMap<u64, Value> = HashMap::new();
+    // Edges keyed by label and then by their outV.
+    let mut edges: HashMap<String, HashMap<u64, Vec<u64>>> = HashMap::new();
+    // Definition ranges from the "item" edges of definition results.
+    let mut item_edges: Vec<(u64, Vec<u64>)> = vec![];
+
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let value: Value = serde_json::from_str(&line)?;
+        let Some(id) = value.get("id").and_then(vertex_id) else {
+            continue;
+        };
+        if value["type"] == "edge" {
+            let (Some(label), Some(out_v)) = (
+                value["label"].as_str(),
+                value.get("outV").and_then(vertex_id),
+            ) else {
+                continue;
+            };
+            if label == "item" {
+                item_edges.push((out_v, edge_targets(&value)));
+            } else {
+                edges
+                    .entry(label.to_string())
+                    .or_default()
+                    .entry(out_v)
+                    .or_default()
+                    .extend(edge_targets(&value));
+            }
+        } else {
+            vertices.insert(id, value);
+        }
+    }
+
+    let label_of = |id: u64| vertices.get(&id).and_then(|v| v["label"].as_str());
+    let first_target =
+        |label: &str, id: u64| -> Option<u64> { edges.get(label)?.get(&id)?.first().copied() };
+
+    let definition_ranges: HashSet<u64> = item_edges
+        .iter()
+        .filter(|(out_v, _)| label_of(*out_v) == Some("definitionResult"))
+        .flat_map(|(_, in_vs)| in_vs.iter().copied())
+        .collect();
+
+    // Follow a range's "next" chain to the result set that identifies its
+    // symbol, collecting the moniker and hover along the way.
+    let resolve = |range_id: u64| -> (u64, Option<u64>, Option<u64>) {
+        let (mut cur, mut moniker, mut hover) = (range_id, None, None);
+        let mut seen = HashSet::new();
+        loop {
+            moniker = moniker.or_else(|| first_target("moniker", cur));
+            hover = hover.or_else(|| first_target("textDocument/hover", cur));
+            match first_target("next", cur) {
+                Some(next) if seen.insert(next) => cur = next,
+                _ => return (cur, moniker, hover),
+            }
+        }
+    };
+
+    // The kind of each symbol, per the tags of its definition ranges.
+    let mut definition_kinds: HashMap<u64, u64> = HashMap::new();
+    for range_id in &definition_ranges {
+        if let Some(kind) = vertices
+            .get(range_id)
+            .and_then(|range| range["tag"]["kind"].as_u64())
+        {
+            definition_kinds.insert(resolve(*range_id).0, kind);
+        }
+    }
+
+    let project_root = vertices
+        .values()
+        .find(|v| v["label"] == "metaData")
+        .and_then(|v| v["projectRoot"].as_str())
+        .unwrap_or_default()
+        .to_string();
+
+    let mut doc_ids: Vec<u64> = vertices
+        .iter()
+        .filter(|(_, v)| v["label"] == "document")
+        .map(|(id, _)| *id)
+        .collect();
+    doc_ids.sort_unstable();
+
+    let mut language_counts: BTreeMap<&'static str, usize> = BTreeMap::new();
+    let mut documents = vec![];
+    for doc_id in doc_ids {
+        let doc = &vertices[&doc_id];
+        let Some(rule) = lsif_language_rule(doc["languageId"].as_str().unwrap_or_default()) else {
+            continue;
+        };
+        *language_counts.entry(rule.scip_tool_name).or_default() += 1;
+
+        let mut range_ids: Vec<u64> = edges
+            .get("contains")
+            .and_then(|contains| contains.get(&doc_id))
+            .cloned()
+            .unwrap_or_default();
+        range_ids.retain(|id| label_of(*id) == Some("range"));
+        range_ids.sort_unstable();
+
+        let mut occurrences = vec![];
+        let mut symbols = BTreeMap::new();
+        for range_id in range_ids {
+            let range = &vertices[&range_id];
+            let Some(scip_range) = lsif_range(range) else {
+                continue;
+            };
+            let (result_set, moniker, hover) = resolve(range_id);
+            let is_definition = definition_ranges.contains(&range_id);
+            let suffix = suffix_for_symbol_kind(definition_kinds.get(&result_set).copied());
+
+            let moniker = moniker.and_then(|id| vertices.get(&id));
+            let symbol = match moniker {
+                Some(moniker) if moniker["kind"] != "local" => format!(
+                    "lsif {} . . {}",
+                    moniker["scheme"].as_str().unwrap_or("lsif"),
+                    moniker_descriptors(
+                        rule,
+                        moniker["identifier"].as_str().unwrap_or_default(),
+                        suffix,
+                    )
+                ),
+                _ => format!("local {}", result_set),
+            };
+
+            if is_definition {
+                let documentation = hover
+                    .and_then(|id| vertices.get(&id))
+                    .map(hover_text)
+                    .unwrap_or_default();
+                symbols
+                    .entry(symbol.clone())
+                    .or_insert_with(|| SymbolInformation {
+                        symbol: symbol.clone(),
+                        documentation,
+                        ..Default::default()
+                    });
+            }
+            occurrences.push(Occurrence {
+                range: scip_range,
+                symbol,
+                symbol_roles: if is_definition {
+                    SymbolRole::Definition as i32
+                } else {
+                    0
+                },
+                ..Default::default()
+            });
+        }
+
+        documents.push(Document {
+            language: doc["languageId"].as_str().unwrap_or_default().to_string(),
+            relative_path: relative_path(doc["uri"].as_str().unwrap_or_default(), &project_root),
+            occurrences,
+            symbols: symbols.into_values().collect(),
+            ..Default::default()
+        });
+    }
+
+    let tool_name = language_counts
+        .iter()
+        .max_by_key(|(_, count)| **count)
+        .map(|(name, _)| name.to_string())
+        .unwrap_or_default();
+
+    Ok(Index {
+        metadata: MessageField::some(Metadata {
+            tool_info: MessageField::some(ToolInfo {
+                name: tool_name,
+                ..Default::default()
+            }),
+            project_root,
+            ..Default::default()
+        }),
+        documents,
+        ..Default::default()
+    })
+}
+
+#[test]
+fn test_lsif_to_scip() {
+    let dump = r#"
+{"id":1,"type":"vertex","label":"metaData","version":"0.4.3","projectRoot":"file:///src/proj"}
+{"id":2,"type":"vertex","label":"document","uri":"file:///src/proj/lib/foo.ts","languageId":"typescript"}
+{"id":3,"type":"vertex","label":"range","start":{"line":0,"character":13},"end":{"line":0,"character":16},"tag":{"type":"definition","text":"Foo","kind":5}}
+{"id":4,"type":"vertex","label":"range","start":{"line":4,"character":8},"end":{"line":4,"character":11}}
+{"id":5,"type":"vertex","label":"resultSet"}
+{"id":6,"type":"vertex","label":"moniker","scheme":"tsc","identifier":"lib/foo:Foo","kind":"export"}
+{"id":7,"type":"vertex","label":"definitionResult"}
+{"id":8,"type":"vertex","label":"hoverResult","result":{"contents":[{"language":"typescript","value":"class Foo"}]}}
+{"id":9,"type":"edge","label":"contains","outV":2,"inVs":[3,4]}
+{"id":10,"type":"edge","label":"next","outV":3,"inV":5}
+{"id":11,"type":"edge","label":"next","outV":4,"inV":5}
+{"id":12,"type":"edge","label":"moniker","outV":5,"inV":6}
+{"id":13,"type":"edge","label":"textDocument/definition","outV":5,"inV":7}
+{"id":14,"type":"edge","label":"item","outV":7,"inVs":[3],"document":2}
+{"id":15,"type":"edge","label":"textDocument/hover","outV":5,"inV":8}
+{"id":16,"type":"vertex","label":"range","start":{"line":5,"character":0},"end":{"line":5,"character":3}}
+{"id":17,"type":"edge","label":"contains","outV":2,"inVs":[16]}
+"#;
+    let index = lsif_to_scip(dump.as_bytes()).unwrap();
+    assert_eq!(index.metadata.tool_info.name, "scip-typescript");
+    assert_eq!(index.documents.len(), 1);
+
+    let doc = &index.documents[0];
+    assert_eq!(doc.relative_path, "lib/foo.ts");
+    assert_eq!(doc.occurrences.len(), 3);
+    assert_eq!(doc.occurrences[0].symbol, "lsif tsc . . lib/foo/Foo#");
+    assert_eq!(doc.occurrences[0].range, vec![0, 13, 16]);
+    assert_eq!(
+        doc.occurrences[0].symbol_roles,
+        SymbolRole::Definition as i32
+    );
+    assert_eq!(doc.occurrences[1].symbol, doc.occurrences[0].symbol);
+    assert_eq!(doc.occurrences[1].symbol_roles, 0);
+    // Ranges without result sets or monikers are locals.
+    assert_eq!(doc.occurrences[2].symbol, "local 16");
+
+    assert_eq!(doc.symbols.len(), 1);
+    assert_eq!(
+        doc.symbols[0].documentation,
+        vec!["```typescript\nclass Foo\n```"]
+    );
+    assert!(scip::symbol::parse_symbol(&doc.symbols[0].symbol).is_ok());
+
+    let rust = lsif_language_rule("rust").unwrap();
+    assert_eq!(
+        moniker_descriptors(rust, "my_crate::net::Socket::connect", Suffix::Method),
+        "my_crate/net/Socket/connect()."
+    );
+}
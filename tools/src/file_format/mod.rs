@@ -7,6 +7,10 @@ pub mod history;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod analysis_manglings;
 #[cfg(not(target_arch = "wasm32"))]
+pub mod asset_manifest;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod bug_references;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod config;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod coverage;
@@ -15,18 +19,52 @@ pub mod crossref_converter;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod crossref_lookup;
 #[cfg(not(target_arch = "wasm32"))]
+pub mod doc_comments;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod doc_trees;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod globbing_file_list;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod identifiers;
 #[cfg(not(target_arch = "wasm32"))]
+pub mod index_manifest;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod js_modules;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod l10n;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod lock_annotations;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod lsif;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod merger;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod ontology_mapping;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod per_file_info;
 #[cfg(not(target_arch = "wasm32"))]
+pub mod query_popularity;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod refcount_graph;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod repo_data_ingestion;
 #[cfg(not(target_arch = "wasm32"))]
+pub mod rust_crates;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod static_inits;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod structured_columns;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod symbol_annotations;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod symbol_attributes;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod symbol_kind_stats;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod test_stats;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod todo_comments;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod trigram_bloom;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod url_map;
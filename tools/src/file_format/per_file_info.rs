@@ -92,6 +92,7 @@ impl FileLookupMap {
         matches.sort_unstable_by(|a, b| natural_lexical_cmp(&a.path, &b.path));
         Ok(FileMatches {
             file_matches: matches,
+            page: None,
         })
     }
 }
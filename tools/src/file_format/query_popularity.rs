@@ -0,0 +1,222 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind};
+
+use serde::{Deserialize, Serialize};
+
+/// How often each symbol (or identifier) and path of a tree was asked about
+/// per the web server's query logs, as ingested by `ingest-query-logs` into
+/// `query-popularity.json` in the tree's index directory.  This is used to
+/// boost search ranking and to pick what `precompute` should warm up.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct QueryPopularity {
+    /// Hits for symbols from `symbol:` searches and symbol-taking query
+    /// parameters, and for identifiers from `id:` and bare identifier
+    /// searches.
+    #[serde(default)]
+    pub symbols: BTreeMap<String, u64>,
+    /// Hits for source file views.
+    #[serde(default)]
+    pub paths: BTreeMap<String, u64>,
+}
+
+pub const QUERY_POPULARITY_FILENAME: &str = "query-popularity.json";
+
+/// Query parameters of presets whose values are symbols or identifiers.
+const SYMBOL_PARAMS: [&str; 4] = ["sym", "id", "class", "symbol"];
+
+fn decode_query_value(value: &str) -> String {
+    let value = value.replace('+', " ");
+    match urlencoding::decode(&value) {
+        Ok(decoded) => decoded.into_owned(),
+        Err(_) => value,
+    }
+}
+
+fn is_identifier(term: &str) -> bool {
+    let mut chars = term.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+}
+
+impl QueryPopularity {
+    pub fn load(path: &str) -> std::io::Result<Option<QueryPopularity>> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        Ok(Some(serde_json::from_reader(BufReader::new(file))?))
+    }
+
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        let file = File::create(path)?;
+        serde_json::to_writer(BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    pub fn symbol_hits(&self, symbol: &str) -> u64 {
+        self.symbols.get(symbol).copied().unwrap_or_default()
+    }
+
+    pub fn path_hits(&self, path: &str) -> u64 {
+        self.paths.get(path).copied().unwrap_or_default()
+    }
+
+    /// The most popular symbols, most popular first.
+    pub fn top_symbols(&self, limit: usize) -> Vec<(&String, u64)> {
+        top_n(&self.symbols, limit)
+    }
+
+    /// The most popular paths, most popular first.
+    pub fn top_paths(&self, limit: usize) -> Vec<(&String, u64)> {
+        top_n(&self.paths, limit)
+    }
+
+    fn record_search_terms(&mut self, query: &str) {
+        for term in query.split_whitespace() {
+            if let Some(symbols) = term.strip_prefix("symbol:") {
+                for symbol in symbols.split(',').filter(|s| !s.is_empty()) {
+                    *self.symbols.entry(symbol.to_string()).or_default() += 1;
+                }
+            } else if let Some(id) = term.strip_prefix("id:") {
+                if !id.is_empty() {
+                    *self.symbols.entry(id.to_string()).or_default() += 1;
+                }
+            } else if is_identifier(term) {
+                *self.symbols.entry(term.to_string()).or_default() += 1;
+            }
+        }
+    }
+
+    /// Record a single web server log line (see `weblog-analyze.sh` for the
+    /// format) if it is a request for the given tree, returning whether it was
+    /// recorded.  Only the `"GET /TREE/... HTTP/1.1"` portion of the line is
+    /// considered, so this also works for other log formats that include the
+    /// request line.
+    pub fn record_log_line(&mut self, tree: &str, line: &str) -> bool {
+        let Some(request) = line
+            .split_once("\"GET /")
+            .and_then(|(_, rest)| rest.split_once(' '))
+            .map(|(url, _)| url)
+        else {
+            return false;
+        };
+        let Some(rest) = request
+            .strip_prefix(tree)
+            .and_then(|rest| rest.strip_prefix('/'))
+        else {
+            return false;
+        };
+        let (route, query_string) = rest.split_once('?').unwrap_or((rest, ""));
+        let params: Vec<(&str, String)> = query_string
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .map(|(k, v)| (k, decode_query_value(v)))
+            .collect();
+
+        if let Some(path) = route.strip_prefix("source/").or_else(|| {
+            route
+                .strip_prefix("rev/")
+                .and_then(|rev_path| rev_path.split_once('/'))
+                .map(|(_, path)| path)
+        }) {
+            if path.is_empty() {
+                return false;
+            }
+            *self.paths.entry(decode_query_value(path)).or_default() += 1;
+            return true;
+        }
+
+        if route == "search" || route.starts_with("query/") {
+            let mut recorded = false;
+            for (key, value) in params {
+                if key == "q" {
+                    self.record_search_terms(&value);
+                    recorded = true;
+                } else if SYMBOL_PARAMS.contains(&key) && !value.is_empty() {
+                    *self.symbols.entry(value).or_default() += 1;
+                    recorded = true;
+                }
+            }
+            return recorded;
+        }
+
+        false
+    }
+
+    /// Only keep the `max_entries` most popular symbols and paths so the store
+    /// stays compact.
+    pub fn prune(&mut self, max_entries: usize) {
+        fn prune_map(map: &mut BTreeMap<String, u64>, max_entries: usize) {
+            if map.len() > max_entries {
+                *map = top_n(map, max_entries)
+                    .into_iter()
+                    .map(|(key, hits)| (key.clone(), hits))
+                    .collect();
+            }
+        }
+        prune_map(&mut self.symbols, max_entries);
+        prune_map(&mut self.paths, max_entries);
+    }
+}
+
+fn top_n(map: &BTreeMap<String, u64>, limit: usize) -> Vec<(&String, u64)> {
+    let mut entries: Vec<(&String, u64)> = map.iter().map(|(k, v)| (k, *v)).collect();
+    entries.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    entries.truncate(limit);
+    entries
+}
+
+#[test]
+fn test_record_log_line() {
+    let mut pop = QueryPopularity::default();
+    let line = |request: &str| {
+        format!(
+            "[14/Oct/2026:10:00:00 +0000] [Cache:MISS] [0.120] [searchfox.org] \
+             [Remote_Addr: 1.2.3.4] - - - searchfox.org to: 127.0.0.1:8001: \
+             \"GET {} HTTP/1.1\" 200 1234 \"-\" \"Mozilla/5.0\"",
+            request
+        )
+    };
+
+    assert!(pop.record_log_line(
+        "mozilla-central",
+        &line("/mozilla-central/source/dom/base/Document.cpp")
+    ));
+    assert!(pop.record_log_line(
+        "mozilla-central",
+        &line("/mozilla-central/rev/abc123/dom/base/Document.cpp")
+    ));
+    assert!(pop.record_log_line(
+        "mozilla-central",
+        &line("/mozilla-central/search?q=symbol:_ZN7Document,_ZN4Node&redirect=false")
+    ));
+    assert!(pop.record_log_line(
+        "mozilla-central",
+        &line("/mozilla-central/search?q=nsIFoo+id:Bar")
+    ));
+    assert!(pop.record_log_line(
+        "mozilla-central",
+        &line("/mozilla-central/query/layout-audit?class=Document")
+    ));
+    // Other trees and routes are ignored.
+    assert!(!pop.record_log_line("mozilla-central", &line("/comm-central/source/mail/foo.js")));
+    assert!(!pop.record_log_line(
+        "mozilla-central",
+        &line("/mozilla-central/static/js/search.js")
+    ));
+    assert!(!pop.record_log_line("mozilla-central", "garbage"));
+
+    assert_eq!(pop.path_hits("dom/base/Document.cpp"), 2);
+    assert_eq!(pop.symbol_hits("_ZN7Document"), 1);
+    assert_eq!(pop.symbol_hits("_ZN4Node"), 1);
+    assert_eq!(pop.symbol_hits("nsIFoo"), 1);
+    assert_eq!(pop.symbol_hits("Bar"), 1);
+    assert_eq!(pop.symbol_hits("Document"), 1);
+    assert_eq!(pop.symbol_hits("redirect=false"), 0);
+
+    pop.prune(1);
+    assert_eq!(pop.paths.len(), 1);
+    assert_eq!(pop.top_symbols(10), vec![(&"Bar".to_string(), 1)]);
+}
@@ -0,0 +1,196 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind};
+
+use serde::{Deserialize, Serialize};
+use ustr::{ustr, Ustr};
+
+use super::analysis::{StructuredFieldInfo, StructuredSuperInfo};
+use super::ontology_pointer_kind::OntologyPointerKind;
+
+/// A refcounted class, which is a class with the "rc" label from the
+/// ontology's refcount rules.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct RefcountClass {
+    pub pretty: String,
+    /// Whether the class is cycle collected, per the "cc" label.
+    #[serde(default)]
+    pub cc: bool,
+    /// The symbols of the class's direct superclasses.
+    #[serde(default)]
+    pub supers: Vec<String>,
+}
+
+/// A member of a refcounted class holding a strong reference, ex: a
+/// `RefPtr<Foo>` or `nsCOMPtr<nsIFoo>` field.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct OwnershipEdge {
+    /// The symbol of the class with the member.
+    pub from: String,
+    /// The symbol of the class the member points to, which need not be
+    /// refcounted itself, ex: an XPCOM interface.
+    pub to: String,
+    pub field: String,
+    pub field_pretty: String,
+    /// The member's type, ex: "RefPtr<Foo>".
+    pub type_pretty: String,
+    /// Whether the cycle collector traverses the member, per the
+    /// "cc-traverse" field label.
+    #[serde(default)]
+    pub traversed: bool,
+}
+
+/// The ownership graph between the tree's refcounted classes, where each
+/// edge is a member holding a strong reference, as determined by the
+/// ontology's pointer rules.  crossref writes this to `refcount-graph.json`
+/// in the tree's index directory for the `refcount-cycles` command.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct RefcountGraph {
+    /// Keyed by symbol.
+    #[serde(default)]
+    pub classes: BTreeMap<String, RefcountClass>,
+    /// Ordered by the class with the member, then the member.
+    #[serde(default)]
+    pub edges: Vec<OwnershipEdge>,
+}
+
+pub const REFCOUNT_GRAPH_FILENAME: &str = "refcount-graph.json";
+
+impl RefcountGraph {
+    pub fn load(path: &str) -> std::io::Result<Option<RefcountGraph>> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        Ok(Some(serde_json::from_reader(BufReader::new(file))?))
+    }
+
+    pub fn write(&self, path: &str) -> std::io::Result<()> {
+        let file = File::create(path)?;
+        serde_json::to_writer(BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    /// Add a class and its members holding strong references if the class is
+    /// refcounted.  The fields' `pointer_info` must already have been
+    /// populated from the ontology.
+    pub fn add_class(
+        &mut self,
+        sym: &str,
+        pretty: &str,
+        labels: &BTreeSet<Ustr>,
+        supers: &[StructuredSuperInfo],
+        fields: &[StructuredFieldInfo],
+    ) {
+        if !labels.contains(&ustr("rc")) {
+            return;
+        }
+        self.classes.insert(
+            sym.to_string(),
+            RefcountClass {
+                pretty: pretty.to_string(),
+                cc: labels.contains(&ustr("cc")),
+                supers: supers.iter().map(|s| s.sym.to_string()).collect(),
+            },
+        );
+        for field in fields {
+            for info in &field.pointer_info {
+                if info.kind != OntologyPointerKind::Strong {
+                    continue;
+                }
+                self.edges.push(OwnershipEdge {
+                    from: sym.to_string(),
+                    to: info.sym.to_string(),
+                    field: field.sym.to_string(),
+                    field_pretty: field.pretty.to_string(),
+                    type_pretty: field.type_pretty.to_string(),
+                    traversed: field.labels.contains(&ustr("cc-traverse")),
+                });
+            }
+        }
+    }
+
+    /// Sort the edges once all the classes have been added.
+    pub fn finish(&mut self) {
+        self.edges
+            .sort_by(|a, b| (&a.from, &a.field, &a.to).cmp(&(&b.from, &b.field, &b.to)));
+    }
+
+    /// The refcounted subclasses of each class, transitively, keyed by the
+    /// superclass, for treating a strong reference to a class as possibly
+    /// owning any of its subclasses.
+    pub fn subclasses(&self) -> BTreeMap<&str, BTreeSet<&str>> {
+        let mut direct: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+        for (sym, class) in &self.classes {
+            for super_sym in &class.supers {
+                direct
+                    .entry(super_sym.as_str())
+                    .or_default()
+                    .push(sym.as_str());
+            }
+        }
+
+        let mut all = BTreeMap::new();
+        for base in direct.keys() {
+            let mut subs = BTreeSet::new();
+            let mut pending = direct[*base].clone();
+            while let Some(sub) = pending.pop() {
+                if subs.insert(sub) {
+                    pending.extend(direct.get(sub).into_iter().flatten().copied());
+                }
+            }
+            all.insert(*base, subs);
+        }
+        all
+    }
+}
+
+#[test]
+fn test_refcount_graph() {
+    let labels = |labels: &[&str]| labels.iter().map(|l| ustr(l)).collect::<BTreeSet<_>>();
+    let foo_fields: Vec<StructuredFieldInfo> = serde_json::from_str(
+        r#"[
+  {"pretty": "Foo::mBar", "sym": "F_<T_Foo>_mBar", "type": "RefPtr<Bar>", "typesym": "T_RefPtr",
+   "pointerInfo": [{"kind": "strong", "sym": "T_Bar"}], "labels": ["cc-traverse"]},
+  {"pretty": "Foo::mOwner", "sym": "F_<T_Foo>_mOwner", "type": "Owner *", "typesym": "T_Owner",
+   "pointerInfo": [{"kind": "raw", "sym": "T_Owner"}]}
+]"#,
+    )
+    .unwrap();
+    let bar_fields: Vec<StructuredFieldInfo> = serde_json::from_str(
+        r#"[
+  {"pretty": "Bar::mListener", "sym": "F_<T_Bar>_mListener", "type": "nsCOMPtr<nsIListener>",
+   "typesym": "T_nsCOMPtr", "pointerInfo": [{"kind": "strong", "sym": "T_nsIListener"}]}
+]"#,
+    )
+    .unwrap();
+    let supers: Vec<StructuredSuperInfo> =
+        serde_json::from_str(r#"[{"sym": "T_nsIListener"}]"#).unwrap();
+
+    let mut graph = RefcountGraph::default();
+    graph.add_class("T_Foo", "Foo", &labels(&["rc", "cc"]), &supers, &foo_fields);
+    graph.add_class("T_Bar", "Bar", &labels(&["rc"]), &[], &bar_fields);
+    graph.add_class("T_Owner", "Owner", &labels(&[]), &[], &[]);
+    graph.finish();
+
+    assert_eq!(
+        graph.classes.keys().collect::<Vec<_>>(),
+        vec!["T_Bar", "T_Foo"]
+    );
+    assert!(graph.classes["T_Foo"].cc && !graph.classes["T_Bar"].cc);
+    assert_eq!(
+        graph
+            .edges
+            .iter()
+            .map(|e| (e.from.as_str(), e.to.as_str(), e.traversed))
+            .collect::<Vec<_>>(),
+        vec![("T_Bar", "T_nsIListener", false), ("T_Foo", "T_Bar", true)]
+    );
+    assert_eq!(
+        graph.subclasses()["T_nsIListener"]
+            .iter()
+            .collect::<Vec<_>>(),
+        vec![&"T_Foo"]
+    );
+}
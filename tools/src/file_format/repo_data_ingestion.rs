@@ -9,12 +9,14 @@ use liquid::Template;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_json::{from_str, from_value, json, to_writer, Map, Value};
-use ustr::{ustr, Ustr};
+use ustr::{ustr, Ustr, UstrMap};
 
+use crate::binary_metadata::extract_binary_metadata;
 use crate::describe::describe_file;
-use crate::languages::select_formatting;
+use crate::languages::{select_formatting, FormatAs};
 use crate::templating::builder::build_and_parse;
 
+use super::analysis::FileClassification;
 use super::config::TreeConfig;
 use super::coverage::interpolate_coverage;
 use super::globbing_file_list::GlobbingFileList;
@@ -38,6 +40,10 @@ pub struct PathKindConfig {
     pub decision_order: u32,
     /// The order in which results will be displayed.
     pub sort_order: u32,
+    /// Explicit classification for files of this path kind.  If omitted, this
+    /// is derived from the `[pathkind.KEY]` key via
+    /// `FileClassification::from_path_kind_key`.
+    pub classification: Option<FileClassification>,
     #[serde(default)]
     pub heuristics: PathKindHeuristics,
 }
@@ -409,6 +415,9 @@ pub struct RepoIngestion {
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ConcisePerFileInfo<T: Ord> {
     pub path_kind: T,
+    /// Derived from `path_kind` by `RepoIngestion::apply_classifications`.
+    #[serde(default)]
+    pub classification: FileClassification,
     pub is_dir: bool,
     pub file_size: u64,
     pub bugzilla_component: Option<(T, T)>,
@@ -422,6 +431,7 @@ impl ConcisePerFileInfo<Ustr> {
     fn default_is_dir(is_dir: bool) -> Self {
         ConcisePerFileInfo {
             path_kind: ustr(""),
+            classification: FileClassification::Normal,
             is_dir,
             file_size: 0,
             bugzilla_component: None,
@@ -587,35 +597,45 @@ impl RepoIngestion {
             };
             let file_size = metadata.len();
 
-            let description = match fs::read_to_string(&raw_file_path) {
-                Ok(contents) => {
-                    let format = select_formatting(&raw_file_path);
-                    let maybe_description = describe_file(&contents, path_wrapper, &format);
-                    if let Some(ref description) = maybe_description {
-                        // We currently want to output
-                        let description_fname =
-                            format!("{}/description/{}", tree_config.paths.index_path, file_path);
-                        let description_file = match File::create(&description_fname) {
-                            Ok(df) => df,
-                            Err(e) => {
-                                warn!(
-                                    "Problem creating description file {}: {}",
-                                    description_fname, e
-                                );
-                                continue;
-                            }
-                        };
-                        let desc_writer = BufWriter::new(description_file);
-                        let file_description = json!({
-                            "description": description,
-                        });
-                        to_writer(desc_writer, &file_description).unwrap();
-                    }
-
-                    maybe_description
+            let format = select_formatting(&raw_file_path);
+            // Binary files get a summary of their metadata as their description
+            // and the metadata itself in their description file.
+            let (description, binary_metadata) = if let FormatAs::Binary = format {
+                match fs::read(&raw_file_path)
+                    .ok()
+                    .and_then(|data| extract_binary_metadata(&data))
+                {
+                    Some(metadata) => (Some(metadata.summary()), Some(metadata)),
+                    None => (None, None),
+                }
+            } else {
+                match fs::read_to_string(&raw_file_path) {
+                    Ok(contents) => (describe_file(&contents, path_wrapper, &format), None),
+                    Err(_) => (None, None),
                 }
-                Err(_) => None,
             };
+            if let Some(ref description) = description {
+                let description_fname =
+                    format!("{}/description/{}", tree_config.paths.index_path, file_path);
+                let description_file = match File::create(&description_fname) {
+                    Ok(df) => df,
+                    Err(e) => {
+                        warn!(
+                            "Problem creating description file {}: {}",
+                            description_fname, e
+                        );
+                        continue;
+                    }
+                };
+                let desc_writer = BufWriter::new(description_file);
+                let mut file_description = json!({
+                    "description": description,
+                });
+                if let Some(metadata) = binary_metadata {
+                    file_description["metadata"] = json!(metadata);
+                }
+                to_writer(desc_writer, &file_description).unwrap();
+            }
 
             self.state.with_file_info(file_path, false, |pfi, _dfi| {
                 pfi.path_kind = use_path_kind;
@@ -625,6 +645,32 @@ impl RepoIngestion {
         }
     }
 
+    /// Derive each file's `classification` from its final path kind.  This
+    /// needs to happen after `ingest_files` because in-tree metadata can
+    /// clobber the path kind assigned by our heuristics.
+    pub fn apply_classifications(&mut self) {
+        let name_to_classification: UstrMap<FileClassification> = self
+            .config
+            .pathkind
+            .iter()
+            .map(|(key, pk_config)| {
+                (
+                    pk_config.name,
+                    pk_config
+                        .classification
+                        .unwrap_or_else(|| FileClassification::from_path_kind_key(key)),
+                )
+            })
+            .collect();
+
+        for info in self.state.concise_per_file.values_mut() {
+            info.classification = name_to_classification
+                .get(&info.path_kind)
+                .copied()
+                .unwrap_or_default();
+        }
+    }
+
     pub fn ingest_dir_list(&mut self, dirs: &Vec<Ustr>) {
         for dir_path in dirs {
             self.state.with_file_info(dir_path, true, |_cfi, _dfi| {});
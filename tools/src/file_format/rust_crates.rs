@@ -0,0 +1,286 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind};
+
+use serde::{Deserialize, Serialize};
+
+/// The subset of the output of `cargo metadata --format-version 1` that we
+/// use, which the tree's build script is expected to save as
+/// `cargo-metadata.json` in the tree's index directory.
+#[derive(Debug, Deserialize)]
+pub struct CargoMetadata {
+    pub packages: Vec<CargoPackage>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CargoPackage {
+    pub name: String,
+    /// The absolute path of the package's Cargo.toml.
+    pub manifest_path: String,
+    #[serde(default)]
+    pub dependencies: Vec<CargoDependency>,
+    #[serde(default)]
+    pub targets: Vec<CargoTarget>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CargoDependency {
+    pub name: String,
+    /// "dev", "build", or None for normal dependencies.
+    #[serde(default)]
+    pub kind: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CargoTarget {
+    pub name: String,
+    #[serde(default)]
+    pub kind: Vec<String>,
+}
+
+pub const CARGO_METADATA_FILENAME: &str = "cargo-metadata.json";
+
+/// An in-tree Rust crate.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct RustCrate {
+    /// The tree-relative directory of the crate's Cargo.toml, or "" for the
+    /// root of the tree.
+    pub dir: String,
+    /// The name of the crate's library target if it differs from the crate's.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lib_name: Option<String>,
+    /// The in-tree crates this crate depends on, including build dependencies.
+    #[serde(default)]
+    pub deps: BTreeSet<String>,
+    /// The in-tree crates this crate only depends on for tests and examples.
+    #[serde(default)]
+    pub dev_deps: BTreeSet<String>,
+    /// How many symbols the crate defines.
+    #[serde(default)]
+    pub symbols: u64,
+    /// How many times code in this crate uses the symbols of each other crate.
+    #[serde(default)]
+    pub refs: BTreeMap<String, u64>,
+}
+
+/// The dependency graph of the tree's Rust crates, built by crossref from
+/// the Cargo metadata captured at index time, along with per-crate symbol
+/// counts and cross-crate reference counts from the Rust analysis.  Written
+/// to `rust-crates.json` for the `rust-crate-graph` command.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct RustCrateIndex {
+    /// The crates keyed by name, with any "-" normalized to "_" as in `use`
+    /// paths and in the crate names of the Rust analysis.
+    #[serde(default)]
+    pub crates: BTreeMap<String, RustCrate>,
+    /// The crate keyed by each tree-relative crate directory.
+    #[serde(skip)]
+    crate_dirs: BTreeMap<String, String>,
+}
+
+pub const RUST_CRATES_FILENAME: &str = "rust-crates.json";
+
+fn normalize_crate_name(name: &str) -> String {
+    name.replace('-', "_")
+}
+
+impl RustCrateIndex {
+    pub fn load(path: &str) -> std::io::Result<Option<RustCrateIndex>> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        Ok(Some(serde_json::from_reader(BufReader::new(file))?))
+    }
+
+    pub fn write(&self, path: &str) -> std::io::Result<()> {
+        let file = File::create(path)?;
+        serde_json::to_writer(BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    /// Build the crate graph from Cargo metadata, keeping only the packages
+    /// whose manifests are under `files_root`, the tree's source directory.
+    pub fn from_cargo_metadata(metadata: &CargoMetadata, files_root: &str) -> RustCrateIndex {
+        let files_root = files_root.trim_end_matches('/');
+        let mut index = RustCrateIndex::default();
+        for package in &metadata.packages {
+            let Some(rel_manifest) = package.manifest_path.strip_prefix(files_root) else {
+                continue;
+            };
+            let Some(rel_manifest) = rel_manifest.strip_prefix('/') else {
+                continue;
+            };
+            let dir = rel_manifest
+                .strip_suffix("Cargo.toml")
+                .unwrap_or_default()
+                .trim_end_matches('/')
+                .to_string();
+            let name = normalize_crate_name(&package.name);
+            let lib_name = package
+                .targets
+                .iter()
+                .find(|target| target.kind.iter().any(|kind| kind.ends_with("lib")))
+                .map(|target| normalize_crate_name(&target.name))
+                .filter(|lib_name| lib_name != &name);
+            index.crate_dirs.insert(dir.clone(), name.clone());
+            index.crates.insert(
+                name,
+                RustCrate {
+                    dir,
+                    lib_name,
+                    ..RustCrate::default()
+                },
+            );
+        }
+
+        // Only now that we know which crates are in-tree can we keep just the
+        // in-tree dependencies.
+        for package in &metadata.packages {
+            let name = normalize_crate_name(&package.name);
+            if !index.crates.contains_key(&name) {
+                continue;
+            }
+            let mut deps = BTreeSet::new();
+            let mut dev_deps = BTreeSet::new();
+            for dep in &package.dependencies {
+                let dep_name = normalize_crate_name(&dep.name);
+                if !index.crates.contains_key(&dep_name) {
+                    continue;
+                }
+                match dep.kind.as_deref() {
+                    Some("dev") => dev_deps.insert(dep_name),
+                    _ => deps.insert(dep_name),
+                };
+            }
+            let krate = index.crates.get_mut(&name).unwrap();
+            krate.deps = deps;
+            krate.dev_deps = dev_deps;
+        }
+        index
+    }
+
+    /// Map a crate name from the Rust analysis, which may be the name of the
+    /// crate's library target rather than the crate's, to the crate's name.
+    pub fn resolve_crate_name<'a>(&'a self, name: &str) -> Option<&'a str> {
+        if let Some((key, _)) = self.crates.get_key_value(name) {
+            return Some(key);
+        }
+        self.crates
+            .iter()
+            .find(|(_, krate)| krate.lib_name.as_deref() == Some(name))
+            .map(|(key, _)| key.as_str())
+    }
+
+    /// The crate containing the tree-relative path, which is the crate with
+    /// the deepest directory containing the path.
+    pub fn crate_for_path(&self, path: &str) -> Option<&str> {
+        let mut dir = path;
+        loop {
+            dir = match dir.rfind('/') {
+                Some(idx) => &dir[..idx],
+                None if dir.is_empty() => return None,
+                None => "",
+            };
+            if let Some(name) = self.crate_dirs.get(dir) {
+                return Some(name);
+            }
+        }
+    }
+
+    /// Count a symbol defined by the named crate.
+    pub fn add_symbol(&mut self, crate_name: &str) {
+        if let Some(krate) = self.crates.get_mut(crate_name) {
+            krate.symbols += 1;
+        }
+    }
+
+    /// Count `count` uses of a symbol of the named crate from the file at the
+    /// tree-relative path, if the file is in a different in-tree crate.
+    pub fn add_references(&mut self, from_path: &str, to_crate: &str, count: u64) {
+        let Some(from_crate) = self.crate_for_path(from_path) else {
+            return;
+        };
+        if from_crate == to_crate || !self.crates.contains_key(to_crate) {
+            return;
+        }
+        let from_crate = from_crate.to_string();
+        let krate = self.crates.get_mut(&from_crate).unwrap();
+        *krate.refs.entry(to_crate.to_string()).or_default() += count;
+    }
+}
+
+#[test]
+fn test_rust_crate_index() {
+    let metadata: CargoMetadata = serde_json::from_str(
+        r#"{
+  "packages": [
+    {
+      "name": "files",
+      "manifest_path": "/index/tests/files/Cargo.toml",
+      "dependencies": [
+        { "name": "test_rust_dependency", "kind": null },
+        { "name": "serde", "kind": null },
+        { "name": "test-helpers", "kind": "dev" }
+      ],
+      "targets": [{ "name": "simple", "kind": ["lib"] }]
+    },
+    {
+      "name": "test_rust_dependency",
+      "manifest_path": "/index/tests/files/test_rust_dependency/Cargo.toml",
+      "dependencies": [],
+      "targets": [{ "name": "test_rust_dependency", "kind": ["lib"] }]
+    },
+    {
+      "name": "test-helpers",
+      "manifest_path": "/index/tests/files/testing/helpers/Cargo.toml"
+    },
+    {
+      "name": "serde",
+      "manifest_path": "/home/user/.cargo/registry/src/serde-1.0.0/Cargo.toml"
+    }
+  ]
+}"#,
+    )
+    .unwrap();
+    let mut index = RustCrateIndex::from_cargo_metadata(&metadata, "/index/tests/files/");
+
+    assert_eq!(
+        index.crates.keys().collect::<Vec<_>>(),
+        vec!["files", "test_helpers", "test_rust_dependency"]
+    );
+    let files = &index.crates["files"];
+    assert_eq!(files.dir, "");
+    assert_eq!(files.lib_name.as_deref(), Some("simple"));
+    assert_eq!(
+        files.deps.iter().collect::<Vec<_>>(),
+        vec!["test_rust_dependency"]
+    );
+    assert_eq!(
+        files.dev_deps.iter().collect::<Vec<_>>(),
+        vec!["test_helpers"]
+    );
+    assert_eq!(index.resolve_crate_name("simple"), Some("files"));
+
+    assert_eq!(
+        index.crate_for_path("test_rust_dependency/src/lib.rs"),
+        Some("test_rust_dependency")
+    );
+    assert_eq!(index.crate_for_path("simple.rs"), Some("files"));
+    assert_eq!(
+        index.crate_for_path("testing/helpers/lib.rs"),
+        Some("test_helpers")
+    );
+
+    index.add_symbol("test_rust_dependency");
+    index.add_references("simple.rs", "test_rust_dependency", 3);
+    index.add_references("simple.rs", "files", 5);
+    index.add_references("test_rust_dependency/src/lib.rs", "serde", 1);
+    assert_eq!(index.crates["test_rust_dependency"].symbols, 1);
+    assert_eq!(
+        index.crates["files"].refs.iter().collect::<Vec<_>>(),
+        vec![(&"test_rust_dependency".to_string(), &3)]
+    );
+    assert!(index.crates["test_rust_dependency"].refs.is_empty());
+}
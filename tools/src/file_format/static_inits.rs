@@ -0,0 +1,135 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind};
+
+use serde::{Deserialize, Serialize};
+
+use super::analysis::StructuredStaticInitInfo;
+
+/// A symbol referenced by a static initializer.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct StaticInitRef {
+    pub sym: String,
+    /// The symbol's pretty name, or the symbol again if it has none.
+    pub pretty: String,
+}
+
+/// A variable whose static initialization or destruction runs code.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct StaticInit {
+    pub sym: String,
+    pub pretty: String,
+    /// The tree-relative path and 1-based line of the variable's definition,
+    /// which is where its initializer is.
+    pub path: String,
+    pub line: u32,
+    /// See `StructuredStaticInitInfo::kind`.
+    pub kind: String,
+    pub dynamic_init: bool,
+    pub destructor: bool,
+    #[serde(default)]
+    pub refs: Vec<StaticInitRef>,
+}
+
+/// Index of the variables with static storage whose initialization or
+/// destruction runs code, ex: global constructors, as captured in the
+/// `staticInit` of the clang plugin's structured records.  crossref writes
+/// this to `static-inits.json` in the tree's index directory for the
+/// `static-inits` command.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct StaticInitIndex {
+    /// Ordered by path and line.
+    #[serde(default)]
+    pub inits: Vec<StaticInit>,
+}
+
+pub const STATIC_INITS_FILENAME: &str = "static-inits.json";
+
+impl StaticInitIndex {
+    pub fn load(path: &str) -> std::io::Result<Option<StaticInitIndex>> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        Ok(Some(serde_json::from_reader(BufReader::new(file))?))
+    }
+
+    pub fn write(&self, path: &str) -> std::io::Result<()> {
+        let file = File::create(path)?;
+        serde_json::to_writer(BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    /// Add a variable defined at the given path and line, looking up the
+    /// pretty names of the symbols its initializer references with
+    /// `lookup_pretty`.
+    pub fn add<F>(
+        &mut self,
+        sym: &str,
+        pretty: &str,
+        path: &str,
+        line: u32,
+        info: &StructuredStaticInitInfo,
+        lookup_pretty: F,
+    ) where
+        F: Fn(&str) -> Option<String>,
+    {
+        self.inits.push(StaticInit {
+            sym: sym.to_string(),
+            pretty: pretty.to_string(),
+            path: path.to_string(),
+            line,
+            kind: info.kind.to_string(),
+            dynamic_init: info.dynamic_init,
+            destructor: info.destructor,
+            refs: info
+                .syms
+                .iter()
+                .map(|ref_sym| StaticInitRef {
+                    sym: ref_sym.to_string(),
+                    pretty: lookup_pretty(ref_sym).unwrap_or_else(|| ref_sym.to_string()),
+                })
+                .collect(),
+        });
+    }
+
+    /// Sort the variables by path and line once they've all been added.
+    pub fn finish(&mut self) {
+        self.inits
+            .sort_by(|a, b| (&a.path, a.line, &a.sym).cmp(&(&b.path, b.line, &b.sym)));
+    }
+}
+
+#[test]
+fn test_static_init_index() {
+    let info: StructuredStaticInitInfo = serde_json::from_str(
+        r#"{"kind": "global", "dynamicInit": true, "destructor": false, "syms": ["_ZN3FooC1Ev", "_Z6helperv"]}"#,
+    )
+    .unwrap();
+    let mut index = StaticInitIndex::default();
+    index.add("_ZL4sFoo", "sFoo", "b.cpp", 3, &info, |sym| match sym {
+        "_ZN3FooC1Ev" => Some("Foo::Foo".to_string()),
+        _ => None,
+    });
+    index.add("_ZL4sBar", "sBar", "a.cpp", 7, &info, |_| None);
+    index.finish();
+
+    assert_eq!(
+        index
+            .inits
+            .iter()
+            .map(|init| (init.path.as_str(), init.line))
+            .collect::<Vec<_>>(),
+        vec![("a.cpp", 7), ("b.cpp", 3)]
+    );
+    let foo = &index.inits[1];
+    assert_eq!(foo.kind, "global");
+    assert!(foo.dynamic_init && !foo.destructor);
+    assert_eq!(
+        foo.refs
+            .iter()
+            .map(|r| r.pretty.as_str())
+            .collect::<Vec<_>>(),
+        vec!["Foo::Foo", "_Z6helperv"]
+    );
+}
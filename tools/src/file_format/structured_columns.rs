@@ -0,0 +1,315 @@
+extern crate memmap;
+
+use self::memmap::Mmap;
+use std::cmp::Ordering;
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::str;
+use std::sync::Arc;
+
+use serde_json::{from_slice, to_vec, Value};
+
+use super::analysis::AnalysisStructured;
+
+/// The columns we store for each structured symbol.  The symbol names
+/// themselves are stored in an implicit "sym" column that all other columns are
+/// aligned with.
+///
+/// This is a deliberately small subset of the structured record; it covers the
+/// information that class-layout and hierarchy analyses need for every symbol
+/// they touch, where deserializing the entire crossref entry (including all of
+/// the uses!) is a waste.  Anything else should continue to come from the
+/// crossref "meta".
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+pub enum StructuredColumn {
+    /// The structured record "kind", like "class" or "method".
+    Kind,
+    /// `sizeBytes` as a number, or null if unknown.
+    SizeBytes,
+    /// The `supers` array.
+    Supers,
+    /// The `fields` array.
+    Fields,
+    /// The names of the platforms the canonical record was observed on.
+    Platforms,
+}
+
+impl StructuredColumn {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StructuredColumn::Kind => "kind",
+            StructuredColumn::SizeBytes => "sizeBytes",
+            StructuredColumn::Supers => "supers",
+            StructuredColumn::Fields => "fields",
+            StructuredColumn::Platforms => "platforms",
+        }
+    }
+}
+
+/// Extract a column from a crossref "meta" (a serialized structured record)
+/// with the same semantics as `StructuredColumns::lookup`, for use when the
+/// columns file isn't available.
+pub fn column_from_meta(meta: &Value, column: StructuredColumn) -> Value {
+    if !meta.is_object() {
+        return Value::Null;
+    }
+    match (meta.get(column.name()), column) {
+        (Some(val), _) => val.clone(),
+        (
+            None,
+            StructuredColumn::Supers | StructuredColumn::Fields | StructuredColumn::Platforms,
+        ) => Value::Array(vec![]),
+        (None, _) => Value::Null,
+    }
+}
+
+// ## File Format
+//
+// All integers are little-endian u64s.
+//
+// - The magic bytes `SFCOLS01`.
+// - The row count N.
+// - The byte offset of each of the 6 columns, in the order: sym, kind,
+//   sizeBytes, supers, fields, platforms.
+// - The column data.
+//
+// The sizeBytes column is a fixed-width column of N u64s where u64::MAX means
+// null.  All other columns are variable-width and consist of N + 1 offsets
+// relative to the end of the offset table followed by the row data, so row `i`
+// is the bytes [offsets[i], offsets[i + 1]).  The sym and kind columns contain
+// raw UTF-8 and the others contain JSON.  Rows are sorted by the bytes of the
+// symbol so that lookups can bisect.
+const MAGIC: &[u8; 8] = b"SFCOLS01";
+const COLUMN_COUNT: usize = 6;
+const HEADER_LEN: usize = 8 + 8 + COLUMN_COUNT * 8;
+const NULL_SIZE: u64 = u64::MAX;
+
+const COL_SYM: usize = 0;
+const COL_KIND: usize = 1;
+const COL_SIZE_BYTES: usize = 2;
+const COL_SUPERS: usize = 3;
+const COL_FIELDS: usize = 4;
+const COL_PLATFORMS: usize = 5;
+
+fn encode_var_column(rows: &[Vec<u8>]) -> Vec<u8> {
+    let mut out = Vec::with_capacity((rows.len() + 1) * 8);
+    let mut offset = 0u64;
+    out.extend_from_slice(&offset.to_le_bytes());
+    for row in rows {
+        offset += row.len() as u64;
+        out.extend_from_slice(&offset.to_le_bytes());
+    }
+    for row in rows {
+        out.extend_from_slice(row);
+    }
+    out
+}
+
+/// Write out the structured columns file for the given structured records,
+/// which must be keyed by their symbol.
+pub fn write_structured_columns<'a, I>(path: &str, records: I) -> std::io::Result<()>
+where
+    I: IntoIterator<Item = (&'a str, &'a AnalysisStructured)>,
+{
+    let mut records: Vec<(&str, &AnalysisStructured)> = records.into_iter().collect();
+    records.sort_by(|a, b| a.0.as_bytes().cmp(b.0.as_bytes()));
+
+    let mut columns: Vec<Vec<Vec<u8>>> = vec![vec![]; COLUMN_COUNT];
+    let mut sizes: Vec<u8> = Vec::with_capacity(records.len() * 8);
+    for (sym, structured) in &records {
+        columns[COL_SYM].push(sym.as_bytes().to_vec());
+        columns[COL_KIND].push(structured.kind.as_bytes().to_vec());
+        let size = structured
+            .size_bytes
+            .map(|size| size as u64)
+            .unwrap_or(NULL_SIZE);
+        sizes.extend_from_slice(&size.to_le_bytes());
+        columns[COL_SUPERS].push(to_vec(&structured.supers)?);
+        columns[COL_FIELDS].push(to_vec(&structured.fields)?);
+        columns[COL_PLATFORMS].push(to_vec(&structured.platforms())?);
+    }
+
+    let mut encoded = vec![];
+    for (i_col, rows) in columns.iter().enumerate() {
+        if i_col == COL_SIZE_BYTES {
+            encoded.push(std::mem::take(&mut sizes));
+        } else {
+            encoded.push(encode_var_column(rows));
+        }
+    }
+
+    let mut out = BufWriter::new(File::create(path)?);
+    out.write_all(MAGIC)?;
+    out.write_all(&(records.len() as u64).to_le_bytes())?;
+    let mut offset = HEADER_LEN as u64;
+    for column in &encoded {
+        out.write_all(&offset.to_le_bytes())?;
+        offset += column.len() as u64;
+    }
+    for column in &encoded {
+        out.write_all(column)?;
+    }
+    out.flush()
+}
+
+/// Memory-mapped reader for the file produced by `write_structured_columns`.
+/// Only the row of the requested column is ever parsed.
+#[derive(Clone, Debug)]
+pub struct StructuredColumns {
+    mm: Arc<Mmap>,
+    row_count: usize,
+    column_offsets: [usize; COLUMN_COUNT],
+}
+
+impl StructuredColumns {
+    /// Returns None if the file doesn't exist or isn't a valid columns file,
+    /// in which case callers should fall back to the crossref "meta".
+    pub fn new(path: &str) -> Option<StructuredColumns> {
+        let file = File::open(path).ok()?;
+        let mm = unsafe { Mmap::map(&file).ok()? };
+        if mm.len() < HEADER_LEN || &mm[0..8] != MAGIC {
+            return None;
+        }
+        let read_u64 = |pos: usize| u64::from_le_bytes(mm[pos..pos + 8].try_into().unwrap());
+        let row_count = read_u64(8) as usize;
+        let mut column_offsets = [0; COLUMN_COUNT];
+        for (i, offset) in column_offsets.iter_mut().enumerate() {
+            *offset = read_u64(16 + i * 8) as usize;
+            if *offset > mm.len() {
+                return None;
+            }
+        }
+        Some(StructuredColumns {
+            mm: Arc::new(mm),
+            row_count,
+            column_offsets,
+        })
+    }
+
+    fn read_u64(&self, pos: usize) -> Option<u64> {
+        let bytes = self.mm.get(pos..pos + 8)?;
+        Some(u64::from_le_bytes(bytes.try_into().ok()?))
+    }
+
+    fn var_row(&self, col: usize, row: usize) -> Option<&[u8]> {
+        let base = self.column_offsets[col];
+        let start = self.read_u64(base + row * 8)? as usize;
+        let end = self.read_u64(base + (row + 1) * 8)? as usize;
+        let data_base = base + (self.row_count + 1) * 8;
+        self.mm.get(data_base + start..data_base + end)
+    }
+
+    fn find_row(&self, sym: &str) -> Option<usize> {
+        let needle = sym.as_bytes();
+        let (mut lo, mut hi) = (0, self.row_count);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            match self.var_row(COL_SYM, mid)?.cmp(needle) {
+                Ordering::Equal => return Some(mid),
+                Ordering::Less => lo = mid + 1,
+                Ordering::Greater => hi = mid,
+            }
+        }
+        None
+    }
+
+    /// Look up a single column for the given symbol, returning Null if the
+    /// symbol has no structured info.
+    pub fn lookup(&self, sym: &str, column: StructuredColumn) -> Value {
+        let Some(row) = self.find_row(sym) else {
+            return Value::Null;
+        };
+        self.lookup_row(row, column)
+    }
+
+    /// Iterate over every symbol with structured info along with its value for
+    /// the given column, in symbol order.
+    pub fn scan(&self, column: StructuredColumn) -> impl Iterator<Item = (&str, Value)> + '_ {
+        (0..self.row_count).filter_map(move |row| {
+            let sym = str::from_utf8(self.var_row(COL_SYM, row)?).ok()?;
+            Some((sym, self.lookup_row(row, column)))
+        })
+    }
+
+    fn lookup_row(&self, row: usize, column: StructuredColumn) -> Value {
+        match column {
+            StructuredColumn::SizeBytes => {
+                match self.read_u64(self.column_offsets[COL_SIZE_BYTES] + row * 8) {
+                    Some(NULL_SIZE) | None => Value::Null,
+                    Some(size) => Value::from(size),
+                }
+            }
+            StructuredColumn::Kind => match self.var_row(COL_KIND, row).map(str::from_utf8) {
+                Some(Ok(kind)) => Value::from(kind),
+                _ => Value::Null,
+            },
+            StructuredColumn::Supers => self.json_row(COL_SUPERS, row),
+            StructuredColumn::Fields => self.json_row(COL_FIELDS, row),
+            StructuredColumn::Platforms => self.json_row(COL_PLATFORMS, row),
+        }
+    }
+
+    fn json_row(&self, col: usize, row: usize) -> Value {
+        self.var_row(col, row)
+            .and_then(|bytes| from_slice(bytes).ok())
+            .unwrap_or(Value::Null)
+    }
+}
+
+#[test]
+fn test_structured_columns_roundtrip() {
+    use serde_json::{from_value, json};
+
+    let foo: AnalysisStructured = from_value(json!({
+        "structured": 1,
+        "sym": "T_Foo",
+        "kind": "class",
+        "sizeBytes": 16,
+        "supers": [{"sym": "T_Base", "pretty": "Base", "props": []}],
+        "platforms": ["linux64", "win64"],
+    }))
+    .unwrap();
+    let bar: AnalysisStructured = from_value(json!({
+        "structured": 1,
+        "sym": "F_bar",
+        "kind": "function",
+    }))
+    .unwrap();
+
+    let path = std::env::temp_dir().join(format!("structured-columns-{}", std::process::id()));
+    let path = path.to_str().unwrap();
+    write_structured_columns(path, vec![("T_Foo", &foo), ("F_bar", &bar)]).unwrap();
+
+    let columns = StructuredColumns::new(path).unwrap();
+    assert_eq!(
+        columns.lookup("T_Foo", StructuredColumn::SizeBytes),
+        json!(16)
+    );
+    assert_eq!(
+        columns.lookup("T_Foo", StructuredColumn::Kind),
+        json!("class")
+    );
+    assert_eq!(
+        columns.lookup("T_Foo", StructuredColumn::Platforms),
+        json!(["linux64", "win64"])
+    );
+    assert_eq!(
+        columns.lookup("T_Foo", StructuredColumn::Supers)[0]["sym"],
+        json!("T_Base")
+    );
+    assert_eq!(
+        columns.lookup("F_bar", StructuredColumn::SizeBytes),
+        Value::Null
+    );
+    assert_eq!(
+        columns.lookup("F_nope", StructuredColumn::Kind),
+        Value::Null
+    );
+    assert_eq!(
+        columns.scan(StructuredColumn::Kind).collect::<Vec<_>>(),
+        vec![("F_bar", json!("function")), ("T_Foo", json!("class"))]
+    );
+
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,141 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Write};
+
+use serde::{Deserialize, Serialize};
+
+/// User-provided tags and notes about symbols, ex: tagging classes as
+/// "memory-hot" to later query all of their layouts.  These are set by the
+/// `annotate` pipeline command and stored in `symbol-annotations.json` in the
+/// tree's `state_path` directory, so they're local to the deployment and
+/// survive reindexing.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct SymbolAnnotations {
+    #[serde(default)]
+    pub symbols: BTreeMap<String, SymbolAnnotation>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct SymbolAnnotation {
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub tags: BTreeSet<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl SymbolAnnotation {
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.note.is_none()
+    }
+
+    pub fn has_tags(&self, tags: &[String]) -> bool {
+        tags.iter().all(|tag| self.tags.contains(tag))
+    }
+}
+
+pub const SYMBOL_ANNOTATIONS_FILENAME: &str = "symbol-annotations.json";
+
+impl SymbolAnnotations {
+    pub fn load(path: &str) -> std::io::Result<Option<SymbolAnnotations>> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        Ok(Some(serde_json::from_reader(BufReader::new(file))?))
+    }
+
+    /// Save the annotations by writing a temporary file and renaming it over
+    /// the store so that concurrent readers never see a partial file.  The
+    /// temporary file is synced first so a failed write (ex: a full disk)
+    /// leaves the existing store alone.
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        let tmp_path = format!("{}.tmp", path);
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+        std::fs::rename(tmp_path, path)
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<&SymbolAnnotation> {
+        self.symbols.get(symbol)
+    }
+
+    /// Add and remove tags from a symbol and optionally replace its note (an
+    /// empty note removes it), dropping the symbol entirely if nothing is left.
+    /// Returns the symbol's resulting annotation.
+    pub fn update(
+        &mut self,
+        symbol: &str,
+        add_tags: &[String],
+        remove_tags: &[String],
+        note: Option<&str>,
+    ) -> SymbolAnnotation {
+        let annotation = self.symbols.entry(symbol.to_string()).or_default();
+        annotation.tags.extend(add_tags.iter().cloned());
+        for tag in remove_tags {
+            annotation.tags.remove(tag);
+        }
+        if let Some(note) = note {
+            annotation.note = if note.is_empty() {
+                None
+            } else {
+                Some(note.to_string())
+            };
+        }
+        let result = annotation.clone();
+        if result.is_empty() {
+            self.symbols.remove(symbol);
+        }
+        result
+    }
+
+    pub fn remove(&mut self, symbol: &str) -> Option<SymbolAnnotation> {
+        self.symbols.remove(symbol)
+    }
+
+    /// The symbols that have all of the given tags, or all annotated symbols
+    /// if no tags are given, in symbol order.
+    pub fn symbols_with_tags<'a>(&'a self, tags: &'a [String]) -> impl Iterator<Item = &'a str> {
+        self.symbols
+            .iter()
+            .filter(move |(_, annotation)| annotation.has_tags(tags))
+            .map(|(symbol, _)| symbol.as_str())
+    }
+}
+
+#[test]
+fn test_update_annotations() {
+    let mut annotations = SymbolAnnotations::default();
+    let tags = |tags: &[&str]| -> Vec<String> { tags.iter().map(|t| t.to_string()).collect() };
+
+    annotations.update("T_Foo", &tags(&["memory-hot", "dom"]), &[], None);
+    annotations.update(
+        "T_Bar",
+        &tags(&["memory-hot"]),
+        &[],
+        Some("Allocated per node"),
+    );
+    let bar = annotations.update("T_Bar", &[], &tags(&["dom"]), None);
+    assert_eq!(bar.note.as_deref(), Some("Allocated per node"));
+
+    assert_eq!(
+        annotations
+            .symbols_with_tags(&tags(&["memory-hot"]))
+            .collect::<Vec<_>>(),
+        vec!["T_Bar", "T_Foo"]
+    );
+    assert_eq!(
+        annotations
+            .symbols_with_tags(&tags(&["memory-hot", "dom"]))
+            .collect::<Vec<_>>(),
+        vec!["T_Foo"]
+    );
+
+    // Removing everything drops the symbol.
+    let foo = annotations.update("T_Foo", &[], &tags(&["memory-hot", "dom"]), Some(""));
+    assert!(foo.is_empty());
+    assert!(annotations.get("T_Foo").is_none());
+    assert_eq!(annotations.symbols_with_tags(&[]).count(), 1);
+}
@@ -0,0 +1,133 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind};
+
+use serde::{Deserialize, Serialize};
+
+/// Index of the attribute-like annotations on symbols, ex: `MOZ_CAN_RUN_SCRIPT`
+/// (which the clang plugin sees as `annotate("moz_can_run_script")`) or Rust's
+/// `#[deprecated]`, as captured in the `attributes` of structured records.
+/// crossref writes this to `symbol-attributes.json` in the tree's index
+/// directory so that `search-attributes` can find every symbol bearing an
+/// attribute without scanning all of the crossref entries.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct SymbolAttributes {
+    /// Normalized attribute name to the symbols bearing it.
+    #[serde(default)]
+    pub attributes: BTreeMap<String, BTreeSet<String>>,
+}
+
+pub const SYMBOL_ATTRIBUTES_FILENAME: &str = "symbol-attributes.json";
+
+/// Rust attributes that say nothing interesting about the symbol itself, so we
+/// don't bother indexing them.
+const SKIPPED_RUST_ATTRIBUTES: &[&str] = &[
+    "allow", "cfg", "cfg_attr", "deny", "doc", "expect", "forbid", "warn",
+];
+
+/// Normalize how an attribute was written, either in source or in a query, to
+/// the name we index, ex: "MOZ_CAN_RUN_SCRIPT" to "moz_can_run_script",
+/// `#[deprecated(since = "1.0")]` to "deprecated", and "[[nodiscard]]" to
+/// "nodiscard".
+pub fn normalize_attribute_name(attr: &str) -> String {
+    let attr = attr.trim();
+    let attr = attr
+        .strip_prefix("#[")
+        .or_else(|| attr.strip_prefix("[["))
+        .unwrap_or(attr);
+    let end = attr
+        .find(|c: char| c == '(' || c == ']' || c == '=' || c.is_whitespace())
+        .unwrap_or(attr.len());
+    attr[..end].to_lowercase()
+}
+
+/// The indexed name of a Rust attribute given its source text, or None if it's
+/// one we skip.
+pub fn rust_attribute_name(attr: &str) -> Option<String> {
+    let name = normalize_attribute_name(attr);
+    if name.is_empty() || SKIPPED_RUST_ATTRIBUTES.contains(&name.as_str()) {
+        None
+    } else {
+        Some(name)
+    }
+}
+
+impl SymbolAttributes {
+    /// Build the index from each symbol's attributes.
+    pub fn from_symbols<'a, I, A>(symbols: I) -> SymbolAttributes
+    where
+        I: IntoIterator<Item = (&'a str, A)>,
+        A: IntoIterator<Item = &'a str>,
+    {
+        let mut index = SymbolAttributes::default();
+        for (sym, attrs) in symbols {
+            for attr in attrs {
+                index
+                    .attributes
+                    .entry(normalize_attribute_name(attr))
+                    .or_default()
+                    .insert(sym.to_string());
+            }
+        }
+        index
+    }
+
+    pub fn load(path: &str) -> std::io::Result<Option<SymbolAttributes>> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        Ok(Some(serde_json::from_reader(BufReader::new(file))?))
+    }
+
+    pub fn write(&self, path: &str) -> std::io::Result<()> {
+        let file = File::create(path)?;
+        serde_json::to_writer(BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    /// The symbols bearing the attribute, however it was written, in symbol
+    /// order.
+    pub fn symbols_with(&self, attr: &str) -> impl Iterator<Item = &str> {
+        self.attributes
+            .get(&normalize_attribute_name(attr))
+            .into_iter()
+            .flatten()
+            .map(|sym| sym.as_str())
+    }
+}
+
+#[test]
+fn test_symbol_attributes() {
+    assert_eq!(
+        normalize_attribute_name("MOZ_CAN_RUN_SCRIPT"),
+        "moz_can_run_script"
+    );
+    assert_eq!(
+        normalize_attribute_name("#[deprecated(since = \"1.0\")]"),
+        "deprecated"
+    );
+    assert_eq!(normalize_attribute_name("[[nodiscard]]"), "nodiscard");
+    assert_eq!(
+        rust_attribute_name("#[tokio::main]").as_deref(),
+        Some("tokio::main")
+    );
+    assert_eq!(rust_attribute_name("#[doc = \"Docs\"]"), None);
+    assert_eq!(rust_attribute_name("#[cfg(test)]"), None);
+
+    let index = SymbolAttributes::from_symbols(vec![
+        ("_ZN3Foo3RunEv", vec!["moz_can_run_script"]),
+        ("_ZN3Foo4StopEv", vec!["moz_can_run_script", "deprecated"]),
+        ("_ZN3Foo4IdleEv", vec![]),
+    ]);
+    assert_eq!(
+        index.symbols_with("MOZ_CAN_RUN_SCRIPT").collect::<Vec<_>>(),
+        vec!["_ZN3Foo3RunEv", "_ZN3Foo4StopEv"]
+    );
+    assert_eq!(
+        index.symbols_with("#[deprecated]").collect::<Vec<_>>(),
+        vec!["_ZN3Foo4StopEv"]
+    );
+    assert_eq!(index.symbols_with("nodiscard").count(), 0);
+}
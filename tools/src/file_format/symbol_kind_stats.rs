@@ -0,0 +1,102 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind};
+
+use serde::{Deserialize, Serialize};
+
+/// Counts of the symbols defined in each directory by kind for one index
+/// generation, so that the growth of code constructs can be watched over time
+/// by comparing generations with the `trend` command.  crossref writes this to
+/// `symbol-kind-stats.json` in the tree's index directory; keeping a history
+/// is a matter of archiving that file from each generation.
+///
+/// The kinds are the structured record kinds, ex: "class", "method", or
+/// "field", plus "macro" for macros, which have no structured records, and
+/// "virtualMethod" for the methods that are virtual, which are also counted as
+/// "method".
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct SymbolKindStats {
+    /// The revision of the tree that was indexed, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision: Option<String>,
+    /// The counts keyed by tree-relative directory ("" for the root of the
+    /// tree) and then by kind.
+    #[serde(default)]
+    pub dirs: BTreeMap<String, BTreeMap<String, u64>>,
+}
+
+pub const SYMBOL_KIND_STATS_FILENAME: &str = "symbol-kind-stats.json";
+
+impl SymbolKindStats {
+    pub fn load(path: &str) -> std::io::Result<Option<SymbolKindStats>> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        Ok(Some(serde_json::from_reader(BufReader::new(file))?))
+    }
+
+    pub fn write(&self, path: &str) -> std::io::Result<()> {
+        let file = File::create(path)?;
+        serde_json::to_writer(BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    /// Count a symbol of the given kind defined in the file at the
+    /// tree-relative path.
+    pub fn add(&mut self, path: &str, kind: &str) {
+        let dir = path.rsplit_once('/').map_or("", |(dir, _)| dir);
+        *self
+            .dirs
+            .entry(dir.to_string())
+            .or_default()
+            .entry(kind.to_string())
+            .or_default() += 1;
+    }
+
+    /// The counts keyed by (directory, kind) with the directories truncated to
+    /// `dir_depth` path components (0 for the full directory), limited to the
+    /// directories under `path_prefix`.
+    pub fn totals(&self, dir_depth: usize, path_prefix: &str) -> BTreeMap<(String, String), u64> {
+        let mut totals = BTreeMap::new();
+        for (dir, kinds) in &self.dirs {
+            if !format!("{}/", dir).starts_with(path_prefix) {
+                continue;
+            }
+            let dir = if dir_depth == 0 {
+                dir.clone()
+            } else {
+                dir.split('/').take(dir_depth).collect::<Vec<_>>().join("/")
+            };
+            for (kind, count) in kinds {
+                *totals.entry((dir.clone(), kind.clone())).or_default() += count;
+            }
+        }
+        totals
+    }
+}
+
+#[test]
+fn test_symbol_kind_stats() {
+    let mut stats = SymbolKindStats::default();
+    stats.add("dom/base/Element.h", "class");
+    stats.add("dom/base/Element.h", "method");
+    stats.add("dom/base/Element.cpp", "method");
+    stats.add("dom/media/Media.h", "class");
+    stats.add("layout/base/Shell.h", "class");
+    stats.add("moz.build", "macro");
+
+    assert_eq!(stats.dirs["dom/base"]["method"], 2);
+    assert_eq!(stats.dirs[""]["macro"], 1);
+
+    let totals = stats.totals(1, "dom/");
+    assert_eq!(
+        totals.into_iter().collect::<Vec<_>>(),
+        vec![
+            (("dom".to_string(), "class".to_string()), 2),
+            (("dom".to_string(), "method".to_string()), 2),
+        ]
+    );
+    assert_eq!(stats.totals(0, "").len(), 5);
+}
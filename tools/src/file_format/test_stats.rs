@@ -0,0 +1,211 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Failure statistics for a single test file.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct TestStat {
+    #[serde(default)]
+    pub runs: u64,
+    #[serde(default)]
+    pub failures: u64,
+    #[serde(default)]
+    pub retriggers: u64,
+}
+
+impl TestStat {
+    fn add(&mut self, other: &TestStat) {
+        self.runs += other.runs;
+        self.failures += other.failures;
+        self.retriggers += other.retriggers;
+    }
+}
+
+/// Test failure and retrigger counts keyed by tree-relative test path, as
+/// ingested by `ingest-test-stats` from CI feeds into `test-stats.json` in the
+/// tree's index directory.  This is joined with the symbol-to-test mapping by
+/// `flaky-heatmap`.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct TestStats {
+    #[serde(default)]
+    pub tests: BTreeMap<String, TestStat>,
+}
+
+pub const TEST_STATS_FILENAME: &str = "test-stats.json";
+
+/// The names feeds use for the test path.
+const PATH_KEYS: [&str; 3] = ["path", "test", "test_path"];
+
+/// Split a CSV line per RFC 4180, without support for newlines in fields.
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, in_quotes) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', _) => in_quotes = !in_quotes,
+            (',', false) => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+impl TestStats {
+    pub fn load(path: &str) -> std::io::Result<Option<TestStats>> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        Ok(Some(serde_json::from_reader(BufReader::new(file))?))
+    }
+
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        let file = File::create(path)?;
+        serde_json::to_writer(BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    /// Add to the counts for a test.
+    pub fn record(&mut self, path: &str, stat: &TestStat) {
+        self.tests.entry(path.to_string()).or_default().add(stat);
+    }
+
+    /// Record a CSV feed whose header row names a test path column ("path",
+    /// "test", or "test_path") and any of the "runs", "failures", and
+    /// "retriggers" columns, returning the number of rows recorded.  Rows
+    /// without a path or with unparseable counts are skipped.
+    pub fn record_csv(&mut self, contents: &str) -> usize {
+        let mut lines = contents.lines().filter(|line| !line.trim().is_empty());
+        let Some(header) = lines.next() else {
+            return 0;
+        };
+        let columns: Vec<String> = split_csv_line(header)
+            .into_iter()
+            .map(|name| name.trim().to_lowercase())
+            .collect();
+        let column = |names: &[&str]| columns.iter().position(|c| names.contains(&c.as_str()));
+        let Some(path_col) = column(&PATH_KEYS) else {
+            return 0;
+        };
+        let count_cols = [
+            column(&["runs"]),
+            column(&["failures"]),
+            column(&["retriggers"]),
+        ];
+
+        let mut recorded = 0;
+        for line in lines {
+            let fields = split_csv_line(line);
+            let Some(path) = fields.get(path_col).map(|path| path.trim()) else {
+                continue;
+            };
+            let counts: Option<Vec<u64>> = count_cols
+                .iter()
+                .map(|col| match col.and_then(|col| fields.get(col)) {
+                    Some(field) if !field.trim().is_empty() => field.trim().parse().ok(),
+                    _ => Some(0),
+                })
+                .collect();
+            let Some(counts) = counts.filter(|_| !path.is_empty()) else {
+                continue;
+            };
+            self.record(
+                path,
+                &TestStat {
+                    runs: counts[0],
+                    failures: counts[1],
+                    retriggers: counts[2],
+                },
+            );
+            recorded += 1;
+        }
+        recorded
+    }
+
+    /// Record a JSON feed, which is either an array of objects with a test
+    /// path key and counts (as in the CSV feed) or an object mapping test paths
+    /// to counts, returning the number of tests recorded.
+    pub fn record_json(&mut self, feed: &Value) -> usize {
+        let mut recorded = 0;
+        match feed {
+            Value::Array(entries) => {
+                for entry in entries {
+                    let Some(path) = PATH_KEYS
+                        .iter()
+                        .find_map(|key| entry.get(key).and_then(|path| path.as_str()))
+                    else {
+                        continue;
+                    };
+                    if let Ok(stat) = serde_json::from_value::<TestStat>(entry.clone()) {
+                        self.record(path, &stat);
+                        recorded += 1;
+                    }
+                }
+            }
+            Value::Object(tests) => {
+                for (path, counts) in tests {
+                    if let Ok(stat) = serde_json::from_value::<TestStat>(counts.clone()) {
+                        self.record(path, &stat);
+                        recorded += 1;
+                    }
+                }
+            }
+            _ => {}
+        }
+        recorded
+    }
+}
+
+#[test]
+fn test_record_test_stats() {
+    use serde_json::json;
+
+    let mut stats = TestStats::default();
+    assert_eq!(
+        stats.record_csv(
+            "Test,Runs,Failures,Retriggers\ndom/test_a.html,100,5,2\n\"dom/test,b.html\",10,,1\n,1,1,1\ndom/test_c.html,x,1,1\n"
+        ),
+        2
+    );
+    assert_eq!(
+        stats.record_json(&json!([
+            { "path": "dom/test_a.html", "runs": 50, "failures": 1 },
+            { "runs": 5 },
+        ])),
+        1
+    );
+    assert_eq!(
+        stats.record_json(&json!({ "layout/test_d.html": { "failures": 3 } })),
+        1
+    );
+
+    assert_eq!(
+        stats.tests["dom/test_a.html"],
+        TestStat {
+            runs: 150,
+            failures: 6,
+            retriggers: 2
+        }
+    );
+    assert_eq!(
+        stats.tests["dom/test,b.html"],
+        TestStat {
+            runs: 10,
+            failures: 0,
+            retriggers: 1
+        }
+    );
+    assert_eq!(stats.tests["layout/test_d.html"].failures, 3);
+    assert_eq!(stats.tests.len(), 3);
+}
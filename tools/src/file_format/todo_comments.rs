@@ -0,0 +1,136 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::bug_references::bug_numbers;
+use crate::languages::select_formatting;
+use crate::tokenize::{tokenize_as, TokenKind};
+
+/// A TODO, FIXME, or XXX comment in the source.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct TodoComment {
+    pub path: String,
+    /// 1-based line number.
+    pub line: u32,
+    /// "TODO", "FIXME", or "XXX".
+    pub tag: String,
+    /// Who the comment is assigned to in the `TODO(someone)` convention.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// The bug number referenced by the comment, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bug: Option<u64>,
+    /// The rest of the comment's line after the tag.
+    pub text: String,
+}
+
+/// Index of the TODO comments in the tree, which crossref writes to
+/// `todo-comments.json` in the tree's index directory for the `todos` command.
+/// Only comments are considered (per the formatter's tokenizer), so ex: a
+/// "TODO" in a string literal doesn't count.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct TodoIndex {
+    #[serde(default)]
+    pub todos: Vec<TodoComment>,
+}
+
+pub const TODO_INDEX_FILENAME: &str = "todo-comments.json";
+
+lazy_static! {
+    static ref TODO_RE: Regex =
+        Regex::new(r"\b(TODO|FIXME|XXX)\b(?:\(([^)]*)\))?[:\s]*(.*)").unwrap();
+}
+
+/// Extract the TODO comments from a file's contents.
+pub fn extract_todos(path: &str, contents: &str) -> Vec<TodoComment> {
+    let format = select_formatting(path);
+    let mut todos = vec![];
+    let mut line = 1;
+    let mut last_end = 0;
+    for token in tokenize_as(&format, contents) {
+        line += contents[last_end..token.start].matches('\n').count() as u32;
+        last_end = token.start;
+        if token.kind != TokenKind::Comment {
+            continue;
+        }
+        let comment = &contents[token.start..token.end];
+        // Only report the first tag on a line since ex: "XXX TODO" is one
+        // comment.
+        let Some(caps) = TODO_RE.captures(comment) else {
+            continue;
+        };
+        let text = caps[3]
+            .trim()
+            .trim_end_matches("*/")
+            .trim_end_matches("-->")
+            .trim()
+            .to_string();
+        todos.push(TodoComment {
+            path: path.to_string(),
+            line,
+            tag: caps[1].to_string(),
+            owner: caps
+                .get(2)
+                .map(|owner| owner.as_str().trim().to_string())
+                .filter(|owner| !owner.is_empty()),
+            bug: bug_numbers(comment).next(),
+            text,
+        });
+    }
+    todos
+}
+
+impl TodoIndex {
+    pub fn load(path: &str) -> std::io::Result<Option<TodoIndex>> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        Ok(Some(serde_json::from_reader(BufReader::new(file))?))
+    }
+
+    pub fn write(&self, path: &str) -> std::io::Result<()> {
+        let file = File::create(path)?;
+        serde_json::to_writer(BufWriter::new(file), self)?;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_extract_todos() {
+    let contents = "// TODO(jdoe): Remove this once bug 1234567 is fixed.\nint x = 0;\nconst char* s = \"TODO not a comment\";\n/* FIXME handle\n * XXX: https://bugzilla.mozilla.org/show_bug.cgi?id=7654321 */\nint TODO_count;\n";
+    let todos = extract_todos("todo.cpp", contents);
+    assert_eq!(
+        todos,
+        vec![
+            TodoComment {
+                path: "todo.cpp".to_string(),
+                line: 1,
+                tag: "TODO".to_string(),
+                owner: Some("jdoe".to_string()),
+                bug: Some(1234567),
+                text: "Remove this once bug 1234567 is fixed.".to_string(),
+            },
+            TodoComment {
+                path: "todo.cpp".to_string(),
+                line: 4,
+                tag: "FIXME".to_string(),
+                owner: None,
+                bug: None,
+                text: "handle".to_string(),
+            },
+            TodoComment {
+                path: "todo.cpp".to_string(),
+                line: 5,
+                tag: "XXX".to_string(),
+                owner: None,
+                bug: Some(7654321),
+                text: "https://bugzilla.mozilla.org/show_bug.cgi?id=7654321".to_string(),
+            },
+        ]
+    );
+}
@@ -0,0 +1,217 @@
+extern crate memmap;
+
+use self::memmap::Mmap;
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::str;
+use std::sync::Arc;
+
+/// A bloom filter over the (ASCII case-folded) byte trigrams present in a file,
+/// used to skip files that can't possibly contain a literal when performing
+/// text searches without the benefit of livegrep.
+///
+/// Trigrams are case-folded so that the same filter can answer both
+/// case-sensitive and case-insensitive queries; this only costs us some extra
+/// false positives for case-sensitive queries.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrigramBloom {
+    words: Vec<u64>,
+}
+
+/// We size each filter based on the number of distinct trigrams in the file,
+/// targeting roughly this many bits per trigram, which with `HASH_COUNT` hashes
+/// gives us a false positive rate of around 2%.
+const BITS_PER_TRIGRAM: usize = 8;
+const HASH_COUNT: u32 = 3;
+const MIN_WORDS: usize = 8;
+/// Cap filters at 128KiB; beyond this it's cheaper to just read the file.
+const MAX_WORDS: usize = 16 * 1024;
+
+fn fold(b: u8) -> u8 {
+    b.to_ascii_lowercase()
+}
+
+fn trigram_key(a: u8, b: u8, c: u8) -> u32 {
+    ((fold(a) as u32) << 16) | ((fold(b) as u32) << 8) | fold(c) as u32
+}
+
+/// Produce `HASH_COUNT` bit indices for a trigram using double hashing.
+fn bit_indices(key: u32, bit_count: usize) -> impl Iterator<Item = usize> {
+    // Two cheap independent multiplicative hashes.
+    let h1 = (key as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    let h2 = (key as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F) | 1;
+    (0..HASH_COUNT as u64)
+        .map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) >> 7) as usize % bit_count)
+}
+
+impl TrigramBloom {
+    pub fn from_contents(contents: &[u8]) -> TrigramBloom {
+        let mut keys: Vec<u32> = contents
+            .windows(3)
+            .map(|w| trigram_key(w[0], w[1], w[2]))
+            .collect();
+        keys.sort_unstable();
+        keys.dedup();
+
+        let word_count = (keys.len() * BITS_PER_TRIGRAM / 64)
+            .next_power_of_two()
+            .clamp(MIN_WORDS, MAX_WORDS);
+        let mut words = vec![0u64; word_count];
+        let bit_count = word_count * 64;
+        for key in keys {
+            for bit in bit_indices(key, bit_count) {
+                words[bit / 64] |= 1 << (bit % 64);
+            }
+        }
+        TrigramBloom { words }
+    }
+
+    fn contains_key(words: &[u64], key: u32) -> bool {
+        let bit_count = words.len() * 64;
+        bit_indices(key, bit_count).all(|bit| words[bit / 64] & (1 << (bit % 64)) != 0)
+    }
+
+    /// Returns false only if the file definitely does not contain `literal`.
+    /// Literals shorter than a trigram always return true.
+    pub fn may_contain(&self, literal: &str) -> bool {
+        may_contain_words(&self.words, literal)
+    }
+}
+
+fn may_contain_words(words: &[u64], literal: &str) -> bool {
+    if words.is_empty() {
+        return true;
+    }
+    literal
+        .as_bytes()
+        .windows(3)
+        .all(|w| TrigramBloom::contains_key(words, trigram_key(w[0], w[1], w[2])))
+}
+
+// ## File Format
+//
+// A sequence of records, one per file, each consisting of:
+// - u32 LE length of the path in bytes, followed by the UTF-8 path.
+// - u32 LE number of u64 words in the filter, followed by the LE words.
+
+/// Write out the `trigram-bloom` file for the given (path, filter) pairs.  The
+/// pairs are consumed lazily so that callers can avoid holding every filter in
+/// memory at once.
+pub fn write_trigram_blooms<S, I>(path: &str, blooms: I) -> std::io::Result<()>
+where
+    S: AsRef<str>,
+    I: IntoIterator<Item = (S, TrigramBloom)>,
+{
+    let mut out = BufWriter::new(File::create(path)?);
+    for (file_path, bloom) in blooms {
+        let file_path = file_path.as_ref();
+        out.write_all(&(file_path.len() as u32).to_le_bytes())?;
+        out.write_all(file_path.as_bytes())?;
+        out.write_all(&(bloom.words.len() as u32).to_le_bytes())?;
+        for word in &bloom.words {
+            out.write_all(&word.to_le_bytes())?;
+        }
+    }
+    out.flush()
+}
+
+/// Memory-mapped reader for the `trigram-bloom` file.  We only keep the byte
+/// offsets of each record in memory; the filters themselves stay in the map.
+#[derive(Clone, Debug)]
+pub struct TrigramBloomIndex {
+    mm: Arc<Mmap>,
+    /// (path start, path end, words start, word count)
+    records: Arc<Vec<(usize, usize, usize, usize)>>,
+}
+
+impl TrigramBloomIndex {
+    /// Returns None if the file doesn't exist or is truncated.
+    pub fn new(path: &str) -> Option<TrigramBloomIndex> {
+        let file = File::open(path).ok()?;
+        let mm = unsafe { Mmap::map(&file).ok()? };
+
+        let read_u32 = |pos: usize| -> Option<usize> {
+            Some(u32::from_le_bytes(mm.get(pos..pos + 4)?.try_into().ok()?) as usize)
+        };
+        let mut records = vec![];
+        let mut pos = 0;
+        while pos < mm.len() {
+            let path_len = read_u32(pos)?;
+            let path_start = pos + 4;
+            let path_end = path_start + path_len;
+            let word_count = read_u32(path_end)?;
+            let words_start = path_end + 4;
+            pos = words_start + word_count * 8;
+            if pos > mm.len() {
+                return None;
+            }
+            records.push((path_start, path_end, words_start, word_count));
+        }
+
+        Some(TrigramBloomIndex {
+            mm: Arc::new(mm),
+            records: Arc::new(records),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Iterate over all of the paths in the index, paired with whether the
+    /// file may contain `literal`.  Passing None for `literal` means every
+    /// file is a candidate.
+    pub fn candidates<'a>(
+        &'a self,
+        literal: Option<&'a str>,
+    ) -> impl Iterator<Item = (&'a str, bool)> + 'a {
+        self.records
+            .iter()
+            .map(move |&(path_start, path_end, words_start, word_count)| {
+                let path = str::from_utf8(&self.mm[path_start..path_end]).unwrap_or("");
+                let may_match = match literal {
+                    Some(literal) => {
+                        let words: Vec<u64> = self.mm[words_start..words_start + word_count * 8]
+                            .chunks_exact(8)
+                            .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()))
+                            .collect();
+                        may_contain_words(&words, literal)
+                    }
+                    None => true,
+                };
+                (path, may_match)
+            })
+    }
+}
+
+#[test]
+fn test_trigram_bloom() {
+    let bloom = TrigramBloom::from_contents(b"fn main() {\n    nsCOMPtr<nsIFoo> foo;\n}\n");
+    assert!(bloom.may_contain("nsCOMPtr"));
+    // Case-folded.
+    assert!(bloom.may_contain("nscomptr"));
+    // Too short to say anything.
+    assert!(bloom.may_contain("zz"));
+    assert!(!bloom.may_contain("RefPtr<nsIBar>"));
+
+    let path = std::env::temp_dir().join(format!("trigram-bloom-{}", std::process::id()));
+    let path = path.to_str().unwrap();
+    let other = TrigramBloom::from_contents(b"RefPtr<nsIBar> bar;");
+    write_trigram_blooms(path, vec![("a.cpp", bloom), ("b.cpp", other)]).unwrap();
+
+    let index = TrigramBloomIndex::new(path).unwrap();
+    assert_eq!(index.len(), 2);
+    let matches: Vec<&str> = index
+        .candidates(Some("RefPtr<nsIBar>"))
+        .filter(|(_, may_match)| *may_match)
+        .map(|(path, _)| path)
+        .collect();
+    assert_eq!(matches, vec!["b.cpp"]);
+
+    std::fs::remove_file(path).unwrap();
+}
@@ -1,22 +1,47 @@
-use crate::abstract_server::{ErrorDetails, ErrorLayer, Result, ServerError};
+use std::io::Write;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use crate::abstract_server::{ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError};
 
 pub fn write_file_ensuring_parent_dir(file_path: &str, contents: &str) -> Result<()> {
     let as_path = std::path::Path::new(file_path);
     let parent_path = match as_path.parent() {
         Some(p) => p,
         None => {
-            return Err(ServerError::StickyProblem(ErrorDetails {
-                layer: ErrorLayer::DataLayer,
-                message: format!("Problem getting parent of '{}'", file_path),
-            }));
+            return Err(ServerError::StickyProblem(ErrorDetails::new(
+                ErrorKind::Internal,
+                ErrorLayer::DataLayer,
+                format!("Problem getting parent of '{}'", file_path),
+            )));
         }
     };
     if let Err(e) = std::fs::create_dir_all(parent_path) {
-        return Err(ServerError::StickyProblem(ErrorDetails {
-            layer: ErrorLayer::DataLayer,
-            message: format!("Problem creating parent of '{}': {}", file_path, e),
-        }));
+        return Err(ServerError::StickyProblem(ErrorDetails::new(
+            ErrorKind::Internal,
+            ErrorLayer::DataLayer,
+            format!("Problem creating parent of '{}': {}", file_path, e),
+        )));
     }
     std::fs::write(as_path, contents)?;
     Ok(())
 }
+
+/// Like `write_file_ensuring_parent_dir` but also writes a gzipped copy of the
+/// contents side-by-side at `file_path` + ".gz" so that nginx's `gzip_static`
+/// can serve it with sendfile instead of compressing it for every request.  If
+/// `placeholder` is true, the uncompressed file is left zero-length because
+/// it only needs to exist for nginx's `try_files`; see
+/// `scripts/compress-outputs.sh`.
+pub fn write_gzipped_file_ensuring_parent_dir(
+    file_path: &str,
+    contents: &str,
+    placeholder: bool,
+) -> Result<()> {
+    write_file_ensuring_parent_dir(file_path, if placeholder { "" } else { contents })?;
+    let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+    gz.write_all(contents.as_bytes())?;
+    std::fs::write(format!("{}.gz", file_path), gz.finish()?)?;
+    Ok(())
+}
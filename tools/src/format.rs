@@ -622,6 +622,12 @@ pub fn format_file_data(
         return Ok(format_perf);
     };
 
+    // Mask any secrets before they can make it into the rendered output.
+    let data = match &tree_config.redactor {
+        Some(redactor) => redactor.redact_file("render", path, &data).unwrap_or(data),
+        None => data,
+    };
+
     let slug = format_to_slug_attribute(&format);
     let pre_format_code = Instant::now();
     let (output_lines, sym_json) = format_code(
@@ -665,6 +671,7 @@ pub fn format_file_data(
         include_date: env::var("MOZSEARCH_DIFFABLE").is_err(),
         revision,
         extra_content_classes: "source-listing not-diff",
+        asset_manifest: &tree_config.asset_manifest,
     };
 
     output::generate_header(&opt, writer)?;
@@ -860,12 +867,7 @@ pub fn format_file_data(
 }
 
 fn format_to_slug_attribute(format: &FormatAs) -> String {
-    let slug = match format {
-        FormatAs::FormatTagLike(spec) => spec.markdown_slug,
-        FormatAs::FormatCLike(spec) => spec.markdown_slug,
-        _ => "",
-    };
-
+    let slug = format.markdown_slug();
     if slug.is_empty() {
         return String::new();
     }
@@ -886,25 +888,17 @@ fn entry_to_blob(repo: &git2::Repository, entry: &git2::TreeEntry) -> Result<Str
     Ok(git_ops::read_blob_entry(repo, entry))
 }
 
-/// Dynamically renders the contents of a specific file with blame annotations but without any
-/// semantic analysis data available.  Used by the "rev" display and the "diff" mechanism when
-/// there aren't actually any changes in the diff.
-pub fn format_path(
-    cfg: &Config,
-    tree_name: &str,
-    rev: &str,
+/// Read the contents of a file as of the given commit, including files inside
+/// of submodules.
+pub fn read_path_at_commit(
+    repo: &git2::Repository,
+    commit: &git2::Commit,
     path: &str,
-    writer: &mut dyn Write,
-) -> Result<(), &'static str> {
-    // Get the file data.
-    let tree_config = cfg.trees.get(tree_name).ok_or("Invalid tree")?;
-    let git = tree_config.get_git()?;
-    let commit_obj = git.repo.revparse_single(rev).map_err(|_| "Bad revision")?;
-    let commit = commit_obj.into_commit().map_err(|_| "Bad revision")?;
+) -> Result<String, &'static str> {
     let commit_tree = commit.tree().map_err(|_| "Bad revision")?;
     let path_obj = Path::new(path);
-    let data = match commit_tree.get_path(path_obj) {
-        Ok(entry) => entry_to_blob(&git.repo, &entry)?,
+    match commit_tree.get_path(path_obj) {
+        Ok(entry) => entry_to_blob(repo, &entry),
         Err(_) => {
             // Check to see if this path is inside a submodule
             let mut test_path = path_obj.parent();
@@ -926,8 +920,7 @@ pub fn format_path(
 
                 // If we get here, the path is inside a submodule
                 let subrepo_path = subrepo_path.to_str().ok_or("UTF-8 error")?;
-                let subrepo = git
-                    .repo
+                let subrepo = repo
                     .find_submodule(subrepo_path)
                     .map_err(|_| "Can't find submodule")?;
                 let subrepo = subrepo.open().map_err(|_| "Can't open submodule")?;
@@ -939,10 +932,28 @@ pub fn format_path(
                     .and_then(|commit| commit.tree())
                     .and_then(|tree| tree.get_path(path_in_subrepo))
                     .map_err(|_| "File not found in submodule")?;
-                break entry_to_blob(&subrepo, &subentry)?;
+                break entry_to_blob(&subrepo, &subentry);
             }
         }
-    };
+    }
+}
+
+/// Dynamically renders the contents of a specific file with blame annotations but without any
+/// semantic analysis data available.  Used by the "rev" display and the "diff" mechanism when
+/// there aren't actually any changes in the diff.
+pub fn format_path(
+    cfg: &Config,
+    tree_name: &str,
+    rev: &str,
+    path: &str,
+    writer: &mut dyn Write,
+) -> Result<(), &'static str> {
+    // Get the file data.
+    let tree_config = cfg.trees.get(tree_name).ok_or("Invalid tree")?;
+    let git = tree_config.get_git()?;
+    let commit_obj = git.repo.revparse_single(rev).map_err(|_| "Bad revision")?;
+    let commit = commit_obj.into_commit().map_err(|_| "Bad revision")?;
+    let data = read_path_at_commit(&git.repo, &commit, path)?;
 
     // Get blame.
     let blame_commit = if let Some(ref blame_repo) = git.blame_repo {
@@ -1240,6 +1251,7 @@ pub fn format_diff(
         include_date: true,
         revision: Some((rev, &header)),
         extra_content_classes: "source-listing diff",
+        asset_manifest: &tree_config.asset_manifest,
     };
 
     output::generate_header(&opt, writer)?;
@@ -1556,6 +1568,7 @@ pub fn format_commit(
         include_date: true,
         revision: None,
         extra_content_classes: "commit",
+        asset_manifest: &tree_config.asset_manifest,
     };
 
     output::generate_header(&opt, writer)?;
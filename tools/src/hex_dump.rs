@@ -0,0 +1,57 @@
+//! Rows of a hex dump, for viewing binary files a page at a time.  The pages
+//! come from `AbstractServer::fetch_raw_bytes` and are exposed by the
+//! `hex-dump` command so that a hex view doesn't need to ship the whole file
+//! to the client.
+
+use serde::Serialize;
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct HexRow {
+    /// The offset of the row's first byte within the file.
+    pub offset: u64,
+    /// The row's bytes as space-separated pairs of hex digits.
+    pub hex: String,
+    /// The row's bytes as ASCII for the sidebar, with `.` standing in for
+    /// anything that isn't printable.
+    pub ascii: String,
+}
+
+/// Split the bytes found at `offset` into rows of `width` bytes.  The last row
+/// may be short.
+pub fn hex_rows(offset: u64, data: &[u8], width: usize) -> Vec<HexRow> {
+    let width = width.max(1);
+    data.chunks(width)
+        .enumerate()
+        .map(|(i, row)| HexRow {
+            offset: offset + (i * width) as u64,
+            hex: row
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<Vec<_>>()
+                .join(" "),
+            ascii: row
+                .iter()
+                .map(|b| {
+                    if b.is_ascii_graphic() || *b == b' ' {
+                        *b as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+#[test]
+fn test_hex_rows() {
+    let rows = hex_rows(32, b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR ok", 8);
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[0].offset, 32);
+    assert_eq!(rows[0].hex, "89 50 4e 47 0d 0a 1a 0a");
+    assert_eq!(rows[0].ascii, ".PNG....");
+    assert_eq!(rows[1].offset, 40);
+    assert_eq!(rows[1].ascii, "....IHDR");
+    assert_eq!(rows[2].hex, "20 6f 6b");
+    assert_eq!(rows[2].ascii, " ok");
+}
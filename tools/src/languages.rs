@@ -16,6 +16,8 @@ pub struct LanguageSpec {
     pub rust_tweaks: bool,
     pub cxx14_digit_separators: bool,
     pub markdown_slug: &'static str,
+    /// Human-readable language name for reporting purposes.
+    pub name: &'static str,
 }
 
 pub const SYN_RESERVED_CLASS: &str = "class=\"syn_reserved\" ";
@@ -569,6 +571,7 @@ static RESERVED_WORDS_KOTLIN: &[&str] = &[
 
 lazy_static! {
     static ref JS_SPEC : LanguageSpec = LanguageSpec {
+        name: "JavaScript",
         reserved_words: make_reserved(RESERVED_WORDS_JS),
         hash_identifier: true,
         c_style_comments: true,
@@ -579,11 +582,13 @@ lazy_static! {
     };
 
     static ref HTML_SPEC : LanguageSpec = LanguageSpec {
+        name: "HTML",
         markdown_slug: "html",
         .. JS_SPEC.clone()
     };
 
     static ref CPP_SPEC : LanguageSpec = LanguageSpec {
+        name: "C/C++",
         reserved_words: make_reserved(RESERVED_WORDS_CPP),
         c_style_comments: true,
         c_preprocessor: true,
@@ -593,30 +598,35 @@ lazy_static! {
     };
 
     static ref AIDL_SPEC : LanguageSpec = LanguageSpec {
+        name: "AIDL",
         reserved_words: make_reserved(RESERVED_WORDS_AIDL),
         c_style_comments: true,
         .. LanguageSpec::default()
     };
 
     static ref IPDL_SPEC : LanguageSpec = LanguageSpec {
+        name: "IPDL",
         reserved_words: make_reserved(RESERVED_WORDS_IPDL),
         c_style_comments: true,
         .. LanguageSpec::default()
     };
 
     static ref IDL_SPEC : LanguageSpec = LanguageSpec {
+        name: "XPIDL",
         reserved_words: make_reserved(RESERVED_WORDS_IDL),
         c_style_comments: true,
         .. LanguageSpec::default()
     };
 
     static ref WEBIDL_SPEC : LanguageSpec = LanguageSpec {
+        name: "WebIDL",
         reserved_words: make_reserved(RESERVED_WORDS_WEBIDL),
         c_style_comments: true,
         .. LanguageSpec::default()
     };
 
     static ref PYTHON_SPEC : LanguageSpec = LanguageSpec {
+        name: "Python",
         reserved_words: make_reserved(RESERVED_WORDS_PYTHON),
         hash_comment: true,
         triple_quote_literals: true,
@@ -625,6 +635,7 @@ lazy_static! {
     };
 
     static ref RUST_SPEC : LanguageSpec = LanguageSpec {
+        name: "Rust",
         reserved_words: make_reserved(RESERVED_WORDS_RUST),
         hash_comment: true, // for now, for attributes
         c_style_comments: true,
@@ -634,6 +645,7 @@ lazy_static! {
     };
 
     static ref JAVA_SPEC : LanguageSpec = LanguageSpec {
+        name: "Java",
         reserved_words: make_reserved(RESERVED_WORDS_JAVA),
         c_style_comments: true,
         markdown_slug: "java",
@@ -641,6 +653,7 @@ lazy_static! {
     };
 
     static ref KOTLIN_SPEC : LanguageSpec = LanguageSpec {
+        name: "Kotlin",
         reserved_words: make_reserved(RESERVED_WORDS_KOTLIN),
         c_style_comments: true,
         .. LanguageSpec::default()
@@ -657,6 +670,29 @@ pub enum FormatAs {
     Binary,
 }
 
+impl FormatAs {
+    /// The name of the language for reporting purposes, like in
+    /// `language-stats`.
+    pub fn language_name(&self) -> &'static str {
+        match self {
+            FormatAs::FormatCLike(spec) | FormatAs::FormatTagLike(spec) => spec.name,
+            FormatAs::CSS => "CSS",
+            FormatAs::Plain => "Plain text",
+            FormatAs::StaticPrefs => "Static prefs",
+            FormatAs::Binary => "Binary",
+        }
+    }
+
+    /// The language's slug for markdown code blocks (ex: "cpp"), or "" if it
+    /// doesn't have one.
+    pub fn markdown_slug(&self) -> &'static str {
+        match self {
+            FormatAs::FormatCLike(spec) | FormatAs::FormatTagLike(spec) => spec.markdown_slug,
+            _ => "",
+        }
+    }
+}
+
 pub fn select_formatting(filename: &str) -> FormatAs {
     let ext = match Path::new(filename).extension() {
         Some(ext) => ext.to_str().unwrap(),
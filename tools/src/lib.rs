@@ -48,9 +48,13 @@ pub mod templating;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod tree_sitter_support;
 
+#[cfg(not(target_arch = "wasm32"))]
+pub mod binary_metadata;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod blame;
 #[cfg(not(target_arch = "wasm32"))]
+pub mod code_similarity;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod describe;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod doc_trees_handler;
@@ -63,18 +67,30 @@ pub mod git_ops;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod glob_helper;
 #[cfg(not(target_arch = "wasm32"))]
+pub mod hex_dump;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod languages;
 #[cfg(not(target_arch = "wasm32"))]
+pub mod line_diff;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod links;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod logging;
 #[cfg(not(target_arch = "wasm32"))]
+pub mod number_format;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod output;
 #[cfg(not(target_arch = "wasm32"))]
+pub mod redaction;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod rust_repr;
+#[cfg(not(target_arch = "wasm32"))]
 mod symbol_graph_edge_kind;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod tokenize;
 #[cfg(not(target_arch = "wasm32"))]
+pub mod unicode_folding;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod url_encode_path;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod url_map_handler;
@@ -0,0 +1,260 @@
+//! Line-level diffs of two versions of a file laid out for side-by-side
+//! display, as produced by the `diff-file` command.  The diffing itself is
+//! git's, via `git2::Patch`, so the hunks match what `git diff` would show.
+//! Changed lines can additionally be diffed token by token so that just the
+//! changed identifiers are highlighted.
+
+use std::ops::Range;
+use std::path::Path;
+
+use git2::{DiffOptions, Patch};
+use serde::Serialize;
+
+use crate::languages::FormatAs;
+use crate::tokenize::tokenize_as;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffRowKind {
+    /// The line is the same on both sides.
+    Context,
+    /// A removed line paired with the added line that replaced it.
+    Changed,
+    /// A removed line with no counterpart on the new side.
+    Removed,
+    /// An added line with no counterpart on the old side.
+    Added,
+}
+
+/// A row of a side-by-side diff, with the 1-based line numbers of the lines
+/// shown on each side.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiffRow {
+    pub kind: DiffRowKind,
+    pub old_lineno: Option<u32>,
+    pub new_lineno: Option<u32>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiffHunk {
+    pub old_start: u32,
+    pub old_lines: u32,
+    pub new_start: u32,
+    pub new_lines: u32,
+    pub rows: Vec<DiffRow>,
+}
+
+/// Pair up a run of removed lines with the run of added lines that followed
+/// them, row by row, leaving any excess on one side unpaired.
+fn flush_changes(rows: &mut Vec<DiffRow>, removed: &mut Vec<u32>, added: &mut Vec<u32>) {
+    for i in 0..removed.len().max(added.len()) {
+        let (old_lineno, new_lineno) = (removed.get(i).copied(), added.get(i).copied());
+        let kind = match (old_lineno, new_lineno) {
+            (Some(_), Some(_)) => DiffRowKind::Changed,
+            (Some(_), None) => DiffRowKind::Removed,
+            _ => DiffRowKind::Added,
+        };
+        rows.push(DiffRow {
+            kind,
+            old_lineno,
+            new_lineno,
+        });
+    }
+    removed.clear();
+    added.clear();
+}
+
+/// Diff two versions of a file, with `context` lines of context around each
+/// change.  `path` is only used to let git pick a diff driver.
+pub fn side_by_side_diff(
+    path: &str,
+    old: &str,
+    new: &str,
+    context: u32,
+) -> Result<Vec<DiffHunk>, git2::Error> {
+    let mut opts = DiffOptions::new();
+    opts.context_lines(context).patience(true);
+    let patch = Patch::from_buffers(
+        old.as_bytes(),
+        Some(Path::new(path)),
+        new.as_bytes(),
+        Some(Path::new(path)),
+        Some(&mut opts),
+    )?;
+
+    let mut hunks = vec![];
+    for hunk_idx in 0..patch.num_hunks() {
+        let (hunk, line_count) = patch.hunk(hunk_idx)?;
+        let mut rows = vec![];
+        let mut removed = vec![];
+        let mut added = vec![];
+        for line_idx in 0..line_count {
+            let line = patch.line_in_hunk(hunk_idx, line_idx)?;
+            match (line.origin(), line.old_lineno(), line.new_lineno()) {
+                ('-', Some(old_lineno), _) => removed.push(old_lineno),
+                ('+', _, Some(new_lineno)) => added.push(new_lineno),
+                (' ', old_lineno, new_lineno) => {
+                    flush_changes(&mut rows, &mut removed, &mut added);
+                    rows.push(DiffRow {
+                        kind: DiffRowKind::Context,
+                        old_lineno,
+                        new_lineno,
+                    });
+                }
+                // The "no newline at end of file" markers.
+                _ => {}
+            }
+        }
+        flush_changes(&mut rows, &mut removed, &mut added);
+        hunks.push(DiffHunk {
+            old_start: hunk.old_start(),
+            old_lines: hunk.old_lines(),
+            new_start: hunk.new_start(),
+            new_lines: hunk.new_lines(),
+            rows,
+        });
+    }
+    Ok(hunks)
+}
+
+/// Lines with more tokens than this are treated as entirely changed rather
+/// than spending quadratic time diffing them, ex: minified code.
+const MAX_INTRA_LINE_TOKENS: usize = 500;
+
+/// Split a line into its language tokens, further splitting tokens with
+/// whitespace in them (ex: comments and string literals) into words, and
+/// return the byte range of each.
+fn line_words(format: &FormatAs, line: &str) -> Vec<Range<usize>> {
+    let mut words = vec![];
+    for token in tokenize_as(format, line) {
+        let mut start = None;
+        for (i, c) in line[token.start..token.end].char_indices() {
+            let i = token.start + i;
+            match (c.is_whitespace(), start) {
+                (true, Some(word_start)) => {
+                    words.push(word_start..i);
+                    start = None;
+                }
+                (false, None) => start = Some(i),
+                _ => {}
+            }
+        }
+        if let Some(word_start) = start {
+            words.push(word_start..token.end);
+        }
+    }
+    words
+}
+
+/// Merge ranges that are only separated by whitespace so that a changed run
+/// of tokens is highlighted as one span.
+fn merge_ranges(line: &str, ranges: Vec<Range<usize>>) -> Vec<Range<usize>> {
+    let mut merged: Vec<Range<usize>> = vec![];
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if line[last.end..range.start].trim().is_empty() => last.end = range.end,
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+/// Diff the old and new versions of a changed line token by token, using the
+/// language's tokenizer, returning the byte ranges of the changed tokens in
+/// each version.
+pub fn intra_line_changes(
+    format: &FormatAs,
+    old: &str,
+    new: &str,
+) -> (Vec<Range<usize>>, Vec<Range<usize>>) {
+    let old_words = line_words(format, old);
+    let new_words = line_words(format, new);
+    if old_words.len() > MAX_INTRA_LINE_TOKENS || new_words.len() > MAX_INTRA_LINE_TOKENS {
+        return (merge_ranges(old, old_words), merge_ranges(new, new_words));
+    }
+
+    // The length of the longest common subsequence of the words from each
+    // pair of positions onwards.
+    let mut lcs = vec![vec![0u32; new_words.len() + 1]; old_words.len() + 1];
+    for i in (0..old_words.len()).rev() {
+        for j in (0..new_words.len()).rev() {
+            lcs[i][j] = if old[old_words[i].clone()] == new[new_words[j].clone()] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut old_changes = vec![];
+    let mut new_changes = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < old_words.len() && j < new_words.len() {
+        if old[old_words[i].clone()] == new[new_words[j].clone()] {
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            old_changes.push(old_words[i].clone());
+            i += 1;
+        } else {
+            new_changes.push(new_words[j].clone());
+            j += 1;
+        }
+    }
+    old_changes.extend(old_words[i..].iter().cloned());
+    new_changes.extend(new_words[j..].iter().cloned());
+    (
+        merge_ranges(old, old_changes),
+        merge_ranges(new, new_changes),
+    )
+}
+
+#[test]
+fn test_side_by_side_diff() {
+    let old = "a\nb\nc\nd\ne\nf\n";
+    let new = "a\nB\nc\nd\nx\ny\nf\n";
+    let hunks = side_by_side_diff("test.txt", old, new, 3).unwrap();
+    let rows = |hunk: &DiffHunk| {
+        hunk.rows
+            .iter()
+            .map(|row| (row.kind, row.old_lineno, row.new_lineno))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(hunks.len(), 1);
+    assert_eq!((hunks[0].old_start, hunks[0].new_start), (1, 1));
+    assert_eq!(
+        rows(&hunks[0]),
+        vec![
+            (DiffRowKind::Context, Some(1), Some(1)),
+            (DiffRowKind::Changed, Some(2), Some(2)),
+            (DiffRowKind::Context, Some(3), Some(3)),
+            (DiffRowKind::Context, Some(4), Some(4)),
+            (DiffRowKind::Changed, Some(5), Some(5)),
+            (DiffRowKind::Added, None, Some(6)),
+            (DiffRowKind::Context, Some(6), Some(7)),
+        ]
+    );
+
+    assert!(side_by_side_diff("test.txt", old, old, 3)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn test_intra_line_changes() {
+    use crate::languages::select_formatting;
+
+    let format = select_formatting("test.cpp");
+    let old = "  int count = GetCount(aFoo); // the count";
+    let new = "  int total = GetCount(aBar); // the total count";
+    let (old_changes, new_changes) = intra_line_changes(&format, old, new);
+    let text = |line: &'static str, ranges: Vec<Range<usize>>| {
+        ranges
+            .into_iter()
+            .map(|range| &line[range])
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(text(old, old_changes), vec!["count", "aFoo"]);
+    assert_eq!(text(new, new_changes), vec!["total", "aBar", "total"]);
+}
@@ -0,0 +1,111 @@
+//! Helpers for formatting counts in human readable output so that the pipeline
+//! tables and the liquid templates agree on how to say "1 byte" vs "2 bytes".
+//!
+//! Searchfox's UI is English-only, so pluralization only implements English's
+//! one/other rule, but digit grouping takes a locale so that callers that know
+//! better can opt into other separators.
+
+/// Return the appropriate form of a noun for `count` items.
+pub fn pluralize<'a>(count: u64, singular: &'a str, plural: &'a str) -> &'a str {
+    if count == 1 {
+        singular
+    } else {
+        plural
+    }
+}
+
+/// The separator to use between groups of 3 digits for a BCP 47-ish locale,
+/// defaulting to English.
+fn group_separator(locale: &str) -> &'static str {
+    let lang = locale.split(['-', '_']).next().unwrap_or_default();
+    match lang {
+        "de" | "es" | "it" | "nl" | "pt" => ".",
+        // Narrow no-break space, per CLDR.
+        "fr" => "\u{202f}",
+        _ => ",",
+    }
+}
+
+/// Format `n` with its digits grouped into thousands per `locale`.
+pub fn group_digits(n: u64, locale: &str) -> String {
+    let digits = n.to_string();
+    let separator = group_separator(locale);
+    let mut result = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            result.push_str(separator);
+        }
+        result.push(c);
+    }
+    result
+}
+
+/// Format a count along with the appropriately pluralized unit, like
+/// "1 byte" or "1,024 bytes".
+pub fn format_count(count: u64, singular: &str, plural: &str) -> String {
+    format!(
+        "{} {}",
+        group_digits(count, "en"),
+        pluralize(count, singular, plural)
+    )
+}
+
+/// Format a size in bytes using binary units, like "512 bytes" or "1.5 KiB".
+pub fn format_byte_size_human(bytes: u64) -> String {
+    const UNITS: &[&str] = &["KiB", "MiB", "GiB"];
+    if bytes < 1024 {
+        return format_count(bytes, "byte", "bytes");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    // Promote based on the value as it will be displayed so that we never
+    // show something like "1024.0 KiB".
+    while (size * 10.0).round() / 10.0 >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    // Only omit the decimal when the value is exact, so rounded values like
+    // "1.0 MiB" remain distinguishable from exact ones.
+    if size.fract() == 0.0 {
+        format!("{} {}", size, UNITS[unit])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+#[test]
+fn test_pluralize() {
+    assert_eq!(pluralize(0, "byte", "bytes"), "bytes");
+    assert_eq!(pluralize(1, "byte", "bytes"), "byte");
+    assert_eq!(pluralize(2, "byte", "bytes"), "bytes");
+}
+
+#[test]
+fn test_group_digits() {
+    assert_eq!(group_digits(0, "en"), "0");
+    assert_eq!(group_digits(999, "en"), "999");
+    assert_eq!(group_digits(1000, "en"), "1,000");
+    assert_eq!(group_digits(1234567, "en-US"), "1,234,567");
+    assert_eq!(group_digits(1234567, "de"), "1.234.567");
+    assert_eq!(group_digits(1234, "fr_FR"), "1\u{202f}234");
+}
+
+#[test]
+fn test_format_count() {
+    assert_eq!(format_count(1, "byte", "bytes"), "1 byte");
+    assert_eq!(format_count(4096, "byte", "bytes"), "4,096 bytes");
+}
+
+#[test]
+fn test_format_byte_size_human() {
+    assert_eq!(format_byte_size_human(1), "1 byte");
+    assert_eq!(format_byte_size_human(1023), "1,023 bytes");
+    assert_eq!(format_byte_size_human(1024), "1 KiB");
+    assert_eq!(format_byte_size_human(1536), "1.5 KiB");
+    assert_eq!(format_byte_size_human(3 * 1024 * 1024), "3 MiB");
+    assert_eq!(format_byte_size_human(1048575), "1.0 MiB");
+    assert_eq!(format_byte_size_human(1048524), "1023.9 KiB");
+    assert_eq!(format_byte_size_human(1024 * 1024 - 51), "1.0 MiB");
+    assert_eq!(format_byte_size_human(1024 * 1024 * 1024 - 1), "1.0 GiB");
+    assert_eq!(format_byte_size_human(2048 * 1024 * 1024), "2048 GiB");
+}
@@ -6,6 +6,7 @@ use std::path::Path;
 
 extern crate chrono;
 use crate::file_format::analysis_manglings::make_file_sym_from_path;
+use crate::file_format::asset_manifest::AssetManifest;
 use crate::url_encode_path::url_encode_path;
 
 use self::chrono::{DateTime, Local};
@@ -19,6 +20,8 @@ pub struct Options<'a> {
     /// source listings where we have particular styling needs for "position: sticky" but want
     /// every other display to have normal padding.
     pub extra_content_classes: &'a str,
+    /// Fingerprints the static asset URLs in the header and footer.
+    pub asset_manifest: &'a AssetManifest,
 }
 
 pub fn choose_icon(path: &str) -> String {
@@ -139,8 +142,8 @@ pub fn generate_header(opt: &Options, writer: &mut dyn Write) -> Result<(), &'st
     let css = ["mozsearch.css", "icons.css", "font-icons.css"];
     let css_tags = css.iter().map(|c| {
         F::T(format!(
-            r#"<link href="/{}/static/css/{}" rel="stylesheet" media="screen"/>"#,
-            opt.tree_name, c
+            r#"<link href="{}" rel="stylesheet" media="screen"/>"#,
+            opt.asset_manifest.url(opt.tree_name, &format!("css/{}", c))
         ))
     });
 
@@ -148,8 +151,8 @@ pub fn generate_header(opt: &Options, writer: &mut dyn Write) -> Result<(), &'st
         F::S(r#"<meta charset="utf-8" />"#),
         F::S(r#"<meta name="color-scheme" content="light dark">"#),
         F::T(format!(
-            r#"<link href="/{}/static/icons/search.png" rel="shortcut icon">"#,
-            opt.tree_name
+            r#"<link href="{}" rel="shortcut icon">"#,
+            opt.asset_manifest.url(opt.tree_name, "icons/search.png")
         )),
         F::T(format!("<title>{}</title>", opt.title)),
     ];
@@ -297,8 +300,8 @@ pub fn generate_footer(
         .iter()
         .map(|s| {
             F::T(format!(
-                r#"<script src="/{}/static/js/{}"></script>"#,
-                opt.tree_name, s
+                r#"<script src="{}"></script>"#,
+                opt.asset_manifest.url(opt.tree_name, &format!("js/{}", s))
             ))
         })
         .collect();
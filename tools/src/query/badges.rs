@@ -0,0 +1,240 @@
+//! SVG badges for embedding code-health indicators (ex: the total padding bytes
+//! under gfx/) in build dashboards.  A badge shows the current value of a
+//! metric extracted from a post-index hook's most recent stored run (see
+//! `query/post_index_hooks.rs`) along with its change since the previous run,
+//! or from a symbol's crossref info when made via the `badge` command.  The
+//! pipeline-server serves hook badges at `/:tree/badge/:hook`.
+
+use serde_json::Value;
+
+use crate::{
+    abstract_server::{AbstractServer, Result},
+    query::post_index_hooks::{list_hook_runs, load_hook_run, PostIndexHookRun},
+};
+
+/// How to reduce the JSON found at a metric's pointer to a number.
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+pub enum BadgeAggregate {
+    /// The value must itself be a number.
+    Value,
+    /// The number of items in an array or object.
+    Count,
+    /// The sum of the numbers in an array (or the numbers at `field` within
+    /// each of its items).
+    Sum,
+}
+
+/// Where to find a metric's value in some JSON.
+#[derive(Clone, Debug)]
+pub struct BadgeMetric {
+    /// JSON pointer to the value, ex: `/DataTable/rows`.
+    pub pointer: String,
+    pub aggregate: BadgeAggregate,
+    /// JSON pointer within each item for `BadgeAggregate::Sum`, ex: `/3`.
+    pub field: Option<String>,
+}
+
+impl BadgeMetric {
+    /// Extract the metric's value, or None if the JSON doesn't have it.
+    pub fn extract(&self, value: &Value) -> Option<f64> {
+        let value = value.pointer(&self.pointer)?;
+        match self.aggregate {
+            BadgeAggregate::Value => value.as_f64(),
+            BadgeAggregate::Count => match value {
+                Value::Array(items) => Some(items.len() as f64),
+                Value::Object(obj) => Some(obj.len() as f64),
+                _ => None,
+            },
+            BadgeAggregate::Sum => {
+                let items = value.as_array()?;
+                Some(
+                    items
+                        .iter()
+                        .filter_map(|item| match &self.field {
+                            Some(field) => item.pointer(field).and_then(Value::as_f64),
+                            None => item.as_f64(),
+                        })
+                        .sum(),
+                )
+            }
+        }
+    }
+}
+
+pub struct Badge {
+    pub label: String,
+    pub value: Option<f64>,
+    /// The value from the previous run, if there is one.
+    pub previous: Option<f64>,
+    /// Whether an increase is good news, which decides the trend's color.
+    pub higher_is_better: bool,
+}
+
+/// Load the most recent run of the hook followed by the run before it, if the
+/// hook has been run that many times.
+pub fn load_badge_runs(
+    server: &(dyn AbstractServer + Send + Sync),
+    hook: &str,
+) -> Result<Vec<PostIndexHookRun>> {
+    let mut runs = vec![];
+    for ran_at in list_hook_runs(server, hook)?.into_iter().rev().take(2) {
+        if let Some(run) = load_hook_run(server, hook, Some(ran_at))? {
+            runs.push(run);
+        }
+    }
+    Ok(runs)
+}
+
+impl Badge {
+    /// Make a badge from the runs returned by `load_badge_runs`.  Failed runs
+    /// have no value.
+    pub fn from_runs(
+        label: &str,
+        runs: &[PostIndexHookRun],
+        metric: &BadgeMetric,
+        higher_is_better: bool,
+    ) -> Self {
+        let value_of = |run: Option<&PostIndexHookRun>| run.and_then(|r| metric.extract(&r.output));
+        Badge {
+            label: label.to_string(),
+            value: value_of(runs.first()),
+            previous: value_of(runs.get(1)),
+            higher_is_better,
+        }
+    }
+
+    /// The change since the previous run.
+    pub fn trend(&self) -> Option<f64> {
+        Some(self.value? - self.previous?)
+    }
+
+    fn message(&self) -> String {
+        let Some(value) = self.value else {
+            return "n/a".to_string();
+        };
+        match self.trend() {
+            Some(delta) if delta > 0.0 => {
+                format!("{} ▲{}", format_number(value), format_number(delta))
+            }
+            Some(delta) if delta < 0.0 => {
+                format!("{} ▼{}", format_number(value), format_number(-delta))
+            }
+            _ => format_number(value),
+        }
+    }
+
+    fn color(&self) -> &'static str {
+        if self.value.is_none() {
+            return "#9f9f9f";
+        }
+        match self.trend() {
+            Some(delta) if delta != 0.0 && (delta > 0.0) == self.higher_is_better => "#4c1",
+            Some(delta) if delta != 0.0 => "#e05d44",
+            _ => "#007ec6",
+        }
+    }
+
+    /// Render the badge as a flat SVG in the style of shields.io badges.
+    pub fn to_svg(&self) -> String {
+        let message = self.message();
+        let label_width = text_width(&self.label);
+        let message_width = text_width(&message);
+        let width = label_width + message_width;
+        format!(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20" role="img" aria-label="{label}: {message}">
+<title>{label}: {message}</title>
+<rect width="{label_width}" height="20" fill="#555"/>
+<rect x="{label_width}" width="{message_width}" height="20" fill="{color}"/>
+<g fill="#fff" text-anchor="middle" font-family="Verdana,DejaVu Sans,sans-serif" font-size="11">
+<text x="{label_x}" y="14">{label}</text>
+<text x="{message_x}" y="14">{message}</text>
+</g>
+</svg>
+"##,
+            width = width,
+            label_width = label_width,
+            message_width = message_width,
+            label = escape_xml(&self.label),
+            message = escape_xml(&message),
+            color = self.color(),
+            label_x = label_width / 2,
+            message_x = label_width + message_width / 2,
+        )
+    }
+}
+
+fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 {
+        format!("{}", value as i64)
+    } else {
+        format!("{:.2}", value)
+    }
+}
+
+/// Roughly how wide the text is at 11px Verdana, plus padding.
+fn text_width(text: &str) -> usize {
+    text.chars().count() * 7 + 10
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[test]
+fn test_badge_metric() {
+    let output = serde_json::json!({
+        "DataTable": {
+            "rows": [["Foo", 8], ["Bar", 24], ["Baz", null]],
+        },
+    });
+
+    let sum = BadgeMetric {
+        pointer: "/DataTable/rows".to_string(),
+        aggregate: BadgeAggregate::Sum,
+        field: Some("/1".to_string()),
+    };
+    assert_eq!(sum.extract(&output), Some(32.0));
+
+    let count = BadgeMetric {
+        aggregate: BadgeAggregate::Count,
+        ..sum.clone()
+    };
+    assert_eq!(count.extract(&output), Some(3.0));
+
+    let value = BadgeMetric {
+        pointer: "/DataTable/rows/1/1".to_string(),
+        aggregate: BadgeAggregate::Value,
+        field: None,
+    };
+    assert_eq!(value.extract(&output), Some(24.0));
+
+    let missing = BadgeMetric {
+        pointer: "/JsonValue".to_string(),
+        ..value
+    };
+    assert_eq!(missing.extract(&output), None);
+}
+
+#[test]
+fn test_badge_svg() {
+    let badge = Badge {
+        label: "gfx padding".to_string(),
+        value: Some(1200.0),
+        previous: Some(1000.0),
+        higher_is_better: false,
+    };
+    assert_eq!(badge.trend(), Some(200.0));
+    let svg = badge.to_svg();
+    assert!(svg.contains("gfx padding: 1200 ▲200"));
+    assert!(svg.contains("#e05d44"));
+
+    let badge = Badge {
+        previous: None,
+        ..badge
+    };
+    assert!(badge.to_svg().contains("gfx padding: 1200<"));
+    assert!(badge.to_svg().contains("#007ec6"));
+}
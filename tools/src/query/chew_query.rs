@@ -8,8 +8,9 @@ use serde::{Deserialize, Serialize};
 use toml::value::Table;
 
 use crate::{
-    abstract_server::{ErrorDetails, ErrorLayer, Result, ServerError},
+    abstract_server::{ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError},
     cmd_pipeline::transforms::path_glob_transform,
+    unicode_folding::text_search_regexp,
 };
 
 /*
@@ -127,6 +128,7 @@ fn apply_transforms(user_val: String, transforms: &[String]) -> String {
     for transform in transforms.iter() {
         val = match transform.as_str() {
             "regexp_escape" => regex::escape(&val),
+            "unicode_text" => text_search_regexp(&val, false),
             "path_glob" => path_glob_transform(&val),
             _ => val,
         }
@@ -178,10 +180,11 @@ impl QueryPipelineGroupBuilder {
 
                 for conflict in term.conflicts.iter() {
                     if terms_processed.iter().any(|x| x == conflict) {
-                        return Err(ServerError::StickyProblem(ErrorDetails {
-                            layer: ErrorLayer::BadInput,
-                            message: format!("{} conflicts with {}", term_str, conflict),
-                        }));
+                        return Err(ServerError::StickyProblem(ErrorDetails::new(
+                            ErrorKind::BadQuery,
+                            ErrorLayer::BadInput,
+                            format!("{} conflicts with {}", term_str, conflict),
+                        )));
                     }
                 }
 
@@ -488,6 +491,20 @@ pub fn chew_query(full_arg_str: &str) -> Result<QueryPipelineGroupBuilder> {
     // We then flush the current phase.  If there are `next_groups`, we repeat
     // the loop with a new phase, otherwise we're done.
 
+    schedule_phases(&mut builder, root_groups, &inputs_to_names)?;
+
+    Ok(builder)
+}
+
+/// Populate `builder.phases` from the groups and junctions in the builder,
+/// starting from the `root_groups` (which have no input).  `inputs_to_names`
+/// maps each input name to the groups/junctions that consume it.  See the
+/// "Walk the graph" comment in `chew_query` for details.
+pub fn schedule_phases(
+    builder: &mut QueryPipelineGroupBuilder,
+    root_groups: BTreeSet<String>,
+    inputs_to_names: &HashMap<String, Vec<String>>,
+) -> Result<()> {
     let mut next_groups: VecDeque<(String, Option<usize>)> =
         root_groups.into_iter().map(|x| (x, None)).collect();
     let mut pending_junctions = BTreeMap::new();
@@ -509,10 +526,11 @@ pub fn chew_query(full_arg_str: &str) -> Result<QueryPipelineGroupBuilder> {
                 // we could be adding a junction, which absolutely can be
                 // arrived at multiple times.
                 if !seen.insert(thing_name.clone()) {
-                    return Err(ServerError::StickyProblem(ErrorDetails {
-                        layer: ErrorLayer::ConfigLayer,
-                        message: format!("pipeline loop: group {} used multiple times", thing_name),
-                    }));
+                    return Err(ServerError::StickyProblem(ErrorDetails::new(
+                        ErrorKind::Internal,
+                        ErrorLayer::ConfigLayer,
+                        format!("pipeline loop: group {} used multiple times", thing_name),
+                    )));
                 }
 
                 // This is a group, put it in the current phase.
@@ -534,13 +552,14 @@ pub fn chew_query(full_arg_str: &str) -> Result<QueryPipelineGroupBuilder> {
                         // result is a terminal output and so there's nothing to do.
                     } else {
                         for next_group in inputs_to_names.get(next_input).ok_or_else(|| {
-                            ServerError::StickyProblem(ErrorDetails {
-                                layer: ErrorLayer::ConfigLayer,
-                                message: format!(
+                            ServerError::StickyProblem(ErrorDetails::new(
+                                ErrorKind::Internal,
+                                ErrorLayer::ConfigLayer,
+                                format!(
                                     "group {} output {} is never consumed",
                                     thing_name, next_input,
                                 ),
-                            })
+                            ))
                         })? {
                             next_groups.push_back((next_group.clone(), next_group_slot));
                         }
@@ -566,13 +585,14 @@ pub fn chew_query(full_arg_str: &str) -> Result<QueryPipelineGroupBuilder> {
                 // Complain if this isn't the first time we've processed this
                 // junction as it does indicate some kind of loop.
                 if !seen.insert(junction_name.clone()) {
-                    return Err(ServerError::StickyProblem(ErrorDetails {
-                        layer: ErrorLayer::ConfigLayer,
-                        message: format!(
+                    return Err(ServerError::StickyProblem(ErrorDetails::new(
+                        ErrorKind::Internal,
+                        ErrorLayer::ConfigLayer,
+                        format!(
                             "pipeline loop: junction {} used multiple times",
                             junction_name
                         ),
-                    }));
+                    )));
                 }
 
                 if let Some(output) = &junction.output {
@@ -580,13 +600,14 @@ pub fn chew_query(full_arg_str: &str) -> Result<QueryPipelineGroupBuilder> {
                         // result is a terminal output and there's nothing to do
                     } else {
                         for next_group in inputs_to_names.get(output).ok_or_else(|| {
-                            ServerError::StickyProblem(ErrorDetails {
-                                layer: ErrorLayer::ConfigLayer,
-                                message: format!(
+                            ServerError::StickyProblem(ErrorDetails::new(
+                                ErrorKind::Internal,
+                                ErrorLayer::ConfigLayer,
+                                format!(
                                     "junction {} output {} is never consumed",
                                     junction_name, output,
                                 ),
-                            })
+                            ))
                         })? {
                             next_groups.push_back((next_group.clone(), None));
                         }
@@ -597,5 +618,5 @@ pub fn chew_query(full_arg_str: &str) -> Result<QueryPipelineGroupBuilder> {
         builder.phases.push(cur_phase);
     }
 
-    Ok(builder)
+    Ok(())
 }
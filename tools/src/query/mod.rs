@@ -1 +1,7 @@
+pub mod badges;
 pub mod chew_query;
+pub mod pipeline_cache;
+pub mod pipeline_spec;
+pub mod post_index_hooks;
+pub mod presets;
+pub mod shared_analyses;
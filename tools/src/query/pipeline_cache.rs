@@ -0,0 +1,229 @@
+//! The pipeline cache holds the outputs of query presets that the `precompute`
+//! command ran at index time for a tree's hot symbols (per the tree's
+//! `precompute` config), so that the pipeline-server can serve the first hit
+//! on expensive pages like field layouts and caller graphs without waiting on
+//! the pipeline.  Each output is stored as JSON at
+//! `pipeline-cache/PRESET/KEY.json` under the index root, where KEY is a hash
+//! of the preset's parameters.  Cached outputs are only used if they were
+//! computed for the revision that is currently indexed.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::warn;
+
+use crate::{
+    abstract_server::{
+        AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, SearchfoxIndexRoot,
+        ServerError,
+    },
+    cmd_pipeline::builder::CommandSafetyLevel,
+    file_format::config::QueryPresetConfig,
+    query::presets::{all_presets, run_preset_config},
+};
+
+/// A cached output of a query preset.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedPresetRun {
+    pub preset: String,
+    pub params: BTreeMap<String, String>,
+    /// The revision of the tree that was indexed, if known.
+    pub revision: Option<String>,
+    pub duration_ms: u64,
+    /// The serialized `PipelineValues` the preset produced.
+    pub output: Value,
+    /// The SYM_INFO for rendering `output` as HTML.
+    pub sym_info: Value,
+}
+
+/// The result of precomputing a preset for a single symbol.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrecomputeOutcome {
+    pub preset: String,
+    pub symbol: String,
+    pub duration_ms: u64,
+    pub error: Option<String>,
+}
+
+/// FNV-1a over the parameters, which is stable across builds (unlike
+/// `DefaultHasher`) so that the server can find what the indexer stored.
+fn cache_key(params: &BTreeMap<String, String>) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for (key, value) in params {
+        for byte in key
+            .bytes()
+            .chain(std::iter::once(b'='))
+            .chain(value.bytes())
+            .chain(std::iter::once(0))
+        {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    format!("{:016x}", hash)
+}
+
+/// Determine the name of the single parameter a preset must have for it to be
+/// precomputed for a symbol.
+fn symbol_param(name: &str, preset: &QueryPresetConfig) -> Result<String> {
+    let required: Vec<&String> = preset
+        .params
+        .iter()
+        .filter(|param| !preset.defaults.contains_key(*param))
+        .collect();
+    match required.as_slice() {
+        [param] => Ok(param.to_string()),
+        _ => Err(ServerError::StickyProblem(ErrorDetails::new(
+            ErrorKind::Internal,
+            ErrorLayer::ConfigLayer,
+            format!(
+                "Preset {} must have exactly one parameter without a default to be precomputed",
+                name
+            ),
+        ))),
+    }
+}
+
+/// Load the cached output of the preset for the given parameters, returning
+/// None if there isn't one for the currently indexed revision.
+pub fn load_cached_preset(
+    server: &(dyn AbstractServer + Send + Sync),
+    preset: &str,
+    params: &BTreeMap<String, String>,
+) -> Result<Option<CachedPresetRun>> {
+    let tree_info = server.tree_info()?;
+    // This also validates the name before we use it as a path component.
+    if !all_presets(&tree_info.query_presets).contains_key(preset) {
+        return Ok(None);
+    }
+    let path = server.translate_path(
+        SearchfoxIndexRoot::PipelineCache,
+        &format!("{}/{}.json", preset, cache_key(params)),
+    )?;
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let run: CachedPresetRun = serde_json::from_str(&contents)?;
+    // Guard against hash collisions and stale outputs from a previous index
+    // that was updated in place.
+    if run.params != *params || run.revision != tree_info.revision {
+        return Ok(None);
+    }
+    if let Some(counters) = server.resource_counters() {
+        counters.add_cache_hit();
+    }
+    Ok(Some(run))
+}
+
+fn store_cached_preset(
+    server: &(dyn AbstractServer + Send + Sync),
+    run: &CachedPresetRun,
+) -> Result<()> {
+    let dir = server.translate_path(SearchfoxIndexRoot::PipelineCache, &run.preset)?;
+    fs::create_dir_all(&dir)?;
+    fs::write(
+        format!("{}/{}.json", dir, cache_key(&run.params)),
+        serde_json::to_string(run)?,
+    )?;
+    Ok(())
+}
+
+/// Delete all of the cached outputs.
+pub fn clear_pipeline_cache(server: &(dyn AbstractServer + Send + Sync)) -> Result<()> {
+    let dir = server.translate_path(SearchfoxIndexRoot::PipelineCache, "")?;
+    match fs::remove_dir_all(dir) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Run each of the named presets for each of the symbols, caching their
+/// outputs.  A preset failing for a symbol does not stop the others from
+/// running; the failure is reported in its outcome.
+pub async fn precompute_presets(
+    server: &(dyn AbstractServer + Send + Sync),
+    preset_names: &[String],
+    symbols: &[String],
+) -> Result<Vec<PrecomputeOutcome>> {
+    let tree_info = server.tree_info()?;
+    let presets = all_presets(&tree_info.query_presets);
+
+    // Validate everything up front so a config mistake doesn't waste the time
+    // spent running the presets that come before it.
+    let mut to_run = vec![];
+    for name in preset_names {
+        let preset = presets.get(name).ok_or_else(|| {
+            ServerError::StickyProblem(
+                ErrorDetails::new(
+                    ErrorKind::NotFound,
+                    ErrorLayer::ConfigLayer,
+                    format!("No such preset to precompute: {}", name),
+                )
+                .with_context("preset", name),
+            )
+        })?;
+        to_run.push((name, preset, symbol_param(name, preset)?));
+    }
+
+    let mut outcomes = vec![];
+    for (name, preset, param) in to_run {
+        for symbol in symbols {
+            let params = BTreeMap::from([(param.clone(), symbol.clone())]);
+            let start = Instant::now();
+            // Use the same safety level the pipeline-server would since the
+            // outputs stand in for what it would have computed.
+            let result =
+                run_preset_config(server, name, preset, &params, CommandSafetyLevel::WebSafety)
+                    .await;
+            let duration_ms = start.elapsed().as_millis() as u64;
+
+            let error = match result {
+                Ok(values) => {
+                    store_cached_preset(
+                        server,
+                        &CachedPresetRun {
+                            preset: name.clone(),
+                            params,
+                            revision: tree_info.revision.clone(),
+                            duration_ms,
+                            output: serde_json::to_value(&values)?,
+                            sym_info: values.sym_info(),
+                        },
+                    )?;
+                    None
+                }
+                Err(err) => {
+                    warn!("Precomputing {} for {} failed: {:?}", name, symbol, err);
+                    Some(format!("{:?}", err))
+                }
+            };
+            outcomes.push(PrecomputeOutcome {
+                preset: name.clone(),
+                symbol: symbol.clone(),
+                duration_ms,
+                error,
+            });
+        }
+    }
+    Ok(outcomes)
+}
+
+#[test]
+fn test_cache_key() {
+    let a = BTreeMap::from([("class".to_string(), "nsIFoo".to_string())]);
+    let b = BTreeMap::from([("class".to_string(), "nsIBar".to_string())]);
+    assert_eq!(cache_key(&a), cache_key(&a.clone()));
+    assert_ne!(cache_key(&a), cache_key(&b));
+    assert_eq!(cache_key(&a).len(), 16);
+    // This must never change or caches stored by older versions will be
+    // silently ignored.
+    assert_eq!(cache_key(&BTreeMap::new()), "cbf29ce484222325");
+}
@@ -0,0 +1,350 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::abstract_server::{ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError};
+
+use super::chew_query::{
+    schedule_phases, JunctionNode, PipelineArgs, PipelineGroup, PipelineSegment,
+    QueryPipelineGroupBuilder,
+};
+
+/*
+  Pipeline spec files describe a pipeline graph in a YAML (or JSON) document as
+  an alternative to CLI pipeline strings and `query-parser` queries.  They are
+  translated directly into the same `QueryPipelineGroupBuilder` representation
+  that `chew_query` produces so they can be run by `build_pipeline_graph`.
+
+  ```yaml
+  include:
+    - common.yaml
+  vars:
+    depth: "4"
+  groups:
+    callers:
+      output: result
+      commands:
+        - command: search-identifiers
+          args:
+            exact-match: true
+            positional: ${id}
+        - command: crossref-lookup
+        - command: traverse
+          args:
+            edge: uses
+            max-depth: ${depth}
+  ```
+
+  - `include`: Other spec files, relative to this one, whose vars, groups, and
+    junctions are merged in.  Definitions in the including file win.
+  - `vars`: Default values for `${name}` placeholders, which can be overridden
+    by the caller.  Placeholders are only expanded in arg values.
+  - `groups`: Linear chains of commands.  A group consumes the `output` of
+    another group or junction via `input` and its output defaults to the name
+    of the group.  Exactly one group or junction must output "result".
+  - `junctions`: Commands that combine multiple `inputs`, like
+    `compile-results`.
+
+  Args use the same conventions as `query_core.toml`: `true` booleans become
+  flags, strings/numbers become `--name=value`, and "positional" may be a
+  string or list of strings.
+*/
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PipelineSpec {
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub vars: BTreeMap<String, String>,
+    #[serde(default)]
+    pub groups: BTreeMap<String, SpecGroup>,
+    #[serde(default)]
+    pub junctions: BTreeMap<String, SpecJunction>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpecGroup {
+    pub input: Option<String>,
+    pub output: Option<String>,
+    pub commands: Vec<SpecCommand>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpecCommand {
+    pub command: String,
+    #[serde(default)]
+    pub args: BTreeMap<String, Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpecJunction {
+    pub inputs: Vec<String>,
+    pub command: String,
+    #[serde(default)]
+    pub args: BTreeMap<String, Value>,
+    pub output: Option<String>,
+}
+
+/// We don't expect deep include hierarchies; this is just to stop cycles.
+const MAX_INCLUDE_DEPTH: usize = 8;
+
+fn spec_error(message: String) -> ServerError {
+    ServerError::StickyProblem(ErrorDetails::new(
+        ErrorKind::BadQuery,
+        ErrorLayer::BadInput,
+        message,
+    ))
+}
+
+/// Parse a spec from a string.  JSON is a subset of YAML, but we use the JSON
+/// parser for `.json` files so that errors make more sense.
+pub fn parse_pipeline_spec(contents: &str, is_json: bool) -> Result<PipelineSpec> {
+    if is_json {
+        serde_json::from_str(contents).map_err(|e| spec_error(format!("Bad spec JSON: {}", e)))
+    } else {
+        serde_yaml::from_str(contents).map_err(|e| spec_error(format!("Bad spec YAML: {}", e)))
+    }
+}
+
+fn load_spec_at_depth(path: &Path, depth: usize) -> Result<PipelineSpec> {
+    if depth > MAX_INCLUDE_DEPTH {
+        return Err(spec_error(format!(
+            "Spec includes nested too deeply (cycle?) at {}",
+            path.display()
+        )));
+    }
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| spec_error(format!("Unable to read spec {}: {}", path.display(), e)))?;
+    let is_json = path.extension().map_or(false, |ext| ext == "json");
+    let mut spec = parse_pipeline_spec(&contents, is_json)?;
+
+    let base_dir = path.parent().map(PathBuf::from).unwrap_or_default();
+    for include in std::mem::take(&mut spec.include) {
+        let included = load_spec_at_depth(&base_dir.join(include), depth + 1)?;
+        for (k, v) in included.vars {
+            spec.vars.entry(k).or_insert(v);
+        }
+        for (k, v) in included.groups {
+            spec.groups.entry(k).or_insert(v);
+        }
+        for (k, v) in included.junctions {
+            spec.junctions.entry(k).or_insert(v);
+        }
+    }
+
+    Ok(spec)
+}
+
+/// Load a spec file from disk, resolving its includes.
+pub fn load_pipeline_spec(path: &str) -> Result<PipelineSpec> {
+    load_spec_at_depth(Path::new(path), 0)
+}
+
+/// Replace `${name}` placeholders in `s` with the values in `vars`.
+fn interpolate(s: &str, vars: &BTreeMap<String, String>) -> Result<String> {
+    let mut result = String::new();
+    let mut rest = s;
+    while let Some(start) = rest.find("${") {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after
+            .find('}')
+            .ok_or_else(|| spec_error(format!("Unterminated placeholder in: {}", s)))?;
+        let name = &after[..end];
+        let val = vars
+            .get(name)
+            .ok_or_else(|| spec_error(format!("No value for spec variable: {}", name)))?;
+        result.push_str(val);
+        rest = &after[end + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+fn value_to_arg_string(key: &str, val: &Value, vars: &BTreeMap<String, String>) -> Result<String> {
+    match val {
+        Value::String(s) => interpolate(s, vars),
+        Value::Number(n) => Ok(n.to_string()),
+        _ => Err(spec_error(format!(
+            "Arg {} must be a string, number, or boolean",
+            key
+        ))),
+    }
+}
+
+fn spec_args(
+    args: &BTreeMap<String, Value>,
+    vars: &BTreeMap<String, String>,
+) -> Result<PipelineArgs> {
+    let mut flattened = PipelineArgs::default();
+    for (key, val) in args {
+        if key.as_str() == "positional" {
+            match val {
+                Value::Array(vals) => {
+                    for v in vals {
+                        flattened
+                            .positional_args
+                            .push(value_to_arg_string(key, v, vars)?);
+                    }
+                }
+                v => flattened
+                    .positional_args
+                    .push(value_to_arg_string(key, v, vars)?),
+            }
+        } else if let Value::Bool(b) = val {
+            // boolean command-line args should be omitted if false
+            if *b {
+                flattened.bool_args.insert(key.clone());
+            }
+        } else {
+            flattened
+                .named_args
+                .insert(key.clone(), (value_to_arg_string(key, val, vars)?, 0));
+        }
+    }
+    Ok(flattened)
+}
+
+impl PipelineSpec {
+    /// Convert the spec into a `QueryPipelineGroupBuilder`, with `vars`
+    /// overriding the spec's own `vars`.
+    pub fn to_group_builder(
+        &self,
+        vars: &BTreeMap<String, String>,
+    ) -> Result<QueryPipelineGroupBuilder> {
+        let mut all_vars = self.vars.clone();
+        all_vars.extend(vars.iter().map(|(k, v)| (k.clone(), v.clone())));
+
+        let mut builder = QueryPipelineGroupBuilder::default();
+        let mut root_groups = BTreeSet::new();
+        let mut inputs_to_names: HashMap<String, Vec<String>> = HashMap::new();
+        let mut result_count = 0;
+
+        for (name, group) in &self.groups {
+            if self.junctions.contains_key(name) {
+                return Err(spec_error(format!(
+                    "{} is both a group and a junction",
+                    name
+                )));
+            }
+            let mut segments = vec![];
+            for cmd in &group.commands {
+                segments.push(PipelineSegment {
+                    command: cmd.command.clone(),
+                    args: spec_args(&cmd.args, &all_vars)?,
+                });
+            }
+            let output = group.output.clone().unwrap_or_else(|| name.clone());
+            if output == "result" {
+                result_count += 1;
+            }
+            match &group.input {
+                Some(input) => inputs_to_names
+                    .entry(input.clone())
+                    .or_default()
+                    .push(name.clone()),
+                None => {
+                    root_groups.insert(name.clone());
+                }
+            }
+            builder.groups.insert(
+                name.clone(),
+                PipelineGroup {
+                    input: group.input.clone(),
+                    segments,
+                    output: Some(output),
+                    depth: 0,
+                },
+            );
+        }
+
+        for (name, junction) in &self.junctions {
+            let output = junction.output.clone().unwrap_or_else(|| name.clone());
+            if output == "result" {
+                result_count += 1;
+            }
+            for input in &junction.inputs {
+                inputs_to_names
+                    .entry(input.clone())
+                    .or_default()
+                    .push(name.clone());
+            }
+            builder.junctions.insert(
+                name.clone(),
+                JunctionNode {
+                    inputs: junction.inputs.clone(),
+                    command: PipelineSegment {
+                        command: junction.command.clone(),
+                        args: spec_args(&junction.args, &all_vars)?,
+                    },
+                    output: Some(output),
+                    depth: 0,
+                },
+            );
+        }
+
+        if result_count != 1 {
+            return Err(spec_error(format!(
+                "Exactly one group or junction must output \"result\", found {}",
+                result_count
+            )));
+        }
+        if root_groups.is_empty() {
+            return Err(spec_error("Spec has no group without an input".to_string()));
+        }
+
+        schedule_phases(&mut builder, root_groups, &inputs_to_names)?;
+        Ok(builder)
+    }
+}
+
+#[test]
+fn test_pipeline_spec_to_group_builder() {
+    let spec = parse_pipeline_spec(
+        r#"
+vars:
+  depth: "2"
+groups:
+  ids:
+    commands:
+      - command: search-identifiers
+        args:
+          exact-match: true
+          positional: ${id}
+  graph:
+    input: ids
+    output: result
+    commands:
+      - command: crossref-lookup
+      - command: traverse
+        args:
+          max-depth: ${depth}
+"#,
+        false,
+    )
+    .unwrap();
+
+    let vars = BTreeMap::from([("id".to_string(), "Foo::Bar".to_string())]);
+    let builder = spec.to_group_builder(&vars).unwrap();
+    assert_eq!(builder.phases.len(), 1);
+    assert_eq!(builder.phases[0].groups, vec![vec!["ids", "graph"]]);
+    assert_eq!(
+        builder.groups["ids"].segments[0].args.to_vec(),
+        vec!["--exact-match", "--", "Foo::Bar"]
+    );
+    assert_eq!(
+        builder.groups["graph"].segments[1].args.to_vec(),
+        vec!["--max-depth=2"]
+    );
+
+    // Missing variables are an error.
+    assert!(spec.to_group_builder(&BTreeMap::new()).is_err());
+}
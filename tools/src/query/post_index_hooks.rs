@@ -0,0 +1,200 @@
+//! Post-index hooks are pipelines listed in a tree's `post_index_hooks` config
+//! that `scripts/post-index-hooks.sh` runs via `run-post-index-hooks` after
+//! each index build (ex: a padding audit or a broken link audit).  The output
+//! of each run is stored as JSON at `post-index-hooks/HOOK/RAN_AT.json` under
+//! the index root, with old runs pruned per the hook's `retention`.  The
+//! stored runs can be retrieved with the `hook-results` command or viewed via
+//! the pipeline-server's `/:tree/hooks/:hook` route, which renders them like
+//! live query results.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::warn;
+
+use crate::{
+    abstract_server::{
+        AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, SearchfoxIndexRoot,
+        ServerError,
+    },
+    cmd_pipeline::builder::CommandSafetyLevel,
+    file_format::config::PostIndexHookConfig,
+    query::presets::run_preset_config,
+};
+
+/// A stored run of a post-index hook.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostIndexHookRun {
+    pub hook: String,
+    /// Seconds since the epoch when the run started, which is also the name of
+    /// the file the run is stored in.
+    pub ran_at: u64,
+    /// The revision of the tree that was indexed, if known.
+    pub revision: Option<String>,
+    pub duration_ms: u64,
+    /// The serialized `PipelineValues` the hook produced, or null if it failed.
+    pub output: Value,
+    /// The SYM_INFO for rendering `output` as HTML.
+    #[serde(default)]
+    pub sym_info: Value,
+    pub error: Option<String>,
+}
+
+fn no_such_hook(name: &str) -> ServerError {
+    ServerError::StickyProblem(
+        ErrorDetails::new(
+            ErrorKind::NotFound,
+            ErrorLayer::BadInput,
+            format!("No such post-index hook: {}", name),
+        )
+        .with_context("hook", name),
+    )
+}
+
+/// Look up the hook's config, which also serves to validate the name before we
+/// use it as a path component.
+pub fn find_hook(
+    server: &(dyn AbstractServer + Send + Sync),
+    name: &str,
+) -> Result<PostIndexHookConfig> {
+    server
+        .tree_info()?
+        .post_index_hooks
+        .get(name)
+        .cloned()
+        .ok_or_else(|| no_such_hook(name))
+}
+
+/// List the `ran_at` times of the stored runs of the hook, oldest first.
+pub fn list_hook_runs(server: &(dyn AbstractServer + Send + Sync), name: &str) -> Result<Vec<u64>> {
+    find_hook(server, name)?;
+    let dir = server.translate_path(SearchfoxIndexRoot::PostIndexHooks, name)?;
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return Ok(vec![]);
+    };
+    let mut runs: Vec<u64> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .and_then(|filename| filename.strip_suffix(".json"))
+                .and_then(|stem| stem.parse().ok())
+        })
+        .collect();
+    runs.sort_unstable();
+    Ok(runs)
+}
+
+/// Load the given run of the hook, or its most recent run if `ran_at` is None.
+/// Returns None if there is no such run.
+pub fn load_hook_run(
+    server: &(dyn AbstractServer + Send + Sync),
+    name: &str,
+    ran_at: Option<u64>,
+) -> Result<Option<PostIndexHookRun>> {
+    let runs = list_hook_runs(server, name)?;
+    let ran_at = match ran_at {
+        Some(ran_at) if runs.contains(&ran_at) => ran_at,
+        Some(_) => return Ok(None),
+        None => match runs.last() {
+            Some(ran_at) => *ran_at,
+            None => return Ok(None),
+        },
+    };
+    let path = server.translate_path(
+        SearchfoxIndexRoot::PostIndexHooks,
+        &format!("{}/{}.json", name, ran_at),
+    )?;
+    let run = serde_json::from_str(&fs::read_to_string(path)?)?;
+    Ok(Some(run))
+}
+
+fn store_hook_run(
+    server: &(dyn AbstractServer + Send + Sync),
+    run: &PostIndexHookRun,
+    retention: usize,
+) -> Result<()> {
+    let dir = server.translate_path(SearchfoxIndexRoot::PostIndexHooks, &run.hook)?;
+    fs::create_dir_all(&dir)?;
+    fs::write(
+        format!("{}/{}.json", dir, run.ran_at),
+        serde_json::to_string(run)?,
+    )?;
+
+    let runs = list_hook_runs(server, &run.hook)?;
+    let excess = runs.len().saturating_sub(retention.max(1));
+    for ran_at in &runs[..excess] {
+        fs::remove_file(format!("{}/{}.json", dir, ran_at))?;
+    }
+    Ok(())
+}
+
+/// Run the given hooks (or all of the tree's hooks if `names` is empty),
+/// storing their outputs.  A hook failing does not stop the other hooks from
+/// running; the failure is recorded in its run.
+pub async fn run_post_index_hooks(
+    server: &(dyn AbstractServer + Send + Sync),
+    names: &[String],
+) -> Result<Vec<PostIndexHookRun>> {
+    let tree_info = server.tree_info()?;
+    let hooks: BTreeMap<String, PostIndexHookConfig> = if names.is_empty() {
+        tree_info.post_index_hooks
+    } else {
+        names
+            .iter()
+            .map(|name| Ok((name.clone(), find_hook(server, name)?)))
+            .collect::<Result<_>>()?
+    };
+
+    let mut runs = vec![];
+    for (name, hook) in hooks {
+        let ran_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let start = Instant::now();
+        // Hooks come from the config rather than users, so we trust them with
+        // the dangerous commands like `notify`.
+        let result = run_preset_config(
+            server,
+            &name,
+            &hook.preset,
+            &BTreeMap::new(),
+            CommandSafetyLevel::DangerousToolUseAllowed,
+        )
+        .await;
+        let duration_ms = start.elapsed().as_millis() as u64;
+
+        let run = match result {
+            Ok(values) => PostIndexHookRun {
+                hook: name.clone(),
+                ran_at,
+                revision: tree_info.revision.clone(),
+                duration_ms,
+                output: serde_json::to_value(&values)?,
+                sym_info: values.sym_info(),
+                error: None,
+            },
+            Err(err) => {
+                warn!("Post-index hook {} failed: {:?}", name, err);
+                PostIndexHookRun {
+                    hook: name.clone(),
+                    ran_at,
+                    revision: tree_info.revision.clone(),
+                    duration_ms,
+                    output: Value::Null,
+                    sym_info: Value::Null,
+                    error: Some(format!("{:?}", err)),
+                }
+            }
+        };
+        store_hook_run(server, &run, hook.retention)?;
+        runs.push(run);
+    }
+    Ok(runs)
+}
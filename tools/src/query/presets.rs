@@ -0,0 +1,289 @@
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+
+use crate::{
+    abstract_server::{AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, ServerError},
+    cmd_pipeline::{
+        builder::{build_pipeline_graph, fab_command_from_opts, CommandSafetyLevel},
+        parser::ToolOpts,
+        PipelineValues,
+    },
+    file_format::config::QueryPresetConfig,
+    query::{chew_query::chew_query, pipeline_spec::load_pipeline_spec},
+};
+use clap::Parser;
+
+#[derive(Deserialize)]
+struct PresetsFile {
+    #[serde(default)]
+    preset: BTreeMap<String, QueryPresetConfig>,
+}
+
+lazy_static! {
+    static ref BUILTIN_PRESETS: BTreeMap<String, QueryPresetConfig> =
+        toml::from_str::<PresetsFile>(include_str!("query_presets.toml"))
+            .unwrap()
+            .preset;
+}
+
+fn bad_input(message: String) -> ServerError {
+    ServerError::StickyProblem(ErrorDetails::new(
+        ErrorKind::BadQuery,
+        ErrorLayer::BadInput,
+        message,
+    ))
+}
+
+/// Return all of the presets available for the tree, with the tree's presets
+/// taking precedence over the built-in presets.
+pub fn all_presets(
+    tree_presets: &BTreeMap<String, QueryPresetConfig>,
+) -> BTreeMap<String, QueryPresetConfig> {
+    let mut presets = BUILTIN_PRESETS.clone();
+    presets.extend(
+        tree_presets
+            .iter()
+            .map(|(name, preset)| (name.clone(), preset.clone())),
+    );
+    presets
+}
+
+/// A preset with its parameters substituted, ready to run.
+#[derive(Debug)]
+pub enum ExpandedPreset {
+    Query(String),
+    Pipeline(String),
+    Spec {
+        path: String,
+        vars: BTreeMap<String, String>,
+    },
+}
+
+/// Substitute the `{name}` placeholders in the preset's spec with the provided
+/// parameters (falling back to the preset's defaults), erroring if a required
+/// parameter is missing or an unknown parameter was provided.
+pub fn expand_preset(
+    name: &str,
+    preset: &QueryPresetConfig,
+    params: &BTreeMap<String, String>,
+) -> Result<ExpandedPreset> {
+    for key in params.keys() {
+        if !preset.params.contains(key) {
+            return Err(bad_input(format!(
+                "Unknown parameter {} for preset {}; expected: {}",
+                key,
+                name,
+                preset.params.join(", ")
+            )));
+        }
+    }
+
+    let (spec, is_pipeline) = match (&preset.query, &preset.pipeline, &preset.spec) {
+        (Some(query), None, None) => (query, false),
+        (None, Some(pipeline), None) => (pipeline, true),
+        (None, None, Some(spec_path)) => {
+            // Spec files do their own interpolation of `${name}` variables.
+            let mut vars = preset.defaults.clone();
+            vars.extend(params.iter().map(|(k, v)| (k.clone(), v.clone())));
+            return Ok(ExpandedPreset::Spec {
+                path: spec_path.clone(),
+                vars,
+            });
+        }
+        _ => {
+            return Err(ServerError::StickyProblem(ErrorDetails::new(
+                ErrorKind::Internal,
+                ErrorLayer::ConfigLayer,
+                format!(
+                    "Preset {} must specify exactly one of `query`, `pipeline`, or `spec`",
+                    name
+                ),
+            )));
+        }
+    };
+
+    let mut expanded = spec.clone();
+    for param in &preset.params {
+        let value = params
+            .get(param)
+            .or_else(|| preset.defaults.get(param))
+            .ok_or_else(|| {
+                bad_input(format!(
+                    "Missing parameter {} for preset {}; use --param {}=...",
+                    param, name, param
+                ))
+            })?;
+        let value = if is_pipeline {
+            shell_words::quote(value).to_string()
+        } else {
+            // Query terms are whitespace delimited and there's no quoting.
+            if value.chars().any(char::is_whitespace) {
+                return Err(bad_input(format!(
+                    "Parameter {} for preset {} can't contain whitespace",
+                    param, name
+                )));
+            }
+            value.clone()
+        };
+        expanded = expanded.replace(&format!("{{{}}}", param), &value);
+    }
+
+    Ok(if is_pipeline {
+        ExpandedPreset::Pipeline(expanded)
+    } else {
+        ExpandedPreset::Query(expanded)
+    })
+}
+
+/// Look up the preset by name and run it against the given server.  Presets
+/// are built with `CommandSafetyLevel::WebSafety` because presets are exposed
+/// by the pipeline-server.
+pub async fn run_preset(
+    server: &(dyn AbstractServer + Send + Sync),
+    name: &str,
+    params: &BTreeMap<String, String>,
+) -> Result<PipelineValues> {
+    let presets = all_presets(&server.tree_info()?.query_presets);
+    let preset = presets
+        .get(name)
+        .ok_or_else(|| bad_input(format!("No such preset: {}", name)))?;
+
+    run_preset_config(server, name, preset, params, CommandSafetyLevel::WebSafety).await
+}
+
+/// Run the given preset, building its commands with the given safety level
+/// regardless of whether it's a query, pipeline, or spec preset.  The name is
+/// only used for error messages.
+pub async fn run_preset_config(
+    server: &(dyn AbstractServer + Send + Sync),
+    name: &str,
+    preset: &QueryPresetConfig,
+    params: &BTreeMap<String, String>,
+    safety: CommandSafetyLevel,
+) -> Result<PipelineValues> {
+    match expand_preset(name, preset, params)? {
+        ExpandedPreset::Query(query) => {
+            let graph = build_pipeline_graph(server.clonify(), chew_query(&query)?, safety)?;
+            graph.run(true).await
+        }
+        ExpandedPreset::Spec { path, vars } => {
+            let builder = load_pipeline_spec(&path)?.to_group_builder(&vars)?;
+            let graph = build_pipeline_graph(server.clonify(), builder, safety)?;
+            graph.run(true).await
+        }
+        ExpandedPreset::Pipeline(pipeline) => {
+            run_pipeline_string(server, &format!("preset {}", name), &pipeline, safety).await
+        }
+    }
+}
+
+/// Run a `|`-delimited pipeline of searchfox-tool commands, building them with
+/// the given safety level.  The description is only used for error messages.
+pub async fn run_pipeline_string(
+    server: &(dyn AbstractServer + Send + Sync),
+    description: &str,
+    pipeline: &str,
+    safety: CommandSafetyLevel,
+) -> Result<PipelineValues> {
+    let all_args = shell_words::split(pipeline).map_err(|e| {
+        ServerError::StickyProblem(ErrorDetails::new(
+            ErrorKind::Internal,
+            ErrorLayer::ConfigLayer,
+            format!("Bad pipeline for {}: {}", description, e),
+        ))
+    })?;
+    let mut value = PipelineValues::Void;
+    for arg_slices in all_args.split(|v| v == "|") {
+        let mut fake_args = vec!["searchfox-tool".to_string()];
+        fake_args.extend(arg_slices.iter().cloned());
+        let opts = ToolOpts::try_parse_from(fake_args).map_err(|e| {
+            ServerError::StickyProblem(ErrorDetails::new(
+                ErrorKind::Internal,
+                ErrorLayer::ConfigLayer,
+                format!("Bad pipeline for {}: {}", description, e),
+            ))
+        })?;
+        let cmd = fab_command_from_opts(opts, safety)?;
+        value = cmd.execute(server, value).await?;
+    }
+    Ok(value)
+}
+
+#[test]
+fn test_expand_preset() {
+    let preset = QueryPresetConfig {
+        pipeline: Some("crossref-lookup {sym} | traverse --max-depth={depth}".to_string()),
+        params: vec!["sym".to_string(), "depth".to_string()],
+        defaults: BTreeMap::from([("depth".to_string(), "4".to_string())]),
+        ..QueryPresetConfig::default()
+    };
+    let params = BTreeMap::from([("sym".to_string(), "_ZN3Foo3BarEv".to_string())]);
+    match expand_preset("p", &preset, &params).unwrap() {
+        ExpandedPreset::Pipeline(p) => {
+            assert_eq!(p, "crossref-lookup _ZN3Foo3BarEv | traverse --max-depth=4")
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+
+    assert!(expand_preset("p", &preset, &BTreeMap::new()).is_err());
+    let unknown = BTreeMap::from([("nope".to_string(), "x".to_string())]);
+    assert!(expand_preset("p", &preset, &unknown).is_err());
+
+    // Every built-in preset should be well-formed.
+    for (name, preset) in BUILTIN_PRESETS.iter() {
+        assert!(
+            preset.query.is_some() != preset.pipeline.is_some(),
+            "{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn test_preset_safety() {
+    use crate::abstract_server::{make_remote_server, RetryPolicy};
+    use url::Url;
+
+    let dir = std::env::temp_dir().join(format!("preset-safety-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let spec_path = dir.join("verify.yaml");
+    std::fs::write(
+        &spec_path,
+        r#"
+groups:
+  verify:
+    output: result
+    commands:
+      - command: verify-index
+"#,
+    )
+    .unwrap();
+
+    let preset = QueryPresetConfig {
+        spec: Some(spec_path.to_str().unwrap().to_string()),
+        ..QueryPresetConfig::default()
+    };
+    // Nothing is fetched from the server since only the graph is built.
+    let server = make_remote_server(
+        Url::parse("http://localhost/").unwrap(),
+        "tests",
+        RetryPolicy::default(),
+    )
+    .unwrap();
+    let build = |safety| match expand_preset("verify", &preset, &BTreeMap::new()).unwrap() {
+        ExpandedPreset::Spec { path, vars } => build_pipeline_graph(
+            server.clonify(),
+            load_pipeline_spec(&path)
+                .unwrap()
+                .to_group_builder(&vars)
+                .unwrap(),
+            safety,
+        ),
+        other => panic!("unexpected {:?}", other),
+    };
+    assert!(build(CommandSafetyLevel::WebSafety).is_err());
+    assert!(build(CommandSafetyLevel::DangerousToolUseAllowed).is_ok());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
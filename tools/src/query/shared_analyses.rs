@@ -0,0 +1,196 @@
+//! Shared analyses are pipelines that someone stored with the `share` command
+//! so that they can be linked to, ex: a caller matrix for a bug comment.  Each
+//! is stored as JSON at `shared-analyses/TOKEN.json` under the index root,
+//! where TOKEN is a hash of the tree, the indexed revision, and the pipeline,
+//! so sharing the same analysis twice produces the same link.  The
+//! pipeline-server re-runs the pipeline when the link is visited, which is
+//! only allowed while the pinned revision is the one that is indexed so that
+//! the link never silently shows different results.
+
+use std::fs;
+use std::io;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use clap::Parser;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    abstract_server::{
+        AbstractServer, ErrorDetails, ErrorKind, ErrorLayer, Result, SearchfoxIndexRoot,
+        ServerError,
+    },
+    cmd_pipeline::{
+        builder::{fab_command_from_opts, CommandSafetyLevel},
+        parser::ToolOpts,
+        PipelineValues,
+    },
+    query::presets::run_pipeline_string,
+};
+
+/// A stored pipeline invocation.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SharedAnalysis {
+    pub tree: String,
+    /// The revision of the tree that was indexed when the analysis was shared,
+    /// if known.
+    pub revision: Option<String>,
+    /// The `|`-delimited searchfox-tool commands, as given to `share`.
+    pub pipeline: String,
+    /// Seconds since the epoch.
+    pub created_at: u64,
+}
+
+/// FNV-1a over the tree, revision, and pipeline, which is stable across builds
+/// (unlike `DefaultHasher`) so that links keep working after a deploy.
+fn analysis_token(tree: &str, revision: Option<&str>, pipeline: &str) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for part in [tree, revision.unwrap_or(""), pipeline] {
+        for byte in part.bytes().chain(std::iter::once(0)) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    format!("{:016x}", hash)
+}
+
+/// Tokens come from URLs, so make sure they're something we generated before
+/// using them as a path component.
+fn is_valid_token(token: &str) -> bool {
+    token.len() == 16
+        && token
+            .bytes()
+            .all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+fn bad_pipeline(message: String) -> ServerError {
+    ServerError::StickyProblem(
+        ErrorDetails::new(ErrorKind::BadQuery, ErrorLayer::BadInput, message)
+            .with_hint("Only commands the pipeline-server allows can be shared."),
+    )
+}
+
+/// Make sure every command of the pipeline parses and is allowed to run on the
+/// web without running any of them.
+fn validate_pipeline(pipeline: &str) -> Result<()> {
+    let all_args = shell_words::split(pipeline).map_err(|e| bad_pipeline(e.to_string()))?;
+    if all_args.is_empty() {
+        return Err(bad_pipeline("The pipeline to share is empty".to_string()));
+    }
+    for arg_slices in all_args.split(|v| v == "|") {
+        let mut fake_args = vec!["searchfox-tool".to_string()];
+        fake_args.extend(arg_slices.iter().cloned());
+        let opts = ToolOpts::try_parse_from(fake_args).map_err(|e| bad_pipeline(e.to_string()))?;
+        fab_command_from_opts(opts, CommandSafetyLevel::WebSafety)?;
+    }
+    Ok(())
+}
+
+fn analysis_path(server: &(dyn AbstractServer + Send + Sync), token: &str) -> Result<String> {
+    server.translate_path(
+        SearchfoxIndexRoot::IndexRoot,
+        &format!("shared-analyses/{}.json", token),
+    )
+}
+
+/// Store the pipeline against the currently indexed revision of the server's
+/// tree, returning its token and the stored analysis.
+pub fn share_analysis(
+    server: &(dyn AbstractServer + Send + Sync),
+    pipeline: &str,
+) -> Result<(String, SharedAnalysis)> {
+    validate_pipeline(pipeline)?;
+
+    let tree_info = server.tree_info()?;
+    let token = analysis_token(&tree_info.name, tree_info.revision.as_deref(), pipeline);
+    let analysis = SharedAnalysis {
+        tree: tree_info.name,
+        revision: tree_info.revision,
+        pipeline: pipeline.to_string(),
+        created_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs()),
+    };
+
+    let dir = server.translate_path(SearchfoxIndexRoot::IndexRoot, "shared-analyses")?;
+    fs::create_dir_all(dir)?;
+    // Re-sharing an analysis keeps its original creation time.
+    let path = analysis_path(server, &token)?;
+    if fs::metadata(&path).is_err() {
+        fs::write(path, serde_json::to_string(&analysis)?)?;
+    }
+    Ok((token, analysis))
+}
+
+/// Load the shared analysis with the given token, returning None if there
+/// isn't one for the server's tree.
+pub fn load_shared_analysis(
+    server: &(dyn AbstractServer + Send + Sync),
+    token: &str,
+) -> Result<Option<SharedAnalysis>> {
+    if !is_valid_token(token) {
+        return Ok(None);
+    }
+    let contents = match fs::read_to_string(analysis_path(server, token)?) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let analysis: SharedAnalysis = serde_json::from_str(&contents)?;
+    if analysis.tree != server.tree_info()?.name {
+        return Ok(None);
+    }
+    Ok(Some(analysis))
+}
+
+/// Re-run the shared analysis with `CommandSafetyLevel::WebSafety`, erroring if
+/// the tree has since been indexed at a different revision.
+pub async fn run_shared_analysis(
+    server: &(dyn AbstractServer + Send + Sync),
+    analysis: &SharedAnalysis,
+) -> Result<PipelineValues> {
+    let indexed = server.tree_info()?.revision;
+    if analysis.revision != indexed {
+        return Err(ServerError::StickyProblem(
+            ErrorDetails::new(
+                ErrorKind::NotFound,
+                ErrorLayer::DataLayer,
+                format!(
+                    "This analysis was shared at revision {} but revision {} is indexed now",
+                    analysis.revision.as_deref().unwrap_or("unknown"),
+                    indexed.as_deref().unwrap_or("unknown")
+                ),
+            )
+            .with_hint("Share the pipeline again to run it against the current revision."),
+        ));
+    }
+    run_pipeline_string(
+        server,
+        "shared analysis",
+        &analysis.pipeline,
+        CommandSafetyLevel::WebSafety,
+    )
+    .await
+}
+
+#[test]
+fn test_analysis_token() {
+    let token = analysis_token("tests", Some("abc123"), "crossref-lookup Foo");
+    assert_eq!(
+        token,
+        analysis_token("tests", Some("abc123"), "crossref-lookup Foo")
+    );
+    assert!(is_valid_token(&token));
+    assert_ne!(
+        token,
+        analysis_token("tests", Some("def456"), "crossref-lookup Foo")
+    );
+    assert_ne!(token, analysis_token("tests", None, "crossref-lookup Foo"));
+    assert_ne!(
+        token,
+        analysis_token("other", Some("abc123"), "crossref-lookup Foo")
+    );
+
+    assert!(!is_valid_token("../../config.js"));
+    assert!(!is_valid_token("0123456789ABCDEF"));
+}
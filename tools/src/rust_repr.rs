@@ -0,0 +1,114 @@
+//! Handling of Rust `#[repr(...)]` attributes for field layout tables.
+//!
+//! The Rust analysis (`scip-indexer`) records the arguments of a type's repr
+//! attributes as the `repr` of its structured record, using "Rust" for types
+//! without one.  Only some reprs guarantee a layout; with the default repr the
+//! compiler is free to reorder fields, so holes between fields in declaration
+//! order mean nothing and can't be fixed by reordering them.
+
+use itertools::Itertools;
+use regex::Regex;
+
+/// The primitive integer types that can be used as the repr of an enum.
+const PRIMITIVE_REPRS: &[&str] = &[
+    "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64", "i128", "isize",
+];
+
+/// Extract the normalized arguments of a `#[repr(...)]` attribute, ex: "C,
+/// packed(2)" for `#[repr(C, packed(2))]`, or None for any other attribute.
+pub fn repr_attribute_args(attr: &str) -> Option<String> {
+    lazy_static! {
+        static ref REPR_RE: Regex = Regex::new(r"(?s)^#\s*\[\s*repr\s*\((.*)\)\s*\]$").unwrap();
+    }
+    let caps = REPR_RE.captures(attr.trim())?;
+    Some(
+        caps[1]
+            .split(',')
+            .map(|arg| arg.split_whitespace().collect::<String>())
+            .filter(|arg| !arg.is_empty())
+            .join(", "),
+    )
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RustRepr {
+    /// `repr(C)`: fields are laid out in declaration order like in C.
+    pub c: bool,
+    /// `repr(transparent)`: laid out like the single non-zero-sized field.
+    pub transparent: bool,
+    /// The integer type of `repr(u8)` and friends, which also fixes the layout
+    /// of enums with fields.
+    pub primitive: Option<String>,
+    /// `repr(packed)` or `repr(packed(N))`, as the maximum field alignment.
+    pub packed: Option<u32>,
+    /// `repr(align(N))`.
+    pub align: Option<u32>,
+}
+
+fn parenthesized_number(arg: &str, name: &str) -> Option<u32> {
+    arg.strip_prefix(name)?
+        .strip_prefix('(')?
+        .strip_suffix(')')?
+        .parse()
+        .ok()
+}
+
+impl RustRepr {
+    /// Parse the `repr` of a structured record.  Unknown arguments are
+    /// ignored.
+    pub fn parse(repr: &str) -> RustRepr {
+        let mut parsed = RustRepr::default();
+        for arg in repr.split(',').map(str::trim) {
+            match arg {
+                "C" => parsed.c = true,
+                "transparent" => parsed.transparent = true,
+                "packed" => parsed.packed = Some(1),
+                _ if PRIMITIVE_REPRS.contains(&arg) => parsed.primitive = Some(arg.to_string()),
+                _ => {
+                    if let Some(n) = parenthesized_number(arg, "packed") {
+                        parsed.packed = Some(n);
+                    } else if let Some(n) = parenthesized_number(arg, "align") {
+                        parsed.align = Some(n);
+                    }
+                }
+            }
+        }
+        parsed
+    }
+
+    /// Whether the fields are laid out in declaration order, which is what
+    /// makes holes between them meaningful.  `packed` and `align` alone don't
+    /// stop the compiler from reordering fields.
+    pub fn has_defined_layout(&self) -> bool {
+        self.c || self.transparent || self.primitive.is_some()
+    }
+}
+
+#[test]
+fn test_repr_attribute_args() {
+    assert_eq!(repr_attribute_args("#[repr(C)]"), Some("C".to_string()));
+    assert_eq!(
+        repr_attribute_args("#[repr(C, packed (2))]"),
+        Some("C, packed(2)".to_string())
+    );
+    assert_eq!(
+        repr_attribute_args("#[ repr(\n    u8,\n    align(8),\n) ]"),
+        Some("u8, align(8)".to_string())
+    );
+    assert_eq!(repr_attribute_args("#[derive(Debug)]"), None);
+    assert_eq!(repr_attribute_args("#[cfg_attr(test, repr(C))]"), None);
+}
+
+#[test]
+fn test_rust_repr() {
+    let repr = RustRepr::parse("C, packed(2)");
+    assert!(repr.c);
+    assert_eq!(repr.packed, Some(2));
+    assert!(repr.has_defined_layout());
+
+    assert!(RustRepr::parse("u8").has_defined_layout());
+    assert!(RustRepr::parse("transparent").has_defined_layout());
+    assert!(!RustRepr::parse("Rust").has_defined_layout());
+    assert!(!RustRepr::parse("packed").has_defined_layout());
+    assert_eq!(RustRepr::parse("align(16)").align, Some(16));
+}
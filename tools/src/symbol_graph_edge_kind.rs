@@ -1,4 +1,6 @@
-#[derive(Clone)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Deserialize, Serialize)]
 pub enum EdgeKind {
     Default, // solid line, closed arrow ("normal")
     // These value are meant to be UML-ish
@@ -4,7 +4,8 @@ use include_dir::{include_dir, Dir};
 use liquid::Template;
 
 use super::liquid_exts::{
-    CompactPathlikeFilterParser, EnsureBugUrlFilterParser, FileExtFilterParser, JsonFilterParser,
+    AssetUrlFilterParser, CompactPathlikeFilterParser, EnsureBugUrlFilterParser,
+    FileExtFilterParser, GroupDigitsFilterParser, JsonFilterParser, PluralizeFilterParser,
     StripPrefixOrEmptyFilterParser,
 };
 
@@ -32,10 +33,13 @@ impl liquid::partials::PartialSource for StaticTemplateSource {
 
 pub fn build_and_parse(s: &str) -> Template {
     liquid::ParserBuilder::with_stdlib()
+        .filter(AssetUrlFilterParser)
         .filter(CompactPathlikeFilterParser)
         .filter(EnsureBugUrlFilterParser)
         .filter(FileExtFilterParser)
+        .filter(GroupDigitsFilterParser)
         .filter(JsonFilterParser)
+        .filter(PluralizeFilterParser)
         .filter(StripPrefixOrEmptyFilterParser)
         .partials(liquid::partials::LazyCompiler::<StaticTemplateSource>::empty())
         .build()
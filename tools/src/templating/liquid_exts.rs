@@ -1,3 +1,4 @@
+use liquid_core::model::ScalarCow;
 use liquid_core::Expression;
 use liquid_core::FilterParameters;
 use liquid_core::FromFilterParameters;
@@ -8,6 +9,9 @@ use liquid_core::{Value, ValueView};
 use regex::Regex;
 use serde_json::to_string_pretty;
 
+use crate::file_format::asset_manifest::asset_url;
+use crate::number_format::{format_count, group_digits};
+
 #[derive(Clone, ParseFilter, FilterReflection)]
 #[filter(
     name = "json",
@@ -138,3 +142,120 @@ impl Filter for StripPrefixOrEmptyFilter {
         }
     }
 }
+
+#[derive(Debug, FilterParameters)]
+struct GroupDigitsArgs {
+    #[parameter(
+        description = "The locale to group digits for, defaulting to \"en\".",
+        arg_type = "str"
+    )]
+    locale: Option<Expression>,
+}
+
+#[derive(Clone, ParseFilter, FilterReflection)]
+#[filter(
+    name = "group_digits",
+    description = "Format an integer with its digits grouped into thousands, like 1,234",
+    parameters(GroupDigitsArgs),
+    parsed(GroupDigitsFilter)
+)]
+pub struct GroupDigitsFilterParser;
+
+#[derive(Debug, FromFilterParameters, Display_filter)]
+#[name = "group_digits"]
+struct GroupDigitsFilter {
+    #[parameters]
+    args: GroupDigitsArgs,
+}
+
+impl Filter for GroupDigitsFilter {
+    fn evaluate(&self, input: &dyn ValueView, runtime: &dyn Runtime) -> Result<Value> {
+        let args = self.args.evaluate(runtime)?;
+
+        let locale = args.locale.as_deref().unwrap_or("en");
+        match input.as_scalar().and_then(|s| s.to_integer()) {
+            Some(n) if n >= 0 => Ok(Value::scalar(group_digits(n as u64, locale))),
+            _ => Ok(input.to_value()),
+        }
+    }
+}
+
+#[derive(Debug, FilterParameters)]
+struct PluralizeArgs {
+    #[parameter(description = "The noun to use for a count of 1.", arg_type = "str")]
+    singular: Expression,
+    #[parameter(description = "The noun to use for other counts.", arg_type = "str")]
+    plural: Expression,
+}
+
+#[derive(Clone, ParseFilter, FilterReflection)]
+#[filter(
+    name = "pluralize",
+    description = "Format a count with the appropriate noun, like \"1 byte\" or \"1,024 bytes\"",
+    parameters(PluralizeArgs),
+    parsed(PluralizeFilter)
+)]
+pub struct PluralizeFilterParser;
+
+#[derive(Debug, FromFilterParameters, Display_filter)]
+#[name = "pluralize"]
+struct PluralizeFilter {
+    #[parameters]
+    args: PluralizeArgs,
+}
+
+impl Filter for PluralizeFilter {
+    fn evaluate(&self, input: &dyn ValueView, runtime: &dyn Runtime) -> Result<Value> {
+        let args = self.args.evaluate(runtime)?;
+
+        let count = input
+            .as_scalar()
+            .and_then(|s| s.to_integer())
+            .unwrap_or(0)
+            .max(0) as u64;
+        Ok(Value::scalar(format_count(
+            count,
+            args.singular.as_str(),
+            args.plural.as_str(),
+        )))
+    }
+}
+
+#[derive(Debug, FilterParameters)]
+struct AssetUrlArgs {
+    #[parameter(description = "The tree whose static URLs to use.", arg_type = "str")]
+    tree: Expression,
+}
+
+#[derive(Clone, ParseFilter, FilterReflection)]
+#[filter(
+    name = "asset_url",
+    description = "Turn a path relative to the static directory into its URL, fingerprinted with the asset's content hash when the `ASSET_MANIFEST` global knows it",
+    parameters(AssetUrlArgs),
+    parsed(AssetUrlFilter)
+)]
+pub struct AssetUrlFilterParser;
+
+#[derive(Debug, FromFilterParameters, Display_filter)]
+#[name = "asset_url"]
+struct AssetUrlFilter {
+    #[parameters]
+    args: AssetUrlArgs,
+}
+
+impl Filter for AssetUrlFilter {
+    fn evaluate(&self, input: &dyn ValueView, runtime: &dyn Runtime) -> Result<Value> {
+        let args = self.args.evaluate(runtime)?;
+
+        let asset_path = input.to_kstr();
+        let asset_path = asset_path.as_str().trim_start_matches('/');
+        let hash = runtime
+            .try_get(&[ScalarCow::new("ASSET_MANIFEST"), ScalarCow::new(asset_path)])
+            .map(|hash| hash.to_kstr().to_string());
+        Ok(Value::scalar(asset_url(
+            args.tree.as_str(),
+            asset_path,
+            hash.as_deref(),
+        )))
+    }
+}
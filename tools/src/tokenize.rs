@@ -1,6 +1,6 @@
 use std::cell::Cell;
 
-use crate::languages::LanguageSpec;
+use crate::languages::{FormatAs, LanguageSpec};
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum TokenKind {
@@ -27,6 +27,19 @@ fn is_whitespace(ch: char) -> bool {
     ch == ' ' || ch == '\t' || ch == '\n' || ch == '\r'
 }
 
+/// Tokenize the input with the tokenizer for its format, as the formatter does.
+/// Binary files have no tokens.
+pub fn tokenize_as(format: &FormatAs, string: &str) -> Vec<Token> {
+    match format {
+        FormatAs::Binary => vec![],
+        FormatAs::CSS => tokenize_css(string),
+        FormatAs::Plain => tokenize_plain(string),
+        FormatAs::StaticPrefs => tokenize_static_prefs(string),
+        FormatAs::FormatCLike(spec) => tokenize_c_like(string, spec),
+        FormatAs::FormatTagLike(script_spec) => tokenize_tag_like(string, script_spec),
+    }
+}
+
 pub fn tokenize_css(string: &str) -> Vec<Token> {
     fn tokenize_css_block(input: &mut cssparser::Parser, raw_input: &str, tokens: &mut Vec<Token>) {
         use cssparser::Token::*;
@@ -0,0 +1,178 @@
+//! Unicode normalization for searches, so that text containing non-ASCII
+//! characters (ex: l10n strings or emoji in comments) is found regardless of
+//! how it was typed.
+//!
+//! Identifiers are written to the `identifiers` file in NFKC form by crossref,
+//! and identifier queries are normalized the same way, so compatibility
+//! variants like the "ﬁ" ligature or fullwidth letters find the same
+//! identifiers.  Text searches can't normalize the indexed source, so the text
+//! is instead turned into a regexp that matches any of its normalization forms.
+//!
+//! Confusable folding additionally treats characters that merely look alike as
+//! the same, ex: Cyrillic "а" and Latin "a".  It's opt-in since it can produce
+//! surprising matches, and searches can disable all of this for exact audits.
+
+use std::borrow::Cow;
+
+use unicode_normalization::{is_nfkc_quick, IsNormalized, UnicodeNormalization};
+
+/// Characters that are commonly confused with an ASCII character but that NFKC
+/// doesn't fold into it, keyed by that ASCII character.
+const CONFUSABLES: &[(char, &str)] = &[
+    // Cyrillic, Latin alpha, and Greek alpha.
+    ('a', "\u{0430}\u{0251}\u{03b1}"),
+    ('c', "\u{0441}\u{03f2}"),
+    ('e', "\u{0435}"),
+    ('i', "\u{0456}\u{03b9}"),
+    ('j', "\u{0458}"),
+    ('o', "\u{043e}\u{03bf}"),
+    ('p', "\u{0440}\u{03c1}"),
+    ('s', "\u{0455}"),
+    ('x', "\u{0445}\u{03c7}"),
+    ('y', "\u{0443}"),
+    // Cyrillic and Greek capitals.
+    ('A', "\u{0410}\u{0391}"),
+    ('B', "\u{0412}\u{0392}"),
+    ('C', "\u{0421}"),
+    ('E', "\u{0415}\u{0395}"),
+    ('H', "\u{041d}\u{0397}"),
+    ('I', "\u{0406}\u{0399}"),
+    ('K', "\u{041a}\u{039a}"),
+    ('M', "\u{041c}\u{039c}"),
+    ('O', "\u{041e}\u{039f}"),
+    ('P', "\u{0420}\u{03a1}"),
+    ('T', "\u{0422}\u{03a4}"),
+    ('X', "\u{0425}\u{03a7}"),
+    // Hyphens, dashes, and the minus sign.
+    ('-', "\u{2010}\u{2011}\u{2012}\u{2013}\u{2014}\u{2212}"),
+    // Curly quotes and primes.
+    ('\'', "\u{2018}\u{2019}\u{201b}\u{2032}"),
+    ('"', "\u{201c}\u{201d}\u{2033}"),
+];
+
+/// Invisible characters that confusable folding removes.
+const ZERO_WIDTH: &[char] = &['\u{200b}', '\u{200c}', '\u{200d}', '\u{2060}', '\u{feff}'];
+
+/// The NFKC form of the string, borrowing it if it's already normalized (which
+/// is always the case for ASCII).
+pub fn nfkc(s: &str) -> Cow<str> {
+    if is_nfkc_quick(s.chars()) == IsNormalized::Yes {
+        Cow::Borrowed(s)
+    } else {
+        Cow::Owned(s.nfkc().collect())
+    }
+}
+
+/// The ASCII character the given character is confusable with, if any.
+fn confusable_prototype(ch: char) -> Option<char> {
+    CONFUSABLES
+        .iter()
+        .find(|(_, confusables)| confusables.contains(ch))
+        .map(|(prototype, _)| *prototype)
+}
+
+/// Replace confusable characters with the ASCII characters they look like and
+/// remove zero-width characters.
+pub fn fold_confusables(s: &str) -> String {
+    s.chars()
+        .filter(|ch| !ZERO_WIDTH.contains(ch))
+        .map(|ch| confusable_prototype(ch).unwrap_or(ch))
+        .collect()
+}
+
+/// Normalize an identifier query like the indexed identifiers, optionally also
+/// folding confusables.
+pub fn normalize_identifier_query(s: &str, confusables: bool) -> String {
+    let normalized = nfkc(s);
+    if confusables {
+        fold_confusables(&normalized)
+    } else {
+        normalized.into_owned()
+    }
+}
+
+/// Escape the string as a regexp in which every character that has
+/// confusables matches any of them.
+fn confusable_regexp(s: &str) -> String {
+    let mut regexp = String::new();
+    for ch in s.chars() {
+        if ZERO_WIDTH.contains(&ch) {
+            continue;
+        }
+        let prototype = confusable_prototype(ch).unwrap_or(ch);
+        match CONFUSABLES.iter().find(|(p, _)| *p == prototype) {
+            Some((prototype, confusables)) => {
+                regexp.push('[');
+                for alike in std::iter::once(*prototype).chain(confusables.chars()) {
+                    regexp.push_str(&regex::escape(&alike.to_string()));
+                }
+                regexp.push(']');
+            }
+            None => regexp.push_str(&regex::escape(&ch.to_string())),
+        }
+    }
+    regexp
+}
+
+/// Turn literal search text into a regexp that matches the text in any of its
+/// NFC, NFD, or NFKC forms, optionally also matching confusables.  ASCII text
+/// without confusable folding is just escaped.
+pub fn text_search_regexp(text: &str, confusables: bool) -> String {
+    let mut forms = vec![text.to_string()];
+    for form in [
+        text.nfc().collect::<String>(),
+        text.nfd().collect(),
+        text.nfkc().collect(),
+    ] {
+        if !forms.contains(&form) {
+            forms.push(form);
+        }
+    }
+
+    let mut regexps: Vec<String> = vec![];
+    for form in forms {
+        let regexp = if confusables {
+            confusable_regexp(&form)
+        } else {
+            regex::escape(&form)
+        };
+        if !regexps.contains(&regexp) {
+            regexps.push(regexp);
+        }
+    }
+    if regexps.len() == 1 {
+        regexps.pop().unwrap()
+    } else {
+        format!("(?:{})", regexps.join("|"))
+    }
+}
+
+#[test]
+fn test_nfkc() {
+    assert!(matches!(nfkc("nsIFrame"), Cow::Borrowed(_)));
+    assert_eq!(nfkc("\u{fb01}le"), "file");
+    assert_eq!(nfkc("\u{ff26}oo"), "Foo");
+    assert_eq!(
+        normalize_identifier_query("\u{041e}uter", false),
+        "\u{041e}uter"
+    );
+    assert_eq!(
+        normalize_identifier_query("\u{041e}uter\u{200b}", true),
+        "Outer"
+    );
+}
+
+#[test]
+fn test_text_search_regexp() {
+    assert_eq!(text_search_regexp("a.b", false), r"a\.b");
+
+    // "café" with a precomposed é also matches the decomposed form.
+    let re = regex::Regex::new(&text_search_regexp("caf\u{e9}", false)).unwrap();
+    assert!(re.is_match("caf\u{e9}"));
+    assert!(re.is_match("cafe\u{301}"));
+    assert!(!re.is_match("cafe"));
+
+    let re = regex::Regex::new(&text_search_regexp("a-b", true)).unwrap();
+    assert!(re.is_match("\u{0430}\u{2013}b"));
+    assert!(!re.is_match("a_b"));
+}
@@ -42,6 +42,9 @@ async fn test_check_glob() -> Result<(), std::io::Error> {
 
         let mut settings = insta::Settings::clone_current();
         settings.set_prepend_module_to_snapshot(false);
+        // Static asset URLs are fingerprinted with content hashes that change
+        // whenever anyone touches the CSS or JS.
+        settings.add_filter(r"\?v=[0-9a-f]+", "?v=[hash]");
 
         // ## Figure out the list of input files
         let input_names = block_in_place_glob_tree(&input_path, "**/*");
@@ -141,6 +144,15 @@ async fn test_check_glob() -> Result<(), std::io::Error> {
                         Ok(PipelineValues::SymbolTreeTableList(sttl)) => {
                             insta::assert_json_snapshot!(&to_value(sttl).unwrap());
                         }
+                        Ok(PipelineValues::SymbolHitGroups(shg)) => {
+                            insta::assert_json_snapshot!(&to_value(shg).unwrap());
+                        }
+                        Ok(PipelineValues::SymbolExamplesList(sel)) => {
+                            insta::assert_json_snapshot!(&to_value(sel).unwrap());
+                        }
+                        Ok(PipelineValues::DataTable(dt)) => {
+                            insta::assert_json_snapshot!(&to_value(dt).unwrap());
+                        }
                         Err(ServerError::Unsupported) => {
                             // We're intentionally skipping doing anything here.
                             // Our assumption is that this error will only be